@@ -0,0 +1,20 @@
+#!/bin/bash
+# Re-record the HTTP fixtures replayed by tests/crawler_fixtures.rs
+#
+# Needs network access. Commit the files written under tests/fixtures/http.
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+PAGES=(
+    "https://docs.rs/tokio/latest/tokio/task/fn.spawn.html"
+    "https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Array/map"
+    "https://requests.readthedocs.io/en/latest/user/quickstart/"
+)
+
+rm -rf tests/fixtures/http
+for url in "${PAGES[@]}"; do
+    echo "Recording $url"
+    CODERAG_HTTP_FIXTURES=record:tests/fixtures/http \
+        cargo run --quiet --features test-fixtures --bin coderag -- preview "$url" > /dev/null
+done
@@ -0,0 +1,229 @@
+//! Search analytics: a per-database query log and zero-result reporting.
+//!
+//! Every search appends one JSON line to `query_log.jsonl` next to the
+//! database. The report aggregates it so users can see which questions the
+//! knowledge base can't answer and which documentation to crawl next.
+
+use crate::error::{CodeRagError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File name of the query log, stored beside the database file
+const QUERY_LOG_FILE: &str = "query_log.jsonl";
+
+/// One logged search
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct QueryLogEntry {
+    /// RFC 3339 time the search ran
+    pub timestamp: String,
+    /// Tool or command that ran the search, e.g. "search_docs"
+    pub tool: String,
+    pub query: String,
+    #[serde(default)]
+    pub filters: QueryFilters,
+    pub result_count: usize,
+    pub top_score: Option<f32>,
+    pub latency_ms: u64,
+}
+
+/// Filters a search was run with
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct QueryFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_tags: Vec<String>,
+}
+
+/// Append-only query log for one database
+#[derive(Debug, Clone)]
+pub struct QueryLog {
+    path: PathBuf,
+}
+
+impl QueryLog {
+    /// Query log kept alongside the given database file
+    pub fn for_database(db_path: &Path) -> Self {
+        Self {
+            path: db_path.with_file_name(QUERY_LOG_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry to the log
+    pub fn append(&self, entry: &QueryLogEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| {
+                CodeRagError::io(format!("Failed to open query log {:?}", self.path), e)
+            })?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+            .map_err(|e| CodeRagError::io(format!("Failed to write query log {:?}", self.path), e))
+    }
+
+    /// Read all entries, skipping lines that don't parse
+    pub fn read(&self) -> Result<Vec<QueryLogEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| {
+            CodeRagError::io(format!("Failed to read query log {:?}", self.path), e)
+        })?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Aggregated view of the query log
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AnalyticsReport {
+    pub total_queries: usize,
+    pub zero_result_queries: usize,
+    pub zero_result_rate: f32,
+    pub avg_latency_ms: f64,
+    /// Most frequent queries that returned nothing: crawl candidates
+    pub top_zero_result: Vec<QueryCount>,
+    pub top_queries: Vec<QueryCount>,
+}
+
+/// How often a (normalized) query was run
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct QueryCount {
+    pub query: String,
+    pub count: usize,
+    pub last_seen: String,
+}
+
+impl AnalyticsReport {
+    /// Summarize log entries, keeping the `limit` most frequent queries per list
+    pub fn from_entries(entries: &[QueryLogEntry], limit: usize) -> Self {
+        let total_queries = entries.len();
+        let zero: Vec<&QueryLogEntry> = entries.iter().filter(|e| e.result_count == 0).collect();
+        let avg_latency_ms = if total_queries == 0 {
+            0.0
+        } else {
+            entries.iter().map(|e| e.latency_ms as f64).sum::<f64>() / total_queries as f64
+        };
+
+        Self {
+            total_queries,
+            zero_result_queries: zero.len(),
+            zero_result_rate: if total_queries == 0 {
+                0.0
+            } else {
+                zero.len() as f32 / total_queries as f32
+            },
+            avg_latency_ms,
+            top_zero_result: count_queries(zero, limit),
+            top_queries: count_queries(entries.iter(), limit),
+        }
+    }
+}
+
+/// Group entries by case- and whitespace-insensitive query text
+fn count_queries<'a>(
+    entries: impl IntoIterator<Item = &'a QueryLogEntry>,
+    limit: usize,
+) -> Vec<QueryCount> {
+    let mut counts: HashMap<String, QueryCount> = HashMap::new();
+
+    for entry in entries {
+        let key = entry
+            .query
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let count = counts.entry(key).or_insert_with(|| QueryCount {
+            query: entry.query.trim().to_string(),
+            count: 0,
+            last_seen: entry.timestamp.clone(),
+        });
+        count.count += 1;
+        if entry.timestamp > count.last_seen {
+            count.last_seen = entry.timestamp.clone();
+        }
+    }
+
+    let mut counts: Vec<QueryCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.last_seen.cmp(&a.last_seen))
+    });
+    counts.truncate(limit);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(query: &str, result_count: usize, timestamp: &str) -> QueryLogEntry {
+        QueryLogEntry {
+            timestamp: timestamp.to_string(),
+            tool: "search_docs".to_string(),
+            query: query.to_string(),
+            filters: QueryFilters::default(),
+            result_count,
+            top_score: (result_count > 0).then_some(0.8),
+            latency_ms: 10,
+        }
+    }
+
+    #[test]
+    fn test_query_log_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = QueryLog::for_database(&temp_dir.path().join("vectordb.json"));
+        assert!(log.read().unwrap().is_empty());
+
+        log.append(&entry("axum routing", 0, "2026-01-01T00:00:00Z"))
+            .unwrap();
+        log.append(&entry("tokio spawn", 3, "2026-01-01T00:01:00Z"))
+            .unwrap();
+        log.append(&entry("Axum  Routing", 0, "2026-01-02T00:00:00Z"))
+            .unwrap();
+        log.append(&entry("sqlx pool", 0, "2026-01-01T00:02:00Z"))
+            .unwrap();
+
+        let entries = log.read().unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(log.path(), temp_dir.path().join("query_log.jsonl"));
+
+        let report = AnalyticsReport::from_entries(&entries, 5);
+        assert_eq!(report.total_queries, 4);
+        assert_eq!(report.zero_result_queries, 3);
+        assert_eq!(report.top_zero_result.len(), 2);
+        assert_eq!(report.top_zero_result[0].query, "axum routing");
+        assert_eq!(report.top_zero_result[0].count, 2);
+        assert_eq!(report.top_zero_result[0].last_seen, "2026-01-02T00:00:00Z");
+        assert_eq!(report.avg_latency_ms, 10.0);
+    }
+}
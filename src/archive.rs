@@ -0,0 +1,113 @@
+//! Archive of documents removed by refresh, delete and expire.
+//!
+//! With `database.archive` enabled, documents are moved into a second
+//! database beside the main one (`<name>.archive.json`) instead of being
+//! destroyed. Ordinary searches never see it; searches with
+//! `include_archived` query it as an extra layer, so questions about older
+//! versions of a library can still be answered after its docs were
+//! refreshed.
+
+use crate::error::Result;
+use crate::vectordb::{Document, VectorDatabase};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Tag carried by every archived document
+pub const ARCHIVED_TAG: &str = "archived";
+
+/// The archive of one database file
+#[derive(Debug, Clone)]
+pub struct Archive {
+    path: PathBuf,
+}
+
+impl Archive {
+    /// Archive kept alongside the given database file
+    pub fn for_database(db_path: &Path) -> Self {
+        let stem = db_path
+            .file_stem()
+            .map_or_else(|| "vectors".into(), |stem| stem.to_string_lossy());
+        Self {
+            path: db_path.with_file_name(format!("{}.archive.json", stem)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add documents removed from the main database, returning how many were archived
+    ///
+    /// Each copy gets the archive time appended to its ID, so a page archived
+    /// by several refreshes keeps one copy per version, and an `archived`
+    /// tag plus an `archived-YYYY-MM-DD` tag with the date.
+    pub fn store(&self, documents: Vec<(Document, Vec<f32>)>) -> Result<usize> {
+        if documents.is_empty() {
+            return Ok(0);
+        }
+
+        let mut archive = self.open()?.unwrap_or(VectorDatabase::new(&self.path)?);
+        let now = Utc::now();
+        let stamp = now.format("%Y%m%dT%H%M%SZ");
+        let count = documents.len();
+        for (mut document, embedding) in documents {
+            document.id = format!("{}@{}", document.id, stamp);
+            document.metadata.tags.push(ARCHIVED_TAG.to_string());
+            document
+                .metadata
+                .tags
+                .push(format!("archived-{}", now.format("%Y-%m-%d")));
+            archive.add_document(document, embedding)?;
+        }
+        archive.save()?;
+        info!("🗄️ Archived {} documents to {:?}", count, self.path);
+
+        Ok(count)
+    }
+
+    /// Load the archive, or `None` if nothing was archived yet
+    pub fn open(&self) -> Result<Option<VectorDatabase>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let mut archive = VectorDatabase::new(&self.path)?;
+        archive.load()?;
+        Ok(Some(archive))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::SearchOptions;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_and_search() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::for_database(&dir.path().join("vectors.json"));
+        assert_eq!(archive.path(), dir.path().join("vectors.archive.json"));
+        assert!(archive.open().unwrap().is_none());
+
+        let document = Document::for_test("spawn_1")
+            .with_content("Spawn a task with tokio::spawn")
+            .with_url("https://docs.rs/tokio/0.2/tokio/fn.spawn.html");
+        let stored = archive
+            .store(vec![(document, vec![1.0, 0.0, 0.0])])
+            .unwrap();
+        assert_eq!(stored, 1);
+
+        let db = archive.open().unwrap().unwrap();
+        let results = db
+            .search(&[1.0, 0.0, 0.0], SearchOptions::default())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].document.id.starts_with("spawn_1@"));
+        assert!(results[0]
+            .document
+            .metadata
+            .tags
+            .contains(&ARCHIVED_TAG.to_string()));
+    }
+}
@@ -0,0 +1,202 @@
+//! Timestamped database backups taken before destructive operations.
+//!
+//! Before delete, expire and refresh remove documents, the database file is
+//! copied into `backups/` next to it. Backup names start with a UTC timestamp
+//! so they sort oldest first; only the newest `keep` are retained. A backup
+//! can be restored from the CLI or the manage_docs tool.
+
+use crate::error::{CodeRagError, Result};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory holding backups, beside the database file
+const BACKUP_DIR: &str = "backups";
+
+/// Timestamp prefix of a backup name
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// A backup of the database file
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BackupInfo {
+    /// File name, usable as the restore target
+    pub name: String,
+    /// RFC 3339 time the backup was taken
+    pub created: String,
+    /// Operation the backup was taken before
+    pub reason: String,
+    pub size_bytes: u64,
+}
+
+/// Backups of one database file
+#[derive(Debug, Clone)]
+pub struct BackupStore {
+    db_path: PathBuf,
+    dir: PathBuf,
+    /// Number of backups retained; 0 disables backups
+    keep: usize,
+}
+
+impl BackupStore {
+    /// Backups kept alongside the given database file
+    pub fn for_database(db_path: &Path, keep: usize) -> Self {
+        Self {
+            db_path: db_path.to_path_buf(),
+            dir: db_path.with_file_name(BACKUP_DIR),
+            keep,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copy the database into a new backup and prune old ones
+    ///
+    /// Returns None when backups are disabled or there is no database file yet.
+    pub fn create(&self, reason: &str) -> Result<Option<BackupInfo>> {
+        if self.keep == 0 || !self.db_path.exists() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            CodeRagError::io(
+                format!("Failed to create backup directory {:?}", self.dir),
+                e,
+            )
+        })?;
+        let name = format!(
+            "{}-{}-{}",
+            Utc::now().format(TIMESTAMP_FORMAT),
+            reason,
+            self.db_file_name()
+        );
+        let path = self.dir.join(&name);
+        fs::copy(&self.db_path, &path).map_err(|e| {
+            CodeRagError::io(format!("Failed to back up database to {:?}", path), e)
+        })?;
+        info!("💾 Backed up database before {} to {:?}", reason, path);
+
+        self.prune()?;
+        Ok(self.info(&name))
+    }
+
+    /// All backups, newest first
+    pub fn list(&self) -> Result<Vec<BackupInfo>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .map_err(|e| {
+                CodeRagError::io(format!("Failed to read backup directory {:?}", self.dir), e)
+            })?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect();
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(names.iter().filter_map(|name| self.info(name)).collect())
+    }
+
+    /// Replace the database with a backup, or the newest one if `name` is None
+    ///
+    /// The current database is backed up first, so a restore can be undone.
+    pub fn restore(&self, name: Option<&str>) -> Result<BackupInfo> {
+        let backups = self.list()?;
+        let backup = match name {
+            Some(name) => backups.into_iter().find(|b| b.name == name),
+            None => backups.into_iter().next(),
+        }
+        .ok_or_else(|| {
+            CodeRagError::NotFound(match name {
+                Some(name) => format!("No backup named {:?} in {:?}", name, self.dir),
+                None => format!("No backups in {:?}", self.dir),
+            })
+        })?;
+
+        // Read before backing up, since pruning may delete the chosen backup
+        let contents = fs::read(self.dir.join(&backup.name))
+            .map_err(|e| CodeRagError::io(format!("Failed to read backup {:?}", backup.name), e))?;
+        self.create("restore")?;
+        fs::write(&self.db_path, contents).map_err(|e| {
+            CodeRagError::io(format!("Failed to restore backup {:?}", backup.name), e)
+        })?;
+        info!("♻️ Restored database from backup {}", backup.name);
+        Ok(backup)
+    }
+
+    /// Delete all but the newest `keep` backups, returning how many were deleted
+    fn prune(&self) -> Result<usize> {
+        let backups = self.list()?;
+        let mut pruned = 0;
+        for backup in backups.iter().skip(self.keep) {
+            match fs::remove_file(self.dir.join(&backup.name)) {
+                Ok(()) => pruned += 1,
+                Err(e) => warn!("Failed to delete old backup {}: {}", backup.name, e),
+            }
+        }
+        Ok(pruned)
+    }
+
+    fn db_file_name(&self) -> String {
+        self.db_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Parse a backup name of the form `<timestamp>-<reason>-<db file name>`
+    fn info(&self, name: &str) -> Option<BackupInfo> {
+        let rest = name.strip_suffix(&format!("-{}", self.db_file_name()))?;
+        let (timestamp, reason) = rest.split_once('-')?;
+        let created = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+        let size_bytes = fs::metadata(self.dir.join(name)).ok()?.len();
+        Some(BackupInfo {
+            name: name.to_string(),
+            created: created.and_utc().to_rfc3339(),
+            reason: reason.to_string(),
+            size_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_retention_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("vectordb.json");
+        let store = BackupStore::for_database(&db_path, 2);
+
+        // Nothing to back up before the database exists
+        assert!(store.create("delete").unwrap().is_none());
+
+        for version in ["v1", "v2", "v3"] {
+            fs::write(&db_path, version).unwrap();
+            store.create("delete").unwrap().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let backups = store.list().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].reason, "delete");
+
+        fs::write(&db_path, "wiped").unwrap();
+        let restored = store.restore(Some(&backups[1].name)).unwrap();
+        assert_eq!(restored.name, backups[1].name);
+        assert_eq!(fs::read_to_string(&db_path).unwrap(), "v2");
+
+        // The pre-restore state is the newest backup
+        let backups = store.list().unwrap();
+        assert_eq!(backups[0].reason, "restore");
+        store.restore(None).unwrap();
+        assert_eq!(fs::read_to_string(&db_path).unwrap(), "wiped");
+
+        assert!(matches!(
+            store.restore(Some("missing")),
+            Err(CodeRagError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+//! Retrieval quality benchmarks.
+//!
+//! `coderag bench` reads a YAML suite of queries with the pages (optionally
+//! sections) that should answer them, runs every query through each search
+//! configuration of the suite and reports recall@k and MRR per configuration.
+//! Weights, fusion and BM25 parameters are compared within one run; compare
+//! chunk sizes or embedding models by running the same suite against databases
+//! built with each (`--db`).
+//!
+//! ```yaml
+//! k: [1, 5, 10]
+//! configurations:
+//!   - { name: vector, method: vector }
+//!   - { name: hybrid-rrf, method: hybrid, fusion: rrf }
+//! queries:
+//!   - query: spawn a background task
+//!     expected: https://docs.rs/tokio/latest/tokio/task/fn.spawn.html
+//!     section: Examples
+//! ```
+
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{
+    FusionStrategy, HybridSearchOptions, KeywordSearchParams, SearchOptions, VectorDatabase,
+};
+use crate::Document;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Queries, their expected results, and the search configurations to compare
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchSuite {
+    /// Cutoffs recall is reported at
+    #[serde(default = "default_cutoffs")]
+    pub k: Vec<usize>,
+    /// Configurations to compare (vector, keyword, hybrid and hybrid RRF if empty)
+    #[serde(default)]
+    pub configurations: Vec<BenchConfig>,
+    pub queries: Vec<BenchQuery>,
+}
+
+fn default_cutoffs() -> Vec<usize> {
+    vec![1, 3, 5, 10]
+}
+
+/// A query and the pages that answer it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchQuery {
+    pub query: String,
+    /// URL of the page that answers the query, or several that each do
+    pub expected: Expected,
+    /// Section of the expected page the answer is in
+    #[serde(default)]
+    pub section: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Expected {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Expected {
+    fn urls(&self) -> &[String] {
+        match self {
+            Self::One(url) => std::slice::from_ref(url),
+            Self::Many(urls) => urls,
+        }
+    }
+}
+
+/// How a configuration searches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMethod {
+    Vector,
+    Keyword,
+    #[default]
+    Hybrid,
+}
+
+/// One search configuration of a benchmark
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchConfig {
+    pub name: String,
+    #[serde(default)]
+    pub method: SearchMethod,
+    /// Hybrid weights (defaults 0.7 and 0.3)
+    pub vector_weight: Option<f32>,
+    pub keyword_weight: Option<f32>,
+    pub fusion: Option<FusionStrategy>,
+    pub rrf_k: Option<f32>,
+    /// BM25 parameters (defaults 1.2 and 0.75)
+    pub k1: Option<f32>,
+    pub b: Option<f32>,
+    pub title_weight: Option<f32>,
+    pub section_weight: Option<f32>,
+}
+
+impl BenchConfig {
+    fn named(name: &str, method: SearchMethod, fusion: Option<FusionStrategy>) -> Self {
+        Self {
+            name: name.to_string(),
+            method,
+            fusion,
+            ..Self::default()
+        }
+    }
+
+    fn search_options(&self, limit: usize) -> HybridSearchOptions {
+        let defaults = HybridSearchOptions::default();
+        let keyword_defaults = KeywordSearchParams::default();
+        let (vector_weight, keyword_weight) = match self.method {
+            SearchMethod::Keyword => (0.0, 1.0),
+            _ => (
+                self.vector_weight.unwrap_or(defaults.vector_weight),
+                self.keyword_weight.unwrap_or(defaults.keyword_weight),
+            ),
+        };
+        HybridSearchOptions {
+            base: SearchOptions {
+                limit,
+                ..SearchOptions::default()
+            },
+            enable_hybrid: self.method != SearchMethod::Vector,
+            vector_weight,
+            keyword_weight,
+            keyword_params: KeywordSearchParams {
+                k1: self.k1.unwrap_or(keyword_defaults.k1),
+                b: self.b.unwrap_or(keyword_defaults.b),
+                title_weight: self.title_weight.unwrap_or(keyword_defaults.title_weight),
+                section_weight: self
+                    .section_weight
+                    .unwrap_or(keyword_defaults.section_weight),
+            },
+            fusion_strategy: match self.method {
+                SearchMethod::Keyword => FusionStrategy::WeightedSum,
+                _ => self.fusion.unwrap_or(defaults.fusion_strategy),
+            },
+            rrf_k: self.rrf_k.unwrap_or(defaults.rrf_k),
+        }
+    }
+}
+
+impl BenchSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            CodeRagError::io(format!("Failed to read benchmark suite {:?}", path), e)
+        })?;
+        Self::from_yaml(&content).map_err(|e| {
+            CodeRagError::InvalidInput(format!("Invalid benchmark suite {:?}: {}", path, e))
+        })
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let invalid = |message: &str| CodeRagError::InvalidInput(message.to_string());
+        let mut suite: Self = serde_yaml::from_str(content).map_err(|e| invalid(&e.to_string()))?;
+        if suite.queries.is_empty() {
+            return Err(invalid("The suite has no queries"));
+        }
+        if suite.k.is_empty() || suite.k.contains(&0) {
+            return Err(invalid("k must list cutoffs of at least 1"));
+        }
+        suite.k.sort_unstable();
+        suite.k.dedup();
+        if suite.configurations.is_empty() {
+            suite.configurations = vec![
+                BenchConfig::named("vector", SearchMethod::Vector, None),
+                BenchConfig::named("keyword", SearchMethod::Keyword, None),
+                BenchConfig::named("hybrid", SearchMethod::Hybrid, None),
+                BenchConfig::named(
+                    "hybrid-rrf",
+                    SearchMethod::Hybrid,
+                    Some(FusionStrategy::Rrf),
+                ),
+            ];
+        }
+        Ok(suite)
+    }
+
+    /// Run every query through every configuration
+    ///
+    /// `embeddings` holds the query embeddings, in the order of `queries`.
+    pub fn run(&self, db: &VectorDatabase, embeddings: &[Vec<f32>]) -> Result<Vec<ConfigReport>> {
+        let limit = self.k.iter().copied().max().unwrap_or(10);
+        self.configurations
+            .iter()
+            .map(|config| {
+                let outcomes = self
+                    .queries
+                    .iter()
+                    .zip(embeddings)
+                    .map(|(query, embedding)| {
+                        let results = db.hybrid_search(
+                            embedding,
+                            &query.query,
+                            config.search_options(limit),
+                        )?;
+                        let ranked: Vec<&Document> = results
+                            .iter()
+                            .filter(|r| {
+                                config.method != SearchMethod::Keyword || r.keyword_score > 0.0
+                            })
+                            .map(|r| &r.document)
+                            .collect();
+                        Ok(QueryOutcome::of(query, &ranked))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(ConfigReport::new(&config.name, &self.k, outcomes))
+            })
+            .collect()
+    }
+}
+
+/// Where the expected results of one query ranked
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryOutcome {
+    pub query: String,
+    /// 1-based rank of the first expected result, if it was found
+    pub first_rank: Option<usize>,
+    /// 1-based rank of each expected URL, in the order listed
+    pub ranks: Vec<Option<usize>>,
+}
+
+impl QueryOutcome {
+    fn of(query: &BenchQuery, ranked: &[&Document]) -> Self {
+        let ranks: Vec<Option<usize>> = query
+            .expected
+            .urls()
+            .iter()
+            .map(|url| {
+                ranked
+                    .iter()
+                    .position(|doc| is_expected(doc, url, query.section.as_deref()))
+                    .map(|index| index + 1)
+            })
+            .collect();
+        Self {
+            query: query.query.clone(),
+            first_rank: ranks.iter().flatten().min().copied(),
+            ranks,
+        }
+    }
+}
+
+/// Whether a result is the expected page, and section if one is given
+///
+/// An expected URL with a fragment must match the result's anchor too;
+/// trailing slashes are ignored.
+fn is_expected(doc: &Document, url: &str, section: Option<&str>) -> bool {
+    let normalize = |url: &str| url.trim_end_matches('/').to_string();
+    let found = if url.contains('#') {
+        doc.citation_url()
+    } else {
+        doc.url.split('#').next().unwrap_or_default().to_string()
+    };
+    if normalize(&found) != normalize(url) {
+        return false;
+    }
+    section.is_none_or(|section| {
+        let section = section.to_lowercase();
+        doc.section
+            .iter()
+            .chain(&doc.metadata.heading_path)
+            .any(|heading| heading.to_lowercase() == section)
+    })
+}
+
+/// Retrieval quality of one configuration over the suite
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigReport {
+    pub name: String,
+    /// Recall at each cutoff: the share of expected results ranked within it
+    pub recall: Vec<(usize, f64)>,
+    /// Mean reciprocal rank of the first expected result of each query
+    pub mrr: f64,
+    pub queries: Vec<QueryOutcome>,
+}
+
+impl ConfigReport {
+    fn new(name: &str, cutoffs: &[usize], queries: Vec<QueryOutcome>) -> Self {
+        let expected: usize = queries.iter().map(|q| q.ranks.len()).sum();
+        let recall = cutoffs
+            .iter()
+            .map(|&k| {
+                let found = queries
+                    .iter()
+                    .flat_map(|q| &q.ranks)
+                    .filter(|rank| rank.is_some_and(|rank| rank <= k))
+                    .count();
+                (k, found as f64 / expected.max(1) as f64)
+            })
+            .collect();
+        let mrr = queries
+            .iter()
+            .map(|q| q.first_rank.map_or(0.0, |rank| 1.0 / rank as f64))
+            .sum::<f64>()
+            / queries.len().max(1) as f64;
+        Self {
+            name: name.to_string(),
+            recall,
+            mrr,
+            queries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::DocumentMetadata;
+    use tempfile::TempDir;
+
+    fn document(id: &str, url: &str, section: &str, content: &str) -> Document {
+        Document::for_test(id)
+            .with_content(content)
+            .with_url(url)
+            .with_section(section)
+            .with_metadata(DocumentMetadata {
+                anchor: Some(section.to_lowercase()),
+                ..Default::default()
+            })
+    }
+
+    #[test]
+    fn test_suite_defaults() {
+        let suite = BenchSuite::from_yaml(
+            "k: [5, 1, 5]\nqueries:\n  - query: spawn\n    expected: [https://a.dev/spawn, https://a.dev/task]\n",
+        )
+        .unwrap();
+        assert_eq!(suite.k, vec![1, 5]);
+        assert_eq!(suite.configurations.len(), 4);
+        assert_eq!(suite.queries[0].expected.urls().len(), 2);
+        assert!(BenchSuite::from_yaml("queries: []").is_err());
+        assert!(BenchSuite::from_yaml("k: [0]\nqueries:\n  - {query: a, expected: b}").is_err());
+    }
+
+    #[test]
+    fn test_run_reports_recall_and_mrr() {
+        let dir = TempDir::new().unwrap();
+        let mut db = VectorDatabase::new(dir.path().join("vectors.json")).unwrap();
+        for (doc, embedding) in [
+            (
+                document(
+                    "spawn",
+                    "https://a.dev/spawn",
+                    "Examples",
+                    "spawn a task on the runtime",
+                ),
+                vec![1.0, 0.0],
+            ),
+            (
+                document(
+                    "sleep",
+                    "https://a.dev/sleep",
+                    "Usage",
+                    "sleep for a duration",
+                ),
+                vec![0.0, 1.0],
+            ),
+        ] {
+            db.add_document(doc, embedding).unwrap();
+        }
+
+        let suite = BenchSuite::from_yaml(
+            r#"
+k: [1, 2]
+configurations:
+  - { name: vector, method: vector }
+  - { name: keyword, method: keyword }
+queries:
+  - query: spawn task
+    expected: https://a.dev/spawn
+    section: Examples
+  - query: sleep duration
+    expected: https://a.dev/sleep#usage
+  - query: timers
+    expected: https://a.dev/timer/
+"#,
+        )
+        .unwrap();
+        // The second query's embedding points at the wrong page
+        let embeddings = vec![vec![1.0, 0.0], vec![1.0, 0.1], vec![0.5, 0.5]];
+        let reports = suite.run(&db, &embeddings).unwrap();
+
+        let vector = &reports[0];
+        assert_eq!(vector.queries[0].first_rank, Some(1));
+        assert_eq!(vector.queries[1].first_rank, Some(2));
+        assert_eq!(vector.queries[2].first_rank, None);
+        assert_eq!(vector.recall, vec![(1, 1.0 / 3.0), (2, 2.0 / 3.0)]);
+        assert!((vector.mrr - 0.5).abs() < 1e-9);
+
+        let keyword = &reports[1];
+        assert_eq!(keyword.queries[1].first_rank, Some(1));
+        assert!((keyword.mrr - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_is_expected() {
+        let doc = document("spawn", "https://a.dev/spawn/", "Examples", "");
+        assert!(is_expected(&doc, "https://a.dev/spawn", None));
+        assert!(is_expected(
+            &doc,
+            "https://a.dev/spawn/#examples",
+            Some("examples")
+        ));
+        assert!(!is_expected(&doc, "https://a.dev/spawn#usage", None));
+        assert!(!is_expected(&doc, "https://a.dev/spawn", Some("Usage")));
+    }
+}
@@ -0,0 +1,447 @@
+//! Checks behind the `mcp-debug conformance` mode.
+//!
+//! Tool arguments are generated from the input schemas the server lists, so
+//! the checks follow the tools as their parameters change. Results are
+//! checked against the MCP `CallToolResult` shape and, for structured
+//! content, against the JSON schema of the response type it names.
+//!
+//! The schema checks cover what schemars generates: `$ref`, `type`, `enum`,
+//! `const`, `required`, `properties`, `additionalProperties`, `items`,
+//! `allOf`, `anyOf` and `oneOf`. Formats and numeric bounds are not checked.
+
+use coderag::mcp::responses::{output_schemas, RESPONSE_URI_PREFIX};
+use serde_json::{json, Map, Value};
+
+/// Value given to string parameters
+const SAMPLE_STRING: &str = "conformance";
+
+/// Value given to string parameters whose name mentions a URL
+const SAMPLE_URL: &str = "https://example.com/";
+
+/// Arguments filling every required parameter of a tool with a valid value
+pub fn sample_arguments(input_schema: &Value) -> Map<String, Value> {
+    sample_object(input_schema, input_schema)
+}
+
+/// Valid arguments except for one parameter given a value of the wrong type
+///
+/// Returns the parameter's name with the arguments, or `None` when no
+/// parameter has a type to violate.
+pub fn wrong_type_arguments(input_schema: &Value) -> Option<(String, Map<String, Value>)> {
+    let root = input_schema;
+    let properties = resolve(input_schema, root).get("properties")?.as_object()?;
+    let required = required_names(resolve(input_schema, root));
+    // Required parameters first, as they can't be skipped by a default
+    let mut names: Vec<&String> = properties.keys().collect();
+    names.sort_by_key(|name| !required.contains(name));
+
+    names.into_iter().find_map(|name| {
+        let types = allowed_types(&properties[name], root);
+        if types.is_empty() {
+            return None;
+        }
+        let wrong = [
+            json!({ "unexpected": true }),
+            json!([]),
+            json!(SAMPLE_STRING),
+            json!(true),
+            json!(0.5),
+        ]
+        .into_iter()
+        .find(|value| !types.iter().any(|ty| type_matches(value, ty)))?;
+        let mut arguments = sample_arguments(input_schema);
+        arguments.insert(name.clone(), wrong);
+        Some((name.clone(), arguments))
+    })
+}
+
+/// Whether a tool takes required parameters that can be left out
+pub fn has_required(input_schema: &Value) -> bool {
+    !required_names(resolve(input_schema, input_schema)).is_empty()
+}
+
+/// Problems with a `tools/call` result, empty when it conforms
+pub fn check_tool_result(result: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let Some(content) = result.get("content").and_then(Value::as_array) else {
+        return vec!["result has no content array".to_string()];
+    };
+    if result
+        .get("isError")
+        .is_some_and(|value| !value.is_boolean())
+    {
+        problems.push("isError is not a boolean".to_string());
+    }
+
+    for (index, item) in content.iter().enumerate() {
+        let path = format!("content[{}]", index);
+        let has_string = |value: &Value, key: &str| value.get(key).is_some_and(Value::is_string);
+        match item.get("type").and_then(Value::as_str) {
+            Some("text") if has_string(item, "text") => {}
+            Some("image") if has_string(item, "data") && has_string(item, "mimeType") => {}
+            Some("resource") => {
+                let resource = item.get("resource").unwrap_or(&Value::Null);
+                if !has_string(resource, "uri")
+                    || !(has_string(resource, "text") || has_string(resource, "blob"))
+                {
+                    problems.push(format!("{}: malformed resource", path));
+                } else {
+                    problems.extend(check_structured_content(resource, &path));
+                }
+            }
+            Some(other) => problems.push(format!("{}: malformed {} content", path, other)),
+            None => problems.push(format!("{}: missing type", path)),
+        }
+    }
+    problems
+}
+
+/// Check structured content against the schema of the response type its URI names
+fn check_structured_content(resource: &Value, path: &str) -> Vec<String> {
+    let uri = resource["uri"].as_str().unwrap_or_default();
+    let Some(type_name) = uri.strip_prefix(RESPONSE_URI_PREFIX) else {
+        return Vec::new();
+    };
+    let Some(schema) = output_schemas().remove(type_name) else {
+        return vec![format!(
+            "{}: no schema for response type {}",
+            path, type_name
+        )];
+    };
+    let value = match resource["text"].as_str().map(serde_json::from_str::<Value>) {
+        Some(Ok(value)) => value,
+        _ => return vec![format!("{}: {} is not JSON text", path, type_name)],
+    };
+    let schema = serde_json::to_value(schema).unwrap_or_default();
+    schema_errors(&value, &schema)
+        .into_iter()
+        .map(|error| format!("{} ({}): {}", path, type_name, error))
+        .collect()
+}
+
+/// Ways a value violates a JSON schema, empty when it conforms
+pub fn schema_errors(value: &Value, schema: &Value) -> Vec<String> {
+    schema_errors_in(value, schema, schema)
+}
+
+fn check(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return errors.push(format!("{}: not allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match lookup(reference, root) {
+            Some(target) => check(value, target, root, path, errors),
+            None => errors.push(format!("{}: unresolved {}", path, reference)),
+        }
+    }
+    for part in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        check(value, part, root, path, errors);
+    }
+    // oneOf is checked like anyOf: untagged variants may overlap
+    for key in ["anyOf", "oneOf"] {
+        if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+            let matches = variants
+                .iter()
+                .any(|variant| schema_errors_in(value, variant, root).is_empty());
+            if !matches {
+                errors.push(format!("{}: matches none of {}", path, key));
+            }
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                Value::from(options.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            errors.push(format!("{}: expected {}", path, expected));
+        }
+    }
+    if let Some(ty) = schema.get("type") {
+        let types: Vec<&str> = match ty {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|ty| type_matches(value, ty)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                types.join(" or "),
+                value
+            ));
+            return;
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            for name in required_names(&Value::Object(schema.clone())) {
+                if !object.contains_key(&name) {
+                    errors.push(format!("{}: missing {}", path, name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in object {
+                let field_path = format!("{}.{}", path, name);
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(field_schema) => check(field, field_schema, root, &field_path, errors),
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            check(field, additional, root, &field_path, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => match schema.get("items") {
+            Some(Value::Array(tuple)) => {
+                for (index, (item, item_schema)) in items.iter().zip(tuple).enumerate() {
+                    check(
+                        item,
+                        item_schema,
+                        root,
+                        &format!("{}[{}]", path, index),
+                        errors,
+                    );
+                }
+            }
+            Some(item_schema) => {
+                for (index, item) in items.iter().enumerate() {
+                    check(
+                        item,
+                        item_schema,
+                        root,
+                        &format!("{}[{}]", path, index),
+                        errors,
+                    );
+                }
+            }
+            None => {}
+        },
+        _ => {}
+    }
+}
+
+fn schema_errors_in(value: &Value, schema: &Value, root: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(value, schema, root, "$", &mut errors);
+    errors
+}
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+/// Resolve a local `$ref` such as `#/definitions/CrawlMode`
+fn lookup<'a>(reference: &str, root: &'a Value) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+/// The schema itself, or the one its `$ref` points to
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => lookup(reference, root).unwrap_or(schema),
+        None => schema,
+    }
+}
+
+fn required_names(schema: &Value) -> Vec<String> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect()
+}
+
+/// JSON types a schema accepts, empty when it accepts anything
+fn allowed_types(schema: &Value, root: &Value) -> Vec<String> {
+    let schema = resolve(schema, root);
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        return options.iter().map(json_type).map(str::to_string).collect();
+    }
+    match schema.get("type") {
+        Some(Value::String(ty)) => return vec![ty.clone()],
+        Some(Value::Array(types)) => {
+            return types
+                .iter()
+                .filter_map(|ty| ty.as_str().map(str::to_string))
+                .collect()
+        }
+        _ => {}
+    }
+    let mut types = Vec::new();
+    for key in ["anyOf", "oneOf", "allOf"] {
+        for variant in schema
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let variant_types = allowed_types(variant, root);
+            if variant_types.is_empty() {
+                return Vec::new();
+            }
+            types.extend(variant_types);
+        }
+    }
+    types
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn sample_object(schema: &Value, root: &Value) -> Map<String, Value> {
+    let schema = resolve(schema, root);
+    let properties = schema.get("properties").and_then(Value::as_object);
+    required_names(schema)
+        .into_iter()
+        .map(|name| {
+            let value = properties
+                .and_then(|properties| properties.get(&name))
+                .map(|field| sample_value(&name, field, root))
+                .unwrap_or_else(|| json!(SAMPLE_STRING));
+            (name, value)
+        })
+        .collect()
+}
+
+fn sample_value(name: &str, schema: &Value, root: &Value) -> Value {
+    let schema = resolve(schema, root);
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|o| o.first())
+    {
+        return first.clone();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.clone();
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        let variant = schema
+            .get(key)
+            .and_then(Value::as_array)
+            .and_then(|variants| {
+                variants
+                    .iter()
+                    .find(|v| v.get("type") != Some(&json!("null")))
+            });
+        if let Some(variant) = variant {
+            return sample_value(name, variant, root);
+        }
+    }
+
+    let ty = match schema.get("type") {
+        Some(Value::String(ty)) => ty.as_str(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|ty| *ty != "null")
+            .unwrap_or("null"),
+        _ => "string",
+    };
+    match ty {
+        "null" => Value::Null,
+        "boolean" => json!(false),
+        "integer" => schema
+            .get("minimum")
+            .and_then(Value::as_f64)
+            .map_or(json!(1), |minimum| json!(minimum.max(1.0) as i64)),
+        "number" => json!(1.0),
+        "array" => match schema.get("items") {
+            Some(items) if items.is_object() => json!([sample_value(name, items, root)]),
+            _ => json!([]),
+        },
+        "object" => Value::Object(sample_object(schema, root)),
+        _ if name.contains("url") => json!(SAMPLE_URL),
+        _ => json!(SAMPLE_STRING),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coderag::mcp::responses::{FeedbackResponse, ToolResponse};
+    use coderag::mcp::sdk_server::CrawlDocsParams;
+    use schemars::schema_for;
+
+    #[test]
+    fn test_arguments_from_schema() {
+        let schema = serde_json::to_value(schema_for!(CrawlDocsParams)).unwrap();
+
+        let valid = sample_arguments(&schema);
+        assert_eq!(valid["url"], SAMPLE_URL);
+        assert!(schema_errors(&Value::Object(valid.clone()), &schema).is_empty());
+        assert!(serde_json::from_value::<CrawlDocsParams>(Value::Object(valid)).is_ok());
+
+        let (name, invalid) = wrong_type_arguments(&schema).unwrap();
+        assert_eq!(name, "url");
+        assert!(!schema_errors(&Value::Object(invalid.clone()), &schema).is_empty());
+        assert!(serde_json::from_value::<CrawlDocsParams>(Value::Object(invalid)).is_err());
+
+        assert!(has_required(&schema));
+        assert!(!has_required(
+            &json!({ "type": "object", "properties": {} })
+        ));
+    }
+
+    #[test]
+    fn test_check_tool_result() {
+        let result = FeedbackResponse {
+            id: "abc".to_string(),
+            helpful: true,
+            boost: 1.2,
+            origin: "project".to_string(),
+        }
+        .into_tool_result()
+        .unwrap();
+        let mut value = serde_json::to_value(&result).unwrap();
+        assert_eq!(check_tool_result(&value), Vec::<String>::new());
+
+        // Structured content that drifted from its schema
+        value["content"][0]["resource"]["text"] = json!(r#"{"id":"abc","helpful":"yes"}"#);
+        let problems = check_tool_result(&value);
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("$.helpful: expected boolean")));
+        assert!(problems.iter().any(|p| p.contains("missing boost")));
+
+        assert!(!check_tool_result(&json!({ "content": [{ "type": "text" }] })).is_empty());
+        assert!(!check_tool_result(&json!({ "isError": true })).is_empty());
+    }
+}
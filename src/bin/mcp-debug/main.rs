@@ -1,14 +1,19 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+#[cfg(feature = "test-fixtures")]
+use coderag::crawler::fixtures::FIXTURES_ENV;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::io::{BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tokio::time::{timeout, Duration};
 use tracing::{error, info};
 
+mod conformance;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "MCP Debug Tool", long_about = None)]
 struct Args {
@@ -61,6 +66,24 @@ enum Commands {
         #[arg(short, long, default_value = "./target/debug/coderag-mcp")]
         server: String,
     },
+
+    /// Call every tool with valid and invalid arguments and check the responses
+    ///
+    /// The server runs against an empty data directory in a temporary
+    /// directory, with crawler requests failing instead of reaching the network.
+    Conformance {
+        /// Path to the MCP server binary
+        #[arg(short, long, default_value = "./target/debug/coderag-mcp")]
+        server: String,
+
+        /// Seconds to wait for each response (the first search may download the model)
+        #[arg(short, long, default_value = "120")]
+        timeout: u64,
+
+        /// Print every request and response
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -564,6 +587,351 @@ async fn send_raw_request(
     Ok(())
 }
 
+/// JSON-RPC error code for invalid method parameters
+const INVALID_PARAMS: i32 = -32602;
+
+/// A server process spoken to over stdio, one request at a time
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines the server writes to stdout, read on their own thread so reads can time out
+    lines: mpsc::UnboundedReceiver<String>,
+    next_id: u64,
+    timeout: Duration,
+    verbose: bool,
+}
+
+impl Session {
+    fn spawn(
+        server_path: &Path,
+        data_dir: &Path,
+        timeout: Duration,
+        verbose: bool,
+    ) -> Result<Self> {
+        let mut command = Command::new(server_path);
+        // Crawl tools fail on a missing recording instead of reaching the network
+        #[cfg(feature = "test-fixtures")]
+        command.env(
+            FIXTURES_ENV,
+            format!("replay:{}", data_dir.join("fixtures").display()),
+        );
+        let mut child = command
+            .arg("--data-dir")
+            .arg(data_dir)
+            // No project database is picked up from the working directory
+            .current_dir(data_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(if verbose {
+                Stdio::inherit()
+            } else {
+                Stdio::null()
+            })
+            .spawn()
+            .with_context(|| format!("Failed to spawn MCP server {:?}", server_path))?;
+
+        let stdin = child.stdin.take().context("Failed to get stdin")?;
+        let stdout = child.stdout.take().context("Failed to get stdout")?;
+        let (sender, lines) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            next_id: 1,
+            timeout,
+            verbose,
+        })
+    }
+
+    fn send(&mut self, request: &JsonRpcRequest) -> Result<()> {
+        let line = serde_json::to_string(request)?;
+        if self.verbose {
+            info!("➡️  {}", line);
+        }
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn notify(&mut self, method: &str) -> Result<()> {
+        self.send(&JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: method.to_string(),
+            params: None,
+        })
+    }
+
+    /// Send a request and wait for the response with its ID, skipping notifications
+    async fn request(&mut self, method: &str, params: Value) -> Result<JsonRpcResponse> {
+        let id = json!(self.next_id);
+        self.next_id += 1;
+        self.send(&JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id.clone()),
+            method: method.to_string(),
+            params: Some(params),
+        })?;
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            let line = match tokio::time::timeout_at(deadline, self.lines.recv()).await {
+                Ok(Some(line)) => line,
+                Ok(None) => anyhow::bail!("Server closed connection"),
+                Err(_) => anyhow::bail!("Timeout waiting for {} response", method),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if self.verbose {
+                info!("⬅️  {}", line);
+            }
+            match serde_json::from_str::<JsonRpcResponse>(&line) {
+                Ok(response) if response.id.as_ref() == Some(&id) => return Ok(response),
+                // Notifications and requests from the server
+                Ok(_) => continue,
+                Err(e) => anyhow::bail!("Invalid JSON-RPC message {:?}: {}", line, e),
+            }
+        }
+    }
+
+    async fn call_tool(
+        &mut self,
+        name: &str,
+        arguments: serde_json::Map<String, Value>,
+    ) -> Result<JsonRpcResponse> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Outcome of one conformance check
+enum Check {
+    Pass,
+    Fail(String),
+    /// The tool has nothing to check this way
+    Skipped,
+}
+
+impl Check {
+    fn from_problems(problems: Vec<String>) -> Self {
+        if problems.is_empty() {
+            Check::Pass
+        } else {
+            Check::Fail(problems.join("; "))
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Check::Pass => "pass",
+            Check::Fail(_) => "FAIL",
+            Check::Skipped => "-",
+        }
+    }
+}
+
+/// Valid arguments may still be refused, e.g. an ID that isn't indexed, but
+/// never for failing to deserialize, and any result must be well-formed
+fn check_valid_call(response: Result<JsonRpcResponse>) -> Check {
+    match response {
+        Err(e) => Check::Fail(e.to_string()),
+        Ok(JsonRpcResponse {
+            error: Some(error), ..
+        }) => {
+            if error.message.contains("deserialize") {
+                Check::Fail(format!("rejected valid arguments: {}", error.message))
+            } else {
+                Check::Pass
+            }
+        }
+        Ok(JsonRpcResponse {
+            result: Some(result),
+            ..
+        }) => Check::from_problems(conformance::check_tool_result(&result)),
+        Ok(_) => Check::Fail("response has neither result nor error".to_string()),
+    }
+}
+
+/// Invalid arguments must be refused with an invalid params error
+fn check_invalid_call(response: Result<JsonRpcResponse>) -> Check {
+    match response {
+        Err(e) => Check::Fail(e.to_string()),
+        Ok(JsonRpcResponse {
+            error: Some(error), ..
+        }) if error.code == INVALID_PARAMS => Check::Pass,
+        Ok(JsonRpcResponse {
+            error: Some(error), ..
+        }) => Check::Fail(format!(
+            "expected error {}, got {}: {}",
+            INVALID_PARAMS, error.code, error.message
+        )),
+        Ok(_) => Check::Fail(format!("expected error {}, got a result", INVALID_PARAMS)),
+    }
+}
+
+async fn run_conformance(server_path: &str, timeout: Duration, verbose: bool) -> Result<()> {
+    let server_path = std::fs::canonicalize(server_path)
+        .with_context(|| format!("MCP server binary not found: {}", server_path))?;
+    let data_dir = std::env::temp_dir().join(format!("coderag-conformance-{}", std::process::id()));
+    std::fs::create_dir_all(&data_dir)?;
+    info!(
+        "🧪 Conformance of {:?} (data in {:?})",
+        server_path, data_dir
+    );
+
+    let result = conformance_matrix(&server_path, &data_dir, timeout, verbose).await;
+    let _ = std::fs::remove_dir_all(&data_dir);
+    let (rows, protocol) = result?;
+
+    println!(
+        "\n{:<24} {:<6} {:<8} {:<10}",
+        "TOOL", "VALID", "MISSING", "WRONG TYPE"
+    );
+    for (tool, checks) in &rows {
+        println!(
+            "{:<24} {:<6} {:<8} {:<10}",
+            tool,
+            checks[0].label(),
+            checks[1].label(),
+            checks[2].label()
+        );
+    }
+    for (name, check) in &protocol {
+        println!("{:<24} {}", name, check.label());
+    }
+
+    let columns = ["valid arguments", "missing required", "wrong type"];
+    let mut checks = 0;
+    let mut failures = Vec::new();
+    for (tool, row) in &rows {
+        for (column, check) in columns.iter().zip(row) {
+            checks += !matches!(check, Check::Skipped) as usize;
+            if let Check::Fail(reason) = check {
+                failures.push(format!("{} ({}): {}", tool, column, reason));
+            }
+        }
+    }
+    for (name, check) in &protocol {
+        checks += 1;
+        if let Check::Fail(reason) = check {
+            failures.push(format!("{}: {}", name, reason));
+        }
+    }
+
+    if failures.is_empty() {
+        println!("\n✅ All {} checks passed", checks);
+        return Ok(());
+    }
+    println!();
+    for failure in &failures {
+        println!("❌ {}", failure);
+    }
+    anyhow::bail!("{} of {} conformance checks failed", failures.len(), checks)
+}
+
+type Matrix = (Vec<(String, [Check; 3])>, Vec<(&'static str, Check)>);
+
+async fn conformance_matrix(
+    server_path: &Path,
+    data_dir: &Path,
+    timeout: Duration,
+    verbose: bool,
+) -> Result<Matrix> {
+    let mut session = Session::spawn(server_path, data_dir, timeout, verbose)?;
+    let mut protocol = Vec::new();
+
+    let init = session
+        .request(
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": "mcp-debug", "version": "0.1.0" }
+            }),
+        )
+        .await?;
+    let init = init.result.unwrap_or_default();
+    let mut problems = Vec::new();
+    for field in ["protocolVersion", "capabilities", "serverInfo"] {
+        if init.get(field).is_none() {
+            problems.push(format!("initialize result has no {}", field));
+        }
+    }
+    if init.pointer("/capabilities/tools").is_none() {
+        problems.push("tools capability not advertised".to_string());
+    }
+    protocol.push(("initialize", Check::from_problems(problems)));
+    session.notify("notifications/initialized")?;
+
+    let list = session.request("tools/list", json!({})).await?;
+    let tools = list
+        .result
+        .as_ref()
+        .and_then(|result| result.get("tools"))
+        .and_then(Value::as_array)
+        .cloned()
+        .context("tools/list returned no tools")?;
+
+    let mut rows = Vec::new();
+    for tool in &tools {
+        let name = tool.get("name").and_then(Value::as_str).unwrap_or_default();
+        let schema = tool.get("inputSchema").cloned().unwrap_or_default();
+        info!("🔧 {}", name);
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            let failure = Check::Fail("inputSchema is not an object schema".to_string());
+            rows.push((name.to_string(), [failure, Check::Skipped, Check::Skipped]));
+            continue;
+        }
+
+        let valid = check_valid_call(
+            session
+                .call_tool(name, conformance::sample_arguments(&schema))
+                .await,
+        );
+        let missing = if conformance::has_required(&schema) {
+            check_invalid_call(session.call_tool(name, Default::default()).await)
+        } else {
+            Check::Skipped
+        };
+        let wrong_type = match conformance::wrong_type_arguments(&schema) {
+            Some((parameter, arguments)) => {
+                match check_invalid_call(session.call_tool(name, arguments).await) {
+                    Check::Fail(reason) => Check::Fail(format!("{}: {}", parameter, reason)),
+                    check => check,
+                }
+            }
+            None => Check::Skipped,
+        };
+        rows.push((name.to_string(), [valid, missing, wrong_type]));
+    }
+
+    let unknown = session
+        .call_tool("conformance_no_such_tool", Default::default())
+        .await;
+    protocol.push(("unknown tool", check_invalid_call(unknown)));
+
+    Ok((rows, protocol))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -590,6 +958,13 @@ async fn main() -> Result<()> {
         Commands::Handshake { server } => {
             test_handshake(&server).await?;
         }
+        Commands::Conformance {
+            server,
+            timeout,
+            verbose,
+        } => {
+            run_conformance(&server, Duration::from_secs(timeout), verbose).await?;
+        }
     }
 
     Ok(())
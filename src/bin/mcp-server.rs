@@ -1,13 +1,19 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use coderag::config::Config;
 use coderag::crawler::{CrawlConfig, CrawlMode, Crawler, DocumentationFocus};
+use coderag::data_dir::DataDir;
 use coderag::embedding_basic::EmbeddingService;
-use coderag::mcp::CodeRagServer;
-use coderag::vectordb::VectorDatabase;
+use coderag::mcp::{CodeRagServer, ServerOptions};
+use coderag::project_manager::ProjectManager;
+use coderag::shutdown;
+use coderag::vectordb::{OpenMode, VectorDatabase};
 use rmcp::{transport::stdio, ServiceExt};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 use tokio::time::timeout;
+use tracing_subscriber::fmt::format::FmtSpan;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "CodeRAG MCP Server", long_about = None)]
@@ -20,6 +26,14 @@ struct Args {
     #[arg(long, action)]
     debug: bool,
 
+    /// Database open mode: auto, read_write, or read_only (defaults to the configured mode)
+    #[arg(long)]
+    open_mode: Option<OpenMode>,
+
+    /// Load the embedding model in the background at startup instead of on the first search
+    #[arg(long)]
+    preload_model: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -27,7 +41,18 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Run the MCP server (default)
-    Serve,
+    Serve {
+        /// Also serve the REST API on this address (requires the rest-api feature)
+        #[arg(long)]
+        rest: Option<SocketAddr>,
+    },
+
+    /// Run only the REST API (requires the rest-api feature)
+    Rest {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: SocketAddr,
+    },
 
     /// Crawl a documentation site directly
     Crawl {
@@ -42,9 +67,9 @@ enum Commands {
         #[arg(short, long, default_value = "all")]
         focus: String,
 
-        /// Maximum pages to crawl
-        #[arg(long, default_value = "100")]
-        max_pages: usize,
+        /// Maximum pages to crawl (defaults to the configured crawler max_pages)
+        #[arg(long)]
+        max_pages: Option<usize>,
 
         /// Enable verbose debug output
         #[arg(short, long)]
@@ -52,6 +77,9 @@ enum Commands {
     },
 }
 
+/// Longest wait for running requests to finish writing after a shutdown signal
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
 // Custom exit function that avoids destructors
 extern "C" fn force_exit() {
     unsafe {
@@ -83,6 +111,12 @@ async fn main() -> Result<()> {
         } else {
             tracing::Level::INFO
         })
+        // Debug output includes the timing of crawl, embedding and search spans
+        .with_span_events(if debug_level {
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        })
         .with_writer(std::io::stderr)
         .with_ansi(false)
         .init();
@@ -95,8 +129,16 @@ async fn main() -> Result<()> {
         PathBuf::from(args.data_dir)
     };
 
-    // Create data directory if it doesn't exist
-    std::fs::create_dir_all(&data_dir)?;
+    // Create the data directory, or move an old flat one into the current layout
+    DataDir::open(&data_dir)?;
+
+    listen_for_shutdown();
+
+    let options = ServerOptions {
+        open_mode: args.open_mode,
+        preload_model: args.preload_model,
+        ..Default::default()
+    };
 
     match args.command {
         Some(Commands::Crawl {
@@ -109,29 +151,98 @@ async fn main() -> Result<()> {
             // Run crawler directly
             run_crawler(data_dir, url, mode, focus, max_pages, verbose_crawl).await
         }
-        Some(Commands::Serve) | None => {
-            // Run MCP server (default behavior)
-            tracing::info!(
-                "Starting CodeRAG MCP server with data directory: {:?}",
-                data_dir
-            );
-            tracing::info!("💡 FastEmbed model will be downloaded on first search request");
+        Some(Commands::Rest { addr }) => {
+            let server = CodeRagServer::with_options(data_dir, options).await?;
+            serve_rest(server.clone(), addr).await?;
+            exit_after_saving(&server).await
+        }
+        Some(Commands::Serve { rest }) => run_mcp_server(data_dir, rest, options).await,
+        None => run_mcp_server(data_dir, None, options).await,
+    }
+}
 
-            // Create and start the MCP server using the official SDK
-            let server = CodeRagServer::new(data_dir).await?;
-            let service = server.serve(stdio()).await.inspect_err(|e| {
-                tracing::error!("Failed to start MCP server: {:?}", e);
-            })?;
+async fn run_mcp_server(
+    data_dir: PathBuf,
+    rest: Option<SocketAddr>,
+    options: ServerOptions,
+) -> Result<()> {
+    tracing::info!(
+        "Starting CodeRAG MCP server with data directory: {:?}",
+        data_dir
+    );
+    // Create and start the MCP server using the official SDK
+    let server = CodeRagServer::with_options(data_dir, options).await?;
+
+    // The REST API shares the server's database and embedding service
+    if let Some(addr) = rest {
+        let rest_server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_rest(rest_server, addr).await {
+                tracing::error!("REST API failed: {:?}", e);
+            }
+        });
+    }
 
-            // Wait for the service to complete
-            service.waiting().await?;
+    let service = server
+        .clone()
+        .serve_with_ct(stdio(), shutdown::token().child_token())
+        .await
+        .inspect_err(|e| {
+            tracing::error!("Failed to start MCP server: {:?}", e);
+        })?;
+
+    // Runs until the client closes stdin or a signal cancels the service
+    let reason = service.waiting().await?;
+    tracing::info!("MCP service stopped: {:?}", reason);
+    exit_after_saving(&server).await
+}
 
-            // Exit cleanly without running destructors
-            unsafe {
-                libc::_exit(0);
+/// Cancel the shutdown token on SIGINT or SIGTERM, and exit at once on a second signal
+fn listen_for_shutdown() {
+    tokio::spawn(async {
+        loop {
+            match shutdown::signal().await {
+                Ok(signal) if shutdown::requested() => {
+                    tracing::warn!("{} received again, exiting without saving", signal);
+                    unsafe { libc::_exit(130) }
+                }
+                Ok(signal) => {
+                    tracing::info!("{} received, shutting down", signal);
+                    shutdown::token().cancel();
+                }
+                Err(e) => {
+                    tracing::warn!("Cannot listen for shutdown signals: {}", e);
+                    return;
+                }
             }
         }
+    });
+}
+
+/// Save the databases once running requests have written, then exit
+///
+/// The process exits without running destructors, which ONNX Runtime's
+/// cleanup can crash in.
+async fn exit_after_saving(server: &CodeRagServer) -> ! {
+    // The locks stay held until the process is gone
+    let locks = timeout(SHUTDOWN_GRACE, server.shutdown()).await;
+    if locks.is_err() {
+        tracing::error!(
+            "Requests were still writing after {}s; exiting without saving",
+            SHUTDOWN_GRACE.as_secs()
+        );
     }
+    unsafe { libc::_exit(if locks.is_ok() { 0 } else { 1 }) }
+}
+
+#[cfg(feature = "rest-api")]
+async fn serve_rest(server: CodeRagServer, addr: SocketAddr) -> Result<()> {
+    coderag::mcp::rest::serve(server, addr).await
+}
+
+#[cfg(not(feature = "rest-api"))]
+async fn serve_rest(_server: CodeRagServer, _addr: SocketAddr) -> Result<()> {
+    anyhow::bail!("REST API support is not compiled in; rebuild with `--features rest-api`")
 }
 
 async fn run_crawler(
@@ -139,52 +250,28 @@ async fn run_crawler(
     url: String,
     mode: String,
     focus: String,
-    max_pages: usize,
+    max_pages: Option<usize>,
     verbose: bool,
 ) -> Result<()> {
+    let project_root = ProjectManager::detect_project_root();
+    let coderag_config = Config::load_with_data_dir(Some(&data_dir), project_root.as_deref())?;
+    let max_pages = max_pages.unwrap_or(coderag_config.crawler.max_pages);
+
     tracing::info!("🕷️ Starting direct crawler");
     tracing::info!("URL: {}", url);
     tracing::info!("Mode: {}", mode);
     tracing::info!("Focus: {}", focus);
     tracing::info!("Max pages: {}", max_pages);
 
-    // Parse crawl mode
-    let crawl_mode = match mode.as_str() {
-        "single" => CrawlMode::SinglePage,
-        "section" => CrawlMode::Section,
-        "full" => CrawlMode::FullDocs,
-        _ => {
-            anyhow::bail!(
-                "Invalid crawl mode: {}. Use 'single', 'section', or 'full'",
-                mode
-            );
-        }
-    };
-
-    // Parse documentation focus
-    let doc_focus = match focus.as_str() {
-        "api" => DocumentationFocus::ApiReference,
-        "examples" => DocumentationFocus::Examples,
-        "changelog" => DocumentationFocus::Changelog,
-        "quickstart" => DocumentationFocus::QuickStart,
-        "all" => DocumentationFocus::All,
-        _ => {
-            anyhow::bail!(
-                "Invalid focus: {}. Use 'api', 'examples', 'changelog', 'quickstart', or 'all'",
-                focus
-            );
-        }
-    };
+    // Parse crawl mode and documentation focus
+    let crawl_mode: CrawlMode = mode.parse()?;
+    let doc_focus: DocumentationFocus = focus.parse()?;
 
     // Parse URL to get domain
     let parsed_url = url::Url::parse(&url)?;
-    let domain = parsed_url
-        .domain()
-        .ok_or_else(|| anyhow::anyhow!("Invalid URL: no domain found"))?
-        .to_string();
-
-    let mut allowed_domains = std::collections::HashSet::new();
-    allowed_domains.insert(domain);
+    if parsed_url.domain().is_none() {
+        anyhow::bail!("Invalid URL: no domain found");
+    }
 
     // Create crawl config
     let config = CrawlConfig {
@@ -192,21 +279,31 @@ async fn run_crawler(
         mode: crawl_mode,
         focus: doc_focus,
         max_pages,
-        max_depth: 3,
-        concurrent_requests: 2,
-        delay_ms: 500,
-        user_agent: "CodeRAG/0.1.0 (AI Documentation Assistant)".to_string(),
-        allowed_domains,
+        max_depth: coderag_config.crawler.max_depth.unwrap_or(3),
+        concurrent_requests: coderag_config.crawler.concurrent_requests,
+        delay_ms: coderag_config.crawler.delay_ms,
+        user_agent: coderag_config.crawler.user_agent.clone(),
+        allowed_domains: coderag_config.allowed_domains(&parsed_url),
+        domain_policy: coderag_config.crawler.domain_policy(),
         url_patterns: coderag::crawler::UrlPatterns::default(),
+        max_page_bytes: coderag_config.crawler.max_page_bytes,
+        max_chunks_per_page: coderag_config.crawler.max_chunks_per_page,
+        max_pages_per_article: coderag_config.crawler.max_pages_per_article,
+        retry: coderag_config.crawler.retry_policy(),
+        breaker: coderag_config.crawler.breaker_policy(),
+        client: coderag_config.crawler.client_options(),
+        auth: coderag_config.crawler.auth.clone(),
     };
 
     // Initialize embedding service (lazy initialization - no model download yet)
     tracing::info!("📦 Creating embedding service...");
-    let embedding_service = EmbeddingService::new().await?;
+    let mut embedding_service = EmbeddingService::with_config(&coderag_config.embedding).await?;
     tracing::info!("✅ Embedding service created (model will download on first use)");
 
     // Initialize vector database
-    let db_path = data_dir.join("coderag_vectordb.json");
+    let db_path = ProjectManager::new(data_dir)
+        .with_backend(coderag_config.database.backend)
+        .global_database_path();
     tracing::info!("📂 Loading vector database from: {:?}", db_path);
 
     let mut vector_db = VectorDatabase::new(db_path.clone())?;
@@ -217,10 +314,15 @@ async fn run_crawler(
             vector_db.document_count()
         );
     }
+    embedding_service.bind_database(&db_path, vector_db.document_count())?;
+    let profile = embedding_service.profile();
+    vector_db.bind_model(&profile.model, profile.dimension)?;
 
     // Create crawler
     tracing::info!("🕷️ Creating crawler...");
-    let mut crawler = Crawler::new(config.clone()).await?;
+    let mut crawler = Crawler::new(config.clone())
+        .await?
+        .with_chunker(coderag_config.chunker());
     tracing::info!("✅ Crawler initialized");
 
     // Run crawl with timeout and detailed progress
@@ -249,6 +351,20 @@ async fn run_crawler(
                 "  - Total documents in database: {}",
                 vector_db.document_count()
             );
+            let progress = crawler.get_progress().await;
+            tracing::info!(
+                "  - Pages failed: {} ({} fetches retried)",
+                progress.pages_failed,
+                progress.retries
+            );
+            for pause in &progress.host_pauses {
+                tracing::info!(
+                    "  - Paused {} for {} ms after {} consecutive failures",
+                    pause.host,
+                    pause.paused_ms,
+                    pause.failures
+                );
+            }
 
             // List the crawled URLs if verbose
             if verbose {
@@ -262,7 +378,7 @@ async fn run_crawler(
         }
         Ok(Err(e)) => {
             tracing::error!("❌ Crawl failed: {}", e);
-            Err(e)
+            Err(e.into())
         }
         Err(_) => {
             tracing::error!("❌ Crawl timeout ({}s)", crawl_timeout.as_secs());
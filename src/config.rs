@@ -0,0 +1,920 @@
+//! Typed configuration loaded from config.toml files and environment variables.
+//!
+//! Settings are layered, later layers overriding earlier ones:
+//! built-in defaults, the global `~/.config/coderag/config.toml`, the
+//! project's `.coderag/config.toml`, then `CODERAG_*` environment variables.
+
+use crate::crawl_policy::CrawlPolicy;
+use crate::crawler::auth::{DomainAuth, Secret};
+use crate::crawler::blob_store::BlobStore;
+use crate::crawler::domains::{DomainPolicy, DomainScope};
+use crate::crawler::fetch::ClientOptions;
+use crate::crawler::page_store::PageStore;
+use crate::crawler::retry::{BreakerPolicy, RetryPolicy};
+use crate::crawler::TextChunker;
+use crate::data_dir::DataDir;
+use crate::embedding_basic::ExecutionProvider;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{FusionStrategy, OpenMode, StorageBackend, VectorStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+/// Environment variable overrides: (variable, section, key, kind)
+const ENV_OVERRIDES: &[(&str, &str, &str, EnvKind)] = &[
+    (
+        "CODERAG_EMBEDDING_MODEL",
+        "embedding",
+        "model",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_EMBEDDING_CACHE_DIR",
+        "embedding",
+        "cache_dir",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_EMBEDDING_MODEL_DIR",
+        "embedding",
+        "model_dir",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_EMBEDDING_PROVIDERS",
+        "embedding",
+        "execution_providers",
+        EnvKind::List,
+    ),
+    (
+        "CODERAG_EMBEDDING_DEVICE_ID",
+        "embedding",
+        "device_id",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_EMBEDDING_THREADS",
+        "embedding",
+        "threads",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_EMBEDDING_PRELOAD",
+        "embedding",
+        "preload",
+        EnvKind::Bool,
+    ),
+    (
+        "CODERAG_CHUNK_SIZE",
+        "chunking",
+        "chunk_size",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CHUNK_OVERLAP",
+        "chunking",
+        "overlap",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_MIN_CHUNK_SIZE",
+        "chunking",
+        "min_chunk_size",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_DELAY_MS",
+        "crawler",
+        "delay_ms",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_CONCURRENCY",
+        "crawler",
+        "concurrent_requests",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_PAGES",
+        "crawler",
+        "max_pages",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_DEPTH",
+        "crawler",
+        "max_depth",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_PAGE_BYTES",
+        "crawler",
+        "max_page_bytes",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_RETRIES",
+        "crawler",
+        "max_retries",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_BREAKER_THRESHOLD",
+        "crawler",
+        "breaker_threshold",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_CHUNKS_PER_PAGE",
+        "crawler",
+        "max_chunks_per_page",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_MAX_PAGES_PER_ARTICLE",
+        "crawler",
+        "max_pages_per_article",
+        EnvKind::Integer,
+    ),
+    ("CODERAG_CRAWL_PROXY", "crawler", "proxy", EnvKind::String),
+    (
+        "CODERAG_CRAWL_NO_PROXY",
+        "crawler",
+        "no_proxy",
+        EnvKind::List,
+    ),
+    (
+        "CODERAG_CRAWL_CA_BUNDLE",
+        "crawler",
+        "ca_bundle",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_CRAWL_ACCEPT_INVALID_CERTS",
+        "crawler",
+        "accept_invalid_certs",
+        EnvKind::Bool,
+    ),
+    (
+        "CODERAG_USER_AGENT",
+        "crawler",
+        "user_agent",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_ALLOWED_DOMAINS",
+        "crawler",
+        "allowed_domains",
+        EnvKind::List,
+    ),
+    (
+        "CODERAG_CRAWL_DOMAIN_SCOPE",
+        "crawler",
+        "domain_scope",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_CRAWL_ALLOW_SUBDOMAINS",
+        "crawler",
+        "allow_subdomains",
+        EnvKind::Bool,
+    ),
+    (
+        "CODERAG_CRAWL_ALLOW",
+        "crawl_policy",
+        "allow",
+        EnvKind::List,
+    ),
+    ("CODERAG_CRAWL_DENY", "crawl_policy", "deny", EnvKind::List),
+    (
+        "CODERAG_CRAWL_MAX_PAGES_PER_DAY",
+        "crawl_policy",
+        "max_pages_per_day",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_CRAWL_REQUIRE_CONFIRMATION",
+        "crawl_policy",
+        "require_confirmation",
+        EnvKind::Bool,
+    ),
+    ("CODERAG_SEARCH_LIMIT", "search", "limit", EnvKind::Integer),
+    (
+        "CODERAG_SEARCH_MIN_SCORE",
+        "search",
+        "min_score",
+        EnvKind::Float,
+    ),
+    ("CODERAG_SEARCH_LAYERED", "search", "layered", EnvKind::Bool),
+    ("CODERAG_SEARCH_FUSION", "search", "fusion", EnvKind::String),
+    (
+        "CODERAG_SEARCH_TIME_BUDGET_MS",
+        "search",
+        "time_budget_ms",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_SEARCH_RECENCY_HALF_LIFE_DAYS",
+        "search",
+        "recency_half_life_days",
+        EnvKind::Float,
+    ),
+    (
+        "CODERAG_SEARCH_INFER_FILTERS",
+        "search",
+        "infer_filters",
+        EnvKind::Bool,
+    ),
+    (
+        "CODERAG_SEARCH_PACKAGE_WEIGHT",
+        "search",
+        "package_weight",
+        EnvKind::Float,
+    ),
+    ("CODERAG_BACKUP_KEEP", "backup", "keep", EnvKind::Integer),
+    (
+        "CODERAG_OPEN_MODE",
+        "database",
+        "open_mode",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_AUTO_RELOAD",
+        "database",
+        "auto_reload",
+        EnvKind::Bool,
+    ),
+    ("CODERAG_ARCHIVE", "database", "archive", EnvKind::Bool),
+    (
+        "CODERAG_STORE_PAGES",
+        "database",
+        "store_pages",
+        EnvKind::Bool,
+    ),
+    (
+        "CODERAG_COMPRESS_PAGES",
+        "database",
+        "compress_pages",
+        EnvKind::Bool,
+    ),
+    ("CODERAG_SNAPSHOTS", "database", "snapshots", EnvKind::Bool),
+    (
+        "CODERAG_STORAGE_BACKEND",
+        "database",
+        "backend",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_MAX_DB_SIZE_MB",
+        "database",
+        "max_size_mb",
+        EnvKind::Integer,
+    ),
+    ("CODERAG_EVICT_LRU", "database", "evict_lru", EnvKind::Bool),
+    (
+        "CODERAG_EVICT_UNUSED_DAYS",
+        "database",
+        "evict_unused_days",
+        EnvKind::Integer,
+    ),
+    (
+        "CODERAG_VECTOR_STORE",
+        "vector_store",
+        "engine",
+        EnvKind::String,
+    ),
+    ("CODERAG_QDRANT_URL", "vector_store", "url", EnvKind::String),
+    (
+        "CODERAG_QDRANT_COLLECTION",
+        "vector_store",
+        "collection",
+        EnvKind::String,
+    ),
+    (
+        "CODERAG_QDRANT_API_KEY",
+        "vector_store",
+        "api_key",
+        EnvKind::String,
+    ),
+    ("CODERAG_METRICS", "telemetry", "metrics", EnvKind::Bool),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum EnvKind {
+    String,
+    Integer,
+    Float,
+    Bool,
+    /// Comma-separated list
+    List,
+}
+
+/// Effective CodeRAG configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub embedding: EmbeddingConfig,
+    pub chunking: ChunkingConfig,
+    pub crawler: CrawlerConfig,
+    /// Limits on what agents may crawl through crawl_docs
+    pub crawl_policy: CrawlPolicy,
+    pub search: SearchConfig,
+    pub backup: BackupConfig,
+    pub database: DatabaseConfig,
+    pub vector_store: VectorStoreConfig,
+    pub telemetry: TelemetryConfig,
+    /// Files and environment variables the configuration was loaded from
+    #[serde(skip)]
+    pub sources: Vec<String>,
+}
+
+/// Embedding model settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    /// FastEmbed model name, e.g. "all-MiniLM-L6-v2"
+    pub model: String,
+    /// Model cache directory (defaults to FASTEMBED_CACHE_PATH or ~/.cache/fastembed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// Directory holding the model's files from `coderag download-model`; nothing is downloaded when set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_dir: Option<PathBuf>,
+    /// ONNX Runtime execution providers to try in order, e.g. ["cuda", "cpu"] (empty: CPU)
+    pub execution_providers: Vec<ExecutionProvider>,
+    /// GPU used by the CUDA and DirectML providers
+    pub device_id: i32,
+    /// Threads embedding batches in parallel (defaults to one per core)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    /// Load the model in the background when the server starts instead of on first use
+    pub preload: bool,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            model: "all-MiniLM-L6-v2".to_string(),
+            cache_dir: None,
+            model_dir: None,
+            execution_providers: Vec::new(),
+            device_id: 0,
+            threads: None,
+            preload: false,
+        }
+    }
+}
+
+/// Default chunk sizes for crawled pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChunkingConfig {
+    pub chunk_size: usize,
+    pub overlap: usize,
+    pub min_chunk_size: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1500,
+            overlap: 200,
+            min_chunk_size: 100,
+        }
+    }
+}
+
+/// Crawler politeness and scope settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlerConfig {
+    pub delay_ms: u64,
+    pub concurrent_requests: usize,
+    pub max_pages: usize,
+    /// Link depth followed from the start URL; unset, the CLI follows 3 levels and crawl_docs 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    pub user_agent: String,
+    /// Domains crawls may follow links to, in addition to the start URL's domain
+    pub allowed_domains: Vec<String>,
+    /// "same_host" keeps crawls on the start URL's host; "same_site" also
+    /// follows links to other hosts of its registrable domain
+    pub domain_scope: DomainScope,
+    /// Also follow links to subdomains of the start host and allowed domains
+    pub allow_subdomains: bool,
+    /// Page bodies are truncated beyond this many bytes
+    pub max_page_bytes: usize,
+    /// Chunks beyond this many per page are dropped
+    pub max_chunks_per_page: usize,
+    /// Pages of one paginated article followed through its next/previous links, in every crawl mode
+    pub max_pages_per_article: usize,
+    /// Retries of a fetch that timed out or got a 5xx or 429 response
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled (with jitter) for each later one
+    pub retry_backoff_ms: u64,
+    pub max_retry_backoff_ms: u64,
+    /// Consecutive failures that pause requests to a host; 0 disables pausing
+    pub breaker_threshold: u32,
+    /// How long a failing host is paused
+    pub breaker_cooldown_ms: u64,
+    /// Proxy for crawl requests, e.g. "http://proxy.corp:3128" (HTTP_PROXY/HTTPS_PROXY apply when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Hosts fetched directly instead of through the proxy
+    pub no_proxy: Vec<String>,
+    /// PEM file with extra CA certificates to trust, e.g. a corporate root CA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// Skip TLS certificate verification (only for trusted internal servers)
+    pub accept_invalid_certs: bool,
+    /// Credentials by domain, e.g. `[crawler.auth."wiki.corp.example"]`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub auth: BTreeMap<String, DomainAuth>,
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: 500,
+            concurrent_requests: 2,
+            max_pages: 100,
+            max_depth: None,
+            user_agent: "CodeRAG/0.1.0 (AI Documentation Assistant)".to_string(),
+            allowed_domains: Vec::new(),
+            domain_scope: DomainScope::SameHost,
+            allow_subdomains: false,
+            max_page_bytes: 5 * 1024 * 1024,
+            max_chunks_per_page: 200,
+            max_pages_per_article: 20,
+            max_retries: 3,
+            retry_backoff_ms: 500,
+            max_retry_backoff_ms: 30_000,
+            breaker_threshold: 5,
+            breaker_cooldown_ms: 60_000,
+            proxy: None,
+            no_proxy: Vec::new(),
+            ca_bundle: None,
+            accept_invalid_certs: false,
+            auth: BTreeMap::new(),
+        }
+    }
+}
+
+impl CrawlerConfig {
+    /// Retry policy for page fetches
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            initial_backoff: Duration::from_millis(self.retry_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_retry_backoff_ms),
+        }
+    }
+
+    /// How crawls match hosts against the allowed domains
+    pub fn domain_policy(&self) -> DomainPolicy {
+        DomainPolicy {
+            scope: self.domain_scope,
+            allow_subdomains: self.allow_subdomains,
+        }
+    }
+
+    /// Circuit breaker policy for the hosts of a crawl
+    pub fn breaker_policy(&self) -> BreakerPolicy {
+        BreakerPolicy {
+            failure_threshold: self.breaker_threshold,
+            cooldown: Duration::from_millis(self.breaker_cooldown_ms),
+        }
+    }
+
+    /// Proxy and TLS settings for crawl requests
+    // The rest is only there with `test-fixtures`
+    #[cfg_attr(not(feature = "test-fixtures"), allow(clippy::needless_update))]
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            proxy: self.proxy.clone(),
+            no_proxy: self.no_proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            accept_invalid_certs: self.accept_invalid_certs,
+            ..Default::default()
+        }
+    }
+}
+
+/// Default search options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub limit: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,
+    /// Also search the global database when inside a project
+    pub layered: bool,
+    /// How hybrid search combines vector and keyword results ("weighted_sum" or "rrf")
+    pub fusion: FusionStrategy,
+    /// Return partial results once a search has taken this long (unlimited if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_budget_ms: Option<u64>,
+    /// Boost recently updated documents, the boost halving every this many days (off if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recency_half_life_days: Option<f32>,
+    /// Turn phrases like "in tokio docs" or "since version 2" in queries into filters
+    pub infer_filters: bool,
+    /// Score multiplier for hits from the package database a search's `path` routes to
+    pub package_weight: f32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            limit: 5,
+            min_score: None,
+            layered: true,
+            fusion: FusionStrategy::default(),
+            time_budget_ms: None,
+            recency_half_life_days: None,
+            infer_filters: true,
+            package_weight: 1.2,
+        }
+    }
+}
+
+/// Backups taken before destructive operations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Number of backups retained per database; 0 disables backups
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self { keep: 5 }
+    }
+}
+
+/// How MCP servers share a database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// "auto" (read-only if another server holds the write lock), "read_write" or "read_only"
+    pub open_mode: OpenMode,
+    /// Reload the database when another process saves it
+    pub auto_reload: bool,
+    /// Quiet period after the last file event before reloading
+    pub reload_debounce_ms: u64,
+    /// Move refreshed, deleted and expired documents to the archive instead of destroying them
+    pub archive: bool,
+    /// Keep each crawled page's extracted content, so it can be re-chunked without refetching
+    pub store_pages: bool,
+    /// Gzip the stored pages
+    pub compress_pages: bool,
+    /// Keep a content-addressed snapshot of every fetched page body
+    pub snapshots: bool,
+    /// File format of newly created project and global databases: "json" or "sqlite"
+    pub backend: StorageBackend,
+    /// Size on disk past which crawl_docs refuses new crawls (unlimited if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size_mb: Option<u64>,
+    /// Instead of refusing, evict the least recently searched sources until the database fits
+    pub evict_lru: bool,
+    /// Evict sources no search has returned in this many days, at startup and daily
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evict_unused_days: Option<u64>,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            open_mode: OpenMode::default(),
+            auto_reload: true,
+            reload_debounce_ms: 500,
+            archive: false,
+            store_pages: true,
+            compress_pages: true,
+            snapshots: true,
+            backend: StorageBackend::default(),
+            max_size_mb: None,
+            evict_lru: false,
+            evict_unused_days: None,
+        }
+    }
+}
+
+/// Engine answering vector searches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorEngine {
+    /// Exact search over the database, in-process
+    #[default]
+    Builtin,
+    /// A Qdrant server (needs the `qdrant` feature)
+    Qdrant,
+}
+
+/// External vector store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VectorStoreConfig {
+    /// "builtin" or "qdrant"
+    pub engine: VectorEngine,
+    /// Qdrant REST endpoint
+    pub url: String,
+    /// Qdrant collection (defaults to one per database file)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Sent as the `api-key` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<Secret>,
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            engine: VectorEngine::default(),
+            url: "http://localhost:6333".to_string(),
+            collection: None,
+            api_key: None,
+        }
+    }
+}
+
+/// Metrics export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// Serve Prometheus metrics at /metrics on the REST API address
+    pub metrics: bool,
+}
+
+impl Config {
+    /// Path of the global configuration file
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config").join("coderag").join("config.toml"))
+    }
+
+    /// Path of a project's configuration file
+    pub fn project_path(project_root: &Path) -> PathBuf {
+        project_root.join(".coderag").join("config.toml")
+    }
+
+    /// Load the effective configuration for an optional project
+    pub fn load(project_root: Option<&Path>) -> Result<Self> {
+        Self::load_with_data_dir(None, project_root)
+    }
+
+    /// Load the configuration, layering the data directory's `config.toml`
+    /// between the global and the project file
+    pub fn load_with_data_dir(
+        data_dir: Option<&Path>,
+        project_root: Option<&Path>,
+    ) -> Result<Self> {
+        let mut files = Vec::new();
+        if let Some(global) = Self::global_path() {
+            files.push(global);
+        }
+        if let Some(data_dir) = data_dir {
+            files.push(DataDir::at(data_dir).config_path());
+        }
+        if let Some(root) = project_root {
+            files.push(Self::project_path(root));
+        }
+
+        Self::load_layered(&files, std::env::vars())
+    }
+
+    /// Merge the given config files (in order) and environment variables
+    fn load_layered(
+        files: &[PathBuf],
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let mut merged = toml::Table::new();
+        let mut sources = Vec::new();
+
+        for path in files.iter().filter(|path| path.exists()) {
+            let content = fs::read_to_string(path).map_err(|e| {
+                CodeRagError::io(format!("Failed to read config file {:?}", path), e)
+            })?;
+            let table: toml::Table = toml::from_str(&content).map_err(|e| {
+                CodeRagError::Config(format!("Failed to parse config file {:?}: {}", path, e))
+            })?;
+            merge_tables(&mut merged, table);
+            sources.push(path.display().to_string());
+        }
+
+        for (name, value) in env {
+            let Some(&(_, section, key, kind)) =
+                ENV_OVERRIDES.iter().find(|(var, ..)| *var == name)
+            else {
+                continue;
+            };
+
+            let value = parse_env_value(&value, kind).map_err(|e| {
+                CodeRagError::Config(format!("Invalid value for {}: {:?} ({})", name, value, e))
+            })?;
+            let section = merged
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let Some(section) = section.as_table_mut() {
+                section.insert(key.to_string(), value);
+            }
+            sources.push(name);
+        }
+
+        let mut config: Config = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e| CodeRagError::Config(format!("Invalid configuration: {}", e)))?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    /// Build a text chunker with the configured chunk sizes
+    pub fn chunker(&self) -> TextChunker {
+        TextChunker::with_sizes(
+            self.chunking.chunk_size,
+            self.chunking.overlap,
+            self.chunking.min_chunk_size,
+        )
+    }
+
+    /// Store for the extracted pages of the database at `db_path`, if storing is on
+    pub fn page_store(&self, db_path: &Path) -> Option<PageStore> {
+        self.database
+            .store_pages
+            .then(|| PageStore::for_database(db_path, self.database.compress_pages))
+    }
+
+    /// Store for snapshots of the pages crawled into the database at `db_path`, if snapshots are on
+    pub fn blob_store(&self, db_path: &Path) -> Option<BlobStore> {
+        self.database
+            .snapshots
+            .then(|| BlobStore::for_database(db_path))
+    }
+
+    /// External store answering vector searches for the database at `db_path`, if one is configured
+    pub fn vector_store(&self, db_path: &Path) -> Result<Option<Box<dyn VectorStore>>> {
+        match self.vector_store.engine {
+            VectorEngine::Builtin => Ok(None),
+            #[cfg(feature = "qdrant")]
+            VectorEngine::Qdrant => {
+                use crate::vectordb::QdrantStore;
+                let collection = self
+                    .vector_store
+                    .collection
+                    .clone()
+                    .unwrap_or_else(|| QdrantStore::default_collection(db_path));
+                Ok(Some(Box::new(QdrantStore::new(
+                    &self.vector_store.url,
+                    &collection,
+                    self.vector_store
+                        .api_key
+                        .as_ref()
+                        .map(|key| key.expose().to_string()),
+                ))))
+            }
+            #[cfg(not(feature = "qdrant"))]
+            VectorEngine::Qdrant => {
+                let _ = db_path;
+                Err(CodeRagError::Unsupported(
+                    "vector_store.engine is \"qdrant\", but the Qdrant store is not compiled in; rebuild with `--features qdrant`"
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Domains a crawl starting at `start_url` may visit
+    pub fn allowed_domains(&self, start_url: &Url) -> HashSet<String> {
+        let mut domains: HashSet<String> = self.crawler.allowed_domains.iter().cloned().collect();
+        if let Some(domain) = start_url.domain() {
+            domains.insert(domain.to_string());
+        }
+        domains
+    }
+
+    /// Render the configuration as TOML
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|e| CodeRagError::Config(format!("Failed to serialize configuration: {}", e)))
+    }
+}
+
+/// Recursively merge `overlay` into `base`, overlay values winning
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_env_value(value: &str, kind: EnvKind) -> Result<toml::Value, String> {
+    fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String>
+    where
+        T::Err: std::fmt::Display,
+    {
+        value.trim().parse().map_err(|e: T::Err| e.to_string())
+    }
+
+    Ok(match kind {
+        EnvKind::String => toml::Value::String(value.to_string()),
+        EnvKind::Integer => toml::Value::Integer(parse(value)?),
+        EnvKind::Float => toml::Value::Float(parse(value)?),
+        EnvKind::Bool => toml::Value::Boolean(parse(value)?),
+        EnvKind::List => toml::Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| toml::Value::String(item.to_string()))
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_layered_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let global = temp_dir.path().join("global.toml");
+        let project = temp_dir.path().join("project.toml");
+        fs::write(
+            &global,
+            "[crawler]\ndelay_ms = 1000\nallowed_domains = [\"docs.rs\"]\n\n[search]\nlimit = 8\n\n\
+             [crawler.auth.\"wiki.corp.example\"]\nbearer_token = \"wiki-token\"\n",
+        )
+        .unwrap();
+        fs::write(&project, "[crawler]\ndelay_ms = 250\n").unwrap();
+
+        let env = vec![
+            ("CODERAG_SEARCH_MIN_SCORE".to_string(), "0.4".to_string()),
+            ("CODERAG_SEARCH_FUSION".to_string(), "rrf".to_string()),
+            (
+                "CODERAG_ALLOWED_DOMAINS".to_string(),
+                "a.dev, b.dev".to_string(),
+            ),
+            (
+                "CODERAG_EMBEDDING_PROVIDERS".to_string(),
+                "cuda,cpu".to_string(),
+            ),
+            ("UNRELATED".to_string(), "x".to_string()),
+        ];
+
+        let config = Config::load_layered(
+            &[global, project, temp_dir.path().join("missing.toml")],
+            env,
+        )
+        .unwrap();
+
+        // Project overrides global, untouched keys keep their defaults
+        assert_eq!(config.crawler.delay_ms, 250);
+        assert_eq!(config.crawler.concurrent_requests, 2);
+        assert_eq!(config.search.limit, 8);
+        assert_eq!(config.search.min_score, Some(0.4));
+        assert_eq!(config.search.fusion, FusionStrategy::Rrf);
+        assert_eq!(config.crawler.allowed_domains, vec!["a.dev", "b.dev"]);
+        assert_eq!(config.embedding.model, "all-MiniLM-L6-v2");
+        assert_eq!(
+            config.embedding.execution_providers,
+            vec![ExecutionProvider::Cuda, ExecutionProvider::Cpu]
+        );
+        assert_eq!(config.sources.len(), 6);
+
+        assert_eq!(
+            config.crawler.auth["wiki.corp.example"]
+                .bearer_token
+                .as_ref()
+                .map(|token| token.expose()),
+            Some("wiki-token")
+        );
+
+        // The rendered configuration parses back to the same values, minus secrets
+        let rendered = config.to_toml_string().unwrap();
+        assert!(!rendered.contains("wiki-token"));
+        let rendered: Config = toml::from_str(&rendered).unwrap();
+        assert_eq!(rendered.crawler.delay_ms, 250);
+        assert_eq!(rendered.search.fusion, FusionStrategy::Rrf);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(&path, "[search]\nlimt = 3\n").unwrap();
+        assert!(Config::load_layered(&[path], Vec::new()).is_err());
+
+        let env = vec![("CODERAG_SEARCH_LIMIT".to_string(), "many".to_string())];
+        assert!(Config::load_layered(&[], env).is_err());
+    }
+}
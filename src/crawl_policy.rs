@@ -0,0 +1,228 @@
+//! Guardrails on what agents may crawl on their own.
+//!
+//! `[crawl_policy]` limits crawl_docs to allowed domains, keeps it off denied
+//! ones, caps the pages crawled per day, and can require the user to confirm
+//! each crawl. A refused crawl reports a [`PolicyViolation`] the agent can
+//! relay. Pages crawled today are counted in `crawl_usage.json` in the data
+//! directory, so the cap holds across servers and restarts. Crawls started
+//! from the CLI are run by a person and aren't subject to the policy.
+
+use crate::data_dir::DataDir;
+use crate::error::{CodeRagError, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use url::Url;
+
+/// Limits on crawl_docs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlPolicy {
+    /// Domains agents may crawl, with their subdomains (empty: any domain)
+    pub allow: Vec<String>,
+    /// Domains agents may never crawl, with their subdomains; wins over `allow`
+    pub deny: Vec<String>,
+    /// Pages agents may crawl per day (UTC), across all crawls (unlimited if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pages_per_day: Option<usize>,
+    /// Refuse crawls the user hasn't confirmed
+    pub require_confirmation: bool,
+}
+
+/// A crawl the policy refuses
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PolicyViolation {
+    /// "denied_domain", "domain_not_allowed", "daily_page_limit" or "confirmation_required"
+    pub rule: String,
+    pub url: String,
+    /// Explanation to relay to the user
+    pub message: String,
+}
+
+impl CrawlPolicy {
+    /// Check a crawl of `url` before it starts
+    pub fn check(
+        &self,
+        url: &Url,
+        confirmed: bool,
+        pages_today: usize,
+    ) -> Result<(), PolicyViolation> {
+        let violation = |rule: &str, message: String| PolicyViolation {
+            rule: rule.to_string(),
+            url: url.to_string(),
+            message,
+        };
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+
+        if let Some(domain) = self.deny.iter().find(|d| domain_matches(&host, d)) {
+            return Err(violation(
+                "denied_domain",
+                format!(
+                    "Crawling {} is not permitted (denied domain {})",
+                    host, domain
+                ),
+            ));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|d| domain_matches(&host, d)) {
+            return Err(violation(
+                "domain_not_allowed",
+                format!(
+                    "Crawling {} is not permitted; allowed domains: {}",
+                    host,
+                    self.allow.join(", ")
+                ),
+            ));
+        }
+        if let Some(limit) = self.max_pages_per_day {
+            if pages_today >= limit {
+                return Err(violation(
+                    "daily_page_limit",
+                    format!(
+                        "The daily limit of {} crawled pages is used up; crawling resumes tomorrow (UTC)",
+                        limit
+                    ),
+                ));
+            }
+        }
+        if self.require_confirmation && !confirmed {
+            return Err(violation(
+                "confirmation_required",
+                format!(
+                    "Crawling requires confirmation: ask the user whether to crawl {}, then retry with confirmed: true",
+                    url
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pages a crawl may still fetch today, if the policy caps them
+    pub fn remaining_pages(&self, pages_today: usize) -> Option<usize> {
+        self.max_pages_per_day
+            .map(|limit| limit.saturating_sub(pages_today))
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+pub(crate) fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches("*.").to_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UsageRecord {
+    /// UTC date the count is for, e.g. "2025-03-14"
+    date: String,
+    pages: usize,
+}
+
+/// Pages crawled by agents today
+#[derive(Debug, Clone)]
+pub struct CrawlUsage {
+    path: PathBuf,
+    usage: UsageRecord,
+}
+
+impl CrawlUsage {
+    /// Usage recorded in a data directory; a missing or unreadable file counts nothing
+    pub fn load(data_dir: &Path) -> Self {
+        let path = DataDir::at(data_dir).crawl_usage_path();
+        let usage = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable crawl usage {:?}: {}", path, e);
+                UsageRecord::default()
+            }),
+            Err(_) => UsageRecord::default(),
+        };
+        Self { path, usage }
+    }
+
+    pub fn pages_today(&self) -> usize {
+        if self.usage.date == today() {
+            self.usage.pages
+        } else {
+            0
+        }
+    }
+
+    /// Add crawled pages to today's count and save it
+    pub fn record(&mut self, pages: usize) -> Result<()> {
+        self.usage = UsageRecord {
+            date: today(),
+            pages: self.pages_today() + pages,
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.usage)?).map_err(|e| {
+            CodeRagError::io(format!("Failed to write crawl usage {:?}", self.path), e)
+        })
+    }
+}
+
+fn today() -> String {
+    Utc::now().date_naive().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_policy_rules() {
+        let policy = CrawlPolicy {
+            allow: vec!["rust-lang.org".to_string(), "docs.rs".to_string()],
+            deny: vec!["internal.rust-lang.org".to_string()],
+            max_pages_per_day: Some(10),
+            require_confirmation: true,
+        };
+        let url = |u: &str| Url::parse(u).unwrap();
+        let rule = |result: Result<(), PolicyViolation>| result.unwrap_err().rule;
+
+        assert!(policy
+            .check(&url("https://doc.rust-lang.org/std/"), true, 0)
+            .is_ok());
+        assert_eq!(
+            rule(policy.check(&url("https://internal.rust-lang.org/"), true, 0)),
+            "denied_domain"
+        );
+        // A suffix that isn't a subdomain doesn't match
+        assert_eq!(
+            rule(policy.check(&url("https://notdocs.rs/"), true, 0)),
+            "domain_not_allowed"
+        );
+        assert_eq!(
+            rule(policy.check(&url("https://docs.rs/serde"), true, 10)),
+            "daily_page_limit"
+        );
+        assert_eq!(
+            rule(policy.check(&url("https://docs.rs/serde"), false, 0)),
+            "confirmation_required"
+        );
+        assert_eq!(policy.remaining_pages(7), Some(3));
+        assert!(CrawlPolicy::default()
+            .check(&url("https://example.com"), false, 1000)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_usage_counts_today() {
+        let dir = TempDir::new().unwrap();
+        let mut usage = CrawlUsage::load(dir.path());
+        usage.record(3).unwrap();
+        usage.record(2).unwrap();
+        assert_eq!(CrawlUsage::load(dir.path()).pages_today(), 5);
+
+        // Yesterday's count doesn't carry over
+        fs::write(
+            DataDir::at(dir.path()).crawl_usage_path(),
+            r#"{"date": "2000-01-01", "pages": 40}"#,
+        )
+        .unwrap();
+        assert_eq!(CrawlUsage::load(dir.path()).pages_today(), 0);
+    }
+}
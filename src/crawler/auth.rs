@@ -0,0 +1,232 @@
+//! Credentials for crawling documentation behind authentication.
+//!
+//! Auth is configured per domain and applies to that domain and its
+//! subdomains, the most specific domain winning. Secrets print as
+//! `[redacted]` in debug output and rendered configuration, so they never end
+//! up in logs or `coderag config` output.
+
+use crate::error::{CodeRagError, Result};
+use reqwest::header::{HeaderName, HeaderValue, COOKIE};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use url::Url;
+
+const REDACTED: &str = "[redacted]";
+
+/// A credential that is never printed or serialized
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The actual value, for sending it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+/// Credentials sent with every request to a domain
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DomainAuth {
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<Secret>,
+    /// Basic auth user, sent with `password`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret>,
+    /// Cookies by name, e.g. a session cookie copied from the browser
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub cookies: BTreeMap<String, Secret>,
+    /// Extra request headers, e.g. an API key header
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, Secret>,
+}
+
+/// Validated headers for one domain
+#[derive(Debug, Clone)]
+struct DomainHeaders {
+    domain: String,
+    auth: DomainAuth,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// Per-domain credentials for a crawl
+#[derive(Debug, Clone, Default)]
+pub struct CrawlAuth {
+    /// Sorted longest domain first so subdomain entries win
+    domains: Vec<DomainHeaders>,
+}
+
+impl CrawlAuth {
+    /// Validate the configured credentials
+    pub fn new(auth: &BTreeMap<String, DomainAuth>) -> Result<Self> {
+        let mut domains = auth
+            .iter()
+            .map(|(domain, auth)| {
+                Ok(DomainHeaders {
+                    domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                    auth: auth.clone(),
+                    headers: domain_headers(domain, auth)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        domains.sort_by_key(|entry| std::cmp::Reverse(entry.domain.len()));
+        Ok(Self { domains })
+    }
+
+    /// Add the credentials for `url`'s domain, if any, to a request
+    pub fn apply(&self, request: RequestBuilder, url: &str) -> RequestBuilder {
+        let Some(domain) = self.for_url(url) else {
+            return request;
+        };
+
+        let mut request = request;
+        if let Some(token) = &domain.auth.bearer_token {
+            request = request.bearer_auth(token.expose());
+        }
+        if let Some(username) = &domain.auth.username {
+            request =
+                request.basic_auth(username, domain.auth.password.as_ref().map(Secret::expose));
+        }
+        for (name, value) in &domain.headers {
+            request = request.header(name, value.clone());
+        }
+        request
+    }
+
+    fn for_url(&self, url: &str) -> Option<&DomainHeaders> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        self.domains.iter().find(|entry| {
+            host == entry.domain
+                || host
+                    .strip_suffix(&entry.domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+/// Cookie and custom headers for a domain, marked sensitive
+fn domain_headers(domain: &str, auth: &DomainAuth) -> Result<Vec<(HeaderName, HeaderValue)>> {
+    let invalid = |what: &str| {
+        CodeRagError::Parse(format!("Invalid {} in crawler auth for {}", what, domain))
+    };
+
+    let mut headers = Vec::new();
+    if !auth.cookies.is_empty() {
+        let cookie = auth
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.expose()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        headers.push((
+            COOKIE,
+            sensitive_value(&cookie).ok_or_else(|| invalid("cookie"))?,
+        ));
+    }
+    for (name, value) in &auth.headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| invalid(&format!("header name {:?}", name)))?;
+        let header_value = sensitive_value(value.expose())
+            .ok_or_else(|| invalid(&format!("value for header {:?}", name)))?;
+        headers.push((header_name, header_value));
+    }
+    Ok(headers)
+}
+
+fn sensitive_value(value: &str) -> Option<HeaderValue> {
+    let mut value = HeaderValue::from_str(value).ok()?;
+    value.set_sensitive(true);
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiki_auth() -> BTreeMap<String, DomainAuth> {
+        let mut auth = BTreeMap::new();
+        auth.insert(
+            "corp.example".to_string(),
+            DomainAuth {
+                bearer_token: Some(Secret::new("corp-token")),
+                ..Default::default()
+            },
+        );
+        auth.insert(
+            "wiki.corp.example".to_string(),
+            DomainAuth {
+                username: Some("docs".to_string()),
+                password: Some(Secret::new("hunter2")),
+                cookies: [("session".to_string(), Secret::new("abc123"))].into(),
+                headers: [("X-Api-Key".to_string(), Secret::new("key"))].into(),
+                ..Default::default()
+            },
+        );
+        auth
+    }
+
+    #[test]
+    fn test_most_specific_domain_wins() {
+        let auth = CrawlAuth::new(&wiki_auth()).unwrap();
+        let client = reqwest::Client::new();
+        let build = |url: &str| auth.apply(client.get(url), url).build().unwrap();
+
+        let wiki = build("https://wiki.corp.example/page");
+        assert!(wiki.headers()["authorization"]
+            .to_str()
+            .unwrap()
+            .starts_with("Basic "));
+        assert_eq!(wiki.headers()["cookie"], "session=abc123");
+        assert_eq!(wiki.headers()["x-api-key"], "key");
+
+        let api = build("https://api.corp.example/");
+        assert_eq!(api.headers()["authorization"], "Bearer corp-token");
+
+        let other = build("https://notcorp.example/");
+        assert!(other.headers().is_empty());
+    }
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let auth = wiki_auth();
+        let debug = format!("{:?}", auth);
+        assert!(!debug.contains("hunter2") && !debug.contains("abc123"));
+        assert!(format!("{:?}", CrawlAuth::new(&auth).unwrap()).contains(REDACTED));
+
+        let rendered = toml::to_string(&auth).unwrap();
+        assert!(!rendered.contains("corp-token") && rendered.contains(REDACTED));
+
+        let mut invalid = BTreeMap::new();
+        invalid.insert(
+            "corp.example".to_string(),
+            DomainAuth {
+                headers: [("Bad Header".to_string(), Secret::new("x"))].into(),
+                ..Default::default()
+            },
+        );
+        assert!(CrawlAuth::new(&invalid).is_err());
+    }
+}
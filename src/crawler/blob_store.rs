@@ -0,0 +1,144 @@
+//! Content-addressed snapshots of crawled pages.
+//!
+//! With `database.snapshots` on, the body of every crawled page is saved as
+//! fetched (HTML, Markdown or plain text) to `<name>.blobs/` next to the
+//! database (`blobs/` in the global data directory), gzipped and named by the SHA-256 of its content. Chunks refer
+//! to their page's snapshot from their provenance, so what was indexed can
+//! be reproduced exactly; identical pages share one blob.
+
+use crate::data_dir::DataDir;
+use crate::error::{CodeRagError, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The snapshots of one database file
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    /// Blob store kept alongside the given database file
+    pub fn for_database(db_path: &Path) -> Self {
+        let stem = db_path
+            .file_stem()
+            .map_or_else(|| "vectors".into(), |stem| stem.to_string_lossy());
+        let dir = DataDir::containing(db_path).map_or_else(
+            || db_path.with_file_name(format!("{}.blobs", stem)),
+            |data_dir| data_dir.blobs_dir(),
+        );
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store content, returning its hash; content stored before is not written again
+    pub fn put(&self, content: &[u8]) -> Result<String> {
+        let hash = format!("{:x}", Sha256::digest(content));
+        let path = self.path(&hash);
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        let parent = path.parent().unwrap_or(&self.dir);
+        std::fs::create_dir_all(parent)
+            .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(content)
+            .and_then(|()| encoder.finish())
+            .map_err(|e| CodeRagError::io(format!("Failed to compress blob {}", hash), e))?;
+
+        // Written under a temporary name so a crash never leaves a partial blob
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, compressed)
+            .and_then(|()| std::fs::rename(&partial, &path))
+            .map_err(|e| CodeRagError::io(format!("Failed to write {:?}", path), e))?;
+        Ok(hash)
+    }
+
+    /// Content stored under `hash`, if there is any
+    pub fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        if hash.len() < 3 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let path = self.path(hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let compressed = std::fs::read(&path)
+            .map_err(|e| CodeRagError::io(format!("Failed to read {:?}", path), e))?;
+        let mut content = Vec::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut content)
+            .map_err(|e| CodeRagError::io(format!("Failed to decompress {:?}", path), e))?;
+        Ok(Some(content))
+    }
+
+    /// Delete the blobs not in `hashes`, returning how many were removed
+    pub fn retain<'a>(&self, hashes: impl IntoIterator<Item = &'a str>) -> Result<usize> {
+        let Ok(prefixes) = std::fs::read_dir(&self.dir) else {
+            return Ok(0);
+        };
+        let keep: HashSet<&str> = hashes.into_iter().collect();
+
+        let mut removed = 0;
+        for prefix in prefixes.flatten() {
+            let Ok(blobs) = std::fs::read_dir(prefix.path()) else {
+                continue;
+            };
+            for blob in blobs.flatten() {
+                let hash = format!(
+                    "{}{}",
+                    prefix.file_name().to_string_lossy(),
+                    blob.file_name().to_string_lossy()
+                );
+                if !keep.contains(hash.as_str()) {
+                    std::fs::remove_file(blob.path()).map_err(|e| {
+                        CodeRagError::io(format!("Failed to remove {:?}", blob.path()), e)
+                    })?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Blobs are spread over subdirectories named by the first two hex digits
+    fn path(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2);
+        self.dir.join(prefix).join(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_put_get_retain() {
+        let dir = TempDir::new().unwrap();
+        let store = BlobStore::for_database(&dir.path().join("vectors.json"));
+        assert_eq!(store.dir(), dir.path().join("vectors.blobs"));
+
+        let page = b"<html><body><h1>Tokio</h1></body></html>";
+        let hash = store.put(page).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(store.put(page).unwrap(), hash);
+        assert_eq!(store.get(&hash).unwrap().as_deref(), Some(&page[..]));
+        assert!(store.get("../../etc/passwd").unwrap().is_none());
+
+        let other = store.put(b"# Tokio").unwrap();
+        assert_eq!(store.retain([hash.as_str()]).unwrap(), 1);
+        assert!(store.get(&other).unwrap().is_none());
+        assert!(store.get(&hash).unwrap().is_some());
+    }
+}
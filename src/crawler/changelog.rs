@@ -0,0 +1,155 @@
+//! Release metadata from changelog pages.
+//!
+//! Changelogs are organized as one section per release, headed by the
+//! version and usually its date: "1.2.0 (2024-01-05)", "[1.2.0] - 2024-01-05",
+//! "v0.13.1 — March 3, 2024". Chunks under such a heading are tagged with
+//! the release, so searches can be limited to a range of versions.
+
+use crate::vectordb::Version;
+use chrono::NaiveDate;
+use regex::Regex;
+
+/// URL path and title fragments of changelog and release notes pages
+const CHANGELOG_NAMES: &[&str] = &[
+    "changelog",
+    "change-log",
+    "changes",
+    "history",
+    "news",
+    "release-notes",
+    "release notes",
+    "releases",
+    "whats-new",
+    "what's new",
+];
+
+/// Longer headings are prose that happens to mention a version
+const MAX_RELEASE_HEADING_CHARS: usize = 80;
+
+/// The release a changelog section describes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    /// ISO 8601 date, when the heading has one
+    pub date: Option<String>,
+}
+
+/// Whether a page looks like a changelog or release notes
+pub fn is_changelog_page(url: &str, title: &str) -> bool {
+    let path = url::Url::parse(url)
+        .map(|url| url.path().to_lowercase())
+        .unwrap_or_default();
+    let title = title.to_lowercase();
+    CHANGELOG_NAMES
+        .iter()
+        .any(|name| path.contains(name) || title.contains(name))
+}
+
+/// The release of the innermost heading in a path that names one
+pub fn find_release(heading_path: &[String]) -> Option<Release> {
+    heading_path
+        .iter()
+        .rev()
+        .find_map(|heading| parse_release_heading(heading))
+}
+
+/// Parse a release heading into its version and date
+pub fn parse_release_heading(heading: &str) -> Option<Release> {
+    if heading.chars().count() > MAX_RELEASE_HEADING_CHARS {
+        return None;
+    }
+
+    let version_re = Regex::new(r"\b[vV]?(\d+(?:\.\d+)+(?:-[0-9A-Za-z][0-9A-Za-z.]*)?)\b").unwrap();
+    let captures = version_re.captures(heading)?;
+    let version = Version::parse(&captures[1])?.to_string();
+
+    Some(Release {
+        version,
+        date: parse_date(heading),
+    })
+}
+
+/// Find a date written as 2024-01-05, 2024/01/05, March 5, 2024 or 5 Mar 2024
+fn parse_date(text: &str) -> Option<String> {
+    let iso_re = Regex::new(r"\b(\d{4})[-/](\d{1,2})[-/](\d{1,2})\b").unwrap();
+    let month_first_re =
+        Regex::new(r"\b([A-Za-z]{3,9})\.? (\d{1,2})(?:st|nd|rd|th)?,? (\d{4})\b").unwrap();
+    let day_first_re = Regex::new(r"\b(\d{1,2}) ([A-Za-z]{3,9})\.? (\d{4})\b").unwrap();
+
+    let date = if let Some(c) = iso_re.captures(text) {
+        NaiveDate::from_ymd_opt(c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?)
+    } else if let Some(c) = month_first_re.captures(text) {
+        parse_month_date(&c[2], &c[1], &c[3])
+    } else if let Some(c) = day_first_re.captures(text) {
+        parse_month_date(&c[1], &c[2], &c[3])
+    } else {
+        None
+    };
+
+    date.map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn parse_month_date(day: &str, month: &str, year: &str) -> Option<NaiveDate> {
+    let text = format!("{} {} {}", day, month, year);
+    NaiveDate::parse_from_str(&text, "%d %B %Y")
+        .or_else(|_| NaiveDate::parse_from_str(&text, "%d %b %Y"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, date: Option<&str>) -> Option<Release> {
+        Some(Release {
+            version: version.to_string(),
+            date: date.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn test_parse_release_heading() {
+        assert_eq!(
+            parse_release_heading("1.2.0 (2024-01-05)"),
+            release("1.2.0", Some("2024-01-05"))
+        );
+        assert_eq!(
+            parse_release_heading("[v2.0.0-rc.1] - 2023/11/30"),
+            release("2.0.0-rc.1", Some("2023-11-30"))
+        );
+        assert_eq!(
+            parse_release_heading("tokio-1.35.0 — March 3rd, 2024"),
+            release("1.35.0", Some("2024-03-03"))
+        );
+        assert_eq!(
+            parse_release_heading("Version 0.13 (5 Sep 2022)"),
+            release("0.13", Some("2022-09-05"))
+        );
+        assert_eq!(parse_release_heading("0.4.1"), release("0.4.1", None));
+        assert_eq!(parse_release_heading("Unreleased"), None);
+        assert_eq!(parse_release_heading("Breaking changes"), None);
+
+        let path = vec![
+            "Changelog".to_string(),
+            "1.1.0".to_string(),
+            "Fixed".to_string(),
+        ];
+        assert_eq!(find_release(&path), release("1.1.0", None));
+    }
+
+    #[test]
+    fn test_is_changelog_page() {
+        assert!(is_changelog_page(
+            "https://github.com/o/r/blob/main/CHANGELOG.md",
+            ""
+        ));
+        assert!(is_changelog_page(
+            "https://docs.example.com/guide/",
+            "Release Notes"
+        ));
+        assert!(!is_changelog_page(
+            "https://docs.example.com/guide/",
+            "Getting started"
+        ));
+    }
+}
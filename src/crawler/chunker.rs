@@ -1,6 +1,10 @@
+use crate::crawler::language::detect_script_language;
+use crate::crawler::links::{markdown_links, strip_links};
+use crate::crawler::simhash::fnv1a;
 use crate::crawler::types::DocumentChunk;
+use crate::vectordb::chunk_core;
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Text chunker for splitting documents into manageable pieces
 #[derive(Debug)]
@@ -31,6 +35,16 @@ impl TextChunker {
         }
     }
 
+    /// Create a new TextChunker with custom chunk sizes
+    pub fn with_sizes(chunk_size: usize, overlap: usize, min_chunk_size: usize) -> Self {
+        Self {
+            chunk_size,
+            overlap,
+            min_chunk_size,
+            seen_content_hashes: HashSet::new(),
+        }
+    }
+
     /// Get the current set of seen content hashes for persistence
     pub fn get_seen_hashes(&self) -> &HashSet<u64> {
         &self.seen_content_hashes
@@ -99,9 +113,10 @@ impl TextChunker {
             return false;
         }
 
-        // Must have substantial alphabetic content
+        // Must have substantial alphabetic content (counted in characters, not
+        // bytes, so multi-byte scripts like Japanese aren't rejected)
         let alpha_count = trimmed.chars().filter(|c| c.is_alphabetic()).count();
-        let total_chars = trimmed.len();
+        let total_chars = trimmed.chars().count();
 
         if alpha_count < total_chars / 3 {
             return false;
@@ -239,6 +254,8 @@ impl TextChunker {
                 end_char: section.len(),
                 has_code: self.contains_code(section),
                 heading_context: self.extract_heading(section),
+                lang: detect_script_language(section).map(str::to_string),
+                links: markdown_links(section),
             };
 
             // Only add if it's quality content
@@ -246,7 +263,8 @@ impl TextChunker {
                 chunks.push(chunk);
             }
         } else {
-            // Need to split the section
+            // Need to split the section; every part belongs under its heading
+            let heading = self.extract_heading(section);
             let paragraphs = self.split_into_paragraphs(section);
             let mut current_chunk = String::new();
             let mut current_start = 0;
@@ -262,7 +280,9 @@ impl TextChunker {
                         start_char: current_start,
                         end_char: current_start + current_chunk.len(),
                         has_code: self.contains_code(&current_chunk),
-                        heading_context: self.extract_heading(&current_chunk),
+                        heading_context: heading.clone(),
+                        lang: detect_script_language(&current_chunk).map(str::to_string),
+                        links: markdown_links(&current_chunk),
                     };
 
                     if self.is_quality_content(&chunk.content) {
@@ -284,7 +304,9 @@ impl TextChunker {
                     start_char: current_start,
                     end_char: current_start + current_chunk.len(),
                     has_code: self.contains_code(&current_chunk),
-                    heading_context: self.extract_heading(&current_chunk),
+                    heading_context: heading.clone(),
+                    lang: detect_script_language(&current_chunk).map(str::to_string),
+                    links: markdown_links(&current_chunk),
                 };
 
                 if self.is_quality_content(&chunk.content) {
@@ -325,7 +347,7 @@ impl TextChunker {
 
         if let Some(captures) = header_re.captures(text) {
             if let Some(heading) = captures.get(1) {
-                return Some(heading.as_str().to_string());
+                return Some(strip_links(heading.as_str()));
             }
         }
 
@@ -435,10 +457,92 @@ impl TextChunker {
     }
 }
 
+/// Stable document ID of a chunk, from its page, section anchor and content
+///
+/// Positional IDs shift whenever a chunk is added above another. This one
+/// only changes when the chunk's own text does: the overlap context copied
+/// from its neighbours is left out of the hash, so citations survive
+/// re-crawls and an unchanged chunk keeps its ID.
+pub fn chunk_id(url: &str, anchor: Option<&str>, content: &str) -> String {
+    let hash = fnv1a(chunk_core(content)) >> 32;
+    match anchor {
+        Some(anchor) => format!("{}#{}_{:08x}", url, anchor, hash),
+        None => format!("{}_{:08x}", url, hash),
+    }
+}
+
+/// Hands out `chunk_id`s for the chunks of one page
+///
+/// Identical text in the same section hashes to the same ID, so repeats get
+/// a "-2", "-3", ... suffix in page order instead of overwriting each other.
+#[derive(Debug, Default)]
+pub struct ChunkIds {
+    seen: HashMap<String, usize>,
+}
+
+impl ChunkIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ID for the next chunk on the page
+    pub fn next_id(&mut self, url: &str, anchor: Option<&str>, content: &str) -> String {
+        let id = chunk_id(url, anchor, content);
+        let count = self.seen.entry(id.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            id
+        } else {
+            format!("{}-{}", id, count)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_chunk_id() {
+        let url = "https://docs.example.com/guide";
+        let id = chunk_id(url, Some("install"), "Run the installer.");
+        assert!(id.starts_with("https://docs.example.com/guide#install_"));
+        assert_eq!(id.len(), url.len() + "#install_".len() + 8);
+
+        // Overlap context from neighbouring chunks doesn't change the ID
+        assert_eq!(
+            chunk_id(
+                url,
+                Some("install"),
+                "...previous section\n\nRun the installer.\n\nnext section..."
+            ),
+            id
+        );
+        assert_ne!(chunk_id(url, Some("install"), "Run the new installer."), id);
+        assert_ne!(chunk_id(url, None, "Run the installer."), id);
+    }
+
+    #[test]
+    fn test_chunk_ids_break_ties_on_one_page() {
+        let url = "https://docs.example.com/guide";
+        let mut ids = ChunkIds::new();
+        let first = ids.next_id(url, Some("example"), "cargo build");
+        let second = ids.next_id(url, Some("example"), "cargo build");
+        let third = ids.next_id(url, Some("example"), "cargo build");
+        let other = ids.next_id(url, Some("usage"), "cargo build");
+
+        assert_eq!(first, chunk_id(url, Some("example"), "cargo build"));
+        assert_eq!(second, format!("{}-2", first));
+        assert_eq!(third, format!("{}-3", first));
+        assert_eq!(other, chunk_id(url, Some("usage"), "cargo build"));
+
+        // A fresh page starts numbering again
+        assert_eq!(
+            ChunkIds::new().next_id(url, Some("example"), "cargo build"),
+            first
+        );
+    }
+
     #[test]
     fn test_basic_chunking() {
         let mut chunker = TextChunker::new();
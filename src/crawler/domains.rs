@@ -0,0 +1,124 @@
+//! Which hosts a crawl may follow links to.
+//!
+//! A crawl stays on its start URL's host and the domains listed in
+//! `crawler.allowed_domains`. With the `same_site` scope it also follows links
+//! to other hosts of the start URL's registrable domain, so a crawl of
+//! `docs.example.com` reaches `www.example.com`. Registrable domains come from
+//! the Public Suffix List compiled into the `psl` crate, which keeps
+//! `github.io` or `co.uk` sites apart. `allow_subdomains` admits subdomains of
+//! every allowed host; a listed domain written `*.example-cdn.net` admits its
+//! subdomains on its own.
+
+use crate::crawl_policy::domain_matches;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Hosts a crawl covers besides the listed domains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainScope {
+    /// Only the start URL's host
+    #[default]
+    SameHost,
+    /// Every host sharing the start URL's registrable domain
+    SameSite,
+}
+
+/// How a crawl matches hosts against its allowed domains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DomainPolicy {
+    pub scope: DomainScope,
+    /// Admit subdomains of every allowed domain
+    pub allow_subdomains: bool,
+}
+
+impl DomainPolicy {
+    /// Whether a crawl starting on `start_host` may visit `host`
+    ///
+    /// `allowed` holds the start host and the configured domains.
+    pub fn allows(&self, host: &str, start_host: &str, allowed: &HashSet<String>) -> bool {
+        let host = host.to_lowercase();
+        if allowed.iter().any(|domain| self.matches(&host, domain)) {
+            return true;
+        }
+        self.scope == DomainScope::SameSite
+            && registrable_domain(&host)
+                .is_some_and(|site| registrable_domain(start_host) == Some(site))
+    }
+
+    fn matches(&self, host: &str, domain: &str) -> bool {
+        if self.allow_subdomains || domain.starts_with("*.") {
+            domain_matches(host, domain)
+        } else {
+            host == domain.to_lowercase()
+        }
+    }
+}
+
+/// The registrable domain of a host, e.g. "example.co.uk" for "docs.example.co.uk"
+///
+/// IP addresses and bare public suffixes have none.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return None;
+    }
+    psl::domain_str(&host).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(
+            registrable_domain("docs.example.com").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            registrable_domain("Docs.Example.CO.UK.").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(
+            registrable_domain("tokio-rs.github.io").as_deref(),
+            Some("tokio-rs.github.io")
+        );
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("127.0.0.1"), None);
+    }
+
+    #[test]
+    fn test_domain_policy() {
+        let allowed: HashSet<String> = ["docs.example.com", "*.example-cdn.net"]
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        let same_host = DomainPolicy::default();
+        let start = "docs.example.com";
+
+        assert!(same_host.allows("DOCS.example.com", start, &allowed));
+        assert!(same_host.allows("assets.example-cdn.net", start, &allowed));
+        assert!(!same_host.allows("www.example.com", start, &allowed));
+        assert!(!same_host.allows("api.docs.example.com", start, &allowed));
+
+        let subdomains = DomainPolicy {
+            allow_subdomains: true,
+            ..DomainPolicy::default()
+        };
+        assert!(subdomains.allows("api.docs.example.com", start, &allowed));
+        assert!(!subdomains.allows("www.example.com", start, &allowed));
+
+        let same_site = DomainPolicy {
+            scope: DomainScope::SameSite,
+            ..DomainPolicy::default()
+        };
+        assert!(same_site.allows("www.example.com", start, &allowed));
+        assert!(same_site.allows("example.com", start, &allowed));
+        assert!(!same_site.allows("example.org", start, &allowed));
+
+        // Sites on a shared hosting suffix stay apart
+        let pages: HashSet<String> = ["tokio-rs.github.io".to_string()].into();
+        assert!(!same_site.allows("someone-else.github.io", "tokio-rs.github.io", &pages));
+    }
+}
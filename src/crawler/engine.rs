@@ -1,48 +1,176 @@
-use anyhow::Result;
+//! Crawl pipeline.
+//!
+//! A crawl runs as stages connected by bounded channels, so network latency,
+//! HTML parsing and embedding overlap instead of running one page at a time:
+//!
+//! fetch → extract → chunk → embed → write
+//!
+//! The fetch stage owns the URL frontier and keeps up to
+//! `concurrent_requests` requests in flight. Extraction runs on the blocking
+//! thread pool and reports each page's links back to the fetch stage. Only
+//! the write stage modifies the database, in the order pages were chunked.
+//!
+//! A start URL inside a GitHub or GitLab repository is crawled through its
+//! repository adapter: the README, docs and wiki pages are fetched as raw
+//! Markdown and indexed under their web URLs. A Stack Exchange question or
+//! tag URL is ingested as questions and answers instead of pages.
+//!
+//! Sites with a prebuilt search index (MkDocs, Sphinx, Docusaurus) have the
+//! pages it lists queued up front, and the index's titles and section anchors
+//! are applied to the extracted pages. A Sphinx site's `objects.inv` is
+//! indexed too, as one reference document per documented symbol.
+
+use crate::error::{CodeRagError, Result};
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
 use governor::clock::DefaultClock;
 use governor::middleware::NoOpMiddleware;
 use governor::state::{InMemoryState, NotKeyed};
 use governor::{Quota, RateLimiter};
-use reqwest::Client;
 // use robotparser::RobotFileParser; // TODO: Find alternative crate
-use chrono::Utc;
 use scraper::{Html, Selector};
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::num::NonZeroU32;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{mpsc, Mutex};
 use tokio::time::sleep;
+use tracing::Instrument;
 use url::Url;
 
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::blob_store::BlobStore;
+use crate::crawler::changelog;
+use crate::crawler::chunker::ChunkIds;
+use crate::crawler::extractor::ExtractedContent;
+use crate::crawler::fetch::{build_client, read_page, HttpClient, PageKind};
+use crate::crawler::inventory::Inventory;
+use crate::crawler::page_store::{PageStore, StoredPage};
+use crate::crawler::pagination::{article_page_links, ArticlePages};
+use crate::crawler::qa::{self, QaSource, QaTarget};
+use crate::crawler::repository::{RepoPage, Repository};
+use crate::crawler::retry::{is_transient, HostBreakers};
+use crate::crawler::simhash::{page_fingerprint, NEAR_DUPLICATE_DISTANCE};
+use crate::crawler::site_index::{Generator, IndexedPage, SiteIndex};
 use crate::crawler::{
-    ContentExtractor, CrawlConfig, CrawlMetadata, CrawlMode, CrawlProgress, CrawlResult,
-    TextChunker,
+    ContentExtractor, CrawlConfig, CrawlMode, CrawlPlan, CrawlProgress, DiscoveredUrl,
+    DocumentationFocus, SkippedPage, TextChunker, EXTRACTOR_VERSION,
 };
 use crate::embedding_basic::EmbeddingService;
-use crate::vectordb::VectorDatabase;
+use crate::shutdown;
+use crate::telemetry;
+use crate::vectordb::{Document, Provenance, VectorDatabase};
 
 type SharedRateLimiter = Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>>;
 
+/// Pages buffered between two pipeline stages
+const STAGE_BUFFER: usize = 16;
+
+/// Inventory symbols embedded per batch
+const SYMBOL_BATCH: usize = 64;
+
+/// Frames fetched per page
+const MAX_FRAMES: usize = 8;
+
+/// A page downloaded by the fetch stage
+struct FetchedPage {
+    url: String,
+    depth: usize,
+    body: String,
+    kind: PageKind,
+    /// Same-host frames and iframes of the page, fetched along with it
+    frames: Vec<Frame>,
+    fetched_at: SystemTime,
+    /// Hash of the body in the blob store
+    snapshot: Option<String>,
+    started: Instant,
+    span: tracing::Span,
+}
+
+/// A frame's document, indexed as part of the page embedding it
+struct Frame {
+    url: String,
+    body: String,
+    kind: PageKind,
+}
+
+/// Links found on a page, reported back to the fetch stage
+struct DiscoveredLinks {
+    url: String,
+    depth: usize,
+    links: Vec<String>,
+    /// Next and previous pages of the same article
+    article_pages: Vec<String>,
+}
+
+/// A page whose content was extracted
+struct ExtractedPage {
+    url: String,
+    extracted: ExtractedContent,
+    fetched_at: SystemTime,
+    snapshot: Option<String>,
+    started: Instant,
+    span: tracing::Span,
+}
+
+/// A page split into documents, ready to embed and write
+struct PreparedPage {
+    url: String,
+    /// Page already indexed or in flight that this one duplicates
+    duplicate_of: Option<String>,
+    documents: Vec<Document>,
+    embeddings: Vec<Vec<f32>>,
+    started: Instant,
+    span: tracing::Span,
+}
+
+/// What crawling a single page would index
+#[derive(Debug, Clone)]
+pub struct PagePreview {
+    pub url: String,
+    pub kind: PageKind,
+    pub extracted: ExtractedContent,
+    /// Links a section or full crawl would consider following
+    pub links: Vec<String>,
+    /// Chunks the page would be indexed as, not yet embedded
+    pub documents: Vec<Document>,
+}
+
 pub struct Crawler {
     config: CrawlConfig,
-    client: Client,
+    client: HttpClient,
+    auth: CrawlAuth,
     rate_limiter: SharedRateLimiter,
-    extractor: ContentExtractor,
+    extractor: Arc<ContentExtractor>,
     chunker: TextChunker,
     visited_urls: Arc<Mutex<HashSet<String>>>,
     url_queue: Arc<Mutex<VecDeque<(String, usize)>>>, // (url, depth)
     progress: Arc<Mutex<CrawlProgress>>,
+    breakers: Arc<Mutex<HostBreakers>>,
+    /// Repository the crawl was started in, if any
+    repository: Option<Repository>,
+    /// Repository pages to fetch raw, by web URL
+    repo_pages: Arc<Mutex<HashMap<String, RepoPage>>>,
+    /// Pages listed in the site's search index, by URL
+    site_pages: BTreeMap<String, IndexedPage>,
+    /// Sphinx inventory of the site, indexed after its pages
+    inventory_url: Option<String>,
+    /// Pages of paginated articles queued so far
+    article_pages: Arc<Mutex<ArticlePages>>,
+    /// ID of this crawl run, recorded in the provenance of every chunk
+    session: String,
+    /// Where extracted pages are kept for re-chunking, if anywhere
+    page_store: Option<PageStore>,
+    /// Where fetched bodies are snapshotted, if anywhere
+    blob_store: Option<BlobStore>,
     // robots_cache: Arc<Mutex<HashMap<String, RobotFileParser>>>, // TODO: Add back with alternative crate
 }
 
 impl Crawler {
     pub async fn new(config: CrawlConfig) -> Result<Self> {
-        // Create HTTP client with appropriate headers
-        let client = Client::builder()
-            .user_agent(&config.user_agent)
-            .timeout(Duration::from_secs(30))
-            .build()?;
+        // Create HTTP client with appropriate headers, proxy and TLS settings
+        let client = build_client(&config.user_agent, &config.client)?;
+        let auth = CrawlAuth::new(&config.auth)?;
 
         // Create rate limiter based on config
         let quota = Quota::per_second(
@@ -50,12 +178,15 @@ impl Crawler {
                 .unwrap_or(NonZeroU32::new(2).unwrap()),
         );
         let rate_limiter = Arc::new(RateLimiter::direct(quota));
+        let breakers = Arc::new(Mutex::new(HostBreakers::new(config.breaker)));
+        let repository = Repository::parse(&config.start_url);
 
         Ok(Self {
             config,
             client,
+            auth,
             rate_limiter,
-            extractor: ContentExtractor::new()?,
+            extractor: Arc::new(ContentExtractor::new()?),
             chunker: TextChunker::new(),
             visited_urls: Arc::new(Mutex::new(HashSet::new())),
             url_queue: Arc::new(Mutex::new(VecDeque::new())),
@@ -64,198 +195,1138 @@ impl Crawler {
                 pages_queued: 0,
                 pages_failed: 0,
                 current_url: None,
+                retries: 0,
+                host_pauses: Vec::new(),
+                skipped: Vec::new(),
             })),
+            breakers,
+            repository,
+            repo_pages: Arc::new(Mutex::new(HashMap::new())),
+            site_pages: BTreeMap::new(),
+            inventory_url: None,
+            article_pages: Arc::new(Mutex::new(ArticlePages::default())),
+            session: uuid::Uuid::new_v4().to_string(),
+            page_store: None,
+            blob_store: None,
             // robots_cache: Arc::new(Mutex::new(HashMap::new())), // TODO: Add back
         })
     }
 
+    /// ID of this crawl run, for tracing chunks back to it
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Use a custom chunker for crawled pages
+    pub fn with_chunker(mut self, chunker: TextChunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    /// Record chunks under an existing session ID, for a run spanning several crawlers
+    pub fn with_session(mut self, session: String) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Keep the extracted content of crawled pages in `page_store`
+    pub fn with_page_store(mut self, page_store: Option<PageStore>) -> Self {
+        self.page_store = page_store;
+        self
+    }
+
+    /// Snapshot the body of every fetched page in `blob_store`
+    pub fn with_blob_store(mut self, blob_store: Option<BlobStore>) -> Self {
+        self.blob_store = blob_store;
+        self
+    }
+
+    /// Re-chunk and re-embed a stored page without fetching it again
+    ///
+    /// Returns the number of chunks written; they replace the page's old ones.
+    pub async fn rechunk_page(
+        &mut self,
+        page: StoredPage,
+        embedding_service: &EmbeddingService,
+        vector_db: &mut VectorDatabase,
+    ) -> Result<usize> {
+        let simhash = page_fingerprint(&page.extracted.markdown);
+        let provenance = self.provenance(page.fetched_at, page.snapshot.clone());
+        let mut chunker = std::mem::take(&mut self.chunker);
+        let documents = self.page_documents(
+            &mut chunker,
+            &page.url,
+            &page.extracted,
+            simhash,
+            &provenance,
+        );
+        self.chunker = chunker;
+
+        let contents: Vec<String> = documents
+            .iter()
+            .map(|document| document.content.clone())
+            .collect();
+        let embeddings = embedding_service.embed_passages(&contents).await?;
+        write_documents(vector_db, &page.url, documents, embeddings)?;
+        Ok(contents.len())
+    }
+
+    /// Fetch, extract and chunk the start URL as a crawl would, without indexing anything
+    pub async fn preview(&mut self) -> Result<PagePreview> {
+        let url = self.config.start_url.clone();
+        let (body, kind) = self.download_with_retries(&url).await?;
+        let extracted = self.extractor.extract_page(&body, &url, kind)?;
+        let links = match kind {
+            PageKind::Html => page_links(&body, &url),
+            PageKind::Markdown | PageKind::PlainText => Vec::new(),
+        };
+
+        let simhash = page_fingerprint(&extracted.markdown);
+        let provenance = self.provenance(SystemTime::now(), None);
+        let mut chunker = std::mem::take(&mut self.chunker);
+        let documents = self.page_documents(&mut chunker, &url, &extracted, simhash, &provenance);
+        self.chunker = chunker;
+
+        Ok(PagePreview {
+            url,
+            kind,
+            extracted,
+            links,
+            documents,
+        })
+    }
+
+    /// Crawl from the start URL, indexing pages into `vector_db`
+    ///
+    /// Returns the URLs of the pages indexed, in the order they were written.
+    /// Once the process starts shutting down no more pages are fetched, and
+    /// the crawl ends after indexing those already fetched.
     pub async fn crawl(
         &mut self,
         embedding_service: &EmbeddingService,
         vector_db: &mut VectorDatabase,
     ) -> Result<Vec<String>> {
-        // Initialize the queue with the start URL
-        {
-            let mut queue = self.url_queue.lock().await;
-            queue.push_back((self.config.start_url.clone(), 0));
+        if let Some(source) = QaSource::parse(&self.config.start_url) {
+            return self
+                .ingest_questions(&source, embedding_service, vector_db)
+                .await;
         }
+        if self.config.start_url.ends_with("objects.inv") {
+            let url = self.config.start_url.clone();
+            self.ingest_inventory(&url, embedding_service, vector_db)
+                .await?;
+            return Ok(vec![url]);
+        }
+
+        self.seed_queue().await;
+
+        // The chunk stage is the only one that needs the chunker mutably
+        let mut chunker = std::mem::take(&mut self.chunker);
+        let vector_db = Mutex::new(vector_db);
+
+        let (fetched_tx, fetched_rx) = mpsc::channel(STAGE_BUFFER);
+        let (links_tx, links_rx) = mpsc::unbounded_channel();
+        let (extracted_tx, extracted_rx) = mpsc::channel(STAGE_BUFFER);
+        let (prepared_tx, prepared_rx) = mpsc::channel(STAGE_BUFFER);
+        let (embedded_tx, embedded_rx) = mpsc::channel(STAGE_BUFFER);
+
+        let ((), (), (), (), crawled_urls) = tokio::join!(
+            self.fetch_stage(fetched_tx, links_rx),
+            self.extract_stage(fetched_rx, links_tx, extracted_tx),
+            self.chunk_stage(&mut chunker, &vector_db, extracted_rx, prepared_tx),
+            self.embed_stage(embedding_service, prepared_rx, embedded_tx),
+            self.write_stage(&vector_db, embedded_rx),
+        );
+
+        self.chunker = chunker;
+
+        if let Some(url) = self.inventory_url.clone() {
+            let vector_db = vector_db.into_inner();
+            if let Err(e) = self
+                .ingest_inventory(&url, embedding_service, vector_db)
+                .await
+            {
+                tracing::warn!("Skipping Sphinx inventory {}: {}", url, e);
+            }
+        }
+        Ok(crawled_urls)
+    }
+
+    /// List the URLs a crawl would fetch, in crawl order, without indexing anything
+    ///
+    /// Only the pages whose links the crawl would follow are downloaded, so a
+    /// section crawl costs one request besides the site's search index.
+    pub async fn discover(&mut self) -> Result<CrawlPlan> {
+        let start_url = self.config.start_url.clone();
+        // Questions and inventories are fetched through APIs, not by following links
+        let follows_links =
+            QaSource::parse(&start_url).is_none() && !start_url.ends_with("objects.inv");
+        if follows_links {
+            self.seed_queue().await;
+        } else {
+            self.url_queue
+                .lock()
+                .await
+                .push_back((start_url.clone(), 0));
+        }
+
+        let mut urls = Vec::new();
+        let mut pages_fetched = 0;
+        while urls.len() < self.config.max_pages {
+            let Some((url, depth)) = self.next_url().await else {
+                break;
+            };
+            let found_by = if self.repo_pages.lock().await.contains_key(&url) {
+                "repository"
+            } else if url == start_url {
+                "start"
+            } else if self.site_pages.contains_key(&url) {
+                "site_index"
+            } else if self.article_pages.lock().await.contains(&url) {
+                "pagination"
+            } else {
+                "link"
+            };
+
+            let follows_pages = self.config.max_pages_per_article > 1;
+            if follows_links && (self.should_follow_links(depth) || follows_pages) {
+                self.rate_limiter.until_ready().await;
+                if pages_fetched > 0 {
+                    sleep(Duration::from_millis(self.config.delay_ms)).await;
+                }
+                pages_fetched += 1;
+                match self.page_links_of(&url).await {
+                    Ok((links, article_pages)) => {
+                        if self.should_follow_links(depth) {
+                            self.queue_links(links, depth + 1).await;
+                        }
+                        self.queue_article_pages(&url, article_pages, depth).await;
+                    }
+                    Err(e) => tracing::warn!("Could not read the links of {}: {}", url, e),
+                }
+            }
+            urls.push(DiscoveredUrl {
+                url,
+                depth,
+                found_by: found_by.to_string(),
+            });
+        }
+
+        let truncated = !self.url_queue.lock().await.is_empty();
+        Ok(CrawlPlan {
+            urls,
+            pages_fetched,
+            truncated,
+        })
+    }
+
+    /// Queue the start URL, or the repository's pages, and the site index's pages
+    async fn seed_queue(&mut self) {
+        match self.repository_pages().await {
+            Some(pages) => {
+                let mut repo_pages = self.repo_pages.lock().await;
+                let mut queue = self.url_queue.lock().await;
+                for page in pages {
+                    queue.push_back((page.url.clone(), 0));
+                    repo_pages.insert(page.url.clone(), page);
+                }
+            }
+            None => {
+                let start_url = self.config.start_url.clone();
+                self.url_queue.lock().await.push_back((start_url, 0));
+                if self.config.mode != CrawlMode::SinglePage {
+                    self.seed_from_site_index().await;
+                }
+            }
+        }
+    }
+
+    /// Download a page for its links and article pages alone, as the extract stage would report them
+    async fn page_links_of(&self, url: &str) -> Result<(Vec<String>, Vec<String>)> {
+        let (body, kind) = self.download_with_retries(url).await?;
+        let repo_page = self.repo_pages.lock().await.get(url).cloned();
+        let links = match (repo_page, &self.repository) {
+            (Some(page), Some(repository)) if repository.is_wiki_url(url) => {
+                let content = self.extractor.extract_markdown(&body, url, &page.title);
+                let mut repo_pages = self.repo_pages.lock().await;
+                repository
+                    .wiki_links(&content.markdown)
+                    .into_iter()
+                    .map(|page| {
+                        let url = page.url.clone();
+                        repo_pages.entry(url.clone()).or_insert(page);
+                        url
+                    })
+                    .collect()
+            }
+            (Some(_), Some(_)) => Vec::new(),
+            _ => match kind {
+                PageKind::Html => {
+                    let mut links = page_links(&body, url);
+                    for frame in self.fetch_frames(url, &body, kind).await {
+                        if frame.kind == PageKind::Html {
+                            links.extend(page_links(&frame.body, &frame.url));
+                        }
+                    }
+                    return Ok((links, article_page_links(&body, url)));
+                }
+                PageKind::Markdown | PageKind::PlainText => Vec::new(),
+            },
+        };
+        Ok((links, Vec::new()))
+    }
+
+    /// Index a Stack Exchange question or tag as one document per good answer
+    ///
+    /// A single question falls back to its page's HTML when the API fails.
+    async fn ingest_questions(
+        &self,
+        source: &QaSource,
+        embedding_service: &EmbeddingService,
+        vector_db: &mut VectorDatabase,
+    ) -> Result<Vec<String>> {
+        let threads = match source
+            .fetch_threads(&self.client, &self.auth, self.config.max_pages)
+            .await
+        {
+            Ok(threads) => threads,
+            Err(e) if matches!(source.target, QaTarget::Question(_)) => {
+                tracing::warn!(
+                    "Stack Exchange API request failed ({}), reading the question page instead",
+                    e
+                );
+                let url = &self.config.start_url;
+                let (html, _) = self.download_with_retries(url).await?;
+                qa::parse_thread_html(&html, url).into_iter().collect()
+            }
+            Err(e) => return Err(e),
+        };
 
         let mut crawled_urls = Vec::new();
+        for thread in threads {
+            let started = Instant::now();
+            let span = tracing::info_span!(
+                "crawl_page",
+                url = %thread.url,
+                depth = 0,
+                chunks = tracing::field::Empty,
+                elapsed_ms = tracing::field::Empty
+            );
+            let documents = thread.documents();
+            if documents.is_empty() {
+                tracing::debug!("No answers worth indexing for {}", thread.url);
+                continue;
+            }
+
+            let contents: Vec<String> = documents
+                .iter()
+                .map(|document| document.content.clone())
+                .collect();
+            let written = async {
+                let embeddings = embedding_service.embed_passages(&contents).await?;
+                for (document, embedding) in documents.into_iter().zip(embeddings) {
+                    vector_db.add_document(document, embedding)?;
+                }
+                Ok::<_, CodeRagError>(())
+            }
+            .instrument(span.clone())
+            .await;
+
+            match written {
+                Ok(()) => {
+                    span.record("chunks", contents.len());
+                    let elapsed = span.in_scope(|| telemetry::record_elapsed(started));
+                    telemetry::record_page_crawled(contents.len(), elapsed);
+                    self.progress.lock().await.pages_crawled += 1;
+                    crawled_urls.push(thread.url);
+                }
+                Err(e) => self.record_failure(&thread.url, &e).await,
+            }
+        }
+
+        Ok(crawled_urls)
+    }
+
+    /// Index the symbols of a Sphinx inventory as reference documents
+    async fn ingest_inventory(
+        &self,
+        url: &str,
+        embedding_service: &EmbeddingService,
+        vector_db: &mut VectorDatabase,
+    ) -> Result<()> {
+        let inventory = Inventory::fetch(&self.client, &self.auth, url).await?;
+        let documents = inventory.documents();
+        tracing::info!(
+            "Indexing {} symbols from the {} inventory",
+            documents.len(),
+            inventory.project
+        );
+
+        for batch in documents.chunks(SYMBOL_BATCH) {
+            let contents: Vec<String> = batch
+                .iter()
+                .map(|document| document.content.clone())
+                .collect();
+            let embeddings = embedding_service.embed_passages(&contents).await?;
+            for (document, embedding) in batch.iter().zip(embeddings) {
+                vector_db.add_document(document.clone(), embedding)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue the pages listed in the site's search index, if it has one
+    ///
+    /// A section crawl only takes the pages below the start URL's path.
+    async fn seed_from_site_index(&mut self) {
+        let Some(index) =
+            SiteIndex::discover(&self.client, &self.auth, &self.config.start_url).await
+        else {
+            return;
+        };
+        if index.generator == Generator::Sphinx {
+            self.inventory_url = index.base.join("objects.inv").ok().map(String::from);
+        }
+        self.site_pages = index.pages;
+
+        let urls = self
+            .site_pages
+            .keys()
+            .filter(|url| self.in_scope(url))
+            .cloned()
+            .collect();
+        self.queue_links(urls, 1).await;
+    }
+
+    /// List the pages of the repository the crawl starts in
+    ///
+    /// Returns `None` when the start URL isn't in a repository, or when
+    /// listing fails and the rendered pages should be crawled instead.
+    async fn repository_pages(&self) -> Option<Vec<RepoPage>> {
+        let repository = self.repository.as_ref()?;
+        let include_docs = self.config.mode != CrawlMode::SinglePage;
+        match repository
+            .list_pages(&self.client, &self.auth, include_docs)
+            .await
+        {
+            Ok(pages) if !pages.is_empty() => {
+                tracing::info!(
+                    "Crawling {} documentation pages from {}",
+                    pages.len(),
+                    repository.path
+                );
+                Some(pages)
+            }
+            Ok(_) => {
+                tracing::warn!("No documentation found in {}", repository.path);
+                None
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Listing {} failed ({}), crawling its rendered pages instead",
+                    repository.path,
+                    e
+                );
+                None
+            }
+        }
+    }
 
-        // Main crawl loop
-        while let Some((url, depth)) = self.get_next_url().await {
-            // Check if we've reached our limits
-            if crawled_urls.len() >= self.config.max_pages {
-                tracing::info!("Reached max pages limit: {}", self.config.max_pages);
+    /// Fetch queued URLs, following the links the extract stage reports
+    ///
+    /// Downloads in flight, the wait before the next request and handing
+    /// pages to the extract stage all progress together, so a slow extract
+    /// stage doesn't stall downloads. Fetched pages waiting for it are capped
+    /// at the concurrency, after which no new requests start.
+    ///
+    /// Ends once the queue is empty (or the page limit is reached) and every
+    /// fetched page has been through extraction, which closes the pipeline.
+    async fn fetch_stage(
+        &self,
+        fetched: mpsc::Sender<FetchedPage>,
+        mut discovered: mpsc::UnboundedReceiver<DiscoveredLinks>,
+    ) {
+        let concurrency = self.config.concurrent_requests.max(1);
+        let delay = Duration::from_millis(self.config.delay_ms);
+        let mut in_flight = FuturesUnordered::new();
+        // The next request, waiting for the rate limiter and delay
+        let mut starting: Option<BoxFuture<'_, (String, usize)>> = None;
+        let mut ready: VecDeque<FetchedPage> = VecDeque::new();
+        let mut pages_fetched = 0;
+        let mut extracting = 0;
+        let mut requests_started = 0;
+        let mut limit_logged = false;
+        let mut shutdown_logged = false;
+
+        loop {
+            // Line up a request while below the concurrency and page limits
+            if starting.is_none() && in_flight.len() < concurrency && ready.len() < concurrency {
+                if shutdown::requested() {
+                    // Pages already fetched are still indexed, but no more are started
+                    if !shutdown_logged {
+                        tracing::info!("Shutting down: not fetching any more pages");
+                        shutdown_logged = true;
+                    }
+                } else if pages_fetched + in_flight.len() >= self.config.max_pages {
+                    if !limit_logged && !self.url_queue.lock().await.is_empty() {
+                        tracing::info!("Reached max pages limit: {}", self.config.max_pages);
+                        limit_logged = true;
+                    }
+                } else if let Some((url, depth)) = self.next_url().await {
+                    // TODO: Check robots.txt when we have a working crate
+                    // if !self.is_allowed_by_robots(&url).await {
+                    //     tracing::warn!("Blocked by robots.txt: {}", url);
+                    //     continue;
+                    // }
+
+                    // Rate limiting, with a delay between requests
+                    let first = requests_started == 0;
+                    requests_started += 1;
+                    starting = Some(Box::pin(async move {
+                        self.rate_limiter.until_ready().await;
+                        if !first {
+                            sleep(delay).await;
+                        }
+                        (url, depth)
+                    }));
+                }
+            }
+
+            if starting.is_none() && in_flight.is_empty() && ready.is_empty() && extracting == 0 {
                 break;
             }
 
+            tokio::select! {
+                Some((url, depth)) = async {
+                    match starting.as_mut() {
+                        Some(start) => Some(start.await),
+                        None => None,
+                    }
+                }, if starting.is_some() => {
+                    starting = None;
+                    self.progress.lock().await.current_url = Some(url.clone());
+                    in_flight.push(self.fetch_page(url, depth));
+                }
+                Some(outcome) = in_flight.next(), if !in_flight.is_empty() => match outcome {
+                    Ok(page) => {
+                        pages_fetched += 1;
+                        ready.push_back(page);
+                    }
+                    Err((url, e)) => self.record_failure(&url, &e).await,
+                },
+                permit = fetched.reserve(), if !ready.is_empty() => match (permit, ready.pop_front()) {
+                    (Ok(permit), Some(page)) => {
+                        extracting += 1;
+                        permit.send(page);
+                    }
+                    // The extract stage stopped early
+                    _ => break,
+                },
+                Some(found) = discovered.recv(), if extracting > 0 => {
+                    extracting -= 1;
+                    if self.should_follow_links(found.depth) {
+                        self.queue_links(found.links, found.depth + 1).await;
+                    }
+                    self.queue_article_pages(&found.url, found.article_pages, found.depth)
+                        .await;
+                }
+                // The extract stage stopped early
+                else => break,
+            }
+        }
+    }
+
+    /// Take the next URL to crawl from the queue
+    async fn next_url(&self) -> Option<(String, usize)> {
+        loop {
+            let (url, depth) = self.url_queue.lock().await.pop_front()?;
             if depth > self.config.max_depth {
                 tracing::debug!("Skipping {} - exceeds max depth", url);
                 continue;
             }
-
-            // Update progress
-            {
-                let mut progress = self.progress.lock().await;
-                progress.current_url = Some(url.clone());
+            // Mark as visited
+            if self.visited_urls.lock().await.insert(url.clone()) {
+                return Some((url, depth));
             }
+        }
+    }
 
-            // TODO: Check robots.txt when we have a working crate
-            // if !self.is_allowed_by_robots(&url).await {
-            //     tracing::warn!("Blocked by robots.txt: {}", url);
-            //     continue;
-            // }
+    /// Download a page, failing with the URL so the fetch stage can report it
+    async fn fetch_page(
+        &self,
+        url: String,
+        depth: usize,
+    ) -> std::result::Result<FetchedPage, (String, CodeRagError)> {
+        let started = Instant::now();
+        let span = tracing::info_span!(
+            "crawl_page",
+            url = %url,
+            depth,
+            chunks = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty
+        );
 
-            // Rate limiting
+        match self
+            .download_with_retries(&url)
+            .instrument(span.clone())
+            .await
+        {
+            Ok((body, kind)) => Ok(FetchedPage {
+                snapshot: self.snapshot(&url, &body),
+                frames: self
+                    .fetch_frames(&url, &body, kind)
+                    .instrument(span.clone())
+                    .await,
+                url,
+                depth,
+                body,
+                kind,
+                fetched_at: SystemTime::now(),
+                started,
+                span,
+            }),
+            Err(e) => Err((url, e)),
+        }
+    }
+
+    /// Download the frames and iframes of an HTML page that are on its host
+    ///
+    /// Frame documents are marked visited, so they aren't indexed again as
+    /// pages of their own. A frame that fails to download is left out.
+    async fn fetch_frames(&self, url: &str, body: &str, kind: PageKind) -> Vec<Frame> {
+        if kind != PageKind::Html {
+            return Vec::new();
+        }
+        let mut frames = Vec::new();
+        for frame_url in frame_sources(body, url) {
+            if !self.visited_urls.lock().await.insert(frame_url.clone()) {
+                continue;
+            }
             self.rate_limiter.until_ready().await;
+            match self.download_with_retries(&frame_url).await {
+                Ok((body, kind)) => frames.push(Frame {
+                    url: frame_url,
+                    body,
+                    kind,
+                }),
+                Err(e) => tracing::warn!("Could not fetch frame {} of {}: {}", frame_url, url, e),
+            }
+        }
+        frames
+    }
+
+    /// Download a page, retrying transient failures and honoring the host's circuit breaker
+    async fn download_with_retries(&self, url: &str) -> Result<(String, PageKind)> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let policy = self.config.retry;
+
+        let mut retry = 0;
+        loop {
+            self.wait_for_host(&host).await;
+            let error = match self.download(url).await {
+                Ok(page) => {
+                    self.breakers.lock().await.record_success(&host);
+                    return Ok(page);
+                }
+                Err(e) if is_transient(&e) => e,
+                Err(e) => return Err(e),
+            };
 
-            // Crawl the page
-            match self
-                .crawl_page(&url, depth, embedding_service, vector_db)
+            let pause = self
+                .breakers
+                .lock()
                 .await
-            {
-                Ok(result) => {
-                    crawled_urls.push(url.clone());
+                .record_failure(&host, Instant::now());
+            if let Some(pause) = pause {
+                tracing::warn!(
+                    "Pausing requests to {} for {} ms after {} consecutive failures",
+                    pause.host,
+                    pause.paused_ms,
+                    pause.failures
+                );
+                self.progress.lock().await.host_pauses.push(pause);
+            }
+
+            if retry >= policy.max_retries {
+                return Err(error);
+            }
+            retry += 1;
+            let backoff = policy.backoff(retry);
+            tracing::warn!(
+                "Fetching {} failed ({}), retry {} of {} in {:?}",
+                url,
+                error,
+                retry,
+                policy.max_retries,
+                backoff
+            );
+            self.progress.lock().await.retries += 1;
+            sleep(backoff).await;
+        }
+    }
+
+    /// Wait until the host's circuit breaker lets requests through
+    async fn wait_for_host(&self, host: &str) {
+        loop {
+            let paused_until = self
+                .breakers
+                .lock()
+                .await
+                .paused_until(host, Instant::now());
+            match paused_until {
+                Some(until) => tokio::time::sleep_until(until.into()).await,
+                None => return,
+            }
+        }
+    }
+
+    /// Download a page once, returning its body and how to extract it
+    async fn download(&self, url: &str) -> Result<(String, PageKind)> {
+        // Repository pages are fetched as raw Markdown
+        let url = match self.repo_pages.lock().await.get(url) {
+            Some(page) => page.raw_url.clone(),
+            None => url.to_string(),
+        };
+        let url = url.as_str();
+        let response = self
+            .client
+            .send(self.auth.apply(self.client.get(url), url))
+            .await?;
+
+        // Handle rate limiting (429) by backing off before the next request
+        if response.status() == 429 {
+            tracing::warn!("Rate limited at {}, backing off", url);
+            sleep(Duration::from_secs(10)).await;
+            return Err(CodeRagError::RateLimited);
+        }
+        let response = response.error_for_status()?;
 
-                    // Update progress
-                    {
-                        let mut progress = self.progress.lock().await;
-                        progress.pages_crawled += 1;
+        let page = read_page(response, self.config.max_page_bytes).await?;
+        if page.truncated {
+            tracing::warn!("Truncated {} at {} bytes", url, self.config.max_page_bytes);
+        }
+        Ok((page.body, page.kind))
+    }
+
+    /// Extract content and links from fetched pages on the blocking thread pool
+    async fn extract_stage(
+        &self,
+        mut fetched: mpsc::Receiver<FetchedPage>,
+        discovered: mpsc::UnboundedSender<DiscoveredLinks>,
+        extracted: mpsc::Sender<ExtractedPage>,
+    ) {
+        while let Some(page) = fetched.recv().await {
+            let FetchedPage {
+                url,
+                depth,
+                body,
+                kind,
+                frames,
+                fetched_at,
+                snapshot,
+                started,
+                span,
+            } = page;
+
+            let extractor = self.extractor.clone();
+            let repo_page = self.repo_pages.lock().await.get(&url).cloned();
+            let repository = self.repository.clone();
+            let indexed = self.site_pages.get(&url).cloned();
+            let page_url = url.clone();
+            let task_span = span.clone();
+            let outcome = tokio::task::spawn_blocking(move || {
+                task_span.in_scope(|| match (repo_page, repository) {
+                    (Some(page), Some(repository)) => {
+                        let content = extractor.extract_markdown(&body, &page_url, &page.title);
+                        // Only wiki pages link to further pages worth crawling
+                        let links = if repository.is_wiki_url(&page_url) {
+                            repository.wiki_links(&content.markdown)
+                        } else {
+                            Vec::new()
+                        };
+                        (Vec::new(), Vec::new(), links, Ok(content))
                     }
+                    _ => {
+                        let (mut links, article_pages) = match kind {
+                            PageKind::Html => (
+                                page_links(&body, &page_url),
+                                article_page_links(&body, &page_url),
+                            ),
+                            PageKind::Markdown | PageKind::PlainText => (Vec::new(), Vec::new()),
+                        };
+                        let content =
+                            extractor
+                                .extract_page(&body, &page_url, kind)
+                                .map(|mut content| {
+                                    for frame in &frames {
+                                        if frame.kind == PageKind::Html {
+                                            links.extend(page_links(&frame.body, &frame.url));
+                                        }
+                                        match extractor.extract_page(
+                                            &frame.body,
+                                            &frame.url,
+                                            frame.kind,
+                                        ) {
+                                            Ok(frame_content) => {
+                                                content.append_frame(frame_content)
+                                            }
+                                            Err(e) => tracing::warn!(
+                                                "Could not extract frame {}: {}",
+                                                frame.url,
+                                                e
+                                            ),
+                                        }
+                                    }
+                                    if let Some(indexed) = &indexed {
+                                        content.apply_index(indexed);
+                                    }
+                                    content
+                                });
+                        (links, article_pages, Vec::new(), content)
+                    }
+                })
+            })
+            .await;
 
-                    // Extract and queue new URLs based on crawl mode
-                    if self.should_follow_links(depth) {
-                        self.extract_and_queue_urls(&result, depth + 1).await?;
+            // Every fetched page reports back, so the fetch stage knows when the crawl is done
+            let (mut links, article_pages, wiki_pages, content) = match outcome {
+                Ok(outcome) => outcome,
+                Err(e) => (
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Err(CodeRagError::Parse(format!("Extraction panicked: {}", e))),
+                ),
+            };
+            if !wiki_pages.is_empty() {
+                let mut repo_pages = self.repo_pages.lock().await;
+                for page in wiki_pages {
+                    links.push(page.url.clone());
+                    repo_pages.entry(page.url.clone()).or_insert(page);
+                }
+            }
+            let _ = discovered.send(DiscoveredLinks {
+                url: url.clone(),
+                depth,
+                links,
+                article_pages,
+            });
+
+            match content {
+                Ok(content) => {
+                    let page = ExtractedPage {
+                        url,
+                        extracted: content,
+                        fetched_at,
+                        snapshot,
+                        started,
+                        span,
+                    };
+                    if extracted.send(page).await.is_err() {
+                        break;
                     }
                 }
-                Err(e) => {
-                    tracing::error!("Failed to crawl {}: {}", url, e);
-                    let mut progress = self.progress.lock().await;
-                    progress.pages_failed += 1;
+                Err(e) => self.record_failure(&url, &e).await,
+            }
+        }
+    }
+
+    /// Skip near-duplicate pages and split the rest into documents
+    async fn chunk_stage(
+        &self,
+        chunker: &mut TextChunker,
+        vector_db: &Mutex<&mut VectorDatabase>,
+        mut extracted: mpsc::Receiver<ExtractedPage>,
+        prepared: mpsc::Sender<PreparedPage>,
+    ) {
+        // Fingerprints of pages chunked by this crawl but possibly not yet written
+        let mut in_flight: Vec<(u64, String)> = Vec::new();
+
+        while let Some(page) = extracted.recv().await {
+            let ExtractedPage {
+                url,
+                extracted,
+                fetched_at,
+                snapshot,
+                started,
+                span,
+            } = page;
+
+            // Index only one copy of pages served at several URLs
+            let simhash = page_fingerprint(&extracted.markdown);
+            let duplicate_of = match simhash {
+                Some(simhash) => vector_db
+                    .lock()
+                    .await
+                    .find_near_duplicate(simhash, NEAR_DUPLICATE_DISTANCE, &url)
+                    .or_else(|| {
+                        in_flight
+                            .iter()
+                            .find(|(other, other_url)| {
+                                other_url != &url
+                                    && (other ^ simhash).count_ones() <= NEAR_DUPLICATE_DISTANCE
+                            })
+                            .map(|(_, other_url)| other_url.clone())
+                    }),
+                None => None,
+            };
+
+            let documents = span.in_scope(|| {
+                if let Some(original) = &duplicate_of {
+                    tracing::info!("{} duplicates {}, recording it as an alias", url, original);
+                    return Vec::new();
+                }
+                let provenance = self.provenance(fetched_at, snapshot.clone());
+                self.page_documents(chunker, &url, &extracted, simhash, &provenance)
+            });
+            if let (Some(simhash), None) = (simhash, &duplicate_of) {
+                in_flight.push((simhash, url.clone()));
+            }
+            if let (Some(store), None) = (&self.page_store, &duplicate_of) {
+                let stored = StoredPage {
+                    url: url.clone(),
+                    fetched_at,
+                    snapshot,
+                    extracted,
+                };
+                if let Err(e) = store.save(&stored) {
+                    tracing::warn!("Failed to store extracted page {}: {}", url, e);
                 }
             }
 
-            // Add delay between requests
-            sleep(Duration::from_millis(self.config.delay_ms)).await;
+            let page = PreparedPage {
+                url,
+                duplicate_of,
+                documents,
+                embeddings: Vec::new(),
+                started,
+                span,
+            };
+            if prepared.send(page).await.is_err() {
+                break;
+            }
         }
+    }
 
-        Ok(crawled_urls)
+    /// Provenance of the chunks of a page this crawl fetched at `fetched_at`
+    fn provenance(&self, fetched_at: SystemTime, snapshot: Option<String>) -> Provenance {
+        Provenance {
+            session: self.session.clone(),
+            extractor_version: EXTRACTOR_VERSION,
+            fetched_at,
+            snapshot,
+        }
     }
 
-    async fn get_next_url(&self) -> Option<(String, usize)> {
-        let mut queue = self.url_queue.lock().await;
-        queue.pop_front()
+    /// Save a fetched body to the blob store, if there is one, returning its hash
+    fn snapshot(&self, url: &str, body: &str) -> Option<String> {
+        let store = self.blob_store.as_ref()?;
+        store
+            .put(body.as_bytes())
+            .map_err(|e| tracing::warn!("Failed to snapshot {}: {}", url, e))
+            .ok()
     }
 
-    async fn crawl_page(
-        &mut self,
+    /// Chunk a page and describe each chunk as a document
+    fn page_documents(
+        &self,
+        chunker: &mut TextChunker,
         url: &str,
-        _depth: usize,
-        embedding_service: &EmbeddingService,
-        vector_db: &mut VectorDatabase,
-    ) -> Result<CrawlResult> {
-        // Mark as visited
-        {
-            let mut visited = self.visited_urls.lock().await;
-            visited.insert(url.to_string());
+        extracted: &ExtractedContent,
+        simhash: Option<u64>,
+        provenance: &Provenance,
+    ) -> Vec<Document> {
+        let mut chunks = chunker.chunk_text(&extracted.markdown);
+        if chunks.len() > self.config.max_chunks_per_page {
+            tracing::warn!(
+                "Keeping the first {} of {} chunks from {}",
+                self.config.max_chunks_per_page,
+                chunks.len(),
+                url
+            );
+            chunks.truncate(self.config.max_chunks_per_page);
         }
 
-        // Fetch the page
-        let response = self.client.get(url).send().await?;
+        // Changelog sections are tagged with the release they describe
+        let is_changelog = matches!(self.config.focus, DocumentationFocus::Changelog)
+            || changelog::is_changelog_page(url, &extracted.title);
 
-        // Handle rate limiting (429) with exponential backoff
-        if response.status() == 429 {
-            tracing::warn!("Rate limited at {}, backing off", url);
-            sleep(Duration::from_secs(10)).await;
-            return Err(anyhow::anyhow!("Rate limited"));
-        }
+        let chunk_count = chunks.len();
+        let mut ids = ChunkIds::new();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                // Deep link to the section the chunk belongs to
+                let (anchor, heading_path) = chunk
+                    .heading_context
+                    .as_deref()
+                    .and_then(|section| extracted.locate_section(section))
+                    .map_or((None, Vec::new()), |(anchor, path)| (Some(anchor), path));
+                let release = if is_changelog {
+                    changelog::find_release(&heading_path).or_else(|| {
+                        chunk
+                            .heading_context
+                            .as_deref()
+                            .and_then(changelog::parse_release_heading)
+                    })
+                } else {
+                    None
+                };
+                let images = extracted
+                    .figures
+                    .iter()
+                    .filter(|figure| chunk.content.contains(&figure.marker()))
+                    .filter_map(|figure| figure.url.clone())
+                    .collect();
 
-        let html = response.text().await?;
+                Document {
+                    id: ids.next_id(url, anchor.as_deref(), &chunk.content),
+                    content: chunk.content,
+                    url: url.to_string(),
+                    title: Some(extracted.title.clone()),
+                    section: chunk.heading_context,
+                    metadata: crate::vectordb::DocumentMetadata {
+                        content_type: crate::vectordb::ContentType::Documentation,
+                        language: extracted.metadata.language.clone(),
+                        // A chunk in a distinctive script overrides the page language
+                        lang: chunk.lang.or_else(|| extracted.metadata.lang.clone()),
+                        last_updated: Some(std::time::SystemTime::now()),
+                        tags: vec![
+                            if chunk.has_code {
+                                "has-code"
+                            } else {
+                                "no-code"
+                            }
+                            .to_string(),
+                            format!("chunk-{}-of-{}", i + 1, chunk_count),
+                        ],
+                        anchor,
+                        heading_path,
+                        boost: None,
+                        links: chunk.links,
+                        aliases: Vec::new(),
+                        simhash,
+                        version: release.as_ref().map(|r| r.version.clone()),
+                        release_date: release.and_then(|r| r.date),
+                        votes: None,
+                        images,
+                        canonical: None,
+                        provenance: Some(provenance.clone()),
+                    },
+                }
+            })
+            .collect()
+    }
 
-        // Extract content
-        let extracted = self.extractor.extract_content(&html, url)?;
+    /// Embed each page's chunks in one batch
+    async fn embed_stage(
+        &self,
+        embedding_service: &EmbeddingService,
+        mut prepared: mpsc::Receiver<PreparedPage>,
+        embedded: mpsc::Sender<PreparedPage>,
+    ) {
+        while let Some(mut page) = prepared.recv().await {
+            let contents: Vec<String> = page
+                .documents
+                .iter()
+                .map(|document| document.content.clone())
+                .collect();
+            let embeddings = embedding_service
+                .embed_passages(&contents)
+                .instrument(page.span.clone())
+                .await;
 
-        // Chunk the content
-        let chunks = self.chunker.chunk_text(&extracted.markdown);
+            match embeddings {
+                Ok(embeddings) => {
+                    page.embeddings = embeddings;
+                    if embedded.send(page).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => self.record_failure(&page.url, &e).await,
+            }
+        }
+    }
 
-        // Create documents and add to vector database
-        for (i, chunk) in chunks.iter().enumerate() {
-            let doc_id = format!("{}_chunk_{}", url, i);
+    /// Add embedded pages to the database, returning the URLs written
+    async fn write_stage(
+        &self,
+        vector_db: &Mutex<&mut VectorDatabase>,
+        mut embedded: mpsc::Receiver<PreparedPage>,
+    ) -> Vec<String> {
+        let mut crawled_urls = Vec::new();
 
-            // Generate embedding
-            let embedding = embedding_service.embed(&chunk.content).await?;
+        while let Some(page) = embedded.recv().await {
+            let PreparedPage {
+                url,
+                duplicate_of,
+                documents,
+                embeddings,
+                started,
+                span,
+            } = page;
+            let chunks = documents.len();
 
-            // Create document
-            let document = crate::vectordb::Document {
-                id: doc_id,
-                content: chunk.content.clone(),
-                url: url.to_string(),
-                title: Some(extracted.title.clone()),
-                section: chunk.heading_context.clone(),
-                metadata: crate::vectordb::DocumentMetadata {
-                    content_type: crate::vectordb::ContentType::Documentation,
-                    language: extracted.metadata.language.clone(),
-                    last_updated: Some(std::time::SystemTime::now()),
-                    tags: vec![
-                        if chunk.has_code {
-                            "has-code"
-                        } else {
-                            "no-code"
+            let written = {
+                let mut vector_db = vector_db.lock().await;
+                span.in_scope(|| -> Result<()> {
+                    match &duplicate_of {
+                        Some(original) => {
+                            vector_db.add_alias(original, &url);
+                            Ok(())
                         }
-                        .to_string(),
-                        format!("chunk-{}-of-{}", i + 1, chunks.len()),
-                    ],
-                },
+                        None => write_documents(&mut vector_db, &url, documents, embeddings),
+                    }
+                })
             };
 
-            // Add to database
-            vector_db.add_document(document, embedding)?;
-        }
-
-        // Create crawl result
-        let result = CrawlResult {
-            url: url.to_string(),
-            title: extracted.title,
-            content: extracted.markdown,
-            chunks,
-            metadata: CrawlMetadata {
-                crawled_at: Utc::now().to_rfc3339(),
-                content_type: "documentation".to_string(),
-                language: extracted.metadata.language,
-                framework: extracted.metadata.framework,
-                version: extracted.metadata.version,
-            },
-        };
+            match written {
+                Ok(()) => {
+                    span.record("chunks", chunks);
+                    let elapsed = span.in_scope(|| telemetry::record_elapsed(started));
+                    telemetry::record_page_crawled(chunks, elapsed);
+                    self.progress.lock().await.pages_crawled += 1;
+                    crawled_urls.push(url);
+                }
+                Err(e) => self.record_failure(&url, &e).await,
+            }
+        }
 
-        Ok(result)
+        crawled_urls
+    }
+
+    async fn record_failure(&self, url: &str, error: &CodeRagError) {
+        // Content that can't be indexed is reported, not counted as a failure
+        if let CodeRagError::UnsupportedContent(reason) = error {
+            tracing::info!("Skipping {}: {}", url, reason);
+            self.progress.lock().await.skipped.push(SkippedPage {
+                url: url.to_string(),
+                reason: reason.clone(),
+            });
+            return;
+        }
+        tracing::error!("Failed to crawl {}: {}", url, error);
+        telemetry::record_page_failed();
+        self.progress.lock().await.pages_failed += 1;
     }
 
     fn should_follow_links(&self, current_depth: usize) -> bool {
         match self.config.mode {
             CrawlMode::SinglePage => false,
-            CrawlMode::Section => current_depth == 0,
-            CrawlMode::FullDocs => current_depth < self.config.max_depth,
+            CrawlMode::Section | CrawlMode::FullDocs => current_depth < self.config.max_depth,
         }
     }
 
-    async fn extract_and_queue_urls(&self, result: &CrawlResult, next_depth: usize) -> Result<()> {
-        let base_url = Url::parse(&result.url)?;
-        let html = Html::parse_document(&result.content);
-        let link_selector = Selector::parse("a[href]").unwrap();
-
+    async fn queue_links(&self, links: Vec<String>, next_depth: usize) {
         let mut new_urls = Vec::new();
-
-        for element in html.select(&link_selector) {
-            if let Some(href) = element.value().attr("href") {
-                if let Ok(absolute_url) = base_url.join(href) {
-                    let url_str = absolute_url.to_string();
-
-                    // Check if we should crawl this URL
-                    if self.should_crawl_url(&url_str).await {
-                        new_urls.push((url_str, next_depth));
-                    }
-                }
+        for url in links {
+            // Check if we should crawl this URL
+            if self.should_crawl_url(&url).await {
+                new_urls.push((url, next_depth));
             }
         }
 
@@ -272,25 +1343,55 @@ impl Crawler {
         // Update progress
         let mut progress = self.progress.lock().await;
         progress.pages_queued = queue.len();
+    }
 
-        Ok(())
+    /// Queue the next and previous pages of the article at `url`, at its depth
+    ///
+    /// They are followed in every crawl mode, up to `max_pages_per_article`
+    /// pages per article.
+    async fn queue_article_pages(&self, url: &str, links: Vec<String>, depth: usize) {
+        if links.is_empty() {
+            return;
+        }
+        let mut article_pages = self.article_pages.lock().await;
+        let mut queue = self.url_queue.lock().await;
+        let visited = self.visited_urls.lock().await;
+        for link in links {
+            if visited.contains(&link) || queue.iter().any(|(u, _)| u == &link) {
+                continue;
+            }
+            if article_pages.admit(url, &link, self.config.max_pages_per_article) {
+                queue.push_back((link, depth));
+            }
+        }
+        self.progress.lock().await.pages_queued = queue.len();
     }
 
     async fn should_crawl_url(&self, url: &str) -> bool {
-        // Check if URL matches our patterns
-        let matches_include = self
-            .config
-            .url_patterns
-            .include
-            .iter()
-            .any(|pattern| url.contains(pattern));
+        // Repository pages were chosen by the adapter
+        if self.repo_pages.lock().await.contains_key(url) {
+            return true;
+        }
+
+        if !self.in_scope(url) {
+            return false;
+        }
+
+        // Check if URL matches our patterns; pages in the site's search index are docs
+        let matches_include = self.site_pages.contains_key(url)
+            || self
+                .config
+                .url_patterns
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(url));
 
         let matches_exclude = self
             .config
             .url_patterns
             .exclude
             .iter()
-            .any(|pattern| url.contains(pattern));
+            .any(|pattern| pattern.matches(url));
 
         if matches_exclude {
             return false;
@@ -310,13 +1411,32 @@ impl Crawler {
                 }
 
                 // Otherwise, check if the domain is allowed
-                return self.config.allowed_domains.contains(host);
+                let start_host = Url::parse(&self.config.start_url)
+                    .ok()
+                    .and_then(|start| start.host_str().map(String::from))
+                    .unwrap_or_default();
+                return self.config.domain_policy.allows(
+                    host,
+                    &start_host,
+                    &self.config.allowed_domains,
+                );
             }
         }
 
         false
     }
 
+    /// Whether a section crawl covers `url`; other modes cover every URL
+    fn in_scope(&self, url: &str) -> bool {
+        if self.config.mode != CrawlMode::Section {
+            return true;
+        }
+        match (Url::parse(&self.config.start_url), Url::parse(url)) {
+            (Ok(start), Ok(url)) => in_section(&start, &url),
+            _ => false,
+        }
+    }
+
     // TODO: Implement robots.txt checking when we have a working crate
     // async fn is_allowed_by_robots(&self, url: &str) -> bool {
     //     true // Allow all for now
@@ -326,3 +1446,393 @@ impl Crawler {
         self.progress.lock().await.clone()
     }
 }
+
+/// Whether `url` shares the path prefix of a section starting at `start`
+///
+/// The prefix is the start URL's path up to its last `/`, or the whole path
+/// when its last segment looks like a directory rather than a file, so both
+/// `/docs/tokio` and `/docs/tokio/index.html` start the section `/docs/tokio/`.
+fn in_section(start: &Url, url: &Url) -> bool {
+    if start.scheme() != url.scheme()
+        || start.host_str() != url.host_str()
+        || start.port_or_known_default() != url.port_or_known_default()
+    {
+        return false;
+    }
+    let path = start.path();
+    let prefix = match path.rsplit_once('/') {
+        Some((dir, last)) if last.contains('.') => format!("{}/", dir),
+        _ if path.ends_with('/') => path.to_string(),
+        _ => format!("{}/", path),
+    };
+    url.path().starts_with(&prefix)
+}
+
+/// Absolute URLs of a page's links, without fragments
+fn page_links(html: &str, url: &str) -> Vec<String> {
+    let Ok(base_url) = Url::parse(url) else {
+        return Vec::new();
+    };
+    let html = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    html.select(&link_selector)
+        .filter_map(|element| element.value().attr("href"))
+        .filter_map(|href| base_url.join(href).ok())
+        .map(|mut absolute_url| {
+            absolute_url.set_fragment(None);
+            absolute_url.to_string()
+        })
+        .collect()
+}
+
+/// Absolute URLs of a page's frames and iframes on the page's own host
+fn frame_sources(html: &str, url: &str) -> Vec<String> {
+    let Ok(base_url) = Url::parse(url) else {
+        return Vec::new();
+    };
+    let html = Html::parse_document(html);
+    let frame_selector = Selector::parse("frame[src], iframe[src]").unwrap();
+
+    let mut sources: Vec<String> = Vec::new();
+    for src in html
+        .select(&frame_selector)
+        .filter_map(|element| element.value().attr("src"))
+    {
+        let Ok(mut frame_url) = base_url.join(src) else {
+            continue;
+        };
+        frame_url.set_fragment(None);
+        if frame_url.host_str() != base_url.host_str()
+            || !matches!(frame_url.scheme(), "http" | "https")
+        {
+            continue;
+        }
+        let frame_url = String::from(frame_url);
+        if frame_url != url && !sources.contains(&frame_url) {
+            sources.push(frame_url);
+        }
+    }
+    sources.truncate(MAX_FRAMES);
+    sources
+}
+
+/// Write a page's chunks, replacing the ones it no longer has
+fn write_documents(
+    vector_db: &mut VectorDatabase,
+    url: &str,
+    mut documents: Vec<Document>,
+    embeddings: Vec<Vec<f32>>,
+) -> Result<()> {
+    // Chunks the page no longer has; unchanged ones are superseded
+    if !documents.is_empty() {
+        let ids: HashSet<&str> = documents.iter().map(|d| d.id.as_str()).collect();
+        vector_db.remove_documents_where(|document| {
+            document.url == url && !ids.contains(document.id.as_str())
+        })?;
+    }
+    // Chunks copied from another site point at the copy indexed first
+    vector_db.link_duplicate_chunks(documents.iter_mut());
+    for (document, embedding) in documents.into_iter().zip(embeddings) {
+        vector_db.add_document(document, embedding)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_page_links() {
+        let html = r#"<html><body>
+            <nav><a href="/docs/guide">Guide</a></nav>
+            <main>
+                <a href="runtime#timeouts">Timeouts</a>
+                <a href="https://other.dev/api/">Other</a>
+                <a name="anchor-only">No href</a>
+            </main>
+        </body></html>"#;
+
+        let links = page_links(html, "https://example.com/docs/tokio/");
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/docs/guide",
+                "https://example.com/docs/tokio/runtime",
+                "https://other.dev/api/",
+            ]
+        );
+        assert!(page_links(html, "not a url").is_empty());
+    }
+
+    #[test]
+    fn test_frame_sources() {
+        let html = r#"<html><frameset cols="20%,80%">
+            <frame src="allclasses-frame.html" name="packageFrame">
+            <frame src="overview-summary.html#top" name="classFrame">
+            <frame src="allclasses-frame.html">
+            <noframes><iframe src="https://ads.example.net/banner"></iframe></noframes>
+        </frameset></html>"#;
+
+        assert_eq!(
+            frame_sources(html, "https://docs.example.com/api/index.html"),
+            vec![
+                "https://docs.example.com/api/allclasses-frame.html",
+                "https://docs.example.com/api/overview-summary.html",
+            ]
+        );
+        assert!(
+            frame_sources(r#"<iframe src="about:blank"></iframe>"#, "https://a.dev/").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_in_section() {
+        let url = |u: &str| Url::parse(u).unwrap();
+        let start = url("https://docs.example.com/guide/tokio");
+
+        assert!(in_section(
+            &start,
+            &url("https://docs.example.com/guide/tokio/runtime")
+        ));
+        assert!(in_section(
+            &start,
+            &url("https://docs.example.com/guide/tokio/sync/mutex.html")
+        ));
+        assert!(!in_section(
+            &start,
+            &url("https://docs.example.com/guide/tokio-util/")
+        ));
+        assert!(!in_section(
+            &start,
+            &url("https://docs.example.com/guide/hyper/")
+        ));
+        assert!(!in_section(
+            &start,
+            &url("https://other.example.com/guide/tokio/runtime")
+        ));
+        assert!(!in_section(
+            &start,
+            &url("http://docs.example.com/guide/tokio/runtime")
+        ));
+
+        // A file's section is its directory
+        let start = url("https://docs.example.com/guide/tokio/index.html");
+        assert!(in_section(
+            &start,
+            &url("https://docs.example.com/guide/tokio/runtime")
+        ));
+        assert!(!in_section(
+            &start,
+            &url("https://docs.example.com/guide/hyper/")
+        ));
+        assert!(in_section(
+            &url("https://docs.example.com/"),
+            &url("https://docs.example.com/any/page")
+        ));
+    }
+
+    /// A local docs site of `pages` pages, /docs/page0 onwards, each linking
+    /// to the next two and answered after `delay`
+    struct TestSite {
+        base: String,
+        /// Requests answered so far
+        served: Arc<AtomicUsize>,
+        /// Most requests being answered at once
+        max_concurrent: Arc<AtomicUsize>,
+    }
+
+    async fn serve_site(pages: usize, delay: Duration) -> TestSite {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_concurrent = Arc::new(AtomicUsize::new(0));
+
+        let site = TestSite {
+            base,
+            served: served.clone(),
+            max_concurrent: max_concurrent.clone(),
+        };
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let (served, active, max_concurrent) =
+                    (served.clone(), active.clone(), max_concurrent.clone());
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let page: Option<usize> = request
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|path| path.strip_prefix("/docs/page"))
+                        .and_then(|n| n.parse().ok())
+                        .filter(|&n| n < pages);
+
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_concurrent.fetch_max(now, Ordering::SeqCst);
+                    sleep(delay).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+
+                    let response = match page {
+                        Some(n) => {
+                            let body = format!(
+                                "<html><head><title>Page {n}</title></head><body><main>\
+                                 <h1>Page {n}</h1><p>Documentation for page {n}.</p>\
+                                 <a href=\"/docs/page{}\">Next</a> <a href=\"/docs/page{}\">Skip</a>\
+                                 </main></body></html>",
+                                n + 1,
+                                n + 2
+                            );
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                body.len(),
+                                body
+                            )
+                        }
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string(),
+                    };
+                    served.fetch_add(1, Ordering::SeqCst);
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        site
+    }
+
+    async fn site_crawler(site: &TestSite, max_pages: usize, concurrency: usize) -> Crawler {
+        Crawler::new(CrawlConfig {
+            start_url: format!("{}/docs/page0", site.base),
+            mode: CrawlMode::FullDocs,
+            max_pages,
+            max_depth: 100,
+            concurrent_requests: concurrency,
+            delay_ms: 0,
+            ..CrawlConfig::default()
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Run the fetch stage against an extract stage that reports each page's
+    /// links, waiting for `gate` before taking the first page
+    ///
+    /// Returns the URLs fetched, in the order they were handed over.
+    async fn run_fetch_stage(
+        crawler: &Crawler,
+        buffer: usize,
+        gate: tokio::sync::oneshot::Receiver<()>,
+    ) -> Vec<String> {
+        let (fetched_tx, mut fetched_rx) = mpsc::channel::<FetchedPage>(buffer);
+        let (links_tx, links_rx) = mpsc::unbounded_channel();
+        let extract = async move {
+            let _ = gate.await;
+            let mut urls = Vec::new();
+            while let Some(page) = fetched_rx.recv().await {
+                let links = page_links(&page.body, &page.url);
+                urls.push(page.url.clone());
+                let _ = links_tx.send(DiscoveredLinks {
+                    url: page.url,
+                    depth: page.depth,
+                    links,
+                    article_pages: Vec::new(),
+                });
+            }
+            urls
+        };
+        let run = async { tokio::join!(crawler.fetch_stage(fetched_tx, links_rx), extract).1 };
+        tokio::time::timeout(Duration::from_secs(30), run)
+            .await
+            .expect("fetch stage didn't finish")
+    }
+
+    fn open_gate() -> tokio::sync::oneshot::Receiver<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tx.send(()).unwrap();
+        rx
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stage_follows_links_until_the_site_is_exhausted() {
+        let site = serve_site(6, Duration::ZERO).await;
+        let crawler = site_crawler(&site, 100, 2).await;
+        crawler
+            .url_queue
+            .lock()
+            .await
+            .push_back((crawler.config.start_url.clone(), 0));
+
+        let mut urls = run_fetch_stage(&crawler, STAGE_BUFFER, open_gate()).await;
+        urls.sort();
+        let expected: Vec<String> = (0..6)
+            .map(|n| format!("{}/docs/page{}", site.base, n))
+            .collect();
+        assert_eq!(urls, expected);
+        // Links past the last page are fetched once and fail
+        assert_eq!(crawler.get_progress().await.pages_failed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stage_stops_at_max_pages() {
+        let site = serve_site(50, Duration::from_millis(50)).await;
+        let crawler = site_crawler(&site, 5, 3).await;
+        crawler
+            .url_queue
+            .lock()
+            .await
+            .push_back((crawler.config.start_url.clone(), 0));
+
+        let urls = run_fetch_stage(&crawler, STAGE_BUFFER, open_gate()).await;
+        assert_eq!(urls.len(), 5);
+        assert_eq!(site.served.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stage_keeps_downloading_while_extraction_is_blocked() {
+        let site = serve_site(10, Duration::from_millis(100)).await;
+        let crawler = site_crawler(&site, 100, 2).await;
+        {
+            let mut queue = crawler.url_queue.lock().await;
+            for n in 0..10 {
+                queue.push_back((format!("{}/docs/page{}", site.base, n), 0));
+            }
+        }
+
+        let (open, gate) = tokio::sync::oneshot::channel();
+        let served = site.served.clone();
+        let check = async move {
+            // With the channel full, downloads in flight still complete and
+            // new ones start until `concurrency` pages wait for extraction
+            let wait = async {
+                while served.load(Ordering::SeqCst) < 4 {
+                    sleep(Duration::from_millis(10)).await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(10), wait)
+                .await
+                .expect("downloads stalled behind the full channel");
+            // ...and then no more start
+            sleep(Duration::from_millis(500)).await;
+            assert_eq!(served.load(Ordering::SeqCst), 4);
+            open.send(()).unwrap();
+        };
+
+        let (urls, ()) = tokio::join!(run_fetch_stage(&crawler, 1, gate), check);
+        assert_eq!(urls.len(), 10);
+        assert!(site.max_concurrent.load(Ordering::SeqCst) >= 2);
+    }
+}
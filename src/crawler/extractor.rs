@@ -1,5 +1,29 @@
-use anyhow::Result;
-use scraper::{Html, Selector};
+use crate::crawler::fetch::PageKind;
+use crate::crawler::language::{detect_script_language, normalize_language_tag};
+use crate::crawler::links::inline_links;
+use crate::crawler::readability::find_content_root;
+use crate::crawler::site_index::IndexedPage;
+use crate::crawler::tables::table_to_markdown;
+use crate::error::Result;
+use scraper::node::Text;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Version of extraction and chunking, recorded with every crawled chunk
+///
+/// Bump it when a change alters what gets indexed for the same page, so
+/// `coderag reprocess` re-crawls the pages indexed before the change.
+pub const EXTRACTOR_VERSION: u32 = 1;
+
+/// Stands in for a converted table until the markdown has been cleaned
+const TABLE_PLACEHOLDER: &str = "coderag-table-";
+
+/// Stands in for an image's description until the markdown has been cleaned
+const FIGURE_PLACEHOLDER: &str = "coderag-figure-";
+
+/// Images with a side at most this many pixels are icons, not figures
+const ICON_SIZE: u32 = 32;
 
 /// Content extractor for cleaning and extracting main content from HTML
 #[derive(Debug)]
@@ -39,6 +63,9 @@ impl ContentExtractor {
         // Extract title
         let title = self.extract_title(&document);
 
+        // Record headings and their anchors before cleanup strips ids
+        let headings = self.extract_headings(&document);
+
         // Remove unwanted elements more thoroughly
         let clean_html = self.remove_unwanted_elements_advanced(&document);
         let clean_doc = Html::parse_document(&clean_html);
@@ -51,23 +78,134 @@ impl ContentExtractor {
         // Extract code blocks before converting to markdown
         let code_blocks = self.extract_code_blocks(&Html::parse_document(&content_html));
 
+        // Tables are converted separately and restored after cleanup
+        let (content_html, tables) = self.extract_tables(&content_html);
+
+        // So are images, which would otherwise be dropped with their alt text
+        let (content_html, figures) = self.extract_figures(&content_html, url);
+
         // Convert to markdown with appropriate width (80 chars default)
         let markdown = html2text::from_read(content_html.as_bytes(), 80);
 
+        // Keep link targets with their text before cleanup separates them
+        let markdown = match Url::parse(url) {
+            Ok(base) => inline_links(&markdown, &base),
+            Err(_) => markdown,
+        };
+
         // Post-process markdown to clean it up more thoroughly
         let cleaned_markdown = self.clean_markdown_advanced(&markdown);
+        let cleaned_markdown = restore_tables(&cleaned_markdown, &tables);
+        let cleaned_markdown = restore_figures(&cleaned_markdown, &figures);
 
         // Extract metadata
-        let metadata = self.extract_metadata(&document, url);
+        let mut metadata = self.extract_metadata(&document, url);
+
+        // Undeclared language: fall back to the script of the text
+        if metadata.lang.is_none() {
+            metadata.lang = detect_script_language(&cleaned_markdown).map(str::to_string);
+        }
 
         Ok(ExtractedContent {
             title,
             markdown: cleaned_markdown,
             code_blocks,
             metadata,
+            headings,
+            figures,
         })
     }
 
+    /// Extract a fetched body with the extractor for its kind
+    ///
+    /// Markdown and plain text are indexed as they are, titled by their
+    /// first heading or else the last segment of the URL.
+    pub fn extract_page(&self, body: &str, url: &str, kind: PageKind) -> Result<ExtractedContent> {
+        match kind {
+            PageKind::Html => self.extract_content(body, url),
+            PageKind::Markdown | PageKind::PlainText => {
+                let title = url
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .filter(|segment| !segment.is_empty())
+                    .unwrap_or(url);
+                Ok(self.extract_markdown(body, url, title))
+            }
+        }
+    }
+
+    /// Describe a page that is already Markdown, such as a README fetched raw
+    ///
+    /// The first level-one heading is the title, falling back to `title`.
+    pub fn extract_markdown(&self, markdown: &str, url: &str, title: &str) -> ExtractedContent {
+        let mut headings = Vec::new();
+        let mut code_blocks = Vec::new();
+        let mut fence: Option<(String, Option<String>, Vec<&str>)> = None;
+
+        for line in markdown.lines() {
+            let trimmed = line.trim_start();
+            if let Some((marker, _, lines)) = fence.as_mut() {
+                if !trimmed.starts_with(marker.as_str()) {
+                    lines.push(line);
+                    continue;
+                }
+                if let Some((_, language, lines)) = fence.take() {
+                    code_blocks.push(CodeBlock {
+                        code: lines.join("\n"),
+                        language,
+                        context: None,
+                        usage_example: false,
+                        api_reference: false,
+                    });
+                }
+                continue;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                let info = trimmed.trim_start_matches(['`', '~']).trim();
+                let language = info.split_whitespace().next().map(str::to_string);
+                fence = Some((trimmed[..3].to_string(), language, Vec::new()));
+                continue;
+            }
+
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+                let text = clean_heading_text(&trimmed[level..]);
+                if !text.is_empty() {
+                    headings.push(Heading {
+                        level: level as u8,
+                        anchor: slugify(&text),
+                        text,
+                    });
+                }
+            }
+        }
+
+        let title = headings
+            .iter()
+            .find(|heading| heading.level == 1)
+            .map_or_else(|| title.to_string(), |heading| heading.text.clone());
+        let metadata = ContentMetadata {
+            url: url.to_string(),
+            description: None,
+            keywords: None,
+            author: None,
+            language: self.detect_language(url, markdown),
+            lang: detect_script_language(markdown).map(str::to_string),
+            framework: self.detect_framework(url, markdown),
+            version: None,
+        };
+
+        ExtractedContent {
+            title,
+            markdown: markdown.trim().to_string(),
+            code_blocks,
+            metadata,
+            headings,
+            figures: Vec::new(),
+        }
+    }
+
     fn extract_title(&self, document: &Html) -> String {
         let title_selector = Selector::parse("title").unwrap();
         let h1_selector = Selector::parse("h1").unwrap();
@@ -91,10 +229,128 @@ impl ContentExtractor {
         "Untitled Document".to_string()
     }
 
+    fn extract_headings(&self, document: &Html) -> Vec<Heading> {
+        let heading_selector = Selector::parse("h1, h2, h3, h4, h5, h6").unwrap();
+        let anchor_selector = Selector::parse("[id], a[name]").unwrap();
+        let mut headings = Vec::new();
+
+        for element in document.select(&heading_selector) {
+            let text = clean_heading_text(&element.text().collect::<String>());
+            if text.is_empty() {
+                continue;
+            }
+            let level = element.value().name()[1..].parse().unwrap_or(6);
+
+            // Prefer an explicit id: on the heading, inside it, or on a wrapping
+            // section (Sphinx); fall back to a GitHub-style slug of the text
+            let anchor = element
+                .value()
+                .id()
+                .or_else(|| {
+                    element
+                        .select(&anchor_selector)
+                        .find_map(|el| el.value().id().or_else(|| el.value().attr("name")))
+                })
+                .or_else(|| {
+                    let parent = element.parent().and_then(scraper::ElementRef::wrap)?;
+                    let first_child = parent.children().find_map(scraper::ElementRef::wrap)?;
+                    (first_child.id() == element.id()
+                        && matches!(parent.value().name(), "section" | "div"))
+                    .then(|| parent.value().id())
+                    .flatten()
+                })
+                .map(str::to_string)
+                .unwrap_or_else(|| slugify(&text));
+
+            headings.push(Heading {
+                level,
+                text,
+                anchor,
+            });
+        }
+
+        headings
+    }
+
+    /// Replace convertible tables with placeholders, returning their Markdown
+    fn extract_tables(&self, html: &str) -> (String, Vec<String>) {
+        let mut document = Html::parse_document(html);
+        let table_selector = Selector::parse("table").unwrap();
+        let mut tables = Vec::new();
+
+        // Layout tables aren't converted, but the tables nested in them are
+        replace_elements(&mut document, &table_selector, |table| {
+            let markdown = table_to_markdown(table)?;
+            tables.push(markdown);
+            Some(format!("{}{}", TABLE_PLACEHOLDER, tables.len() - 1))
+        });
+
+        (document.html(), tables)
+    }
+
+    /// Replace figures and meaningful images with placeholders
+    ///
+    /// Each keeps its alt text and caption, and its image URL resolved
+    /// against the page. Images without either, and icons, are left alone.
+    fn extract_figures(&self, html: &str, url: &str) -> (String, Vec<Figure>) {
+        let mut document = Html::parse_document(html);
+        let figure_selector = Selector::parse("figure").unwrap();
+        let img_selector = Selector::parse("img").unwrap();
+        let caption_selector = Selector::parse("figcaption").unwrap();
+        let base = Url::parse(url).ok();
+        let image_url = |img: ElementRef| {
+            let src = img.value().attr("src")?;
+            let resolved = match &base {
+                Some(base) => base.join(src).ok()?,
+                None => Url::parse(src).ok()?,
+            };
+            matches!(resolved.scheme(), "http" | "https").then(|| resolved.to_string())
+        };
+
+        let mut figures = Vec::new();
+        let mut placeholder = |figure: Figure| {
+            figures.push(figure);
+            Some(format!("{}{}", FIGURE_PLACEHOLDER, figures.len() - 1))
+        };
+
+        replace_elements(&mut document, &figure_selector, |figure| {
+            let img = figure.select(&img_selector).next();
+            let alt = img.and_then(alt_text).unwrap_or_default();
+            let caption = figure
+                .select(&caption_selector)
+                .next()
+                .map(|caption| collapse_whitespace(&caption.text().collect::<String>()))
+                .unwrap_or_default();
+            let description = match (alt.is_empty(), caption.is_empty()) {
+                (true, true) => return None,
+                (false, true) => alt,
+                (true, false) => caption,
+                (false, false) if alt == caption => alt,
+                (false, false) => format!("{} — {}", alt, caption),
+            };
+            let url = img.and_then(image_url);
+            placeholder(Figure { description, url })
+        });
+
+        // Images in figures were replaced with them and are no longer found
+        replace_elements(&mut document, &img_selector, |img| {
+            let is_icon = ["width", "height"].iter().any(|side| {
+                img.value()
+                    .attr(side)
+                    .and_then(|size| size.trim_end_matches("px").parse::<u32>().ok())
+                    .is_some_and(|size| size <= ICON_SIZE)
+            });
+            let description = alt_text(img).filter(|_| !is_icon)?;
+            let url = image_url(img);
+            placeholder(Figure { description, url })
+        });
+
+        (document.html(), figures)
+    }
+
     fn remove_unwanted_elements_advanced(&self, document: &Html) -> String {
-        // Create a new document by cloning the original
-        let mut html = document.html();
-        let mut doc = Html::parse_document(&html);
+        // Work on a copy of the tree so the original stays intact
+        let mut doc = document.clone();
 
         // Define comprehensive selectors for unwanted elements
         let unwanted_selectors = [
@@ -164,31 +420,20 @@ impl ContentExtractor {
             ".search-form",
         ];
 
-        // Remove elements by parsing fresh each time to avoid stale references
+        // Detach matching subtrees from the DOM
         for selector_str in &unwanted_selectors {
             if let Ok(selector) = Selector::parse(selector_str) {
-                // Collect elements to remove
-                let elements_to_remove: Vec<_> =
-                    doc.select(&selector).map(|el| el.html()).collect();
-
-                // Remove each element by string replacement
-                for element_html in elements_to_remove {
-                    html = html.replace(&element_html, "");
-                }
-
-                // Re-parse to get clean DOM
-                doc = Html::parse_document(&html);
+                remove_elements(&mut doc, &selector, |_| true);
             }
         }
 
         // Additional cleanup for elements with specific text content
-        self.remove_elements_by_text_content(&mut html);
+        self.remove_elements_by_text_content(&mut doc);
 
-        html
+        doc.html()
     }
 
-    fn remove_elements_by_text_content(&self, html: &mut String) {
-        let doc = Html::parse_document(html);
+    fn remove_elements_by_text_content(&self, doc: &mut Html) {
         let all_elements = Selector::parse("*").unwrap();
 
         let boilerplate_texts = [
@@ -203,24 +448,19 @@ impl ContentExtractor {
             "scroll to top",
         ];
 
-        for element in doc.select(&all_elements) {
+        remove_elements(doc, &all_elements, |element| {
             let text = element.text().collect::<String>().to_lowercase();
             let trimmed = text.trim();
 
-            // Remove elements that contain only boilerplate text
-            if boilerplate_texts
+            // Elements that contain only boilerplate text
+            let boilerplate = boilerplate_texts
                 .iter()
                 .any(|&pattern| trimmed.contains(pattern))
-                && trimmed.len() < 50
-            {
-                *html = html.replace(&element.html(), "");
-            }
+                && trimmed.len() < 50;
 
-            // Remove copyright notices
-            if trimmed.starts_with("©") || trimmed.starts_with("copyright") {
-                *html = html.replace(&element.html(), "");
-            }
-        }
+            // Copyright notices
+            boilerplate || trimmed.starts_with("©") || trimmed.starts_with("copyright")
+        });
     }
 
     fn find_main_content(&self, document: &Html) -> Option<String> {
@@ -263,6 +503,14 @@ impl ContentExtractor {
             }
         }
 
+        // No recognizable container: score candidates by their text
+        if let Some(element) = find_content_root(document) {
+            let content = self.filter_ai_relevant_content(&element, document);
+            if !content.trim().is_empty() && content.len() > 100 {
+                return Some(content);
+            }
+        }
+
         None
     }
 
@@ -272,8 +520,7 @@ impl ContentExtractor {
         _document: &Html,
     ) -> String {
         // Create a filtered version that removes navigation and focuses on valuable content for AI
-        let mut html = element.html();
-        let doc = Html::parse_document(&html);
+        let mut doc = Html::parse_document(&element.html());
 
         // Remove navigation elements using our selectors
         let unwanted_selectors = [
@@ -285,15 +532,11 @@ impl ContentExtractor {
             &self.menu_selector,
         ];
 
-        for selector in &unwanted_selectors {
-            let elements_to_remove: Vec<_> = doc.select(selector).map(|el| el.html()).collect();
-            for element_html in elements_to_remove {
-                html = html.replace(&element_html, "");
-            }
+        for selector in unwanted_selectors {
+            remove_elements(&mut doc, selector, |_| true);
         }
 
         // Additional cleanup for AI-focused content
-        let doc = Html::parse_document(&html);
         let mut valuable_sections = Vec::new();
 
         // Prioritize sections with code examples and explanations
@@ -321,7 +564,11 @@ impl ContentExtractor {
         if !valuable_sections.is_empty() {
             valuable_sections.join("\n")
         } else {
-            html
+            // The element was parsed into the body of its own document
+            let body = Selector::parse("body").unwrap();
+            doc.select(&body)
+                .next()
+                .map_or_else(|| doc.html(), |body| body.inner_html())
         }
     }
 
@@ -596,11 +843,31 @@ impl ContentExtractor {
             keywords: None,
             author: None,
             language: None,
+            lang: None,
             framework: None,
             version: None,
         };
 
+        // Declared page language: <html lang>, then Content-Language / og:locale
+        metadata.lang = document
+            .root_element()
+            .value()
+            .attr("lang")
+            .and_then(normalize_language_tag);
+
         for element in document.select(&meta_selector) {
+            let declared_lang = element
+                .value()
+                .attr("http-equiv")
+                .is_some_and(|v| v.eq_ignore_ascii_case("content-language"))
+                || element.value().attr("property") == Some("og:locale");
+            if declared_lang && metadata.lang.is_none() {
+                metadata.lang = element
+                    .value()
+                    .attr("content")
+                    .and_then(normalize_language_tag);
+            }
+
             if let Some(name) = element.value().attr("name") {
                 let content = element.value().attr("content").map(|s| s.to_string());
 
@@ -668,15 +935,236 @@ impl ContentExtractor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedContent {
     pub title: String,
     pub markdown: String,
     pub code_blocks: Vec<CodeBlock>,
     pub metadata: ContentMetadata,
+    pub headings: Vec<Heading>, // Page headings in document order
+    pub figures: Vec<Figure>,   // Images kept as descriptions, in document order
 }
 
-#[derive(Debug, Clone)]
+impl ExtractedContent {
+    /// Find the heading a chunk's section refers to, returning its anchor and
+    /// the heading path leading to it (outermost first)
+    pub fn locate_section(&self, section: &str) -> Option<(String, Vec<String>)> {
+        let key = heading_key(section);
+        let index = self
+            .headings
+            .iter()
+            .position(|heading| heading_key(&heading.text) == key)?;
+
+        let mut path = vec![self.headings[index].text.clone()];
+        let mut level = self.headings[index].level;
+        for heading in self.headings[..index].iter().rev() {
+            if heading.level < level {
+                path.push(heading.text.clone());
+                level = heading.level;
+            }
+        }
+        path.reverse();
+
+        Some((self.headings[index].anchor.clone(), path))
+    }
+
+    /// Add the content of a frame embedded in the page after the page's own
+    pub fn append_frame(&mut self, frame: ExtractedContent) {
+        if self.title.trim().is_empty() {
+            self.title = frame.title;
+        }
+        if !frame.markdown.trim().is_empty() {
+            if !self.markdown.trim().is_empty() {
+                self.markdown.push_str("\n\n");
+            }
+            self.markdown.push_str(&frame.markdown);
+        }
+        self.code_blocks.extend(frame.code_blocks);
+        self.headings.extend(frame.headings);
+        self.figures.extend(frame.figures);
+        let metadata = &mut self.metadata;
+        metadata.description = metadata.description.take().or(frame.metadata.description);
+        metadata.language = metadata.language.take().or(frame.metadata.language);
+        metadata.lang = metadata.lang.take().or(frame.metadata.lang);
+    }
+
+    /// Use the title and section anchors a site's search index lists for the page
+    ///
+    /// Index anchors are the ones the generator linked, so they replace the
+    /// anchors of headings with the same text.
+    pub fn apply_index(&mut self, page: &IndexedPage) {
+        if let Some(title) = &page.title {
+            self.title = title.clone();
+        }
+        for (text, anchor) in &page.sections {
+            let key = heading_key(text);
+            if let Some(heading) = self
+                .headings
+                .iter_mut()
+                .find(|heading| heading_key(&heading.text) == key)
+            {
+                heading.anchor = anchor.clone();
+            }
+        }
+    }
+}
+
+/// A page heading and the URL fragment that links to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+}
+
+/// Strip permalink markers that documentation generators append to headings
+/// Detach the elements matching `selector` for which `matches` returns true
+///
+/// Works on the tree itself, so the rest of the document is untouched no
+/// matter how the removed elements serialize.
+fn remove_elements(document: &mut Html, selector: &Selector, matches: impl Fn(ElementRef) -> bool) {
+    let ids: Vec<_> = document
+        .select(selector)
+        .filter(|element| matches(*element))
+        .map(|element| element.id())
+        .collect();
+    for id in ids {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
+
+/// Swap elements for paragraphs holding the placeholder `replacement` returns
+///
+/// Elements `replacement` returns `None` for are kept. Elements inside a
+/// replaced one, or in subtrees detached earlier, are skipped.
+fn replace_elements(
+    document: &mut Html,
+    selector: &Selector,
+    mut replacement: impl FnMut(ElementRef) -> Option<String>,
+) {
+    let root = document.tree.root().id();
+    let mut replaced = Vec::new();
+    for element in document.select(selector) {
+        // Selection also walks detached nodes
+        let attached = element.ancestors().last().map(|node| node.id()) == Some(root);
+        let inside_replaced = element
+            .ancestors()
+            .any(|node| replaced.iter().any(|(id, _)| *id == node.id()));
+        if !attached || inside_replaced {
+            continue;
+        }
+        if let Some(placeholder) = replacement(element) {
+            replaced.push((element.id(), placeholder));
+        }
+    }
+
+    let fragment = Html::parse_fragment("<p></p>");
+    let Some(paragraph) = fragment
+        .select(&Selector::parse("p").unwrap())
+        .next()
+        .map(|p| p.value().clone())
+    else {
+        return;
+    };
+    for (id, placeholder) in replaced {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.insert_before(Node::Element(paragraph.clone()))
+                .append(Node::Text(Text {
+                    text: placeholder.as_str().into(),
+                }));
+            node.detach();
+        }
+    }
+}
+
+/// Put converted tables back in place of their placeholders
+fn restore_tables(markdown: &str, tables: &[String]) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            line.trim()
+                .strip_prefix(TABLE_PLACEHOLDER)
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| tables.get(index))
+                .map_or_else(|| line.to_string(), |table| format!("\n{}\n", table))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Put image descriptions back in place of their placeholders
+fn restore_figures(markdown: &str, figures: &[Figure]) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            line.trim()
+                .strip_prefix(FIGURE_PLACEHOLDER)
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| figures.get(index))
+                .map_or_else(|| line.to_string(), Figure::marker)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An image's alt text, unless it is empty or decorative
+fn alt_text(img: ElementRef) -> Option<String> {
+    let alt = collapse_whitespace(img.value().attr("alt")?);
+    (!alt.is_empty() && img.value().attr("role") != Some("presentation")).then_some(alt)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn clean_heading_text(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    text.trim_end_matches(['¶', '#', '§', '🔗'])
+        .trim()
+        .to_string()
+}
+
+/// Comparison key that ignores markup and punctuation differences
+fn heading_key(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// GitHub-style heading slug: lowercase, spaces to dashes, punctuation dropped
+fn slugify(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() => Some(c.to_lowercase().next().unwrap_or(c)),
+            ' ' | '-' => Some('-'),
+            '_' => Some('_'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// An image kept as its description
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Figure {
+    /// Alt text and caption
+    pub description: String,
+    /// Absolute image URL, when it is an http(s) one
+    pub url: Option<String>,
+}
+
+impl Figure {
+    /// Inline text standing in for the image, e.g. "[Figure: request lifecycle]"
+    pub fn marker(&self) -> String {
+        format!("[Figure: {}]", self.description)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeBlock {
     pub code: String,
     pub language: Option<String>,
@@ -685,13 +1173,274 @@ pub struct CodeBlock {
     pub api_reference: bool,     // Whether this is API documentation code
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContentMetadata {
     pub url: String,
     pub description: Option<String>,
     pub keywords: Option<String>,
     pub author: Option<String>,
-    pub language: Option<String>,
+    pub language: Option<String>, // Programming language
+    pub lang: Option<String>,     // Natural language of the page, e.g. "ja"
     pub framework: Option<String>,
     pub version: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_markdown() {
+        let extractor = ContentExtractor::new().unwrap();
+        let markdown = "Intro text\n\n# Tokio\n\n## Getting Started!\n\n```rust\n# not a heading\nfn main() {}\n```\n\n### Next steps\n";
+        let extracted = extractor.extract_markdown(
+            markdown,
+            "https://github.com/tokio-rs/tokio/blob/master/README.md",
+            "tokio-rs/tokio",
+        );
+
+        assert_eq!(extracted.title, "Tokio");
+        let headings: Vec<_> = extracted
+            .headings
+            .iter()
+            .map(|heading| (heading.level, heading.anchor.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![(1, "tokio"), (2, "getting-started"), (3, "next-steps")]
+        );
+        assert_eq!(extracted.code_blocks.len(), 1);
+        assert_eq!(extracted.code_blocks[0].language.as_deref(), Some("rust"));
+        assert_eq!(
+            extracted.code_blocks[0].code,
+            "# not a heading\nfn main() {}"
+        );
+
+        let untitled = extractor.extract_markdown("Just text", "https://example.com/x.md", "x");
+        assert_eq!(untitled.title, "x");
+    }
+
+    #[test]
+    fn test_heading_anchors() {
+        let html = r#"<html><body><main>
+            <h1>Tokio</h1>
+            <section id="runtime"><h2>Runtime¶</h2><p>Runtime docs.</p></section>
+            <h3><a name="time-outs"></a>Timeouts</h3>
+            <h2 id="io-setup">I/O</h2>
+            <h2>Spawning Tasks</h2>
+        </main></body></html>"#;
+
+        let extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/tokio")
+            .unwrap();
+
+        let anchors: Vec<&str> = extracted
+            .headings
+            .iter()
+            .map(|h| h.anchor.as_str())
+            .collect();
+        assert_eq!(
+            anchors,
+            [
+                "tokio",
+                "runtime",
+                "time-outs",
+                "io-setup",
+                "spawning-tasks"
+            ]
+        );
+
+        let (anchor, path) = extracted.locate_section("Timeouts").unwrap();
+        assert_eq!(anchor, "time-outs");
+        assert_eq!(path, ["Tokio", "Runtime", "Timeouts"]);
+
+        // Chunk headings come from markdown and may differ in punctuation
+        let (anchor, path) = extracted.locate_section("I/O").unwrap();
+        assert_eq!(anchor, "io-setup");
+        assert_eq!(path, ["Tokio", "I/O"]);
+        assert!(extracted.locate_section("Missing").is_none());
+        assert_eq!(extracted.metadata.lang, None);
+    }
+
+    #[test]
+    fn test_apply_index() {
+        let html = "<html><head><title>Spawning - Tokio Docs</title></head><body><main>\
+                    <h1>Spawning</h1><h2>Spawning Tasks</h2><p>Spawn docs.</p></main></body></html>";
+        let mut extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/spawning/")
+            .unwrap();
+
+        extracted.apply_index(&IndexedPage {
+            title: Some("Spawning".to_string()),
+            sections: vec![("Spawning tasks".to_string(), "spawning-tasks-1".to_string())],
+        });
+        assert_eq!(extracted.title, "Spawning");
+        let (anchor, _) = extracted.locate_section("Spawning Tasks").unwrap();
+        assert_eq!(anchor, "spawning-tasks-1");
+    }
+
+    #[test]
+    fn test_remove_unwanted_elements() {
+        let html = r#"<html><body>
+            <header><nav><a href="/">Home</a></nav></header>
+            <div class="content">
+              <p>Spawning a task submits it to the runtime.</p>
+              <div class="sidebar"><div class="menu"><p>Guides</p></div></div>
+              <p><a href="/#top">Back to top</a></p>
+              <p>Spawning a task submits it to the runtime.</p>
+            </div>
+            <footer><p>© 2025 Example</p></footer>
+        </body></html>"#;
+
+        let extractor = ContentExtractor::new().unwrap();
+        let cleaned = extractor.remove_unwanted_elements_advanced(&Html::parse_document(html));
+        assert!(!cleaned.contains("Home"));
+        assert!(!cleaned.contains("Guides"));
+        assert!(!cleaned.contains("Back to top"));
+        assert!(!cleaned.contains("©"));
+        assert_eq!(cleaned.matches("submits it to the runtime").count(), 2);
+    }
+
+    #[test]
+    fn test_tables() {
+        let html = r#"<html><body><main>
+            <h1>Configuration</h1>
+            <p>The runtime builder accepts the following options.</p>
+            <table>
+              <tr><th>Option</th><th>Default</th><th>Description</th></tr>
+              <tr><td>worker_threads</td><td>cores</td><td>Number of worker threads</td></tr>
+              <tr><td>max_blocking_threads</td><td>512</td><td>Blocking pool limit</td></tr>
+            </table>
+        </main></body></html>"#;
+
+        let extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/config")
+            .unwrap();
+        assert!(extracted.markdown.contains(
+            "| Option | Default | Description |\n| --- | --- | --- |\n\
+             | worker_threads | cores | Number of worker threads |"
+        ));
+        assert!(!extracted.markdown.contains(TABLE_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_tables_with_attributes() {
+        let html = r#"<html><body><main>
+            <h1>Feature flags</h1>
+            <p>Each flag enables an optional part of the crate.</p>
+            <table class="docutils align-default" id="feature-flags" data-sortable="true" border="1">
+              <tr><th>Flag</th><th>Enables</th><th>Default</th></tr>
+              <tr><td>rt</td><td>The current-thread runtime</td><td>no</td></tr>
+              <tr><td>net</td><td>TCP and UDP sockets</td><td>no</td></tr>
+            </table>
+            <p>The full flag enables all of them.</p>
+            <table class="docutils align-default" id="full" data-sortable="true" border="1">
+              <tr><th>Flag</th><th>Enables</th><th>Default</th></tr>
+              <tr><td>full</td><td>Every flag above</td><td>no</td></tr>
+            </table>
+        </main></body></html>"#;
+
+        let extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/features")
+            .unwrap();
+        assert!(extracted.markdown.contains(
+            "| Flag | Enables | Default |\n| --- | --- | --- |\n\
+             | rt | The current-thread runtime | no |"
+        ));
+        assert!(extracted.markdown.contains(
+            "| Flag | Enables | Default |\n| --- | --- | --- |\n\
+             | full | Every flag above | no |"
+        ));
+        assert!(!extracted.markdown.contains(TABLE_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_figures() {
+        let html = r#"<html><body><main>
+            <h1>Architecture</h1>
+            <p>Every request passes through the same stages before reaching a handler.</p>
+            <figure>
+              <img src="/img/lifecycle.svg" alt="Request lifecycle diagram">
+              <figcaption>Middleware runs before routing.</figcaption>
+            </figure>
+            <p>Routing is described below. <img src="/icons/info.png" alt="Note" width="16"></p>
+            <img src="data:image/png;base64,AAAA" alt="Router tree">
+            <img src="/img/spacer.gif" alt="">
+        </main></body></html>"#;
+
+        let extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/guide/architecture")
+            .unwrap();
+        assert_eq!(
+            extracted.figures,
+            vec![
+                Figure {
+                    description: "Request lifecycle diagram — Middleware runs before routing."
+                        .to_string(),
+                    url: Some("https://docs.example.com/img/lifecycle.svg".to_string()),
+                },
+                Figure {
+                    description: "Router tree".to_string(),
+                    url: None,
+                },
+            ]
+        );
+        assert!(extracted
+            .markdown
+            .contains("[Figure: Request lifecycle diagram — Middleware runs before routing.]"));
+        assert!(extracted.markdown.contains("[Figure: Router tree]"));
+        assert!(!extracted.markdown.contains(FIGURE_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_links() {
+        let html = r#"<html><body><main>
+            <h1>Spawning</h1>
+            <p>Tasks are spawned onto the runtime. See the
+               <a href="../tutorial/spawning.html">spawning tutorial</a> for a walkthrough
+               of how the scheduler runs them on worker threads.</p>
+        </main></body></html>"#;
+
+        let extracted = ContentExtractor::new()
+            .unwrap()
+            .extract_content(html, "https://docs.example.com/api/task.html")
+            .unwrap();
+        assert!(extracted
+            .markdown
+            .contains("[spawning tutorial](https://docs.example.com/tutorial/spawning.html)"));
+        assert!(!extracted.markdown.contains("[1]"));
+    }
+
+    #[test]
+    fn test_page_language() {
+        let extractor = ContentExtractor::new().unwrap();
+        let extract = |html: &str| {
+            extractor
+                .extract_content(html, "https://docs.example.com/")
+                .unwrap()
+                .metadata
+                .lang
+        };
+
+        assert_eq!(
+            extract(r#"<html lang="ja-JP"><body><p>Hello</p></body></html>"#).as_deref(),
+            Some("ja")
+        );
+        assert_eq!(
+            extract(
+                r#"<html><head><meta property="og:locale" content="fr_FR"></head><body><p>Bonjour</p></body></html>"#
+            )
+            .as_deref(),
+            Some("fr")
+        );
+        assert_eq!(
+            extract("<html><body><p>非同期タスクを生成します</p></body></html>").as_deref(),
+            Some("ja")
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Recorded HTTP responses for deterministic crawler tests.
+//!
+//! A client built with fixtures sends every request through
+//! [`HttpFixtures::send`]. In record mode requests hit the network and each response is
+//! saved under the fixture directory as `<host>/<name>.json` (status, headers,
+//! final URL) plus `<name>.body` (the decoded body); in replay mode the saved
+//! responses are served instead and a request without a recording fails, so
+//! tests never touch the network. Extraction regressions on real sites (MDN,
+//! docs.rs, Read the Docs) are captured once and replayed in CI.
+//!
+//! Only built with the `test-fixtures` feature. Tests set
+//! [`ClientOptions::fixtures`](super::fetch::ClientOptions::fixtures); clients
+//! built without them pick fixtures up from
+//! `CODERAG_HTTP_FIXTURES=record:<dir>` or `replay:<dir>`.
+
+use crate::error::{CodeRagError, Result};
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use url::Url;
+
+/// Environment variable selecting a fixture mode and directory
+pub const FIXTURES_ENV: &str = "CODERAG_HTTP_FIXTURES";
+
+/// Response headers not recorded: the body is stored decoded, and cookies aren't needed
+const SKIPPED_HEADERS: &[&str] = &[
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+    "set-cookie",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Fetch from the network and save every response
+    Record,
+    /// Serve saved responses and fail requests that have none
+    Replay,
+}
+
+/// A directory of recorded responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpFixtures {
+    dir: PathBuf,
+    mode: FixtureMode,
+}
+
+/// What is saved of a response besides its body
+#[derive(Debug, Serialize, Deserialize)]
+struct Recording {
+    method: String,
+    url: String,
+    /// URL the response came from, after redirects
+    final_url: String,
+    status: u16,
+    headers: BTreeMap<String, String>,
+}
+
+impl HttpFixtures {
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: FixtureMode::Record,
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: FixtureMode::Replay,
+        }
+    }
+
+    /// Fixtures named by `CODERAG_HTTP_FIXTURES`, if set
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var(FIXTURES_ENV).ok()?;
+        match value.split_once(':') {
+            Some(("record", dir)) => Some(Self::record(dir)),
+            Some(("replay", dir)) => Some(Self::replay(dir)),
+            _ => {
+                warn!(
+                    "Ignoring {}={:?}: expected record:<dir> or replay:<dir>",
+                    FIXTURES_ENV, value
+                );
+                None
+            }
+        }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    /// Send a request, recording or replaying its response
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        let (meta_path, body_path) = self.paths(&method, request.url());
+
+        match self.mode {
+            FixtureMode::Replay => {
+                let missing = || {
+                    CodeRagError::MissingFixture(format!(
+                        "{} {} in {:?}; record it with {}=record:<dir>",
+                        method, url, self.dir, FIXTURES_ENV
+                    ))
+                };
+                let meta = fs::read_to_string(&meta_path).map_err(|_| missing())?;
+                let recording: Recording = serde_json::from_str(&meta)?;
+                let body = fs::read(&body_path).map_err(|_| missing())?;
+                debug!("Replaying {} {} from {:?}", method, url, meta_path);
+                recording.into_response(body)
+            }
+            FixtureMode::Record => {
+                let response = client.execute(request).await?;
+                let headers = response
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect();
+                let recording = Recording {
+                    method,
+                    url,
+                    final_url: response.url().to_string(),
+                    status: response.status().as_u16(),
+                    headers,
+                };
+                let body = response.bytes().await?.to_vec();
+                save(
+                    &meta_path,
+                    serde_json::to_string_pretty(&recording)?.as_bytes(),
+                )?;
+                save(&body_path, &body)?;
+                debug!(
+                    "Recorded {} {} to {:?}",
+                    recording.method, recording.url, meta_path
+                );
+                recording.into_response(body)
+            }
+        }
+    }
+
+    /// Files holding the recording and body of a request
+    ///
+    /// Names start with the URL path, readable in a diff, and end with a hash
+    /// of the method and full URL.
+    fn paths(&self, method: &str, url: &Url) -> (PathBuf, PathBuf) {
+        let hash = Sha256::digest(format!("{} {}", method, url).as_bytes());
+        let slug: String = url
+            .path()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let slug = slug.trim_matches('_');
+        let slug = if slug.is_empty() { "index" } else { slug };
+        // Only ASCII is left, and the end of a long path is the telling part
+        let slug = &slug[slug.len().saturating_sub(60)..];
+        let prefix = match method {
+            "GET" => String::new(),
+            other => format!("{}-", other.to_lowercase()),
+        };
+        let name = format!("{}{}-{}", prefix, slug, &format!("{:x}", hash)[..8]);
+        let dir = self.dir.join(url.host_str().unwrap_or("local"));
+        (
+            dir.join(format!("{}.json", name)),
+            dir.join(format!("{}.body", name)),
+        )
+    }
+}
+
+impl Recording {
+    fn into_response(self, body: Vec<u8>) -> Result<Response> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .url(Url::parse(&self.final_url)?);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder.body(body).map_err(|e| {
+            CodeRagError::Parse(format!("Invalid recording of {}: {}", self.url, e))
+        })?;
+        Ok(Response::from(response))
+    }
+}
+
+fn save(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+    }
+    fs::write(path, content).map_err(|e| CodeRagError::io(format!("Failed to write {:?}", path), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_replay() {
+        let dir = TempDir::new().unwrap();
+        let fixtures = HttpFixtures::replay(dir.path());
+        let client = reqwest::Client::new();
+        let url = Url::parse("https://docs.rs/tokio/latest/tokio/?search=spawn").unwrap();
+
+        let error = fixtures.send(client.get(url.as_str())).await.unwrap_err();
+        assert!(matches!(error, CodeRagError::MissingFixture(_)));
+
+        // A recording as record mode writes it, with a Latin-1 body
+        let (meta, body) = fixtures.paths("GET", &url);
+        assert!(meta.starts_with(dir.path().join("docs.rs")));
+        assert!(meta
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("tokio_latest_tokio-"));
+        let recording = Recording {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            final_url: "https://docs.rs/tokio/1.40.0/tokio/".to_string(),
+            status: 200,
+            headers: [(
+                "content-type".to_string(),
+                "text/html; charset=iso-8859-1".to_string(),
+            )]
+            .into(),
+        };
+        save(&meta, serde_json::to_string(&recording).unwrap().as_bytes()).unwrap();
+        save(&body, b"<p>Caf\xe9</p>").unwrap();
+
+        let response = fixtures.send(client.get(url.as_str())).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.url().as_str(),
+            "https://docs.rs/tokio/1.40.0/tokio/"
+        );
+        let page = crate::crawler::fetch::read_page(response, 1024)
+            .await
+            .unwrap();
+        assert_eq!(page.body, "<p>Café</p>");
+
+        // Other methods are recorded separately
+        let (head, _) = fixtures.paths("HEAD", &url);
+        assert_ne!(head, meta);
+    }
+}
@@ -0,0 +1,259 @@
+//! Sphinx `objects.inv` inventories.
+//!
+//! Sphinx publishes an inventory of every documented object (modules,
+//! classes, functions, options) with the exact URL of its description; it is
+//! what intersphinx uses to resolve cross-references. Each object becomes a
+//! small Reference document titled with its qualified name and pointing at
+//! its anchor, so a lookup such as "numpy.ndarray.reshape" lands on the
+//! right entry instead of on a page that merely mentions it.
+
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::fetch::HttpClient;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{ContentType, Document, DocumentMetadata, SYMBOL_TAG};
+use flate2::read::ZlibDecoder;
+use regex::Regex;
+use std::io::Read;
+use url::Url;
+
+/// An object listed in an inventory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryItem {
+    /// Fully qualified name, e.g. "numpy.ndarray.reshape"
+    pub name: String,
+    /// Sphinx domain, e.g. "py"
+    pub domain: String,
+    /// Object type within the domain, e.g. "method"
+    pub role: String,
+    /// Absolute URL of the object's description, with its anchor
+    pub url: String,
+    /// Name as displayed in the docs
+    pub display_name: String,
+}
+
+/// A parsed `objects.inv`
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    pub project: String,
+    pub version: String,
+    pub items: Vec<InventoryItem>,
+}
+
+impl Inventory {
+    /// Download and parse the inventory at `url`
+    pub async fn fetch(client: &HttpClient, auth: &CrawlAuth, url: &str) -> Result<Self> {
+        let response = client
+            .send(auth.apply(client.get(url), url))
+            .await?
+            .error_for_status()?;
+        let body = response.bytes().await?;
+        Self::parse(&body, &Url::parse(url)?)
+    }
+
+    /// Parse a version 2 inventory served at `url`
+    ///
+    /// Objects Sphinx hides from search (priority -1), and labels and pages,
+    /// which aren't symbols, are left out.
+    pub fn parse(data: &[u8], url: &Url) -> Result<Self> {
+        let invalid =
+            |what: &str| CodeRagError::Parse(format!("Invalid inventory {}: {}", url, what));
+
+        let mut rest = data;
+        let mut header = Vec::new();
+        for _ in 0..4 {
+            let end = rest
+                .iter()
+                .position(|b| *b == b'\n')
+                .ok_or_else(|| invalid("truncated header"))?;
+            header.push(String::from_utf8_lossy(&rest[..end]).trim().to_string());
+            rest = &rest[end + 1..];
+        }
+        if header[0] != "# Sphinx inventory version 2" {
+            return Err(invalid("only version 2 inventories are supported"));
+        }
+        let field = |line: &str, name: &str| {
+            line.strip_prefix(name)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let project = field(&header[1], "# Project:");
+        let version = field(&header[2], "# Version:");
+
+        let mut text = String::new();
+        ZlibDecoder::new(rest)
+            .read_to_string(&mut text)
+            .map_err(|e| invalid(&e.to_string()))?;
+
+        // name domain:role priority uri display-name; names may contain spaces
+        let line_re = Regex::new(r"^(.+?)\s+(\S+?):(\S+)\s+(-?\d+)\s+(\S*)\s+(.*)$").unwrap();
+        let items = text
+            .lines()
+            .filter_map(|line| {
+                let captures = line_re.captures(line)?;
+                let (name, domain, role) = (&captures[1], &captures[2], &captures[3]);
+                let hidden = &captures[4] == "-1";
+                if hidden || (domain == "std" && matches!(role, "label" | "doc")) {
+                    return None;
+                }
+
+                // "$" abbreviates the name, "-" a display name equal to it
+                let uri = match captures[5].strip_suffix('$') {
+                    Some(prefix) => format!("{}{}", prefix, name),
+                    None => captures[5].to_string(),
+                };
+                let display_name = match captures[6].trim() {
+                    "-" => name.to_string(),
+                    display => display.to_string(),
+                };
+                Some(InventoryItem {
+                    name: name.to_string(),
+                    domain: domain.to_string(),
+                    role: role.to_string(),
+                    url: url.join(&uri).ok()?.to_string(),
+                    display_name,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            project,
+            version,
+            items,
+        })
+    }
+
+    /// One Reference document per object
+    pub fn documents(&self) -> Vec<Document> {
+        self.items
+            .iter()
+            .map(|item| item.document(&self.project, &self.version))
+            .collect()
+    }
+}
+
+impl InventoryItem {
+    /// Describe the object as a document citing its anchor
+    pub fn document(&self, project: &str, version: &str) -> Document {
+        let (page_url, anchor) = match self.url.split_once('#') {
+            Some((page, anchor)) => (page.to_string(), Some(anchor.to_string())),
+            None => (self.url.clone(), None),
+        };
+
+        let mut content = format!("{}\n{}", self.name, self.kind());
+        if !project.is_empty() {
+            content.push_str(&format!(" documented in {} {}", project, version));
+        }
+        if self.display_name != self.name {
+            content.push_str(&format!("\n{}", self.display_name));
+        }
+
+        Document {
+            id: format!("{}_symbol_{}", page_url, self.name),
+            content: content.trim_end().to_string(),
+            url: page_url,
+            title: Some(self.name.clone()),
+            section: None,
+            metadata: DocumentMetadata {
+                content_type: ContentType::Reference,
+                language: self.language().map(str::to_string),
+                lang: None,
+                last_updated: Some(std::time::SystemTime::now()),
+                tags: vec![
+                    SYMBOL_TAG.to_string(),
+                    format!("{}:{}", self.domain, self.role),
+                ],
+                anchor,
+                heading_path: Vec::new(),
+                boost: None,
+                links: Vec::new(),
+                aliases: Vec::new(),
+                simhash: None,
+                version: None,
+                release_date: None,
+                votes: None,
+                images: Vec::new(),
+                canonical: None,
+                provenance: None,
+            },
+        }
+    }
+
+    /// Readable object type, e.g. "Python method"
+    fn kind(&self) -> String {
+        match (self.domain.as_str(), self.role.as_str()) {
+            ("std", "cmdoption") => "command-line option".to_string(),
+            ("std", "envvar") => "environment variable".to_string(),
+            ("std", "term") => "glossary term".to_string(),
+            (_, role) => match self.language() {
+                Some(language) => format!("{} {}", language, role),
+                None => format!("{}:{}", self.domain, role),
+            },
+        }
+    }
+
+    fn language(&self) -> Option<&'static str> {
+        match self.domain.as_str() {
+            "py" => Some("Python"),
+            "c" => Some("C"),
+            "cpp" => Some("C++"),
+            "js" => Some("JavaScript"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn inventory_bytes(entries: &str) -> Vec<u8> {
+        let mut data = b"# Sphinx inventory version 2\n# Project: NumPy\n# Version: 1.26\n\
+                         # The remainder of this file is compressed using zlib.\n"
+            .to_vec();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(entries.as_bytes()).unwrap();
+        data.extend(encoder.finish().unwrap());
+        data
+    }
+
+    #[test]
+    fn test_parse_inventory() {
+        let data = inventory_bytes(
+            "numpy.ndarray.reshape py:method 1 reference/generated/numpy.ndarray.reshape.html#$ -\n\
+             numpy py:module 0 reference/index.html#module-$ -\n\
+             numpy.private py:function -1 private.html#$ -\n\
+             basics.creation std:label -1 user/basics.creation.html#basics-creation Array creation\n\
+             array creation std:doc -1 user/basics.creation.html Array creation\n\
+             --verbose std:cmdoption 1 cli.html#cmdoption-verbose -\n",
+        );
+        let url = Url::parse("https://numpy.org/doc/stable/objects.inv").unwrap();
+        let inventory = Inventory::parse(&data, &url).unwrap();
+
+        assert_eq!(inventory.project, "NumPy");
+        assert_eq!(inventory.version, "1.26");
+        let names: Vec<&str> = inventory.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["numpy.ndarray.reshape", "numpy", "--verbose"]);
+        assert_eq!(
+            inventory.items[0].url,
+            "https://numpy.org/doc/stable/reference/generated/numpy.ndarray.reshape.html#numpy.ndarray.reshape"
+        );
+
+        let document = &inventory.documents()[0];
+        assert_eq!(document.title.as_deref(), Some("numpy.ndarray.reshape"));
+        assert_eq!(
+            document.citation_url(),
+            "https://numpy.org/doc/stable/reference/generated/numpy.ndarray.reshape.html#numpy.ndarray.reshape"
+        );
+        assert_eq!(document.metadata.content_type, ContentType::Reference);
+        assert_eq!(document.metadata.tags, ["symbol", "py:method"]);
+        assert_eq!(
+            document.content,
+            "numpy.ndarray.reshape\nPython method documented in NumPy 1.26"
+        );
+
+        assert!(Inventory::parse(b"# Sphinx inventory version 1\n", &url).is_err());
+    }
+}
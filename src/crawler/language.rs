@@ -0,0 +1,99 @@
+//! Natural language detection for crawled pages and chunks.
+//!
+//! Pages usually declare their language with `<html lang>` or a
+//! Content-Language meta tag. When they don't, or a chunk is written in a
+//! different script than its page, the dominant Unicode script identifies
+//! the language. Latin-script text can't be told apart this way and is left
+//! to the page declaration.
+
+/// Share of a text's letters a script needs before it decides the language
+const SCRIPT_THRESHOLD: f32 = 0.3;
+
+/// Normalize a BCP 47 tag or locale to its primary subtag ("ja-JP", "ja_JP" → "ja")
+pub fn normalize_language_tag(tag: &str) -> Option<String> {
+    let primary = tag.trim().split(['-', '_']).next()?;
+    let valid =
+        (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_alphabetic());
+    valid.then(|| primary.to_ascii_lowercase())
+}
+
+/// Detect a language from the dominant non-Latin script of a text
+pub fn detect_script_language(text: &str) -> Option<&'static str> {
+    let mut letters = 0;
+    let mut kana = 0;
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        let Some(language) = script_language(c) else {
+            continue;
+        };
+        if matches!(c, '\u{3040}'..='\u{30ff}') {
+            kana += 1;
+        }
+        match counts.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((language, 1)),
+        }
+    }
+
+    // Japanese mixes kanji with kana; Han characters alone read as Chinese
+    if kana > 0 {
+        let han = counts
+            .iter()
+            .find(|(l, _)| *l == "zh")
+            .map_or(0, |(_, n)| *n);
+        counts.retain(|(l, _)| *l != "zh");
+        if let Some((_, count)) = counts.iter_mut().find(|(l, _)| *l == "ja") {
+            *count += han;
+        }
+    }
+
+    let (language, count) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+    (count as f32 >= letters as f32 * SCRIPT_THRESHOLD).then_some(language)
+}
+
+/// Language written in the script a character belongs to, if not Latin
+fn script_language(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{3040}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' => "ja",
+        '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}' => "ko",
+        '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' => "zh",
+        '\u{0400}'..='\u{04ff}' => "ru",
+        '\u{0370}'..='\u{03ff}' => "el",
+        '\u{0590}'..='\u{05ff}' => "he",
+        '\u{0600}'..='\u{06ff}' => "ar",
+        '\u{0900}'..='\u{097f}' => "hi",
+        '\u{0e00}'..='\u{0e7f}' => "th",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_detection() {
+        assert_eq!(normalize_language_tag("ja-JP").as_deref(), Some("ja"));
+        assert_eq!(normalize_language_tag(" en_US ").as_deref(), Some("en"));
+        assert_eq!(normalize_language_tag("x"), None);
+
+        assert_eq!(
+            detect_script_language("非同期タスクを生成するには tokio::spawn を使います"),
+            Some("ja")
+        );
+        assert_eq!(detect_script_language("使用异步运行时"), Some("zh"));
+        assert_eq!(
+            detect_script_language("비동기 작업을 생성합니다"),
+            Some("ko")
+        );
+        assert_eq!(detect_script_language("Spawning async tasks"), None);
+
+        // A few foreign words don't make a chunk foreign
+        assert_eq!(
+            detect_script_language("Use tokio::spawn to start a background task (タスク)"),
+            None
+        );
+    }
+}
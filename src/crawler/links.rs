@@ -0,0 +1,145 @@
+//! Hyperlinks in extracted content.
+//!
+//! html2text renders links as `[text][1]` with a list of `[1]: href`
+//! footnotes at the end of the page, which the markdown cleanup breaks apart.
+//! Links are rewritten inline as `[text](url)` with absolute URLs, so every
+//! chunk carries the targets of the links it mentions.
+
+use regex::Regex;
+use std::collections::HashMap;
+use url::Url;
+
+/// Rewrite html2text's reference links as inline links resolved against `base`
+///
+/// Links that don't resolve to an http(s) URL (mailto:, javascript:) are
+/// reduced to their text.
+pub fn inline_links(text: &str, base: &Url) -> String {
+    let mut lines: Vec<&str> = text.lines().collect();
+
+    // Footnotes trail the content
+    let mut targets: HashMap<usize, Option<String>> = HashMap::new();
+    while let Some(line) = lines.last() {
+        if let Some((number, href)) = parse_footnote(line) {
+            targets.insert(number, resolve(base, href));
+        } else if !line.trim().is_empty() {
+            break;
+        }
+        lines.pop();
+    }
+    if targets.is_empty() {
+        return text.to_string();
+    }
+
+    let body = lines.join("\n");
+    let mut out = String::with_capacity(body.len());
+    let mut open = Vec::new();
+    let mut rest = body.as_str();
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '[' => {
+                open.push(out.len());
+                out.push(c);
+            }
+            ']' => {
+                let start = open.pop();
+                let reference = parse_reference(rest)
+                    .and_then(|(number, tail)| Some((targets.get(&number)?, tail)));
+                match reference {
+                    Some((Some(url), tail)) => {
+                        out.push_str("](");
+                        out.push_str(url);
+                        out.push(')');
+                        rest = tail;
+                    }
+                    Some((None, tail)) => {
+                        if let Some(start) = start {
+                            out.remove(start);
+                        }
+                        rest = tail;
+                    }
+                    None => out.push(c),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Targets of the inline http(s) links in markdown, in order of appearance
+pub fn markdown_links(markdown: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    for (index, _) in markdown.match_indices("](") {
+        let target = &markdown[index + 2..];
+        let Some(end) = target.find([')', ' ', '\n']) else {
+            continue;
+        };
+        let url = &target[..end];
+        let is_http = url.starts_with("http://") || url.starts_with("https://");
+        if is_http && !links.iter().any(|l| l == url) {
+            links.push(url.to_string());
+        }
+    }
+    links
+}
+
+/// Replace inline http(s) links with their text
+pub fn strip_links(markdown: &str) -> String {
+    let link_re = Regex::new(r"\[([^\]]*)\]\(https?://[^)\s]*\)").unwrap();
+    link_re.replace_all(markdown, "$1").into_owned()
+}
+
+/// Parse a `[1]: href` footnote line
+fn parse_footnote(line: &str) -> Option<(usize, &str)> {
+    let (number, tail) = parse_reference(line)?;
+    Some((number, tail.strip_prefix(": ")?.trim()))
+}
+
+/// Parse a leading `[1]` reference, returning its number and the rest
+fn parse_reference(text: &str) -> Option<(usize, &str)> {
+    let inner = text.strip_prefix('[')?;
+    let end = inner.find(']')?;
+    let number = inner[..end].parse().ok()?;
+    Some((number, &inner[end + 1..]))
+}
+
+fn resolve(base: &Url, href: &str) -> Option<String> {
+    let url = base.join(href).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_links() {
+        let base = Url::parse("https://docs.example.com/guide/intro.html").unwrap();
+        let text = "See the [tutorial][1] or [write to\nus][2]. Jump to [arrays][3].\n\n\
+                    [1]: ../tutorial/\n[2]: mailto:team@example.com\n[3]: #arrays\n";
+
+        let inlined = inline_links(text, &base);
+        assert_eq!(
+            inlined,
+            "See the [tutorial](https://docs.example.com/tutorial/) or write to\nus. \
+             Jump to [arrays](https://docs.example.com/guide/intro.html#arrays)."
+        );
+        assert_eq!(
+            markdown_links(&inlined),
+            [
+                "https://docs.example.com/tutorial/",
+                "https://docs.example.com/guide/intro.html#arrays"
+            ]
+        );
+
+        assert_eq!(
+            strip_links(&inlined),
+            "See the tutorial or write to\nus. Jump to arrays."
+        );
+
+        // Text without footnotes is left alone
+        assert_eq!(inline_links("items[0][1]", &base), "items[0][1]");
+    }
+}
@@ -0,0 +1,475 @@
+//! Command-line tool documentation.
+//!
+//! Many tools have no web documentation, only a man page and `--help`
+//! output. Both are converted to Markdown (man pages from roff), chunked like
+//! crawled pages and indexed as Reference documents under `man:tool(1)` and
+//! `help:tool` URLs, so questions about a tool's flags can be answered from
+//! the local machine's copy.
+
+use crate::crawler::chunker::ChunkIds;
+use crate::crawler::extractor::ContentExtractor;
+use crate::crawler::TextChunker;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{ContentType, Document, DocumentMetadata};
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+/// Markdown documentation of a tool, ready to chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliPage {
+    pub url: String,
+    pub title: String,
+    pub markdown: String,
+    /// "man" or "help"
+    pub kind: &'static str,
+}
+
+impl CliPage {
+    /// The tool's man page, if `man` can find one
+    pub fn man_page(tool: &str) -> Result<Option<Self>> {
+        let output = match Command::new("man").args(["-w", tool]).output() {
+            Ok(output) if output.status.success() => output,
+            Ok(_) => return Ok(None),
+            Err(e) => {
+                tracing::debug!("Can't run man: {}", e);
+                return Ok(None);
+            }
+        };
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let Some(path) = path.lines().next().filter(|path| !path.is_empty()) else {
+            return Ok(None);
+        };
+        let roff = read_man_file(Path::new(path))?;
+        Ok(Some(Self::from_roff(tool, &roff)))
+    }
+
+    /// Convert a man page's roff source
+    pub fn from_roff(tool: &str, roff: &str) -> Self {
+        let (markdown, section) = roff_to_markdown(roff);
+        let section = section.unwrap_or_else(|| "1".to_string());
+        Self {
+            url: format!("man:{}({})", tool, section),
+            title: format!("{}({})", tool, section),
+            markdown,
+            kind: "man",
+        }
+    }
+
+    /// Run `tool --help` and capture what it prints
+    pub fn capture_help(tool: &str) -> Result<Self> {
+        let output = Command::new(tool)
+            .arg("--help")
+            .output()
+            .map_err(|e| CodeRagError::io(format!("Running {} --help", tool), e))?;
+        // Some tools print usage to stderr, or exit non-zero after printing it
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let help = if stdout.trim().is_empty() {
+            String::from_utf8_lossy(&output.stderr).to_string()
+        } else {
+            stdout.to_string()
+        };
+        if help.trim().is_empty() {
+            return Err(CodeRagError::Parse(format!(
+                "{} --help printed nothing",
+                tool
+            )));
+        }
+        Ok(Self::from_help(tool, &help))
+    }
+
+    /// Describe captured `--help` output
+    pub fn from_help(tool: &str, help: &str) -> Self {
+        Self {
+            url: format!("help:{}", tool),
+            title: format!("{} --help", tool),
+            markdown: help_to_markdown(help),
+            kind: "help",
+        }
+    }
+
+    /// Chunk the page into Reference documents
+    pub fn documents(
+        &self,
+        extractor: &ContentExtractor,
+        chunker: &mut TextChunker,
+    ) -> Vec<Document> {
+        let extracted = extractor.extract_markdown(&self.markdown, &self.url, &self.title);
+        let chunks = chunker.chunk_text(&extracted.markdown);
+        let chunk_count = chunks.len();
+        let mut ids = ChunkIds::new();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let heading_path = chunk
+                    .heading_context
+                    .as_deref()
+                    .and_then(|section| extracted.locate_section(section))
+                    .map(|(_, path)| path)
+                    .unwrap_or_default();
+                Document {
+                    id: ids.next_id(&self.url, None, &chunk.content),
+                    content: chunk.content,
+                    url: self.url.clone(),
+                    title: Some(self.title.clone()),
+                    section: chunk.heading_context,
+                    metadata: DocumentMetadata {
+                        content_type: ContentType::Reference,
+                        language: None,
+                        lang: chunk.lang,
+                        last_updated: Some(std::time::SystemTime::now()),
+                        tags: vec![
+                            "cli".to_string(),
+                            self.kind.to_string(),
+                            format!("chunk-{}-of-{}", i + 1, chunk_count),
+                        ],
+                        anchor: None,
+                        heading_path,
+                        boost: None,
+                        links: Vec::new(),
+                        aliases: Vec::new(),
+                        simhash: None,
+                        version: None,
+                        release_date: None,
+                        votes: None,
+                        images: Vec::new(),
+                        canonical: None,
+                        provenance: None,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Read a man page source, decompressing `.gz` files
+fn read_man_file(path: &Path) -> Result<String> {
+    let context = || format!("Reading {}", path.display());
+    let bytes = std::fs::read(path).map_err(|e| CodeRagError::io(context(), e))?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut roff = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut roff)
+            .map_err(|e| CodeRagError::io(context(), e))?;
+        Ok(roff)
+    } else {
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+/// Convert man(7) roff to Markdown, returning it with the manual section
+///
+/// Covers the macros man pages are written with: sections become headings,
+/// tagged paragraphs become a bold tag followed by its description, and
+/// no-fill blocks become code blocks. Other requests are dropped.
+pub fn roff_to_markdown(roff: &str) -> (String, Option<String>) {
+    let mut out = String::new();
+    let mut section = None;
+    let mut in_code = false;
+    // A .TP tag is the line that follows it
+    let mut tag_next = false;
+
+    for line in roff.lines() {
+        if line.starts_with(".\\\"") || line.starts_with("'\\\"") || line == "." {
+            continue;
+        }
+        if in_code && !line.starts_with(".fi") && !line.starts_with(".EE") {
+            out.push_str(&unescape(line));
+            out.push('\n');
+            continue;
+        }
+
+        let Some(request) = line.strip_prefix('.').or_else(|| line.strip_prefix('\'')) else {
+            let text = unescape(line);
+            if tag_next {
+                out.push_str(&format!("\n**{}**\n", text.trim()));
+                tag_next = false;
+            } else {
+                out.push_str(&text);
+                out.push('\n');
+            }
+            continue;
+        };
+        let (name, args) = request.split_once([' ', '\t']).unwrap_or((request, ""));
+        let args = split_args(args);
+        let text = match name {
+            "TH" | "Dt" => {
+                section = args.get(1).cloned();
+                continue;
+            }
+            "SH" | "Sh" => {
+                out.push_str(&format!("\n## {}\n\n", title_case(&args.join(" "))));
+                continue;
+            }
+            "SS" | "Ss" => {
+                out.push_str(&format!("\n### {}\n\n", args.join(" ")));
+                continue;
+            }
+            "PP" | "P" | "LP" | "Pp" | "sp" | "br" => {
+                out.push('\n');
+                continue;
+            }
+            "TP" | "TQ" => {
+                tag_next = true;
+                continue;
+            }
+            "IP" => {
+                if let Some(tag) = args.first().filter(|tag| !tag.is_empty()) {
+                    out.push_str(&format!("\n**{}**\n", unescape(tag)));
+                } else {
+                    out.push('\n');
+                }
+                continue;
+            }
+            "nf" | "EX" => {
+                out.push_str("\n```\n");
+                in_code = true;
+                continue;
+            }
+            "fi" | "EE" => {
+                if in_code {
+                    out.push_str("```\n\n");
+                    in_code = false;
+                }
+                continue;
+            }
+            // Font macros: arguments alternate fonts and run together
+            "B" | "I" | "SM" | "SB" => args.join(" "),
+            "BR" | "BI" | "IR" | "IB" | "RB" | "RI" => args.concat(),
+            _ => continue,
+        };
+        let text = unescape(&text);
+        if tag_next {
+            out.push_str(&format!("\n**{}**\n", text.trim()));
+            tag_next = false;
+        } else {
+            out.push_str(&text);
+            out.push('\n');
+        }
+    }
+    if in_code {
+        out.push_str("```\n");
+    }
+
+    (collapse_blank_lines(&out), section)
+}
+
+/// Turn `--help` output into Markdown, with its sections as headings
+///
+/// Unindented lines ending in a colon ("Options:") start a section; the rest
+/// is kept verbatim so option columns stay aligned.
+pub fn help_to_markdown(help: &str) -> String {
+    let mut out = String::new();
+    let mut in_code = false;
+    for line in help.lines() {
+        let is_heading = !line.starts_with([' ', '\t'])
+            && line.trim_end().ends_with(':')
+            && line.len() < 60
+            && !line.trim_start().starts_with('-');
+        if is_heading {
+            if in_code {
+                out.truncate(out.trim_end().len());
+                out.push_str("\n```\n");
+                in_code = false;
+            }
+            out.push_str(&format!(
+                "\n## {}\n\n",
+                line.trim_end().trim_end_matches(':')
+            ));
+            continue;
+        }
+        if !in_code && !line.trim().is_empty() {
+            out.push_str("```\n");
+            in_code = true;
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    if in_code {
+        out.push_str("```\n");
+    }
+    collapse_blank_lines(&out)
+}
+
+/// Split a request's arguments, honouring double quotes
+fn split_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = args.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Replace roff escapes with the characters they print
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Font changes: \fB, \fI, \fR, \fP, \f(CW, \f[CR]
+            Some('f') => match chars.next() {
+                Some('(') => {
+                    chars.next();
+                    chars.next();
+                }
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Some('(') => {
+                let name: String = chars.by_ref().take(2).collect();
+                out.push_str(special_char(&name));
+            }
+            Some('[') => {
+                let name: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                out.push_str(special_char(&name));
+            }
+            Some('-') => out.push('-'),
+            Some('e') | Some('\\') => out.push('\\'),
+            Some(' ') | Some('~') => out.push(' '),
+            Some('&') | Some('|') | Some('^') | Some(',') | Some('/') | Some('c') => {}
+            Some('"') => break,
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+fn special_char(name: &str) -> &'static str {
+    match name {
+        "em" => "—",
+        "en" => "–",
+        "bu" => "•",
+        "aq" => "'",
+        "dq" => "\"",
+        "lq" => "“",
+        "rq" => "”",
+        "oq" => "‘",
+        "cq" => "’",
+        "co" => "©",
+        "hy" | "mi" => "-",
+        "ti" => "~",
+        "ha" => "^",
+        "rs" => "\\",
+        _ => "",
+    }
+}
+
+/// "OPTIONS" → "Options", as section names are shouted in man pages
+fn title_case(heading: &str) -> String {
+    let heading = unescape(heading);
+    if heading.chars().any(|c| c.is_lowercase()) {
+        return heading;
+    }
+    let lower = heading.to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => lower,
+    }
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = true;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+        } else {
+            out.push_str(line);
+            out.push('\n');
+            blank = false;
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roff_to_markdown() {
+        let roff = r#".\" Generated by hand
+.TH GREP 1 "2023-01-01" "GNU grep 3.11"
+.SH NAME
+grep \- print lines that match patterns
+.SH SYNOPSIS
+.B grep
+[\fIOPTION\fR...] \fIPATTERNS\fR [\fIFILE\fR...]
+.SH OPTIONS
+.SS "Matching Control"
+.TP
+.BR \-i ", " \-\^\-ignore\-case
+Ignore case distinctions in patterns.
+.SH EXAMPLES
+.nf
+grep \-i hello file.txt
+.fi
+"#;
+        let (markdown, section) = roff_to_markdown(roff);
+        assert_eq!(section.as_deref(), Some("1"));
+        assert_eq!(
+            markdown,
+            "## Name\n\ngrep - print lines that match patterns\n\n## Synopsis\n\n\
+             grep\n[OPTION...] PATTERNS [FILE...]\n\n## Options\n\n### Matching Control\n\n\
+             **-i, --ignore-case**\nIgnore case distinctions in patterns.\n\n## Examples\n\n\
+             ```\ngrep -i hello file.txt\n```"
+        );
+    }
+
+    #[test]
+    fn test_help_to_markdown() {
+        let help = "Usage: tool [OPTIONS] <FILE>\n\nOptions:\n  -v, --verbose  Print more\n  -h, --help     Print help\n";
+        assert_eq!(
+            help_to_markdown(help),
+            "```\nUsage: tool [OPTIONS] <FILE>\n```\n\n## Options\n\n```\n  -v, --verbose  Print more\n  -h, --help     Print help\n```"
+        );
+    }
+
+    #[test]
+    fn test_documents() {
+        let help = "Options:\n  --color <WHEN>    Colorize output: always, never or auto\n  \
+                    --config <FILE>   Read settings from FILE instead of ~/.toolrc\n";
+        let page = CliPage::from_help("tool", help);
+        let documents = page.documents(&ContentExtractor::new().unwrap(), &mut TextChunker::new());
+        assert!(!documents.is_empty());
+        assert_eq!(documents[0].url, "help:tool");
+        assert_eq!(documents[0].metadata.content_type, ContentType::Reference);
+        assert!(documents[0].content.contains("--color"));
+        assert_eq!(documents[0].metadata.tags[..2], ["cli", "help"]);
+    }
+}
@@ -1,9 +1,30 @@
+pub mod auth;
+pub mod blob_store;
+pub mod changelog;
 pub mod chunker;
+pub mod domains;
 pub mod engine;
 pub mod extractor;
+pub mod fetch;
+#[cfg(feature = "test-fixtures")]
+pub mod fixtures;
+pub mod inventory;
+pub mod language;
+pub mod links;
+pub mod manpage;
+pub mod page_store;
+pub mod pagination;
+pub mod qa;
+pub mod readability;
+pub mod repository;
+pub mod retry;
+pub mod simhash;
+pub mod site_index;
+pub mod tables;
 pub mod types;
+pub mod verify;
 
 pub use chunker::TextChunker;
-pub use engine::Crawler;
-pub use extractor::ContentExtractor;
+pub use engine::{Crawler, PagePreview};
+pub use extractor::{ContentExtractor, EXTRACTOR_VERSION};
 pub use types::*;
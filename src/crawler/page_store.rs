@@ -0,0 +1,183 @@
+//! Extracted pages kept beside the database.
+//!
+//! With `database.store_pages` on, every crawled page's extracted Markdown,
+//! headings and metadata are saved to `<name>.pages/` next to the database
+//! (`cache/pages/` in the global data directory), gzip-compressed unless `database.compress_pages` is off. `coderag
+//! reprocess` re-chunks and re-embeds from these copies, so a chunker change
+//! takes effect without fetching the site again.
+
+use crate::crawler::extractor::ExtractedContent;
+use crate::crawler::simhash::fnv1a;
+use crate::data_dir::DataDir;
+use crate::error::{CodeRagError, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A page as the extractor left it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPage {
+    pub url: String,
+    /// When the page was fetched
+    pub fetched_at: SystemTime,
+    /// Hash of the fetched body in the blob store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    pub extracted: ExtractedContent,
+}
+
+/// The stored pages of one database file
+#[derive(Debug, Clone)]
+pub struct PageStore {
+    dir: PathBuf,
+    compress: bool,
+}
+
+impl PageStore {
+    /// Page store kept alongside the given database file
+    pub fn for_database(db_path: &Path, compress: bool) -> Self {
+        let stem = db_path
+            .file_stem()
+            .map_or_else(|| "vectors".into(), |stem| stem.to_string_lossy());
+        let dir = DataDir::containing(db_path).map_or_else(
+            || db_path.with_file_name(format!("{}.pages", stem)),
+            |data_dir| data_dir.cache_dir().join("pages"),
+        );
+        Self { dir, compress }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Save a page, replacing an earlier copy
+    pub fn save(&self, page: &StoredPage) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", self.dir), e))?;
+        let json = serde_json::to_vec(page)?;
+        let (path, other) = (
+            self.path(&page.url, self.compress),
+            self.path(&page.url, !self.compress),
+        );
+
+        let bytes = if self.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(&json)
+                .and_then(|()| encoder.finish())
+                .map_err(|e| CodeRagError::io(format!("Failed to compress {}", page.url), e))?
+        } else {
+            json
+        };
+        std::fs::write(&path, bytes)
+            .map_err(|e| CodeRagError::io(format!("Failed to write {:?}", path), e))?;
+        // A copy saved before `compress_pages` changed
+        if other.exists() {
+            let _ = std::fs::remove_file(other);
+        }
+        Ok(())
+    }
+
+    /// Load the stored copy of a page, if there is one
+    pub fn load(&self, url: &str) -> Result<Option<StoredPage>> {
+        for compressed in [true, false] {
+            let path = self.path(url, compressed);
+            if !path.exists() {
+                continue;
+            }
+            let bytes = std::fs::read(&path)
+                .map_err(|e| CodeRagError::io(format!("Failed to read {:?}", path), e))?;
+            let json = if compressed {
+                let mut json = Vec::new();
+                GzDecoder::new(bytes.as_slice())
+                    .read_to_end(&mut json)
+                    .map_err(|e| CodeRagError::io(format!("Failed to decompress {:?}", path), e))?;
+                json
+            } else {
+                bytes
+            };
+            let page: StoredPage = serde_json::from_slice(&json)?;
+            // File names are hashes; don't hand back a colliding page
+            return Ok((page.url == url).then_some(page));
+        }
+        Ok(None)
+    }
+
+    /// Delete the pages whose URL isn't in `urls`, returning how many were removed
+    pub fn retain<'a>(&self, urls: impl IntoIterator<Item = &'a str>) -> Result<usize> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Ok(0);
+        };
+        let keep: HashSet<String> = urls.into_iter().map(file_stem).collect();
+
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let stem = name.to_string_lossy();
+            let stem = stem.split('.').next().unwrap_or_default();
+            if !keep.contains(stem) {
+                std::fs::remove_file(entry.path()).map_err(|e| {
+                    CodeRagError::io(format!("Failed to remove {:?}", entry.path()), e)
+                })?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn path(&self, url: &str, compressed: bool) -> PathBuf {
+        let extension = if compressed { "json.gz" } else { "json" };
+        self.dir.join(format!("{}.{}", file_stem(url), extension))
+    }
+}
+
+fn file_stem(url: &str) -> String {
+    format!("{:016x}", fnv1a(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::ContentExtractor;
+    use tempfile::TempDir;
+
+    fn page(url: &str) -> StoredPage {
+        let extractor = ContentExtractor::new().unwrap();
+        StoredPage {
+            url: url.to_string(),
+            fetched_at: SystemTime::UNIX_EPOCH,
+            snapshot: None,
+            extracted: extractor.extract_markdown("# Tokio\n\nSpawn tasks.", url, "tokio"),
+        }
+    }
+
+    #[test]
+    fn test_save_load_retain() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("vectors.json");
+        let store = PageStore::for_database(&db_path, true);
+        assert_eq!(store.dir(), dir.path().join("vectors.pages"));
+
+        let (spawn, select) = ("https://tokio.rs/spawn", "https://tokio.rs/select");
+        store.save(&page(spawn)).unwrap();
+        store.save(&page(select)).unwrap();
+        let loaded = store.load(spawn).unwrap().unwrap();
+        assert_eq!(loaded.extracted.title, "Tokio");
+        assert!(store.load("https://tokio.rs/missing").unwrap().is_none());
+
+        // Switching compression off replaces the compressed copy
+        let plain = PageStore::for_database(&db_path, false);
+        plain.save(&page(spawn)).unwrap();
+        assert!(plain.load(spawn).unwrap().is_some());
+        assert_eq!(std::fs::read_dir(store.dir()).unwrap().count(), 2);
+
+        assert_eq!(store.retain([spawn]).unwrap(), 1);
+        assert!(store.load(select).unwrap().is_none());
+        assert!(store.load(spawn).unwrap().is_some());
+    }
+}
@@ -0,0 +1,174 @@
+//! Articles split across several pages.
+//!
+//! Long guides are often paginated as `?page=2`, `/page/2` or `guide-2.html`.
+//! Their pages announce the neighbouring ones with `rel="next"`/`rel="prev"`
+//! links or "Next"/"Previous" anchors. Crawls follow those in every mode, but
+//! only to pages of the same article (the same path once a trailing page
+//! number is removed), so the "next chapter" links of a book don't pull in the
+//! whole book. [`ArticlePages`] caps how many pages one article contributes.
+
+use regex::Regex;
+use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use url::Url;
+
+/// Links to the next and previous pages of the article at `url`
+pub fn article_page_links(html: &str, url: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(url) else {
+        return Vec::new();
+    };
+    let html = Html::parse_document(html);
+    let selector = Selector::parse("link[href], a[href]").unwrap();
+
+    let mut links: Vec<String> = Vec::new();
+    for element in html.select(&selector) {
+        let value = element.value();
+        let rel = value.attr("rel").unwrap_or_default().to_lowercase();
+        let by_rel = rel
+            .split_whitespace()
+            .any(|r| matches!(r, "next" | "prev" | "previous"));
+        let by_text = value.name() == "a" && {
+            let text: String = element
+                .text()
+                .collect::<String>()
+                .to_lowercase()
+                .chars()
+                .filter(|c| c.is_alphabetic() || c.is_whitespace())
+                .collect();
+            matches!(
+                text.split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .as_str(),
+                "next" | "next page" | "previous" | "previous page" | "prev"
+            )
+        };
+        if !by_rel && !by_text {
+            continue;
+        }
+        let Some(mut link) = value.attr("href").and_then(|href| base.join(href).ok()) else {
+            continue;
+        };
+        link.set_fragment(None);
+        let link = String::from(link);
+        if same_article(&base, &link) && !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// Whether `link` is another page of the article at `current`
+fn same_article(current: &Url, link: &str) -> bool {
+    let Ok(link) = Url::parse(link) else {
+        return false;
+    };
+    let mut page = current.clone();
+    page.set_fragment(None);
+    page != link
+        && page.scheme() == link.scheme()
+        && page.host_str() == link.host_str()
+        && article_path(page.path()) == article_path(link.path())
+}
+
+/// A page's path without its trailing page number, e.g. "/guide.html" for "/guide-2.html"
+fn article_path(path: &str) -> String {
+    static PAGE_NUMBER: OnceLock<Regex> = OnceLock::new();
+    let pattern = PAGE_NUMBER
+        .get_or_init(|| Regex::new(r"(?i)^(.*?)(?:[/_-](?:page[/_-]?)?\d+)?(\.[a-z]+)?$").unwrap());
+    let path = path.trim_end_matches('/');
+    match pattern.captures(path) {
+        Some(captures) => format!(
+            "{}{}",
+            &captures[1],
+            captures.get(2).map_or("", |ext| ext.as_str())
+        ),
+        None => path.to_string(),
+    }
+}
+
+/// Pages of each article reached so far, to cap how many one article adds
+#[derive(Debug, Default)]
+pub struct ArticlePages {
+    /// First page reached of the article each page belongs to
+    first_page: HashMap<String, String>,
+    /// Pages reached per article, by first page
+    pages: HashMap<String, usize>,
+}
+
+impl ArticlePages {
+    /// Record `link`, found on `from`, as a page of the same article
+    ///
+    /// Returns false, recording nothing, once the article has `limit` pages
+    /// or when `link` was already reached.
+    pub fn admit(&mut self, from: &str, link: &str, limit: usize) -> bool {
+        if self.first_page.contains_key(link) || self.pages.contains_key(link) {
+            return false;
+        }
+        let first = self
+            .first_page
+            .get(from)
+            .cloned()
+            .unwrap_or_else(|| from.to_string());
+        let pages = self.pages.entry(first.clone()).or_insert(1);
+        if *pages >= limit {
+            return false;
+        }
+        *pages += 1;
+        self.first_page.insert(link.to_string(), first);
+        true
+    }
+
+    /// Whether `url` was reached through another page of its article
+    pub fn contains(&self, url: &str) -> bool {
+        self.first_page.contains_key(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_article_page_links() {
+        let html = r#"<html><head>
+            <link rel="next" href="/guide/async?page=3">
+            <link rel="prev" href="/guide/async?page=1">
+        </head><body>
+            <a href="/guide/async?page=3#top">Next »</a>
+            <a rel="next" href="/guide/streams">Streams</a>
+            <a href="/guide/async/page/4">4</a>
+        </body></html>"#;
+        let links = article_page_links(html, "https://example.com/guide/async?page=2");
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/guide/async?page=3",
+                "https://example.com/guide/async?page=1",
+            ]
+        );
+
+        // Numbered pages, but not the next chapter of a book
+        let html = r#"<a href="guide-3.html">Next</a>
+            <a rel="next" href="ch02-00.html">Chapter 2</a>"#;
+        assert_eq!(
+            article_page_links(html, "https://example.com/book/guide-2.html"),
+            vec!["https://example.com/book/guide-3.html"]
+        );
+        assert!(article_page_links(html, "https://example.com/book/ch01-00.html").is_empty());
+        assert_eq!(article_path("/blog/tokio/page/2/"), "/blog/tokio");
+    }
+
+    #[test]
+    fn test_article_pages_limit() {
+        let mut pages = ArticlePages::default();
+        assert!(pages.admit("https://a.dev/g", "https://a.dev/g?page=2", 3));
+        assert!(pages.admit("https://a.dev/g?page=2", "https://a.dev/g?page=3", 3));
+        assert!(!pages.admit("https://a.dev/g?page=3", "https://a.dev/g?page=4", 3));
+        assert!(!pages.admit("https://a.dev/g?page=3", "https://a.dev/g?page=2", 3));
+        assert!(!pages.admit("https://a.dev/g?page=2", "https://a.dev/g", 5));
+        assert!(pages.contains("https://a.dev/g?page=3"));
+        assert!(!pages.contains("https://a.dev/g"));
+    }
+}
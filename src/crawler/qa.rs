@@ -0,0 +1,485 @@
+//! Q&A ingestion for Stack Overflow and other Stack Exchange sites.
+//!
+//! A question page is a poor fit for page chunking: the answers that matter
+//! are mixed with comments, sidebars and low-voted answers, and a chunk of an
+//! answer loses the question it answers. Questions are read through the
+//! Stack Exchange API instead (or from the question page's HTML when the API
+//! is unavailable), and each accepted or well-voted answer becomes one
+//! document that carries the question as context along with its vote count.
+
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::fetch::HttpClient;
+use crate::crawler::language::detect_script_language;
+use crate::crawler::links::inline_links;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{ContentType, Document, DocumentMetadata};
+use scraper::{ElementRef, Html, Selector};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use url::Url;
+
+const API_BASE: &str = "https://api.stackexchange.com/2.3";
+
+/// Answers kept per question
+const MAX_ANSWERS: usize = 3;
+
+/// Votes an answer needs to be kept when it isn't accepted
+const MIN_ANSWER_SCORE: i64 = 1;
+
+/// Characters of the question body stored with each answer
+const QUESTION_EXCERPT_CHARS: usize = 600;
+
+/// Items per API page, the API's maximum
+const API_PAGE_SIZE: usize = 100;
+
+/// Pages of answers read at most for one batch of questions
+const MAX_ANSWER_PAGES: usize = 10;
+
+/// What a Stack Exchange URL points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QaTarget {
+    /// A single question by ID
+    Question(u64),
+    /// The highest-voted questions with all of these tags
+    Tagged(Vec<String>),
+}
+
+/// A question or tag listing on a Stack Exchange site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QaSource {
+    /// API site parameter, e.g. "stackoverflow" or "unix"
+    pub site: String,
+    /// Scheme and host of the site
+    origin: String,
+    pub target: QaTarget,
+}
+
+/// A question with its answers, bodies converted to Markdown
+#[derive(Debug, Clone)]
+pub struct Thread {
+    /// Canonical question URL
+    pub url: String,
+    pub title: String,
+    pub question: String,
+    pub tags: Vec<String>,
+    pub answers: Vec<Answer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub id: u64,
+    pub score: i64,
+    pub accepted: bool,
+    pub body: String,
+    pub has_code: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiPage<T> {
+    items: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiQuestion {
+    question_id: u64,
+    title: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiAnswer {
+    answer_id: u64,
+    question_id: u64,
+    score: i64,
+    #[serde(default)]
+    is_accepted: bool,
+    #[serde(default)]
+    body: String,
+}
+
+impl QaSource {
+    /// Recognize a question or tag URL on a Stack Exchange site
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        let site = match host {
+            "stackoverflow.com" | "superuser.com" | "serverfault.com" | "askubuntu.com" => {
+                host.trim_end_matches(".com")
+            }
+            "mathoverflow.net" => "mathoverflow",
+            _ => host.strip_suffix(".stackexchange.com")?,
+        };
+
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        let target = match segments.as_slice() {
+            ["questions", "tagged", tags, ..] => {
+                let tags: Vec<String> = tags
+                    .split(['+', ' '])
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if tags.is_empty() {
+                    return None;
+                }
+                QaTarget::Tagged(tags)
+            }
+            ["questions" | "q", id, ..] => QaTarget::Question(id.parse().ok()?),
+            _ => return None,
+        };
+
+        Some(Self {
+            site: site.to_string(),
+            origin: url.origin().ascii_serialization(),
+            target,
+        })
+    }
+
+    /// Fetch the questions and answers through the Stack Exchange API
+    ///
+    /// A tag listing returns up to `limit` of its highest-voted questions.
+    pub async fn fetch_threads(
+        &self,
+        client: &HttpClient,
+        auth: &CrawlAuth,
+        limit: usize,
+    ) -> Result<Vec<Thread>> {
+        let questions_url = match &self.target {
+            QaTarget::Question(id) => format!(
+                "{}/questions/{}?site={}&filter=withbody",
+                API_BASE, id, self.site
+            ),
+            QaTarget::Tagged(tags) => format!(
+                "{}/questions?tagged={}&site={}&sort=votes&order=desc&pagesize={}&filter=withbody",
+                API_BASE,
+                encode(&tags.join(";")),
+                self.site,
+                limit.clamp(1, API_PAGE_SIZE)
+            ),
+        };
+        let questions: ApiPage<ApiQuestion> = get_json(client, auth, &questions_url).await?;
+        if questions.items.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids = questions
+            .items
+            .iter()
+            .map(|question| question.question_id.to_string())
+            .collect::<Vec<_>>()
+            .join(";");
+        let mut answers = Vec::new();
+        for page in 1..=MAX_ANSWER_PAGES {
+            let answers_url = format!(
+                "{}/questions/{}/answers?site={}&sort=votes&order=desc&pagesize={}&page={}&filter=withbody",
+                API_BASE, ids, self.site, API_PAGE_SIZE, page
+            );
+            let batch: ApiPage<ApiAnswer> = get_json(client, auth, &answers_url).await?;
+            answers.extend(batch.items);
+            if !batch.has_more {
+                break;
+            }
+        }
+
+        Ok(questions
+            .items
+            .into_iter()
+            .map(|question| {
+                let url = self.question_url(question.question_id);
+                let base = Url::parse(&url).ok();
+                let answers = answers
+                    .iter()
+                    .filter(|answer| answer.question_id == question.question_id)
+                    .map(|answer| Answer {
+                        id: answer.answer_id,
+                        score: answer.score,
+                        accepted: answer.is_accepted,
+                        body: post_markdown(&answer.body, base.as_ref()),
+                        has_code: answer.body.contains("<pre"),
+                    })
+                    .collect();
+                Thread {
+                    title: fragment_text(&question.title),
+                    question: post_markdown(&question.body, base.as_ref()),
+                    tags: question.tags,
+                    answers,
+                    url,
+                }
+            })
+            .collect())
+    }
+
+    /// Canonical URL of a question on this site
+    pub fn question_url(&self, id: u64) -> String {
+        format!("{}/questions/{}", self.origin, id)
+    }
+}
+
+/// Read a question and its answers from the question page's HTML
+pub fn parse_thread_html(html: &str, url: &str) -> Option<Thread> {
+    let document = Html::parse_document(html);
+    let title_selector = Selector::parse("#question-header h1, h1[itemprop=name]").unwrap();
+    let question_selector = Selector::parse("#question, .question").unwrap();
+    let answer_selector = Selector::parse(".answer[data-answerid]").unwrap();
+    let tag_selector = Selector::parse(".post-tag").unwrap();
+
+    let base = Url::parse(url).ok();
+    let title = document
+        .select(&title_selector)
+        .next()
+        .map(|title| element_text(&title))?;
+    let question = document.select(&question_selector).next()?;
+    let tags = question
+        .select(&tag_selector)
+        .map(|tag| element_text(&tag))
+        .collect();
+
+    let answers = document
+        .select(&answer_selector)
+        .filter_map(|answer| {
+            let id = answer.value().attr("data-answerid")?.parse().ok()?;
+            let score = answer.value().attr("data-score")?.parse().ok()?;
+            let body = post_body_html(&answer)?;
+            Some(Answer {
+                id,
+                score,
+                accepted: answer.value().classes().any(|c| c == "accepted-answer"),
+                has_code: body.contains("<pre"),
+                body: post_markdown(&body, base.as_ref()),
+            })
+        })
+        .collect();
+
+    Some(Thread {
+        url: url.to_string(),
+        title,
+        question: post_markdown(&post_body_html(&question)?, base.as_ref()),
+        tags,
+        answers,
+    })
+}
+
+impl Thread {
+    /// The answers worth indexing: the accepted one, then the best-voted
+    pub fn best_answers(&self) -> Vec<&Answer> {
+        let mut answers: Vec<&Answer> = self
+            .answers
+            .iter()
+            .filter(|answer| answer.accepted || answer.score >= MIN_ANSWER_SCORE)
+            .collect();
+        answers.sort_by_key(|answer| (!answer.accepted, std::cmp::Reverse(answer.score)));
+        answers.truncate(MAX_ANSWERS);
+        answers
+    }
+
+    /// One document per indexed answer, each prefixed with the question
+    pub fn documents(&self) -> Vec<Document> {
+        let excerpt = excerpt(&self.question, QUESTION_EXCERPT_CHARS);
+
+        self.best_answers()
+            .into_iter()
+            .map(|answer| {
+                let status = if answer.accepted { ", accepted" } else { "" };
+                let content = format!(
+                    "Question: {}\n\n{}\n\nAnswer ({} votes{}):\n\n{}",
+                    self.title, excerpt, answer.score, status, answer.body
+                );
+
+                let mut tags = vec![
+                    "qa".to_string(),
+                    if answer.has_code {
+                        "has-code"
+                    } else {
+                        "no-code"
+                    }
+                    .to_string(),
+                ];
+                if answer.accepted {
+                    tags.push("accepted-answer".to_string());
+                }
+                tags.extend(self.tags.iter().cloned());
+
+                Document {
+                    id: format!("{}_answer_{}", self.url, answer.id),
+                    url: self.url.clone(),
+                    title: Some(self.title.clone()),
+                    section: Some(self.title.clone()),
+                    metadata: DocumentMetadata {
+                        content_type: if answer.has_code {
+                            ContentType::CodeExample
+                        } else {
+                            ContentType::Tutorial
+                        },
+                        language: None,
+                        lang: detect_script_language(&content).map(str::to_string),
+                        last_updated: Some(std::time::SystemTime::now()),
+                        tags,
+                        // Answers are linked by their ID on the question page
+                        anchor: Some(answer.id.to_string()),
+                        heading_path: Vec::new(),
+                        boost: None,
+                        links: crate::crawler::links::markdown_links(&answer.body),
+                        aliases: Vec::new(),
+                        simhash: None,
+                        version: None,
+                        release_date: None,
+                        votes: Some(answer.score),
+                        images: Vec::new(),
+                        canonical: None,
+                        provenance: None,
+                    },
+                    content,
+                }
+            })
+            .collect()
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &HttpClient,
+    auth: &CrawlAuth,
+    url: &str,
+) -> Result<T> {
+    let response = client.send(auth.apply(client.get(url), url)).await?;
+    if response.status() == 429 {
+        return Err(CodeRagError::RateLimited);
+    }
+    Ok(response.error_for_status()?.json().await?)
+}
+
+/// Convert a post body to Markdown with absolute links
+fn post_markdown(html: &str, base: Option<&Url>) -> String {
+    let markdown = html2text::from_read(html.as_bytes(), 80);
+    let markdown = match base {
+        Some(base) => inline_links(&markdown, base),
+        None => markdown,
+    };
+    markdown.trim().to_string()
+}
+
+/// Inner HTML of a post's body
+fn post_body_html(post: &ElementRef) -> Option<String> {
+    let body_selector = Selector::parse(".js-post-body, .post-text, [itemprop=text]").unwrap();
+    post.select(&body_selector)
+        .next()
+        .map(|body| body.inner_html())
+}
+
+fn element_text(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Text of an HTML fragment, e.g. an API title with escaped entities
+fn fragment_text(html: &str) -> String {
+    element_text(&Html::parse_fragment(html).root_element())
+}
+
+/// The start of `text`, cut at a word boundary
+fn excerpt(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = cut
+        .rsplit_once(char::is_whitespace)
+        .map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end())
+}
+
+fn encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        let question = QaSource::parse(
+            "https://stackoverflow.com/questions/28519997/what-are-rusts-exact-auto-dereferencing-rules",
+        )
+        .unwrap();
+        assert_eq!(question.site, "stackoverflow");
+        assert_eq!(question.target, QaTarget::Question(28519997));
+        assert_eq!(
+            question.question_url(28519997),
+            "https://stackoverflow.com/questions/28519997"
+        );
+
+        let tagged =
+            QaSource::parse("https://unix.stackexchange.com/questions/tagged/bash+awk").unwrap();
+        assert_eq!(tagged.site, "unix");
+        assert_eq!(
+            tagged.target,
+            QaTarget::Tagged(vec!["bash".to_string(), "awk".to_string()])
+        );
+
+        assert!(QaSource::parse("https://stackoverflow.com/users/1/jeff").is_none());
+        assert!(QaSource::parse("https://docs.rs/tokio").is_none());
+    }
+
+    #[test]
+    fn test_parse_thread_html() {
+        let html = r#"<html><body>
+            <div id="question-header"><h1><a href="/questions/1">How do I read a file   in Rust?</a></h1></div>
+            <div id="question" class="question">
+                <div class="s-prose js-post-body"><p>I want the contents as a <code>String</code>.</p></div>
+                <a class="post-tag">rust</a><a class="post-tag">file-io</a>
+            </div>
+            <div class="answer" data-answerid="11" data-score="3">
+                <div class="s-prose js-post-body"><p>Open it and read it yourself.</p></div>
+            </div>
+            <div class="answer accepted-answer" data-answerid="12" data-score="2">
+                <div class="s-prose js-post-body"><pre><code>let s = std::fs::read_to_string("a.txt")?;</code></pre></div>
+            </div>
+            <div class="answer" data-answerid="13" data-score="0">
+                <div class="s-prose js-post-body"><p>Me too.</p></div>
+            </div>
+        </body></html>"#;
+
+        let thread = parse_thread_html(html, "https://stackoverflow.com/questions/1").unwrap();
+        assert_eq!(thread.title, "How do I read a file in Rust?");
+        assert_eq!(thread.tags, vec!["rust", "file-io"]);
+        assert_eq!(thread.answers.len(), 3);
+
+        let ids: Vec<u64> = thread.best_answers().iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![12, 11]);
+
+        let documents = thread.documents();
+        assert_eq!(documents.len(), 2);
+        let accepted = &documents[0];
+        assert_eq!(
+            accepted.id,
+            "https://stackoverflow.com/questions/1_answer_12"
+        );
+        assert_eq!(accepted.metadata.content_type, ContentType::CodeExample);
+        assert_eq!(accepted.metadata.votes, Some(2));
+        assert_eq!(accepted.metadata.anchor.as_deref(), Some("12"));
+        assert!(accepted
+            .metadata
+            .tags
+            .contains(&"accepted-answer".to_string()));
+        assert!(accepted
+            .content
+            .starts_with("Question: How do I read a file in Rust?"));
+        assert!(accepted.content.contains("read_to_string"));
+        assert_eq!(documents[1].metadata.content_type, ContentType::Tutorial);
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("short", 10), "short");
+        assert_eq!(excerpt("one two three four", 10), "one two…");
+    }
+}
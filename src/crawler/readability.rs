@@ -0,0 +1,134 @@
+//! Readability-style main content detection.
+//!
+//! Sites that don't mark their content with `<main>`, `<article>` or a
+//! well-known class still put it in one container full of paragraphs.
+//! Each paragraph scores its parent and grandparent by length and commas;
+//! containers are weighted by tag and class names and penalized for link
+//! density, and the best scoring one is the content root.
+
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+
+/// Paragraphs shorter than this don't say anything about their container
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Lowest score a candidate needs to be trusted as the content root
+const MIN_CANDIDATE_SCORE: f32 = 20.0;
+
+/// Class and id fragments of content containers
+const POSITIVE_NAMES: &[&str] = &[
+    "article", "body", "content", "doc", "entry", "main", "markdown", "page", "post", "prose",
+    "text",
+];
+
+/// Class and id fragments of boilerplate containers
+const NEGATIVE_NAMES: &[&str] = &[
+    "ad-", "banner", "comment", "footer", "header", "menu", "nav", "related", "share", "sidebar",
+    "social", "widget",
+];
+
+/// Find the element most likely to hold the page's main content
+pub fn find_content_root(document: &Html) -> Option<ElementRef<'_>> {
+    let paragraph_selector = Selector::parse("p, pre, td, li, dd, blockquote").unwrap();
+    let mut scores: HashMap<_, f32> = HashMap::new();
+
+    for paragraph in document.select(&paragraph_selector) {
+        let text = paragraph.text().collect::<String>();
+        let length = text.trim().chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f32 + (length as f32 / 100.0).min(3.0);
+
+        // Parents get the full score, grandparents half
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            let entry = scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor));
+            *entry += score / (level + 1) as f32;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .filter(|(_, score)| *score >= MIN_CANDIDATE_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)
+}
+
+/// Starting score of a candidate from its tag and class names
+fn initial_score(element: ElementRef) -> f32 {
+    let tag_score = match element.value().name() {
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    let names = [element.value().attr("class"), element.value().attr("id")]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let mut name_score = 0.0;
+    if NEGATIVE_NAMES.iter().any(|name| names.contains(name)) {
+        name_score -= 25.0;
+    }
+    if POSITIVE_NAMES.iter().any(|name| names.contains(name)) {
+        name_score += 25.0;
+    }
+
+    tag_score + name_score
+}
+
+/// Share of an element's text that sits inside links
+fn link_density(element: ElementRef) -> f32 {
+    let text_length = element.text().map(str::len).sum::<usize>();
+    if text_length == 0 {
+        return 0.0;
+    }
+    let link_selector = Selector::parse("a").unwrap();
+    let link_length = element
+        .select(&link_selector)
+        .flat_map(|link| link.text())
+        .map(str::len)
+        .sum::<usize>();
+    link_length as f32 / text_length as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_content_root() {
+        let paragraph = "<p>The scheduler runs tasks on a pool of worker threads, \
+            stealing work from busy threads, so long-running tasks should yield.</p>";
+        let link = r##"<p><a href="#">A link to another page of the guide, here</a></p>"##;
+        let html = format!(
+            r#"<html><body>
+              <div id="wrapper">
+                <div class="links">{links}</div>
+                <div class="x-7f3a">{paragraphs}</div>
+              </div>
+            </body></html>"#,
+            links = link.repeat(6),
+            paragraphs = paragraph.repeat(4)
+        );
+
+        let document = Html::parse_document(&html);
+        let root = find_content_root(&document).unwrap();
+        assert_eq!(root.value().attr("class"), Some("x-7f3a"));
+
+        let sparse = Html::parse_document("<html><body><p>Hello</p></body></html>");
+        assert!(find_content_root(&sparse).is_none());
+    }
+}
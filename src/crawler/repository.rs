@@ -0,0 +1,444 @@
+//! Source adapters for documentation kept in code repositories.
+//!
+//! Crawling a repository's rendered HTML wastes most requests on navigation,
+//! file listings and issue links, and the extracted text carries the site
+//! chrome. For github.com and GitLab repositories the README and the
+//! Markdown files under `docs/` are listed through the API and fetched raw
+//! instead, along with GitHub wiki pages reachable from the wiki's Home page.
+//!
+//! API requests go through the crawler's per-domain auth, so a token for
+//! `api.github.com` (or the GitLab host) raises the rate limit and gives
+//! access to private repositories.
+
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::fetch::HttpClient;
+use crate::error::Result;
+use reqwest::header::ACCEPT;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use url::Url;
+
+/// Entries requested per page of a GitLab tree listing
+const GITLAB_PAGE_SIZE: usize = 100;
+
+/// Pages of a GitLab tree listing read at most
+const GITLAB_MAX_PAGES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+}
+
+/// A repository hosted on GitHub or GitLab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    pub forge: Forge,
+    /// Scheme and host, e.g. "https://gitlab.com"
+    origin: String,
+    /// "owner/repo", or the full group path on GitLab
+    pub path: String,
+}
+
+/// A Markdown file in a repository, indexed under its web URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoPage {
+    /// The page as shown on the forge, used for citations
+    pub url: String,
+    /// Where the raw Markdown is fetched from
+    pub raw_url: String,
+    /// Title used when the file has no top-level heading
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    default_branch: String,
+    #[serde(default)]
+    has_wiki: bool,
+}
+
+#[derive(Deserialize)]
+struct GitHubTree {
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+impl Repository {
+    /// Recognize a github.com or GitLab repository URL
+    ///
+    /// Any URL inside the repository works, e.g. a blob or wiki page.
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+        let (forge, path) = if host == "github.com" || host == "www.github.com" {
+            let [owner, repo, ..] = segments.as_slice() else {
+                return None;
+            };
+            (Forge::GitHub, vec![*owner, repo.trim_end_matches(".git")])
+        } else if host == "gitlab.com" || host.starts_with("gitlab.") {
+            // Everything before the "-" separator is the group and project path
+            let path: Vec<&str> = segments
+                .iter()
+                .take_while(|segment| **segment != "-")
+                .map(|segment| segment.trim_end_matches(".git"))
+                .collect();
+            if path.len() < 2 {
+                return None;
+            }
+            (Forge::GitLab, path)
+        } else {
+            return None;
+        };
+
+        let origin = match forge {
+            Forge::GitHub => "https://github.com".to_string(),
+            Forge::GitLab => url.origin().ascii_serialization(),
+        };
+        Some(Self {
+            forge,
+            origin,
+            path: path.join("/"),
+        })
+    }
+
+    /// List the repository's documentation pages, README first
+    ///
+    /// With `include_docs` unset only the README is returned.
+    pub async fn list_pages(
+        &self,
+        client: &HttpClient,
+        auth: &CrawlAuth,
+        include_docs: bool,
+    ) -> Result<Vec<RepoPage>> {
+        match self.forge {
+            Forge::GitHub => {
+                let api = format!("https://api.github.com/repos/{}", self.path);
+                let repo: GitHubRepo = get_json(client, auth, &api).await?;
+                let tree_url = format!(
+                    "{}/git/trees/{}?recursive=1",
+                    api,
+                    encode_segment(&repo.default_branch)
+                );
+                let tree: GitHubTree = get_json(client, auth, &tree_url).await?;
+                if tree.truncated {
+                    tracing::warn!(
+                        "Tree of {} is truncated, some docs may be missed",
+                        self.path
+                    );
+                }
+
+                let mut pages = self.doc_pages(&repo.default_branch, &tree.tree, include_docs);
+                if include_docs && repo.has_wiki {
+                    pages.extend(self.wiki_page("Home"));
+                }
+                Ok(pages)
+            }
+            Forge::GitLab => {
+                let api = format!(
+                    "{}/api/v4/projects/{}",
+                    self.origin,
+                    encode_segment(&self.path)
+                );
+                let project: GitLabProject = get_json(client, auth, &api).await?;
+                // Empty projects have no branch
+                let Some(branch) = project.default_branch else {
+                    return Ok(Vec::new());
+                };
+
+                let mut entries = Vec::new();
+                for page in 1..=GITLAB_MAX_PAGES {
+                    let tree_url = format!(
+                        "{}/repository/tree?recursive=true&per_page={}&page={}&ref={}",
+                        api,
+                        GITLAB_PAGE_SIZE,
+                        page,
+                        encode_segment(&branch)
+                    );
+                    let batch: Vec<TreeEntry> = get_json(client, auth, &tree_url).await?;
+                    let last = batch.len() < GITLAB_PAGE_SIZE;
+                    entries.extend(batch);
+                    if last {
+                        break;
+                    }
+                }
+                Ok(self.doc_pages(&branch, &entries, include_docs))
+            }
+        }
+    }
+
+    /// The README and, with `include_docs`, the Markdown files under `docs/`
+    fn doc_pages(&self, branch: &str, entries: &[TreeEntry], include_docs: bool) -> Vec<RepoPage> {
+        let files = entries.iter().filter(|entry| entry.kind == "blob");
+        let readme = files
+            .clone()
+            .find(|entry| is_readme(&entry.path))
+            .map(|entry| self.file_page(branch, &entry.path));
+        let docs = files
+            .filter(|entry| include_docs && is_docs_file(&entry.path))
+            .map(|entry| self.file_page(branch, &entry.path));
+        readme.into_iter().chain(docs).collect()
+    }
+
+    fn file_page(&self, branch: &str, path: &str) -> RepoPage {
+        let file: Vec<&str> = path.split('/').collect();
+        let (url, raw_url) = match self.forge {
+            Forge::GitHub => (
+                join_path(&self.origin, &[&self.path, "blob", branch], &file),
+                join_path(
+                    "https://raw.githubusercontent.com",
+                    &[&self.path, branch],
+                    &file,
+                ),
+            ),
+            Forge::GitLab => (
+                join_path(&self.origin, &[&self.path, "-", "blob", branch], &file),
+                join_path(&self.origin, &[&self.path, "-", "raw", branch], &file),
+            ),
+        };
+
+        let title = if is_readme(path) {
+            self.path.clone()
+        } else {
+            let name = file.last().copied().unwrap_or(path);
+            name.rsplit_once('.')
+                .map_or(name, |(stem, _)| stem)
+                .to_string()
+        };
+        RepoPage {
+            url,
+            raw_url,
+            title,
+        }
+    }
+
+    /// A GitHub wiki page by the name in its URL, e.g. "Getting-Started"
+    pub fn wiki_page(&self, name: &str) -> Option<RepoPage> {
+        if self.forge != Forge::GitHub || name.is_empty() || name.contains('/') {
+            return None;
+        }
+        Some(RepoPage {
+            url: format!("{}/{}/wiki/{}", self.origin, self.path, name),
+            raw_url: format!(
+                "https://raw.githubusercontent.com/wiki/{}/{}.md",
+                self.path, name
+            ),
+            title: name.replace('-', " "),
+        })
+    }
+
+    /// Whether `url` is a page of this repository's wiki
+    pub fn is_wiki_url(&self, url: &str) -> bool {
+        url.starts_with(&format!("{}/{}/wiki/", self.origin, self.path))
+    }
+
+    /// Wiki pages a wiki page links to
+    ///
+    /// Understands relative links, absolute wiki URLs and `[[Page Name]]`
+    /// style links.
+    pub fn wiki_links(&self, markdown: &str) -> Vec<RepoPage> {
+        let wiki = format!("{}/{}/wiki/", self.origin, self.path);
+        let Ok(base) = Url::parse(&wiki) else {
+            return Vec::new();
+        };
+
+        let inline = markdown.match_indices("](").filter_map(|(index, _)| {
+            let target = &markdown[index + 2..];
+            let end = target.find([')', ' ', '\n'])?;
+            Some(target[..end].to_string())
+        });
+        let bracketed = markdown.match_indices("[[").filter_map(|(index, _)| {
+            let inner = &markdown[index + 2..];
+            let inner = &inner[..inner.find("]]")?];
+            // [[Link text|Page Name]]
+            let name = inner.rsplit('|').next()?.trim();
+            Some(name.replace(' ', "-"))
+        });
+
+        let mut pages: Vec<RepoPage> = Vec::new();
+        for target in inline.chain(bracketed) {
+            let Ok(mut url) = base.join(&target) else {
+                continue;
+            };
+            url.set_fragment(None);
+            url.set_query(None);
+            let Some(name) = url.as_str().strip_prefix(&wiki) else {
+                continue;
+            };
+            if let Some(page) = self.wiki_page(name) {
+                if !pages.iter().any(|p| p.url == page.url) {
+                    pages.push(page);
+                }
+            }
+        }
+        pages
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &HttpClient,
+    auth: &CrawlAuth,
+    url: &str,
+) -> Result<T> {
+    let response = client
+        .send(
+            auth.apply(client.get(url), url)
+                .header(ACCEPT, "application/json"),
+        )
+        .await?
+        .error_for_status()?;
+    Ok(response.json().await?)
+}
+
+/// `base` with the `parts` (which may contain slashes) and `file` segments appended
+fn join_path(base: &str, parts: &[&str], file: &[&str]) -> String {
+    let mut url = Url::parse(base).expect("forge origins are valid URLs");
+    url.path_segments_mut()
+        .expect("forge origins have a path")
+        .pop_if_empty()
+        .extend(parts.iter().flat_map(|part| part.split('/')))
+        .extend(file);
+    url.to_string()
+}
+
+/// Percent-encode a value used as a single path segment or query value
+fn encode_segment(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, ext)| {
+        matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown" | "mdx")
+    })
+}
+
+fn is_readme(path: &str) -> bool {
+    !path.contains('/')
+        && is_markdown(path)
+        && path
+            .split('.')
+            .next()
+            .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"))
+}
+
+fn is_docs_file(path: &str) -> bool {
+    (path.starts_with("docs/") || path.starts_with("doc/")) && is_markdown(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repository_urls() {
+        let github =
+            Repository::parse("https://github.com/tokio-rs/tokio/tree/master/docs").unwrap();
+        assert_eq!(github.forge, Forge::GitHub);
+        assert_eq!(github.path, "tokio-rs/tokio");
+        assert_eq!(
+            Repository::parse("https://github.com/tokio-rs/tokio.git").unwrap(),
+            github
+        );
+
+        let gitlab =
+            Repository::parse("https://gitlab.com/gitlab-org/cli/glab/-/blob/main/README.md")
+                .unwrap();
+        assert_eq!(gitlab.forge, Forge::GitLab);
+        assert_eq!(gitlab.path, "gitlab-org/cli/glab");
+
+        assert!(Repository::parse("https://github.com/tokio-rs").is_none());
+        assert!(Repository::parse("https://docs.rs/tokio").is_none());
+    }
+
+    #[test]
+    fn test_doc_pages() {
+        let repo = Repository::parse("https://github.com/tokio-rs/tokio").unwrap();
+        let entries: Vec<TreeEntry> = serde_json::from_str(
+            r#"[
+                {"path": "CONTRIBUTING.md", "type": "blob"},
+                {"path": "README.md", "type": "blob"},
+                {"path": "docs", "type": "tree"},
+                {"path": "docs/Getting Started.md", "type": "blob"},
+                {"path": "docs/logo.png", "type": "blob"},
+                {"path": "src/docs/notes.md", "type": "blob"}
+            ]"#,
+        )
+        .unwrap();
+
+        let pages = repo.doc_pages("master", &entries, true);
+        assert_eq!(
+            pages,
+            vec![
+                RepoPage {
+                    url: "https://github.com/tokio-rs/tokio/blob/master/README.md".to_string(),
+                    raw_url: "https://raw.githubusercontent.com/tokio-rs/tokio/master/README.md"
+                        .to_string(),
+                    title: "tokio-rs/tokio".to_string(),
+                },
+                RepoPage {
+                    url: "https://github.com/tokio-rs/tokio/blob/master/docs/Getting%20Started.md"
+                        .to_string(),
+                    raw_url: "https://raw.githubusercontent.com/tokio-rs/tokio/master/docs/Getting%20Started.md"
+                        .to_string(),
+                    title: "Getting Started".to_string(),
+                },
+            ]
+        );
+        assert_eq!(repo.doc_pages("master", &entries, false).len(), 1);
+
+        let gitlab = Repository::parse("https://gitlab.com/gitlab-org/cli").unwrap();
+        let readme = gitlab.doc_pages("main", &entries, false).remove(0);
+        assert_eq!(
+            readme.url,
+            "https://gitlab.com/gitlab-org/cli/-/blob/main/README.md"
+        );
+        assert_eq!(
+            readme.raw_url,
+            "https://gitlab.com/gitlab-org/cli/-/raw/main/README.md"
+        );
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let repo = Repository::parse("https://github.com/tokio-rs/tokio").unwrap();
+        let markdown = "See [Setup](Setup#linux), [[Getting Started]] and \
+                        [[the FAQ|FAQ]]. Also [again](https://github.com/tokio-rs/tokio/wiki/Setup), \
+                        [issues](https://github.com/tokio-rs/tokio/issues) and [docs](https://docs.rs).";
+
+        let names: Vec<_> = repo
+            .wiki_links(markdown)
+            .into_iter()
+            .map(|page| page.url)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "https://github.com/tokio-rs/tokio/wiki/Setup",
+                "https://github.com/tokio-rs/tokio/wiki/Getting-Started",
+                "https://github.com/tokio-rs/tokio/wiki/FAQ",
+            ]
+        );
+
+        let home = repo.wiki_page("Home").unwrap();
+        assert_eq!(
+            home.raw_url,
+            "https://raw.githubusercontent.com/wiki/tokio-rs/tokio/Home.md"
+        );
+        assert!(repo.is_wiki_url(&home.url));
+    }
+}
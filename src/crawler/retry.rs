@@ -0,0 +1,207 @@
+//! Retries and per-host circuit breaking for page fetches.
+//!
+//! Transient failures (timeouts, connection errors, 5xx responses and rate
+//! limiting) are retried with jittered exponential backoff. A host that keeps
+//! failing is paused by its circuit breaker: requests to it wait out a
+//! cooldown, and the first failure after it pauses the host again.
+
+use crate::error::CodeRagError;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How often and how patiently a failed fetch is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled for each later one
+    pub initial_backoff: Duration,
+    /// Upper bound for the backoff
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    ///
+    /// Half of the exponential backoff is fixed and half random, so retries
+    /// from concurrent requests don't hit a recovering host at the same time.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Whether a fetch error is worth retrying
+pub fn is_transient(error: &CodeRagError) -> bool {
+    match error {
+        CodeRagError::RateLimited => true,
+        CodeRagError::Network(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.is_body()
+                || e.status().is_some_and(|status| status.is_server_error())
+        }
+        _ => false,
+    }
+}
+
+/// When a host's circuit breaker opens and how long it stays open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerPolicy {
+    /// Consecutive transient failures that pause a host; 0 disables the breaker
+    pub failure_threshold: u32,
+    /// How long a paused host is left alone
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A host paused by its circuit breaker during a crawl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostPause {
+    pub host: String,
+    /// Consecutive failures that opened the breaker
+    pub failures: u32,
+    pub paused_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    consecutive_failures: u32,
+    paused_until: Option<Instant>,
+}
+
+/// Circuit breakers for the hosts of a crawl
+#[derive(Debug)]
+pub struct HostBreakers {
+    policy: BreakerPolicy,
+    hosts: HashMap<String, HostState>,
+}
+
+impl HostBreakers {
+    pub fn new(policy: BreakerPolicy) -> Self {
+        Self {
+            policy,
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// When requests to `host` may resume, if it is paused at `now`
+    pub fn paused_until(&self, host: &str, now: Instant) -> Option<Instant> {
+        self.hosts
+            .get(host)
+            .and_then(|state| state.paused_until)
+            .filter(|until| *until > now)
+    }
+
+    /// Close the breaker after a successful request
+    pub fn record_success(&mut self, host: &str) {
+        self.hosts.remove(host);
+    }
+
+    /// Count a transient failure, returning the pause if it opened the breaker
+    ///
+    /// After a pause the next failure opens it again, until a request succeeds.
+    pub fn record_failure(&mut self, host: &str, now: Instant) -> Option<HostPause> {
+        if self.policy.failure_threshold == 0 {
+            return None;
+        }
+        let state = self.hosts.entry(host.to_string()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < self.policy.failure_threshold {
+            return None;
+        }
+
+        state.paused_until = Some(now + self.policy.cooldown);
+        Some(HostPause {
+            host: host.to_string(),
+            failures: state.consecutive_failures,
+            paused_ms: self.policy.cooldown.as_millis() as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_with_jitter_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+        };
+
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            let capped = policy.backoff(10);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_breaker_pauses_and_resumes_host() {
+        let mut breakers = HostBreakers::new(BreakerPolicy {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(30),
+        });
+        let now = Instant::now();
+
+        assert!(breakers.record_failure("docs.rs", now).is_none());
+        let pause = breakers.record_failure("docs.rs", now).unwrap();
+        assert_eq!(pause.failures, 2);
+        assert_eq!(pause.paused_ms, 30_000);
+
+        // Only the failing host is paused, until the cooldown is over
+        assert_eq!(
+            breakers.paused_until("docs.rs", now),
+            Some(now + Duration::from_secs(30))
+        );
+        assert!(breakers.paused_until("example.com", now).is_none());
+        assert!(breakers
+            .paused_until("docs.rs", now + Duration::from_secs(31))
+            .is_none());
+
+        // A failure right after the cooldown pauses it again; a success resets it
+        let later = now + Duration::from_secs(31);
+        assert!(breakers.record_failure("docs.rs", later).is_some());
+        breakers.record_success("docs.rs");
+        assert!(breakers.paused_until("docs.rs", later).is_none());
+        assert!(breakers.record_failure("docs.rs", later).is_none());
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient(&CodeRagError::RateLimited));
+        assert!(!is_transient(&CodeRagError::UnsupportedContent(
+            "binary content".to_string()
+        )));
+    }
+}
@@ -0,0 +1,93 @@
+//! Near-duplicate page detection.
+//!
+//! Doc sites often serve the same page at several URLs: versioned copies,
+//! print views, trailing-slash variants. A page's simhash is built from
+//! overlapping word shingles, so pages that differ only in a version banner
+//! or navigation link end up a few bits apart while unrelated pages differ
+//! in about half of the 64 bits.
+
+/// Words per shingle
+const SHINGLE_SIZE: usize = 3;
+
+/// Pages with fewer words carry too little text to compare reliably
+const MIN_WORDS: usize = 20;
+
+/// Largest Hamming distance between fingerprints of near-duplicate pages
+pub const NEAR_DUPLICATE_DISTANCE: u32 = 3;
+
+/// Compute the simhash fingerprint of a page's text
+///
+/// Returns None for pages too short to fingerprint.
+pub fn page_fingerprint(text: &str) -> Option<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(SHINGLE_SIZE) {
+        let hash = fnv1a(&shingle.join(" "));
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit)),
+    )
+}
+
+/// 64-bit FNV-1a, stable across builds since fingerprints are persisted
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_fingerprint() {
+        let page: String = (0..80)
+            .map(|i| {
+                format!(
+                    "Step {} spawns task {} onto the tokio runtime scheduler. ",
+                    i,
+                    i * 7
+                )
+            })
+            .collect();
+        let copy = format!("You are reading the docs for version 1.38. {}", page);
+        let other: String = (0..80)
+            .map(|i| {
+                format!(
+                    "Field {} of struct {} is serialized by serde derive. ",
+                    i,
+                    i * 3
+                )
+            })
+            .collect();
+
+        let a = page_fingerprint(&page).unwrap();
+        let b = page_fingerprint(&copy).unwrap();
+        let c = page_fingerprint(&other).unwrap();
+        assert!((a ^ b).count_ones() <= NEAR_DUPLICATE_DISTANCE);
+        assert!((a ^ c).count_ones() > NEAR_DUPLICATE_DISTANCE);
+
+        assert_eq!(page_fingerprint("Too short to compare"), None);
+    }
+}
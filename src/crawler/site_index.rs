@@ -0,0 +1,342 @@
+//! Prebuilt search indexes published by documentation generators.
+//!
+//! MkDocs (`search/search_index.json`), Sphinx (`searchindex.js`) and
+//! Docusaurus sites using the local search plugin (`search-index.json`)
+//! ship an index of every page and its sections. When a crawl finds one, its
+//! pages seed the frontier, so pages missing from the navigation aren't
+//! missed, and its section titles and anchors replace the anchors the
+//! extractor had to guess.
+
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::fetch::HttpClient;
+use crate::error::{CodeRagError, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use url::Url;
+
+/// Indexes larger than this are ignored
+const MAX_INDEX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Directories above the start URL searched for an index
+const MAX_ANCESTORS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    MkDocs,
+    Sphinx,
+    Docusaurus,
+}
+
+impl Generator {
+    /// Index file, relative to the site root
+    fn index_path(self) -> &'static str {
+        match self {
+            Generator::MkDocs => "search/search_index.json",
+            Generator::Sphinx => "searchindex.js",
+            Generator::Docusaurus => "search-index.json",
+        }
+    }
+}
+
+/// A page listed in a search index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedPage {
+    pub title: Option<String>,
+    /// Section titles and their anchors, in index order
+    pub sections: Vec<(String, String)>,
+}
+
+/// The pages of a documentation site, by URL without fragment
+#[derive(Debug, Clone)]
+pub struct SiteIndex {
+    pub generator: Generator,
+    /// Site root the index was found in
+    pub base: Url,
+    pub pages: BTreeMap<String, IndexedPage>,
+}
+
+#[derive(Deserialize)]
+struct MkDocsIndex {
+    docs: Vec<MkDocsEntry>,
+}
+
+#[derive(Deserialize)]
+struct MkDocsEntry {
+    location: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct SphinxIndex {
+    docnames: Vec<String>,
+    #[serde(default)]
+    titles: Vec<String>,
+    /// Section title to (document index, anchor) pairs; newer Sphinx only
+    #[serde(default)]
+    alltitles: HashMap<String, Vec<(usize, Option<String>)>>,
+}
+
+#[derive(Deserialize)]
+struct DocusaurusIndex {
+    documents: Vec<DocusaurusEntry>,
+}
+
+#[derive(Deserialize)]
+struct DocusaurusEntry {
+    #[serde(rename = "t")]
+    title: String,
+    #[serde(rename = "u")]
+    url: String,
+    /// Anchor, for headings
+    #[serde(rename = "h", default)]
+    hash: Option<String>,
+    /// Section title, set on content entries only
+    #[serde(rename = "s", default)]
+    section: Option<String>,
+}
+
+impl SiteIndex {
+    /// Look for a search index in the start URL's directory and its parents
+    ///
+    /// Returns the first index found, or `None` if the site has none.
+    pub async fn discover(client: &HttpClient, auth: &CrawlAuth, start_url: &str) -> Option<Self> {
+        for base in candidate_bases(start_url) {
+            for generator in [Generator::MkDocs, Generator::Sphinx, Generator::Docusaurus] {
+                let Ok(index_url) = base.join(generator.index_path()) else {
+                    continue;
+                };
+                let Some(body) = fetch_index(client, auth, index_url.as_str()).await else {
+                    continue;
+                };
+                match Self::parse(generator, &body, &base) {
+                    Ok(index) if !index.pages.is_empty() => {
+                        tracing::info!(
+                            "Found {:?} search index at {} listing {} pages",
+                            generator,
+                            index_url,
+                            index.pages.len()
+                        );
+                        return Some(index);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("Ignoring {}: {}", index_url, e),
+                }
+            }
+        }
+        None
+    }
+
+    /// Parse an index file served from the site root `base`
+    pub fn parse(generator: Generator, body: &str, base: &Url) -> Result<Self> {
+        let mut pages: BTreeMap<String, IndexedPage> = BTreeMap::new();
+        let mut add = |url: Url, title: &str| {
+            let mut page_url = url.clone();
+            page_url.set_fragment(None);
+            let page = pages.entry(page_url.to_string()).or_insert(IndexedPage {
+                title: None,
+                sections: Vec::new(),
+            });
+            let title = title.trim();
+            match url.fragment().filter(|anchor| !anchor.is_empty()) {
+                Some(anchor) if !title.is_empty() => {
+                    page.sections.push((title.to_string(), anchor.to_string()))
+                }
+                Some(_) => {}
+                None if !title.is_empty() => page.title = Some(title.to_string()),
+                None => {}
+            }
+        };
+
+        match generator {
+            Generator::MkDocs => {
+                let index: MkDocsIndex = serde_json::from_str(body)?;
+                for entry in index.docs {
+                    if let Ok(url) = base.join(&entry.location) {
+                        add(url, &entry.title);
+                    }
+                }
+            }
+            Generator::Sphinx => {
+                let json = body
+                    .trim()
+                    .strip_prefix("Search.setIndex(")
+                    .and_then(|rest| rest.trim_end_matches(';').strip_suffix(')'))
+                    .ok_or_else(|| CodeRagError::Parse("not a Sphinx search index".to_string()))?;
+                let index: SphinxIndex = serde_json::from_str(json)?;
+                let urls: Vec<Option<Url>> = index
+                    .docnames
+                    .iter()
+                    .map(|name| base.join(&format!("{}.html", name)).ok())
+                    .collect();
+                for (url, title) in urls.iter().zip(&index.titles) {
+                    if let Some(url) = url {
+                        add(url.clone(), title);
+                    }
+                }
+                // Sorted so sections come out in a stable order
+                let alltitles: BTreeMap<_, _> = index.alltitles.into_iter().collect();
+                for (title, refs) in alltitles {
+                    for (doc, anchor) in refs {
+                        let (Some(Some(url)), Some(anchor)) = (urls.get(doc), anchor) else {
+                            continue;
+                        };
+                        let mut url = url.clone();
+                        url.set_fragment(Some(&anchor));
+                        add(url, &title);
+                    }
+                }
+            }
+            Generator::Docusaurus => {
+                // One entry each for titles, headings and content
+                let indexes: Vec<DocusaurusIndex> = serde_json::from_str(body)?;
+                for entry in indexes.iter().flat_map(|index| &index.documents) {
+                    // Content entries hold text, not titles
+                    if entry.section.is_some() {
+                        continue;
+                    }
+                    let Ok(mut url) = base.join(&entry.url) else {
+                        continue;
+                    };
+                    if let Some(hash) = &entry.hash {
+                        url.set_fragment(Some(hash.trim_start_matches('#')));
+                    }
+                    add(url, &entry.title);
+                }
+            }
+        }
+
+        Ok(Self {
+            generator,
+            base: base.clone(),
+            pages,
+        })
+    }
+}
+
+/// The start URL's directory and its parents, closest first
+fn candidate_bases(start_url: &str) -> Vec<Url> {
+    let Ok(mut url) = Url::parse(start_url) else {
+        return Vec::new();
+    };
+    url.set_query(None);
+    url.set_fragment(None);
+
+    let mut bases = Vec::new();
+    let mut current = url.join("./").ok();
+    while let Some(base) = current {
+        if bases.len() > MAX_ANCESTORS {
+            break;
+        }
+        let parent = base.join("../").ok().filter(|parent| parent != &base);
+        bases.push(base);
+        current = parent;
+    }
+    bases
+}
+
+async fn fetch_index(client: &HttpClient, auth: &CrawlAuth, url: &str) -> Option<String> {
+    let response = client.send(auth.apply(client.get(url), url)).await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_INDEX_BYTES)
+    {
+        tracing::warn!("Ignoring search index {}: too large", url);
+        return None;
+    }
+    response.text().await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn test_candidate_bases() {
+        let bases: Vec<String> =
+            candidate_bases("https://requests.readthedocs.io/en/latest/user/quickstart/?x=1")
+                .into_iter()
+                .map(String::from)
+                .collect();
+        assert_eq!(
+            bases,
+            vec![
+                "https://requests.readthedocs.io/en/latest/user/quickstart/",
+                "https://requests.readthedocs.io/en/latest/user/",
+                "https://requests.readthedocs.io/en/latest/",
+                "https://requests.readthedocs.io/en/",
+                "https://requests.readthedocs.io/",
+            ]
+        );
+        assert_eq!(candidate_bases("https://example.com/guide.html").len(), 1);
+    }
+
+    #[test]
+    fn test_parse_mkdocs() {
+        let body = r#"{"config": {"lang": ["en"]}, "docs": [
+            {"location": "", "title": "Home", "text": "..."},
+            {"location": "setup/", "title": "Setup", "text": "..."},
+            {"location": "setup/#requirements", "title": "Requirements", "text": "..."}
+        ]}"#;
+        let index =
+            SiteIndex::parse(Generator::MkDocs, body, &base("https://example.com/proj/")).unwrap();
+
+        assert_eq!(index.pages.len(), 2);
+        let setup = &index.pages["https://example.com/proj/setup/"];
+        assert_eq!(setup.title.as_deref(), Some("Setup"));
+        assert_eq!(
+            setup.sections,
+            vec![("Requirements".to_string(), "requirements".to_string())]
+        );
+        assert_eq!(
+            index.pages["https://example.com/proj/"].title.as_deref(),
+            Some("Home")
+        );
+    }
+
+    #[test]
+    fn test_parse_sphinx() {
+        let body = r#"Search.setIndex({"alltitles": {"Installation": [[1, null]],
+            "Using pip": [[1, "using-pip"]]}, "docnames": ["index", "user/install"],
+            "titles": ["Requests", "Installation"], "terms": {}})"#;
+        let index = SiteIndex::parse(
+            Generator::Sphinx,
+            body,
+            &base("https://requests.readthedocs.io/en/latest/"),
+        )
+        .unwrap();
+
+        let install = &index.pages["https://requests.readthedocs.io/en/latest/user/install.html"];
+        assert_eq!(install.title.as_deref(), Some("Installation"));
+        assert_eq!(
+            install.sections,
+            vec![("Using pip".to_string(), "using-pip".to_string())]
+        );
+        assert!(SiteIndex::parse(Generator::Sphinx, "{}", &base("https://x.dev/")).is_err());
+    }
+
+    #[test]
+    fn test_parse_docusaurus() {
+        let body = r##"[
+            {"documents": [{"i": 1, "t": "Introduction", "u": "/docs/intro", "b": []}], "index": {}},
+            {"documents": [{"i": 2, "t": "Getting Started", "u": "/docs/intro", "h": "#getting-started", "p": 1}], "index": {}},
+            {"documents": [{"i": 3, "t": "Install the CLI first.", "s": "Getting Started", "u": "/docs/intro", "h": "#getting-started", "p": 1}], "index": {}}
+        ]"##;
+        let index =
+            SiteIndex::parse(Generator::Docusaurus, body, &base("https://docusaurus.io/")).unwrap();
+
+        let intro = &index.pages["https://docusaurus.io/docs/intro"];
+        assert_eq!(intro.title.as_deref(), Some("Introduction"));
+        assert_eq!(
+            intro.sections,
+            vec![("Getting Started".to_string(), "getting-started".to_string())]
+        );
+    }
+}
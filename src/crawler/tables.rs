@@ -0,0 +1,131 @@
+//! HTML table conversion.
+//!
+//! html2text renders tables as wrapped columns of pipes, which the markdown
+//! cleanup then drops as navigation. Reference pages keep their most useful
+//! facts in tables (configuration options, feature flags, type mappings), so
+//! tables are converted separately: two-column tables become key-value lists,
+//! wider ones Markdown tables.
+
+use scraper::{ElementRef, Selector};
+
+/// Convert a table element to Markdown
+///
+/// Returns None for tables without any text, and for layout tables that nest
+/// other tables, which are better left to the regular conversion.
+pub fn table_to_markdown(table: ElementRef) -> Option<String> {
+    let nested = Selector::parse("table").unwrap();
+    if table.select(&nested).next().is_some() {
+        return None;
+    }
+
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("th, td").unwrap();
+    let header_selector = Selector::parse("thead tr").unwrap();
+
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut has_header = table.select(&header_selector).next().is_some();
+    for (index, row) in table.select(&row_selector).enumerate() {
+        let cells: Vec<ElementRef> = row.select(&cell_selector).collect();
+        if index == 0 && !cells.is_empty() && cells.iter().all(|c| c.value().name() == "th") {
+            has_header = true;
+        }
+        rows.push(cells.into_iter().map(cell_text).collect());
+    }
+    rows.retain(|row| row.iter().any(|cell| !cell.is_empty()));
+    if rows.is_empty() {
+        return None;
+    }
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(columns, String::new());
+    }
+
+    let mut lines = Vec::new();
+    let caption = Selector::parse("caption").unwrap();
+    if let Some(caption) = table.select(&caption).next().map(cell_text) {
+        if !caption.is_empty() {
+            lines.push(format!("**{}**", caption));
+            lines.push(String::new());
+        }
+    }
+
+    if columns == 2 {
+        // The header of a key-value table only names the columns
+        let body = if has_header { &rows[1..] } else { &rows[..] };
+        for row in body {
+            if row[1].is_empty() {
+                lines.push(format!("- **{}**", row[0]));
+            } else {
+                lines.push(format!("- **{}**: {}", row[0], row[1]));
+            }
+        }
+    } else {
+        // Markdown tables need a header row, so one without uses its first row
+        let (header, body) = rows.split_first()?;
+        lines.push(markdown_row(header));
+        lines.push(markdown_row(&vec!["---".to_string(); columns]));
+        lines.extend(body.iter().map(|row| markdown_row(row)));
+    }
+
+    Some(lines.join("\n"))
+}
+
+/// A cell's text with whitespace collapsed and pipes escaped
+fn cell_text(cell: ElementRef) -> String {
+    cell.text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+fn markdown_row(cells: &[String]) -> String {
+    format!("| {} |", cells.join(" | "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::Html;
+
+    fn convert(html: &str) -> Option<String> {
+        let document = Html::parse_fragment(html);
+        let table = document
+            .select(&Selector::parse("table").unwrap())
+            .next()
+            .unwrap();
+        table_to_markdown(table)
+    }
+
+    #[test]
+    fn test_table_to_markdown() {
+        let options = r#"<table>
+            <thead><tr><th>Option</th><th>Description</th></tr></thead>
+            <tbody>
+              <tr><td><code>worker_threads</code></td><td>Number of worker
+                threads</td></tr>
+              <tr><td>enable_io</td><td>Enables the I/O driver</td></tr>
+            </tbody></table>"#;
+        assert_eq!(
+            convert(options).unwrap(),
+            "- **worker_threads**: Number of worker threads\n- **enable_io**: Enables the I/O driver"
+        );
+
+        let features = r#"<table><caption>Feature flags</caption>
+            <tr><th>Flag</th><th>Default</th><th>Enables</th></tr>
+            <tr><td>rt</td><td>no</td><td>Runtime | scheduler</td></tr>
+            <tr><td>macros</td><td>no</td></tr></table>"#;
+        assert_eq!(
+            convert(features).unwrap(),
+            "**Feature flags**\n\n| Flag | Default | Enables |\n| --- | --- | --- |\n\
+             | rt | no | Runtime \\| scheduler |\n| macros | no |  |"
+        );
+
+        assert_eq!(convert("<table><tr><td> </td></tr></table>"), None);
+        assert_eq!(
+            convert("<table><tr><td><table><tr><td>a</td></tr></table></td></tr></table>"),
+            None
+        );
+    }
+}
@@ -1,10 +1,17 @@
+use crate::crawler::auth::DomainAuth;
+use crate::crawler::domains::DomainPolicy;
+use crate::crawler::fetch::ClientOptions;
+use crate::crawler::retry::{BreakerPolicy, HostPause, RetryPolicy};
+use crate::error::CodeRagError;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use url::Url;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum CrawlMode {
     SinglePage, // Just crawl the provided URL
-    Section,    // Crawl the page and its direct children
+    Section,    // Crawl the pages below the start URL's path, up to max_depth
     FullDocs,   // Crawl the entire documentation site
 }
 
@@ -17,6 +24,40 @@ pub enum DocumentationFocus {
     All,          // No specific focus
 }
 
+impl std::str::FromStr for CrawlMode {
+    type Err = CodeRagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single" => Ok(CrawlMode::SinglePage),
+            "section" => Ok(CrawlMode::Section),
+            "full" => Ok(CrawlMode::FullDocs),
+            _ => Err(CodeRagError::Parse(format!(
+                "Invalid crawl mode: {}. Use 'single', 'section', or 'full'",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentationFocus {
+    type Err = CodeRagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "api" => Ok(DocumentationFocus::ApiReference),
+            "examples" => Ok(DocumentationFocus::Examples),
+            "changelog" => Ok(DocumentationFocus::Changelog),
+            "quickstart" => Ok(DocumentationFocus::QuickStart),
+            "all" => Ok(DocumentationFocus::All),
+            _ => Err(CodeRagError::Parse(format!(
+                "Invalid focus: {}. Use 'api', 'examples', 'changelog', 'quickstart', or 'all'",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CrawlConfig {
     pub start_url: String,
@@ -28,7 +69,23 @@ pub struct CrawlConfig {
     pub delay_ms: u64,
     pub user_agent: String,
     pub allowed_domains: HashSet<String>,
+    /// How hosts are matched against `allowed_domains`
+    pub domain_policy: DomainPolicy,
     pub url_patterns: UrlPatterns,
+    /// Page bodies are truncated beyond this many bytes
+    pub max_page_bytes: usize,
+    /// Chunks beyond this many per page are dropped
+    pub max_chunks_per_page: usize,
+    /// Pages of one paginated article followed through its next/previous links (1 disables)
+    pub max_pages_per_article: usize,
+    /// Retries of fetches that failed transiently
+    pub retry: RetryPolicy,
+    /// Pausing of hosts that keep failing
+    pub breaker: BreakerPolicy,
+    /// Proxy and TLS settings
+    pub client: ClientOptions,
+    /// Credentials by domain
+    pub auth: BTreeMap<String, DomainAuth>,
 }
 
 impl Default for CrawlConfig {
@@ -43,39 +100,132 @@ impl Default for CrawlConfig {
             delay_ms: 500,
             user_agent: "CodeRAG/0.1.0 (AI Documentation Assistant)".to_string(),
             allowed_domains: HashSet::new(),
+            domain_policy: DomainPolicy::default(),
             url_patterns: UrlPatterns::default(),
+            max_page_bytes: 5 * 1024 * 1024,
+            max_chunks_per_page: 200,
+            max_pages_per_article: 20,
+            retry: RetryPolicy::default(),
+            breaker: BreakerPolicy::default(),
+            client: ClientOptions::default(),
+            auth: BTreeMap::new(),
         }
     }
 }
 
+/// Which discovered links a crawl follows
+///
+/// Excludes win over includes; with includes, a link must match one of them
+/// (or be listed in the site's search index).
 #[derive(Debug, Clone)]
 pub struct UrlPatterns {
-    pub include: Vec<String>,
-    pub exclude: Vec<String>,
+    pub include: Vec<UrlPattern>,
+    pub exclude: Vec<UrlPattern>,
 }
 
 impl Default for UrlPatterns {
     fn default() -> Self {
+        let contains = |fragments: &[&str]| {
+            fragments
+                .iter()
+                .map(|fragment| UrlPattern::Contains(fragment.to_string()))
+                .collect()
+        };
         Self {
-            include: vec![
-                "/docs/".to_string(),
-                "/api/".to_string(),
-                "/guide/".to_string(),
-                "/reference/".to_string(),
-                "/tutorial/".to_string(),
-                "/manual/".to_string(),
-                "/changelog/".to_string(),
-                "/whatsnew/".to_string(),
-            ],
-            exclude: vec![
-                "/blog/".to_string(),
-                "/forum/".to_string(),
-                "/community/".to_string(),
-                "/discuss/".to_string(),
-                "/issues/".to_string(),
-                "/pull/".to_string(),
-                "/commits/".to_string(),
-            ],
+            include: contains(&[
+                "/docs/",
+                "/api/",
+                "/guide/",
+                "/reference/",
+                "/tutorial/",
+                "/manual/",
+                "/changelog/",
+                "/whatsnew/",
+            ]),
+            exclude: contains(&[
+                "/blog/",
+                "/forum/",
+                "/community/",
+                "/discuss/",
+                "/issues/",
+                "/pull/",
+                "/commits/",
+            ]),
+        }
+    }
+}
+
+impl UrlPatterns {
+    /// Patterns given for one crawl, replacing the built-in lists; none keeps them
+    pub fn custom(include: &[String], exclude: &[String]) -> Result<Self, CodeRagError> {
+        if include.is_empty() && exclude.is_empty() {
+            return Ok(Self::default());
+        }
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| UrlPattern::parse(pattern))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: parse(include)?,
+            exclude: parse(exclude)?,
+        })
+    }
+}
+
+/// A pattern a URL can match
+///
+/// `re:` starts a regular expression searched for in the whole URL. A pattern
+/// with `*` or `?` is a glob: `*` and `?` stay within one path segment, `**`
+/// spans any number. Globs starting with `/` match the URL path, others the
+/// whole URL. Anything else matches URLs containing it.
+#[derive(Debug, Clone)]
+pub enum UrlPattern {
+    Contains(String),
+    Glob { regex: Regex, path_only: bool },
+    Regex(Regex),
+}
+
+impl UrlPattern {
+    pub fn parse(pattern: &str) -> Result<Self, CodeRagError> {
+        let invalid = |e: regex::Error| {
+            CodeRagError::Parse(format!("Invalid URL pattern {:?}: {}", pattern, e))
+        };
+        if let Some(regex) = pattern.strip_prefix("re:") {
+            return Regex::new(regex).map(Self::Regex).map_err(invalid);
+        }
+        if !pattern.contains(['*', '?']) {
+            return Ok(Self::Contains(pattern.to_string()));
+        }
+
+        let mut translated = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    translated.push_str(".*");
+                }
+                '*' => translated.push_str("[^/]*"),
+                '?' => translated.push_str("[^/]"),
+                c => translated.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        translated.push('$');
+        Ok(Self::Glob {
+            regex: Regex::new(&translated).map_err(invalid)?,
+            path_only: pattern.starts_with('/'),
+        })
+    }
+
+    pub fn matches(&self, url: &str) -> bool {
+        match self {
+            Self::Contains(fragment) => url.contains(fragment.as_str()),
+            Self::Glob { regex, path_only } if *path_only => Url::parse(url)
+                .map(|url| regex.is_match(url.path()))
+                .unwrap_or(false),
+            Self::Glob { regex, .. } | Self::Regex(regex) => regex.is_match(url),
         }
     }
 }
@@ -96,6 +246,10 @@ pub struct DocumentChunk {
     pub end_char: usize,
     pub has_code: bool,
     pub heading_context: Option<String>,
+    /// Natural language detected from the chunk's script, if distinctive
+    pub lang: Option<String>,
+    /// Absolute URLs of the links in the chunk
+    pub links: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -103,14 +257,86 @@ pub struct CrawlMetadata {
     pub crawled_at: String,
     pub content_type: String,
     pub language: Option<String>,
+    /// Natural language of the page, e.g. "ja"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
     pub framework: Option<String>,
     pub version: Option<String>,
 }
 
+/// The pages a crawl would fetch, from a discover-only run
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CrawlPlan {
+    /// URLs in the order the crawl would fetch them
+    pub urls: Vec<DiscoveredUrl>,
+    /// Pages downloaded to read their links
+    pub pages_fetched: usize,
+    /// The page limit cut the list short
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DiscoveredUrl {
+    pub url: String,
+    /// Links followed from the start URL to reach it
+    pub depth: usize,
+    /// How it was found: "start", "repository", "site_index", "pagination" or "link"
+    pub found_by: String,
+}
+
+/// A page left out of the index because its content can't be extracted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SkippedPage {
+    pub url: String,
+    /// e.g. "JSON response: application/json"
+    pub reason: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct CrawlProgress {
     pub pages_crawled: usize,
     pub pages_queued: usize,
     pub pages_failed: usize,
     pub current_url: Option<String>,
+    /// Fetches retried after a transient failure
+    pub retries: usize,
+    /// Hosts paused by their circuit breaker, in the order it opened
+    pub host_pauses: Vec<HostPause>,
+    /// Pages skipped for their content type, such as JSON, images or archives
+    pub skipped: Vec<SkippedPage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_patterns() {
+        let matches = |pattern: &str, url: &str| UrlPattern::parse(pattern).unwrap().matches(url);
+
+        assert!(matches(
+            "/docs/**",
+            "https://example.com/docs/guide/intro.html"
+        ));
+        assert!(!matches("/docs/**", "https://example.com/blog/docs/post"));
+        assert!(matches(
+            "/api/*.html",
+            "https://example.com/api/client.html"
+        ));
+        assert!(!matches(
+            "/api/*.html",
+            "https://example.com/api/v2/client.html"
+        ));
+        assert!(matches(
+            "https://*.rs/tokio/**",
+            "https://docs.rs/tokio/latest/"
+        ));
+        assert!(matches("/blog/", "https://example.com/blog/2024"));
+        assert!(matches(r"re:/v\d+/", "https://example.com/api/v2/client"));
+        assert!(UrlPattern::parse("re:(").is_err());
+
+        let patterns = UrlPatterns::custom(&["/docs/**".to_string()], &[]).unwrap();
+        assert_eq!((patterns.include.len(), patterns.exclude.len()), (1, 0));
+        assert!(!UrlPatterns::custom(&[], &[]).unwrap().include.is_empty());
+    }
 }
@@ -0,0 +1,130 @@
+//! Checking indexed pages for dead or moved content.
+//!
+//! Documentation sites get restructured: pages disappear, and whole sites
+//! move to a new domain behind a redirect. A few pages per source are
+//! requested with HEAD, which is enough to tell when a source has gone
+//! stale without re-crawling it.
+
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::fetch::HttpClient;
+use crate::vectordb::source_root;
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Default number of pages checked per source
+pub const DEFAULT_SAMPLE_SIZE: usize = 5;
+
+/// What is wrong with an indexed page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageProblem {
+    /// The server answered 404 Not Found or 410 Gone
+    Gone(u16),
+    /// The page redirects to another domain
+    Moved(String),
+    /// Any other error status or a network failure, possibly transient
+    Unreachable(String),
+}
+
+/// Pick up to `per_source` URLs from each source, spread over its pages
+pub fn sample_urls<'a>(urls: impl IntoIterator<Item = &'a str>, per_source: usize) -> Vec<String> {
+    let mut by_source: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for url in urls {
+        by_source.entry(source_root(url)).or_default().push(url);
+    }
+
+    let mut sample = Vec::new();
+    for urls in by_source.values_mut() {
+        urls.sort_unstable();
+        urls.dedup();
+        let count = per_source.min(urls.len());
+        sample.extend((0..count).map(|i| urls[i * urls.len() / count].to_string()));
+    }
+    sample
+}
+
+/// Check a page, returning what's wrong with it if anything
+///
+/// Servers that reject HEAD requests are asked again with GET. Requests carry
+/// the configured credentials so pages behind auth aren't reported as broken.
+pub async fn check_url(client: &HttpClient, auth: &CrawlAuth, url: &str) -> Option<PageProblem> {
+    let response = match client.send(auth.apply(client.head(url), url)).await {
+        Ok(response)
+            if matches!(
+                response.status(),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            ) =>
+        {
+            client.send(auth.apply(client.get(url), url)).await
+        }
+        other => other,
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Some(PageProblem::Unreachable(e.to_string())),
+    };
+
+    let status = response.status();
+    if matches!(status, StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Some(PageProblem::Gone(status.as_u16()));
+    }
+    if !status.is_success() {
+        return Some(PageProblem::Unreachable(format!("HTTP {}", status)));
+    }
+    if moved_to_new_domain(url, response.url()) {
+        return Some(PageProblem::Moved(response.url().to_string()));
+    }
+    None
+}
+
+/// Whether a redirect ended up on a different host, ignoring a `www.` prefix
+fn moved_to_new_domain(original: &str, final_url: &Url) -> bool {
+    let host = |url: &Url| {
+        url.host_str()
+            .map(|host| host.trim_start_matches("www.").to_lowercase())
+    };
+    match Url::parse(original) {
+        Ok(original) => host(&original) != host(final_url),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_urls() {
+        let urls = [
+            "https://docs.rs/tokio/latest/tokio/a.html",
+            "https://docs.rs/tokio/latest/tokio/b.html",
+            "https://docs.rs/tokio/latest/tokio/c.html",
+            "https://docs.rs/tokio/latest/tokio/d.html",
+            "https://docs.rs/tokio/latest/tokio/b.html",
+            "https://serde.rs/derive.html",
+        ];
+        assert_eq!(
+            sample_urls(urls, 2),
+            [
+                "https://docs.rs/tokio/latest/tokio/a.html",
+                "https://docs.rs/tokio/latest/tokio/c.html",
+                "https://serde.rs/derive.html",
+            ]
+        );
+        assert!(sample_urls(urls, 0).is_empty());
+    }
+
+    #[test]
+    fn test_moved_to_new_domain() {
+        let moved = |from: &str, to: &str| moved_to_new_domain(from, &Url::parse(to).unwrap());
+        assert!(!moved("https://example.com/a", "https://www.example.com/b"));
+        assert!(!moved(
+            "http://Docs.example.com/a",
+            "https://docs.example.com/a"
+        ));
+        assert!(moved(
+            "https://old.example.com/a",
+            "https://new.example.org/a"
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! Layout of the global data directory.
+//!
+//! ```text
+//! ~/.coderag/
+//!   layout.json     layout version marker
+//!   config.toml     settings for databases in this directory
+//!   sources.json    crawl history
+//!   projects.json   project roots opened before, for list_projects
+//!   crawl_usage.json  pages agents crawled today, for the crawl policy
+//!   db/             the database file, its lock, profile, archive, query log and backups
+//!   index/          persisted vector indexes
+//!   cache/          stored pages, import checkpoints and other data that can be rebuilt
+//!   blobs/          page snapshots
+//! ```
+//!
+//! Data directories written before the layout existed held everything flat
+//! beside `coderag_vectordb.json`; [`DataDir::open`] moves those files into
+//! place the first time a newer CodeRAG opens the directory.
+
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::DatabaseLock;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Version of the directory layout written by this build
+pub const LAYOUT_VERSION: u32 = 1;
+
+const LAYOUT_FILE: &str = "layout.json";
+
+/// Name of the global database file, without extension
+pub const DATABASE_STEM: &str = "coderag_vectordb";
+
+/// Flat files of the old layout that belong in `db/`
+const DATABASE_FILES: &[&str] = &[
+    "coderag_vectordb.json",
+    "coderag_vectordb.sqlite",
+    "coderag_vectordb.archive.json",
+    "embedding_profile.json",
+    "query_log.jsonl",
+    "backups",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LayoutMarker {
+    version: u32,
+}
+
+/// A data directory with the namespaced layout
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    /// Open a data directory, creating it or migrating a flat one to the current layout
+    pub fn open(root: &Path) -> Result<Self> {
+        let dir = Self::at(root);
+        fs::create_dir_all(root).map_err(|e| {
+            CodeRagError::io(format!("Failed to create data directory {:?}", root), e)
+        })?;
+
+        match dir.version()? {
+            Some(LAYOUT_VERSION) => {}
+            Some(version) if version > LAYOUT_VERSION => {
+                return Err(CodeRagError::LayoutVersion {
+                    path: root.to_path_buf(),
+                    found: version,
+                    supported: LAYOUT_VERSION,
+                })
+            }
+            _ => {
+                if !dir.migrate_flat()? {
+                    return Ok(dir);
+                }
+            }
+        }
+        for sub in [
+            dir.db_dir(),
+            dir.index_dir(),
+            dir.cache_dir(),
+            dir.blobs_dir(),
+        ] {
+            fs::create_dir_all(&sub)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", sub), e))?;
+        }
+        Ok(dir)
+    }
+
+    /// A data directory as it is, without creating or migrating it
+    pub fn at(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// The data directory a database file belongs to, if it is one with this layout
+    pub fn containing(db_path: &Path) -> Option<Self> {
+        let db_dir = db_path.parent()?;
+        let root = db_dir.parent()?;
+        (db_dir.file_name()? == "db" && root.join(LAYOUT_FILE).exists()).then(|| Self::at(root))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn db_dir(&self) -> PathBuf {
+        self.root.join("db")
+    }
+
+    pub fn index_dir(&self) -> PathBuf {
+        self.root.join("index")
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    /// Checkpoints of URL list imports
+    pub fn imports_dir(&self) -> PathBuf {
+        self.cache_dir().join("imports")
+    }
+
+    pub fn blobs_dir(&self) -> PathBuf {
+        self.root.join("blobs")
+    }
+
+    pub fn sources_path(&self) -> PathBuf {
+        self.root.join("sources.json")
+    }
+
+    pub fn projects_path(&self) -> PathBuf {
+        self.root.join("projects.json")
+    }
+
+    pub fn crawl_usage_path(&self) -> PathBuf {
+        self.root.join("crawl_usage.json")
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.root.join("config.toml")
+    }
+
+    /// Path of a database file; a directory not migrated yet keeps it at the top level
+    pub fn database_path(&self, file_name: &str) -> PathBuf {
+        if self.root.join(LAYOUT_FILE).exists() {
+            self.db_dir().join(file_name)
+        } else {
+            self.root.join(file_name)
+        }
+    }
+
+    /// Layout version recorded in the directory, if any
+    fn version(&self) -> Result<Option<u32>> {
+        let path = self.root.join(LAYOUT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| CodeRagError::io(format!("Failed to read {:?}", path), e))?;
+        let marker: LayoutMarker = serde_json::from_str(&content)
+            .map_err(|e| CodeRagError::Corrupt(format!("Failed to parse {:?}: {}", path, e)))?;
+        Ok(Some(marker.version))
+    }
+
+    /// Move the files of a flat data directory into the layout
+    ///
+    /// Returns false, leaving everything in place, while another instance
+    /// still has the old database open for writing.
+    fn migrate_flat(&self) -> Result<bool> {
+        let old_database = self.root.join(format!("{}.json", DATABASE_STEM));
+        let Some(lock) = DatabaseLock::try_acquire(&old_database)? else {
+            warn!(
+                "Not migrating {:?} to the new layout while another CodeRAG instance uses it",
+                self.root
+            );
+            return Ok(false);
+        };
+
+        let db_dir = self.db_dir();
+        fs::create_dir_all(&db_dir)
+            .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", db_dir), e))?;
+        let mut moves: Vec<(PathBuf, PathBuf)> = DATABASE_FILES
+            .iter()
+            .map(|name| (self.root.join(name), db_dir.join(name)))
+            .collect();
+        moves.push((
+            self.root.join(format!("{}.blobs", DATABASE_STEM)),
+            self.blobs_dir(),
+        ));
+        moves.push((
+            self.root.join(format!("{}.pages", DATABASE_STEM)),
+            self.cache_dir().join("pages"),
+        ));
+
+        let mut moved = 0;
+        for (from, to) in moves {
+            if !from.exists() || to.exists() {
+                continue;
+            }
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+            }
+            fs::rename(&from, &to).map_err(|e| {
+                CodeRagError::io(format!("Failed to move {:?} to {:?}", from, to), e)
+            })?;
+            moved += 1;
+        }
+        let old_lock = lock.path().to_path_buf();
+        drop(lock);
+        let _ = fs::remove_file(old_lock);
+
+        let marker = serde_json::to_string_pretty(&LayoutMarker {
+            version: LAYOUT_VERSION,
+        })?;
+        let path = self.root.join(LAYOUT_FILE);
+        fs::write(&path, marker)
+            .map_err(|e| CodeRagError::io(format!("Failed to write {:?}", path), e))?;
+        if moved > 0 {
+            info!(
+                "Migrated {} files in {:?} to layout version {}",
+                moved, self.root, LAYOUT_VERSION
+            );
+        }
+        Ok(true)
+    }
+}
+
+/// Bytes the file or directory tree at `path` occupies, 0 if it doesn't exist
+///
+/// Symlinks are counted as links, not followed.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_disk_usage() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("db/backups")).unwrap();
+        fs::write(root.join("db/vectors.json"), vec![b'x'; 1000]).unwrap();
+        fs::write(root.join("db/backups/1.json"), vec![b'x'; 500]).unwrap();
+
+        assert_eq!(disk_usage(&root.join("db/vectors.json")), 1000);
+        assert_eq!(disk_usage(root), 1500);
+        assert_eq!(disk_usage(&root.join("missing")), 0);
+    }
+
+    #[test]
+    fn test_migrates_flat_directory() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::write(root.join("coderag_vectordb.json"), "{}").unwrap();
+        fs::write(root.join("sources.json"), "{}").unwrap();
+        fs::create_dir_all(root.join("coderag_vectordb.blobs/ab")).unwrap();
+        fs::create_dir_all(root.join("backups")).unwrap();
+
+        let dir = DataDir::open(root).unwrap();
+        assert!(dir.db_dir().join("coderag_vectordb.json").exists());
+        assert!(dir.db_dir().join("backups").is_dir());
+        assert!(dir.blobs_dir().join("ab").is_dir());
+        assert!(dir.sources_path().exists());
+        assert!(!root.join("coderag_vectordb.json").exists());
+        assert_eq!(dir.version().unwrap(), Some(LAYOUT_VERSION));
+
+        let db_path = dir.database_path("coderag_vectordb.json");
+        assert_eq!(db_path, dir.db_dir().join("coderag_vectordb.json"));
+        assert_eq!(DataDir::containing(&db_path).unwrap().root(), root);
+        assert!(DataDir::containing(&root.join("vectordb.json")).is_none());
+
+        // A newer layout is refused rather than misread
+        fs::write(root.join(LAYOUT_FILE), r#"{"version": 99}"#).unwrap();
+        assert!(DataDir::open(root).is_err());
+    }
+}
@@ -1,11 +1,289 @@
-use anyhow::{Context, Result};
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
-use tokio::sync::OnceCell;
-use tracing::{debug, error, info};
+use crate::config::EmbeddingConfig;
+use crate::error::{CodeRagError, Result};
+use crate::telemetry;
+use fastembed::{
+    EmbeddingModel, ExecutionProviderDispatch, InitOptions, InitOptionsUserDefined, ModelInfo,
+    TextEmbedding, TokenizerFiles, UserDefinedEmbeddingModel,
+};
+use ort::execution_providers::{
+    CPUExecutionProvider, CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::{watch, OnceCell};
+use tracing::{debug, error, info, warn};
+
+/// Supported models: (config name, FastEmbed model, dimension, query prefix, passage prefix)
+const SUPPORTED_MODELS: &[(&str, EmbeddingModel, usize, &str, &str)] = &[
+    (
+        "all-MiniLM-L6-v2",
+        EmbeddingModel::AllMiniLML6V2,
+        384,
+        "",
+        "",
+    ),
+    (
+        "all-MiniLM-L12-v2",
+        EmbeddingModel::AllMiniLML12V2,
+        384,
+        "",
+        "",
+    ),
+    (
+        "bge-small-en-v1.5",
+        EmbeddingModel::BGESmallENV15,
+        384,
+        BGE_QUERY_PREFIX,
+        "",
+    ),
+    (
+        "bge-base-en-v1.5",
+        EmbeddingModel::BGEBaseENV15,
+        768,
+        BGE_QUERY_PREFIX,
+        "",
+    ),
+    (
+        "nomic-embed-text-v1.5",
+        EmbeddingModel::NomicEmbedTextV15,
+        768,
+        "search_query: ",
+        "search_document: ",
+    ),
+    // Multilingual models, for documentation that isn't in English
+    (
+        "multilingual-e5-small",
+        EmbeddingModel::MultilingualE5Small,
+        384,
+        "query: ",
+        "passage: ",
+    ),
+    (
+        "multilingual-e5-base",
+        EmbeddingModel::MultilingualE5Base,
+        768,
+        "query: ",
+        "passage: ",
+    ),
+    (
+        "paraphrase-multilingual-MiniLM-L12-v2",
+        EmbeddingModel::ParaphraseMLMiniLML12V2,
+        384,
+        "",
+        "",
+    ),
+];
+
+/// Instruction BGE English models expect before retrieval queries
+const BGE_QUERY_PREFIX: &str = "Represent this sentence for searching relevant passages: ";
+
+/// Tokenizer files loaded alongside the ONNX model, in the order of [`TokenizerFiles`]
+const TOKENIZER_FILES: [&str; 4] = [
+    "tokenizer.json",
+    "config.json",
+    "special_tokens_map.json",
+    "tokenizer_config.json",
+];
+
+/// Default Hugging Face endpoint, overridden like fastembed does with HF_ENDPOINT
+const HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// ONNX Runtime execution provider used to run the embedding model
+///
+/// GPU providers need the matching cargo feature (`cuda`, `coreml` or
+/// `directml`) and runtime libraries; ONNX Runtime falls back to the next
+/// provider in the list, and finally the CPU, when one can't be registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProvider {
+    Cpu,
+    /// NVIDIA GPUs
+    Cuda,
+    /// Apple Neural Engine and GPUs
+    #[serde(rename = "coreml")]
+    CoreMl,
+    /// DirectX 12 GPUs on Windows
+    #[serde(rename = "directml")]
+    DirectMl,
+}
+
+impl ExecutionProvider {
+    /// Cargo feature that compiles in support for this provider
+    pub fn feature(self) -> Option<&'static str> {
+        match self {
+            Self::Cpu => None,
+            Self::Cuda => Some("cuda"),
+            Self::CoreMl => Some("coreml"),
+            Self::DirectMl => Some("directml"),
+        }
+    }
+
+    /// Whether support for this provider was compiled in
+    pub fn is_compiled_in(self) -> bool {
+        match self {
+            Self::Cpu => true,
+            Self::Cuda => cfg!(feature = "cuda"),
+            Self::CoreMl => cfg!(feature = "coreml"),
+            Self::DirectMl => cfg!(feature = "directml"),
+        }
+    }
+
+    fn dispatch(self, device_id: i32) -> ExecutionProviderDispatch {
+        match self {
+            Self::Cpu => CPUExecutionProvider::default().build(),
+            Self::Cuda => CUDAExecutionProvider::default()
+                .with_device_id(device_id)
+                .build(),
+            Self::CoreMl => CoreMLExecutionProvider::default().build(),
+            Self::DirectMl => DirectMLExecutionProvider::default()
+                .with_device_id(device_id)
+                .build(),
+        }
+    }
+}
+
+/// File name of the embedding profile, stored beside the database file
+const PROFILE_FILE: &str = "embedding_profile.json";
+
+/// Model and text prefixes used to embed a database
+///
+/// Asymmetric models (BGE, nomic, E5) embed queries and passages with
+/// different prefixes. The profile is saved next to the database so later
+/// searches and crawls embed text exactly the way it was indexed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingProfile {
+    pub model: String,
+    pub dimension: usize,
+    #[serde(default)]
+    pub query_prefix: String,
+    #[serde(default)]
+    pub passage_prefix: String,
+}
+
+impl EmbeddingProfile {
+    fn path(db_path: &Path) -> PathBuf {
+        db_path.with_file_name(PROFILE_FILE)
+    }
+
+    /// Load the profile stored with a database, if any
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path(db_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).map_err(|e| {
+            CodeRagError::io(format!("Failed to read embedding profile {:?}", path), e)
+        })?;
+        let profile = serde_json::from_str(&content).map_err(|e| {
+            CodeRagError::Corrupt(format!(
+                "Failed to parse embedding profile {:?}: {}",
+                path, e
+            ))
+        })?;
+        Ok(Some(profile))
+    }
+
+    /// Store the profile beside a database
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let path = Self::path(db_path);
+        fs::write(&path, serde_json::to_string_pretty(self)?).map_err(|e| {
+            CodeRagError::io(format!("Failed to write embedding profile {:?}", path), e)
+        })
+    }
+}
+
+/// How far loading the embedding model has got, for reporting to clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelStatus {
+    /// Not needed yet
+    NotLoaded,
+    /// Another process is downloading the model into the same cache
+    WaitingForDownload,
+    /// Downloading one of the model's files; `total` is None when the size is unknown
+    Downloading {
+        file: String,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// Loading the model and running a first embedding
+    Loading,
+    Ready,
+    Failed(String),
+}
+
+impl ModelStatus {
+    /// Share of the current file downloaded, when its size is known
+    pub fn percent(&self) -> Option<u64> {
+        match self {
+            Self::Downloading {
+                downloaded,
+                total: Some(total),
+                ..
+            } if *total > 0 => Some(downloaded * 100 / total),
+            _ => None,
+        }
+    }
+
+    /// Whether the model is on its way but can't be used yet
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::WaitingForDownload | Self::Downloading { .. } | Self::Loading
+        )
+    }
+}
+
+impl fmt::Display for ModelStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MB: f64 = 1024.0 * 1024.0;
+        match self {
+            Self::NotLoaded => write!(f, "not loaded"),
+            Self::WaitingForDownload => write!(f, "waiting for another process to download it"),
+            Self::Downloading {
+                file,
+                downloaded,
+                total,
+            } => match (self.percent(), total) {
+                (Some(percent), Some(total)) => write!(
+                    f,
+                    "downloading {} {}% ({:.1} of {:.1} MB)",
+                    file,
+                    percent,
+                    *downloaded as f64 / MB,
+                    *total as f64 / MB
+                ),
+                _ => write!(
+                    f,
+                    "downloading {} ({:.1} MB)",
+                    file,
+                    *downloaded as f64 / MB
+                ),
+            },
+            Self::Loading => write!(f, "loading"),
+            Self::Ready => write!(f, "ready"),
+            Self::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
 
 /// Embedding service using FastEmbed with lazy initialization
 pub struct EmbeddingService {
     model: OnceCell<TextEmbedding>,
+    /// Progress of loading the model, watched by servers to keep clients informed
+    status: watch::Sender<ModelStatus>,
+    model_name: &'static str,
+    embedding_model: EmbeddingModel,
+    dimension: usize,
+    profile: EmbeddingProfile,
+    cache_dir: Option<PathBuf>,
+    /// Directory with pre-downloaded model files; the model is never downloaded when set
+    model_dir: Option<PathBuf>,
+    execution_providers: Vec<ExecutionProviderDispatch>,
+    /// Threads embedding batches in parallel; None uses rayon's global pool
+    thread_pool: Option<rayon::ThreadPool>,
 }
 
 impl Drop for EmbeddingService {
@@ -18,59 +296,368 @@ impl Drop for EmbeddingService {
 impl EmbeddingService {
     /// Create a new embedding service with lazy initialization
     pub async fn new() -> Result<Self> {
+        Self::with_config(&EmbeddingConfig::default()).await
+    }
+
+    /// Create an embedding service for the configured model and cache directory
+    pub async fn with_config(config: &EmbeddingConfig) -> Result<Self> {
+        let &(model_name, ref embedding_model, dimension, query_prefix, passage_prefix) =
+            SUPPORTED_MODELS
+                .iter()
+                .find(|(name, ..)| name.eq_ignore_ascii_case(&config.model))
+                .ok_or_else(|| {
+                    let supported: Vec<&str> =
+                        SUPPORTED_MODELS.iter().map(|(name, ..)| *name).collect();
+                    CodeRagError::Embedding(format!(
+                        "Unsupported embedding model: {}. Supported models: {}",
+                        config.model,
+                        supported.join(", ")
+                    ))
+                })?;
+
         info!("🚀 Creating FastEmbed embedding service (lazy initialization)");
-        info!("📦 Model: all-MiniLM-L6-v2 (384 dimensions)");
-        info!("💡 Model will be downloaded on first use (~90MB, 1-2 minutes)");
+        info!("📦 Model: {} ({} dimensions)", model_name, dimension);
+        match &config.model_dir {
+            Some(dir) => info!("💡 Model will be loaded from {:?} on first use", dir),
+            None => info!("💡 Model will be downloaded on first use"),
+        }
+
+        for provider in &config.execution_providers {
+            match provider.feature() {
+                Some(feature) if !provider.is_compiled_in() => warn!(
+                    "⚠️ {:?} support is not compiled in (rebuild with `--features {}`), falling back",
+                    provider, feature
+                ),
+                _ => info!("⚡ Execution provider: {:?}", provider),
+            }
+        }
+        let execution_providers = config
+            .execution_providers
+            .iter()
+            .map(|provider| provider.dispatch(config.device_id))
+            .collect();
+        let thread_pool = config
+            .threads
+            .map(|threads| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|i| format!("coderag-embed-{}", i))
+                    .build()
+            })
+            .transpose()
+            .map_err(|e| {
+                CodeRagError::Embedding(format!("Failed to start embedding threads: {}", e))
+            })?;
 
         Ok(Self {
             model: OnceCell::new(),
+            status: watch::channel(ModelStatus::NotLoaded).0,
+            model_name,
+            embedding_model: embedding_model.clone(),
+            dimension,
+            profile: EmbeddingProfile {
+                model: model_name.to_string(),
+                dimension,
+                query_prefix: query_prefix.to_string(),
+                passage_prefix: passage_prefix.to_string(),
+            },
+            cache_dir: config.cache_dir.clone(),
+            model_dir: config.model_dir.clone(),
+            execution_providers,
+            thread_pool,
         })
     }
 
+    /// Profile applied to queries and passages
+    pub fn profile(&self) -> &EmbeddingProfile {
+        &self.profile
+    }
+
+    /// Use the embedding profile stored with a database
+    ///
+    /// A database without a profile gets the current one; if it already holds
+    /// documents they were indexed without prefixes, so none are applied.
+    /// Fails if the database was indexed with a different model.
+    pub fn bind_database(&mut self, db_path: &Path, document_count: usize) -> Result<()> {
+        match EmbeddingProfile::load(db_path)? {
+            Some(stored) if stored.model != self.profile.model => {
+                return Err(CodeRagError::ModelMismatch {
+                    path: db_path.to_path_buf(),
+                    stored: stored.model,
+                    configured: self.profile.model.clone(),
+                })
+            }
+            Some(stored) => self.profile = stored,
+            None => {
+                if document_count > 0 {
+                    self.profile.query_prefix.clear();
+                    self.profile.passage_prefix.clear();
+                }
+                self.profile.save(db_path)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Ensure the model is initialized (download and load if needed)
+    ///
+    /// Concurrent callers share one initialization, and processes using the
+    /// same cache directory take turns, so the model is only downloaded once.
     async fn ensure_initialized(&self) -> Result<&TextEmbedding> {
         self.model
             .get_or_try_init(|| async {
-                info!("🔄 First embedding request - initializing FastEmbed model...");
-                info!("📥 Downloading all-MiniLM-L6-v2 model (~90MB)...");
-                info!("⏳ This may take 1-2 minutes on first run...");
+                let result = self.initialize_model().await;
+                self.status.send_replace(match &result {
+                    Ok(_) => ModelStatus::Ready,
+                    Err(e) => ModelStatus::Failed(e.to_string()),
+                });
+                result
+            })
+            .await
+    }
+
+    async fn initialize_model(&self) -> Result<TextEmbedding> {
+        info!("🔄 Initializing FastEmbed model...");
 
+        let model = match &self.model_dir {
+            Some(dir) => {
+                info!("📂 Loading {} model from {:?}", self.model_name, dir);
+                self.status.send_replace(ModelStatus::Loading);
+                self.load_local_model(dir)?
+            }
+            None => {
                 // Set cache directory
-                let cache_dir = std::env::var("FASTEMBED_CACHE_PATH")
-                    .map(std::path::PathBuf::from)
-                    .unwrap_or_else(|_| {
-                        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-                        std::path::PathBuf::from(format!("{}/.cache/fastembed", home))
-                    });
+                let cache_dir = self.cache_dir.clone().unwrap_or_else(|| {
+                    std::env::var("FASTEMBED_CACHE_PATH")
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|_| {
+                            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+                            PathBuf::from(format!("{}/.cache/fastembed", home))
+                        })
+                });
 
                 info!("📂 Using cache directory: {:?}", cache_dir);
+                let _download_lock = self.lock_cache(&cache_dir).await?;
 
-                // Try to initialize the model with better error handling
-                let model = Self::try_initialize_model(&cache_dir)?;
+                // Caches filled by earlier versions are in FastEmbed's own layout
+                let model_dir = cache_dir.join(self.model_name);
+                let fastembed_dir = cache_dir.join(format!(
+                    "models--{}",
+                    self.model_info()?.model_code.replace('/', "--")
+                ));
+                if !model_dir.exists() && fastembed_dir.exists() {
+                    self.status.send_replace(ModelStatus::Loading);
+                    // Try to initialize the model with better error handling
+                    self.try_initialize_model(&cache_dir)?
+                } else {
+                    info!("📥 Downloading {} model...", self.model_name);
+                    info!("⏳ This may take 1-2 minutes on first run...");
+                    self.download_model(&model_dir).await.map_err(|e| {
+                        error!("❌ Failed to download the embedding model: {}", e);
+                        error!("💡 Download it where the network allows with `coderag download-model`,");
+                        error!("   then point embedding.model_dir at the downloaded directory");
+                        CodeRagError::Embedding(format!(
+                            "Failed to download the {} model: {}",
+                            self.model_name, e
+                        ))
+                    })?;
+                    self.status.send_replace(ModelStatus::Loading);
+                    self.load_local_model(&model_dir)?
+                }
+            }
+        };
 
-                info!("✅ Successfully loaded all-MiniLM-L6-v2 model");
-                info!("🔄 Warming up model...");
+        info!("✅ Successfully loaded {} model", self.model_name);
+        info!("🔄 Warming up model...");
 
-                // Warm up the model with a test embedding
-                let start = std::time::Instant::now();
-                model
-                    .embed(vec!["test"], None)
-                    .map_err(|e| anyhow::anyhow!("Model warm-up failed: {}", e))?;
-                let duration = start.elapsed();
-                info!(
-                    "✅ Model fully initialized and ready (warm-up took {:?})",
-                    duration
-                );
+        // Warm up the model with a test embedding
+        let start = std::time::Instant::now();
+        model
+            .embed(vec!["test"], None)
+            .map_err(|e| CodeRagError::Embedding(format!("Model warm-up failed: {}", e)))?;
+        let duration = start.elapsed();
+        info!(
+            "✅ Model fully initialized and ready (warm-up took {:?})",
+            duration
+        );
 
-                Ok(model)
-            })
+        Ok(model)
+    }
+
+    /// Take the download lock of a cache directory, waiting while another process holds it
+    async fn lock_cache(&self, cache_dir: &Path) -> Result<File> {
+        fs::create_dir_all(cache_dir).map_err(|e| {
+            CodeRagError::io(
+                format!(
+                    "Failed to create cache directory {:?} (set FASTEMBED_CACHE_PATH to a writable directory)",
+                    cache_dir
+                ),
+                e,
+            )
+        })?;
+        let path = cache_dir.join(format!(".{}.lock", self.model_name));
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| CodeRagError::io(format!("Failed to open lock file {:?}", path), e))?;
+
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => {
+                return Err(CodeRagError::io(format!("Failed to lock {:?}", path), e))
+            }
+        }
+        info!(
+            "⏳ Another process is downloading the {} model, waiting for it",
+            self.model_name
+        );
+        self.status.send_replace(ModelStatus::WaitingForDownload);
+        tokio::task::spawn_blocking(move || file.lock().map(|()| file))
             .await
+            .map_err(|e| {
+                CodeRagError::Embedding(format!("Waiting for the download failed: {}", e))
+            })?
+            .map_err(|e| CodeRagError::io(format!("Failed to lock {:?}", path), e))
+    }
+
+    /// Download and load the model now rather than on the first embedding request
+    pub async fn warm_up(&self) -> Result<()> {
+        self.ensure_initialized().await.map(|_| ())
+    }
+
+    /// Whether the model has been loaded
+    pub fn is_ready(&self) -> bool {
+        self.model.initialized()
+    }
+
+    /// Follow the model's loading progress
+    pub fn status(&self) -> watch::Receiver<ModelStatus> {
+        self.status.subscribe()
+    }
+
+    /// FastEmbed's description of the model: repository and file names
+    fn model_info(&self) -> Result<&ModelInfo<EmbeddingModel>> {
+        TextEmbedding::get_model_info(&self.embedding_model)
+            .map_err(|e| CodeRagError::Embedding(e.to_string()))
+    }
+
+    /// Load the model from files laid out like its Hugging Face repository
+    fn load_local_model(&self, dir: &Path) -> Result<TextEmbedding> {
+        let read = |file: &str| {
+            let path = dir.join(file);
+            fs::read(&path).map_err(|e| {
+                CodeRagError::io(
+                    format!(
+                        "Failed to read model file {:?} (fetch the files with `coderag download-model`)",
+                        path
+                    ),
+                    e
+                )
+            })
+        };
+
+        let [tokenizer, config, special_tokens_map, tokenizer_config] = TOKENIZER_FILES;
+        let tokenizer_files = TokenizerFiles {
+            tokenizer_file: read(tokenizer)?,
+            config_file: read(config)?,
+            special_tokens_map_file: read(special_tokens_map)?,
+            tokenizer_config_file: read(tokenizer_config)?,
+        };
+        let mut model =
+            UserDefinedEmbeddingModel::new(read(&self.model_info()?.model_file)?, tokenizer_files)
+                .with_quantization(TextEmbedding::get_quantization_mode(&self.embedding_model));
+        if let Some(pooling) = TextEmbedding::get_default_pooling_method(&self.embedding_model) {
+            model = model.with_pooling(pooling);
+        }
+
+        let options = InitOptionsUserDefined::new()
+            .with_execution_providers(self.execution_providers.clone());
+        TextEmbedding::try_new_from_user_defined(model, options).map_err(|e| {
+            CodeRagError::Embedding(format!(
+                "Failed to load {} model from {:?}: {}",
+                self.model_name, dir, e
+            ))
+        })
+    }
+
+    /// Download the model's files into a directory usable as `embedding.model_dir`
+    ///
+    /// Files already present are kept, so an interrupted download can be resumed.
+    /// Progress is published through [`status`](Self::status). Returns the paths
+    /// of the model's files.
+    pub async fn download_model(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let info = self.model_info()?;
+        let endpoint = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| HF_ENDPOINT.to_string());
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("CodeRAG/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let files = std::iter::once(info.model_file.as_str())
+            .chain(info.additional_files.iter().map(String::as_str))
+            .chain(TOKENIZER_FILES);
+        let mut paths = Vec::new();
+        for file in files {
+            let path = dir.join(file);
+            paths.push(path.clone());
+            if path.exists() {
+                info!("✅ {} already downloaded", file);
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    CodeRagError::io(format!("Failed to create directory {:?}", parent), e)
+                })?;
+            }
+
+            let url = format!(
+                "{}/{}/resolve/main/{}",
+                endpoint.trim_end_matches('/'),
+                info.model_code,
+                file
+            );
+            info!("📥 Downloading {}", url);
+            let mut response = client.get(&url).send().await?.error_for_status()?;
+
+            // Write to a temporary file so a partial download isn't mistaken for a complete one
+            let partial = dir.join(format!("{}.part", file));
+            let mut out = fs::File::create(&partial)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", partial), e))?;
+            let total = response.content_length();
+            let mut downloaded = 0;
+            let mut reported = None;
+            while let Some(chunk) = response.chunk().await? {
+                out.write_all(&chunk)
+                    .map_err(|e| CodeRagError::io(format!("Failed to write {:?}", partial), e))?;
+                downloaded += chunk.len() as u64;
+
+                // Publish each percent, or each megabyte when the size is unknown
+                let status = ModelStatus::Downloading {
+                    file: file.to_string(),
+                    downloaded,
+                    total,
+                };
+                let step = status.percent().unwrap_or(downloaded >> 20);
+                if reported != Some(step) {
+                    reported = Some(step);
+                    self.status.send_replace(status);
+                }
+            }
+            fs::rename(&partial, &path).map_err(|e| {
+                CodeRagError::io(format!("Failed to move {:?} to {:?}", partial, path), e)
+            })?;
+        }
+
+        Ok(paths)
     }
 
     /// Try to initialize the FastEmbed model with comprehensive error handling
-    fn try_initialize_model(cache_dir: &std::path::Path) -> Result<TextEmbedding> {
-        let init_options =
-            InitOptions::new(EmbeddingModel::AllMiniLML6V2).with_cache_dir(cache_dir.to_path_buf());
+    fn try_initialize_model(&self, cache_dir: &std::path::Path) -> Result<TextEmbedding> {
+        let init_options = InitOptions::new(self.embedding_model.clone())
+            .with_cache_dir(cache_dir.to_path_buf())
+            .with_execution_providers(self.execution_providers.clone());
 
         match TextEmbedding::try_new(init_options) {
             Ok(model) => Ok(model),
@@ -95,99 +682,121 @@ impl EmbeddingService {
                     error!(
                         "   4. Contact your network administrator if behind a corporate firewall"
                     );
+                    error!(
+                        "   5. Download it where the network allows with `coderag download-model`,"
+                    );
+                    error!("      then point embedding.model_dir at the downloaded directory");
 
-                    Err(anyhow::anyhow!(
+                    Err(CodeRagError::Embedding(
                         "Failed to download FastEmbed model. This appears to be a network connectivity issue. \
                         The model download works in local environments but may fail in restricted environments like Claude Desktop. \
                         Try running the crawler directly first: `cargo run --release --bin coderag-mcp crawl https://example.com`"
+                            .to_string(),
                     ))
                 } else if error_msg.contains("permission") || error_msg.contains("access") {
                     error!("🔒 Permission Error: Unable to write to cache directory");
                     error!("📂 Cache directory: {:?}", cache_dir);
                     error!("💡 Try setting FASTEMBED_CACHE_PATH to a writable directory");
 
-                    Err(anyhow::anyhow!(
+                    Err(CodeRagError::Embedding(format!(
                         "Permission denied writing to cache directory: {:?}. \
                         Set FASTEMBED_CACHE_PATH environment variable to a writable directory.",
                         cache_dir
-                    ))
+                    )))
                 } else {
                     error!("❓ Unexpected error during model initialization");
                     error!("📝 Error details: {}", e);
 
-                    Err(anyhow::anyhow!("FastEmbed initialization failed: {}", e))
+                    Err(CodeRagError::Embedding(format!(
+                        "FastEmbed initialization failed: {}",
+                        e
+                    )))
                 }
             }
         }
     }
 
-    /// Generate embedding for a single text
+    /// Embed a search query with the profile's query prefix
+    pub async fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&format!("{}{}", self.profile.query_prefix, text))
+            .await
+    }
+
+    /// Embed a document chunk with the profile's passage prefix
+    pub async fn embed_passage(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&format!("{}{}", self.profile.passage_prefix, text))
+            .await
+    }
+
+    /// Embed document chunks with the profile's passage prefix, batched for throughput
+    pub async fn embed_passages(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(
+            texts
+                .iter()
+                .map(|text| format!("{}{}", self.profile.passage_prefix, text))
+                .collect(),
+        )
+        .await
+    }
+
+    /// Generate embedding for a single text, without prefixes
     pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
         let embeddings = self.embed_batch(vec![text.to_string()]).await?;
         embeddings
             .into_iter()
             .next()
-            .ok_or_else(|| anyhow::anyhow!("No embedding generated"))
+            .ok_or_else(|| CodeRagError::Embedding("No embedding generated".to_string()))
     }
 
     /// Generate embeddings for multiple texts
+    #[tracing::instrument(skip_all, fields(texts = texts.len(), elapsed_ms))]
     pub async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
         // Ensure model is initialized
-        let model = self
-            .ensure_initialized()
-            .await
-            .context("Failed to initialize embedding model")?;
-
-        // Generate embeddings for each text
-        let mut all_embeddings = Vec::new();
+        let model = self.ensure_initialized().await?;
+        let started = std::time::Instant::now();
 
-        for text in &texts {
-            debug!(
-                "🔄 Generating embedding for: '{}'",
-                if text.len() > 50 {
-                    format!("{}...", &text[..50])
-                } else {
-                    text.clone()
-                }
-            );
+        debug!("🔄 Generating embeddings for {} texts", texts.len());
 
-            let embeddings = model.embed(vec![text.as_str()], None).with_context(|| {
-                format!(
-                    "Failed to generate embedding for text: {}",
-                    if text.len() > 100 {
-                        format!("{}...", &text[..100])
-                    } else {
-                        text.clone()
-                    }
-                )
-            })?;
-
-            if let Some(embedding) = embeddings.first() {
-                debug!("✅ Generated embedding: {} dimensions", embedding.len());
-                all_embeddings.push(embedding.clone());
-            } else {
-                return Err(anyhow::anyhow!("No embedding generated for text"));
-            }
+        // FastEmbed splits the texts into batches and embeds them in parallel on
+        // the current rayon pool
+        let embed = || model.embed(texts.iter().map(String::as_str).collect(), None);
+        let all_embeddings = match &self.thread_pool {
+            Some(pool) => pool.install(embed),
+            None => embed(),
+        }
+        .map_err(|e| CodeRagError::Embedding(format!("Failed to generate embeddings: {}", e)))?;
+        if all_embeddings.len() != texts.len() {
+            return Err(CodeRagError::Embedding(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                all_embeddings.len()
+            )));
         }
 
+        let elapsed = telemetry::record_elapsed(started);
+        telemetry::record_embedding_batch(texts.len(), elapsed);
         Ok(all_embeddings)
     }
 
     /// Get the embedding dimension - useful for validation and debugging
     #[allow(dead_code)]
     pub fn dimension(&self) -> usize {
-        384 // all-MiniLM-L6-v2 produces 384-dimensional embeddings
+        self.dimension
     }
 
     /// Validate that an embedding has the correct dimensions
     #[allow(dead_code)]
-    pub fn validate_embedding(&self, embedding: &[f32]) -> anyhow::Result<()> {
+    pub fn validate_embedding(&self, embedding: &[f32]) -> Result<()> {
         if embedding.len() != self.dimension() {
-            anyhow::bail!(
-                "Invalid embedding dimension: expected {}, got {}",
-                self.dimension(),
-                embedding.len()
-            );
+            return Err(CodeRagError::DimensionMismatch {
+                expected: self.dimension(),
+                actual: embedding.len(),
+                message: format!(
+                    "Invalid embedding dimension: expected {}, got {}",
+                    self.dimension(),
+                    embedding.len()
+                ),
+            });
         }
         Ok(())
     }
@@ -196,6 +805,7 @@ impl EmbeddingService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
 
     #[tokio::test]
     async fn test_embedding_service() -> Result<()> {
@@ -204,4 +814,81 @@ mod tests {
         assert_eq!(embedding.len(), 384);
         Ok(())
     }
+
+    #[test]
+    fn test_model_status_progress() {
+        let status = ModelStatus::Downloading {
+            file: "model.onnx".to_string(),
+            downloaded: 29 << 20,
+            total: Some(67 << 20),
+        };
+        assert_eq!(status.percent(), Some(43));
+        assert!(status.is_pending());
+        assert_eq!(
+            status.to_string(),
+            "downloading model.onnx 43% (29.0 of 67.0 MB)"
+        );
+
+        let unknown_size = ModelStatus::Downloading {
+            file: "tokenizer.json".to_string(),
+            downloaded: 3 << 19,
+            total: None,
+        };
+        assert_eq!(unknown_size.percent(), None);
+        assert_eq!(
+            unknown_size.to_string(),
+            "downloading tokenizer.json (1.5 MB)"
+        );
+        assert!(!ModelStatus::Ready.is_pending());
+    }
+
+    #[tokio::test]
+    async fn test_bind_database_profile() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let config = |model: &str| EmbeddingConfig {
+            model: model.to_string(),
+            ..EmbeddingConfig::default()
+        };
+
+        // A new database records the model's prefixes
+        let new_db = temp_dir.path().join("new").join("vectordb.json");
+        fs::create_dir_all(new_db.parent().unwrap())?;
+        let mut service = EmbeddingService::with_config(&config("nomic-embed-text-v1.5")).await?;
+        service.bind_database(&new_db, 0)?;
+        let stored = EmbeddingProfile::load(&new_db)?.unwrap();
+        assert_eq!(stored.passage_prefix, "search_document: ");
+
+        // A populated database without a profile was indexed without prefixes
+        let legacy_db = temp_dir.path().join("vectordb.json");
+        let mut service = EmbeddingService::with_config(&config("bge-small-en-v1.5")).await?;
+        assert!(!service.profile().query_prefix.is_empty());
+        service.bind_database(&legacy_db, 10)?;
+        assert!(service.profile().query_prefix.is_empty());
+
+        // The stored profile wins over the model defaults
+        let mut service = EmbeddingService::with_config(&config("bge-small-en-v1.5")).await?;
+        service.bind_database(&legacy_db, 10)?;
+        assert!(service.profile().query_prefix.is_empty());
+
+        // A different model is rejected
+        let mut service = EmbeddingService::with_config(&config("all-MiniLM-L6-v2")).await?;
+        assert!(service.bind_database(&legacy_db, 10).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_dir_missing_files() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let service = EmbeddingService::with_config(&EmbeddingConfig {
+            model_dir: Some(temp_dir.path().to_path_buf()),
+            ..EmbeddingConfig::default()
+        })
+        .await?;
+
+        // An empty model directory fails without trying to download the model
+        let err = service.embed("test text").await.unwrap_err();
+        assert!(matches!(err, CodeRagError::Io { .. }));
+        assert!(err.to_string().contains("coderag download-model"));
+        Ok(())
+    }
 }
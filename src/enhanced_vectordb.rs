@@ -5,11 +5,11 @@
 //! document chunking.
 
 use crate::embedding_basic::EmbeddingService;
+use crate::error::Result;
 use crate::vectordb::{
     ChunkingStrategy, Document, DocumentMetadata, EnhancedChunker, HnswParams, HybridSearchOptions,
     QuantizationMethod, VectorDatabase,
 };
-use anyhow::Result;
 use std::path::Path;
 use tracing::{debug, info};
 
@@ -109,16 +109,13 @@ impl EnhancedVectorDbService {
 
         let mut document_ids = Vec::with_capacity(total_chunks);
 
-        // Process each chunk
-        for (i, chunk) in chunks.into_iter().enumerate() {
-            // Generate embedding
-            debug!(
-                "Generating embedding for chunk {} (size: {} bytes)",
-                i + 1,
-                chunk.content.len()
-            );
-            let embedding = embedding_service.embed(&chunk.content).await?;
+        // Embed all chunks in one batch
+        debug!("Generating embeddings for {} chunks", total_chunks);
+        let contents: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let embeddings = embedding_service.embed_passages(&contents).await?;
 
+        // Process each chunk
+        for ((i, chunk), embedding) in chunks.into_iter().enumerate().zip(embeddings) {
             // Create document
             let doc_id = format!("{}_{}", url, i);
             let document = Document {
@@ -130,6 +127,7 @@ impl EnhancedVectorDbService {
                 metadata: DocumentMetadata {
                     content_type,
                     language: None, // Could be detected
+                    lang: None,
                     last_updated: Some(std::time::SystemTime::now()),
                     tags: vec![
                         if chunk.has_code {
@@ -140,6 +138,18 @@ impl EnhancedVectorDbService {
                         .to_string(),
                         format!("chunk-{}-of-{}", i + 1, total_chunks),
                     ],
+                    anchor: None,
+                    heading_path: Vec::new(),
+                    boost: None,
+                    links: Vec::new(),
+                    aliases: Vec::new(),
+                    simhash: None,
+                    version: None,
+                    release_date: None,
+                    votes: None,
+                    images: Vec::new(),
+                    canonical: None,
+                    provenance: None,
                 },
             };
 
@@ -160,7 +170,7 @@ impl EnhancedVectorDbService {
     ) -> Result<Vec<Document>> {
         // Generate embedding for query
         debug!("Generating embedding for query: {}", query);
-        let query_embedding = embedding_service.embed(query).await?;
+        let query_embedding = embedding_service.embed_query(query).await?;
 
         // Prepare hybrid search options
         let options = HybridSearchOptions {
@@ -169,11 +179,22 @@ impl EnhancedVectorDbService {
                 min_score: Some(0.1), // Minimum similarity threshold
                 source_filter: None,
                 content_type_filter: None,
+                ef_search: None,
+                allowed_urls: None,
+                lang_filter: None,
+                section_filter: None,
+                version_filter: None,
+                budget: None,
+                source_weights: Vec::new(),
+                recency_half_life_days: None,
+                exclude_sources: Vec::new(),
+                exclude_tags: Vec::new(),
             },
             enable_hybrid: true,
             vector_weight: 0.7,
             keyword_weight: 0.3,
             keyword_params: crate::vectordb::KeywordSearchParams::default(),
+            ..HybridSearchOptions::default()
         };
 
         // Perform hybrid search
@@ -0,0 +1,155 @@
+//! Error type for the library's vector database, crawler and embedding APIs.
+//!
+//! Variants separate the failures an embedder may want to handle differently:
+//! a network error is worth retrying, a dimension or model mismatch needs a
+//! different database, and corruption needs `--repair` or a backup. Settings,
+//! data directories, backups and the other library modules report through it
+//! too; only the binaries wrap it in `anyhow` for reporting.
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result type of the library APIs
+pub type Result<T, E = CodeRagError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum CodeRagError {
+    /// Reading or writing a file failed
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// An HTTP request failed
+    #[error("Request failed: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// The server answered 429 Too Many Requests
+    #[error("Rate limited")]
+    RateLimited,
+
+    /// Replaying HTTP fixtures, a request had no recorded response
+    #[cfg(feature = "test-fixtures")]
+    #[error("No recorded response for {0}")]
+    MissingFixture(String),
+
+    /// A page was fetched but can't be indexed (binary or unsupported content)
+    #[error("Skipping {0}")]
+    UnsupportedContent(String),
+
+    /// A URL could not be parsed
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    /// A value such as a setting or a version constraint could not be parsed
+    #[error("{0}")]
+    Parse(String),
+
+    /// A configuration file or environment variable is invalid
+    #[error("{0}")]
+    Config(String),
+
+    /// A project manifest such as Cargo.toml or package.json couldn't be parsed
+    #[error("Failed to parse {path:?}: {message}")]
+    Manifest { path: PathBuf, message: String },
+
+    /// Input given to an API, such as an import file or benchmark suite, is invalid
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// A source or backup named by the caller doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// A SQLite database file couldn't be read or written
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    /// Writing a Parquet export failed
+    #[cfg(feature = "parquet")]
+    #[error("Parquet export failed: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// Building the Arrow columns of a Parquet export failed
+    #[cfg(feature = "parquet")]
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    /// Serializing data failed
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    /// A vector doesn't have the dimension the database or index expects
+    #[error("{message}")]
+    DimensionMismatch {
+        expected: usize,
+        actual: usize,
+        message: String,
+    },
+
+    /// The database was indexed with a different embedding model
+    #[error(
+        "Database {path:?} was indexed with embedding model {stored}, but {configured} is configured. \
+        Set embedding.model = \"{stored}\" or use a new database."
+    )]
+    ModelMismatch {
+        path: PathBuf,
+        stored: String,
+        configured: String,
+    },
+
+    /// The database file was written by an incompatible storage version
+    #[error("Storage version mismatch: expected {expected}, found {found}")]
+    StorageVersion { expected: u32, found: u32 },
+
+    /// The data directory was written by a newer CodeRAG
+    #[error(
+        "Data directory {path:?} uses layout version {found}, but this CodeRAG only knows version {supported}; upgrade CodeRAG"
+    )]
+    LayoutVersion {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+
+    /// The request needs a feature this build doesn't include
+    #[error("{0}")]
+    Unsupported(String),
+
+    /// The database file or an entry in it is damaged
+    #[error("{0}")]
+    Corrupt(String),
+
+    /// Another instance holds the database's write lock
+    #[error("Database {0:?} is locked by another CodeRAG instance; open it read-only instead")]
+    Locked(PathBuf),
+
+    /// No document with this ID
+    #[error("Document not found: {0}")]
+    DocumentNotFound(String),
+
+    /// The HNSW index or external vector store is inconsistent or can't serve the request
+    #[error("{0}")]
+    Index(String),
+
+    /// Quantizing or decoding a vector failed
+    #[error("{0}")]
+    Quantization(String),
+
+    /// The embedding model failed to load or to embed text
+    #[error("{0}")]
+    Embedding(String),
+}
+
+impl CodeRagError {
+    /// Wrap an I/O error with what was being done
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
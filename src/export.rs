@@ -0,0 +1,400 @@
+//! NDJSON export and import of documents.
+//!
+//! `coderag export` writes one JSON object per line for every document a
+//! filter matches, streaming them so exports of large databases don't have to
+//! fit in memory. Lines hold the document as stored plus `indexed_at`, and its
+//! `embedding` when vectors are requested, ready for analytics or building
+//! fine-tuning datasets.
+//!
+//! `coderag import-embeddings` reads the same format back, or the shorter
+//! [`EmbeddingRecord`] lines an external pipeline can produce, and adds the
+//! documents with their precomputed embeddings instead of embedding them again.
+
+use crate::crawler::chunker::chunk_id;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{ContentType, Document, DocumentMetadata, VectorDatabase};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::time::SystemTime;
+
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "parquet")]
+pub use self::parquet::write_parquet;
+
+/// Which documents an export includes
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// URL prefix of the pages to export
+    pub source: Option<String>,
+    pub content_type: Option<ContentType>,
+    /// Only documents updated or indexed at or after this time
+    pub since: Option<SystemTime>,
+    /// Only documents updated or indexed before this time
+    pub until: Option<SystemTime>,
+}
+
+impl ExportFilter {
+    pub fn matches(&self, document: &Document, indexed_at: SystemTime) -> bool {
+        let updated = document.metadata.last_updated.unwrap_or(indexed_at);
+        self.source
+            .as_ref()
+            .is_none_or(|source| document.url.starts_with(source.as_str()))
+            && self
+                .content_type
+                .is_none_or(|content_type| document.metadata.content_type == content_type)
+            && self.since.is_none_or(|since| updated >= since)
+            && self.until.is_none_or(|until| updated < until)
+    }
+}
+
+/// One line of an export
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    #[serde(flatten)]
+    document: &'a Document,
+    /// RFC 3339 time the document was indexed
+    indexed_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding: Option<Vec<f32>>,
+}
+
+/// Write the documents a filter matches as NDJSON, returning how many were written
+pub fn write_ndjson(
+    db: &VectorDatabase,
+    filter: &ExportFilter,
+    with_vectors: bool,
+    mut writer: impl Write,
+) -> Result<usize> {
+    let mut written = 0;
+    for exported in db.stream_documents_where(|doc, at| filter.matches(doc, at), with_vectors) {
+        let (document, indexed_at, embedding) = exported?;
+        let record = ExportRecord {
+            document,
+            indexed_at: DateTime::<Utc>::from(indexed_at).to_rfc3339(),
+            embedding,
+        };
+        serde_json::to_writer(&mut writer, &record)?;
+        writer
+            .write_all(b"\n")
+            .map_err(|e| CodeRagError::io("Failed to write export", e))?;
+        written += 1;
+    }
+    writer
+        .flush()
+        .map_err(|e| CodeRagError::io("Failed to write export", e))?;
+    Ok(written)
+}
+
+/// A document with a precomputed embedding, one NDJSON line of an import
+///
+/// Lines written by `coderag export --vectors` carry the full `metadata`;
+/// other pipelines can give just `content_type` and `tags`.
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingRecord {
+    /// Document ID (derived from the URL and content when missing)
+    pub id: Option<String>,
+    pub url: String,
+    pub content: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<DocumentMetadata>,
+    /// Content type name as accepted by [`parse_content_type`] (default "documentation")
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub embedding: Vec<f32>,
+    /// Embedding model that produced `embedding`, checked when given
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl EmbeddingRecord {
+    fn into_document(self) -> Result<(Document, Vec<f32>)> {
+        let metadata = match self.metadata {
+            Some(metadata) => metadata,
+            None => DocumentMetadata {
+                content_type: match &self.content_type {
+                    Some(name) => parse_content_type(name)?,
+                    None => ContentType::Documentation,
+                },
+                language: None,
+                lang: None,
+                last_updated: None,
+                tags: self.tags,
+                anchor: None,
+                heading_path: Vec::new(),
+                boost: None,
+                links: Vec::new(),
+                aliases: Vec::new(),
+                simhash: None,
+                version: None,
+                release_date: None,
+                votes: None,
+                images: Vec::new(),
+                canonical: None,
+                provenance: None,
+            },
+        };
+        let id = self
+            .id
+            .unwrap_or_else(|| chunk_id(&self.url, metadata.anchor.as_deref(), &self.content));
+        let document = Document {
+            id,
+            content: self.content,
+            url: self.url,
+            title: self.title,
+            section: self.section,
+            metadata,
+        };
+        Ok((document, self.embedding))
+    }
+}
+
+/// Add the documents of an NDJSON import, returning how many were added
+///
+/// Every embedding must have `dimension` values and every line naming a model
+/// must name `model`. Nothing is added unless all lines are valid; the caller
+/// saves the database.
+pub fn read_ndjson(
+    db: &mut VectorDatabase,
+    reader: impl BufRead,
+    model: &str,
+    dimension: usize,
+) -> Result<usize> {
+    let mut documents = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let number = index + 1;
+        let line =
+            line.map_err(|e| CodeRagError::io(format!("Failed to read line {}", number), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid =
+            |message: String| CodeRagError::InvalidInput(format!("Line {}: {}", number, message));
+        let record: EmbeddingRecord = serde_json::from_str(&line)
+            .map_err(|e| invalid(format!("not a document with an embedding: {}", e)))?;
+        if let Some(record_model) = record.model.as_deref() {
+            if !record_model.eq_ignore_ascii_case(model) {
+                return Err(invalid(format!(
+                    "embedded with {}, but the database uses {}",
+                    record_model, model
+                )));
+            }
+        }
+        if record.embedding.len() != dimension {
+            return Err(CodeRagError::DimensionMismatch {
+                expected: dimension,
+                actual: record.embedding.len(),
+                message: format!(
+                    "Line {}: embedding has {} dimensions, but {} produces {}",
+                    number,
+                    record.embedding.len(),
+                    model,
+                    dimension
+                ),
+            });
+        }
+        if record.embedding.iter().any(|value| !value.is_finite()) {
+            return Err(invalid(
+                "embedding contains NaN or infinite values".to_string(),
+            ));
+        }
+        documents.push(record.into_document().map_err(|e| invalid(e.to_string()))?);
+    }
+
+    let count = documents.len();
+    for (document, embedding) in documents {
+        db.add_document(document, embedding)?;
+    }
+    Ok(count)
+}
+
+/// Parse a content type name, e.g. "tutorial" or "code_example" ("code" and "api" also work)
+pub fn parse_content_type(name: &str) -> Result<ContentType> {
+    Ok(match name.to_lowercase().replace('-', "_").as_str() {
+        "documentation" | "docs" => ContentType::Documentation,
+        "code_example" | "code" => ContentType::CodeExample,
+        "tutorial" => ContentType::Tutorial,
+        "reference" | "api" => ContentType::Reference,
+        "blog_post" | "blog" => ContentType::BlogPost,
+        "other" => ContentType::Other,
+        _ => {
+            return Err(CodeRagError::InvalidInput(format!(
+                "Unknown content type {:?}; expected documentation, code_example, tutorial, reference, blog_post or other",
+                name
+            )))
+        }
+    })
+}
+
+/// Parse a date ("2025-03-14", midnight UTC) or an RFC 3339 time
+pub fn parse_time(value: &str) -> Result<SystemTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc().into());
+    }
+    match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => Ok(time.into()),
+        Err(_) => Err(CodeRagError::InvalidInput(format!(
+            "Invalid date {:?}; use YYYY-MM-DD or an RFC 3339 time",
+            value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::DocumentMetadata;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn document(id: &str, url: &str, content_type: ContentType, updated: u64) -> Document {
+        Document::for_test(id)
+            .with_url(url)
+            .with_metadata(DocumentMetadata {
+                content_type,
+                last_updated: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(updated)),
+                ..Default::default()
+            })
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let dir = TempDir::new().unwrap();
+        let mut db = VectorDatabase::new(dir.path().join("vectors.json")).unwrap();
+        let day = 24 * 60 * 60;
+        for (doc, embedding) in [
+            (
+                document(
+                    "spawn",
+                    "https://docs.rs/tokio/spawn",
+                    ContentType::Reference,
+                    10 * day,
+                ),
+                vec![1.0, 0.0],
+            ),
+            (
+                document(
+                    "guide",
+                    "https://tokio.rs/tutorial",
+                    ContentType::Tutorial,
+                    20 * day,
+                ),
+                vec![0.0, 1.0],
+            ),
+            (
+                document(
+                    "select",
+                    "https://docs.rs/tokio/select",
+                    ContentType::CodeExample,
+                    30 * day,
+                ),
+                vec![0.6, 0.8],
+            ),
+        ] {
+            db.add_document(doc, embedding).unwrap();
+        }
+
+        let export = |filter: &ExportFilter, with_vectors: bool| {
+            let mut out = Vec::new();
+            let written = write_ndjson(&db, filter, with_vectors, &mut out).unwrap();
+            let lines: Vec<serde_json::Value> = String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            assert_eq!(written, lines.len());
+            lines
+        };
+
+        let all = export(&ExportFilter::default(), false);
+        assert_eq!(all.len(), 3);
+        assert!(all[0].get("embedding").is_none());
+        assert!(all[0]["indexed_at"].is_string());
+
+        let filter = ExportFilter {
+            source: Some("https://docs.rs/tokio/".to_string()),
+            since: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(15 * day)),
+            ..ExportFilter::default()
+        };
+        let lines = export(&filter, true);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["id"], "select");
+        assert_eq!(lines[0]["embedding"].as_array().unwrap().len(), 2);
+
+        let filter = ExportFilter {
+            content_type: Some(parse_content_type("tutorial").unwrap()),
+            until: Some(parse_time("1970-01-25").unwrap()),
+            ..ExportFilter::default()
+        };
+        assert_eq!(export(&filter, false)[0]["id"], "guide");
+    }
+
+    #[test]
+    fn test_read_ndjson() {
+        let dir = TempDir::new().unwrap();
+        let mut source = VectorDatabase::new(dir.path().join("source.json")).unwrap();
+        source
+            .add_document(
+                document(
+                    "spawn",
+                    "https://docs.rs/tokio/spawn",
+                    ContentType::Reference,
+                    0,
+                ),
+                vec![0.6, 0.8],
+            )
+            .unwrap();
+        let mut exported = Vec::new();
+        write_ndjson(&source, &ExportFilter::default(), true, &mut exported).unwrap();
+        let external = r#"{"url": "https://serde.rs/derive.html", "content": "Derive Serialize", "content_type": "tutorial", "embedding": [1.0, 0.0], "model": "all-MiniLM-L6-v2"}"#;
+        let input = format!("{}\n{}\n", String::from_utf8(exported).unwrap(), external);
+
+        let mut db = VectorDatabase::new(dir.path().join("vectors.json")).unwrap();
+        let imported = read_ndjson(&mut db, input.as_bytes(), "all-MiniLM-L6-v2", 2).unwrap();
+        assert_eq!(imported, 2);
+        assert_eq!(
+            db.get_document("spawn").unwrap().metadata.content_type,
+            ContentType::Reference
+        );
+        let derived = db
+            .get_documents_by_url("https://serde.rs/derive.html")
+            .pop()
+            .unwrap();
+        assert!(derived.id.starts_with("https://serde.rs/derive.html_"));
+        assert_eq!(derived.metadata.content_type, ContentType::Tutorial);
+
+        // Invalid lines reject the whole import
+        let mut db = VectorDatabase::new(dir.path().join("other.json")).unwrap();
+        let error = read_ndjson(&mut db, input.as_bytes(), "all-MiniLM-L6-v2", 384).unwrap_err();
+        assert!(matches!(error, CodeRagError::DimensionMismatch { .. }));
+        assert!(error
+            .to_string()
+            .starts_with("Line 1: embedding has 2 dimensions"));
+        let error = read_ndjson(&mut db, input.as_bytes(), "bge-small-en-v1.5", 2).unwrap_err();
+        assert!(matches!(error, CodeRagError::InvalidInput(_)));
+        assert!(error.to_string().contains("embedded with all-MiniLM-L6-v2"));
+        assert_eq!(db.document_count(), 0);
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(parse_content_type("api").unwrap(), ContentType::Reference);
+        assert_eq!(
+            parse_content_type("Code-Example").unwrap(),
+            ContentType::CodeExample
+        );
+        assert!(parse_content_type("video").is_err());
+        assert_eq!(
+            parse_time("2025-03-14").unwrap(),
+            parse_time("2025-03-14T00:00:00Z").unwrap()
+        );
+        assert!(parse_time("14/03/2025").is_err());
+    }
+}
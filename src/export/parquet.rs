@@ -0,0 +1,250 @@
+//! Parquet export of documents, with the `parquet` feature.
+//!
+//! The file has one row per document with its text, the metadata useful for
+//! analysis, and with vectors its embedding as a fixed-size list column, so
+//! pandas, Polars or DuckDB load exactly the indexed data.
+
+use super::ExportFilter;
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::{Document, VectorDatabase};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, StringArray, TimestampMillisecondArray,
+};
+use arrow_schema::{DataType, Field};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Rows converted and written at a time
+const BATCH_ROWS: usize = 1024;
+
+type Row<'a> = (&'a Document, SystemTime, Option<Vec<f32>>);
+
+/// Write the documents a filter matches as Parquet, returning how many were written
+pub fn write_parquet(
+    db: &VectorDatabase,
+    filter: &ExportFilter,
+    with_vectors: bool,
+    writer: impl Write + Send,
+) -> Result<usize> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    // The schema needs the embedding dimension, so the writer starts with the first batch
+    let mut writer = Some(writer);
+    let mut parquet: Option<ArrowWriter<_>> = None;
+    let mut rows: Vec<Row> = Vec::with_capacity(BATCH_ROWS);
+    let mut written = 0;
+
+    let mut documents = db.stream_documents_where(|doc, at| filter.matches(doc, at), with_vectors);
+    loop {
+        rows.clear();
+        for exported in documents.by_ref().take(BATCH_ROWS) {
+            rows.push(exported?);
+        }
+        // An empty export still writes a file with the schema
+        if rows.is_empty() && parquet.is_some() {
+            break;
+        }
+        let batch = record_batch(&rows, with_vectors)?;
+        if let Some(writer) = writer.take() {
+            parquet = Some(ArrowWriter::try_new(
+                writer,
+                batch.schema(),
+                Some(properties.clone()),
+            )?);
+        }
+        if let Some(parquet) = parquet.as_mut() {
+            parquet.write(&batch)?;
+        }
+        written += rows.len();
+        if rows.len() < BATCH_ROWS {
+            break;
+        }
+    }
+    if let Some(parquet) = parquet {
+        parquet.close()?;
+    }
+    Ok(written)
+}
+
+/// Columns of a batch of rows
+fn record_batch(rows: &[Row], with_vectors: bool) -> Result<RecordBatch> {
+    let strings = |value: fn(&Document) -> Option<&str>| -> ArrayRef {
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|(doc, ..)| value(doc)),
+        ))
+    };
+    let lists = |values: fn(&Document) -> &[String]| -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        for (doc, ..) in rows {
+            builder.append_value(values(doc).iter().map(Some));
+        }
+        Arc::new(builder.finish())
+    };
+    let millis = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as i64)
+    };
+
+    let mut columns: Vec<(&str, ArrayRef)> = vec![
+        ("id", strings(|doc| Some(&doc.id))),
+        ("url", strings(|doc| Some(&doc.url))),
+        ("title", strings(|doc| doc.title.as_deref())),
+        ("section", strings(|doc| doc.section.as_deref())),
+        ("content", strings(|doc| Some(&doc.content))),
+        (
+            "content_type",
+            Arc::new(StringArray::from_iter_values(
+                rows.iter()
+                    .map(|(doc, ..)| format!("{:?}", doc.metadata.content_type)),
+            )),
+        ),
+        ("language", strings(|doc| doc.metadata.language.as_deref())),
+        ("lang", strings(|doc| doc.metadata.lang.as_deref())),
+        ("anchor", strings(|doc| doc.metadata.anchor.as_deref())),
+        ("version", strings(|doc| doc.metadata.version.as_deref())),
+        ("tags", lists(|doc| &doc.metadata.tags)),
+        ("heading_path", lists(|doc| &doc.metadata.heading_path)),
+        (
+            "boost",
+            Arc::new(Float32Array::from_iter(
+                rows.iter().map(|(doc, ..)| doc.metadata.boost),
+            )),
+        ),
+        (
+            "last_updated",
+            Arc::new(
+                TimestampMillisecondArray::from_iter(
+                    rows.iter()
+                        .map(|(doc, ..)| doc.metadata.last_updated.map(millis)),
+                )
+                .with_timezone_utc(),
+            ),
+        ),
+        (
+            "indexed_at",
+            Arc::new(
+                TimestampMillisecondArray::from_iter_values(
+                    rows.iter().map(|(_, at, _)| millis(*at)),
+                )
+                .with_timezone_utc(),
+            ),
+        ),
+    ];
+
+    if with_vectors {
+        let dimension = rows
+            .first()
+            .and_then(|(.., vector)| vector.as_ref())
+            .map_or(0, Vec::len);
+        let mut values = Vec::with_capacity(rows.len() * dimension);
+        for (doc, _, vector) in rows {
+            let vector = vector.as_deref().unwrap_or_default();
+            if vector.len() != dimension {
+                return Err(CodeRagError::DimensionMismatch {
+                    expected: dimension,
+                    actual: vector.len(),
+                    message: format!(
+                        "Document {} has a {}-dimensional embedding, expected {}",
+                        doc.id,
+                        vector.len(),
+                        dimension
+                    ),
+                });
+            }
+            values.extend_from_slice(vector);
+        }
+        let embedding = FixedSizeListArray::try_new(
+            Arc::new(Field::new("item", DataType::Float32, false)),
+            dimension as i32,
+            Arc::new(Float32Array::from(values)),
+            None,
+        )?;
+        columns.push(("embedding", Arc::new(embedding)));
+    }
+
+    Ok(RecordBatch::try_from_iter(columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::{ContentType, DocumentMetadata};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_parquet() {
+        let dir = TempDir::new().unwrap();
+        let mut db = VectorDatabase::new(dir.path().join("vectors.json")).unwrap();
+        for i in 0..BATCH_ROWS + 1 {
+            let doc = Document::for_test(&format!("doc{}", i))
+                .with_title("Tokio")
+                .with_metadata(DocumentMetadata {
+                    content_type: ContentType::Reference,
+                    language: Some("rust".to_string()),
+                    tags: vec!["async".to_string()],
+                    ..Default::default()
+                });
+            db.add_document(doc, vec![1.0, 0.0, i as f32]).unwrap();
+        }
+
+        let path = dir.path().join("docs.parquet");
+        let written = write_parquet(
+            &db,
+            &ExportFilter::default(),
+            true,
+            File::create(&path).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(written, BATCH_ROWS + 1);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(
+            batches.iter().map(RecordBatch::num_rows).sum::<usize>(),
+            BATCH_ROWS + 1
+        );
+        let embedding = batches[0]
+            .column_by_name("embedding")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<FixedSizeListArray>()
+            .unwrap();
+        assert_eq!(embedding.value_length(), 3);
+        assert_eq!(
+            batches[0]
+                .column_by_name("content_type")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(0),
+            "Reference"
+        );
+
+        // No matches still give a readable file, without an embedding column when not asked for
+        let filter = ExportFilter {
+            source: Some("https://serde.rs/".to_string()),
+            ..ExportFilter::default()
+        };
+        let empty = dir.path().join("empty.parquet");
+        assert_eq!(
+            write_parquet(&db, &filter, false, File::create(&empty).unwrap()).unwrap(),
+            0
+        );
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&empty).unwrap()).unwrap();
+        assert!(builder.schema().field_with_name("embedding").is_err());
+    }
+}
@@ -1,12 +1,28 @@
+pub mod analytics;
+pub mod archive;
+pub mod backups;
+pub mod bench;
+pub mod config;
+pub mod crawl_policy;
 pub mod crawler;
+pub mod data_dir;
 pub mod embedding_basic;
 pub mod enhanced_vectordb;
+pub mod error;
+pub mod export;
 pub mod mcp;
 pub mod project_manager;
+pub mod shutdown;
+pub mod sources;
+pub mod telemetry;
+pub mod url_import;
 pub mod vectordb;
 
+pub use config::Config;
+
 // Use the basic embedding service as the default
-pub use embedding_basic::EmbeddingService;
+pub use embedding_basic::{EmbeddingService, ModelStatus};
 pub use enhanced_vectordb::EnhancedVectorDbService;
+pub use error::{CodeRagError, Result};
 pub use mcp::CodeRagServer;
 pub use vectordb::{Document, VectorDatabase};
@@ -1,17 +1,1783 @@
 use anyhow::Result;
-use std::path::PathBuf;
-use tracing::{error, info};
+use clap::{Parser, Subcommand};
+use coderag::analytics::{AnalyticsReport, QueryCount, QueryFilters, QueryLog, QueryLogEntry};
+use coderag::archive::Archive;
+use coderag::backups::BackupStore;
+use coderag::bench::BenchSuite;
+use coderag::config::Config;
+use coderag::crawler::blob_store::BlobStore;
+use coderag::crawler::manpage::CliPage;
+use coderag::crawler::page_store::PageStore;
+use coderag::crawler::{ContentExtractor, CrawlConfig, Crawler, UrlPatterns, EXTRACTOR_VERSION};
+use coderag::data_dir::DataDir;
+use coderag::embedding_basic::{self, EmbeddingService};
+use coderag::export::{parse_content_type, parse_time, read_ndjson, write_ndjson, ExportFilter};
+use coderag::mcp::responses::{ImportUrlsResponse, PreviewExtractionResponse};
+use coderag::project_manager::{ProjectManager, ProjectRegistry};
+use coderag::sources::{CrawlSettings, SourceRegistry};
+use coderag::url_import::{parse_url_list, ImportCheckpoint, ImportResult};
+use coderag::vectordb::{
+    ContentType, Document, DocumentMetadata, SearchOptions, StorageBackend, VectorDatabase,
+    VersionRange,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use tracing_subscriber::fmt::format::FmtSpan;
 
-mod embedding_basic;
-mod vectordb;
+#[derive(Parser, Debug)]
+#[command(author, version, about = "CodeRAG - Documentation RAG for AI-Assisted Development", long_about = None)]
+struct Cli {
+    /// Data directory for the global database
+    #[arg(short, long, global = true, default_value = "~/.coderag")]
+    data_dir: String,
 
-use crate::vectordb::{ContentType, Document, DocumentMetadata, SearchOptions, VectorDatabase};
+    /// Use this database file instead of the project or global database
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    /// Print JSON instead of tables
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Drop corrupt entries when loading the database, backing it up first
+    #[arg(long, global = true)]
+    repair: bool,
+
+    /// Enable debug logging
+    #[arg(long, global = true)]
+    debug: bool,
+
+    /// Runs the demo when omitted
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Crawl and index a documentation site
+    Crawl {
+        /// URL to crawl
+        url: String,
+
+        /// Crawl mode: single, section, or full
+        #[arg(short, long, default_value = "single")]
+        mode: String,
+
+        /// Documentation focus: api, examples, changelog, quickstart, or all
+        #[arg(short, long, default_value = "all")]
+        focus: String,
+
+        /// Maximum pages to crawl (defaults to the configured crawler max_pages)
+        #[arg(long)]
+        max_pages: Option<usize>,
+
+        /// Only follow links matching this pattern (repeatable): a substring, a glob such as "/docs/**", or "re:<regex>"
+        #[arg(long = "include")]
+        include_patterns: Vec<String>,
+
+        /// Never follow links matching this pattern (repeatable), e.g. "/blog/**"
+        #[arg(long = "exclude")]
+        exclude_patterns: Vec<String>,
+
+        /// List the URLs the crawl would fetch without indexing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show what crawling a URL would index, without changing the database
+    Preview {
+        /// URL of the page to fetch
+        url: String,
+
+        /// Documentation focus: api, examples, changelog, quickstart, or all
+        #[arg(short, long, default_value = "all")]
+        focus: String,
+    },
+
+    /// Index every URL of a list or bookmarks file as a single page, resuming an interrupted import
+    Import {
+        /// File with one URL per line, or exported bookmarks ("-" reads standard input)
+        file: PathBuf,
+
+        /// Documentation focus: api, examples, changelog, quickstart, or all
+        #[arg(short, long, default_value = "all")]
+        focus: String,
+
+        /// Index at most this many URLs in this run
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Import every URL again instead of resuming an earlier import of the list
+        #[arg(long)]
+        restart: bool,
+    },
+
+    /// Index a command-line tool's man page and --help output
+    Tool {
+        /// Tool name, e.g. "rg"
+        name: String,
+
+        /// Read captured --help output from this file instead of running the tool
+        #[arg(long)]
+        help_file: Option<PathBuf>,
+
+        /// Skip the man page
+        #[arg(long)]
+        no_man: bool,
+    },
+
+    /// Search the indexed documentation
+    Search {
+        /// Search query
+        query: String,
+
+        /// Maximum number of results (defaults to the configured search limit)
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Only return results whose URL contains this pattern
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Only return results in this natural language (e.g. "ja")
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only return results under this section path (e.g. "Configuration > TLS")
+        #[arg(long)]
+        section: Option<String>,
+
+        /// Skip results whose URL contains this pattern (repeatable)
+        #[arg(long = "exclude-source")]
+        exclude_sources: Vec<String>,
+
+        /// Skip results with this tag (repeatable)
+        #[arg(long = "exclude-tag")]
+        exclude_tags: Vec<String>,
+
+        /// Only return changelog entries for these releases (e.g. ">1.2" or ">=1.0, <2.0")
+        #[arg(long = "versions")]
+        versions: Option<String>,
+    },
+
+    /// List indexed documentation sources
+    List,
+
+    /// Weight a source's documents in search, e.g. 0.5 for a mirror
+    Prioritize {
+        /// URL the source was crawled from
+        url: String,
+
+        /// Score multiplier (defaults to 1.0, removing any weight)
+        weight: Option<f32>,
+    },
+
+    /// Delete documents by source URL or age
+    Delete {
+        /// Source URL to delete
+        #[arg(required_unless_present = "older_than_days")]
+        source: Option<String>,
+
+        /// Delete documents older than this many days instead
+        #[arg(long, conflicts_with = "source")]
+        older_than_days: Option<u64>,
+
+        /// Report what would be deleted without changing the database
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Roll the database back to a backup taken before a destructive operation
+    Restore {
+        /// Backup name to restore (defaults to the newest)
+        backup: Option<String>,
+
+        /// List available backups instead of restoring
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Re-crawl pages indexed by an older version of the extractor
+    Reprocess {
+        /// List the pages without reprocessing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Fetch pages again instead of re-chunking their stored copies
+        #[arg(long)]
+        refetch: bool,
+
+        /// Reprocess every crawled page, not only those from an older extractor
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Rewrite the database without superseded entries and report space reclaimed
+    Compact {
+        /// Report what would be dropped without rewriting the database
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show database statistics
+    Stats,
+
+    /// Stream documents as NDJSON, one JSON object per line, or write them to Parquet
+    Export {
+        /// File to write (defaults to stdout; required for Parquet)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format: ndjson, or parquet (built with `--features parquet`)
+        #[arg(long, default_value = "ndjson")]
+        format: String,
+
+        /// Only pages under this URL prefix
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Only this content type, e.g. tutorial, reference or code_example
+        #[arg(long)]
+        content_type: Option<String>,
+
+        /// Only documents updated on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only documents updated before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Include each document's embedding
+        #[arg(long)]
+        vectors: bool,
+    },
+
+    /// Add documents with precomputed embeddings from NDJSON, without re-embedding them
+    ImportEmbeddings {
+        /// NDJSON file, e.g. from `coderag export --vectors` ("-" reads standard input)
+        file: PathBuf,
+
+        /// Model that produced the embeddings; must be the configured embedding model
+        #[arg(long)]
+        model: Option<String>,
+    },
+
+    /// Measure retrieval quality: recall@k and MRR of a YAML query suite per search configuration
+    Bench {
+        /// YAML file with the queries, their expected URLs, and configurations to compare
+        suite: PathBuf,
+    },
+
+    /// Show search analytics, including frequent queries with no results
+    Analytics {
+        /// Queries listed per ranking
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+    },
+
+    /// Show the effective configuration and where it was loaded from
+    Config,
+
+    /// Run the embedding and search smoke test (the default)
+    Demo,
+
+    /// Download the configured embedding model for use without network access
+    DownloadModel {
+        /// Directory to store the model files (defaults to <data-dir>/models/<model>)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Commands::Demo);
+
+    // Keep stdout clean for output; progress goes to stderr
+    let level = match (&command, cli.debug) {
+        (_, true) => tracing::Level::DEBUG,
+        (
+            Commands::Crawl { .. }
+            | Commands::Import { .. }
+            | Commands::Reprocess { .. }
+            | Commands::Tool { .. }
+            | Commands::Demo
+            | Commands::DownloadModel { .. },
+            false,
+        ) => tracing::Level::INFO,
+        _ => tracing::Level::WARN,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        // Debug output includes the timing of crawl, embedding and search spans
+        .with_span_events(if cli.debug {
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        })
+        .with_writer(std::io::stderr)
+        .init();
+
+    let project_root = ProjectManager::detect_project_root();
+    let data_dir = PathBuf::from(shellexpand::tilde(&cli.data_dir).as_ref());
+    let config = Config::load_with_data_dir(Some(&data_dir), project_root.as_deref())?;
+
+    let json = cli.json;
+    let db_path = || database_path(cli.db.as_deref(), &data_dir, config.database.backend);
+    let open = || open_database(&db_path()?, cli.repair, &config);
+
+    match command {
+        Commands::Config => show_config(&config, project_root.as_deref(), json),
+        Commands::Demo => run_demo().await,
+        Commands::DownloadModel { output } => {
+            let output =
+                output.unwrap_or_else(|| data_dir.join("models").join(&config.embedding.model));
+            download_model(&config, &output, json).await
+        }
+        Commands::Crawl {
+            url,
+            mode,
+            focus,
+            max_pages,
+            include_patterns,
+            exclude_patterns,
+            dry_run,
+        } => {
+            let settings = CrawlSettings {
+                mode,
+                focus,
+                max_pages,
+                include_patterns,
+                exclude_patterns,
+            };
+            if dry_run {
+                plan_crawl(&config, &url, &settings, json).await
+            } else {
+                let (mut db, db_path) = open()?;
+                run_crawl(&config, &mut db, &db_path, &url, settings, json).await
+            }
+        }
+        Commands::Preview { url, focus } => preview_page(&config, &url, &focus, json).await,
+        Commands::Import {
+            file,
+            focus,
+            limit,
+            restart,
+        } => {
+            let (mut db, db_path) = open()?;
+            import_urls(
+                &config, &mut db, &db_path, &file, &focus, limit, restart, json,
+            )
+            .await
+        }
+        Commands::Tool {
+            name,
+            help_file,
+            no_man,
+        } => {
+            let (mut db, db_path) = open()?;
+            ingest_tool(
+                &config,
+                &mut db,
+                &db_path,
+                &name,
+                help_file.as_deref(),
+                no_man,
+                json,
+            )
+            .await
+        }
+        Commands::Search {
+            query,
+            limit,
+            source,
+            lang,
+            section,
+            versions,
+            exclude_sources,
+            exclude_tags,
+        } => {
+            let (db, db_path) = open()?;
+            let options = SearchOptions {
+                source_weights: SourceRegistry::for_database(&db_path).priorities(),
+                recency_half_life_days: config.search.recency_half_life_days,
+                limit: limit.unwrap_or(config.search.limit),
+                min_score: config.search.min_score,
+                source_filter: source,
+                lang_filter: lang,
+                section_filter: section,
+                version_filter: versions.as_deref().map(VersionRange::parse).transpose()?,
+                exclude_sources,
+                exclude_tags,
+                ..SearchOptions::default()
+            };
+            run_search(&config, &db, &db_path, &query, options, json).await
+        }
+        Commands::List => {
+            let (db, db_path) = open()?;
+            list_sources(&db, &SourceRegistry::for_database(&db_path), json)
+        }
+        Commands::Prioritize { url, weight } => {
+            let db_path = db_path()?;
+            prioritize_source(
+                &mut SourceRegistry::for_database(&db_path),
+                &url,
+                weight,
+                json,
+            )
+        }
+        Commands::Delete {
+            source,
+            older_than_days,
+            dry_run,
+        } => {
+            let (mut db, db_path) = open()?;
+            let backups = BackupStore::for_database(&db_path, config.backup.keep);
+            delete_documents(
+                &mut db,
+                &backups,
+                source.as_deref(),
+                older_than_days,
+                dry_run,
+                json,
+            )
+        }
+        Commands::Restore { backup, list } => {
+            // Restoring must work even when the current database can't be loaded
+            let db_path = db_path()?;
+            let backups = BackupStore::for_database(&db_path, config.backup.keep);
+            restore_backup(&backups, backup.as_deref(), list, json)
+        }
+        Commands::Reprocess {
+            dry_run,
+            refetch,
+            all,
+        } => {
+            let (mut db, db_path) = open()?;
+            reprocess_pages(&config, &mut db, &db_path, dry_run, refetch, all, json).await
+        }
+        Commands::Compact { dry_run } => {
+            let (mut db, db_path) = open()?;
+            let page_store = config.page_store(&db_path);
+            let blob_store = config.blob_store(&db_path);
+            compact_database(
+                &mut db,
+                page_store.as_ref(),
+                blob_store.as_ref(),
+                &Archive::for_database(&db_path),
+                dry_run,
+                json,
+            )
+        }
+        Commands::Stats => {
+            let (db, db_path) = open()?;
+            show_stats(&db, &db_path, &SourceRegistry::for_database(&db_path), json)
+        }
+        Commands::Export {
+            output,
+            format,
+            source,
+            content_type,
+            since,
+            until,
+            vectors,
+        } => {
+            let filter = ExportFilter {
+                source,
+                content_type: content_type
+                    .as_deref()
+                    .map(parse_content_type)
+                    .transpose()?,
+                since: since.as_deref().map(parse_time).transpose()?,
+                until: until.as_deref().map(parse_time).transpose()?,
+            };
+            let (db, _) = open()?;
+            export_documents(&db, &filter, vectors, &format, output.as_deref())
+        }
+        Commands::ImportEmbeddings { file, model } => {
+            let (mut db, db_path) = open()?;
+            import_embeddings(&config, &mut db, &db_path, &file, model.as_deref(), json).await
+        }
+        Commands::Bench { suite } => {
+            let (db, db_path) = open()?;
+            run_bench(&config, &db, &db_path, &BenchSuite::load(&suite)?, json).await
+        }
+        Commands::Analytics { limit } => {
+            let db_path = db_path()?;
+            show_analytics(&QueryLog::for_database(&db_path), limit, json)
+        }
+    }
+}
+
+/// Path of the explicit database, or the project/global one like the MCP server uses
+fn database_path(db: Option<&Path>, data_dir: &Path, backend: StorageBackend) -> Result<PathBuf> {
+    match db {
+        Some(path) => Ok(path.to_path_buf()),
+        None => {
+            DataDir::open(data_dir)?;
+            let project_manager = ProjectManager::new(data_dir.to_path_buf()).with_backend(backend);
+            ProjectRegistry::remember(data_dir, &project_manager.get_project_info());
+            Ok(project_manager.get_database_path()?)
+        }
+    }
+}
+
+/// Load a database, optionally dropping corrupt entries after backing it up
+fn open_database(
+    db_path: &Path,
+    repair: bool,
+    config: &Config,
+) -> Result<(VectorDatabase, PathBuf)> {
+    let mut db = VectorDatabase::new(db_path)?;
+    if let Some(store) = config.vector_store(db_path)? {
+        db = db.with_vector_store(store);
+    }
+    if !db_path.exists() {
+        return Ok((db, db_path.to_path_buf()));
+    }
+
+    if repair {
+        let dropped = db.load_repairing()?;
+        if dropped > 0 {
+            BackupStore::for_database(db_path, config.backup.keep).create("repair")?;
+            db.save()?;
+        }
+        eprintln!("Repair dropped {} corrupt entries", dropped);
+    } else {
+        db.load()?;
+    }
+
+    Ok((db, db_path.to_path_buf()))
+}
+
+fn show_config(config: &Config, project_root: Option<&Path>, json: bool) -> Result<()> {
+    if json {
+        let output = serde_json::json!({
+            "sources": config.sources,
+            "config": config,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if let Some(global) = Config::global_path() {
+        println!("# Global config:  {}", global.display());
+    }
+    if let Some(root) = project_root {
+        println!("# Project config: {}", Config::project_path(root).display());
+    }
+    if config.sources.is_empty() {
+        println!("# Loaded from: built-in defaults");
+    } else {
+        println!("# Loaded from: {}", config.sources.join(", "));
+    }
+    println!();
+    print!("{}", config.to_toml_string()?);
+
+    Ok(())
+}
+
+async fn run_crawl(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    url: &str,
+    settings: CrawlSettings,
+    json: bool,
+) -> Result<()> {
+    let crawl_config = settings_crawl_config(config, url, &settings)?;
+
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.bind_model(&profile.model, profile.dimension)?;
+    let documents_before = db.document_count();
+
+    let mut crawler = Crawler::new(crawl_config)
+        .await?
+        .with_chunker(config.chunker())
+        .with_page_store(config.page_store(db_path))
+        .with_blob_store(config.blob_store(db_path));
+    let mut registry = SourceRegistry::for_database(db_path);
+    let crawled_urls = match crawler.crawl(&embedding_service, db).await {
+        Ok(crawled_urls) => {
+            registry.record_crawl(url, settings, crawled_urls.len());
+            crawled_urls
+        }
+        Err(e) => {
+            registry.record_failure(url, settings, &e.to_string());
+            registry.save()?;
+            return Err(e.into());
+        }
+    };
+    db.save()?;
+    registry.save()?;
+
+    let progress = crawler.get_progress().await;
+    let documents_added = db.document_count().saturating_sub(documents_before);
+    if json {
+        let output = serde_json::json!({
+            "session": crawler.session(),
+            "crawled_urls": crawled_urls,
+            "documents_added": documents_added,
+            "total_documents": db.document_count(),
+            "pages_failed": progress.pages_failed,
+            "retries": progress.retries,
+            "host_pauses": progress.host_pauses,
+            "skipped": progress.skipped,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        for url in &crawled_urls {
+            println!("{}", url);
+        }
+        println!(
+            "\nCrawled {} pages, added {} documents ({} total)",
+            crawled_urls.len(),
+            documents_added,
+            db.document_count()
+        );
+        println!("Crawl session: {}", crawler.session());
+        if progress.pages_failed > 0 || progress.retries > 0 {
+            println!(
+                "{} pages failed, {} fetches retried",
+                progress.pages_failed, progress.retries
+            );
+        }
+        for pause in &progress.host_pauses {
+            println!(
+                "Paused {} for {} ms after {} consecutive failures",
+                pause.host, pause.paused_ms, pause.failures
+            );
+        }
+        for skipped in &progress.skipped {
+            println!("Skipped {}: {}", skipped.url, skipped.reason);
+        }
+    }
+
+    Ok(())
+}
+
+/// Index every URL of a list as a single page, checkpointing each outcome
+#[allow(clippy::too_many_arguments)]
+async fn import_urls(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    file: &Path,
+    focus: &str,
+    limit: Option<usize>,
+    restart: bool,
+    json: bool,
+) -> Result<()> {
+    let text = if file == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", file, e))?
+    };
+    let urls = parse_url_list(&text);
+    if urls.is_empty() {
+        anyhow::bail!("Found no http(s) URLs in {:?}", file);
+    }
+    let mut checkpoint = ImportCheckpoint::for_database(db_path, &urls);
+    if restart {
+        checkpoint.clear()?;
+    }
+    let pending: Vec<String> = urls
+        .iter()
+        .filter(|url| !checkpoint.is_done(url))
+        .cloned()
+        .collect();
+    let already_done = urls.len() - pending.len();
+    if already_done > 0 && !json {
+        println!(
+            "Resuming: {} of {} URLs were imported before",
+            already_done,
+            urls.len()
+        );
+    }
+
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.bind_model(&profile.model, profile.dimension)?;
+    let mut registry = SourceRegistry::for_database(db_path);
+
+    let mut results = Vec::new();
+    for (i, url) in pending.iter().take(limit.unwrap_or(usize::MAX)).enumerate() {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_millis(config.crawler.delay_ms)).await;
+        }
+        let result = import_url(
+            config,
+            db,
+            db_path,
+            &embedding_service,
+            &mut registry,
+            url,
+            focus,
+        )
+        .await;
+        checkpoint.record(result.clone())?;
+        registry.save()?;
+        if !json {
+            println!(
+                "[{}/{}] {:<7} {}{}",
+                already_done + i + 1,
+                urls.len(),
+                result.status,
+                url,
+                result
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(" ({})", reason))
+                    .unwrap_or_default()
+            );
+        }
+        results.push(result);
+    }
+
+    let response = ImportUrlsResponse::new(urls.len(), already_done, results);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else {
+        println!(
+            "\nIndexed {}, skipped {}, failed {}: added {} documents ({} total)",
+            response.indexed,
+            response.skipped,
+            response.failed,
+            response.documents_created,
+            db.document_count()
+        );
+        if response.pending > 0 {
+            println!(
+                "{} URLs pending; run the same import again to continue",
+                response.pending
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Crawl one URL of an import as a single page
+async fn import_url(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    embedding_service: &EmbeddingService,
+    registry: &mut SourceRegistry,
+    url: &str,
+    focus: &str,
+) -> ImportResult {
+    let settings = CrawlSettings {
+        mode: "single".to_string(),
+        focus: focus.to_string(),
+        max_pages: Some(1),
+        include_patterns: Vec::new(),
+        exclude_patterns: Vec::new(),
+    };
+    let documents_before = db.document_count();
+    let crawl = async {
+        let mut crawler = Crawler::new(settings_crawl_config(config, url, &settings)?)
+            .await?
+            .with_chunker(config.chunker())
+            .with_page_store(config.page_store(db_path))
+            .with_blob_store(config.blob_store(db_path));
+        let crawled_urls = crawler.crawl(embedding_service, db).await?;
+        db.save()?;
+        anyhow::Ok((crawled_urls, crawler.get_progress().await))
+    }
+    .await;
+
+    match crawl {
+        Ok((crawled_urls, _)) if !crawled_urls.is_empty() => {
+            registry.record_crawl(url, settings, crawled_urls.len());
+            ImportResult {
+                url: url.to_string(),
+                status: "indexed".to_string(),
+                documents_created: db.document_count().saturating_sub(documents_before),
+                reason: None,
+            }
+        }
+        Ok((_, progress)) => match progress.skipped.into_iter().next() {
+            Some(skipped) => ImportResult {
+                url: url.to_string(),
+                status: "skipped".to_string(),
+                documents_created: 0,
+                reason: Some(skipped.reason),
+            },
+            None => {
+                let reason = "The page could not be fetched";
+                registry.record_failure(url, settings, reason);
+                ImportResult::failed(url, reason)
+            }
+        },
+        Err(e) => {
+            registry.record_failure(url, settings, &e.to_string());
+            ImportResult::failed(url, e.to_string())
+        }
+    }
+}
+
+/// Print the URLs a crawl would fetch, in crawl order, without indexing them
+async fn plan_crawl(
+    config: &Config,
+    url: &str,
+    settings: &CrawlSettings,
+    json: bool,
+) -> Result<()> {
+    let crawl_config = settings_crawl_config(config, url, settings)?;
+    let plan = Crawler::new(crawl_config).await?.discover().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = plan
+        .urls
+        .iter()
+        .map(|found| {
+            vec![
+                found.depth.to_string(),
+                found.found_by.clone(),
+                found.url.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["DEPTH", "FOUND BY", "URL"], &rows);
+    println!(
+        "\n{} URLs would be crawled ({} pages fetched to find them){}",
+        plan.urls.len(),
+        plan.pages_fetched,
+        if plan.truncated {
+            "; the page limit leaves more out"
+        } else {
+            ""
+        }
+    );
+
+    Ok(())
+}
+
+/// Print the extracted content and chunks of a page, without indexing it
+async fn preview_page(config: &Config, url: &str, focus: &str, json: bool) -> Result<()> {
+    let crawl_config = crawl_config(config, url, "single", focus, Some(1))?;
+    let mut crawler = Crawler::new(crawl_config)
+        .await?
+        .with_chunker(config.chunker());
+    let preview = PreviewExtractionResponse::from(crawler.preview().await?);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&preview)?);
+        return Ok(());
+    }
+
+    println!("Title: {}", preview.title);
+    if let Some(lang) = &preview.lang {
+        println!("Language: {}", lang);
+    }
+    println!(
+        "{} bytes of markdown, {} code blocks, {} links, {} chunks",
+        preview.markdown.len(),
+        preview.code_blocks.len(),
+        preview.links_found,
+        preview.chunks.len()
+    );
+    println!("\n--- markdown ---\n{}", preview.markdown);
+    for (i, chunk) in preview.chunks.iter().enumerate() {
+        println!(
+            "\n--- chunk {} of {}: {} [{}] ---\n{}",
+            i + 1,
+            preview.chunks.len(),
+            chunk.section.as_deref().unwrap_or("(no section)"),
+            chunk.tags.join(", "),
+            chunk.content
+        );
+    }
+
+    Ok(())
+}
+
+/// Crawler settings for a crawl of `url` with the given mode, focus, limit and patterns
+fn settings_crawl_config(
+    config: &Config,
+    url: &str,
+    settings: &CrawlSettings,
+) -> Result<CrawlConfig> {
+    let mut crawl_config = crawl_config(
+        config,
+        url,
+        &settings.mode,
+        &settings.focus,
+        settings.max_pages,
+    )?;
+    crawl_config.url_patterns =
+        UrlPatterns::custom(&settings.include_patterns, &settings.exclude_patterns)?;
+    Ok(crawl_config)
+}
+
+/// Crawler settings for a crawl started from the command line
+fn crawl_config(
+    config: &Config,
+    url: &str,
+    mode: &str,
+    focus: &str,
+    max_pages: Option<usize>,
+) -> Result<CrawlConfig> {
+    let start_url = url::Url::parse(url)?;
+    Ok(CrawlConfig {
+        start_url: url.to_string(),
+        mode: mode.parse()?,
+        focus: focus.parse()?,
+        max_pages: max_pages.unwrap_or(config.crawler.max_pages),
+        max_depth: config.crawler.max_depth.unwrap_or(3),
+        concurrent_requests: config.crawler.concurrent_requests,
+        delay_ms: config.crawler.delay_ms,
+        user_agent: config.crawler.user_agent.clone(),
+        allowed_domains: config.allowed_domains(&start_url),
+        domain_policy: config.crawler.domain_policy(),
+        url_patterns: UrlPatterns::default(),
+        max_page_bytes: config.crawler.max_page_bytes,
+        max_chunks_per_page: config.crawler.max_chunks_per_page,
+        max_pages_per_article: config.crawler.max_pages_per_article,
+        retry: config.crawler.retry_policy(),
+        breaker: config.crawler.breaker_policy(),
+        client: config.crawler.client_options(),
+        auth: config.crawler.auth.clone(),
+    })
+}
+
+/// Re-chunk and re-embed, one page at a time, the pages indexed by an older extractor
+///
+/// Pages with a stored copy are re-chunked from it; the rest, or all of them
+/// with `refetch`, are fetched again. `all` reprocesses every crawled page,
+/// e.g. after changing the chunk size.
+async fn reprocess_pages(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    dry_run: bool,
+    refetch: bool,
+    all: bool,
+    json: bool,
+) -> Result<()> {
+    // Every crawled page was indexed before the version after this one
+    let version = if all {
+        EXTRACTOR_VERSION + 1
+    } else {
+        EXTRACTOR_VERSION
+    };
+    let outdated = db.outdated_pages(version);
+    let page_store = config.page_store(db_path);
+    let session = uuid::Uuid::new_v4().to_string();
+    let mut rechunked = Vec::new();
+    let mut refetched = Vec::new();
+    let mut failed = Vec::new();
+
+    if !dry_run && !outdated.is_empty() {
+        BackupStore::for_database(db_path, config.backup.keep).create("reprocess")?;
+        let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+        embedding_service.bind_database(db_path, db.document_count())?;
+        let profile = embedding_service.profile();
+        db.bind_model(&profile.model, profile.dimension)?;
+
+        for url in &outdated {
+            let crawl_config = crawl_config(config, url, "single", "all", Some(1))?;
+            let mut crawler = Crawler::new(crawl_config)
+                .await?
+                .with_chunker(config.chunker())
+                .with_page_store(page_store.clone())
+                .with_blob_store(config.blob_store(db_path))
+                .with_session(session.clone());
+
+            let stored = match (&page_store, refetch) {
+                (Some(store), false) => store.load(url).unwrap_or_else(|e| {
+                    warn!("Ignoring the stored copy of {}: {}", url, e);
+                    None
+                }),
+                _ => None,
+            };
+            let outcome = match stored {
+                Some(page) => crawler
+                    .rechunk_page(page, &embedding_service, db)
+                    .await
+                    .map(|chunks| (chunks > 0, &mut rechunked)),
+                None => crawler
+                    .crawl(&embedding_service, db)
+                    .await
+                    .map(|crawled| (!crawled.is_empty(), &mut refetched)),
+            };
+            match outcome {
+                Ok((true, done)) => done.push(url.clone()),
+                Ok((false, _)) => failed.push(url.clone()),
+                Err(e) => {
+                    warn!("Failed to reprocess {}: {}", url, e);
+                    failed.push(url.clone());
+                }
+            }
+        }
+        db.save()?;
+    }
+
+    if json {
+        let output = serde_json::json!({
+            "extractor_version": EXTRACTOR_VERSION,
+            "session": (!dry_run).then_some(&session),
+            "outdated_pages": outdated,
+            "rechunked": rechunked,
+            "refetched": refetched,
+            "failed": failed,
+            "dry_run": dry_run,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if dry_run {
+        for url in &outdated {
+            println!("{}", url);
+        }
+        println!("\n{} pages to reprocess", outdated.len());
+    } else {
+        println!(
+            "Reprocessed {} of {} pages ({} re-chunked from stored copies, {} fetched again)",
+            rechunked.len() + refetched.len(),
+            outdated.len(),
+            rechunked.len(),
+            refetched.len()
+        );
+        println!("Session: {}", session);
+        for url in &failed {
+            println!("Failed: {}", url);
+        }
+    }
+
+    Ok(())
+}
+
+/// Index a tool's man page and `--help` output, replacing earlier copies
+async fn ingest_tool(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    tool: &str,
+    help_file: Option<&Path>,
+    no_man: bool,
+    json: bool,
+) -> Result<()> {
+    let mut pages = Vec::new();
+    if !no_man {
+        match CliPage::man_page(tool)? {
+            Some(page) => pages.push(page),
+            None => warn!("No man page found for {}", tool),
+        }
+    }
+    match help_file {
+        Some(path) => pages.push(CliPage::from_help(tool, &std::fs::read_to_string(path)?)),
+        None => match CliPage::capture_help(tool) {
+            Ok(page) => pages.push(page),
+            Err(e) => warn!("Skipping --help output: {}", e),
+        },
+    }
+    if pages.is_empty() {
+        anyhow::bail!("Found no documentation for {}", tool);
+    }
+
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.bind_model(&profile.model, profile.dimension)?;
+
+    let extractor = ContentExtractor::new()?;
+    let mut chunker = config.chunker();
+    let mut indexed = Vec::new();
+    for page in &pages {
+        let documents = page.documents(&extractor, &mut chunker);
+        let contents: Vec<String> = documents.iter().map(|d| d.content.clone()).collect();
+        let embeddings = embedding_service.embed_passages(&contents).await?;
+        db.remove_documents_by_source(&page.url)?;
+        for (document, embedding) in documents.into_iter().zip(embeddings) {
+            db.add_document(document, embedding)?;
+        }
+        indexed.push((page.url.clone(), contents.len()));
+    }
+    db.save()?;
+
+    if json {
+        let output: Vec<_> = indexed
+            .iter()
+            .map(|(url, count)| serde_json::json!({ "url": url, "documents": count }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        for (url, count) in &indexed {
+            println!("Indexed {} documents from {}", count, url);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_search(
+    config: &Config,
+    db: &VectorDatabase,
+    db_path: &Path,
+    query: &str,
+    options: SearchOptions,
+    json: bool,
+) -> Result<()> {
+    let started = Instant::now();
+    let filters = QueryFilters {
+        source_filter: options.source_filter.clone(),
+        lang: options.lang_filter.clone(),
+        section: options.section_filter.clone(),
+        version: options.version_filter.as_ref().map(ToString::to_string),
+        exclude_sources: options.exclude_sources.clone(),
+        exclude_tags: options.exclude_tags.clone(),
+        ..QueryFilters::default()
+    };
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.check_model(&profile.model, profile.dimension)?;
+    let query_embedding = embedding_service.embed_query(query).await?;
+
+    let results = db.search(&query_embedding, options)?;
+
+    let entry = QueryLogEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        tool: "cli".to_string(),
+        query: query.to_string(),
+        filters,
+        result_count: results.len(),
+        top_score: results.iter().map(|r| r.score).reduce(f32::max),
+        latency_ms: started.elapsed().as_millis() as u64,
+    };
+    if let Err(e) = QueryLog::for_database(db_path).append(&entry) {
+        warn!("Failed to write query log: {}", e);
+    }
+
+    if json {
+        let output: Vec<_> = results
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "id": r.document.id,
+                    "score": r.score,
+                    "url": r.document.citation_url(),
+                    "title": r.document.title,
+                    "section": r.document.section,
+                    "heading_path": r.document.metadata.heading_path,
+                    "breadcrumb": r.document.breadcrumb(),
+                    "version": r.document.metadata.version,
+                    "release_date": r.document.metadata.release_date,
+                    "votes": r.document.metadata.votes,
+                    "content": r.document.content,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| {
+            vec![
+                format!("{:.3}", r.score),
+                r.document.title.clone().unwrap_or_default(),
+                r.document.citation_url(),
+                preview(&r.document.content, 60),
+            ]
+        })
+        .collect();
+    print_table(&["SCORE", "TITLE", "URL", "PREVIEW"], &rows);
+
+    Ok(())
+}
+
+/// Run a benchmark suite against the database and report each configuration
+async fn run_bench(
+    config: &Config,
+    db: &VectorDatabase,
+    db_path: &Path,
+    suite: &BenchSuite,
+    json: bool,
+) -> Result<()> {
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.check_model(&profile.model, profile.dimension)?;
+    let mut embeddings = Vec::with_capacity(suite.queries.len());
+    for query in &suite.queries {
+        embeddings.push(embedding_service.embed_query(&query.query).await?);
+    }
+
+    let reports = suite.run(db, &embeddings)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    let recall_headers: Vec<String> = suite.k.iter().map(|k| format!("RECALL@{}", k)).collect();
+    let mut headers = vec!["CONFIGURATION"];
+    headers.extend(recall_headers.iter().map(String::as_str));
+    headers.push("MRR");
+    let rows: Vec<Vec<String>> = reports
+        .iter()
+        .map(|report| {
+            let mut row = vec![report.name.clone()];
+            row.extend(
+                report
+                    .recall
+                    .iter()
+                    .map(|(_, recall)| format!("{:.3}", recall)),
+            );
+            row.push(format!("{:.3}", report.mrr));
+            row
+        })
+        .collect();
+    print_table(&headers, &rows);
+    println!(
+        "\n{} queries against {} documents",
+        suite.queries.len(),
+        db.document_count()
+    );
+
+    Ok(())
+}
+
+fn list_sources(db: &VectorDatabase, registry: &SourceRegistry, json: bool) -> Result<()> {
+    let mut sources: Vec<(String, usize)> = db
+        .get_documents_by_source()
+        .into_iter()
+        .map(|(source, docs)| (source, docs.len()))
+        .collect();
+    sources.sort();
+
+    if json {
+        let output: Vec<_> = sources
+            .iter()
+            .map(|(source, count)| {
+                serde_json::json!({
+                    "source": source,
+                    "documents": count,
+                    "last_crawled": registry.get(source).and_then(|r| r.last_crawled.clone()),
+                    "priority": registry.get(source).and_then(|r| r.priority),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = sources
+        .into_iter()
+        .map(|(source, count)| {
+            // Date part of the RFC 3339 timestamp
+            let last_crawled = registry
+                .get(&source)
+                .and_then(|r| r.last_crawled.as_deref())
+                .map_or("-", |time| time.get(..10).unwrap_or(time))
+                .to_string();
+            vec![count.to_string(), last_crawled, source]
+        })
+        .collect();
+    print_table(&["DOCS", "CRAWLED", "SOURCE"], &rows);
+
+    Ok(())
+}
+
+fn prioritize_source(
+    registry: &mut SourceRegistry,
+    url: &str,
+    weight: Option<f32>,
+    json: bool,
+) -> Result<()> {
+    let previous = registry.set_priority(url, weight)?;
+    registry.save()?;
+
+    let weight = weight.unwrap_or(1.0);
+    if json {
+        let output = serde_json::json!({
+            "source": url,
+            "priority": weight,
+            "previous_priority": previous,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("{}: priority {} (was {})", url, weight, previous);
+    }
+
+    Ok(())
+}
+
+fn delete_documents(
+    db: &mut VectorDatabase,
+    backups: &BackupStore,
+    source: Option<&str>,
+    older_than_days: Option<u64>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let deleted = match (source, older_than_days) {
+        (Some(source), _) if dry_run => db
+            .get_documents_by_source()
+            .get(source)
+            .map_or(0, |docs| docs.len()),
+        (Some(source), _) => {
+            backups.create("delete")?;
+            db.remove_documents_by_source(source)?
+        }
+        (None, Some(days)) if dry_run => db.documents_older_than(days).len(),
+        (None, Some(days)) => {
+            backups.create("expire")?;
+            db.remove_documents_by_age(days)?
+        }
+        (None, None) => anyhow::bail!("Specify a source URL or --older-than-days"),
+    };
+
+    if !dry_run && deleted > 0 {
+        db.save()?;
+    }
+
+    if json {
+        let output = serde_json::json!({
+            "deleted_documents": deleted,
+            "dry_run": dry_run,
+            "total_documents_remaining": db.document_count(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "{} {} documents ({} remaining)",
+            if dry_run { "Would delete" } else { "Deleted" },
+            deleted,
+            db.document_count()
+        );
+    }
+
+    Ok(())
+}
+
+/// Write documents as NDJSON to a file or stdout
+fn export_documents(
+    db: &VectorDatabase,
+    filter: &ExportFilter,
+    vectors: bool,
+    format: &str,
+    output: Option<&Path>,
+) -> Result<()> {
+    let output = output.filter(|path| *path != Path::new("-"));
+    let exported = match (format, output) {
+        ("parquet", Some(path)) => {
+            let file = std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("Failed to create {:?}: {}", path, e))?;
+            write_parquet(db, filter, vectors, file)?
+        }
+        ("parquet", None) => anyhow::bail!("Parquet exports need an --output file"),
+        ("ndjson", Some(path)) => {
+            let file = std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("Failed to create {:?}: {}", path, e))?;
+            write_ndjson(db, filter, vectors, std::io::BufWriter::new(file))?
+        }
+        ("ndjson", None) => write_ndjson(
+            db,
+            filter,
+            vectors,
+            std::io::BufWriter::new(std::io::stdout().lock()),
+        )?,
+        _ => anyhow::bail!("Unknown export format {:?}; use ndjson or parquet", format),
+    };
+    // Stdout carries the documents, so the summary goes to stderr
+    eprintln!("Exported {} documents", exported);
+    Ok(())
+}
+
+/// Add documents with precomputed embeddings from an NDJSON file or stdin
+async fn import_embeddings(
+    config: &Config,
+    db: &mut VectorDatabase,
+    db_path: &Path,
+    file: &Path,
+    model: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    // Searches embed queries with the configured model, so imported vectors must come from it
+    if let Some(model) = model.filter(|m| !m.eq_ignore_ascii_case(&config.embedding.model)) {
+        anyhow::bail!(
+            "The embeddings come from {}, but the configured embedding model is {}; \
+             set embedding.model to {} (and use a database indexed with it)",
+            model,
+            config.embedding.model,
+            model
+        );
+    }
+    let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    embedding_service.bind_database(db_path, db.document_count())?;
+    let profile = embedding_service.profile();
+    db.bind_model(&profile.model, profile.dimension)?;
+
+    let imported = if file == Path::new("-") {
+        read_ndjson(
+            db,
+            std::io::stdin().lock(),
+            &profile.model,
+            profile.dimension,
+        )?
+    } else {
+        let reader = std::fs::File::open(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", file, e))?;
+        read_ndjson(
+            db,
+            std::io::BufReader::new(reader),
+            &profile.model,
+            profile.dimension,
+        )?
+    };
+    if imported > 0 {
+        db.save()?;
+    }
+
+    if json {
+        let output = serde_json::json!({
+            "imported_documents": imported,
+            "model": profile.model,
+            "total_documents": db.document_count(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "Imported {} documents embedded with {} ({} total)",
+            imported,
+            profile.model,
+            db.document_count()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet(
+    db: &VectorDatabase,
+    filter: &ExportFilter,
+    vectors: bool,
+    file: std::fs::File,
+) -> Result<usize> {
+    Ok(coderag::export::write_parquet(db, filter, vectors, file)?)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(
+    _db: &VectorDatabase,
+    _filter: &ExportFilter,
+    _vectors: bool,
+    _file: std::fs::File,
+) -> Result<usize> {
+    anyhow::bail!("Parquet export is not compiled in; rebuild with `--features parquet`")
+}
+
+fn restore_backup(backups: &BackupStore, name: Option<&str>, list: bool, json: bool) -> Result<()> {
+    if list {
+        let available = backups.list()?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&available)?);
+        } else if available.is_empty() {
+            println!("No backups in {}", backups.dir().display());
+        } else {
+            let rows: Vec<Vec<String>> = available
+                .into_iter()
+                .map(|b| {
+                    vec![
+                        b.created.get(..19).unwrap_or(&b.created).to_string(),
+                        b.reason,
+                        b.size_bytes.to_string(),
+                        b.name,
+                    ]
+                })
+                .collect();
+            print_table(&["CREATED", "BEFORE", "BYTES", "NAME"], &rows);
+        }
+        return Ok(());
+    }
+
+    let restored = backups.restore(name)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&restored)?);
+    } else {
+        println!(
+            "Restored {} (taken before {} at {})",
+            restored.name, restored.reason, restored.created
+        );
+    }
+
+    Ok(())
+}
+
+fn compact_database(
+    db: &mut VectorDatabase,
+    page_store: Option<&PageStore>,
+    blob_store: Option<&BlobStore>,
+    archive: &Archive,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if dry_run {
+        let entries = db.compactable_entries();
+        if json {
+            let output = serde_json::json!({
+                "entries_dropped": entries,
+                "dry_run": true,
+                "bytes_before": db.disk_usage(),
+                "total_documents_remaining": db.document_count(),
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            println!(
+                "Would drop {} superseded entries ({} bytes on disk)",
+                entries,
+                db.disk_usage()
+            );
+        }
+        return Ok(());
+    }
+
+    let report = db.compact()?;
+    // Stored pages of URLs no longer indexed can't be reprocessed into anything
+    let stored_pages_removed = match page_store {
+        Some(store) => Some(store.retain(db.get_documents_by_source().keys().map(String::as_str))?),
+        None => None,
+    };
+    let snapshots_removed = match blob_store {
+        Some(store) => {
+            // Archived documents keep their snapshots too
+            let archive = archive.open()?;
+            let mut snapshots = db.snapshots();
+            snapshots.extend(archive.iter().flat_map(|a| a.snapshots()));
+            Some(store.retain(snapshots)?)
+        }
+        None => None,
+    };
+    if json {
+        let output = serde_json::json!({
+            "entries_dropped": report.entries_dropped,
+            "dry_run": false,
+            "bytes_before": report.bytes_before,
+            "bytes_after": report.bytes_after,
+            "bytes_reclaimed": report.bytes_reclaimed(),
+            "total_documents_remaining": db.document_count(),
+            "stored_pages_removed": stored_pages_removed,
+            "snapshots_removed": snapshots_removed,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "Dropped {} superseded entries, reclaimed {} bytes ({} -> {})",
+            report.entries_dropped,
+            report.bytes_reclaimed(),
+            report.bytes_before,
+            report.bytes_after
+        );
+        if let Some(removed) = stored_pages_removed.filter(|&removed| removed > 0) {
+            println!("Removed {} stored pages no longer indexed", removed);
+        }
+        if let Some(removed) = snapshots_removed.filter(|&removed| removed > 0) {
+            println!("Removed {} unreferenced page snapshots", removed);
+        }
+    }
+
+    Ok(())
+}
+
+async fn download_model(config: &Config, output: &Path, json: bool) -> Result<()> {
+    let embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+    let model = &embedding_service.profile().model;
+    let files = embedding_service.download_model(output).await?;
+
+    if json {
+        let output = serde_json::json!({
+            "model": model,
+            "model_dir": output,
+            "files": files,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "Downloaded {} ({} files) to {}",
+            model,
+            files.len(),
+            output.display()
+        );
+        println!(
+            "Set embedding.model_dir = {:?} (or CODERAG_EMBEDDING_MODEL_DIR) to load it without network access",
+            output.display().to_string()
+        );
+    }
+
+    Ok(())
+}
+
+fn show_analytics(log: &QueryLog, limit: usize, json: bool) -> Result<()> {
+    let report = AnalyticsReport::from_entries(&log.read()?, limit);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Query log: {}", log.path().display());
+    println!(
+        "{} searches, {} with no results ({:.0}%), {:.0} ms average latency",
+        report.total_queries,
+        report.zero_result_queries,
+        report.zero_result_rate * 100.0,
+        report.avg_latency_ms
+    );
+
+    let rows = |counts: &[QueryCount]| -> Vec<Vec<String>> {
+        counts
+            .iter()
+            .map(|c| vec![c.count.to_string(), c.last_seen.clone(), c.query.clone()])
+            .collect()
+    };
+    println!("\nZero-result queries (crawl candidates):");
+    print_table(
+        &["COUNT", "LAST SEEN", "QUERY"],
+        &rows(&report.top_zero_result),
+    );
+    println!("\nTop queries:");
+    print_table(&["COUNT", "LAST SEEN", "QUERY"], &rows(&report.top_queries));
+
+    Ok(())
+}
+
+fn show_stats(
+    db: &VectorDatabase,
+    db_path: &Path,
+    registry: &SourceRegistry,
+    json: bool,
+) -> Result<()> {
+    let by_source = db.get_documents_by_source();
+    let mut content_types: HashMap<String, usize> = HashMap::new();
+    for doc in by_source.values().flatten() {
+        *content_types
+            .entry(format!("{:?}", doc.metadata.content_type))
+            .or_default() += 1;
+    }
+    let file_size = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+    // Crawled sources, most searched first
+    let mut usage: Vec<_> = registry.sources().iter().collect();
+    usage.sort_by_key(|(_, record)| std::cmp::Reverse(record.search_hits));
+
+    if json {
+        let output = serde_json::json!({
+            "database": db_path.display().to_string(),
+            "file_size_bytes": file_size,
+            "documents": db.document_count(),
+            "sources": by_source.len(),
+            "content_types": content_types,
+            "quantization": db.quantizer_params(),
+            "vector_index": db.index_name(),
+            "index": db.index_stats().map(|stats| serde_json::json!({
+                "nodes": stats.node_count,
+                "max_level": stats.max_level,
+                "ef_search": stats.ef_search,
+            })),
+            "source_usage": usage.iter().map(|(url, record)| serde_json::json!({
+                "source": url,
+                "search_hits": record.search_hits,
+                "last_accessed": record.last_accessed,
+            })).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let mut rows = vec![
+        vec!["Database".to_string(), db_path.display().to_string()],
+        vec!["File size".to_string(), format!("{} bytes", file_size)],
+        vec!["Documents".to_string(), db.document_count().to_string()],
+        vec!["Sources".to_string(), by_source.len().to_string()],
+    ];
+    let mut content_types: Vec<_> = content_types.into_iter().collect();
+    content_types.sort();
+    for (content_type, count) in content_types {
+        rows.push(vec![format!("  {}", content_type), count.to_string()]);
+    }
+    if let Some(stats) = db.index_stats() {
+        rows.push(vec![
+            "HNSW index".to_string(),
+            format!(
+                "{} nodes, {} levels, ef_search {}",
+                stats.node_count,
+                stats.max_level + 1,
+                stats.ef_search
+            ),
+        ]);
+    } else if let Some(name) = db.index_name() {
+        rows.push(vec!["Vector index".to_string(), name.to_string()]);
+    }
+    if !usage.is_empty() {
+        rows.push(vec!["Searches per source".to_string(), String::new()]);
+    }
+    for (url, record) in usage {
+        let last = record
+            .last_accessed
+            .as_deref()
+            .map_or(String::new(), |time| {
+                format!(", last {}", time.get(..10).unwrap_or(time))
+            });
+        rows.push(vec![
+            format!("  {}", url),
+            format!("{}{}", record.search_hits, last),
+        ]);
+    }
+    print_table(&["STAT", "VALUE"], &rows);
+
+    Ok(())
+}
+
+/// Print rows as a left-aligned table with a header
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+/// Single-line preview of document content
+fn preview(content: &str, max_chars: usize) -> String {
+    let line = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > max_chars {
+        format!("{}...", line.chars().take(max_chars).collect::<String>())
+    } else {
+        line
+    }
+}
 
+async fn run_demo() -> Result<()> {
     info!("Starting CodeRAG - Documentation RAG for AI-Assisted Development");
 
     match embedding_basic::EmbeddingService::new().await {
@@ -67,10 +1833,9 @@ async fn main() -> Result<()> {
                     title: Some("Tokio Documentation".to_string()),
                     section: Some("Introduction".to_string()),
                     metadata: DocumentMetadata {
-                        content_type: ContentType::Documentation,
                         language: Some("en".to_string()),
-                        last_updated: None,
                         tags: vec!["async".to_string(), "runtime".to_string(), "tokio".to_string()],
+                        ..Default::default()
                     },
                 },
                 Document {
@@ -82,8 +1847,8 @@ async fn main() -> Result<()> {
                     metadata: DocumentMetadata {
                         content_type: ContentType::Tutorial,
                         language: Some("en".to_string()),
-                        last_updated: None,
                         tags: vec!["error-handling".to_string(), "result".to_string()],
+                        ..Default::default()
                     },
                 },
                 Document {
@@ -93,10 +1858,9 @@ async fn main() -> Result<()> {
                     title: Some("FastEmbed Rust Documentation".to_string()),
                     section: Some("Overview".to_string()),
                     metadata: DocumentMetadata {
-                        content_type: ContentType::Documentation,
                         language: Some("en".to_string()),
-                        last_updated: None,
                         tags: vec!["embeddings".to_string(), "ml".to_string(), "onnx".to_string()],
+                        ..Default::default()
                     },
                 },
             ];
@@ -107,7 +1871,7 @@ async fn main() -> Result<()> {
                     "📄 Adding document: {}",
                     doc.title.as_ref().unwrap_or(&doc.id)
                 );
-                let embedding = service.embed(&doc.content).await?;
+                let embedding = service.embed_passage(&doc.content).await?;
                 db.add_document(doc, embedding)?;
             }
 
@@ -129,7 +1893,7 @@ async fn main() -> Result<()> {
                 info!(""); // Separator for each query
                 info!("Query: '{}'", query);
 
-                let query_embedding = service.embed(query).await?;
+                let query_embedding = service.embed_query(query).await?;
                 let results = db.search(
                     &query_embedding,
                     SearchOptions {
@@ -137,6 +1901,16 @@ async fn main() -> Result<()> {
                         min_score: Some(0.3),
                         source_filter: None,
                         content_type_filter: None,
+                        ef_search: None,
+                        allowed_urls: None,
+                        lang_filter: None,
+                        section_filter: None,
+                        version_filter: None,
+                        budget: None,
+                        source_weights: Vec::new(),
+                        recency_half_life_days: None,
+                        exclude_sources: Vec::new(),
+                        exclude_tags: Vec::new(),
                     },
                 )?;
 
@@ -165,7 +1939,7 @@ async fn main() -> Result<()> {
         Err(e) => {
             error!("❌ Failed to initialize embedding service: {}", e);
             info!("💡 This might be due to network issues or missing ONNX runtime");
-            return Err(e);
+            return Err(e.into());
         }
     }
 
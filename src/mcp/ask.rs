@@ -0,0 +1,214 @@
+//! One-call RAG context building for ask_docs.
+//!
+//! Hybrid search hits are reranked by query term coverage, deduplicated,
+//! packed into a token budget and laid out in reading order, each distinct
+//! section getting a numbered citation marker.
+
+use crate::mcp::responses::Citation;
+use crate::vectordb::LayeredSearchResult;
+use std::collections::{HashMap, HashSet};
+
+/// Default token budget for the assembled context
+pub const DEFAULT_TOKEN_BUDGET: usize = 2000;
+
+/// Number of search hits considered before packing the context
+pub const CANDIDATE_CHUNKS: usize = 20;
+
+/// Rough token count, assuming ~4 characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Context window assembled from the top chunks
+#[derive(Debug, Clone)]
+pub struct AssembledContext {
+    pub context: String,
+    pub citations: Vec<Citation>,
+    pub token_estimate: usize,
+    pub chunks_included: usize,
+}
+
+/// Rerank hits by how many of the question's terms each chunk contains
+///
+/// The retrieval score still dominates; term coverage scales it by up to a
+/// quarter so chunks that mention every term win close calls.
+pub fn rerank(question: &str, mut hits: Vec<LayeredSearchResult>) -> Vec<LayeredSearchResult> {
+    let terms: HashSet<String> = question
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|term| term.len() > 2)
+        .map(str::to_lowercase)
+        .collect();
+
+    if terms.is_empty() {
+        return hits;
+    }
+
+    for hit in &mut hits {
+        let content = hit.result.document.content.to_lowercase();
+        let covered = terms.iter().filter(|term| content.contains(*term)).count();
+        let coverage = covered as f32 / terms.len() as f32;
+        hit.result.score *= 0.75 + 0.25 * coverage;
+    }
+
+    hits.sort_by(|a, b| b.result.score.total_cmp(&a.result.score));
+    hits
+}
+
+/// Pack ranked hits into a context of at most `token_budget` tokens
+///
+/// Chunks are taken best-first, skipping duplicates and chunks that no
+/// longer fit, then laid out page by page (best page first) in chunk order.
+pub fn assemble_context(hits: &[LayeredSearchResult], token_budget: usize) -> AssembledContext {
+    let mut seen_content = HashSet::new();
+    let mut selected: Vec<(usize, &LayeredSearchResult, String)> = Vec::new();
+    let mut used = 0;
+
+    for (rank, hit) in hits.iter().enumerate() {
+        let content = hit.result.document.content.trim();
+        let key: String = content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        if content.is_empty() || !seen_content.insert(key) {
+            continue;
+        }
+
+        let tokens = estimate_tokens(content);
+        if used + tokens <= token_budget {
+            used += tokens;
+            selected.push((rank, hit, content.to_string()));
+        } else if selected.is_empty() {
+            // Always return something: cut the best chunk down to the budget
+            let truncated: String = content.chars().take(token_budget * 4).collect();
+            used += estimate_tokens(&truncated);
+            selected.push((rank, hit, truncated));
+        }
+    }
+
+    // Reading order: pages by their best rank, chunks by position in the page
+    let mut page_rank: HashMap<&str, usize> = HashMap::new();
+    for (rank, hit, _) in &selected {
+        page_rank
+            .entry(hit.result.document.url.as_str())
+            .or_insert(*rank);
+    }
+    selected.sort_by_key(|(rank, hit, _)| {
+        let document = &hit.result.document;
+        (
+            page_rank[document.url.as_str()],
+            document.chunk_position().map_or(*rank, |(index, _)| index),
+        )
+    });
+
+    let mut citations: Vec<Citation> = Vec::new();
+    let mut context = String::new();
+
+    for (_, hit, content) in &selected {
+        let document = &hit.result.document;
+        let url = document.citation_url();
+
+        let marker = match citations.iter().find(|c| c.url == url) {
+            Some(citation) => citation.marker,
+            None => {
+                let marker = citations.len() + 1;
+                citations.push(Citation {
+                    marker,
+                    title: document.title.clone(),
+                    url: url.clone(),
+                    heading_path: document.metadata.heading_path.clone(),
+                    score: hit.result.score,
+                    origin: hit.origin.clone(),
+                });
+                marker
+            }
+        };
+
+        let label = match (&document.title, document.metadata.heading_path.is_empty()) {
+            (Some(title), false) => {
+                format!("{} › {}", title, document.metadata.heading_path.join(" › "))
+            }
+            (Some(title), true) => title.clone(),
+            (None, _) => url.clone(),
+        };
+        context.push_str(&format!(
+            "[{}] {}\nSource: {}\n\n{}\n\n",
+            marker, label, url, content
+        ));
+    }
+
+    AssembledContext {
+        context: context.trim_end().to_string(),
+        citations,
+        token_estimate: used,
+        chunks_included: selected.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::{Document, DocumentMetadata, SearchResult};
+
+    fn hit(url: &str, index: usize, content: &str, score: f32) -> LayeredSearchResult {
+        LayeredSearchResult {
+            result: SearchResult {
+                document: Document::for_test(&format!("{}_chunk_{}", url, index))
+                    .with_content(content)
+                    .with_url(url)
+                    .with_title(&format!("Page {}", url))
+                    .with_metadata(DocumentMetadata {
+                        tags: vec![format!("chunk-{}-of-3", index + 1)],
+                        anchor: Some(format!("s{}", index)),
+                        ..Default::default()
+                    }),
+                score,
+            },
+            origin: "project".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rerank_by_term_coverage() {
+        let hits = vec![
+            hit("a", 0, "Unrelated text about builders", 0.80),
+            hit("b", 0, "Configure a timeout on the runtime", 0.78),
+        ];
+        let reranked = rerank("runtime timeout", hits);
+        assert_eq!(reranked[0].result.document.url, "b");
+    }
+
+    #[test]
+    fn test_assemble_context_budget_and_order() {
+        let long = "word ".repeat(400);
+        let hits = vec![
+            hit("a", 2, "Third chunk of page a", 0.9),
+            hit("b", 0, "Only chunk of page b", 0.8),
+            hit("a", 0, "First chunk of page a", 0.7),
+            hit("c", 0, &long, 0.6),
+            hit("d", 0, "  only chunk of PAGE b ", 0.5),
+        ];
+
+        let assembled = assemble_context(&hits, 50);
+
+        // The long chunk doesn't fit and the duplicate is dropped
+        assert_eq!(assembled.chunks_included, 3);
+        assert!(assembled.token_estimate <= 50);
+        assert!(!assembled.context.contains("word word"));
+
+        // Page a comes first, its chunks in page order
+        let first = assembled.context.find("First chunk of page a").unwrap();
+        let third = assembled.context.find("Third chunk of page a").unwrap();
+        let page_b = assembled.context.find("Only chunk of page b").unwrap();
+        assert!(first < third && third < page_b);
+
+        assert_eq!(assembled.citations.len(), 3);
+        assert_eq!(assembled.citations[0].url, "a#s0");
+        assert!(assembled.context.starts_with("[1] Page a\nSource: a#s0"));
+
+        // An oversized best chunk is truncated rather than dropped
+        let assembled = assemble_context(&hits[3..4], 10);
+        assert_eq!(assembled.chunks_included, 1);
+        assert!(assembled.token_estimate <= 10);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod ask;
+pub mod prompts;
+pub mod responses;
+#[cfg(feature = "rest-api")]
+pub mod rest;
+pub mod router;
 pub mod sdk_server;
+pub mod summarize;
+pub mod watch;
 
-pub use sdk_server::CodeRagServer;
+pub use sdk_server::{CodeRagServer, ServerOptions, ShutdownLocks};
@@ -0,0 +1,226 @@
+//! Canned RAG prompt templates served over MCP.
+//!
+//! Each template runs a search and returns a prompt pre-filled with the top
+//! chunks and numbered citation markers, so clients don't have to assemble
+//! retrieval context themselves.
+
+use crate::vectordb::LayeredSearchResult;
+use rmcp::model::{JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole};
+
+/// Default number of chunks included in a prompt
+pub const DEFAULT_PROMPT_CHUNKS: usize = 5;
+
+/// A prompt template backed by a documentation search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptTemplate {
+    /// Answer a question grounded in the retrieved documentation
+    AnswerWithDocs,
+    /// Explain an API item with usage examples from the documentation
+    ExplainApi,
+}
+
+impl PromptTemplate {
+    const ALL: [PromptTemplate; 2] = [PromptTemplate::AnswerWithDocs, PromptTemplate::ExplainApi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptTemplate::AnswerWithDocs => "answer-with-docs",
+            PromptTemplate::ExplainApi => "explain-api",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|template| template.name() == name)
+    }
+
+    /// All templates as MCP prompt definitions
+    pub fn list() -> Vec<Prompt> {
+        Self::ALL.into_iter().map(Self::definition).collect()
+    }
+
+    fn definition(self) -> Prompt {
+        let limit = argument(
+            "limit",
+            "Number of documentation chunks to include (default 5)",
+            false,
+        );
+
+        match self {
+            PromptTemplate::AnswerWithDocs => Prompt::new(
+                self.name(),
+                Some(
+                    "Answer a question using the top matching documentation chunks, with citations",
+                ),
+                Some(vec![
+                    argument("question", "The question to answer", true),
+                    limit,
+                ]),
+            ),
+            PromptTemplate::ExplainApi => Prompt::new(
+                self.name(),
+                Some("Explain an API item and show how to use it, citing the documentation"),
+                Some(vec![
+                    argument("symbol", "Function, type or module to explain", true),
+                    argument("library", "Library the symbol belongs to", false),
+                    limit,
+                ]),
+            ),
+        }
+    }
+
+    /// Required argument the template is built around
+    pub fn subject_argument(self) -> &'static str {
+        match self {
+            PromptTemplate::AnswerWithDocs => "question",
+            PromptTemplate::ExplainApi => "symbol",
+        }
+    }
+
+    /// Search query for the given arguments
+    pub fn query(self, arguments: &JsonObject) -> Option<String> {
+        let subject = string_argument(arguments, self.subject_argument())?;
+        Some(match (self, string_argument(arguments, "library")) {
+            (PromptTemplate::ExplainApi, Some(library)) => format!("{} {}", library, subject),
+            _ => subject,
+        })
+    }
+
+    /// Number of chunks requested, falling back to the default
+    pub fn limit(arguments: &JsonObject) -> usize {
+        arguments
+            .get("limit")
+            .and_then(|value| {
+                value
+                    .as_u64()
+                    .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+            })
+            .map_or(DEFAULT_PROMPT_CHUNKS, |limit| limit as usize)
+            .max(1)
+    }
+
+    /// Render the prompt text for a subject and its retrieved chunks
+    pub fn render(self, subject: &str, hits: &[LayeredSearchResult]) -> PromptMessage {
+        let task = match self {
+            PromptTemplate::AnswerWithDocs => format!(
+                "Answer the following question using the documentation excerpts below.\n\nQuestion: {}",
+                subject
+            ),
+            PromptTemplate::ExplainApi => format!(
+                "Explain what `{}` does and show how to use it, based on the documentation excerpts below. \
+                Include a short code example.",
+                subject
+            ),
+        };
+
+        let mut text = task;
+        text.push_str("\n\n## Documentation\n");
+
+        if hits.is_empty() {
+            text.push_str(
+                "\nNo matching documentation is indexed. Say so, and suggest which documentation to crawl.\n",
+            );
+        }
+
+        for (i, hit) in hits.iter().enumerate() {
+            let document = &hit.result.document;
+            let title = document.title.as_deref().unwrap_or(&document.url);
+            text.push_str(&format!("\n[{}] {}", i + 1, title));
+            if let Some(breadcrumb) = document.breadcrumb() {
+                text.push_str(&format!(" — {}", breadcrumb));
+            } else if let Some(section) = &document.section {
+                text.push_str(&format!(" — {}", section));
+            }
+            text.push_str(&format!(
+                "\nSource: {}\n\n{}\n",
+                document.citation_url(),
+                document.content.trim()
+            ));
+        }
+
+        if !hits.is_empty() {
+            text.push_str(
+                "\n## Instructions\n\
+                Cite the excerpts you rely on with their markers, e.g. [1] or [2][3]. \
+                If the excerpts don't cover something, say so instead of guessing.\n",
+            );
+        }
+
+        PromptMessage::new_text(PromptMessageRole::User, text)
+    }
+}
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        required: Some(required),
+    }
+}
+
+fn string_argument(arguments: &JsonObject, name: &str) -> Option<String> {
+    arguments
+        .get(name)
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::{Document, SearchResult};
+    use rmcp::model::PromptMessageContent;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_answer_with_docs() {
+        let hit = |id: &str, section: Option<&str>| LayeredSearchResult {
+            result: SearchResult {
+                document: Document {
+                    section: section.map(str::to_string),
+                    ..Document::for_test(id)
+                        .with_url(&format!("https://docs.example.com/{}", id))
+                        .with_title(&format!("Title {}", id))
+                },
+                score: 0.9,
+            },
+            origin: "project".to_string(),
+        };
+
+        let arguments = json!({ "question": "How do timeouts work?", "limit": "3" });
+        let arguments = arguments.as_object().unwrap();
+        let template = PromptTemplate::from_name("answer-with-docs").unwrap();
+        assert_eq!(
+            template.query(arguments).as_deref(),
+            Some("How do timeouts work?")
+        );
+        assert_eq!(PromptTemplate::limit(arguments), 3);
+
+        let message = template.render(
+            "How do timeouts work?",
+            &[hit("a", Some("Timeouts")), hit("b", None)],
+        );
+        let PromptMessageContent::Text { text } = message.content else {
+            panic!("expected text content");
+        };
+        assert!(text.contains("Question: How do timeouts work?"));
+        assert!(text.contains("[1] Title a — Timeouts\nSource: https://docs.example.com/a"));
+        assert!(text.contains("[2] Title b\n"));
+        assert!(text.contains("Cite the excerpts"));
+    }
+
+    #[test]
+    fn test_explain_api_query() {
+        let arguments = json!({ "symbol": "select!", "library": "tokio" });
+        let template = PromptTemplate::from_name("explain-api").unwrap();
+        assert_eq!(
+            template.query(arguments.as_object().unwrap()).as_deref(),
+            Some("tokio select!")
+        );
+        assert!(template.query(&JsonObject::new()).is_none());
+        assert!(PromptTemplate::from_name("unknown").is_none());
+    }
+}
@@ -0,0 +1,917 @@
+//! Typed responses returned by the MCP tools.
+//!
+//! Every tool result carries the response as an embedded `application/json`
+//! resource whose URI names the response type, so typed clients can
+//! deserialize it without scraping text, followed by the same JSON as a text
+//! item for clients that only read text. `output_schemas` publishes the JSON
+//! schema of each type.
+
+use crate::analytics::AnalyticsReport;
+use crate::backups::BackupInfo;
+use crate::crawler::{CrawlPlan, PagePreview, SkippedPage};
+use crate::project_manager::{ProjectDependency, WorkspaceMember};
+use crate::sources::SourceRecord;
+use crate::url_import::ImportResult;
+use crate::vectordb::Document;
+use rmcp::model::{CallToolResult, Content, ResourceContents};
+use rmcp::Error as McpError;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// URI prefix of the structured JSON content attached to tool results
+pub const RESPONSE_URI_PREFIX: &str = "coderag://responses/";
+
+/// Response types that can be returned as structured tool output
+pub trait ToolResponse: Serialize {
+    /// Type name used in the structured content URI and schema map
+    const TYPE_NAME: &'static str;
+
+    /// Build a tool result with structured JSON content and a text fallback
+    fn into_tool_result(self) -> Result<CallToolResult, McpError>
+    where
+        Self: Sized,
+    {
+        let json = serde_json::to_string(&self)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(CallToolResult::success(vec![
+            Content::resource(ResourceContents::TextResourceContents {
+                uri: format!("{}{}", RESPONSE_URI_PREFIX, Self::TYPE_NAME),
+                mime_type: Some("application/json".to_string()),
+                text: json.clone(),
+            }),
+            Content::text(json),
+        ]))
+    }
+}
+
+/// A single search hit
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchHit {
+    pub id: String,
+    pub content: String,
+    pub score: f32,
+    pub url: String,
+    /// Deep link to the hit's section (`url#anchor`), or the page URL
+    pub citation_url: String,
+    /// Headings enclosing the hit, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heading_path: Vec<String>,
+    /// Heading path for display, e.g. "Guide › Configuration › TLS"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breadcrumb: Option<String>,
+    pub metadata: serde_json::Value,
+    /// Other URLs serving the same content, e.g. mirrors of a tutorial
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternate_urls: Vec<String>,
+    /// Database layer the hit came from ("project" or "global")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+/// Ranked hits from search_docs or related_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchResponse {
+    pub results: Vec<SearchHit>,
+    /// Whether the search ran out of its time budget and the results may be incomplete
+    pub truncated: bool,
+    /// Stage that used up the time budget: embedding, ann, scan, rerank, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_exceeded_in: Option<String>,
+    /// Filters recognized in the query text and applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inferred_filters: Option<InferredFilters>,
+}
+
+impl SearchResponse {
+    /// Results of a search, truncated if the budget ran out in `exceeded_in`
+    pub fn new(results: Vec<SearchHit>, exceeded_in: Option<&str>) -> Self {
+        Self {
+            results,
+            truncated: exceeded_in.is_some(),
+            budget_exceeded_in: exceeded_in.map(str::to_string),
+            inferred_filters: None,
+        }
+    }
+
+    pub fn with_inferred_filters(mut self, filters: Option<InferredFilters>) -> Self {
+        self.inferred_filters = filters;
+        self
+    }
+}
+
+/// Filters inferred from phrases such as "in tokio docs" or "since version 2"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InferredFilters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl InferredFilters {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl ToolResponse for SearchResponse {
+    const TYPE_NAME: &'static str = "SearchResponse";
+}
+
+/// Assembled context and citations from ask_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AskDocsResponse {
+    pub question: String,
+    /// Chunks in reading order, each prefixed with its citation marker
+    pub context: String,
+    pub citations: Vec<Citation>,
+    pub token_budget: usize,
+    pub token_estimate: usize,
+    pub chunks_considered: usize,
+    pub chunks_included: usize,
+    /// Whether the search ran out of its time budget and fewer chunks were considered
+    pub truncated: bool,
+    /// Filters recognized in the question and applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inferred_filters: Option<InferredFilters>,
+}
+
+impl ToolResponse for AskDocsResponse {
+    const TYPE_NAME: &'static str = "AskDocsResponse";
+}
+
+/// A numbered source referenced from assembled context
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Citation {
+    pub marker: usize,
+    pub title: Option<String>,
+    /// Deep link to the cited section
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heading_path: Vec<String>,
+    pub score: f32,
+    pub origin: String,
+}
+
+/// Indexed sources and project context from list_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListDocsResponse {
+    /// Documents of all listed sources, across pages
+    pub total_documents: usize,
+    /// Sources listed across pages
+    pub total_sources: usize,
+    pub offset: usize,
+    /// Offset of the next page of sources, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+    pub sources: Vec<SourceSummary>,
+    /// Crawl history of each crawled URL: last_crawled, settings, failures
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub crawled_sources: BTreeMap<String, SourceRecord>,
+    pub project_context: ProjectContext,
+}
+
+impl ToolResponse for ListDocsResponse {
+    const TYPE_NAME: &'static str = "ListDocsResponse";
+}
+
+/// An indexed page and its chunk count
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SourceSummary {
+    pub url: String,
+    pub documents: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// When the newest chunk was indexed (RFC 3339, UTC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<String>,
+    /// Sections in page order with their chunk counts; only in the detail view
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<SectionSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SectionSummary {
+    pub section: String,
+    pub chunks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectContext {
+    pub is_project: bool,
+    pub project_name: Option<String>,
+    pub database_location: String,
+    pub layered_search: bool,
+    pub scopes: Vec<WorkspaceMember>,
+    /// Workspace members with a database of their own, searched when a search's path is inside them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub package_databases: Vec<String>,
+}
+
+/// Outcome of crawl_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrawlResponse {
+    pub status: String,
+    pub source_url: String,
+    pub mode: String,
+    pub pages_crawled: usize,
+    pub documents_created: usize,
+    pub chunks_created: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Pages left out because their content can't be indexed, with the reason
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedPage>,
+    /// ID of the crawl run, recorded in the provenance of each chunk it wrote
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+impl ToolResponse for CrawlResponse {
+    const TYPE_NAME: &'static str = "CrawlResponse";
+}
+
+/// URLs a crawl would fetch, from a crawl_docs dry run
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrawlPlanResponse {
+    pub source_url: String,
+    pub mode: String,
+    #[serde(flatten)]
+    pub plan: CrawlPlan,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl ToolResponse for CrawlPlanResponse {
+    const TYPE_NAME: &'static str = "CrawlPlanResponse";
+}
+
+/// Outcome of import_urls
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImportUrlsResponse {
+    pub total_urls: usize,
+    /// URLs an earlier import of the same list already indexed or skipped
+    pub already_done: usize,
+    pub indexed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// URLs left for a later import of the same list
+    pub pending: usize,
+    pub documents_created: usize,
+    /// Outcome of each URL tried by this import
+    pub results: Vec<ImportResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl ToolResponse for ImportUrlsResponse {
+    const TYPE_NAME: &'static str = "ImportUrlsResponse";
+}
+
+impl ImportUrlsResponse {
+    /// Summarize the URLs tried by an import of a list of `total_urls`
+    pub fn new(total_urls: usize, already_done: usize, results: Vec<ImportResult>) -> Self {
+        let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+        let pending = total_urls.saturating_sub(already_done + results.len());
+        Self {
+            total_urls,
+            already_done,
+            indexed: count("indexed"),
+            skipped: count("skipped"),
+            failed: count("failed"),
+            pending,
+            documents_created: results.iter().map(|r| r.documents_created).sum(),
+            note: (pending > 0).then(|| {
+                "Import the same list again to continue with the pending URLs".to_string()
+            }),
+            results,
+        }
+    }
+}
+
+/// What crawling a URL would index, from preview_extraction
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewExtractionResponse {
+    pub url: String,
+    pub title: String,
+    /// Natural language detected for the page, e.g. "en"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Cleaned content the page is chunked from
+    pub markdown: String,
+    pub code_blocks: Vec<PreviewCodeBlock>,
+    pub chunks: Vec<PreviewChunk>,
+    /// Links a section or full crawl would consider following
+    pub links_found: usize,
+}
+
+impl ToolResponse for PreviewExtractionResponse {
+    const TYPE_NAME: &'static str = "PreviewExtractionResponse";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewCodeBlock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// A chunk as it would be indexed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewChunk {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    pub tags: Vec<String>,
+    pub content: String,
+}
+
+impl From<PagePreview> for PreviewExtractionResponse {
+    fn from(preview: PagePreview) -> Self {
+        let extracted = preview.extracted;
+        Self {
+            url: preview.url,
+            title: extracted.title,
+            lang: extracted.metadata.lang,
+            markdown: extracted.markdown,
+            code_blocks: extracted
+                .code_blocks
+                .into_iter()
+                .map(|block| PreviewCodeBlock {
+                    language: block.language,
+                    code: block.code,
+                })
+                .collect(),
+            chunks: preview
+                .documents
+                .into_iter()
+                .map(|doc| PreviewChunk {
+                    id: doc.id,
+                    section: doc.section,
+                    anchor: doc.metadata.anchor,
+                    tags: doc.metadata.tags,
+                    content: doc.content,
+                })
+                .collect(),
+            links_found: preview.links.len(),
+        }
+    }
+}
+
+/// A single chunk or a reassembled page from get_doc
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum GetDocResponse {
+    Page(DocPage),
+    Chunk(DocChunk),
+}
+
+impl ToolResponse for GetDocResponse {
+    const TYPE_NAME: &'static str = "GetDocResponse";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DocChunk {
+    pub id: String,
+    pub url: String,
+    pub citation_url: String,
+    pub title: Option<String>,
+    pub section: Option<String>,
+    pub content: String,
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DocPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub sections: Vec<String>,
+    pub chunk_count: usize,
+    pub chunks: Vec<ChunkRef>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChunkRef {
+    pub id: String,
+    pub section: Option<String>,
+}
+
+/// Undocumented dependencies from suggest_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SuggestDocsResponse {
+    pub is_project: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub dependency_count: usize,
+    pub documented: Vec<String>,
+    pub suggestions: Vec<ProjectDependency>,
+}
+
+impl ToolResponse for SuggestDocsResponse {
+    const TYPE_NAME: &'static str = "SuggestDocsResponse";
+}
+
+/// Updated boost after feedback on a document
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FeedbackResponse {
+    pub id: String,
+    pub helpful: bool,
+    /// Score multiplier now applied to the document
+    pub boost: f32,
+    pub origin: String,
+}
+
+impl ToolResponse for FeedbackResponse {
+    const TYPE_NAME: &'static str = "FeedbackResponse";
+}
+
+/// Topics discovered in the knowledge base by map_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MapDocsResponse {
+    pub document_count: usize,
+    /// Topics, largest first
+    pub topics: Vec<TopicSummary>,
+}
+
+impl ToolResponse for MapDocsResponse {
+    const TYPE_NAME: &'static str = "MapDocsResponse";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TopicSummary {
+    /// Title and heading words that distinguish the topic
+    pub keywords: Vec<String>,
+    pub document_count: usize,
+    /// Document counts per documentation source
+    pub sources: BTreeMap<String, usize>,
+    /// Documents closest to the topic centre, at most one per source
+    pub representatives: Vec<TopicDocument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TopicDocument {
+    pub id: String,
+    pub title: Option<String>,
+    pub citation_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breadcrumb: Option<String>,
+    /// Cosine similarity to the topic centre
+    pub similarity: f32,
+}
+
+/// Extractive summary of a documentation source from summarize_source
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SummarizeSourceResponse {
+    pub source: String,
+    /// Chunks indexed under the source
+    pub document_count: usize,
+    /// Markdown list of the key points with links
+    pub summary: String,
+    pub points: Vec<SummaryPoint>,
+}
+
+impl ToolResponse for SummarizeSourceResponse {
+    const TYPE_NAME: &'static str = "SummarizeSourceResponse";
+}
+
+/// One chunk's contribution to a source summary
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SummaryPoint {
+    /// Opening sentences of the chunk
+    pub text: String,
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breadcrumb: Option<String>,
+    pub citation_url: String,
+    /// Cosine similarity to the centroid of the source
+    pub centrality: f32,
+}
+
+/// Embedding of a text from embed_text
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmbedTextResponse {
+    pub model: String,
+    pub dimension: usize,
+    pub embedding: Vec<f32>,
+    /// Cosine similarity to the `compare_to` text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+impl ToolResponse for EmbedTextResponse {
+    const TYPE_NAME: &'static str = "EmbedTextResponse";
+}
+
+/// Outcome of reload_docs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReloadResponse {
+    pub status: String,
+    pub documents_loaded: usize,
+}
+
+impl ToolResponse for ReloadResponse {
+    const TYPE_NAME: &'static str = "ReloadResponse";
+}
+
+/// Project databases known to list_projects
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListProjectsResponse {
+    /// Root of the project the server is serving, if it serves one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_project: Option<String>,
+    /// Most recently opened first
+    pub projects: Vec<ProjectSummary>,
+}
+
+impl ToolResponse for ListProjectsResponse {
+    const TYPE_NAME: &'static str = "ListProjectsResponse";
+}
+
+/// A project's database as reported by list_projects
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub root: String,
+    pub database_path: String,
+    /// False if the database was never saved or has been deleted
+    pub exists: bool,
+    pub size_bytes: u64,
+    /// Crawled source URLs recorded for the database
+    pub sources: Vec<String>,
+    /// RFC 3339 time CodeRAG last opened the project
+    pub last_opened: String,
+    pub active: bool,
+}
+
+/// Outcome of switch_project
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SwitchProjectResponse {
+    /// "switched", or "unchanged" if the project was already active
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    pub project_root: String,
+    pub database_path: String,
+    pub documents_loaded: usize,
+    /// Whether another instance holds the database's write lock
+    pub read_only: bool,
+}
+
+impl ToolResponse for SwitchProjectResponse {
+    const TYPE_NAME: &'static str = "SwitchProjectResponse";
+}
+
+/// Outcome of a manage_docs operation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum ManageDocsResponse {
+    Delete {
+        target: String,
+        /// How target was matched: "source", "id", "prefix", or "tag"
+        delete_by: String,
+        deleted_documents: usize,
+        dry_run: bool,
+        total_documents_remaining: usize,
+        /// Documents moved to the archive before removal, when archiving is enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        archived_documents: Option<usize>,
+        /// Backup taken before the operation, restorable with manage_docs restore
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backup: Option<String>,
+    },
+    Expire {
+        max_age_days: u64,
+        expired_documents: usize,
+        dry_run: bool,
+        total_documents_remaining: usize,
+        /// Sources not crawled within max_age_days, candidates for refresh
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        stale_sources: Vec<String>,
+        /// Documents moved to the archive before removal, when archiving is enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        archived_documents: Option<usize>,
+        /// Backup taken before the operation, restorable with manage_docs restore
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backup: Option<String>,
+    },
+    Evict {
+        max_age_days: u64,
+        /// Sources no search returned within max_age_days, removed whole
+        evicted_sources: Vec<String>,
+        evicted_documents: usize,
+        dry_run: bool,
+        total_documents_remaining: usize,
+        /// Documents moved to the archive before removal, when archiving is enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        archived_documents: Option<usize>,
+        /// Backup taken before the operation, restorable with manage_docs restore
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backup: Option<String>,
+    },
+    Refresh {
+        target: String,
+        removed_documents: usize,
+        new_documents: usize,
+        dry_run: bool,
+        net_change: i64,
+        /// Chunks added, removed and modified by the refresh; omitted on dry runs
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        changes: Option<RefreshChanges>,
+        /// Documents moved to the archive before removal, when archiving is enabled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        archived_documents: Option<usize>,
+        /// Backup taken before the operation, restorable with manage_docs restore
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backup: Option<String>,
+    },
+    Verify {
+        target: String,
+        sources_checked: usize,
+        urls_checked: usize,
+        problems: Vec<SourceProblem>,
+    },
+    Compact {
+        entries_dropped: usize,
+        dry_run: bool,
+        bytes_before: u64,
+        /// Omitted on dry runs, which don't rewrite storage
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bytes_after: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bytes_reclaimed: Option<u64>,
+        total_documents_remaining: usize,
+        /// Stored pages deleted because nothing indexed comes from them any more
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stored_pages_removed: Option<usize>,
+        /// Page snapshots deleted because no document refers to them any more
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        snapshots_removed: Option<usize>,
+    },
+    Restore {
+        target: String,
+        /// Backup the database was rolled back to; None on dry runs
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        restored: Option<BackupInfo>,
+        dry_run: bool,
+        /// Available backups, newest first
+        backups: Vec<BackupInfo>,
+        total_documents_remaining: usize,
+    },
+    Prioritize {
+        target: String,
+        /// Score multiplier now applied to the source's documents
+        priority: f32,
+        previous_priority: f32,
+    },
+}
+
+/// What a refresh changed, comparing chunks by their content-derived IDs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RefreshChanges {
+    pub unchanged: usize,
+    pub added: Vec<ChunkChange>,
+    pub removed: Vec<ChunkChange>,
+    /// Chunks whose section is still there with different text
+    pub modified: Vec<ChunkChange>,
+}
+
+/// A chunk added, removed or modified by a refresh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ChunkChange {
+    pub id: String,
+    /// ID the chunk had before the refresh, for modified chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+impl RefreshChanges {
+    /// Compare the chunks of a source before and after a refresh
+    ///
+    /// A chunk ID only changes with the chunk's text, so IDs present on both
+    /// sides are unchanged. Of the rest, a removed and an added chunk in the
+    /// same section (anchor and heading) count as one modified chunk.
+    pub fn between(before: &[Document], after: &[&Document]) -> Self {
+        let section_key = |doc: &Document| (doc.metadata.anchor.clone(), doc.section.clone());
+        let change = |doc: &Document, previous_id: Option<String>| ChunkChange {
+            id: doc.id.clone(),
+            previous_id,
+            section: doc.section.clone(),
+        };
+
+        let mut changes = Self::default();
+        let mut removed: Vec<&Document> = before
+            .iter()
+            .filter(|old| !after.iter().any(|new| new.id == old.id))
+            .collect();
+        for new in after {
+            if before.iter().any(|old| old.id == new.id) {
+                changes.unchanged += 1;
+                continue;
+            }
+            match removed
+                .iter()
+                .position(|old| section_key(old) == section_key(new))
+            {
+                Some(index) => {
+                    let old = removed.remove(index);
+                    changes.modified.push(change(new, Some(old.id.clone())));
+                }
+                None => changes.added.push(change(new, None)),
+            }
+        }
+        changes.removed = removed.into_iter().map(|old| change(old, None)).collect();
+        changes
+    }
+}
+
+/// An indexed page that is gone, moved or unreachable
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SourceProblem {
+    pub url: String,
+    pub problem: String, // "gone", "moved" or "unreachable"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirected_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub affected_documents: usize,
+}
+
+impl ToolResponse for ManageDocsResponse {
+    const TYPE_NAME: &'static str = "ManageDocsResponse";
+}
+
+impl ToolResponse for AnalyticsReport {
+    const TYPE_NAME: &'static str = "AnalyticsReport";
+}
+
+/// JSON schemas of all tool response types, keyed by type name
+pub fn output_schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        (SearchResponse::TYPE_NAME, schema_for!(SearchResponse)),
+        (AnalyticsReport::TYPE_NAME, schema_for!(AnalyticsReport)),
+        (AskDocsResponse::TYPE_NAME, schema_for!(AskDocsResponse)),
+        (ListDocsResponse::TYPE_NAME, schema_for!(ListDocsResponse)),
+        (CrawlResponse::TYPE_NAME, schema_for!(CrawlResponse)),
+        (CrawlPlanResponse::TYPE_NAME, schema_for!(CrawlPlanResponse)),
+        (
+            ImportUrlsResponse::TYPE_NAME,
+            schema_for!(ImportUrlsResponse),
+        ),
+        (
+            PreviewExtractionResponse::TYPE_NAME,
+            schema_for!(PreviewExtractionResponse),
+        ),
+        (FeedbackResponse::TYPE_NAME, schema_for!(FeedbackResponse)),
+        (EmbedTextResponse::TYPE_NAME, schema_for!(EmbedTextResponse)),
+        (MapDocsResponse::TYPE_NAME, schema_for!(MapDocsResponse)),
+        (
+            SummarizeSourceResponse::TYPE_NAME,
+            schema_for!(SummarizeSourceResponse),
+        ),
+        (GetDocResponse::TYPE_NAME, schema_for!(GetDocResponse)),
+        (
+            SuggestDocsResponse::TYPE_NAME,
+            schema_for!(SuggestDocsResponse),
+        ),
+        (ReloadResponse::TYPE_NAME, schema_for!(ReloadResponse)),
+        (
+            ListProjectsResponse::TYPE_NAME,
+            schema_for!(ListProjectsResponse),
+        ),
+        (
+            SwitchProjectResponse::TYPE_NAME,
+            schema_for!(SwitchProjectResponse),
+        ),
+        (
+            ManageDocsResponse::TYPE_NAME,
+            schema_for!(ManageDocsResponse),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_tool_result() {
+        let response = ManageDocsResponse::Delete {
+            target: "https://docs.rs/tokio".to_string(),
+            delete_by: "source".to_string(),
+            deleted_documents: 3,
+            dry_run: false,
+            total_documents_remaining: 10,
+            archived_documents: None,
+            backup: None,
+        };
+        let result = response.into_tool_result().unwrap();
+        assert_eq!(result.content.len(), 2);
+
+        // Structured content round-trips into the typed response
+        let resource = result.content[0].raw.as_resource().unwrap();
+        let ResourceContents::TextResourceContents {
+            uri,
+            mime_type,
+            text,
+        } = &resource.resource
+        else {
+            panic!("expected text resource");
+        };
+        assert_eq!(uri, "coderag://responses/ManageDocsResponse");
+        assert_eq!(mime_type.as_deref(), Some("application/json"));
+        let value: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(value["operation"], "delete");
+        assert_eq!(value["deleted_documents"], 3);
+        let parsed: ManageDocsResponse = serde_json::from_str(text).unwrap();
+        assert!(matches!(
+            parsed,
+            ManageDocsResponse::Delete {
+                deleted_documents: 3,
+                ..
+            }
+        ));
+
+        // Text fallback holds the same compact JSON
+        assert_eq!(&result.content[1].raw.as_text().unwrap().text, text);
+    }
+
+    #[test]
+    fn test_refresh_changes() {
+        let chunk = |id: &str, anchor: &str| {
+            Document::for_test(id)
+                .with_url("https://example.com/guide")
+                .with_section(anchor)
+                .with_metadata(crate::vectordb::DocumentMetadata {
+                    anchor: Some(anchor.to_string()),
+                    ..Default::default()
+                })
+        };
+        let before = vec![
+            chunk("guide#intro_00000001", "intro"),
+            chunk("guide#install_00000002", "install"),
+            chunk("guide#legacy_00000003", "legacy"),
+        ];
+        let after = [
+            chunk("guide#intro_00000001", "intro"),
+            chunk("guide#install_00000004", "install"),
+            chunk("guide#faq_00000005", "faq"),
+        ];
+        let changes = RefreshChanges::between(&before, &after.iter().collect::<Vec<_>>());
+
+        assert_eq!(changes.unchanged, 1);
+        assert_eq!(changes.modified.len(), 1);
+        assert_eq!(changes.modified[0].id, "guide#install_00000004");
+        assert_eq!(
+            changes.modified[0].previous_id.as_deref(),
+            Some("guide#install_00000002")
+        );
+        assert_eq!(changes.added[0].id, "guide#faq_00000005");
+        assert_eq!(changes.removed[0].id, "guide#legacy_00000003");
+    }
+
+    #[test]
+    fn test_output_schemas() {
+        let schemas = output_schemas();
+        assert_eq!(
+            schemas.keys().copied().collect::<Vec<_>>(),
+            [
+                "AnalyticsReport",
+                "AskDocsResponse",
+                "CrawlPlanResponse",
+                "CrawlResponse",
+                "EmbedTextResponse",
+                "FeedbackResponse",
+                "GetDocResponse",
+                "ImportUrlsResponse",
+                "ListDocsResponse",
+                "ListProjectsResponse",
+                "ManageDocsResponse",
+                "MapDocsResponse",
+                "PreviewExtractionResponse",
+                "ReloadResponse",
+                "SearchResponse",
+                "SuggestDocsResponse",
+                "SummarizeSourceResponse",
+                "SwitchProjectResponse",
+            ]
+        );
+        let search = serde_json::to_value(&schemas["SearchResponse"]).unwrap();
+        assert_eq!(search["type"], "object");
+        assert!(search["properties"]["truncated"].is_object());
+    }
+}
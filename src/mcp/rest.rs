@@ -0,0 +1,302 @@
+//! REST API exposing the knowledge base to non-MCP tooling.
+//!
+//! Handlers call the same tool implementations as the MCP server, so both
+//! interfaces share one VectorDatabase and EmbeddingService.
+
+use crate::mcp::sdk_server::{CrawlDocsParams, ListDocsParams, ManageDocsParams, SearchDocsParams};
+use crate::mcp::CodeRagServer;
+use crate::shutdown;
+use crate::telemetry;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rmcp::model::{CallToolResult, ErrorCode, RawContent, ResourceContents};
+use rmcp::Error as McpError;
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+struct DeleteSourceQuery {
+    url: String,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Build the REST router for a server
+pub fn router(server: CodeRagServer) -> Router {
+    Router::new()
+        .route("/search", get(search))
+        .route("/crawl", post(crawl))
+        .route("/sources", get(list_sources).delete(delete_source))
+        .with_state(server)
+}
+
+/// Serve the REST API until the process shuts down
+pub async fn serve(server: CodeRagServer, addr: SocketAddr) -> anyhow::Result<()> {
+    let mut app = router(server.clone());
+    if server.config().telemetry.metrics {
+        let handle = telemetry::install_prometheus()?;
+        app = app.route("/metrics", get(move || std::future::ready(handle.render())));
+        info!("📈 Serving Prometheus metrics at /metrics");
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("🌐 REST API listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown::token().cancelled())
+        .await?;
+    Ok(())
+}
+
+async fn search(
+    State(server): State<CodeRagServer>,
+    Query(params): Query<SearchDocsParams>,
+) -> Response {
+    tool_response(server.search_docs(params).await)
+}
+
+async fn crawl(
+    State(server): State<CodeRagServer>,
+    Json(params): Json<CrawlDocsParams>,
+) -> Response {
+    tool_response(server.crawl_docs(params).await)
+}
+
+async fn list_sources(
+    State(server): State<CodeRagServer>,
+    Query(params): Query<ListDocsParams>,
+) -> Response {
+    tool_response(server.list_docs(params).await)
+}
+
+async fn delete_source(
+    State(server): State<CodeRagServer>,
+    Query(query): Query<DeleteSourceQuery>,
+) -> Response {
+    let params = ManageDocsParams {
+        operation: "delete".to_string(),
+        target: query.url,
+        max_age_days: None,
+        dry_run: Some(query.dry_run),
+        crawl_mode: None,
+        crawl_focus: None,
+        max_pages: None,
+        sample_size: None,
+        delete_by: None,
+        priority: None,
+    };
+    tool_response(server.manage_docs(params).await)
+}
+
+/// Convert a tool result into an HTTP response, passing its JSON through
+fn tool_response(result: Result<CallToolResult, McpError>) -> Response {
+    match result {
+        Ok(result) => {
+            let body = result
+                .content
+                .first()
+                .and_then(|content| match &content.raw {
+                    RawContent::Text(text) => Some(text.text.clone()),
+                    RawContent::Resource(resource) => match &resource.resource {
+                        ResourceContents::TextResourceContents { text, .. } => Some(text.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .unwrap_or_else(|| "null".to_string());
+            ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+        }
+        Err(e) => {
+            // Refusals carry structured data the client can act on
+            let detail = [
+                ("policy_violation", StatusCode::FORBIDDEN),
+                ("quota_exceeded", StatusCode::INSUFFICIENT_STORAGE),
+            ]
+            .into_iter()
+            .find_map(|(key, status)| Some((key, status, e.data.as_ref()?.get(key)?.clone())));
+            let status = match &detail {
+                Some((_, status, _)) => *status,
+                None if e.code == ErrorCode::INVALID_PARAMS => StatusCode::BAD_REQUEST,
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let mut body = json!({ "error": e.message });
+            if let Some((key, _, value)) = detail {
+                body[key] = value;
+            }
+            (status, Json(body)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_dir::DataDir;
+    use crate::mcp::ServerOptions;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use rmcp::model::Content;
+    use serde_json::Value;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    /// A router over a server with its own data directory and project
+    async fn test_router(config: &str) -> (Router, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let data_dir = dir.path().join("data");
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(DataDir::at(&data_dir).config_path(), config).unwrap();
+        let options = ServerOptions {
+            project_root: Some(project),
+            ..Default::default()
+        };
+        let server = CodeRagServer::with_options(data_dir, options)
+            .await
+            .unwrap();
+        (router(server), dir)
+    }
+
+    async fn call(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<&str>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let (router, _dir) = test_router("[crawl_policy]\ndeny = [\"example.com\"]\n").await;
+
+        let (status, body) = call(&router, Method::GET, "/sources", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_sources"], 0);
+        assert!(body["sources"].as_array().unwrap().is_empty());
+
+        let (status, body) = call(
+            &router,
+            Method::DELETE,
+            "/sources?url=https://docs.rs/serde&dry_run=true",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        // Missing or malformed parameters
+        let (status, _) = call(&router, Method::GET, "/search", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(&router, Method::DELETE, "/sources", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(&router, Method::POST, "/crawl", Some("{\"url\":")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Refused by the crawl policy, with the violation in the body
+        let (status, body) = call(
+            &router,
+            Method::POST,
+            "/crawl",
+            Some(r#"{"url": "https://docs.example.com/guide"}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["policy_violation"]["rule"], "denied_domain");
+
+        let (status, _) = call(&router, Method::GET, "/nothing-here", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(&router, Method::PUT, "/sources", None).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_tool_response_status() {
+        let status_of = |error: McpError| tool_response(Err(error)).status();
+        assert_eq!(
+            status_of(McpError::invalid_request(
+                "denied",
+                Some(json!({ "policy_violation": { "rule": "denied_domain" } })),
+            )),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of(McpError::invalid_request(
+                "full",
+                Some(json!({ "quota_exceeded": { "size_bytes": 2, "max_bytes": 1 } })),
+            )),
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+        assert_eq!(
+            status_of(McpError::invalid_params("bad", None)),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status_of(McpError::invalid_request("refused", None)),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status_of(McpError::internal_error("broken", None)),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        // The structured detail is passed through
+        let response = tool_response(Err(McpError::invalid_request(
+            "full",
+            Some(json!({ "quota_exceeded": { "size_bytes": 2, "max_bytes": 1 } })),
+        )));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "full");
+        assert_eq!(body["quota_exceeded"]["max_bytes"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_response_body() {
+        let body_of = |content: Content| async move {
+            let response = tool_response(Ok(CallToolResult::success(vec![content])));
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Value>(&bytes).unwrap()
+        };
+
+        let resource = Content::resource(ResourceContents::TextResourceContents {
+            uri: "coderag://responses/ListDocsResponse".to_string(),
+            mime_type: Some("application/json".to_string()),
+            text: r#"{"total_sources":3}"#.to_string(),
+        });
+        assert_eq!(body_of(resource).await["total_sources"], 3);
+        assert_eq!(body_of(Content::text(r#"{"ok":true}"#)).await["ok"], true);
+
+        let empty = tool_response(Ok(CallToolResult::success(Vec::new())));
+        let bytes = axum::body::to_bytes(empty.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&bytes[..], b"null");
+    }
+}
@@ -0,0 +1,137 @@
+//! Filter inference for search_docs and ask_docs.
+//!
+//! Agents tend to put scoping into the query text instead of the filter
+//! parameters: "timeouts in tokio docs", "rust example of retrying a
+//! request", "breaking changes since version 2". Such phrases are recognized
+//! and turned into source, content type and version filters; the phrase is
+//! dropped from the text that gets embedded. Filters given explicitly are
+//! never overridden.
+
+use crate::mcp::responses::InferredFilters;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Query text after filter inference
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutedQuery {
+    /// The query without the phrases turned into filters
+    pub query: String,
+    pub filters: InferredFilters,
+}
+
+struct Patterns {
+    source: Regex,
+    example: Regex,
+    since: Regex,
+    before: Regex,
+    between: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let version = r"(?:version\s+|v)?(\d+(?:\.\d+)*)";
+        Patterns {
+            source: Regex::new(
+                r"(?i)\b(?:in|from|on|using|per)\s+(?:the\s+)?([a-z0-9][\w.\-]*)(?:'s)?\s+(?:docs|documentation|reference|guide|manual|book)\b",
+            )
+            .unwrap(),
+            example: Regex::new(
+                r"(?i)\b(?:an?\s+)?(?:code\s+)?(?:examples?|samples?|snippets?)\s+(?:of|for|showing|on)\b",
+            )
+            .unwrap(),
+            since: Regex::new(&format!(r"(?i)\b(?:since|after)\s+{}", version)).unwrap(),
+            before: Regex::new(&format!(r"(?i)\b(?:before|prior\s+to|until)\s+{}", version))
+                .unwrap(),
+            between: Regex::new(&format!(r"(?i)\bbetween\s+{}\s+and\s+{}", version, version))
+                .unwrap(),
+        }
+    })
+}
+
+/// Infer filters from a query
+///
+/// `is_source` decides whether a name such as "tokio" matches an indexed
+/// source; unknown names are left in the query, since filtering on them
+/// would return nothing.
+pub fn route(query: &str, is_source: impl Fn(&str) -> bool) -> RoutedQuery {
+    let patterns = patterns();
+    let mut text = query.to_string();
+    let mut filters = InferredFilters::default();
+
+    if let Some(captures) = patterns.source.captures(&text) {
+        let name = captures[1].to_lowercase();
+        if is_source(&name) {
+            text.replace_range(captures.get(0).unwrap().range(), "");
+            filters.source_filter = Some(name);
+        }
+    }
+
+    if let Some(found) = patterns.example.find(&text) {
+        text.replace_range(found.range(), "");
+        filters.content_type = Some("code".to_string());
+    }
+
+    // Change-oriented phrases only; "in version 1.4" also names documentation
+    // pages, while the version filter keeps changelog entries alone
+    if let Some(captures) = patterns.between.captures(&text) {
+        filters.version = Some(format!(">={}, <={}", &captures[1], &captures[2]));
+        text.replace_range(captures.get(0).unwrap().range(), "");
+    } else if let Some(captures) = patterns.since.captures(&text) {
+        filters.version = Some(format!(">{}", &captures[1]));
+        text.replace_range(captures.get(0).unwrap().range(), "");
+    } else if let Some(captures) = patterns.before.captures(&text) {
+        filters.version = Some(format!("<{}", &captures[1]));
+        text.replace_range(captures.get(0).unwrap().range(), "");
+    }
+
+    let cleaned = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    RoutedQuery {
+        // A query that was nothing but scoping still says what to look for
+        query: if cleaned.is_empty() {
+            query.to_string()
+        } else {
+            cleaned
+        },
+        filters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_with_tokio(query: &str) -> RoutedQuery {
+        route(query, |name| name == "tokio")
+    }
+
+    #[test]
+    fn test_route_source() {
+        let routed = route_with_tokio("timeout handling in the Tokio docs");
+        assert_eq!(routed.query, "timeout handling");
+        assert_eq!(routed.filters.source_filter.as_deref(), Some("tokio"));
+
+        // Names that aren't indexed stay part of the query
+        let routed = route_with_tokio("timeouts in async-std docs");
+        assert_eq!(routed.query, "timeouts in async-std docs");
+        assert_eq!(routed.filters, InferredFilters::default());
+    }
+
+    #[test]
+    fn test_route_content_type_and_version() {
+        let routed = route_with_tokio("rust example of retrying a request");
+        assert_eq!(routed.query, "rust retrying a request");
+        assert_eq!(routed.filters.content_type.as_deref(), Some("code"));
+
+        let routed = route_with_tokio("breaking changes since version 2");
+        assert_eq!(routed.query, "breaking changes");
+        assert_eq!(routed.filters.version.as_deref(), Some(">2"));
+
+        let routed = route_with_tokio("deprecations between v1.0 and 1.4");
+        assert_eq!(routed.filters.version.as_deref(), Some(">=1.0, <=1.4"));
+
+        let routed = route_with_tokio("how do I spawn a task");
+        assert_eq!(routed.query, "how do I spawn a task");
+        assert_eq!(routed.filters, InferredFilters::default());
+    }
+}
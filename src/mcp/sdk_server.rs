@@ -1,24 +1,89 @@
-use crate::crawler::{CrawlConfig, CrawlMode, DocumentationFocus};
-use crate::project_manager::{ProjectInfo, ProjectManager};
-use crate::vectordb::{SearchOptions, VectorDatabase};
-use crate::EmbeddingService;
-use rmcp::{model::*, tool, Error as McpError, ServerHandler};
-use serde::{Deserialize, Serialize};
+use crate::analytics::{AnalyticsReport, QueryFilters, QueryLog, QueryLogEntry};
+use crate::archive::Archive;
+use crate::backups::BackupStore;
+use crate::config::Config;
+use crate::crawl_policy::{CrawlUsage, PolicyViolation};
+use crate::crawler::auth::CrawlAuth;
+use crate::crawler::blob_store::BlobStore;
+use crate::crawler::changelog;
+use crate::crawler::chunker::ChunkIds;
+use crate::crawler::fetch::{build_client, read_page};
+use crate::crawler::page_store::{PageStore, StoredPage};
+use crate::crawler::verify::{self, PageProblem};
+use crate::crawler::{
+    CrawlConfig, CrawlMode, Crawler, DocumentationFocus, SkippedPage, UrlPatterns,
+    EXTRACTOR_VERSION,
+};
+use crate::data_dir::{self, DataDir};
+use crate::embedding_basic::EmbeddingProfile;
+use crate::error::CodeRagError;
+use crate::mcp::ask;
+use crate::mcp::prompts::PromptTemplate;
+use crate::mcp::responses::{
+    AskDocsResponse, ChunkRef, CrawlPlanResponse, CrawlResponse, DocChunk, DocPage,
+    EmbedTextResponse, FeedbackResponse, GetDocResponse, ImportUrlsResponse, InferredFilters,
+    ListDocsResponse, ListProjectsResponse, ManageDocsResponse, MapDocsResponse,
+    PreviewExtractionResponse, ProjectContext, ProjectSummary, RefreshChanges, ReloadResponse,
+    SearchHit, SearchResponse, SectionSummary, SourceProblem, SourceSummary, SuggestDocsResponse,
+    SummarizeSourceResponse, SwitchProjectResponse, ToolResponse, TopicDocument, TopicSummary,
+};
+use crate::mcp::router;
+use crate::mcp::summarize;
+use crate::mcp::watch;
+use crate::project_manager::{DatabaseRoutes, ProjectInfo, ProjectManager, ProjectRegistry};
+use crate::shutdown;
+use crate::sources::{CrawlSettings, SourceRegistry};
+use crate::telemetry;
+use crate::url_import::{parse_url_list, ImportCheckpoint, ImportResult};
+use crate::vectordb::{
+    collapse_duplicates, cosine_similarity, has_quoted_phrase, merge_layered_results, source_root,
+    DatabaseLock, Document, HybridSearchOptions, LayeredSearchResult, OpenMode, Provenance,
+    SearchBudget, SearchOptions, SearchResult, VectorDatabase, VectorStorage, VersionRange,
+};
+use crate::{EmbeddingService, ModelStatus};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::{
+    model::*, service::RequestContext, tool, Error as McpError, Peer, RoleServer, ServerHandler,
+};
+use serde::Deserialize;
 use serde_json::json;
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::info;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::{debug, info, warn};
 use url::Url;
 
+/// Hits fetched per requested result, so duplicates can be collapsed without running short
+const DUPLICATE_HEADROOM: usize = 2;
+
+/// Origin of hits from the package database a search's `path` routes to
+const PACKAGE_ORIGIN: &str = "package";
+
+/// Bytes in a megabyte, for `database.max_size_mb`
+const MB: u64 = 1024 * 1024;
+
+/// How often search counts of sources are saved
+const ACCESS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct SearchDocsParams {
     pub query: String,
-    #[serde(default = "default_limit")]
-    pub limit: usize,
+    pub limit: Option<usize>, // Defaults to the configured search limit
     pub source_filter: Option<String>,
     pub content_type: Option<String>,
+    pub layered: Option<bool>, // Also search the global database when inside a project
+    pub scope: Option<String>, // Workspace member whose dependencies bound the search
+    pub lang: Option<String>,  // Natural language of the docs, e.g. "ja"
+    pub section: Option<String>, // Section path, e.g. "Configuration > TLS"
+    pub version: Option<String>, // Changelog release range, e.g. ">1.2" or ">=1.0, <2.0"
+    pub time_budget_ms: Option<u64>, // Return partial results after this long
+    pub include_archived: Option<bool>, // Also search documents archived by refresh, delete and expire
+    pub recency_half_life_days: Option<f32>, // Boost recently updated documents, halving every this many days
+    pub exclude_sources: Option<Vec<String>>, // Skip documents whose URL contains any of these
+    pub exclude_tags: Option<Vec<String>>,   // Skip documents with any of these tags
+    pub path: Option<String>, // File or directory being worked on; favors the docs of the package containing it
 }
 
 fn default_limit() -> usize {
@@ -32,390 +97,2512 @@ pub struct CrawlDocsParams {
     pub mode: String,
     #[serde(default = "default_focus")]
     pub focus: String,
-    #[serde(default = "default_max_pages")]
-    pub max_pages: usize,
+    pub max_pages: Option<usize>, // Defaults to the configured crawler max_pages
+    pub confirmed: Option<bool>,  // The user approved this crawl, when the crawl policy asks for it
+    pub dry_run: Option<bool>,    // List the URLs the crawl would fetch instead of indexing them
+    pub include_patterns: Option<Vec<String>>, // Only follow links matching one: substring, glob ("/docs/**") or "re:<regex>"
+    pub exclude_patterns: Option<Vec<String>>, // Never follow links matching one, e.g. "/blog/**"
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct ManageDocsParams {
-    pub operation: String, // "delete", "expire", or "refresh"
-    pub target: String,    // URL or document ID
+    pub operation: String, // "delete", "expire", "evict", "refresh", "verify", "compact", "restore", or "prioritize"
+    pub target: String, // URL, URL prefix, document ID, or tag; for verify a URL prefix, or "*" for all sources
     pub max_age_days: Option<u64>,
     pub dry_run: Option<bool>,
     pub crawl_mode: Option<String>,
     pub crawl_focus: Option<String>,
     pub max_pages: Option<usize>,
+    pub sample_size: Option<usize>, // Pages checked per source by verify
+    pub delete_by: Option<String>,  // "source" (default), "id", "prefix", "tag", or "session"
+    pub priority: Option<f32>,      // Search weight set by prioritize; omit to reset to 1.0
 }
 
-fn default_mode() -> String {
-    "single".to_string()
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct AskDocsParams {
+    pub question: String,
+    pub token_budget: Option<usize>, // Approximate token size of the assembled context
+    pub source_filter: Option<String>,
+    pub layered: Option<bool>,
+    pub scope: Option<String>,
+    pub lang: Option<String>,
+    pub section: Option<String>,
+    pub version: Option<String>,
+    pub path: Option<String>,
 }
 
-fn default_focus() -> String {
-    "all".to_string()
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct EmbedTextParams {
+    pub text: String,
+    pub compare_to: Option<String>, // Embedded as a document passage and compared with `text`
+    pub as_query: Option<bool>,     // Embed `text` as a search query (default) or a passage
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PreviewExtractionParams {
+    pub url: String,
+    pub focus: Option<String>, // Documentation focus as for crawl_docs (default "all")
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ImportUrlsParams {
+    pub urls: Option<Vec<String>>, // URLs to index, one page each
+    pub text: Option<String>,      // A URL list or bookmarks export to pick http(s) URLs out of
+    pub focus: Option<String>,     // Documentation focus as for crawl_docs (default "all")
+    pub limit: Option<usize>, // URLs to index in this call; import the same list again to continue
+    pub restart: Option<bool>, // Import every URL again instead of resuming an earlier import of the list
+    pub confirmed: Option<bool>, // The user approved importing the list, when the crawl policy asks for it
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SwitchProjectParams {
+    pub project: String, // Project root or name, as reported by list_projects
+}
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct ListDocsParams {
+    pub offset: Option<usize>,  // Sources skipped, for paging
+    pub limit: Option<usize>,   // Sources per page (default 50)
+    pub sort: Option<String>,   // "url" (default), "documents", or "last_crawled"
+    pub source: Option<String>, // URL prefix; lists its pages with their sections and chunk counts
+}
+
+/// Sources listed per list_docs page unless the caller asks otherwise
+const DEFAULT_LIST_LIMIT: usize = 50;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct MapDocsParams {
+    pub topics: Option<usize>, // Number of topics; defaults to one scaled to the database size
+    pub representatives: Option<usize>, // Representative documents per topic (default 3)
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SummarizeSourceParams {
+    pub source: String, // Page URL or URL prefix, e.g. "https://docs.rs/tokio"
+    pub max_points: Option<usize>, // Chunks the summary is built from (default 8)
 }
 
-fn default_max_pages() -> usize {
-    100
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct FeedbackParams {
+    pub id: String,    // Document ID from a search result
+    pub helpful: bool, // true boosts the document, false buries it
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchAnalyticsParams {
+    #[serde(default = "default_analytics_limit")]
+    pub limit: usize, // Queries listed per ranking
+}
+
+fn default_analytics_limit() -> usize {
+    10
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetDocParams {
+    pub id: Option<String>,  // Document (chunk) ID from a search result
+    pub url: Option<String>, // Page URL; returns all chunks reassembled in order
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RelatedDocsParams {
+    pub id: String, // Document ID from a previous search result
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_mode() -> String {
+    "single".to_string()
 }
 
-#[derive(Debug, Serialize)]
-pub struct SearchResult {
-    pub id: String,
-    pub content: String,
-    pub score: f32,
-    pub metadata: serde_json::Value,
+fn default_focus() -> String {
+    "all".to_string()
 }
 
+/// MCP server over the documentation databases
+///
+/// Locks are always taken in one order, so requests and [`shutdown`](Self::shutdown)
+/// can't deadlock: the embedding service, the primary database, the global
+/// database, package databases, then the source registry.
 #[derive(Clone)]
 pub struct CodeRagServer {
     embedding_service: Arc<Mutex<EmbeddingService>>,
     vector_db: Arc<Mutex<VectorDatabase>>,
-    #[allow(dead_code)]
+    /// Global database, searched alongside the project database when layered
+    global_db: Option<Arc<Mutex<VectorDatabase>>>,
     project_manager: Arc<ProjectManager>,
-    project_info: Arc<ProjectInfo>,
+    config: Arc<Config>,
+    sources: Arc<Mutex<SourceRegistry>>,
+    /// The primary database's project and stores, replaced by switch_project
+    active: Arc<RwLock<Arc<ActiveDatabase>>>,
+    open_mode: OpenMode,
+    /// Loading progress of the embedding model, readable while a call holds the service
+    model_status: tokio::sync::watch::Receiver<ModelStatus>,
+    /// Least severe logging notification the client wants, set by `logging/setLevel`
+    log_level: Arc<RwLock<LoggingLevel>>,
 }
 
-#[tool(tool_box)]
-impl CodeRagServer {
-    pub async fn new(data_dir: PathBuf) -> anyhow::Result<Self> {
-        info!("🚀 Initializing CodeRAG server...");
-        info!("📂 Global data directory: {:?}", data_dir);
+/// Project context and stores that belong to the primary database
+struct ActiveDatabase {
+    project_info: ProjectInfo,
+    query_log: QueryLog,
+    backups: BackupStore,
+    /// Where refreshed and deleted documents go when `database.archive` is set
+    archive: Archive,
+    /// Where crawled pages' extracted content is kept, when `database.store_pages` is set
+    page_store: Option<PageStore>,
+    /// Where fetched page bodies are snapshotted, when `database.snapshots` is set
+    blob_store: Option<BlobStore>,
+    /// Write lock on the database; None when this instance opened it read-only
+    db_lock: Option<DatabaseLock>,
+    /// Workspace members with databases of their own
+    routes: DatabaseRoutes,
+    /// Member databases loaded so far, by path
+    package_dbs: Mutex<HashMap<PathBuf, Arc<Mutex<VectorDatabase>>>>,
+}
 
-        // Initialize project manager
-        let project_manager = ProjectManager::new(data_dir);
-        let project_info = project_manager.get_project_info();
+impl ActiveDatabase {
+    fn new(
+        project_info: ProjectInfo,
+        project_manager: &ProjectManager,
+        config: &Config,
+        db_lock: Option<DatabaseLock>,
+    ) -> Self {
+        let db_path = &project_info.database_path;
+        Self {
+            routes: project_info
+                .project_root
+                .as_deref()
+                .map(|root| project_manager.database_routes(root))
+                .unwrap_or_default(),
+            package_dbs: Mutex::new(HashMap::new()),
+            query_log: QueryLog::for_database(db_path),
+            backups: BackupStore::for_database(db_path, config.backup.keep),
+            archive: Archive::for_database(db_path),
+            page_store: config.page_store(db_path),
+            blob_store: config.blob_store(db_path),
+            db_lock,
+            project_info,
+        }
+    }
 
-        if project_info.is_project {
-            info!(
-                "📁 Detected project: {}",
-                project_info
-                    .project_name
-                    .as_ref()
-                    .unwrap_or(&"unknown".to_string())
-            );
-            info!("📂 Project root: {:?}", project_info.project_root);
-            info!(
-                "💾 Using project-local database: {:?}",
-                project_info.database_path
-            );
-        } else {
-            info!("🌍 No project detected, using global database");
-            info!("💾 Global database path: {:?}", project_info.database_path);
+    /// Bytes the database and everything kept for it occupy on disk
+    ///
+    /// A database in a data directory counts the whole directory; any other
+    /// counts its backups, archive, stored pages and snapshots. The databases
+    /// of workspace members count too.
+    fn disk_usage(&self, vector_db: &VectorDatabase) -> u64 {
+        let db_path = &self.project_info.database_path;
+        let own = match DataDir::containing(db_path) {
+            Some(dir) => data_dir::disk_usage(dir.root()),
+            None => {
+                let mut paths = vec![self.backups.dir(), self.archive.path()];
+                paths.extend(self.page_store.as_ref().map(PageStore::dir));
+                paths.extend(self.blob_store.as_ref().map(BlobStore::dir));
+                vector_db.disk_usage() + paths.into_iter().map(data_dir::disk_usage).sum::<u64>()
+            }
+        };
+        let packages: u64 = self
+            .routes
+            .nested()
+            .iter()
+            .filter_map(|nested| nested.database_path.parent())
+            .map(data_dir::disk_usage)
+            .sum();
+        own + packages
+    }
+}
+
+/// Sources to evict, least recently used first, to bring `size` under 90% of `max_bytes`
+///
+/// What a source frees is estimated from its share of the database file. The
+/// source being crawled is kept. None if nothing can be evicted, or evicting
+/// everything that can still wouldn't get under the limit, e.g. when backups
+/// take up most of it.
+fn sources_to_evict(
+    vector_db: &VectorDatabase,
+    least_recently_used: Vec<String>,
+    size: u64,
+    max_bytes: u64,
+    crawl_url: &str,
+) -> Option<Vec<String>> {
+    let bytes_per_document = vector_db.disk_usage() / vector_db.document_count().max(1) as u64;
+    let pages: Vec<(String, usize)> = vector_db
+        .get_documents_by_source()
+        .into_iter()
+        .map(|(url, documents)| (url, documents.len()))
+        .collect();
+    let target = max_bytes / 10 * 9;
+    let mut freed = 0;
+    let mut evicted = Vec::new();
+    for source in least_recently_used {
+        if size.saturating_sub(freed) < target {
+            break;
+        }
+        if crawl_url.starts_with(&source) {
+            continue;
         }
+        let documents: usize = pages
+            .iter()
+            .filter(|(url, _)| url.starts_with(&source))
+            .map(|(_, count)| count)
+            .sum();
+        freed += documents as u64 * bytes_per_document;
+        evicted.push(source);
+    }
+    (!evicted.is_empty() && size.saturating_sub(freed) < max_bytes).then_some(evicted)
+}
+
+/// Open the primary database, taking its write lock as `open_mode` asks
+///
+/// Binds the embedding service to the profile the database was indexed with.
+fn open_primary(
+    db_path: &Path,
+    open_mode: OpenMode,
+    config: &Config,
+    embedding_service: &mut EmbeddingService,
+) -> anyhow::Result<(VectorDatabase, Option<DatabaseLock>)> {
+    let db_lock = DatabaseLock::for_mode(db_path, open_mode)?;
+    if db_lock.is_none() {
+        info!("🔒 Opening database read-only (open mode: {:?})", open_mode);
+    }
+    let mut vector_db = VectorDatabase::new(db_path)?;
+    if let Some(store) = config.vector_store(db_path)? {
+        info!("🔌 Vector searches go to {}", store.name());
+        vector_db = vector_db.with_vector_store(store);
+    }
+
+    // A database that fails validation must not be replaced by an empty one on the next save
+    if db_path.exists() {
+        vector_db.load()?;
+        info!("✅ Loaded existing vector database");
+    } else {
+        info!("📝 Starting with empty vector database");
+    }
+    embedding_service.bind_database(db_path, vector_db.document_count())?;
+    let profile = embedding_service.profile();
+    vector_db.bind_model(&profile.model, profile.dimension)?;
+    Ok((vector_db, db_lock))
+}
 
-        info!("⏳ Creating embedding service (model downloads on first search)...");
-        let embedding_service = EmbeddingService::new().await?;
+/// Locks a shut down server holds so no request writes after its final save
+#[must_use = "requests can write again once the locks are dropped"]
+pub struct ShutdownLocks {
+    _databases: Vec<OwnedMutexGuard<VectorDatabase>>,
+    _sources: OwnedMutexGuard<SourceRegistry>,
+}
 
-        info!("📊 Initializing vector database...");
-        let db_path = project_manager.get_database_path()?;
-        let mut vector_db = VectorDatabase::new(&db_path)?;
+/// Command-line overrides applied when creating a server
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Database open mode, overriding `database.open_mode`
+    pub open_mode: Option<OpenMode>,
+    /// Load the embedding model at startup, as if `embedding.preload` were set
+    pub preload_model: bool,
+    /// Serve this project instead of the one around the working directory
+    pub project_root: Option<PathBuf>,
+}
 
-        // Try to load existing data
-        match vector_db.load() {
-            Ok(_) => info!("✅ Loaded existing vector database"),
-            Err(_) => info!("📝 Starting with empty vector database"),
+/// Load the embedding model in the background so the first search doesn't wait for it
+///
+/// Requests that need the model meanwhile wait for the warm-up to finish. If it
+/// fails, the model is loaded again on first use and reports the error there.
+fn spawn_model_warm_up(embedding_service: Arc<Mutex<EmbeddingService>>) {
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let embedding_service = embedding_service.lock().await;
+        match embedding_service.warm_up().await {
+            Ok(()) => info!(
+                "🔥 Embedding model preloaded in {:.1}s",
+                started.elapsed().as_secs_f64()
+            ),
+            Err(e) => warn!("⚠️ Could not preload embedding model: {}", e),
         }
+    });
+}
 
-        info!("✅ CodeRAG server initialization complete!");
+/// Parse the version range parameter of a search
+/// Content type named by a `content_type` parameter: "documentation", "code" or "api"
+fn content_type_filter(content_type: Option<&str>) -> Option<crate::vectordb::ContentType> {
+    match content_type? {
+        "documentation" => Some(crate::vectordb::ContentType::Documentation),
+        "code" => Some(crate::vectordb::ContentType::CodeExample),
+        "api" => Some(crate::vectordb::ContentType::Reference),
+        _ => None,
+    }
+}
 
-        Ok(Self {
-            embedding_service: Arc::new(Mutex::new(embedding_service)),
-            vector_db: Arc::new(Mutex::new(vector_db)),
-            project_manager: Arc::new(project_manager),
-            project_info: Arc::new(project_info),
-        })
+/// Summary of one indexed page; with `detailed`, also its sections and their chunk counts
+fn source_summary(url: String, documents: &[&Document], detailed: bool) -> SourceSummary {
+    let mut sections: Vec<SectionSummary> = Vec::new();
+    if detailed {
+        let mut chunks = documents.to_vec();
+        chunks.sort_by_key(|doc| doc.chunk_position().map_or(usize::MAX, |(index, _)| index));
+        for section in chunks.iter().filter_map(|doc| doc.section.as_deref()) {
+            match sections.last_mut() {
+                Some(last) if last.section == section => last.chunks += 1,
+                _ => sections.push(SectionSummary {
+                    section: section.to_string(),
+                    chunks: 1,
+                }),
+            }
+        }
     }
 
-    #[tool(
-        description = "Search your indexed documentation using semantic search. Use this tool when you need current, accurate information about programming frameworks, libraries, APIs, or coding concepts to help with development tasks. This is YOUR resource - use it proactively when you encounter unfamiliar technologies or need to verify current best practices."
-    )]
-    async fn search_docs(
-        &self,
-        #[tool(aggr)] params: SearchDocsParams,
-    ) -> Result<CallToolResult, McpError> {
-        let embedding_service = self.embedding_service.lock().await;
-        let vector_db = self.vector_db.lock().await;
+    SourceSummary {
+        title: documents.iter().find_map(|doc| doc.title.clone()),
+        last_updated: documents
+            .iter()
+            .filter_map(|doc| doc.metadata.last_updated)
+            .max()
+            .map(|time| {
+                chrono::DateTime::<chrono::Utc>::from(time)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+            }),
+        documents: documents.len(),
+        sections,
+        url,
+    }
+}
+
+/// Error for a crawl the crawl policy refuses, carrying the violation as structured data
+fn policy_error(violation: PolicyViolation) -> McpError {
+    McpError::invalid_request(
+        violation.message.clone(),
+        Some(json!({ "policy_violation": violation })),
+    )
+}
+
+/// Error for a crawl refused because the database is over its size limit
+fn quota_error(size_bytes: u64, max_bytes: u64) -> McpError {
+    McpError::invalid_request(
+        format!(
+            "The database is full ({:.1} MB of its {} MB limit); delete or expire documentation \
+             with manage_docs before crawling more",
+            size_bytes as f64 / MB as f64,
+            max_bytes / MB
+        ),
+        Some(json!({
+            "quota_exceeded": { "size_bytes": size_bytes, "max_bytes": max_bytes }
+        })),
+    )
+}
+
+/// Link patterns of a crawl_docs call, replacing the built-in ones if any are given
+fn url_patterns(params: &CrawlDocsParams) -> Result<UrlPatterns, McpError> {
+    UrlPatterns::custom(
+        params.include_patterns.as_deref().unwrap_or_default(),
+        params.exclude_patterns.as_deref().unwrap_or_default(),
+    )
+    .map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+fn version_range(version: Option<&str>) -> Result<Option<VersionRange>, McpError> {
+    version
+        .map(VersionRange::parse)
+        .transpose()
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))
+}
+
+impl CodeRagServer {
+    /// Start the time budget of a search, if the request or configuration sets one
+    fn search_budget(&self, time_budget_ms: Option<u64>) -> Option<SearchBudget> {
+        time_budget_ms
+            .or(self.config.search.time_budget_ms)
+            .map(|ms| SearchBudget::new(Duration::from_millis(ms)))
+    }
 
+    /// Run a search as configured, labeling each hit with its database origin
+    ///
+    /// Also returns the stage that used up the search's time budget, if it ran out.
+    async fn search_hits(
+        &self,
+        params: SearchDocsParams,
+    ) -> Result<(Vec<LayeredSearchResult>, Option<&'static str>), McpError> {
         let SearchDocsParams {
             query,
             limit,
             source_filter,
             content_type,
+            layered,
+            scope,
+            lang,
+            section,
+            version,
+            time_budget_ms,
+            include_archived,
+            recency_half_life_days,
+            exclude_sources,
+            exclude_tags,
+            path,
         } = params;
+        let budget = self.search_budget(time_budget_ms);
+        self.sync_with_disk().await;
+        let package_db = match &path {
+            Some(path) => self.package_database(path).await,
+            None => None,
+        };
 
-        // Generate embedding for query
-        let query_embedding = embedding_service
-            .embed(&query)
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        // Fetch extra hits so copies of one chunk on several mirrors can be collapsed
+        let limit = limit.unwrap_or(self.config.search.limit);
+        let collapse = |hits: Vec<LayeredSearchResult>| {
+            let mut hits = collapse_duplicates(hits);
+            hits.truncate(limit);
+            hits
+        };
 
         // Prepare search options
-        let options = SearchOptions {
-            limit,
-            min_score: None,
+        let mut options = SearchOptions {
+            limit: limit * DUPLICATE_HEADROOM,
+            min_score: self.config.search.min_score,
             source_filter,
-            content_type_filter: content_type.and_then(|ct| match ct.as_str() {
-                "documentation" => Some(crate::vectordb::ContentType::Documentation),
-                "code" => Some(crate::vectordb::ContentType::CodeExample),
-                "api" => Some(crate::vectordb::ContentType::Reference),
-                _ => None,
-            }),
+            content_type_filter: content_type_filter(content_type.as_deref()),
+            ef_search: None,
+            allowed_urls: None,
+            lang_filter: lang,
+            section_filter: section,
+            version_filter: version_range(version.as_deref())?,
+            budget: budget.clone(),
+            source_weights: self.sources.lock().await.priorities(),
+            recency_half_life_days: recency_half_life_days
+                .or(self.config.search.recency_half_life_days),
+            exclude_sources: exclude_sources.unwrap_or_default(),
+            exclude_tags: exclude_tags.unwrap_or_default(),
+        };
+        let layered = layered.unwrap_or(self.config.search.layered);
+        let exceeded_in = || budget.as_ref().and_then(SearchBudget::exceeded_in);
+        let archive = if include_archived.unwrap_or(false) {
+            self.open_archive()?
+        } else {
+            None
         };
 
-        // Search for similar documents
-        let results = vector_db
-            .search(&query_embedding, options)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        // Quoted phrases need exact keyword matching, which only hybrid search does
+        if has_quoted_phrase(&query) {
+            let hits = self
+                .hybrid_hits(
+                    &query,
+                    options,
+                    layered,
+                    scope,
+                    package_db,
+                    archive.as_ref(),
+                )
+                .await?;
+            return Ok((collapse(hits), exceeded_in()));
+        }
 
-        // Convert results to response format
-        let search_results: Vec<SearchResult> = results
-            .into_iter()
-            .map(|r| SearchResult {
-                id: r.document.id,
-                content: r.document.content,
-                score: r.score,
-                metadata: serde_json::to_value(r.document.metadata).unwrap_or(json!({})),
-            })
-            .collect();
+        let embedding_service = self.embedding_service.lock().await;
+        let vector_db = self.vector_db.lock().await;
 
-        let response_json = serde_json::to_string_pretty(&search_results)
+        // Generate embedding for query
+        let query_embedding = embedding_service
+            .embed_query(&query)
+            .await
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        options.budget_exceeded("embedding");
 
-        Ok(CallToolResult::success(vec![Content::text(response_json)]))
-    }
-
-    #[tool(
-        description = "List all currently indexed documentation sources and their document counts. Use this tool to see what documentation is available in your knowledge base before searching, or to check if you need to crawl additional sources for a particular technology or framework."
-    )]
-    async fn list_docs(&self) -> Result<CallToolResult, McpError> {
-        let vector_db = self.vector_db.lock().await;
+        // Restrict to documentation of the scoped member's dependencies
+        if let Some(scope) = scope {
+            options.allowed_urls = Some(self.scope_sources(&scope, &vector_db).await?);
+        }
 
-        // Get documents grouped by source
-        let docs_by_source = vector_db.get_documents_by_source();
+        let primary_origin = if self.active().project_info.is_project {
+            "project"
+        } else {
+            "global"
+        };
 
-        // Build summary
-        let mut summary = HashMap::new();
-        for (source, documents) in docs_by_source {
-            summary.insert(source, documents.len());
+        // Search the primary database, under the package database the path routes to,
+        // layered over the global one when available and over the archive when asked to
+        let global_db = match (&self.global_db, layered) {
+            (Some(global_db), true) => Some(global_db.lock().await),
+            _ => None,
+        };
+        let mut package_db = match &package_db {
+            Some(db) => Some(db.lock().await),
+            None => None,
+        };
+        if let Some(db) = &mut package_db {
+            watch::reload_if_changed(db, PACKAGE_ORIGIN);
         }
+        let mut layers: Vec<(&str, &VectorDatabase, f32)> = Vec::new();
+        if let Some(db) = &package_db {
+            layers.push((PACKAGE_ORIGIN, db, self.config.search.package_weight));
+        }
+        layers.push((primary_origin, &vector_db, 1.0));
+        if let Some(global_db) = &global_db {
+            layers.push(("global", global_db, 1.0));
+        }
+        if let Some(archive) = &archive {
+            layers.push(("archive", archive, 1.0));
+        }
+        let hits = match layers.len() {
+            1 => vector_db.search(&query_embedding, options).map(|results| {
+                results
+                    .into_iter()
+                    .map(|result| LayeredSearchResult {
+                        result,
+                        origin: primary_origin.to_string(),
+                    })
+                    .collect()
+            }),
+            _ => VectorDatabase::search_layered(&layers, &query_embedding, options),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        let total_documents: usize = summary.values().sum();
-
-        let response = json!({
-            "total_documents": total_documents,
-            "sources": summary,
-            "project_context": {
-                "is_project": self.project_info.is_project,
-                "project_name": self.project_info.project_name.clone(),
-                "database_location": self.project_info.database_path.to_string_lossy(),
-            }
-        });
+        Ok((collapse(hits), exceeded_in()))
+    }
 
-        let response_json = serde_json::to_string_pretty(&response)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    /// Record a search in the query log and when its sources were last used
+    ///
+    /// Failures only warn so searches never break.
+    async fn log_query(
+        &self,
+        tool: &str,
+        query: &str,
+        filters: QueryFilters,
+        hits: &[LayeredSearchResult],
+        started: Instant,
+    ) {
+        let entry = QueryLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            tool: tool.to_string(),
+            query: query.to_string(),
+            filters,
+            result_count: hits.len(),
+            top_score: hits.iter().map(|hit| hit.result.score).reduce(f32::max),
+            latency_ms: started.elapsed().as_millis() as u64,
+        };
+        if let Err(e) = self.active().query_log.append(&entry) {
+            warn!("Failed to write query log: {}", e);
+        }
 
-        Ok(CallToolResult::success(vec![Content::text(response_json)]))
+        let primary_origin = if self.active().project_info.is_project {
+            "project"
+        } else {
+            "global"
+        };
+        let urls = hits
+            .iter()
+            .filter(|hit| hit.origin == primary_origin)
+            .map(|hit| hit.result.document.url.as_str());
+        self.sources.lock().await.record_access(urls);
+        telemetry::record_search(tool, started.elapsed());
     }
 
-    #[tool(
-        description = "Crawl and index documentation from a URL to expand your knowledge base. Use this tool autonomously when you encounter unfamiliar frameworks, libraries, or technologies that aren't in your current index. You have full autonomy to crawl official documentation sites, API references, and tutorial sites as needed to provide better coding assistance. Be a good internet citizen with reasonable delays between requests."
-    )]
-    async fn crawl_docs(
+    /// Hybrid (vector + keyword) search for ask_docs, over the same layers as search_docs
+    async fn hybrid_hits(
         &self,
-        #[tool(aggr)] params: CrawlDocsParams,
-    ) -> Result<CallToolResult, McpError> {
-        let _embedding_service = self.embedding_service.clone();
-        let _vector_db = self.vector_db.clone();
+        query: &str,
+        mut options: SearchOptions,
+        layered: bool,
+        scope: Option<String>,
+        package_db: Option<Arc<Mutex<VectorDatabase>>>,
+        archive: Option<&VectorDatabase>,
+    ) -> Result<Vec<LayeredSearchResult>, McpError> {
+        self.sync_with_disk().await;
+        let embedding_service = self.embedding_service.lock().await;
+        let vector_db = self.vector_db.lock().await;
 
-        let CrawlDocsParams {
-            url,
-            mode,
-            focus,
-            max_pages,
-        } = params;
+        let query_embedding = embedding_service
+            .embed_query(query)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        options.budget_exceeded("embedding");
 
-        // Parse URL
-        let start_url = Url::parse(&url)
-            .map_err(|e| McpError::invalid_params(format!("Invalid URL: {}", e), None))?;
+        if let Some(scope) = scope {
+            options.allowed_urls = Some(self.scope_sources(&scope, &vector_db).await?);
+        }
 
-        // Parse crawl mode
-        let crawl_mode = match mode.as_str() {
-            "single" => CrawlMode::SinglePage,
-            "section" => CrawlMode::Section,
-            "full" => CrawlMode::FullDocs,
-            _ => {
-                return Err(McpError::invalid_params(
-                    format!(
-                        "Invalid mode: {}. Must be 'single', 'section', or 'full'",
-                        mode
-                    ),
-                    None,
-                ))
-            }
+        let primary_origin = if self.active().project_info.is_project {
+            "project"
+        } else {
+            "global"
         };
 
-        // Parse documentation focus
-        let doc_focus = match focus.as_str() {
-            "api" => DocumentationFocus::ApiReference,
-            "examples" => DocumentationFocus::Examples,
-            "changelog" => DocumentationFocus::Changelog,
-            "quickstart" => DocumentationFocus::QuickStart,
-            "all" => DocumentationFocus::All,
-            _ => return Err(McpError::invalid_params(
-                format!("Invalid focus: {}. Must be 'api', 'examples', 'changelog', 'quickstart', or 'all'", focus),
-                None,
-            )),
+        let limit = options.limit;
+        let hybrid = |db: &VectorDatabase| {
+            db.hybrid_search(
+                &query_embedding,
+                query,
+                HybridSearchOptions {
+                    base: options.clone(),
+                    fusion_strategy: self.config.search.fusion,
+                    ..HybridSearchOptions::default()
+                },
+            )
+            .map(|results| {
+                results
+                    .into_iter()
+                    .map(|r| SearchResult {
+                        document: r.document,
+                        score: r.combined_score,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .map_err(|e| McpError::internal_error(e.to_string(), None))
         };
 
-        info!("Starting crawl of {} with mode {:?}", url, &crawl_mode);
+        let global_db = match (&self.global_db, layered) {
+            (Some(global_db), true) => Some(global_db.lock().await),
+            _ => None,
+        };
+        let mut layers = Vec::new();
+        if let Some(package_db) = package_db {
+            let mut package_db = package_db.lock().await;
+            watch::reload_if_changed(&mut package_db, PACKAGE_ORIGIN);
+            let mut results = hybrid(&package_db)?;
+            for result in &mut results {
+                result.score *= self.config.search.package_weight;
+            }
+            layers.push((PACKAGE_ORIGIN.to_string(), results));
+        }
+        layers.push((primary_origin.to_string(), hybrid(&vector_db)?));
+        if let Some(global_db) = &global_db {
+            layers.push(("global".to_string(), hybrid(global_db)?));
+        }
+        if let Some(archive) = archive {
+            layers.push(("archive".to_string(), hybrid(archive)?));
+        }
 
-        // The crawler uses non-Send types (scraper::Html) which prevents it from being
-        // used directly in async contexts that require Send. This is a known limitation
-        // of the HTML parsing library. For now, we'll run a simplified version.
+        Ok(merge_layered_results(layers, limit))
+    }
 
-        // Create crawler configuration
-        let config = CrawlConfig {
-            start_url: start_url.to_string(),
-            mode: crawl_mode.clone(),
-            focus: doc_focus,
-            max_pages,
-            max_depth: 10,
-            concurrent_requests: 2,
-            delay_ms: 500,
-            user_agent: "CodeRAG/0.1.0 (AI Documentation Assistant)".to_string(),
-            allowed_domains: HashSet::from([start_url.domain().unwrap_or("").to_string()]),
-            url_patterns: crate::crawler::types::UrlPatterns::default(),
+    /// Fill filters the caller left unset from phrases in the query, and strip those phrases
+    ///
+    /// Returns the filters that were filled in, if any.
+    async fn infer_filters(
+        &self,
+        query: &mut String,
+        source_filter: &mut Option<String>,
+        content_type: &mut Option<String>,
+        version: &mut Option<String>,
+    ) -> Option<InferredFilters> {
+        if !self.config.search.infer_filters {
+            return None;
+        }
+        let routed = {
+            let sources = self.sources.lock().await;
+            router::route(query, |name| {
+                sources
+                    .sources()
+                    .keys()
+                    .any(|url| url.to_lowercase().contains(name))
+            })
         };
 
-        // For now, implement a simplified version that crawls just the single page
-        // TODO: Refactor crawler to be Send-safe or use a different approach
-        if crawl_mode != CrawlMode::SinglePage {
-            info!(
-                "Multi-page crawling not yet available in SDK version, falling back to single page"
-            );
+        let mut applied = InferredFilters::default();
+        for (slot, value, applied_slot) in [
+            (
+                source_filter,
+                routed.filters.source_filter,
+                &mut applied.source_filter,
+            ),
+            (
+                content_type,
+                routed.filters.content_type,
+                &mut applied.content_type,
+            ),
+            (version, routed.filters.version, &mut applied.version),
+        ] {
+            if slot.is_none() && value.is_some() {
+                slot.clone_from(&value);
+                *applied_slot = value;
+            }
         }
+        *query = routed.query;
+        (!applied.is_empty()).then_some(applied)
+    }
 
-        // Fetch and process single page
-        info!("Creating HTTP client...");
-        let client = reqwest::Client::builder()
-            .user_agent(&config.user_agent)
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
+    /// Load the archive of refreshed and deleted documents, if there is one
+    fn open_archive(&self) -> Result<Option<VectorDatabase>, McpError> {
+        self.active()
+            .archive
+            .open()
+            .map_err(|e| McpError::internal_error(format!("Failed to load archive: {}", e), None))
+    }
+
+    /// Move the documents about to be removed into the archive, if archiving is enabled
+    ///
+    /// Returns how many were archived, or `None` when archiving is off.
+    fn archive_where(
+        &self,
+        vector_db: &VectorDatabase,
+        matches: impl Fn(&Document) -> bool,
+    ) -> Result<Option<usize>, McpError> {
+        if !self.config.database.archive {
+            return Ok(None);
+        }
+        vector_db
+            .documents_with_vectors_where(matches)
+            .and_then(|documents| self.active().archive.store(documents))
+            .map(Some)
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to archive documents: {}", e), None)
+            })
+    }
+
+    /// Drop registry records of sources that no longer have any documents
+    async fn prune_sources(&self, vector_db: &VectorDatabase) {
+        let indexed = vector_db.get_documents_by_source();
+        let mut sources = self.sources.lock().await;
+        if sources.retain(|source| indexed.keys().any(|url| url.starts_with(source))) > 0 {
+            if let Err(e) = sources.save() {
+                warn!("Failed to save source registry: {}", e);
+            }
+        }
+    }
+
+    /// Refuse an operation that would save the database when it is open read-only
+    fn ensure_writable(&self) -> Result<(), McpError> {
+        if self.active().db_lock.is_some() {
+            return Ok(());
+        }
+        Err(McpError::invalid_request(
+            "The database is open read-only because another CodeRAG server holds its write lock; \
+             make changes from that instance or the coderag CLI",
+            None,
+        ))
+    }
+
+    /// Reload the primary database if another process saved it
+    ///
+    /// Covers changes the file watcher hasn't picked up yet, or platforms where it fails.
+    async fn sync_with_disk(&self) {
+        watch::reload_if_changed(&mut *self.vector_db.lock().await, "primary");
+    }
+
+    /// Back up the database before a destructive operation, returning the backup's name
+    ///
+    /// A failed backup aborts the operation rather than risk unrecoverable data loss.
+    fn backup_before(&self, operation: &str) -> Result<Option<String>, McpError> {
+        self.active()
+            .backups
+            .create(operation)
+            .map(|backup| backup.map(|b| b.name))
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to back up database: {}", e), None)
+            })
+    }
+
+    /// Keep the database within `database.max_size_mb` before a new crawl
+    ///
+    /// The limit covers everything kept for the database, backups included.
+    /// Over it the crawl is refused, or with `evict_lru` the least recently
+    /// searched sources are removed until the database is back under 90% of
+    /// it. The source being crawled is never evicted.
+    async fn enforce_quota(&self, crawl_url: &str) -> Result<(), McpError> {
+        let Some(max_mb) = self.config.database.max_size_mb else {
+            return Ok(());
+        };
+        let max_bytes = max_mb.saturating_mul(MB);
+        let active = self.active();
+        let mut vector_db = self.vector_db.lock().await;
+        let size = active.disk_usage(&vector_db);
+        if size < max_bytes {
+            return Ok(());
+        }
+        if !self.config.database.evict_lru {
+            return Err(quota_error(size, max_bytes));
+        }
+
+        let least_recently_used = self.sources.lock().await.least_recently_used();
+        let Some(evicted) =
+            sources_to_evict(&vector_db, least_recently_used, size, max_bytes, crawl_url)
+        else {
+            return Err(quota_error(size, max_bytes));
+        };
+
+        // A backup or archive copy would only move the bytes being freed
+        let (removed, _, _) = self.evict_sources(&mut vector_db, &evicted, false).await?;
+        info!(
+            "Evicted {} documents of least recently used sources to stay under {} MB: {}",
+            removed,
+            max_mb,
+            evicted.join(", ")
+        );
+
+        let size = active.disk_usage(&vector_db);
+        if size >= max_bytes {
+            return Err(quota_error(size, max_bytes));
+        }
+        Ok(())
+    }
+
+    /// Check the size limit again after a crawl wrote `page_url`
+    ///
+    /// A crawl that pushed the database over the limit, with nothing left to
+    /// evict, has its page taken back out.
+    async fn recheck_quota(&self, page_url: &str) -> Result<(), McpError> {
+        let Err(e) = self.enforce_quota(page_url).await else {
+            return Ok(());
+        };
+        let mut vector_db = self.vector_db.lock().await;
+        let removed = vector_db
+            .remove_documents_where(|doc| doc.url == page_url)
             .map_err(|e| {
-                McpError::internal_error(format!("Failed to create HTTP client: {}", e), None)
+                McpError::internal_error(format!("Failed to remove documents: {}", e), None)
             })?;
+        vector_db.compact().map_err(|e| {
+            McpError::internal_error(format!("Failed to compact database: {}", e), None)
+        })?;
+        self.prune_sources(&vector_db).await;
+        warn!(
+            "Removed the {} documents of {} again, which put the database over its size limit",
+            removed, page_url
+        );
+        Err(e)
+    }
+
+    /// Remove every document of `sources` and compact
+    ///
+    /// With `keep_copies` the database is backed up and the documents archived
+    /// first. Returns the documents removed, how many were archived and the
+    /// backup taken.
+    async fn evict_sources(
+        &self,
+        vector_db: &mut VectorDatabase,
+        sources: &[String],
+        keep_copies: bool,
+    ) -> Result<(usize, Option<usize>, Option<String>), McpError> {
+        let matches = |doc: &Document| {
+            sources
+                .iter()
+                .any(|source| doc.url.starts_with(source.as_str()))
+        };
+        let (backup, archived) = if keep_copies {
+            (
+                self.backup_before("evict")?,
+                self.archive_where(vector_db, matches)?,
+            )
+        } else {
+            (None, None)
+        };
+        let removed = vector_db.remove_documents_where(matches).map_err(|e| {
+            McpError::internal_error(format!("Failed to evict documents: {}", e), None)
+        })?;
+        // Compaction saves and gives the space back on disk
+        vector_db.compact().map_err(|e| {
+            McpError::internal_error(format!("Failed to compact database: {}", e), None)
+        })?;
+        self.prune_sources(vector_db).await;
+        Ok((removed, archived, backup))
+    }
+
+    /// Evict the sources no search has returned in `max_age_days`
+    async fn evict_unused(
+        &self,
+        max_age_days: u64,
+        dry_run: bool,
+    ) -> Result<ManageDocsResponse, McpError> {
+        let mut vector_db = self.vector_db.lock().await;
+        let unused = self.sources.lock().await.unused_sources(max_age_days);
+        let (evicted_documents, archived_documents, backup) = if dry_run || unused.is_empty() {
+            let documents = vector_db
+                .get_documents_by_source()
+                .iter()
+                .filter(|(url, _)| unused.iter().any(|source| url.starts_with(source)))
+                .map(|(_, documents)| documents.len())
+                .sum();
+            (documents, None, None)
+        } else {
+            self.evict_sources(&mut vector_db, &unused, true).await?
+        };
+
+        Ok(ManageDocsResponse::Evict {
+            max_age_days,
+            evicted_sources: unused,
+            evicted_documents,
+            dry_run,
+            total_documents_remaining: vector_db.document_count(),
+            archived_documents,
+            backup,
+        })
+    }
+
+    /// Save the search counts of sources now and then, rather than after every search
+    fn spawn_access_flush(&self) {
+        let sources = self.sources.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ACCESS_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = sources.lock().await.flush_access() {
+                    warn!("Failed to save source registry: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Evict unused sources daily, when `database.evict_unused_days` is set
+    fn spawn_auto_evict(&self) {
+        let Some(max_age_days) = self.config.database.evict_unused_days else {
+            return;
+        };
+        if self.active().db_lock.is_none() {
+            return;
+        }
+        let server = self.clone();
+        tokio::spawn(async move {
+            let period = Duration::from_secs(24 * 60 * 60);
+            // The first eviction is a day after startup, not at it
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                server.sync_with_disk().await;
+                match server.evict_unused(max_age_days, false).await {
+                    Ok(ManageDocsResponse::Evict {
+                        evicted_sources,
+                        evicted_documents,
+                        ..
+                    }) if !evicted_sources.is_empty() => info!(
+                        "🧹 Evicted {} documents of sources unused for {} days: {}",
+                        evicted_documents,
+                        max_age_days,
+                        evicted_sources.join(", ")
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("⚠️ Automatic eviction failed: {}", e.message),
+                }
+            }
+        });
+    }
+
+    /// Run a tool call, telling the client how loading the embedding model goes meanwhile
+    ///
+    /// A call that needs the model waits for its download, which can take
+    /// minutes on first use. Progress goes out as logging notifications, as
+    /// tool calls carry no progress token in this protocol version.
+    async fn with_model_status<F: std::future::Future>(
+        &self,
+        peer: &Peer<RoleServer>,
+        call: F,
+    ) -> F::Output {
+        let mut status = self.model_status.clone();
+        let initial = status.borrow_and_update().clone();
+        if initial == ModelStatus::Ready {
+            return call.await;
+        }
+        if initial.is_pending() {
+            self.log_model_status(peer, &initial).await;
+        }
+
+        let mut call = std::pin::pin!(call);
+        loop {
+            tokio::select! {
+                output = &mut call => return output,
+                changed = status.changed() => {
+                    if changed.is_err() {
+                        return call.await;
+                    }
+                    let current = status.borrow_and_update().clone();
+                    self.log_model_status(peer, &current).await;
+                }
+            }
+        }
+    }
+
+    async fn log_model_status(&self, peer: &Peer<RoleServer>, status: &ModelStatus) {
+        let level = match status {
+            ModelStatus::Failed(_) => LoggingLevel::Error,
+            _ => LoggingLevel::Info,
+        };
+        if (level.clone() as u8) < (self.log_level.read().unwrap().clone() as u8) {
+            return;
+        }
+        let notification = LoggingMessageNotificationParam {
+            level,
+            logger: Some("coderag".to_string()),
+            data: json!(format!("Embedding model: {}", status)),
+        };
+        if let Err(e) = peer.notify_logging_message(notification).await {
+            debug!("Could not send model status to the client: {}", e);
+        }
+    }
+
+    /// Crawl a page for crawl_docs and record the outcome in the source registry
+    async fn crawl_page(&self, params: CrawlDocsParams) -> Result<CrawlResponse, McpError> {
+        let url = params.url.clone();
+        let settings = CrawlSettings {
+            mode: params.mode.clone(),
+            focus: params.focus.clone(),
+            max_pages: params.max_pages,
+            include_patterns: params.include_patterns.clone().unwrap_or_default(),
+            exclude_patterns: params.exclude_patterns.clone().unwrap_or_default(),
+        };
+        let result = self.index_page(params).await;
+
+        let mut sources = self.sources.lock().await;
+        match &result {
+            // Duplicates are recorded as aliases of the page they copy, and
+            // skipped pages were never indexed
+            Ok(response) if response.status == "duplicate" || response.status == "skipped" => {
+                return result
+            }
+            Ok(response) => sources.record_crawl(&url, settings, response.pages_crawled),
+            // Invalid parameters aren't a failure of the source
+            Err(e) if e.code != ErrorCode::INTERNAL_ERROR => return result,
+            Err(e) => sources.record_failure(&url, settings, &e.message),
+        }
+        if let Err(e) = sources.save() {
+            warn!("Failed to save source registry: {}", e);
+        }
+
+        result
+    }
+
+    /// The URLs a crawl would fetch, for a crawl_docs dry run
+    async fn plan_crawl(&self, params: CrawlDocsParams) -> Result<CrawlPlanResponse, McpError> {
+        let start_url = Url::parse(&params.url)
+            .map_err(|e| McpError::invalid_params(format!("Invalid URL: {}", e), None))?;
+        let invalid = |e: CodeRagError| McpError::invalid_params(e.to_string(), None);
+        let mode: CrawlMode = params.mode.parse().map_err(invalid)?;
+        let focus: DocumentationFocus = params.focus.parse().map_err(invalid)?;
+        let note = (mode != CrawlMode::SinglePage).then(|| {
+            "crawl_docs indexes only the start page for now; `coderag crawl` fetches every URL listed"
+                .to_string()
+        });
+
+        let mut config = self.crawl_config(&start_url, mode, focus, params.max_pages);
+        config.url_patterns = url_patterns(&params)?;
+        let mut crawler = Crawler::new(config)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let plan = crawler.discover().await.map_err(|e| {
+            McpError::internal_error(format!("Failed to discover URLs: {}", e), None)
+        })?;
+        Ok(CrawlPlanResponse {
+            source_url: params.url,
+            mode: params.mode,
+            plan,
+            note,
+        })
+    }
+
+    /// Crawler settings from the configuration for a crawl of `start_url`
+    fn crawl_config(
+        &self,
+        start_url: &Url,
+        mode: CrawlMode,
+        focus: DocumentationFocus,
+        max_pages: Option<usize>,
+    ) -> CrawlConfig {
+        let crawler = &self.config.crawler;
+        CrawlConfig {
+            start_url: start_url.to_string(),
+            mode,
+            focus,
+            max_pages: max_pages.unwrap_or(crawler.max_pages),
+            max_depth: crawler.max_depth.unwrap_or(10),
+            concurrent_requests: crawler.concurrent_requests,
+            delay_ms: crawler.delay_ms,
+            user_agent: crawler.user_agent.clone(),
+            allowed_domains: self.config.allowed_domains(start_url),
+            domain_policy: crawler.domain_policy(),
+            url_patterns: UrlPatterns::default(),
+            max_page_bytes: crawler.max_page_bytes,
+            max_chunks_per_page: crawler.max_chunks_per_page,
+            max_pages_per_article: crawler.max_pages_per_article,
+            retry: crawler.retry_policy(),
+            breaker: crawler.breaker_policy(),
+            client: crawler.client_options(),
+            auth: crawler.auth.clone(),
+        }
+    }
+
+    /// Fetch, chunk, embed and index a page
+    async fn index_page(&self, params: CrawlDocsParams) -> Result<CrawlResponse, McpError> {
+        let url_patterns = url_patterns(&params)?;
+        let CrawlDocsParams {
+            url,
+            mode,
+            focus,
+            max_pages,
+            ..
+        } = params;
+        if shutdown::requested() {
+            return Err(McpError::invalid_request(
+                "The server is shutting down",
+                None,
+            ));
+        }
+        self.sync_with_disk().await;
+
+        // Parse URL
+        let start_url = Url::parse(&url)
+            .map_err(|e| McpError::invalid_params(format!("Invalid URL: {}", e), None))?;
+
+        // Parse crawl mode
+        let crawl_mode = match mode.as_str() {
+            "single" => CrawlMode::SinglePage,
+            "section" => CrawlMode::Section,
+            "full" => CrawlMode::FullDocs,
+            _ => {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "Invalid mode: {}. Must be 'single', 'section', or 'full'",
+                        mode
+                    ),
+                    None,
+                ))
+            }
+        };
+
+        // Parse documentation focus
+        let doc_focus = match focus.as_str() {
+            "api" => DocumentationFocus::ApiReference,
+            "examples" => DocumentationFocus::Examples,
+            "changelog" => DocumentationFocus::Changelog,
+            "quickstart" => DocumentationFocus::QuickStart,
+            "all" => DocumentationFocus::All,
+            _ => return Err(McpError::invalid_params(
+                format!("Invalid focus: {}. Must be 'api', 'examples', 'changelog', 'quickstart', or 'all'", focus),
+                None,
+            )),
+        };
+
+        info!("Starting crawl of {} with mode {:?}", url, &crawl_mode);
+
+        // The crawler uses non-Send types (scraper::Html) which prevents it from being
+        // used directly in async contexts that require Send. This is a known limitation
+        // of the HTML parsing library. For now, we'll run a simplified version.
+
+        // Create crawler configuration
+        let mut config = self.crawl_config(&start_url, crawl_mode.clone(), doc_focus, max_pages);
+        config.url_patterns = url_patterns;
+
+        // For now, implement a simplified version that crawls just the single page
+        // TODO: Refactor crawler to be Send-safe or use a different approach
+        if crawl_mode != CrawlMode::SinglePage {
+            info!(
+                "Multi-page crawling not yet available in SDK version, falling back to single page"
+            );
+        }
+
+        // Fetch and process single page
+        info!("Creating HTTP client...");
+        let client = build_client(&config.user_agent, &config.client).map_err(|e| {
+            McpError::internal_error(format!("Failed to create HTTP client: {}", e), None)
+        })?;
+        let auth = CrawlAuth::new(&config.auth)
+            .map_err(|e| McpError::internal_error(format!("Invalid crawler auth: {}", e), None))?;
 
         info!("Fetching URL: {}", &url);
-        let response =
-            client.get(&url).send().await.map_err(|e| {
-                McpError::internal_error(format!("Failed to fetch URL: {}", e), None)
-            })?;
+        let mut provenance = Provenance {
+            session: uuid::Uuid::new_v4().to_string(),
+            extractor_version: EXTRACTOR_VERSION,
+            fetched_at: std::time::SystemTime::now(),
+            snapshot: None,
+        };
+        let response = client
+            .send(auth.apply(client.get(&url), &url))
+            .await
+            .map_err(|e| McpError::internal_error(format!("Failed to fetch URL: {}", e), None))?;
 
         info!("Reading response body...");
-        let html = response.text().await.map_err(|e| {
-            McpError::internal_error(format!("Failed to read response: {}", e), None)
-        })?;
-        info!("Response body length: {} bytes", html.len());
+        let page = match read_page(response, config.max_page_bytes).await {
+            Ok(page) => page,
+            Err(CodeRagError::UnsupportedContent(reason)) => {
+                info!("Skipping {}: {}", url, reason);
+                return Ok(CrawlResponse {
+                    status: "skipped".to_string(),
+                    source_url: url.clone(),
+                    mode,
+                    pages_crawled: 0,
+                    documents_created: 0,
+                    chunks_created: 0,
+                    note: Some(format!("Nothing indexed: {}", reason)),
+                    skipped: vec![SkippedPage { url, reason }],
+                    session: None,
+                });
+            }
+            Err(e) => {
+                return Err(McpError::internal_error(
+                    format!("Failed to read response: {}", e),
+                    None,
+                ))
+            }
+        };
+        let body = page.body;
+        if let Some(store) = &self.active().blob_store {
+            match store.put(body.as_bytes()) {
+                Ok(hash) => provenance.snapshot = Some(hash),
+                Err(e) => warn!("Failed to snapshot {}: {}", url, e),
+            }
+        }
+        info!("Response body length: {} bytes", body.len());
+        if page.truncated {
+            warn!("Page truncated at {} bytes", config.max_page_bytes);
+        }
 
         // Extract content
         info!("Creating content extractor...");
         let extractor = crate::crawler::ContentExtractor::new().map_err(|e| {
             McpError::internal_error(format!("Failed to create extractor: {}", e), None)
         })?;
-        info!("Extracting content from HTML...");
-        let extracted = extractor.extract_content(&html, &url).map_err(|e| {
-            McpError::internal_error(format!("Failed to extract content: {}", e), None)
-        })?;
+        info!("Extracting {:?} content...", page.kind);
+        let extracted = extractor
+            .extract_page(&body, &url, page.kind)
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to extract content: {}", e), None)
+            })?;
         info!(
             "Content extracted, markdown length: {} bytes",
             extracted.markdown.len()
         );
 
-        // Chunk the content
-        info!("Creating text chunker...");
-        let mut chunker = crate::crawler::TextChunker::new();
-        info!("Chunking text...");
-        let chunks = chunker.chunk_text(&extracted.markdown);
-        info!("Created {} chunks", chunks.len());
+        // Index only one copy of pages served at several URLs
+        let simhash = crate::crawler::simhash::page_fingerprint(&extracted.markdown);
+        if let Some(simhash) = simhash {
+            let mut vector_db = self.vector_db.lock().await;
+            let duplicate_of = vector_db.find_near_duplicate(
+                simhash,
+                crate::crawler::simhash::NEAR_DUPLICATE_DISTANCE,
+                &url,
+            );
+            if let Some(original) = duplicate_of {
+                info!("{} duplicates {}, recording it as an alias", url, original);
+                vector_db.add_alias(&original, &url);
+                vector_db.save().map_err(|e| {
+                    McpError::internal_error(format!("Failed to save database: {}", e), None)
+                })?;
+                return Ok(CrawlResponse {
+                    status: "duplicate".to_string(),
+                    source_url: url,
+                    mode,
+                    pages_crawled: 1,
+                    documents_created: 0,
+                    chunks_created: 0,
+                    note: Some(format!(
+                        "Page duplicates {}; recorded as an alias instead of indexing it again",
+                        original
+                    )),
+                    skipped: Vec::new(),
+                    session: None,
+                });
+            }
+        }
+
+        // Chunk the content
+        info!("Creating text chunker...");
+        let mut chunker = self.config.chunker();
+        info!("Chunking text...");
+        let mut chunks = chunker.chunk_text(&extracted.markdown);
+        info!("Created {} chunks", chunks.len());
+        if chunks.len() > config.max_chunks_per_page {
+            warn!(
+                "Keeping the first {} of {} chunks",
+                config.max_chunks_per_page,
+                chunks.len()
+            );
+            chunks.truncate(config.max_chunks_per_page);
+        }
+
+        // Process chunks
+        info!("Acquiring embedding service lock...");
+        let embedding_service = self.embedding_service.lock().await;
+        info!("Acquiring vector database lock...");
+        let mut vector_db = self.vector_db.lock().await;
+        let mut documents_created = 0;
+
+        // Changelog sections are tagged with the release they describe
+        let is_changelog = matches!(config.focus, DocumentationFocus::Changelog)
+            || changelog::is_changelog_page(&url, &extracted.title);
+
+        info!("Generating embeddings for {} chunks...", chunks.len());
+        let contents: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let embeddings = embedding_service
+            .embed_passages(&contents)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to generate embeddings: {}", e), None)
+            })?;
+        info!("Embeddings generated successfully");
+
+        info!("Processing {} chunks...", chunks.len());
+        let mut documents = Vec::with_capacity(chunks.len());
+        let mut ids = ChunkIds::new();
+        for ((i, chunk), embedding) in chunks.iter().enumerate().zip(embeddings) {
+            // Deep link to the section the chunk belongs to
+            let (anchor, heading_path) = chunk
+                .heading_context
+                .as_deref()
+                .and_then(|section| extracted.locate_section(section))
+                .map_or((None, Vec::new()), |(anchor, path)| (Some(anchor), path));
+            let doc_id = ids.next_id(&url, anchor.as_deref(), &chunk.content);
+            let release = if is_changelog {
+                changelog::find_release(&heading_path).or_else(|| {
+                    chunk
+                        .heading_context
+                        .as_deref()
+                        .and_then(changelog::parse_release_heading)
+                })
+            } else {
+                None
+            };
+
+            // Create document
+            let document = crate::vectordb::Document {
+                id: doc_id,
+                content: chunk.content.clone(),
+                url: url.to_string(),
+                title: Some(extracted.title.clone()),
+                section: chunk.heading_context.clone(),
+                metadata: crate::vectordb::DocumentMetadata {
+                    content_type: crate::vectordb::ContentType::Documentation,
+                    language: extracted.metadata.language.clone(),
+                    // A chunk in a distinctive script overrides the page language
+                    lang: chunk
+                        .lang
+                        .clone()
+                        .or_else(|| extracted.metadata.lang.clone()),
+                    last_updated: Some(std::time::SystemTime::now()),
+                    tags: vec![
+                        if chunk.has_code {
+                            "has-code"
+                        } else {
+                            "no-code"
+                        }
+                        .to_string(),
+                        format!("chunk-{}-of-{}", i + 1, chunks.len()),
+                    ],
+                    anchor,
+                    heading_path,
+                    boost: None,
+                    links: chunk.links.clone(),
+                    aliases: Vec::new(),
+                    simhash,
+                    version: release.as_ref().map(|r| r.version.clone()),
+                    release_date: release.and_then(|r| r.date),
+                    votes: None,
+                    images: Vec::new(),
+                    canonical: None,
+                    provenance: Some(provenance.clone()),
+                },
+            };
+
+            documents.push((document, embedding));
+        }
+
+        // Chunks copied from another site point at the copy indexed first
+        let linked = vector_db.link_duplicate_chunks(documents.iter_mut().map(|(d, _)| d));
+        if linked > 0 {
+            info!(
+                "{} chunks are copies of chunks indexed from other URLs",
+                linked
+            );
+        }
+
+        // Drop chunks the page no longer has; unchanged ones are superseded
+        if !documents.is_empty() {
+            let ids: HashSet<&str> = documents.iter().map(|(d, _)| d.id.as_str()).collect();
+            vector_db
+                .remove_documents_where(|document| {
+                    document.url == url && !ids.contains(document.id.as_str())
+                })
+                .map_err(|e| {
+                    McpError::internal_error(format!("Failed to remove stale chunks: {}", e), None)
+                })?;
+        }
+
+        // Add to database
+        for (document, embedding) in documents {
+            vector_db.add_document(document, embedding).map_err(|e| {
+                McpError::internal_error(format!("Failed to add document: {}", e), None)
+            })?;
+            documents_created += 1;
+        }
+
+        // Save the database
+        vector_db.save().map_err(|e| {
+            McpError::internal_error(format!("Failed to save database: {}", e), None)
+        })?;
+
+        if let Some(store) = &self.active().page_store {
+            let stored = StoredPage {
+                url: url.clone(),
+                fetched_at: provenance.fetched_at,
+                snapshot: provenance.snapshot,
+                extracted,
+            };
+            if let Err(e) = store.save(&stored) {
+                warn!("Failed to store extracted page {}: {}", url, e);
+            }
+        }
+
+        Ok(CrawlResponse {
+            status: "success".to_string(),
+            source_url: url,
+            mode,
+            pages_crawled: 1,
+            documents_created,
+            chunks_created: chunks.len(),
+            note: Some(
+                "Currently only single-page crawling is supported in the SDK version".to_string(),
+            ),
+            skipped: Vec::new(),
+            session: Some(provenance.session),
+        })
+    }
+
+    /// Collect the indexed source URLs that document a workspace member's dependencies
+    async fn scope_sources(
+        &self,
+        scope: &str,
+        vector_db: &VectorDatabase,
+    ) -> Result<HashSet<String>, McpError> {
+        let active = self.active();
+        let member = active
+            .project_info
+            .members
+            .iter()
+            .find(|m| m.name == scope)
+            .ok_or_else(|| {
+                let available: Vec<&str> = active
+                    .project_info
+                    .members
+                    .iter()
+                    .map(|m| m.name.as_str())
+                    .collect();
+                McpError::invalid_params(
+                    format!(
+                        "Unknown scope '{}'. Available scopes: {:?}",
+                        scope, available
+                    ),
+                    None,
+                )
+            })?;
+
+        let dependencies = ProjectManager::detect_dependencies(&member.path)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let mut sources: Vec<String> = vector_db.get_documents_by_source().into_keys().collect();
+        if let Some(global_db) = &self.global_db {
+            sources.extend(global_db.lock().await.get_documents_by_source().into_keys());
+        }
+
+        Ok(sources
+            .into_iter()
+            .filter(|source| {
+                dependencies
+                    .iter()
+                    .any(|dep| dep.is_documented_by([source.as_str()]))
+            })
+            .collect())
+    }
+}
+
+#[tool(tool_box)]
+impl CodeRagServer {
+    pub async fn new(data_dir: PathBuf) -> anyhow::Result<Self> {
+        Self::with_options(data_dir, ServerOptions::default()).await
+    }
+
+    /// Create a server, applying command-line overrides to the configuration
+    pub async fn with_options(data_dir: PathBuf, options: ServerOptions) -> anyhow::Result<Self> {
+        info!("🚀 Initializing CodeRAG server...");
+        info!("📂 Global data directory: {:?}", data_dir);
+
+        DataDir::open(&data_dir)?;
+        let project_root = options
+            .project_root
+            .clone()
+            .or_else(ProjectManager::detect_project_root);
+        let mut config = Config::load_with_data_dir(Some(&data_dir), project_root.as_deref())?;
+        config.embedding.preload |= options.preload_model;
+
+        // Initialize project manager
+        let project_manager = ProjectManager::new(data_dir).with_backend(config.database.backend);
+        let project_info = match &options.project_root {
+            Some(root) => project_manager.project_info_at(root),
+            None => project_manager.get_project_info(),
+        };
+
+        if project_info.is_project {
+            info!(
+                "📁 Detected project: {}",
+                project_info
+                    .project_name
+                    .as_ref()
+                    .unwrap_or(&"unknown".to_string())
+            );
+            info!("📂 Project root: {:?}", project_info.project_root);
+            info!(
+                "💾 Using project-local database: {:?}",
+                project_info.database_path
+            );
+        } else {
+            info!("🌍 No project detected, using global database");
+            info!("💾 Global database path: {:?}", project_info.database_path);
+        }
+
+        if !config.sources.is_empty() {
+            info!("⚙️ Loaded configuration from: {:?}", config.sources);
+        }
+
+        if config.embedding.preload {
+            info!("⏳ Creating embedding service (model loads in the background)...");
+        } else {
+            info!("⏳ Creating embedding service (model downloads on first search)...");
+        }
+        let mut embedding_service = EmbeddingService::with_config(&config.embedding).await?;
+
+        info!("📊 Initializing vector database...");
+        let db_path = project_manager.get_database_path()?;
+        let open_mode = options.open_mode.unwrap_or(config.database.open_mode);
+        let (vector_db, db_lock) =
+            open_primary(&db_path, open_mode, &config, &mut embedding_service)?;
+        ProjectRegistry::remember(project_manager.global_data_dir(), &project_info);
+
+        // Inside a project, keep the global database around for layered search
+        let global_path = project_manager.global_database_path();
+        let global_db = if project_info.is_project && global_path.exists() {
+            let mut global_db = VectorDatabase::new(&global_path)?;
+            // Queries are embedded once, so both layers must share a profile
+            let profile_matches = EmbeddingProfile::load(&global_path)
+                .ok()
+                .flatten()
+                .is_none_or(|profile| &profile == embedding_service.profile());
+            match global_db.load() {
+                Ok(_) if !profile_matches => {
+                    warn!("⚠️ Global database was indexed with a different embedding profile, layered search disabled");
+                    None
+                }
+                Ok(_) => {
+                    info!(
+                        "🌍 Loaded global database for layered search ({} documents)",
+                        global_db.document_count()
+                    );
+                    Some(Arc::new(Mutex::new(global_db)))
+                }
+                Err(e) => {
+                    info!("⚠️ Could not load global database: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let vector_db = Arc::new(Mutex::new(vector_db));
+        if config.database.auto_reload {
+            let debounce = std::time::Duration::from_millis(config.database.reload_debounce_ms);
+            let mut watched = vec![(vector_db.clone(), db_path.clone(), "primary")];
+            if let Some(global_db) = &global_db {
+                watched.push((global_db.clone(), global_path.clone(), "global"));
+            }
+            for (database, path, label) in watched {
+                if let Err(e) = watch::spawn_auto_reload(database, &path, label, debounce) {
+                    warn!("⚠️ Could not watch {} database for changes: {}", label, e);
+                }
+            }
+        }
+
+        let model_status = embedding_service.status();
+        let embedding_service = Arc::new(Mutex::new(embedding_service));
+        if config.embedding.preload {
+            spawn_model_warm_up(embedding_service.clone());
+        }
+
+        info!("✅ CodeRAG server initialization complete!");
+
+        let server = Self {
+            embedding_service,
+            vector_db,
+            global_db,
+            sources: Arc::new(Mutex::new(SourceRegistry::for_database(&db_path))),
+            active: Arc::new(RwLock::new(Arc::new(ActiveDatabase::new(
+                project_info,
+                &project_manager,
+                &config,
+                db_lock,
+            )))),
+            open_mode,
+            project_manager: Arc::new(project_manager),
+            config: Arc::new(config),
+            model_status,
+            log_level: Arc::new(RwLock::new(LoggingLevel::Info)),
+        };
+        server.spawn_access_flush();
+        server.spawn_auto_evict();
+        Ok(server)
+    }
+
+    /// Project context and stores of the primary database
+    fn active(&self) -> Arc<ActiveDatabase> {
+        self.active.read().unwrap().clone()
+    }
+
+    /// The database of the workspace member containing `path`, loaded on first use
+    ///
+    /// None if no member with a database of its own contains the path, or its
+    /// database was indexed with a different embedding profile than the server's.
+    async fn package_database(&self, path: &str) -> Option<Arc<Mutex<VectorDatabase>>> {
+        let active = self.active();
+        let nested = active.routes.route(Path::new(path))?;
+        let mut loaded = active.package_dbs.lock().await;
+        if let Some(db) = loaded.get(&nested.database_path) {
+            return Some(db.clone());
+        }
+
+        // Queries are embedded once, so the package must share the server's profile
+        if let Some(profile) = EmbeddingProfile::load(&nested.database_path).ok().flatten() {
+            if &profile != self.embedding_service.lock().await.profile() {
+                warn!(
+                    "⚠️ {} package database was indexed with a different embedding profile, not searching it",
+                    nested.name
+                );
+                return None;
+            }
+        }
+        let opened = VectorDatabase::new(&nested.database_path).and_then(|mut db| {
+            db.load()?;
+            Ok(db)
+        });
+        match opened {
+            Ok(db) => {
+                info!(
+                    "📦 Loaded {} package database ({} documents)",
+                    nested.name,
+                    db.document_count()
+                );
+                let db = Arc::new(Mutex::new(db));
+                loaded.insert(nested.database_path.clone(), db.clone());
+                Some(db)
+            }
+            Err(e) => {
+                warn!("⚠️ Could not load {} package database: {}", nested.name, e);
+                None
+            }
+        }
+    }
+
+    /// Effective configuration the server was started with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Save unsaved changes and keep the databases locked until the process exits
+    ///
+    /// Requests in the middle of a write finish it first. Requests that reach
+    /// a database later wait on its lock, so nothing changes after the final
+    /// save. A read-only instance saves nothing.
+    pub async fn shutdown(&self) -> ShutdownLocks {
+        let active = self.active();
+        let mut databases = vec![("primary".to_string(), self.vector_db.clone())];
+        databases.extend(self.global_db.clone().map(|db| ("global".to_string(), db)));
+        databases.extend(
+            active
+                .package_dbs
+                .lock()
+                .await
+                .iter()
+                .map(|(path, db)| (format!("{:?}", path), db.clone())),
+        );
+
+        let mut locked = Vec::new();
+        for (name, database) in databases {
+            let database = database.lock_owned().await;
+            if active.db_lock.is_some() && database.is_modified() {
+                match database.save() {
+                    Ok(()) => info!("💾 Saved {} database before exiting", name),
+                    Err(e) => warn!("Failed to save {} database: {}", name, e),
+                }
+            }
+            locked.push(database);
+        }
+        let mut sources = self.sources.clone().lock_owned().await;
+        if let Err(e) = sources.flush_access() {
+            warn!("Failed to save source registry: {}", e);
+        }
+        ShutdownLocks {
+            _databases: locked,
+            _sources: sources,
+        }
+    }
+
+    #[tool(
+        description = "Search your indexed documentation using semantic search. Use this tool when you need current, accurate information about programming frameworks, libraries, APIs, or coding concepts to help with development tasks. This is YOUR resource - use it proactively when you encounter unfamiliar technologies or need to verify current best practices."
+    )]
+    pub(crate) async fn search_docs(
+        &self,
+        #[tool(aggr)] params: SearchDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let started = Instant::now();
+        let query = params.query.clone();
+        let mut params = params;
+        let inferred = self
+            .infer_filters(
+                &mut params.query,
+                &mut params.source_filter,
+                &mut params.content_type,
+                &mut params.version,
+            )
+            .await;
+        let filters = QueryFilters {
+            source_filter: params.source_filter.clone(),
+            content_type: params.content_type.clone(),
+            scope: params.scope.clone(),
+            lang: params.lang.clone(),
+            section: params.section.clone(),
+            version: params.version.clone(),
+            exclude_sources: params.exclude_sources.clone().unwrap_or_default(),
+            exclude_tags: params.exclude_tags.clone().unwrap_or_default(),
+        };
+        let (results, exceeded_in) = self.search_hits(params).await?;
+        self.log_query("search_docs", &query, filters, &results, started)
+            .await;
+
+        // Convert results to response format
+        let hits = results
+            .into_iter()
+            .map(|r| SearchHit {
+                url: r.result.document.url.clone(),
+                citation_url: r.result.document.citation_url(),
+                heading_path: r.result.document.metadata.heading_path.clone(),
+                breadcrumb: r.result.document.breadcrumb(),
+                alternate_urls: r.result.document.metadata.aliases.clone(),
+                id: r.result.document.id,
+                content: r.result.document.content,
+                score: r.result.score,
+                metadata: serde_json::to_value(r.result.document.metadata).unwrap_or(json!({})),
+                origin: Some(r.origin),
+            })
+            .collect();
+
+        SearchResponse::new(hits, exceeded_in)
+            .with_inferred_filters(inferred)
+            .into_tool_result()
+    }
+
+    #[tool(
+        description = "List indexed documentation sources and their document counts, a page at a time (offset, limit, next_offset) sorted by url, documents or last_crawled. Pass source (a URL prefix) to list that source's pages with their titles, sections and chunk counts. Use this tool to see what documentation is available in your knowledge base before searching, or to check if you need to crawl additional sources for a particular technology or framework."
+    )]
+    pub(crate) async fn list_docs(
+        &self,
+        #[tool(aggr)] params: ListDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let ListDocsParams {
+            offset,
+            limit,
+            sort,
+            source,
+        } = params;
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT).max(1);
+        self.sync_with_disk().await;
+        let vector_db = self.vector_db.lock().await;
+
+        // Summarize each page, in detail when looking at one source
+        let mut sources: Vec<SourceSummary> = vector_db
+            .get_documents_by_source()
+            .into_iter()
+            .filter(|(url, _)| {
+                source
+                    .as_deref()
+                    .is_none_or(|prefix| url.starts_with(prefix))
+            })
+            .map(|(url, documents)| source_summary(url, &documents, source.is_some()))
+            .collect();
+        match sort.as_deref().unwrap_or("url") {
+            "url" => sources.sort_by(|a, b| a.url.cmp(&b.url)),
+            "documents" => sources.sort_by(|a, b| {
+                b.documents
+                    .cmp(&a.documents)
+                    .then_with(|| a.url.cmp(&b.url))
+            }),
+            "last_crawled" => sources.sort_by(|a, b| {
+                b.last_updated
+                    .cmp(&a.last_updated)
+                    .then_with(|| a.url.cmp(&b.url))
+            }),
+            other => {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "Invalid sort: {}. Must be 'url', 'documents', or 'last_crawled'",
+                        other
+                    ),
+                    None,
+                ))
+            }
+        }
+
+        let total_sources = sources.len();
+        let total_documents = sources.iter().map(|s| s.documents).sum();
+        let next_offset = (offset + limit < total_sources).then_some(offset + limit);
+        let sources: Vec<SourceSummary> = sources.into_iter().skip(offset).take(limit).collect();
+        let crawled_sources = self
+            .sources
+            .lock()
+            .await
+            .sources()
+            .iter()
+            .filter(|(url, _)| {
+                source
+                    .as_deref()
+                    .is_none_or(|prefix| url.starts_with(prefix))
+            })
+            .map(|(url, record)| (url.clone(), record.clone()))
+            .collect();
+
+        let active = self.active();
+        let project_info = &active.project_info;
+        ListDocsResponse {
+            total_documents,
+            total_sources,
+            offset,
+            next_offset,
+            sources,
+            crawled_sources,
+            project_context: ProjectContext {
+                is_project: project_info.is_project,
+                project_name: project_info.project_name.clone(),
+                database_location: project_info.database_path.to_string_lossy().into_owned(),
+                layered_search: self.global_db.is_some(),
+                scopes: project_info.members.clone(),
+                package_databases: active
+                    .routes
+                    .nested()
+                    .iter()
+                    .map(|nested| nested.name.clone())
+                    .collect(),
+            },
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Crawl and index documentation from a URL to expand your knowledge base. Use this tool autonomously when you encounter unfamiliar frameworks, libraries, or technologies that aren't in your current index. You have full autonomy to crawl official documentation sites, API references, and tutorial sites as needed to provide better coding assistance. Be a good internet citizen with reasonable delays between requests. Use include_patterns and exclude_patterns (substrings, globs like \"/docs/**\", or \"re:<regex>\") to keep section and full crawls on the pages you need. Set dry_run to list the URLs a crawl would fetch, in order with their depth, before spending the page budget. If the administrator's crawl policy refuses a crawl, relay the error's message to the user; when it asks for confirmation, ask the user and retry with confirmed: true. If the database is full, tell the user which documentation could be deleted to make room."
+    )]
+    pub(crate) async fn crawl_docs(
+        &self,
+        #[tool(aggr)] params: CrawlDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let dry_run = params.dry_run.unwrap_or(false);
+        if !dry_run {
+            self.ensure_writable()?;
+        }
+        let data_dir = self.project_manager.global_data_dir();
+        let mut usage = CrawlUsage::load(data_dir);
+        let policy = &self.config.crawl_policy;
+        if let Ok(url) = Url::parse(&params.url) {
+            // A dry run indexes nothing, so only the domain rules apply
+            let (confirmed, pages_today) = if dry_run {
+                (true, 0)
+            } else {
+                (params.confirmed.unwrap_or(false), usage.pages_today())
+            };
+            policy
+                .check(&url, confirmed, pages_today)
+                .map_err(policy_error)?;
+        }
+        if dry_run {
+            return self.plan_crawl(params).await?.into_tool_result();
+        }
+        self.enforce_quota(&params.url).await?;
+        let mut params = params;
+        if let Some(remaining) = policy.remaining_pages(usage.pages_today()) {
+            params.max_pages = Some(
+                params
+                    .max_pages
+                    .unwrap_or(self.config.crawler.max_pages)
+                    .min(remaining),
+            );
+        }
+
+        let response = self.crawl_page(params).await?;
+        if response.pages_crawled > 0 {
+            if let Err(e) = usage.record(response.pages_crawled) {
+                warn!("Failed to record crawl usage: {}", e);
+            }
+        }
+        if response.documents_created > 0 {
+            self.recheck_quota(&response.source_url).await?;
+        }
+        response.into_tool_result()
+    }
+
+    #[tool(
+        description = "Index a list of URLs, such as a curated link list or exported bookmarks, one page each. Pass the URLs, or the list's text to pick http(s) URLs out of. Pages are fetched one after another with the crawler's delay, and the response reports whether each was indexed, skipped or failed. Progress is checkpointed: importing the same list again continues with the pending and failed URLs, so use limit to import a long list over several calls. The crawl policy applies to every URL; one confirmation covers the whole list."
+    )]
+    async fn import_urls(
+        &self,
+        #[tool(aggr)] params: ImportUrlsParams,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_writable()?;
+        let mut text = params.urls.unwrap_or_default().join("\n");
+        if let Some(list) = &params.text {
+            text.push('\n');
+            text.push_str(list);
+        }
+        let urls = parse_url_list(&text);
+        if urls.is_empty() {
+            return Err(McpError::invalid_params(
+                "No http(s) URLs found in urls or text",
+                None,
+            ));
+        }
+        let focus = params.focus.unwrap_or_else(default_focus);
+        focus
+            .parse::<DocumentationFocus>()
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+
+        let db_path = self.active().project_info.database_path.clone();
+        let mut checkpoint = ImportCheckpoint::for_database(&db_path, &urls);
+        if params.restart.unwrap_or(false) {
+            checkpoint
+                .clear()
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        }
+        let limit = params.limit.unwrap_or(usize::MAX);
+        let confirmed = params.confirmed.unwrap_or(false);
+        let mut usage = CrawlUsage::load(self.project_manager.global_data_dir());
+
+        let pending: Vec<&String> = urls.iter().filter(|url| !checkpoint.is_done(url)).collect();
+        let already_done = urls.len() - pending.len();
+        let mut results: Vec<ImportResult> = Vec::new();
+        let mut stopped = None;
+        for url in pending {
+            if results.len() >= limit {
+                break;
+            }
+            if shutdown::requested() {
+                stopped = Some(
+                    "The server is shutting down; import the list again to resume".to_string(),
+                );
+                break;
+            }
+            let parsed = Url::parse(url)
+                .map_err(|e| McpError::invalid_params(format!("Invalid URL: {}", e), None))?;
+            match self
+                .config
+                .crawl_policy
+                .check(&parsed, confirmed, usage.pages_today())
+            {
+                Ok(()) => {}
+                // Rules about the whole import end it; the rest only skip the URL
+                Err(violation)
+                    if violation.rule == "daily_page_limit"
+                        || violation.rule == "confirmation_required" =>
+                {
+                    stopped = Some(violation.message);
+                    break;
+                }
+                Err(violation) => {
+                    let result = ImportResult::failed(url, violation.message);
+                    if let Err(e) = checkpoint.record(result.clone()) {
+                        warn!("Failed to checkpoint import: {}", e);
+                    }
+                    results.push(result);
+                    continue;
+                }
+            }
+            self.enforce_quota(url).await?;
+            if !results.is_empty() {
+                tokio::time::sleep(Duration::from_millis(self.config.crawler.delay_ms)).await;
+            }
+
+            let crawl = self
+                .crawl_page(CrawlDocsParams {
+                    url: url.clone(),
+                    mode: "single".to_string(),
+                    focus: focus.clone(),
+                    max_pages: Some(1),
+                    confirmed: None,
+                    dry_run: None,
+                    include_patterns: None,
+                    exclude_patterns: None,
+                })
+                .await;
+            let result = match crawl {
+                Ok(response) => {
+                    if response.pages_crawled > 0 {
+                        if let Err(e) = usage.record(response.pages_crawled) {
+                            warn!("Failed to record crawl usage: {}", e);
+                        }
+                    }
+                    if response.documents_created > 0 {
+                        self.recheck_quota(&response.source_url).await?;
+                    }
+                    let skipped = response.status == "skipped" || response.status == "duplicate";
+                    ImportResult {
+                        url: url.clone(),
+                        status: if skipped { "skipped" } else { "indexed" }.to_string(),
+                        documents_created: response.documents_created,
+                        reason: if skipped { response.note } else { None },
+                    }
+                }
+                Err(e) => ImportResult::failed(url, e.message),
+            };
+            if let Err(e) = checkpoint.record(result.clone()) {
+                warn!("Failed to checkpoint import: {}", e);
+            }
+            results.push(result);
+        }
+
+        let mut response = ImportUrlsResponse::new(urls.len(), already_done, results);
+        if stopped.is_some() {
+            response.note = stopped;
+        }
+        response.into_tool_result()
+    }
+
+    #[tool(
+        description = "Show what crawling a URL would index, without changing the knowledge base. Fetches the single page and returns its extracted title, cleaned markdown, detected code blocks, and the chunks it would be split into. Use this tool to diagnose poor search results from a site, such as navigation text in the chunks or code that went missing. The crawl policy's domain rules apply."
+    )]
+    async fn preview_extraction(
+        &self,
+        #[tool(aggr)] params: PreviewExtractionParams,
+    ) -> Result<CallToolResult, McpError> {
+        let start_url = Url::parse(&params.url)
+            .map_err(|e| McpError::invalid_params(format!("Invalid URL: {}", e), None))?;
+        let focus: DocumentationFocus = params
+            .focus
+            .as_deref()
+            .unwrap_or("all")
+            .parse()
+            .map_err(|e: CodeRagError| McpError::invalid_params(e.to_string(), None))?;
+        // Only one page is fetched, and only with the domain rules to answer to
+        self.config
+            .crawl_policy
+            .check(&start_url, true, 0)
+            .map_err(policy_error)?;
+
+        let config = self.crawl_config(&start_url, CrawlMode::SinglePage, focus, Some(1));
+        let mut crawler = Crawler::new(config)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .with_chunker(self.config.chunker());
+        let preview = crawler.preview().await.map_err(|e| {
+            McpError::internal_error(format!("Failed to preview {}: {}", start_url, e), None)
+        })?;
+        PreviewExtractionResponse::from(preview).into_tool_result()
+    }
+
+    #[tool(
+        description = "Answer-ready context in one call. Runs a hybrid (semantic + keyword) search for the question, reranks and deduplicates the top chunks, packs them into a token budget in reading order, and returns the context with numbered citations linking to each section. Use this tool instead of search_docs + get_doc when you want documentation context to answer a question directly, especially with a small tool-call budget."
+    )]
+    async fn ask_docs(
+        &self,
+        #[tool(aggr)] params: AskDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let AskDocsParams {
+            question,
+            token_budget,
+            mut source_filter,
+            layered,
+            scope,
+            lang,
+            section,
+            mut version,
+            path,
+        } = params;
+        let token_budget = token_budget.unwrap_or(ask::DEFAULT_TOKEN_BUDGET).max(1);
+        let started = Instant::now();
+        let mut search_text = question.clone();
+        let mut content_type = None;
+        let inferred = self
+            .infer_filters(
+                &mut search_text,
+                &mut source_filter,
+                &mut content_type,
+                &mut version,
+            )
+            .await;
+        let filters = QueryFilters {
+            source_filter: source_filter.clone(),
+            content_type: content_type.clone(),
+            scope: scope.clone(),
+            lang: lang.clone(),
+            section: section.clone(),
+            version: version.clone(),
+            ..QueryFilters::default()
+        };
+
+        let package_db = match &path {
+            Some(path) => self.package_database(path).await,
+            None => None,
+        };
+        let options = SearchOptions {
+            limit: ask::CANDIDATE_CHUNKS,
+            min_score: self.config.search.min_score,
+            source_filter,
+            content_type_filter: content_type_filter(content_type.as_deref()),
+            lang_filter: lang,
+            section_filter: section,
+            version_filter: version_range(version.as_deref())?,
+            budget: self.search_budget(None),
+            source_weights: self.sources.lock().await.priorities(),
+            recency_half_life_days: self.config.search.recency_half_life_days,
+            ..SearchOptions::default()
+        };
+        let budget = options.budget.clone();
+        let layered = layered.unwrap_or(self.config.search.layered);
+        let hits = self
+            .hybrid_hits(&search_text, options, layered, scope, package_db, None)
+            .await?;
+        let hits = collapse_duplicates(hits);
+        self.log_query("ask_docs", &question, filters, &hits, started)
+            .await;
+
+        let chunks_considered = hits.len();
+        let hits = ask::rerank(&search_text, hits);
+        let assembled = ask::assemble_context(&hits, token_budget);
+
+        AskDocsResponse {
+            question,
+            context: assembled.context,
+            citations: assembled.citations,
+            token_budget,
+            token_estimate: assembled.token_estimate,
+            chunks_considered,
+            chunks_included: assembled.chunks_included,
+            truncated: budget.is_some_and(|budget| budget.exceeded_in().is_some()),
+            inferred_filters: inferred,
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Give feedback on a search result. Mark a document as helpful to boost it, or as irrelevant (helpful: false) to bury it, in future searches. The adjustment is stored with the document and applied as a score multiplier, so the knowledge base improves as you use it."
+    )]
+    async fn feedback(
+        &self,
+        #[tool(aggr)] params: FeedbackParams,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_writable()?;
+        self.sync_with_disk().await;
+        let mut databases = vec![("project", self.vector_db.clone())];
+        if let Some(global_db) = &self.global_db {
+            databases.push(("global", global_db.clone()));
+        }
+
+        // Apply to whichever database holds the document
+        for (origin, database) in databases {
+            let mut db = database.lock().await;
+            if db.get_document(&params.id).is_none() {
+                continue;
+            }
+
+            let boost = db
+                .apply_feedback(&params.id, params.helpful)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            db.save().map_err(|e| {
+                McpError::internal_error(format!("Failed to save database: {}", e), None)
+            })?;
+
+            let origin = if self.active().project_info.is_project {
+                origin
+            } else {
+                "global"
+            };
+            return FeedbackResponse {
+                id: params.id,
+                helpful: params.helpful,
+                boost,
+                origin: origin.to_string(),
+            }
+            .into_tool_result();
+        }
+
+        Err(McpError::invalid_params(
+            format!("Document not found: {}", params.id),
+            None,
+        ))
+    }
+
+    #[tool(
+        description = "Embed arbitrary text with the same model and prefixes used for the knowledge base and return the raw vector. Pass `compare_to` to also get the cosine similarity between the two texts; `text` is embedded as a search query and `compare_to` as a document passage, so the similarity is the score search would give that passage (set `as_query: false` to compare two passages). Use this tool for your own clustering or similarity checks, or to debug why a search did or didn't match."
+    )]
+    async fn embed_text(
+        &self,
+        #[tool(aggr)] params: EmbedTextParams,
+    ) -> Result<CallToolResult, McpError> {
+        let embedding_service = self.embedding_service.lock().await;
+        let embed_error = |e: CodeRagError| {
+            McpError::internal_error(format!("Failed to embed text: {}", e), None)
+        };
+
+        let embedding = if params.as_query.unwrap_or(true) {
+            embedding_service.embed_query(&params.text).await
+        } else {
+            embedding_service.embed_passage(&params.text).await
+        }
+        .map_err(embed_error)?;
+
+        let similarity = match &params.compare_to {
+            Some(other) => {
+                let other = embedding_service
+                    .embed_passage(other)
+                    .await
+                    .map_err(embed_error)?;
+                Some(cosine_similarity(&embedding, &other))
+            }
+            None => None,
+        };
+
+        let profile = embedding_service.profile();
+        EmbedTextResponse {
+            model: profile.model.clone(),
+            dimension: embedding.len(),
+            embedding,
+            similarity,
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Map the knowledge base into topics. Clusters the indexed documents by embedding similarity and returns each topic's distinguishing keywords, its document count per documentation source, and the documents closest to its centre. Use this tool to see what the knowledge base covers and where coverage is thin: small topics, or topics served by a single source, point at documentation worth crawling."
+    )]
+    async fn map_docs(
+        &self,
+        #[tool(aggr)] params: MapDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let vector_db = self.vector_db.lock().await;
+        let topics = vector_db
+            .topic_map(params.topics, params.representatives.unwrap_or(3))
+            .map_err(|e| {
+                McpError::internal_error(format!("Failed to cluster documents: {}", e), None)
+            })?;
+
+        let topics = topics
+            .into_iter()
+            .map(|topic| TopicSummary {
+                keywords: topic.keywords,
+                document_count: topic.size,
+                sources: topic.sources,
+                representatives: topic
+                    .representatives
+                    .into_iter()
+                    .filter_map(|(id, similarity)| {
+                        let document = vector_db.get_document(&id)?;
+                        Some(TopicDocument {
+                            title: document.title.clone(),
+                            citation_url: document.citation_url(),
+                            breadcrumb: document.breadcrumb(),
+                            id,
+                            similarity,
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        MapDocsResponse {
+            document_count: vector_db.document_count(),
+            topics,
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Brief yourself on an indexed documentation source. Pass a page URL or URL prefix (e.g. https://docs.rs/tokio); the chunks most central to that source's content are picked by embedding similarity to their centroid, and their opening sentences are returned as an extractive summary with a deep link for each point. Use this tool right after crawling a new library to learn what it covers before searching it."
+    )]
+    async fn summarize_source(
+        &self,
+        #[tool(aggr)] params: SummarizeSourceParams,
+    ) -> Result<CallToolResult, McpError> {
+        let max_points = params
+            .max_points
+            .unwrap_or(summarize::DEFAULT_SUMMARY_POINTS)
+            .max(1);
+        let vector_db = self.vector_db.lock().await;
+        let (document_count, central) = vector_db
+            .central_documents(&params.source, max_points)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        if document_count == 0 {
+            return Err(McpError::invalid_params(
+                format!("No documents indexed for source: {}", params.source),
+                None,
+            ));
+        }
+
+        let (summary, points) = summarize::build_summary(&central);
+        SummarizeSourceResponse {
+            source: params.source,
+            document_count,
+            summary,
+            points,
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Report search analytics from the query log: total searches, latency, the most frequent queries, and the most frequent queries that returned no results. Use this tool to find gaps in the knowledge base — zero-result queries point at documentation worth crawling next."
+    )]
+    async fn search_analytics(
+        &self,
+        #[tool(aggr)] params: SearchAnalyticsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let entries = self
+            .active()
+            .query_log
+            .read()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        AnalyticsReport::from_entries(&entries, params.limit).into_tool_result()
+    }
 
-        // Process chunks
-        info!("Acquiring embedding service lock...");
-        let embedding_service = self.embedding_service.lock().await;
-        info!("Acquiring vector database lock...");
-        let mut vector_db = self.vector_db.lock().await;
-        let mut documents_created = 0;
+    #[tool(
+        description = "Retrieve the full content of a document. Pass `id` to fetch a single chunk from a search result, or `url` to fetch every chunk of a page reassembled in order with its title and section structure. Use this tool after search_docs when a hit looks relevant and you need the surrounding context."
+    )]
+    async fn get_doc(
+        &self,
+        #[tool(aggr)] params: GetDocParams,
+    ) -> Result<CallToolResult, McpError> {
+        let vector_db = self.vector_db.lock().await;
 
-        info!("Processing {} chunks...", chunks.len());
-        for (i, chunk) in chunks.iter().enumerate() {
-            let doc_id = format!("{}_chunk_{}", url, i);
+        let response = match (params.id, params.url) {
+            (Some(id), _) => {
+                let document = vector_db.get_document(&id).ok_or_else(|| {
+                    McpError::invalid_params(format!("Document not found: {}", id), None)
+                })?;
+
+                GetDocResponse::Chunk(DocChunk {
+                    id: document.id.clone(),
+                    url: document.url.clone(),
+                    citation_url: document.citation_url(),
+                    title: document.title.clone(),
+                    section: document.section.clone(),
+                    content: document.content.clone(),
+                    metadata: serde_json::to_value(&document.metadata).unwrap_or(json!({})),
+                })
+            }
+            (None, Some(url)) => {
+                let chunks = vector_db.get_documents_by_url(&url);
+                if chunks.is_empty() {
+                    return Err(McpError::invalid_params(
+                        format!("No documents indexed for URL: {}", url),
+                        None,
+                    ));
+                }
 
-            // Generate embedding
-            info!(
-                "Generating embedding for chunk {} of {} (size: {} bytes)",
-                i + 1,
-                chunks.len(),
-                chunk.content.len()
-            );
-            let embedding = embedding_service.embed(&chunk.content).await.map_err(|e| {
-                McpError::internal_error(format!("Failed to generate embedding: {}", e), None)
-            })?;
-            info!("Embedding generated successfully");
+                // Distinct sections in page order
+                let mut sections: Vec<String> = Vec::new();
+                for section in chunks.iter().filter_map(|doc| doc.section.as_deref()) {
+                    if sections.last().map(String::as_str) != Some(section) {
+                        sections.push(section.to_string());
+                    }
+                }
 
-            // Create document
-            let document = crate::vectordb::Document {
-                id: doc_id,
-                content: chunk.content.clone(),
-                url: url.to_string(),
-                title: Some(extracted.title.clone()),
-                section: chunk.heading_context.clone(),
-                metadata: crate::vectordb::DocumentMetadata {
-                    content_type: crate::vectordb::ContentType::Documentation,
-                    language: extracted.metadata.language.clone(),
-                    last_updated: Some(std::time::SystemTime::now()),
-                    tags: vec![
-                        if chunk.has_code {
-                            "has-code"
-                        } else {
-                            "no-code"
-                        }
-                        .to_string(),
-                        format!("chunk-{}-of-{}", i + 1, chunks.len()),
-                    ],
-                },
-            };
+                GetDocResponse::Page(DocPage {
+                    title: chunks.iter().find_map(|doc| doc.title.clone()),
+                    sections,
+                    chunk_count: chunks.len(),
+                    chunks: chunks
+                        .iter()
+                        .map(|doc| ChunkRef {
+                            id: doc.id.clone(),
+                            section: doc.section.clone(),
+                        })
+                        .collect(),
+                    content: crate::vectordb::reassemble_chunks(&chunks),
+                    url,
+                })
+            }
+            (None, None) => {
+                return Err(McpError::invalid_params(
+                    "Either 'id' or 'url' must be provided".to_string(),
+                    None,
+                ))
+            }
+        };
 
-            // Add to database
-            vector_db.add_document(document, embedding).map_err(|e| {
-                McpError::internal_error(format!("Failed to add document: {}", e), None)
-            })?;
-            documents_created += 1;
+        response.into_tool_result()
+    }
+
+    #[tool(
+        description = "Find documents related to a known-good result (\"more like this\"). Pass the `id` of a document returned by search_docs to explore a topic outward from it without writing a new query. Use this tool when a search hit is relevant and you want neighbouring pages, examples, or API details on the same subject."
+    )]
+    async fn related_docs(
+        &self,
+        #[tool(aggr)] params: RelatedDocsParams,
+    ) -> Result<CallToolResult, McpError> {
+        let vector_db = self.vector_db.lock().await;
+
+        if vector_db.get_document(&params.id).is_none() {
+            return Err(McpError::invalid_params(
+                format!("Document not found: {}", params.id),
+                None,
+            ));
         }
 
-        // Save the database
-        vector_db.save().map_err(|e| {
-            McpError::internal_error(format!("Failed to save database: {}", e), None)
-        })?;
+        let results = vector_db
+            .find_similar(&params.id, params.limit)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        // Build response
-        let response = json!({
-            "status": "success",
-            "source_url": url,
-            "mode": mode,
-            "pages_crawled": 1,
-            "documents_created": documents_created,
-            "chunks_created": chunks.len(),
-            "note": "Currently only single-page crawling is supported in the SDK version"
-        });
+        let related = results
+            .into_iter()
+            .map(|r| SearchHit {
+                url: r.document.url.clone(),
+                citation_url: r.document.citation_url(),
+                heading_path: r.document.metadata.heading_path.clone(),
+                breadcrumb: r.document.breadcrumb(),
+                alternate_urls: r.document.metadata.aliases.clone(),
+                id: r.document.id,
+                content: r.document.content,
+                score: r.score,
+                metadata: serde_json::to_value(r.document.metadata).unwrap_or(json!({})),
+                origin: None,
+            })
+            .collect();
 
-        let response_json = serde_json::to_string_pretty(&response)
+        SearchResponse::new(related, None).into_tool_result()
+    }
+
+    #[tool(
+        description = "Suggest documentation to crawl for the current project. Reads the project's Cargo.toml, package.json and pyproject.toml, maps each dependency to its documentation URL (docs.rs, npmjs, readthedocs), and lists the dependencies that have no indexed documentation yet. Use this tool when starting work on a project to see which crawl_docs calls would fill gaps in your knowledge base."
+    )]
+    async fn suggest_docs(&self) -> Result<CallToolResult, McpError> {
+        let Some(project_root) = self.active().project_info.project_root.clone() else {
+            return SuggestDocsResponse {
+                is_project: false,
+                project_root: None,
+                message: Some(
+                    "No project detected; dependency suggestions need a project manifest"
+                        .to_string(),
+                ),
+                dependency_count: 0,
+                documented: Vec::new(),
+                suggestions: Vec::new(),
+            }
+            .into_tool_result();
+        };
+
+        let dependencies = ProjectManager::detect_dependencies(&project_root)
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(response_json)]))
+        // Sources from every database a search would cover
+        let mut sources: HashSet<String> = self
+            .vector_db
+            .lock()
+            .await
+            .get_documents_by_source()
+            .into_keys()
+            .collect();
+        if let Some(global_db) = &self.global_db {
+            sources.extend(global_db.lock().await.get_documents_by_source().into_keys());
+        }
+
+        let (documented, undocumented): (Vec<_>, Vec<_>) = dependencies
+            .into_iter()
+            .partition(|dep| dep.is_documented_by(sources.iter().map(String::as_str)));
+
+        SuggestDocsResponse {
+            is_project: true,
+            project_root: Some(project_root.to_string_lossy().into_owned()),
+            message: None,
+            dependency_count: documented.len() + undocumented.len(),
+            documented: documented.into_iter().map(|dep| dep.name).collect(),
+            suggestions: undocumented,
+        }
+        .into_tool_result()
     }
 
     #[tool(
-        description = "Reload the vector database from disk to refresh your knowledge base with any externally added documentation. Use this tool if you suspect the database has been updated outside of your current session or if you need to refresh your available documentation sources."
+        description = "Reload the vector database from disk to refresh your knowledge base with any externally added documentation. The server already reloads automatically when another process saves the database, so use this tool only if you suspect an external update was missed."
     )]
     async fn reload_docs(&self) -> Result<CallToolResult, McpError> {
         let mut vector_db = self.vector_db.lock().await;
@@ -424,27 +2611,171 @@ impl CodeRagServer {
             .load()
             .map_err(|e| McpError::internal_error(format!("Reload failed: {}", e), None))?;
 
+        if let Some(global_db) = &self.global_db {
+            global_db.lock().await.load().map_err(|e| {
+                McpError::internal_error(format!("Global database reload failed: {}", e), None)
+            })?;
+        }
+
         let doc_count: usize = vector_db
             .get_documents_by_source()
             .values()
             .map(|docs| docs.len())
             .sum();
 
-        let response = json!({
-            "status": "success",
-            "documents_loaded": doc_count,
-        });
+        ReloadResponse {
+            status: "success".to_string(),
+            documents_loaded: doc_count,
+        }
+        .into_tool_result()
+    }
 
-        let response_json = serde_json::to_string_pretty(&response)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    #[tool(
+        description = "List the project databases CodeRAG has opened before, with each one's size on disk, crawled sources, and whether this server is serving it. Use this tool to find documentation indexed for another project, then switch_project to search it."
+    )]
+    async fn list_projects(&self) -> Result<CallToolResult, McpError> {
+        let registry = ProjectRegistry::load(self.project_manager.global_data_dir());
+        let active_root = self.active().project_info.project_root.clone();
+
+        let mut projects: Vec<ProjectSummary> = registry
+            .projects()
+            .iter()
+            .map(|(root, known)| {
+                let database_path = self.project_manager.project_database_path(root);
+                let exists = database_path.exists();
+                // Opening storage creates its directory, so skip projects without a database
+                let size_bytes = if exists {
+                    VectorStorage::new(&database_path).map_or(0, |storage| storage.disk_usage())
+                } else {
+                    0
+                };
+                ProjectSummary {
+                    name: known.name.clone(),
+                    root: root.to_string_lossy().into_owned(),
+                    database_path: database_path.to_string_lossy().into_owned(),
+                    exists,
+                    size_bytes,
+                    sources: SourceRegistry::for_database(&database_path)
+                        .sources()
+                        .keys()
+                        .cloned()
+                        .collect(),
+                    last_opened: known.last_opened.clone(),
+                    active: active_root.as_ref() == Some(root),
+                }
+            })
+            .collect();
+        projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+
+        ListProjectsResponse {
+            active_project: active_root.map(|root| root.to_string_lossy().into_owned()),
+            projects,
+        }
+        .into_tool_result()
+    }
+
+    #[tool(
+        description = "Switch this server to another project's database without restarting it. Pass a project root or name from list_projects. Later searches, crawls and management operations use that database; the server keeps the configuration it was started with."
+    )]
+    async fn switch_project(
+        &self,
+        #[tool(aggr)] params: SwitchProjectParams,
+    ) -> Result<CallToolResult, McpError> {
+        let data_dir = self.project_manager.global_data_dir();
+        let registry = ProjectRegistry::load(data_dir);
+        let root = registry
+            .find(&params.project)
+            .or_else(|| {
+                // A project not opened before, but with a database of its own
+                let path = Path::new(&params.project);
+                path.join(".coderag")
+                    .is_dir()
+                    .then(|| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()))
+            })
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    format!(
+                        "Unknown project '{}'; use list_projects to see the known ones",
+                        params.project
+                    ),
+                    None,
+                )
+            })?;
+
+        let info = self.project_manager.project_info_at(&root);
+        let db_path = info.database_path.clone();
+        let response =
+            |status: &str, documents_loaded: usize, read_only: bool| SwitchProjectResponse {
+                status: status.to_string(),
+                project_name: info.project_name.clone(),
+                project_root: root.to_string_lossy().into_owned(),
+                database_path: db_path.to_string_lossy().into_owned(),
+                documents_loaded,
+                read_only,
+            };
+
+        let current = self.active();
+        if current.project_info.database_path == db_path {
+            let documents = self.vector_db.lock().await.document_count();
+            return response("unchanged", documents, current.db_lock.is_none()).into_tool_result();
+        }
+        drop(current);
+
+        // Hold both locks so no tool sees one project's database with another's stores
+        let (documents, read_only) = {
+            let mut embedding_service = self.embedding_service.lock().await;
+            let mut vector_db = self.vector_db.lock().await;
+            let (switched, db_lock) = open_primary(
+                &db_path,
+                self.open_mode,
+                &self.config,
+                &mut embedding_service,
+            )
+            .map_err(|e| {
+                McpError::invalid_request(
+                    format!("Failed to open database {:?}: {}", db_path, e),
+                    None,
+                )
+            })?;
+            *vector_db = switched;
+            let read_only = db_lock.is_none();
+            *self.active.write().unwrap() = Arc::new(ActiveDatabase::new(
+                info.clone(),
+                &self.project_manager,
+                &self.config,
+                db_lock,
+            ));
+            (vector_db.document_count(), read_only)
+        };
+        let mut sources = self.sources.lock().await;
+        if let Err(e) = sources.flush_access() {
+            warn!("Failed to save source registry: {}", e);
+        }
+        *sources = SourceRegistry::for_database(&db_path);
+        drop(sources);
+
+        // The previous database's watcher stays, but reloading only checks the current file
+        if self.config.database.auto_reload {
+            let debounce = Duration::from_millis(self.config.database.reload_debounce_ms);
+            if let Err(e) =
+                watch::spawn_auto_reload(self.vector_db.clone(), &db_path, "primary", debounce)
+            {
+                warn!("⚠️ Could not watch primary database for changes: {}", e);
+            }
+        }
+        ProjectRegistry::remember(data_dir, &info);
+        info!(
+            "🔀 Switched to project database {:?} ({} documents)",
+            db_path, documents
+        );
 
-        Ok(CallToolResult::success(vec![Content::text(response_json)]))
+        response("switched", documents, read_only).into_tool_result()
     }
 
     #[tool(
-        description = "Manage documents in the knowledge base with operations like delete, expire, and refresh. Use this tool to maintain knowledge base quality by removing outdated content, cleaning up stale documents, or refreshing specific sources. This consolidates document lifecycle management into a single efficient tool."
+        description = "Manage documents in the knowledge base with operations like delete, expire, evict, refresh, verify, compact, restore, and prioritize. Use this tool to maintain knowledge base quality by removing outdated content, cleaning up stale documents, evicting sources no search has returned in max_age_days (evict), deleting by source URL, URL prefix, document ID, tag, or crawl session ID (delete_by; crawl_docs reports the session), refreshing specific sources, checking which indexed pages have gone dead or moved, compacting storage to reclaim space after many deletes and re-crawls, weighting a crawled source's results with prioritize (priority 0.5 ranks a mirror below official docs), or restoring a backup (delete, expire, evict and refresh back up the database first; restore with target 'latest' or a backup name, dry_run lists backups). This consolidates document lifecycle management into a single efficient tool."
     )]
-    async fn manage_docs(
+    pub(crate) async fn manage_docs(
         &self,
         #[tool(aggr)] params: ManageDocsParams,
     ) -> Result<CallToolResult, McpError> {
@@ -456,66 +2787,90 @@ impl CodeRagServer {
             crawl_mode,
             crawl_focus,
             max_pages,
+            sample_size,
+            delete_by,
+            priority,
         } = params;
 
+        // verify and dry runs only read; everything else saves the database
+        if operation != "verify" && !dry_run.unwrap_or(false) {
+            self.ensure_writable()?;
+        }
+        self.sync_with_disk().await;
+
         match operation.as_str() {
             "delete" => {
                 let mut vector_db = self.vector_db.lock().await;
                 let dry_run = dry_run.unwrap_or(false);
 
-                let deleted_count = if dry_run {
-                    // Count how many would be deleted without actually deleting
-                    let all_sources = vector_db.get_documents_by_source();
-                    all_sources.get(&target).map(|docs| docs.len()).unwrap_or(0)
-                } else {
-                    // Actually delete documents from the specified source
-                    vector_db.remove_documents_by_source(&target).map_err(|e| {
-                        McpError::internal_error(format!("Failed to delete documents: {}", e), None)
-                    })?
+                let delete_by = delete_by.unwrap_or_else(|| "source".to_string());
+                if !["source", "id", "prefix", "tag", "session"].contains(&delete_by.as_str()) {
+                    return Err(McpError::invalid_params(
+                        format!(
+                            "Invalid delete_by: {}. Must be 'source', 'id', 'prefix', 'tag', or 'session'",
+                            delete_by
+                        ),
+                        None,
+                    ));
+                }
+                let matches = |doc: &Document| match delete_by.as_str() {
+                    "id" => doc.id == target,
+                    "prefix" => doc.url.starts_with(target.as_str()),
+                    "tag" => doc.metadata.tags.contains(&target),
+                    "session" => doc
+                        .metadata
+                        .provenance
+                        .as_ref()
+                        .is_some_and(|p| p.session == target),
+                    _ => doc.url == target,
                 };
 
+                // Count first so only deletes that remove something take a backup
+                let mut deleted_count = vector_db.count_documents_where(matches);
+                let mut backup = None;
+                let mut archived = None;
                 if !dry_run && deleted_count > 0 {
+                    backup = self.backup_before("delete")?;
+                    archived = self.archive_where(&vector_db, matches)?;
+                    deleted_count = vector_db.remove_documents_where(matches).map_err(|e| {
+                        McpError::internal_error(format!("Failed to delete documents: {}", e), None)
+                    })?;
                     vector_db.save().map_err(|e| {
                         McpError::internal_error(format!("Failed to save database: {}", e), None)
                     })?;
+                    self.prune_sources(&vector_db).await;
                 }
 
-                let response = json!({
-                    "operation": "delete",
-                    "target": target,
-                    "deleted_documents": deleted_count,
-                    "dry_run": dry_run,
-                    "total_documents_remaining": vector_db.document_count()
-                });
-
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&response)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
+                ManageDocsResponse::Delete {
+                    target,
+                    delete_by,
+                    deleted_documents: deleted_count,
+                    dry_run,
+                    total_documents_remaining: vector_db.document_count(),
+                    archived_documents: archived,
+                    backup,
+                }
+                .into_tool_result()
             }
             "expire" => {
                 let mut vector_db = self.vector_db.lock().await;
                 let age_days = max_age_days.unwrap_or(90);
                 let dry_run = dry_run.unwrap_or(false);
+                let stale_sources = self.sources.lock().await.stale_sources(age_days);
+                let backup = if dry_run {
+                    None
+                } else {
+                    self.backup_before("expire")?
+                };
+                let mut archived = None;
 
                 let expired_count = if dry_run {
                     // Count how many would be expired without actually removing them
-                    use std::time::{Duration, SystemTime};
-                    let cutoff_time = SystemTime::now()
-                        .checked_sub(Duration::from_secs(age_days * 24 * 60 * 60))
-                        .unwrap_or(SystemTime::UNIX_EPOCH);
-
-                    vector_db
-                        .get_documents_by_source()
-                        .values()
-                        .flatten()
-                        .filter(|doc| {
-                            doc.metadata.last_updated.unwrap_or(SystemTime::UNIX_EPOCH)
-                                <= cutoff_time
-                        })
-                        .count()
+                    vector_db.documents_older_than(age_days).len()
                 } else {
-                    // Actually remove expired documents
+                    // Actually remove expired documents, archiving them first
+                    let expired = vector_db.documents_older_than(age_days);
+                    archived = self.archive_where(&vector_db, |doc| expired.contains(&doc.id))?;
                     vector_db.remove_documents_by_age(age_days).map_err(|e| {
                         McpError::internal_error(format!("Failed to expire documents: {}", e), None)
                     })?
@@ -525,25 +2880,48 @@ impl CodeRagServer {
                     vector_db.save().map_err(|e| {
                         McpError::internal_error(format!("Failed to save database: {}", e), None)
                     })?;
+                    self.prune_sources(&vector_db).await;
                 }
 
-                let response = json!({
-                    "operation": "expire",
-                    "max_age_days": age_days,
-                    "expired_documents": expired_count,
-                    "dry_run": dry_run,
-                    "total_documents_remaining": vector_db.document_count()
-                });
-
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&response)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
+                ManageDocsResponse::Expire {
+                    max_age_days: age_days,
+                    expired_documents: expired_count,
+                    dry_run,
+                    total_documents_remaining: vector_db.document_count(),
+                    stale_sources,
+                    archived_documents: archived,
+                    backup,
+                }
+                .into_tool_result()
             }
             "refresh" => {
                 let mut vector_db = self.vector_db.lock().await;
                 let dry_run = dry_run.unwrap_or(false);
 
+                let backup = if dry_run {
+                    None
+                } else {
+                    self.backup_before("refresh")?
+                };
+
+                // Keep the old chunks to report what the refresh changed
+                let previous: Vec<Document> = if dry_run {
+                    Vec::new()
+                } else {
+                    vector_db
+                        .get_documents_by_url(&target)
+                        .into_iter()
+                        .filter(|doc| doc.url == target)
+                        .cloned()
+                        .collect()
+                };
+
+                let archived = if dry_run {
+                    None
+                } else {
+                    self.archive_where(&vector_db, |doc| doc.url == target)?
+                };
+
                 // First, count/remove existing documents from this source
                 let existing_count = if dry_run {
                     vector_db
@@ -564,56 +2942,267 @@ impl CodeRagServer {
                 drop(vector_db);
 
                 let new_documents = if !dry_run {
-                    // Crawl new content to replace the old
-                    let crawl_result = self
-                        .crawl_docs(CrawlDocsParams {
-                            url: target.clone(),
-                            mode: crawl_mode.unwrap_or_else(|| "single".to_string()),
-                            focus: crawl_focus.unwrap_or_else(|| "all".to_string()),
-                            max_pages: max_pages.unwrap_or(1),
-                        })
-                        .await?;
-
-                    // Extract document count from crawl result
-                    if let Some(first_content) = crawl_result.content.first() {
-                        if let Some(text_content) = first_content.raw.as_text() {
-                            if let Ok(crawl_response) =
-                                serde_json::from_str::<serde_json::Value>(&text_content.text)
-                            {
-                                crawl_response
-                                    .get("documents_created")
-                                    .and_then(|v| v.as_u64())
-                                    .unwrap_or(0) as usize
-                            } else {
-                                0
-                            }
-                        } else {
-                            0
+                    // Crawl new content to replace the old, as the source was last crawled
+                    let recorded = self
+                        .sources
+                        .lock()
+                        .await
+                        .get(&target)
+                        .map(|record| record.settings.clone());
+                    self.crawl_page(CrawlDocsParams {
+                        url: target.clone(),
+                        mode: crawl_mode
+                            .or_else(|| recorded.as_ref().map(|s| s.mode.clone()))
+                            .unwrap_or_else(|| "single".to_string()),
+                        focus: crawl_focus
+                            .or_else(|| recorded.as_ref().map(|s| s.focus.clone()))
+                            .unwrap_or_else(|| "all".to_string()),
+                        max_pages: Some(
+                            max_pages
+                                .or_else(|| recorded.as_ref().and_then(|s| s.max_pages))
+                                .unwrap_or(1),
+                        ),
+                        confirmed: None,
+                        dry_run: None,
+                        include_patterns: recorded.as_ref().map(|s| s.include_patterns.clone()),
+                        exclude_patterns: recorded.map(|s| s.exclude_patterns),
+                    })
+                    .await?
+                    .documents_created
+                } else {
+                    0
+                };
+                let changes = if dry_run {
+                    None
+                } else {
+                    let vector_db = self.vector_db.lock().await;
+                    let current: Vec<&Document> = vector_db
+                        .get_documents_by_url(&target)
+                        .into_iter()
+                        .filter(|doc| doc.url == target)
+                        .collect();
+                    Some(RefreshChanges::between(&previous, &current))
+                };
+
+                ManageDocsResponse::Refresh {
+                    target,
+                    removed_documents: existing_count,
+                    new_documents,
+                    dry_run,
+                    net_change: new_documents as i64 - existing_count as i64,
+                    changes,
+                    archived_documents: archived,
+                    backup,
+                }
+                .into_tool_result()
+            }
+            "verify" => {
+                let vector_db = self.vector_db.lock().await;
+                let document_counts: BTreeMap<String, usize> = vector_db
+                    .get_documents_by_source()
+                    .into_iter()
+                    .filter(|(url, _)| url.starts_with("http"))
+                    .filter(|(url, _)| target == "*" || url.starts_with(&target))
+                    .map(|(url, documents)| (url, documents.len()))
+                    .collect();
+                // Release the lock before making requests
+                drop(vector_db);
+
+                let sample_size = sample_size.unwrap_or(verify::DEFAULT_SAMPLE_SIZE);
+                let urls =
+                    verify::sample_urls(document_counts.keys().map(String::as_str), sample_size);
+                let sources_checked = urls
+                    .iter()
+                    .map(|url| source_root(url))
+                    .collect::<HashSet<_>>()
+                    .len();
+
+                let client = build_client(
+                    &self.config.crawler.user_agent,
+                    &self.config.crawler.client_options(),
+                )
+                .map_err(|e| {
+                        McpError::internal_error(
+                            format!("Failed to create HTTP client: {}", e),
+                            None,
+                        )
+                    })?;
+                let auth = CrawlAuth::new(&self.config.crawler.auth).map_err(|e| {
+                    McpError::internal_error(format!("Invalid crawler auth: {}", e), None)
+                })?;
+
+                let mut problems = Vec::new();
+                for (index, url) in urls.iter().enumerate() {
+                    if index > 0 {
+                        tokio::time::sleep(std::time::Duration::from_millis(
+                            self.config.crawler.delay_ms,
+                        ))
+                        .await;
+                    }
+                    let Some(problem) = verify::check_url(&client, &auth, url).await else {
+                        continue;
+                    };
+                    info!("Verify: {} is {:?}", url, problem);
+                    let mut entry = SourceProblem {
+                        url: url.clone(),
+                        problem: String::new(),
+                        status: None,
+                        redirected_to: None,
+                        error: None,
+                        affected_documents: document_counts.get(url).copied().unwrap_or(0),
+                    };
+                    match problem {
+                        PageProblem::Gone(status) => {
+                            entry.problem = "gone".to_string();
+                            entry.status = Some(status);
+                        }
+                        PageProblem::Moved(location) => {
+                            entry.problem = "moved".to_string();
+                            entry.redirected_to = Some(location);
+                        }
+                        PageProblem::Unreachable(error) => {
+                            entry.problem = "unreachable".to_string();
+                            entry.error = Some(error);
+                        }
+                    }
+                    problems.push(entry);
+                }
+
+                ManageDocsResponse::Verify {
+                    target,
+                    sources_checked,
+                    urls_checked: urls.len(),
+                    problems,
+                }
+                .into_tool_result()
+            }
+            "compact" => {
+                let mut vector_db = self.vector_db.lock().await;
+                let dry_run = dry_run.unwrap_or(false);
+
+                let response = if dry_run {
+                    ManageDocsResponse::Compact {
+                        entries_dropped: vector_db.compactable_entries(),
+                        dry_run,
+                        bytes_before: vector_db.disk_usage(),
+                        bytes_after: None,
+                        bytes_reclaimed: None,
+                        total_documents_remaining: vector_db.document_count(),
+                        stored_pages_removed: None,
+                        snapshots_removed: None,
+                    }
+                } else {
+                    let report = vector_db.compact().map_err(|e| {
+                        McpError::internal_error(format!("Failed to compact database: {}", e), None)
+                    })?;
+                    info!(
+                        "Compacted database: dropped {} entries, reclaimed {} bytes",
+                        report.entries_dropped,
+                        report.bytes_reclaimed()
+                    );
+                    let stored_pages_removed = match &self.active().page_store {
+                        Some(store) => {
+                            let sources = vector_db.get_documents_by_source();
+                            Some(store.retain(sources.keys().map(String::as_str)).map_err(
+                                |e| {
+                                    McpError::internal_error(
+                                        format!("Failed to prune stored pages: {}", e),
+                                        None,
+                                    )
+                                },
+                            )?)
+                        }
+                        None => None,
+                    };
+                    let snapshots_removed = match &self.active().blob_store {
+                        Some(store) => {
+                            // Archived documents keep their snapshots too
+                            let archive = self.open_archive()?;
+                            let mut snapshots = vector_db.snapshots();
+                            snapshots.extend(archive.iter().flat_map(|a| a.snapshots()));
+                            Some(store.retain(snapshots).map_err(|e| {
+                                McpError::internal_error(
+                                    format!("Failed to prune snapshots: {}", e),
+                                    None,
+                                )
+                            })?)
                         }
-                    } else {
-                        0
+                        None => None,
+                    };
+                    ManageDocsResponse::Compact {
+                        entries_dropped: report.entries_dropped,
+                        dry_run,
+                        bytes_before: report.bytes_before,
+                        bytes_after: Some(report.bytes_after),
+                        bytes_reclaimed: Some(report.bytes_reclaimed()),
+                        total_documents_remaining: vector_db.document_count(),
+                        stored_pages_removed,
+                        snapshots_removed,
                     }
+                };
+
+                response.into_tool_result()
+            }
+            "restore" => {
+                let mut vector_db = self.vector_db.lock().await;
+                let dry_run = dry_run.unwrap_or(false);
+
+                let restored = if dry_run {
+                    None
                 } else {
-                    0
+                    let name = (target != "latest").then_some(target.as_str());
+                    let backup = self.active().backups.restore(name).map_err(|e| {
+                        McpError::invalid_params(format!("Restore failed: {}", e), None)
+                    })?;
+                    vector_db.load().map_err(|e| {
+                        McpError::internal_error(
+                            format!("Failed to load restored database: {}", e),
+                            None,
+                        )
+                    })?;
+                    Some(backup)
                 };
 
-                let response = json!({
-                    "operation": "refresh",
-                    "target": target,
-                    "removed_documents": existing_count,
-                    "new_documents": new_documents,
-                    "dry_run": dry_run,
-                    "net_change": new_documents as i64 - existing_count as i64
-                });
+                let backups = self.active().backups.list().map_err(|e| {
+                    McpError::internal_error(format!("Failed to list backups: {}", e), None)
+                })?;
 
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&response)
-                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
-                )]))
+                ManageDocsResponse::Restore {
+                    target,
+                    restored,
+                    dry_run,
+                    backups,
+                    total_documents_remaining: vector_db.document_count(),
+                }
+                .into_tool_result()
+            }
+            "evict" => {
+                let max_age_days = max_age_days
+                    .or(self.config.database.evict_unused_days)
+                    .unwrap_or(90);
+                self.evict_unused(max_age_days, dry_run.unwrap_or(false))
+                    .await?
+                    .into_tool_result()
+            }
+            "prioritize" => {
+                let mut sources = self.sources.lock().await;
+                let previous_priority = sources
+                    .set_priority(&target, priority)
+                    .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+                sources.save().map_err(|e| {
+                    McpError::internal_error(format!("Failed to save sources: {}", e), None)
+                })?;
+
+                ManageDocsResponse::Prioritize {
+                    target,
+                    priority: priority.unwrap_or(1.0),
+                    previous_priority,
+                }
+                .into_tool_result()
             }
             _ => Err(McpError::invalid_params(
                 format!(
-                    "Invalid operation: {}. Must be 'delete', 'expire', or 'refresh'",
+                    "Invalid operation: {}. Must be 'delete', 'expire', 'evict', 'refresh', 'verify', 'compact', 'restore', or 'prioritize'",
                     operation
                 ),
                 None,
@@ -622,12 +3211,15 @@ impl CodeRagServer {
     }
 }
 
-#[tool(tool_box)]
 impl ServerHandler for CodeRagServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_prompts()
+                .enable_tools()
+                .build(),
             server_info: Implementation {
                 name: "coderag".to_string(),
                 version: "0.1.0".to_string(),
@@ -639,11 +3231,190 @@ impl ServerHandler for CodeRagServer {
                 Use search_docs proactively when you need accurate, up-to-date information for coding assistance. \
                 Use crawl_docs autonomously to index new documentation when you encounter unfamiliar technologies. \
                 Use list_docs to see what's available in your knowledge base and current project context. \
-                Use reload_docs to refresh your database. \
-                Each project maintains its own isolated documentation set, ensuring relevant results. \
+                Use ask_docs to get token-budgeted context with citations for a question in a single call. \
+                Use get_doc to read the full page behind a search hit. \
+                Use feedback to boost helpful results and bury irrelevant ones. \
+                Use search_analytics to see which searches returned nothing, then crawl documentation for them. \
+                Use related_docs to find more documents like a relevant search hit. \
+                Use embed_text to get raw embeddings and similarity scores for your own comparisons. \
+                Use map_docs to see the topics the knowledge base covers and spot coverage gaps. \
+                Use summarize_source to brief yourself on a newly crawled library. \
+                Use suggest_docs to find project dependencies whose documentation is not indexed yet. \
+                Use list_projects to see the databases of other projects, and switch_project to serve one of them. \
+                In workspaces and monorepos, pass a member name as the search_docs scope to only search documentation for that member's dependencies, and the file you are working on as path to favor docs indexed for its package. \
+                The database reloads automatically when another process updates it; reload_docs forces a reload. \
+                Tool results are an embedded application/json resource (coderag://responses/<Type>) holding the response as JSON, followed by the same JSON as text. \
+                Each project maintains its own documentation set; search_docs also covers the global database and labels each hit with its origin. \
                 You have full agency to use these tools as needed to provide better coding help - don't wait for permission."
                     .to_string(),
             ),
         }
     }
+
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            tools: Self::tool_box().list(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let peer = context.peer.clone();
+        let call = Self::tool_box().call(ToolCallContext::new(self, request, context));
+        self.with_model_status(&peer, call).await
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        *self.log_level.write().unwrap() = request.level;
+        Ok(())
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult {
+            prompts: PromptTemplate::list(),
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let template = PromptTemplate::from_name(&request.name).ok_or_else(|| {
+            McpError::invalid_params(format!("Unknown prompt: {}", request.name), None)
+        })?;
+        let arguments = request.arguments.unwrap_or_default();
+
+        let query = template.query(&arguments).ok_or_else(|| {
+            McpError::invalid_params(
+                format!("Missing required argument: {}", template.subject_argument()),
+                None,
+            )
+        })?;
+        let subject = arguments
+            .get(template.subject_argument())
+            .and_then(|value| value.as_str())
+            .unwrap_or(&query)
+            .trim()
+            .to_string();
+
+        let hits = self
+            .search_hits(SearchDocsParams {
+                query,
+                limit: Some(PromptTemplate::limit(&arguments)),
+                source_filter: None,
+                content_type: None,
+                layered: None,
+                scope: None,
+                lang: None,
+                section: None,
+                version: None,
+                time_budget_ms: None,
+                include_archived: None,
+                recency_half_life_days: None,
+                exclude_sources: None,
+                exclude_tags: None,
+                path: None,
+            })
+            .await?
+            .0;
+
+        Ok(GetPromptResult {
+            description: Some(format!(
+                "{} ({} documentation chunks)",
+                template.name(),
+                hits.len()
+            )),
+            messages: vec![template.render(&subject, &hits)],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn document(url: &str, i: usize) -> Document {
+        let content = format!("Chunk {} of {}: {}", i, url, "lorem ipsum ".repeat(40));
+        Document::for_test(&format!("{}#{}", url, i))
+            .with_content(&content)
+            .with_url(url)
+    }
+
+    #[test]
+    fn test_eviction_brings_database_under_quota() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("vectors.json");
+        let mut vector_db = VectorDatabase::new(&db_path).unwrap();
+        let sources = [
+            "https://docs.rs/serde",
+            "https://docs.rs/rand",
+            "https://tokio.rs/",
+        ];
+        for source in sources {
+            for i in 0..20 {
+                let url = format!("{}/page{}", source, i % 4);
+                vector_db
+                    .add_document(document(&url, i), vec![0.5; 8])
+                    .unwrap();
+            }
+        }
+        vector_db.save().unwrap();
+
+        // Fill past a limit a little under the database's size
+        let size = vector_db.disk_usage();
+        let max_bytes = size * 9 / 10;
+        let lru: Vec<String> = sources.iter().map(|s| s.to_string()).collect();
+
+        // The source being crawled is never picked, the least recently used goes first
+        let evicted = sources_to_evict(
+            &vector_db,
+            lru.clone(),
+            size,
+            max_bytes,
+            "https://docs.rs/serde/x",
+        )
+        .unwrap();
+        assert_eq!(evicted, ["https://docs.rs/rand"]);
+
+        vector_db
+            .remove_documents_where(|doc| evicted.iter().any(|s| doc.url.starts_with(s)))
+            .unwrap();
+        vector_db.compact().unwrap();
+        assert_eq!(vector_db.document_count(), 40);
+        assert!(vector_db.disk_usage() < max_bytes / 10 * 9);
+
+        // Nothing is evicted when backups alone keep it over the limit
+        let backups = dir.path().join("backups");
+        std::fs::create_dir_all(&backups).unwrap();
+        std::fs::write(
+            backups.join("evict.json"),
+            vec![b'x'; 2 * max_bytes as usize],
+        )
+        .unwrap();
+        let size = vector_db.disk_usage() + data_dir::disk_usage(&backups);
+        assert!(size > max_bytes);
+        assert_eq!(
+            sources_to_evict(&vector_db, lru, size, max_bytes, "https://docs.rs/new"),
+            None
+        );
+    }
 }
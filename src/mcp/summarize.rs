@@ -0,0 +1,111 @@
+//! Extractive source summaries for summarize_source.
+//!
+//! The chunks closest to the centroid of a source's embeddings cover what
+//! the source is mostly about. Each one contributes its opening sentences
+//! and a deep link, most central first.
+
+use crate::crawler::links::strip_links;
+use crate::mcp::responses::SummaryPoint;
+use crate::vectordb::Document;
+
+/// Default number of chunks a summary is built from
+pub const DEFAULT_SUMMARY_POINTS: usize = 8;
+
+/// Longest excerpt taken from a single chunk
+const MAX_EXCERPT_CHARS: usize = 300;
+
+/// Build summary points from central chunks and lay them out as a Markdown list
+pub fn build_summary(central: &[(&Document, f32)]) -> (String, Vec<SummaryPoint>) {
+    let points: Vec<SummaryPoint> = central
+        .iter()
+        .filter_map(|(document, centrality)| {
+            let text = lead_sentences(&document.content, MAX_EXCERPT_CHARS);
+            (!text.is_empty()).then(|| SummaryPoint {
+                text,
+                title: document.title.clone(),
+                breadcrumb: document.breadcrumb(),
+                citation_url: document.citation_url(),
+                centrality: *centrality,
+            })
+        })
+        .collect();
+
+    let summary = points
+        .iter()
+        .map(|point| {
+            let label = point
+                .breadcrumb
+                .as_deref()
+                .or(point.title.as_deref())
+                .unwrap_or(&point.citation_url);
+            format!("- {} ([{}]({}))", point.text, label, point.citation_url)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    (summary, points)
+}
+
+/// Opening prose sentences of a chunk, up to about `max_chars`
+///
+/// Headings, code blocks and table rows are skipped. A first sentence longer
+/// than the limit is cut at a word boundary.
+pub fn lead_sentences(content: &str, max_chars: usize) -> String {
+    let mut in_code = false;
+    let mut prose = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || line.is_empty() || line.starts_with('#') || line.starts_with('|') {
+            continue;
+        }
+        prose.push(line);
+    }
+    let prose = strip_links(&prose.join(" "));
+
+    let mut excerpt = String::new();
+    for sentence in prose.split_inclusive(". ") {
+        if excerpt.chars().count() + sentence.chars().count() > max_chars {
+            break;
+        }
+        excerpt.push_str(sentence);
+    }
+
+    if excerpt.is_empty() && !prose.is_empty() {
+        let mut words = Vec::new();
+        let mut length = 0;
+        for word in prose.split_whitespace() {
+            length += word.chars().count() + 1;
+            if length > max_chars {
+                break;
+            }
+            words.push(word);
+        }
+        excerpt = format!("{}…", words.join(" "));
+    }
+
+    excerpt.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lead_sentences() {
+        let content = "## Spawning\n\nTasks run on the [runtime](https://tokio.rs/). \
+            They are cheap.\n\n```rust\ntokio::spawn(async {});\n```\n| a | b |\nA third sentence.";
+        assert_eq!(
+            lead_sentences(content, 50),
+            "Tasks run on the runtime. They are cheap."
+        );
+        assert_eq!(
+            lead_sentences(content, 300),
+            "Tasks run on the runtime. They are cheap. A third sentence."
+        );
+        assert_eq!(lead_sentences(content, 12), "Tasks run…");
+        assert_eq!(lead_sentences("```\ncode\n```", 100), "");
+    }
+}
@@ -0,0 +1,95 @@
+//! Automatic reload of databases saved by other processes.
+//!
+//! A CLI crawl or a second editor window may save the database a server has
+//! loaded. The server watches the database's directory and, once events for
+//! the database file have stopped arriving for the debounce interval, reloads
+//! it if its modification time differs from the one this server last saw.
+//! The server's own saves therefore don't trigger a reload.
+
+use crate::vectordb::VectorDatabase;
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Reload a database if another process saved it, logging the document delta
+///
+/// Returns whether it was reloaded.
+pub fn reload_if_changed(vector_db: &mut VectorDatabase, label: &str) -> bool {
+    let before = vector_db.document_count();
+    match vector_db.reload_if_changed() {
+        Ok(true) => {
+            let after = vector_db.document_count();
+            info!(
+                "🔄 Reloaded {} database saved by another process: {} -> {} documents ({:+})",
+                label,
+                before,
+                after,
+                after as i64 - before as i64
+            );
+            true
+        }
+        Ok(false) => false,
+        Err(e) => {
+            warn!("Failed to reload {} database changed on disk: {}", label, e);
+            false
+        }
+    }
+}
+
+/// Watch a database file and reload it when another process saves it
+///
+/// The watcher lives as long as the spawned task, i.e. until the runtime stops.
+pub fn spawn_auto_reload(
+    vector_db: Arc<Mutex<VectorDatabase>>,
+    db_path: &Path,
+    label: &'static str,
+    debounce: Duration,
+) -> Result<()> {
+    let dir = db_path
+        .parent()
+        .context("Database path has no parent directory")?
+        .to_path_buf();
+    let file_name = db_path.file_name().map(|name| name.to_os_string());
+
+    // Saves replace the file by renaming a temp file over it, so watch the
+    // directory rather than the file itself
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher: RecommendedWatcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == file_name.as_deref())
+            {
+                let _ = tx.send(());
+            }
+        })?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {:?}", dir))?;
+    debug!("Watching {:?} for changes to the {} database", dir, label);
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            // Wait until the burst of events from one save is over
+            loop {
+                match tokio::time::timeout(debounce, rx.recv()).await {
+                    Ok(Some(())) => continue,
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            reload_if_changed(&mut *vector_db.lock().await, label);
+        }
+    });
+
+    Ok(())
+}
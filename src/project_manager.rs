@@ -1,4 +1,9 @@
-use anyhow::{Context, Result};
+use crate::data_dir::{DataDir, DATABASE_STEM};
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::StorageBackend;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -7,11 +12,26 @@ use std::path::{Path, PathBuf};
 pub struct ProjectManager {
     /// The base data directory for fallback/global database
     global_data_dir: PathBuf,
+    /// File format of the databases it locates
+    backend: StorageBackend,
 }
 
 impl ProjectManager {
     pub fn new(global_data_dir: PathBuf) -> Self {
-        Self { global_data_dir }
+        Self {
+            global_data_dir,
+            backend: StorageBackend::default(),
+        }
+    }
+
+    pub fn global_data_dir(&self) -> &Path {
+        &self.global_data_dir
+    }
+
+    /// Locate databases stored with the given backend
+    pub fn with_backend(mut self, backend: StorageBackend) -> Self {
+        self.backend = backend;
+        self
     }
 
     /// Detect the current project root by looking for common project markers
@@ -41,7 +61,10 @@ impl ProjectManager {
             // Check if any project marker exists in current directory
             for marker in &project_markers {
                 if current.join(marker).exists() {
-                    return Some(current.to_path_buf());
+                    // A workspace member belongs to the enclosing workspace
+                    return Some(
+                        Self::find_workspace_root(current).unwrap_or_else(|| current.to_path_buf()),
+                    );
                 }
             }
 
@@ -55,6 +78,115 @@ impl ProjectManager {
         None
     }
 
+    /// Find the nearest Cargo or npm workspace that has `start_path` as a member
+    ///
+    /// A workspace further up that doesn't list the directory in its members
+    /// is not its workspace, e.g. a crate vendored inside another repository.
+    fn find_workspace_root(start_path: &Path) -> Option<PathBuf> {
+        start_path
+            .ancestors()
+            .find(|dir| {
+                Self::workspace_patterns(dir).is_some_and(|patterns| {
+                    *dir == start_path
+                        || patterns.iter().any(|pattern| {
+                            Self::expand_member_pattern(dir, pattern)
+                                .iter()
+                                .any(|member| member == start_path)
+                        })
+                })
+            })
+            .map(Path::to_path_buf)
+    }
+
+    /// Member patterns of the Cargo or npm workspace declared in `dir`, if any
+    ///
+    /// Patterns come from Cargo `workspace.members` and npm `workspaces`.
+    fn workspace_patterns(dir: &Path) -> Option<Vec<String>> {
+        let mut patterns: Vec<String> = Vec::new();
+        let mut is_workspace = false;
+
+        if let Some(manifest) = fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()
+            .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        {
+            is_workspace |= manifest.get("workspace").is_some();
+            if let Some(members) = manifest
+                .get("workspace")
+                .and_then(|w| w.get("members"))
+                .and_then(|m| m.as_array())
+            {
+                patterns.extend(members.iter().filter_map(|m| m.as_str().map(String::from)));
+            }
+        }
+
+        if let Some(manifest) = fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        {
+            is_workspace |= manifest.get("workspaces").is_some();
+            // Either an array or { "packages": [...] } (yarn)
+            let workspaces = manifest.get("workspaces").and_then(|w| {
+                w.as_array()
+                    .or_else(|| w.get("packages").and_then(|p| p.as_array()))
+            });
+            if let Some(workspaces) = workspaces {
+                patterns.extend(
+                    workspaces
+                        .iter()
+                        .filter_map(|w| w.as_str().map(String::from)),
+                );
+            }
+        }
+
+        is_workspace.then_some(patterns)
+    }
+
+    /// List the members of the workspace rooted at `root`
+    ///
+    /// Members come from Cargo `workspace.members` and npm `workspaces`. For a
+    /// polyglot monorepo without a workspace manifest, every direct
+    /// subdirectory with its own manifest is treated as a member.
+    pub fn workspace_members(root: &Path) -> Vec<WorkspaceMember> {
+        let mut patterns = Self::workspace_patterns(root).unwrap_or_default();
+        if patterns.is_empty() {
+            patterns.push("*".to_string());
+        }
+
+        let mut members: Vec<WorkspaceMember> = Vec::new();
+        for pattern in patterns {
+            for dir in Self::expand_member_pattern(root, &pattern) {
+                if members.iter().any(|m| m.path == dir) {
+                    continue;
+                }
+                if let Some(member) = WorkspaceMember::from_dir(root, &dir) {
+                    members.push(member);
+                }
+            }
+        }
+
+        members
+    }
+
+    /// Expand a workspace member pattern; only a trailing `*` is supported
+    fn expand_member_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+        let pattern = pattern.trim_end_matches('/');
+        let Some(parent) = pattern.strip_suffix('*') else {
+            return vec![root.join(pattern)];
+        };
+
+        let mut dirs: Vec<PathBuf> = fs::read_dir(root.join(parent))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        dirs
+    }
+
     /// Get the vector database path for the current context
     pub fn get_database_path(&self) -> Result<PathBuf> {
         // Try to detect project root
@@ -63,29 +195,45 @@ impl ProjectManager {
 
             // Create .coderag directory if it doesn't exist
             if !coderag_dir.exists() {
-                fs::create_dir_all(&coderag_dir).with_context(|| {
-                    format!("Failed to create .coderag directory at {:?}", coderag_dir)
+                fs::create_dir_all(&coderag_dir).map_err(|e| {
+                    CodeRagError::io(
+                        format!("Failed to create .coderag directory at {:?}", coderag_dir),
+                        e,
+                    )
                 })?;
 
                 // Add .coderag to .gitignore
                 self.update_gitignore(&project_root)?;
             }
 
-            Ok(coderag_dir.join("vectordb.json"))
+            Ok(self.project_database_path(&project_root))
         } else {
             // Fall back to global database
-            Ok(self.global_data_dir.join("coderag_vectordb.json"))
+            Ok(self.global_database_path())
         }
     }
 
+    /// Get the path of the global database, regardless of project context
+    pub fn global_database_path(&self) -> PathBuf {
+        DataDir::at(&self.global_data_dir).database_path(&self.database_file(DATABASE_STEM))
+    }
+
+    fn database_file(&self, stem: &str) -> String {
+        format!("{}.{}", stem, self.backend.extension())
+    }
+
     /// Update .gitignore to include .coderag directory
     fn update_gitignore(&self, project_root: &Path) -> Result<()> {
         let gitignore_path = project_root.join(".gitignore");
 
         // Read existing .gitignore content
         let mut content = if gitignore_path.exists() {
-            fs::read_to_string(&gitignore_path)
-                .with_context(|| format!("Failed to read .gitignore at {:?}", gitignore_path))?
+            fs::read_to_string(&gitignore_path).map_err(|e| {
+                CodeRagError::io(
+                    format!("Failed to read .gitignore at {:?}", gitignore_path),
+                    e,
+                )
+            })?
         } else {
             String::new()
         };
@@ -105,8 +253,12 @@ impl ProjectManager {
             content.push('\n');
 
             // Write updated .gitignore
-            fs::write(&gitignore_path, content)
-                .with_context(|| format!("Failed to write .gitignore at {:?}", gitignore_path))?;
+            fs::write(&gitignore_path, content).map_err(|e| {
+                CodeRagError::io(
+                    format!("Failed to write .gitignore at {:?}", gitignore_path),
+                    e,
+                )
+            })?;
         }
 
         Ok(())
@@ -115,25 +267,455 @@ impl ProjectManager {
     /// Get information about the current project context
     pub fn get_project_info(&self) -> ProjectInfo {
         if let Some(project_root) = Self::detect_project_root() {
-            let db_path = project_root.join(".coderag").join("vectordb.json");
-            ProjectInfo {
-                is_project: true,
-                project_root: Some(project_root.clone()),
-                database_path: db_path,
-                project_name: project_root
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string()),
-            }
+            self.project_info_at(&project_root)
         } else {
             ProjectInfo {
                 is_project: false,
                 project_root: None,
-                database_path: self.global_data_dir.join("coderag_vectordb.json"),
+                database_path: self.global_database_path(),
                 project_name: None,
+                members: Vec::new(),
             }
         }
     }
+
+    /// Information about the project rooted at `project_root`
+    pub fn project_info_at(&self, project_root: &Path) -> ProjectInfo {
+        ProjectInfo {
+            is_project: true,
+            project_root: Some(project_root.to_path_buf()),
+            database_path: self.project_database_path(project_root),
+            project_name: project_root
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string()),
+            members: Self::workspace_members(project_root),
+        }
+    }
+
+    /// Routes from paths inside a project to its members' own databases
+    ///
+    /// A member of a workspace or monorepo can keep a `.coderag` database of
+    /// its own, next to the one at the project root.
+    pub fn database_routes(&self, project_root: &Path) -> DatabaseRoutes {
+        let nested = Self::workspace_members(project_root)
+            .into_iter()
+            .filter_map(|member| {
+                let database_path = self.project_database_path(&member.path);
+                database_path.exists().then(|| NestedDatabase {
+                    name: member.name,
+                    root: member.path.canonicalize().unwrap_or(member.path),
+                    database_path,
+                })
+            })
+            .collect();
+        DatabaseRoutes {
+            project_root: project_root.to_path_buf(),
+            nested,
+        }
+    }
+
+    /// Path of a project's database, whether or not it exists yet
+    pub fn project_database_path(&self, project_root: &Path) -> PathBuf {
+        project_root
+            .join(".coderag")
+            .join(self.database_file("vectordb"))
+    }
+
+    /// Detect the dependencies declared in the project's manifests
+    ///
+    /// Reads Cargo.toml, package.json and pyproject.toml from the project root
+    /// and maps each dependency to its canonical documentation URL.
+    pub fn detect_dependencies(project_root: &Path) -> Result<Vec<ProjectDependency>> {
+        let mut dependencies = Vec::new();
+
+        let cargo_toml = project_root.join("Cargo.toml");
+        if cargo_toml.exists() {
+            let content = read_manifest(&cargo_toml)?;
+            let manifest: toml::Value =
+                toml::from_str(&content).map_err(|e| manifest_error(&cargo_toml, e))?;
+
+            let tables = [
+                manifest.get("dependencies"),
+                manifest.get("dev-dependencies"),
+                manifest
+                    .get("workspace")
+                    .and_then(|w| w.get("dependencies")),
+            ];
+            for table in tables.into_iter().flatten() {
+                if let Some(table) = table.as_table() {
+                    for name in table.keys() {
+                        dependencies.push(ProjectDependency::new(name, Ecosystem::Rust));
+                    }
+                }
+            }
+        }
+
+        let package_json = project_root.join("package.json");
+        if package_json.exists() {
+            let content = read_manifest(&package_json)?;
+            let manifest: serde_json::Value =
+                serde_json::from_str(&content).map_err(|e| manifest_error(&package_json, e))?;
+
+            for key in ["dependencies", "devDependencies"] {
+                if let Some(table) = manifest.get(key).and_then(|t| t.as_object()) {
+                    for name in table.keys() {
+                        let mut dependency = ProjectDependency::new(name, Ecosystem::Node);
+                        dependency.documentation = installed_homepage(project_root, name);
+                        dependencies.push(dependency);
+                    }
+                }
+            }
+        }
+
+        let pyproject = project_root.join("pyproject.toml");
+        if pyproject.exists() {
+            let content = read_manifest(&pyproject)?;
+            let manifest: toml::Value =
+                toml::from_str(&content).map_err(|e| manifest_error(&pyproject, e))?;
+
+            // PEP 621 dependency specifiers, e.g. "requests>=2.31"
+            if let Some(specs) = manifest
+                .get("project")
+                .and_then(|p| p.get("dependencies"))
+                .and_then(|d| d.as_array())
+            {
+                for spec in specs.iter().filter_map(|s| s.as_str()) {
+                    let name = spec
+                        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+                        .next()
+                        .unwrap_or_default();
+                    if !name.is_empty() {
+                        dependencies.push(ProjectDependency::new(name, Ecosystem::Python));
+                    }
+                }
+            }
+
+            // Poetry dependency table
+            if let Some(table) = manifest
+                .get("tool")
+                .and_then(|t| t.get("poetry"))
+                .and_then(|p| p.get("dependencies"))
+                .and_then(|d| d.as_table())
+            {
+                for name in table.keys().filter(|name| *name != "python") {
+                    dependencies.push(ProjectDependency::new(name, Ecosystem::Python));
+                }
+            }
+        }
+
+        // A dependency can appear in several tables
+        let mut seen = std::collections::HashSet::new();
+        dependencies.retain(|dep| seen.insert((dep.ecosystem, dep.name.clone())));
+
+        Ok(dependencies)
+    }
+}
+
+/// A crate or package inside a workspace or monorepo
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct WorkspaceMember {
+    /// Package name from the member's manifest, or its directory name
+    pub name: String,
+    pub path: PathBuf,
+    pub ecosystem: Ecosystem,
+}
+
+impl WorkspaceMember {
+    /// Build a member from a directory, if it has a recognized manifest
+    fn from_dir(root: &Path, dir: &Path) -> Option<Self> {
+        let read_toml = |file: &str| {
+            fs::read_to_string(dir.join(file))
+                .ok()
+                .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        };
+
+        let (ecosystem, name) = if let Some(manifest) = read_toml("Cargo.toml") {
+            let name = manifest
+                .get("package")
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .map(String::from);
+            (Ecosystem::Rust, name)
+        } else if let Some(manifest) = fs::read_to_string(dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        {
+            let name = manifest
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(String::from);
+            (Ecosystem::Node, name)
+        } else if let Some(manifest) = read_toml("pyproject.toml") {
+            let name = manifest
+                .get("project")
+                .or_else(|| manifest.get("tool").and_then(|t| t.get("poetry")))
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .map(String::from);
+            (Ecosystem::Python, name)
+        } else {
+            return None;
+        };
+
+        let name = name.or_else(|| {
+            dir.strip_prefix(root)
+                .ok()
+                .map(|rel| rel.to_string_lossy().into_owned())
+        })?;
+
+        Some(Self {
+            name,
+            path: dir.to_path_buf(),
+            ecosystem,
+        })
+    }
+}
+
+/// Package ecosystem a dependency belongs to
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Rust,
+    Node,
+    Python,
+}
+
+/// A dependency declared in a project manifest
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ProjectDependency {
+    pub name: String,
+    pub ecosystem: Ecosystem,
+    /// Canonical documentation URL for the dependency
+    pub docs_url: String,
+    /// Documentation URL the package declares itself, when it is installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<String>,
+}
+
+impl ProjectDependency {
+    fn new(name: &str, ecosystem: Ecosystem) -> Self {
+        let docs_url = match ecosystem {
+            Ecosystem::Rust => format!("https://docs.rs/{}", name),
+            Ecosystem::Node => format!("https://www.npmjs.com/package/{}", name),
+            Ecosystem::Python => format!(
+                "https://{}.readthedocs.io/",
+                name.to_lowercase().replace(['_', '.'], "-")
+            ),
+        };
+
+        Self {
+            name: name.to_string(),
+            ecosystem,
+            docs_url,
+            documentation: None,
+        }
+    }
+
+    /// Registry page of the dependency, e.g. crates.io/crates/serde
+    fn registry_url(&self) -> String {
+        match self.ecosystem {
+            Ecosystem::Rust => format!("https://crates.io/crates/{}", self.name),
+            Ecosystem::Node => format!("https://www.npmjs.com/package/{}", self.name),
+            Ecosystem::Python => format!("https://pypi.org/project/{}", self.name),
+        }
+    }
+
+    /// Check whether any of the indexed source URLs documents this dependency
+    ///
+    /// A source counts when it lies under the canonical documentation URL, the
+    /// registry page or the documentation URL the package declares.
+    pub fn is_documented_by<'a>(&self, sources: impl IntoIterator<Item = &'a str>) -> bool {
+        let registry_url = self.registry_url();
+        let bases: Vec<&str> = [Some(self.docs_url.as_str()), Some(registry_url.as_str())]
+            .into_iter()
+            .chain([self.documentation.as_deref()])
+            .flatten()
+            .collect();
+
+        sources
+            .into_iter()
+            .any(|source| bases.iter().any(|base| url_is_under(source, base)))
+    }
+}
+
+/// Whether `url` is `base` or a page below it, ignoring a leading "www."
+fn url_is_under(url: &str, base: &str) -> bool {
+    let (Ok(url), Ok(base)) = (url::Url::parse(url), url::Url::parse(base)) else {
+        return false;
+    };
+    let host = |u: &url::Url| {
+        u.host_str()
+            .map(|h| h.trim_start_matches("www.").to_lowercase())
+    };
+    if host(&url).is_none() || host(&url) != host(&base) {
+        return false;
+    }
+
+    let segments = |u: &url::Url| -> Vec<String> {
+        u.path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    segments(&url).starts_with(&segments(&base))
+}
+
+fn read_manifest(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|e| CodeRagError::io(format!("Failed to read {:?}", path), e))
+}
+
+fn manifest_error(path: &Path, error: impl std::fmt::Display) -> CodeRagError {
+    CodeRagError::Manifest {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    }
+}
+
+/// Homepage from an installed package's package.json under node_modules
+fn installed_homepage(project_root: &Path, name: &str) -> Option<String> {
+    let manifest = project_root
+        .join("node_modules")
+        .join(name)
+        .join("package.json");
+    let content = fs::read_to_string(manifest).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+    manifest
+        .get("homepage")?
+        .as_str()
+        .filter(|url| url.starts_with("http"))
+        .map(str::to_string)
+}
+
+/// A workspace member with a `.coderag` database of its own
+#[derive(Debug, Clone)]
+pub struct NestedDatabase {
+    /// Name of the member
+    pub name: String,
+    pub root: PathBuf,
+    pub database_path: PathBuf,
+}
+
+/// Nested databases of a project, by the directory each one covers
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseRoutes {
+    project_root: PathBuf,
+    nested: Vec<NestedDatabase>,
+}
+
+impl DatabaseRoutes {
+    pub fn nested(&self) -> &[NestedDatabase] {
+        &self.nested
+    }
+
+    /// The nested database covering a file or directory, the innermost if several do
+    ///
+    /// Relative paths are taken from the project root.
+    pub fn route(&self, working_path: &Path) -> Option<&NestedDatabase> {
+        let path = self.project_root.join(working_path);
+        let path = path.canonicalize().unwrap_or(path);
+        self.nested
+            .iter()
+            .filter(|nested| path.starts_with(&nested.root))
+            .max_by_key(|nested| nested.root.components().count())
+    }
+}
+
+/// A project root CodeRAG has opened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownProject {
+    pub name: Option<String>,
+    /// RFC 3339 time the project was last opened
+    pub last_opened: String,
+}
+
+/// Project roots seen before, kept in `projects.json` in the global data directory
+///
+/// list_projects reports their databases and switch_project moves a running
+/// server to one of them.
+#[derive(Debug, Clone)]
+pub struct ProjectRegistry {
+    path: PathBuf,
+    projects: BTreeMap<PathBuf, KnownProject>,
+}
+
+impl ProjectRegistry {
+    /// Registry of the given data directory; a missing or unreadable file starts an empty one
+    pub fn load(global_data_dir: &Path) -> Self {
+        let path = DataDir::at(global_data_dir).projects_path();
+        let projects = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable project registry {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self { path, projects }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.projects)?).map_err(|e| {
+            CodeRagError::io(
+                format!("Failed to write project registry {:?}", self.path),
+                e,
+            )
+        })
+    }
+
+    pub fn projects(&self) -> &BTreeMap<PathBuf, KnownProject> {
+        &self.projects
+    }
+
+    /// Note that a project was opened; the global database isn't recorded
+    pub fn record(&mut self, info: &ProjectInfo) {
+        if let Some(root) = &info.project_root {
+            self.projects.insert(
+                root.clone(),
+                KnownProject {
+                    name: info.project_name.clone(),
+                    last_opened: Utc::now().to_rfc3339(),
+                },
+            );
+        }
+    }
+
+    /// Record a project and save, logging rather than failing if the file can't be written
+    pub fn remember(global_data_dir: &Path, info: &ProjectInfo) {
+        if !info.is_project {
+            return;
+        }
+        let mut registry = Self::load(global_data_dir);
+        registry.record(info);
+        if let Err(e) = registry.save() {
+            tracing::warn!("Failed to save project registry: {}", e);
+        }
+    }
+
+    /// Root of a known project, given its root path or name
+    pub fn find(&self, project: &str) -> Option<PathBuf> {
+        let path = Path::new(project);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if self.projects.contains_key(&path) {
+            return Some(path);
+        }
+        self.projects
+            .iter()
+            .find(|(_, known)| known.name.as_deref() == Some(project))
+            .map(|(root, _)| root.clone())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -142,6 +724,8 @@ pub struct ProjectInfo {
     pub project_root: Option<PathBuf>,
     pub database_path: PathBuf,
     pub project_name: Option<String>,
+    /// Workspace members usable as search scopes (empty for single-package projects)
+    pub members: Vec<WorkspaceMember>,
 }
 
 #[cfg(test)]
@@ -190,4 +774,217 @@ mod tests {
         assert!(gitignore_content.contains("node_modules/"));
         assert!(gitignore_content.contains(".coderag/"));
     }
+
+    #[test]
+    fn test_workspace_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\n",
+        )
+        .unwrap();
+        for (dir, name) in [
+            ("crates/core", "demo-core"),
+            ("crates/web", "demo-web"),
+            ("tools/cli", "demo-cli"),
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(
+                root.join(dir).join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\n", name),
+            )
+            .unwrap();
+        }
+
+        // A member resolves to the workspace root
+        let detected = ProjectManager::find_project_root(&root.join("crates/web")).unwrap();
+        assert_eq!(detected, root);
+
+        // A crate the workspace doesn't list keeps its own root
+        let vendored = root.join("vendor/patched");
+        fs::create_dir_all(&vendored).unwrap();
+        fs::write(
+            vendored.join("Cargo.toml"),
+            "[package]\nname = \"patched\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ProjectManager::find_project_root(&vendored).unwrap(),
+            vendored
+        );
+
+        // A nested workspace is the root for its own members
+        let nested = root.join("tools/cli");
+        fs::write(
+            nested.join("Cargo.toml"),
+            "[package]\nname = \"demo-cli\"\n\n[workspace]\nmembers = [\"plugins/*\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(nested.join("plugins/fmt")).unwrap();
+        fs::write(
+            nested.join("plugins/fmt/Cargo.toml"),
+            "[package]\nname = \"fmt\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ProjectManager::find_project_root(&nested.join("plugins/fmt")).unwrap(),
+            nested
+        );
+
+        let members = ProjectManager::workspace_members(root);
+        let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["demo-core", "demo-web", "demo-cli"]);
+        assert!(members.iter().all(|m| m.ecosystem == Ecosystem::Rust));
+    }
+
+    #[test]
+    fn test_polyglot_members() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("backend")).unwrap();
+        fs::create_dir_all(root.join("ml")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(
+            root.join("backend/Cargo.toml"),
+            "[package]\nname = \"api\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("ml/pyproject.toml"),
+            "[project]\nname = \"trainer\"\n",
+        )
+        .unwrap();
+
+        let members = ProjectManager::workspace_members(root);
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name, "api");
+        assert_eq!(members[0].ecosystem, Ecosystem::Rust);
+        assert_eq!(members[1].name, "trainer");
+        assert_eq!(members[1].ecosystem, Ecosystem::Python);
+    }
+
+    #[test]
+    fn test_detect_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1\"\ntokio = { version = \"1\" }\n\n[dev-dependencies]\nserde = \"1\"\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("package.json"),
+            r#"{"dependencies": {"react": "^18"}, "devDependencies": {"jest": "^29"}}"#,
+        )
+        .unwrap();
+        let react_dir = temp_dir.path().join("node_modules/react");
+        fs::create_dir_all(&react_dir).unwrap();
+        fs::write(
+            react_dir.join("package.json"),
+            r#"{"name": "react", "homepage": "https://react.dev/"}"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[project]\ndependencies = [\"requests>=2.31\", \"Flask_Login\"]\n",
+        )
+        .unwrap();
+
+        let deps = ProjectManager::detect_dependencies(temp_dir.path()).unwrap();
+        let names: Vec<&str> = deps.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["serde", "tokio", "react", "jest", "requests", "Flask_Login"]
+        );
+
+        let serde = &deps[0];
+        assert_eq!(serde.docs_url, "https://docs.rs/serde");
+        assert!(serde.is_documented_by(["https://docs.rs/serde/latest/serde/"]));
+        assert!(serde.is_documented_by(["https://crates.io/crates/serde"]));
+        assert!(!serde.is_documented_by(["https://docs.rs/serde_json/latest/"]));
+        assert!(!serde.is_documented_by(["https://serde.rs/derive.html"]));
+
+        // Only the installed package declares a homepage
+        let react = &deps[2];
+        assert_eq!(react.documentation.as_deref(), Some("https://react.dev/"));
+        assert!(react.is_documented_by(["https://react.dev/reference/react/useState"]));
+        assert!(!react.is_documented_by(["https://react-spring.dev/"]));
+        assert_eq!(deps[3].documentation, None);
+
+        // A short name is not matched against host labels
+        let log = ProjectDependency::new("log", Ecosystem::Rust);
+        assert!(!log.is_documented_by(["https://log.example.com/docs"]));
+        assert!(log.is_documented_by(["https://docs.rs/log/0.4/log/"]));
+
+        assert_eq!(deps[5].docs_url, "https://flask-login.readthedocs.io/");
+    }
+
+    #[test]
+    fn test_database_routes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"workspaces": ["packages/*"]}"#,
+        )
+        .unwrap();
+        for name in ["foo", "bar"] {
+            let dir = root.join("packages").join(name);
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(
+                dir.join("package.json"),
+                format!(r#"{{"name": "{}"}}"#, name),
+            )
+            .unwrap();
+        }
+        // Only foo has a database of its own
+        fs::create_dir_all(root.join("packages/foo/.coderag")).unwrap();
+        fs::write(root.join("packages/foo/.coderag/vectordb.json"), "{}").unwrap();
+
+        let manager = ProjectManager::new(root.join("data"));
+        let routes = manager.database_routes(&root);
+        assert_eq!(routes.nested().len(), 1);
+
+        let foo = routes
+            .route(Path::new("packages/foo/src/index.ts"))
+            .unwrap();
+        assert_eq!(foo.name, "foo");
+        assert_eq!(
+            routes
+                .route(&root.join("packages/foo"))
+                .unwrap()
+                .database_path,
+            root.join("packages/foo/.coderag/vectordb.json")
+        );
+        assert!(routes.route(Path::new("packages/bar/src")).is_none());
+        assert!(routes.route(Path::new("README.md")).is_none());
+    }
+
+    #[test]
+    fn test_project_registry() {
+        let data_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let root = project.path().canonicalize().unwrap();
+        let manager = ProjectManager::new(data_dir.path().to_path_buf());
+
+        let info = manager.project_info_at(&root);
+        ProjectRegistry::remember(data_dir.path(), &info);
+        // The global database isn't a project
+        let global = ProjectInfo {
+            is_project: false,
+            project_root: None,
+            database_path: manager.global_database_path(),
+            project_name: None,
+            members: Vec::new(),
+        };
+        ProjectRegistry::remember(data_dir.path(), &global);
+
+        let registry = ProjectRegistry::load(data_dir.path());
+        assert_eq!(registry.projects().len(), 1);
+        assert_eq!(registry.find(&root.to_string_lossy()), Some(root.clone()));
+        let name = info.project_name.unwrap();
+        assert_eq!(registry.find(&name), Some(root));
+        assert!(registry.find("unknown-project").is_none());
+    }
 }
@@ -0,0 +1,40 @@
+//! Graceful shutdown on SIGINT and SIGTERM.
+//!
+//! A signal cancels the process-wide [`token`]. The MCP and REST services stop
+//! taking requests, crawls stop at the next page boundary after indexing the
+//! pages already fetched, and the server saves what is still unsaved before
+//! the process exits.
+
+use std::sync::OnceLock;
+use tokio_util::sync::CancellationToken;
+
+static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+/// Token cancelled when the process is asked to shut down
+pub fn token() -> &'static CancellationToken {
+    TOKEN.get_or_init(CancellationToken::new)
+}
+
+/// Whether the process is shutting down
+pub fn requested() -> bool {
+    token().is_cancelled()
+}
+
+/// Wait for SIGINT or SIGTERM (Ctrl-C elsewhere), returning the signal's name
+pub async fn signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = interrupt.recv() => Ok("SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok("Ctrl-C")
+    }
+}
@@ -0,0 +1,451 @@
+//! Crawl history of each documentation source.
+//!
+//! `sources.json` next to the database records, per crawled URL, when it was
+//! first and last crawled, the settings it was crawled with, and how many
+//! crawls failed. list_docs shows it, refresh reuses the recorded settings,
+//! and expire reports sources that haven't been crawled for a while. A
+//! source can also carry a priority that weights its documents in search,
+//! and counts the searches that returned its documents and when the last one
+//! did, so users can see what gets used and unused sources can be evicted.
+
+use crate::data_dir::DataDir;
+use crate::error::{CodeRagError, Result};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// File name of the source registry, stored beside the database file
+const SOURCES_FILE: &str = "sources.json";
+
+/// Settings a source was crawled with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CrawlSettings {
+    pub mode: String,
+    pub focus: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+    /// Patterns links had to match to be followed, replacing the built-in ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_patterns: Vec<String>,
+    /// Patterns of links not followed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_patterns: Vec<String>,
+}
+
+/// Crawl history of one source URL
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SourceRecord {
+    /// RFC 3339 time of the first successful crawl
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_crawled: Option<String>,
+    /// RFC 3339 time of the latest successful crawl
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_crawled: Option<String>,
+    /// Pages fetched by the latest successful crawl
+    pub pages_crawled: usize,
+    /// Settings of the latest crawl attempt
+    pub settings: CrawlSettings,
+    pub crawl_count: usize,
+    pub failure_count: usize,
+    /// Error of the latest crawl, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Score multiplier for the source's documents, e.g. 0.5 for a mirror (None = 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f32>,
+    /// RFC 3339 time a search last returned one of the source's documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed: Option<String>,
+    /// Searches that returned at least one of the source's documents
+    #[serde(default)]
+    pub search_hits: u64,
+    /// RFC 3339 time searches of the source started being counted: when it was
+    /// first recorded, or when a registry from before search counting was loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracked_since: Option<String>,
+}
+
+impl SourceRecord {
+    fn new(settings: CrawlSettings) -> Self {
+        Self {
+            first_crawled: None,
+            last_crawled: None,
+            pages_crawled: 0,
+            settings,
+            crawl_count: 0,
+            failure_count: 0,
+            last_error: None,
+            priority: None,
+            last_accessed: None,
+            search_hits: 0,
+            tracked_since: Some(Utc::now().to_rfc3339()),
+        }
+    }
+
+    /// Whether the source hasn't been crawled successfully since `cutoff`
+    pub fn is_stale(&self, cutoff: DateTime<Utc>) -> bool {
+        match self
+            .last_crawled
+            .as_deref()
+            .map(DateTime::parse_from_rfc3339)
+        {
+            Some(Ok(last_crawled)) => last_crawled < cutoff,
+            _ => true,
+        }
+    }
+
+    /// When a search last returned the source
+    ///
+    /// A source no search has returned yet counts as used when it was last
+    /// crawled or when search counting started for it, whichever is later, so
+    /// sources searched before counting existed aren't mistaken for unused ones.
+    pub fn last_used(&self) -> Option<DateTime<FixedOffset>> {
+        let parse = |time: &Option<String>| {
+            time.as_deref()
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        };
+        parse(&self.last_accessed)
+            .or_else(|| parse(&self.last_crawled).max(parse(&self.tracked_since)))
+    }
+}
+
+/// Crawl history of all sources in one database
+#[derive(Debug, Clone)]
+pub struct SourceRegistry {
+    path: PathBuf,
+    sources: BTreeMap<String, SourceRecord>,
+    /// Search counts changed since [`flush_access`](Self::flush_access) last saved them
+    unsaved_access: bool,
+}
+
+impl SourceRegistry {
+    /// Registry kept alongside the given database file
+    ///
+    /// A missing or unreadable file starts an empty registry.
+    pub fn for_database(db_path: &Path) -> Self {
+        let path = DataDir::containing(db_path).map_or_else(
+            || db_path.with_file_name(SOURCES_FILE),
+            |data_dir| data_dir.sources_path(),
+        );
+        let mut sources: BTreeMap<String, SourceRecord> = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable source registry {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        // Searches of sources recorded before counting existed start counting now
+        let now = Utc::now().to_rfc3339();
+        for record in sources.values_mut() {
+            record.tracked_since.get_or_insert_with(|| now.clone());
+        }
+        Self {
+            path,
+            sources,
+            unsaved_access: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.sources)?).map_err(|e| {
+            CodeRagError::io(
+                format!("Failed to write source registry {:?}", self.path),
+                e,
+            )
+        })
+    }
+
+    pub fn get(&self, url: &str) -> Option<&SourceRecord> {
+        self.sources.get(url)
+    }
+
+    pub fn sources(&self) -> &BTreeMap<String, SourceRecord> {
+        &self.sources
+    }
+
+    /// Record a successful crawl
+    pub fn record_crawl(&mut self, url: &str, settings: CrawlSettings, pages_crawled: usize) {
+        let now = Utc::now().to_rfc3339();
+        let record = self
+            .sources
+            .entry(url.to_string())
+            .or_insert_with(|| SourceRecord::new(settings.clone()));
+        record.first_crawled.get_or_insert_with(|| now.clone());
+        record.last_crawled = Some(now);
+        record.pages_crawled = pages_crawled;
+        record.settings = settings;
+        record.crawl_count += 1;
+        record.last_error = None;
+    }
+
+    /// Record a failed crawl, keeping the time of the last successful one
+    pub fn record_failure(&mut self, url: &str, settings: CrawlSettings, error: &str) {
+        let record = self
+            .sources
+            .entry(url.to_string())
+            .or_insert_with(|| SourceRecord::new(settings.clone()));
+        record.settings = settings;
+        record.failure_count += 1;
+        record.last_error = Some(error.to_string());
+    }
+
+    /// Set the ranking weight of a recorded source; `None` resets it to 1.0
+    ///
+    /// Returns the previous weight.
+    pub fn set_priority(&mut self, url: &str, priority: Option<f32>) -> Result<f32> {
+        if let Some(priority) = priority {
+            if !(priority.is_finite() && priority > 0.0) {
+                return Err(CodeRagError::InvalidInput(format!(
+                    "Priority must be a positive number, got {}",
+                    priority
+                )));
+            }
+        }
+        let record = self
+            .sources
+            .get_mut(url)
+            .ok_or_else(|| CodeRagError::NotFound(format!("{} is not a crawled source", url)))?;
+        let previous = record.priority.unwrap_or(1.0);
+        record.priority = priority;
+        Ok(previous)
+    }
+
+    /// URL prefixes and weights of the sources with a priority set
+    pub fn priorities(&self) -> Vec<(String, f32)> {
+        self.sources
+            .iter()
+            .filter_map(|(url, record)| Some((url.clone(), record.priority?)))
+            .collect()
+    }
+
+    /// Note that a search returned documents from these URLs
+    ///
+    /// Each URL counts for the recorded source it falls under, and each source
+    /// counts once per search. The counts are only kept in memory until the
+    /// next save or [`flush_access`](Self::flush_access), so searches don't
+    /// rewrite the registry. Returns whether any source was hit.
+    pub fn record_access<'a>(&mut self, urls: impl IntoIterator<Item = &'a str>) -> bool {
+        let hit: BTreeSet<String> = urls
+            .into_iter()
+            .filter_map(|url| {
+                self.sources
+                    .keys()
+                    .filter(|source| url.starts_with(source.as_str()))
+                    .max_by_key(|source| source.len())
+                    .cloned()
+            })
+            .collect();
+        let now = Utc::now().to_rfc3339();
+        for source in &hit {
+            if let Some(record) = self.sources.get_mut(source) {
+                record.search_hits += 1;
+                record.last_accessed = Some(now.clone());
+            }
+        }
+        self.unsaved_access |= !hit.is_empty();
+        !hit.is_empty()
+    }
+
+    /// Save the search counts recorded since the last flush, if there are any
+    pub fn flush_access(&mut self) -> Result<()> {
+        if self.unsaved_access {
+            self.save()?;
+            self.unsaved_access = false;
+        }
+        Ok(())
+    }
+
+    /// Sources ordered from least to most recently used, as [`SourceRecord::last_used`] tells
+    pub fn least_recently_used(&self) -> Vec<String> {
+        let mut sources: Vec<(&String, Option<DateTime<FixedOffset>>)> = self
+            .sources
+            .iter()
+            .map(|(url, record)| (url, record.last_used()))
+            .collect();
+        sources.sort_by_key(|(_, used)| *used);
+        sources.into_iter().map(|(url, _)| url.clone()).collect()
+    }
+
+    /// Sources no search has returned in `max_age_days`
+    ///
+    /// A source no search has returned yet counts from its last crawl, or from
+    /// when search counting started, so new sources get the same grace period.
+    pub fn unused_sources(&self, max_age_days: u64) -> Vec<String> {
+        let cutoff = Utc::now() - Duration::days(max_age_days as i64);
+        self.sources
+            .iter()
+            .filter(|(_, record)| record.last_used().is_none_or(|used| used < cutoff))
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    /// Sources last crawled more than `max_age_days` ago, or never successfully
+    pub fn stale_sources(&self, max_age_days: u64) -> Vec<String> {
+        let cutoff = Utc::now() - Duration::days(max_age_days as i64);
+        self.sources
+            .iter()
+            .filter(|(_, record)| record.is_stale(cutoff))
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    /// Drop the records of sources `keep` rejects, returning how many were dropped
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) -> usize {
+        let before = self.sources.len();
+        self.sources.retain(|url, _| keep(url));
+        before - self.sources.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: &str) -> CrawlSettings {
+        CrawlSettings {
+            mode: mode.to_string(),
+            focus: "all".to_string(),
+            max_pages: None,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_source_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("coderag.db");
+        let url = "https://docs.rs/tokio/latest/";
+
+        let mut registry = SourceRegistry::for_database(&db_path);
+        registry.record_failure(url, settings("single"), "connection refused");
+        assert_eq!(registry.stale_sources(30), [url]);
+
+        registry.record_crawl(url, settings("section"), 12);
+        registry.record_failure(url, settings("section"), "timed out");
+        registry.save().unwrap();
+
+        let registry = SourceRegistry::for_database(&db_path);
+        let record = registry.get(url).unwrap();
+        assert_eq!(record.settings, settings("section"));
+        assert_eq!(record.pages_crawled, 12);
+        assert_eq!((record.crawl_count, record.failure_count), (1, 2));
+        assert_eq!(record.last_error.as_deref(), Some("timed out"));
+        assert!(record.first_crawled.is_some());
+        assert!(registry.stale_sources(30).is_empty());
+    }
+
+    #[test]
+    fn test_source_priority() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = SourceRegistry::for_database(&dir.path().join("coderag.db"));
+        let url = "https://mirror.example.com/tokio/";
+        registry.record_crawl(url, settings("section"), 3);
+
+        assert_eq!(registry.set_priority(url, Some(0.5)).unwrap(), 1.0);
+        assert_eq!(registry.priorities(), [(url.to_string(), 0.5)]);
+        assert!(registry.set_priority(url, Some(0.0)).is_err());
+        assert!(registry
+            .set_priority("https://unknown.example.com/", None)
+            .is_err());
+
+        assert_eq!(registry.set_priority(url, None).unwrap(), 0.5);
+        assert!(registry.priorities().is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = SourceRegistry::for_database(&dir.path().join("coderag.db"));
+        let (tokio, serde, axum) = (
+            "https://docs.rs/tokio/",
+            "https://serde.rs/",
+            "https://docs.rs/axum/",
+        );
+        for url in [tokio, serde, axum] {
+            registry.record_crawl(url, settings("section"), 5);
+        }
+
+        // Several hits on one source count as one search
+        assert!(registry.record_access([
+            "https://docs.rs/tokio/latest/tokio/fn.spawn.html",
+            "https://docs.rs/tokio/latest/tokio/task/index.html",
+        ]));
+        assert!(registry.record_access(["https://docs.rs/tokio/"]));
+        assert!(!registry.record_access(["https://unknown.example.com/"]));
+        let record = registry.get(tokio).unwrap();
+        assert!(record.last_accessed.is_some());
+        assert_eq!(record.search_hits, 2);
+        assert_eq!(registry.get(axum).unwrap().search_hits, 0);
+
+        let order = registry.least_recently_used();
+        assert_eq!(order.last().map(String::as_str), Some(tokio));
+        assert_eq!(order.len(), 3);
+
+        assert!(registry.unused_sources(30).is_empty());
+        let record = registry.sources.get_mut(serde).unwrap();
+        record.last_crawled = Some("2000-01-01T00:00:00+00:00".to_string());
+        record.tracked_since = record.last_crawled.clone();
+        assert_eq!(registry.unused_sources(30), [serde]);
+        assert_eq!(registry.least_recently_used()[0], serde);
+    }
+
+    #[test]
+    fn test_access_counts_are_flushed_not_saved_per_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("coderag.db");
+        let url = "https://docs.rs/tokio/";
+        let mut registry = SourceRegistry::for_database(&db_path);
+        registry.record_crawl(url, settings("section"), 5);
+        registry.save().unwrap();
+
+        assert!(registry.record_access(["https://docs.rs/tokio/latest/tokio/"]));
+        let on_disk = || {
+            SourceRegistry::for_database(&db_path)
+                .get(url)
+                .unwrap()
+                .search_hits
+        };
+        assert_eq!(on_disk(), 0);
+
+        registry.flush_access().unwrap();
+        assert_eq!(on_disk(), 1);
+    }
+
+    #[test]
+    fn test_sources_from_before_search_counting_are_not_unused() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("coderag.db");
+        let url = "https://docs.rs/serde/";
+        fs::write(
+            dir.path().join(SOURCES_FILE),
+            serde_json::json!({
+                url: {
+                    "first_crawled": "2000-01-01T00:00:00+00:00",
+                    "last_crawled": "2000-01-01T00:00:00+00:00",
+                    "pages_crawled": 5,
+                    "settings": settings("section"),
+                    "crawl_count": 1,
+                    "failure_count": 0
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let registry = SourceRegistry::for_database(&db_path);
+        assert!(registry.get(url).unwrap().tracked_since.is_some());
+        assert!(registry.unused_sources(30).is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+//! Metrics for crawls, embeddings and searches.
+//!
+//! Counters and histograms are recorded through the `metrics` facade, which
+//! discards them unless a recorder is installed. With the rest-api feature and
+//! `telemetry.metrics = true`, the REST server installs a Prometheus recorder
+//! and serves the metrics at `/metrics`. Timings are also attached to the
+//! `crawl_page`, `embed_batch` and `search` tracing spans.
+
+use std::time::{Duration, Instant};
+
+pub const PAGES_CRAWLED: &str = "pages_crawled_total";
+pub const PAGES_FAILED: &str = "pages_failed_total";
+pub const CHUNKS_INDEXED: &str = "chunks_indexed_total";
+pub const PAGE_CRAWL_SECONDS: &str = "page_crawl_seconds";
+pub const EMBEDDINGS: &str = "embeddings_total";
+pub const EMBEDDING_BATCH_SECONDS: &str = "embedding_batch_seconds";
+pub const SEARCHES: &str = "searches_total";
+pub const SEARCH_LATENCY_SECONDS: &str = "search_latency_seconds";
+
+/// Record the time since `started` as the current span's `elapsed_ms` field
+pub fn record_elapsed(started: Instant) -> Duration {
+    let elapsed = started.elapsed();
+    tracing::Span::current().record("elapsed_ms", elapsed.as_millis() as u64);
+    elapsed
+}
+
+/// Record a crawled page and the chunks indexed from it
+pub fn record_page_crawled(chunks: usize, elapsed: Duration) {
+    metrics::counter!(PAGES_CRAWLED).increment(1);
+    metrics::counter!(CHUNKS_INDEXED).increment(chunks as u64);
+    metrics::histogram!(PAGE_CRAWL_SECONDS).record(elapsed.as_secs_f64());
+}
+
+/// Record a page that could not be crawled
+pub fn record_page_failed() {
+    metrics::counter!(PAGES_FAILED).increment(1);
+}
+
+/// Record a batch of texts embedded
+pub fn record_embedding_batch(texts: usize, elapsed: Duration) {
+    metrics::counter!(EMBEDDINGS).increment(texts as u64);
+    metrics::histogram!(EMBEDDING_BATCH_SECONDS).record(elapsed.as_secs_f64());
+}
+
+/// Record a search served by a tool
+pub fn record_search(tool: &str, elapsed: Duration) {
+    metrics::counter!(SEARCHES, "tool" => tool.to_string()).increment(1);
+    metrics::histogram!(SEARCH_LATENCY_SECONDS, "tool" => tool.to_string())
+        .record(elapsed.as_secs_f64());
+}
+
+/// Install the process-wide Prometheus recorder
+///
+/// Latencies are exported as histograms with buckets from 5 ms to 2 minutes.
+#[cfg(feature = "rest-api")]
+pub fn install_prometheus() -> anyhow::Result<metrics_exporter_prometheus::PrometheusHandle> {
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    const LATENCY_BUCKETS: &[f64] = &[
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 120.0,
+    ];
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)?
+        .install_recorder()?;
+    Ok(handle)
+}
@@ -0,0 +1,192 @@
+//! Bulk import of URL lists.
+//!
+//! `coderag import` and the import_urls tool index every URL of a list as a
+//! single page, one after the other with the crawler's delay between them.
+//! A list can be plain text with one URL per line (`#` starts a comment) or
+//! anything else URLs can be picked out of, such as exported browser
+//! bookmarks. Each URL's outcome is checkpointed in the data directory's
+//! `cache/imports/` (`imports/` beside a project database), keyed by the
+//! list, so importing the same list again
+//! resumes where an interrupted import stopped, retrying only the URLs that
+//! failed.
+
+use crate::data_dir::DataDir;
+use crate::error::{CodeRagError, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// What importing one URL did
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ImportResult {
+    pub url: String,
+    /// "indexed", "skipped" (nothing indexable) or "failed"
+    pub status: String,
+    pub documents_created: usize,
+    /// Why the page was skipped or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ImportResult {
+    pub fn failed(url: &str, reason: impl Into<String>) -> Self {
+        Self {
+            url: url.to_string(),
+            status: "failed".to_string(),
+            documents_created: 0,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// The http(s) URLs in a URL list or bookmarks file, in order and without duplicates
+pub fn parse_url_list(text: &str) -> Vec<String> {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    let pattern = URL.get_or_init(|| Regex::new(r#"https?://[^\s"'<>]+"#).unwrap());
+
+    let mut urls: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        for found in pattern.find_iter(line) {
+            let Ok(mut url) = url::Url::parse(&html_unescape(found.as_str())) else {
+                continue;
+            };
+            url.set_fragment(None);
+            let url = String::from(url);
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// Undo the entity escaping of URLs in HTML attributes, as in bookmark exports
+fn html_unescape(url: &str) -> String {
+    url.replace("&amp;", "&")
+}
+
+/// Outcomes recorded so far by an import of one URL list
+#[derive(Debug)]
+pub struct ImportCheckpoint {
+    path: PathBuf,
+    results: BTreeMap<String, ImportResult>,
+}
+
+impl ImportCheckpoint {
+    /// The checkpoint of an import of `urls` into a database, if one was started
+    pub fn for_database(db_path: &Path, urls: &[String]) -> Self {
+        let hash = Sha256::digest(urls.join("\n").as_bytes());
+        let name = format!("{}.json", &format!("{:x}", hash)[..16]);
+        let path = DataDir::containing(db_path).map_or_else(
+            || db_path.with_file_name("imports").join(&name),
+            |data_dir| data_dir.imports_dir().join(&name),
+        );
+        let results = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable import checkpoint {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self { path, results }
+    }
+
+    /// Whether an earlier run indexed or skipped the URL; failed ones are tried again
+    pub fn is_done(&self, url: &str) -> bool {
+        self.results
+            .get(url)
+            .is_some_and(|result| result.status != "failed")
+    }
+
+    /// Record a URL's outcome and save the checkpoint
+    pub fn record(&mut self, result: ImportResult) -> Result<()> {
+        self.results.insert(result.url.clone(), result);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CodeRagError::io(format!("Failed to create {:?}", parent), e))?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.results)?).map_err(|e| {
+            CodeRagError::io(
+                format!("Failed to write import checkpoint {:?}", self.path),
+                e,
+            )
+        })
+    }
+
+    /// Forget the recorded outcomes, so the whole list is imported again
+    pub fn clear(&mut self) -> Result<()> {
+        self.results.clear();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(CodeRagError::io(
+                format!("Failed to remove import checkpoint {:?}", self.path),
+                e,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_url_list() {
+        let list = "# curated\nhttps://docs.rs/tokio/latest/tokio/\n\n  https://serde.rs/derive.html#top  \nnot a url\nhttps://docs.rs/tokio/latest/tokio/\n";
+        assert_eq!(
+            parse_url_list(list),
+            vec![
+                "https://docs.rs/tokio/latest/tokio/",
+                "https://serde.rs/derive.html"
+            ]
+        );
+
+        let bookmarks = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><A HREF="https://tokio.rs/tokio/tutorial?lang=en&amp;v=1" ADD_DATE="1700000000">Tutorial</A>
+    <DT><A HREF="ftp://files.example.com/">Files</A>
+</DL><p>"#;
+        assert_eq!(
+            parse_url_list(bookmarks),
+            vec!["https://tokio.rs/tokio/tutorial?lang=en&v=1"]
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_resumes() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("vectordb.json");
+        let urls = vec!["https://a.dev/".to_string(), "https://b.dev/".to_string()];
+        let mut checkpoint = ImportCheckpoint::for_database(&db_path, &urls);
+        checkpoint
+            .record(ImportResult {
+                url: "https://a.dev/".to_string(),
+                status: "indexed".to_string(),
+                documents_created: 4,
+                reason: None,
+            })
+            .unwrap();
+        checkpoint
+            .record(ImportResult::failed("https://b.dev/", "404"))
+            .unwrap();
+
+        let mut resumed = ImportCheckpoint::for_database(&db_path, &urls);
+        assert!(resumed.is_done("https://a.dev/"));
+        assert!(!resumed.is_done("https://b.dev/"));
+        // Another list has its own checkpoint
+        assert!(!ImportCheckpoint::for_database(&db_path, &urls[..1]).is_done("https://a.dev/"));
+
+        resumed.clear().unwrap();
+        assert!(!ImportCheckpoint::for_database(&db_path, &urls).is_done("https://a.dev/"));
+    }
+}
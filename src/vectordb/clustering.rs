@@ -0,0 +1,392 @@
+//! Topic discovery over stored vectors
+//!
+//! Spherical k-means groups documents by embedding direction. Each cluster
+//! becomes a topic labelled by the title and heading words that distinguish
+//! it from the rest of the knowledge base, with its closest documents per
+//! source as representatives, so small topics show where coverage is thin.
+
+use super::tokenizer::is_stopword;
+use super::types::Document;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use url::Url;
+
+/// Fixed seed so the same knowledge base always yields the same topics
+const KMEANS_SEED: u64 = 42;
+
+/// Iteration cap for k-means; assignments usually settle well before
+const MAX_ITERATIONS: usize = 50;
+
+/// Keywords reported per topic
+const TOPIC_KEYWORDS: usize = 3;
+
+/// A group of vectors around a shared centroid
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    /// Unit-length mean direction of the members
+    pub centroid: Vec<f32>,
+    /// Indices of the member vectors
+    pub members: Vec<usize>,
+}
+
+/// A discovered topic in the knowledge base
+#[derive(Debug, Clone)]
+pub struct Topic {
+    /// Words that distinguish this topic, most distinctive first
+    pub keywords: Vec<String>,
+    /// Number of documents in the topic
+    pub size: usize,
+    /// Document counts per source
+    pub sources: BTreeMap<String, usize>,
+    /// IDs of the documents closest to the topic centre, at most one per source,
+    /// with their similarity to it
+    pub representatives: Vec<(String, f32)>,
+}
+
+/// Default number of topics for a knowledge base of `count` documents
+pub fn default_topic_count(count: usize) -> usize {
+    ((count as f64 / 2.0).sqrt().round() as usize).clamp(2, 20)
+}
+
+/// Cluster vectors by cosine similarity into at most `k` clusters
+///
+/// Empty clusters are dropped, and clusters are returned largest first.
+pub fn kmeans(vectors: &[Vec<f32>], k: usize) -> Vec<Cluster> {
+    let k = k.min(vectors.len());
+    if k == 0 {
+        return Vec::new();
+    }
+    let vectors: Vec<Vec<f32>> = vectors.iter().map(|v| normalized(v)).collect();
+
+    let mut centroids = initial_centroids(&vectors, k);
+    let mut assignments = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (vector, assignment) in vectors.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_centroid(vector, &centroids);
+            if *assignment != nearest {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        // Clusters that lost all members keep their previous centroid
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let mut sum = vec![0.0; centroid.len()];
+            for (vector, _) in vectors
+                .iter()
+                .zip(&assignments)
+                .filter(|(_, a)| **a == cluster)
+            {
+                for (total, value) in sum.iter_mut().zip(vector) {
+                    *total += value;
+                }
+            }
+            if sum.iter().any(|v| *v != 0.0) {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = centroids
+        .into_iter()
+        .enumerate()
+        .map(|(cluster, centroid)| Cluster {
+            centroid,
+            members: (0..vectors.len())
+                .filter(|&i| assignments[i] == cluster)
+                .collect(),
+        })
+        .filter(|cluster| !cluster.members.is_empty())
+        .collect();
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.members.len()));
+    clusters
+}
+
+/// Rank vectors by similarity to their common centroid, most central first
+///
+/// Vectors more similar than `max_redundancy` to one already picked are
+/// skipped, so near-identical chunks don't crowd out the rest.
+pub fn central_members(
+    vectors: &[Vec<f32>],
+    count: usize,
+    max_redundancy: f32,
+) -> Vec<(usize, f32)> {
+    let vectors: Vec<Vec<f32>> = vectors.iter().map(|v| normalized(v)).collect();
+    let Some(dimension) = vectors.first().map(Vec::len) else {
+        return Vec::new();
+    };
+    let mut sum = vec![0.0; dimension];
+    for vector in &vectors {
+        for (total, value) in sum.iter_mut().zip(vector) {
+            *total += value;
+        }
+    }
+    let centroid = normalized(&sum);
+
+    let mut ranked: Vec<(usize, f32)> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, vector)| (i, dot(vector, &centroid)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut picked: Vec<(usize, f32)> = Vec::new();
+    for (i, centrality) in ranked {
+        if picked.len() == count {
+            break;
+        }
+        let redundant = picked
+            .iter()
+            .any(|(j, _)| dot(&vectors[i], &vectors[*j]) > max_redundancy);
+        if !redundant {
+            picked.push((i, centrality));
+        }
+    }
+    picked
+}
+
+/// Describe clusters of documents as topics
+///
+/// `documents[i]` is the document of vector `vectors[i]`.
+pub fn describe_topics(
+    clusters: &[Cluster],
+    documents: &[&Document],
+    vectors: &[Vec<f32>],
+    representatives_per_topic: usize,
+) -> Vec<Topic> {
+    let words: Vec<HashSet<String>> = documents.iter().map(|doc| label_words(doc)).collect();
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for word in words.iter().flatten() {
+        *document_frequency.entry(word).or_default() += 1;
+    }
+
+    clusters
+        .iter()
+        .map(|cluster| {
+            let size = cluster.members.len();
+
+            // Words common in the topic but rare elsewhere
+            let mut topic_frequency: HashMap<&str, usize> = HashMap::new();
+            for &member in &cluster.members {
+                for word in &words[member] {
+                    *topic_frequency.entry(word).or_default() += 1;
+                }
+            }
+            let mut scored: Vec<(&str, f32)> = topic_frequency
+                .into_iter()
+                .map(|(word, count)| {
+                    let idf = (documents.len() as f32 / document_frequency[word] as f32).ln();
+                    (word, count as f32 / size as f32 * idf)
+                })
+                .filter(|(_, score)| *score > 0.0)
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+            let keywords = scored
+                .into_iter()
+                .take(TOPIC_KEYWORDS)
+                .map(|(word, _)| word.to_string())
+                .collect();
+
+            let mut sources: BTreeMap<String, usize> = BTreeMap::new();
+            for &member in &cluster.members {
+                *sources
+                    .entry(source_root(&documents[member].url))
+                    .or_default() += 1;
+            }
+
+            // The closest document of each source, best first
+            let mut ranked: Vec<(usize, f32)> = cluster
+                .members
+                .iter()
+                .map(|&member| {
+                    let similarity = dot(&normalized(&vectors[member]), &cluster.centroid);
+                    (member, similarity)
+                })
+                .collect();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let mut seen_sources = HashSet::new();
+            let representatives = ranked
+                .into_iter()
+                .filter(|(member, _)| seen_sources.insert(source_root(&documents[*member].url)))
+                .take(representatives_per_topic)
+                .map(|(member, similarity)| (documents[member].id.clone(), similarity))
+                .collect();
+
+            Topic {
+                keywords,
+                size,
+                sources,
+                representatives,
+            }
+        })
+        .collect()
+}
+
+/// Documentation set a URL belongs to: its origin and first directory
+///
+/// `https://docs.rs/tokio/latest/tokio/` → `https://docs.rs/tokio`,
+/// `https://serde.rs/derive.html` → `https://serde.rs`
+pub fn source_root(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return if url.is_empty() {
+            "local".to_string()
+        } else {
+            url.to_string()
+        };
+    };
+    let origin = parsed.origin().ascii_serialization();
+    let segments: Vec<&str> = parsed.path_segments().map_or(Vec::new(), Iterator::collect);
+    match segments.as_slice() {
+        [directory, _, ..] if !directory.is_empty() => format!("{}/{}", origin, directory),
+        _ => origin,
+    }
+}
+
+/// Words from a document's title and headings used to label topics
+fn label_words(document: &Document) -> HashSet<String> {
+    let text = [document.title.as_deref(), document.section.as_deref()]
+        .into_iter()
+        .flatten()
+        .chain(document.metadata.heading_path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() >= 3 && !word.chars().all(|c| c.is_numeric()) && !is_stopword(word)
+        })
+        .collect()
+}
+
+/// k-means++ seeding: spread the initial centroids out
+fn initial_centroids(vectors: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(KMEANS_SEED);
+    let mut centroids = vec![vectors[rng.gen_range(0..vectors.len())].clone()];
+
+    while centroids.len() < k {
+        let distances: Vec<f32> = vectors
+            .iter()
+            .map(|v| {
+                let nearest = centroids.iter().map(|c| dot(v, c)).fold(f32::MIN, f32::max);
+                (1.0 - nearest).max(0.0).powi(2)
+            })
+            .collect();
+        let total: f32 = distances.iter().sum();
+        if total <= 0.0 {
+            // Fewer distinct vectors than clusters
+            break;
+        }
+
+        let mut target = rng.gen_range(0.0..total);
+        let mut chosen = distances.len() - 1;
+        for (i, distance) in distances.iter().enumerate() {
+            if target < *distance {
+                chosen = i;
+                break;
+            }
+            target -= distance;
+        }
+        centroids.push(vectors[chosen].clone());
+    }
+
+    centroids
+}
+
+fn nearest_centroid(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, centroid)| (i, dot(vector, centroid)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = dot(vector, vector).sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|v| v / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::{ContentType, DocumentMetadata};
+
+    fn document(id: &str, url: &str, title: &str) -> Document {
+        Document::for_test(id).with_url(url).with_title(title)
+    }
+
+    #[test]
+    fn test_topics() {
+        let vectors = vec![
+            vec![1.0, 0.1, 0.0],
+            vec![0.9, 0.0, 0.1],
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.1],
+            vec![0.1, 0.9, 0.0],
+        ];
+        let documents = [
+            document(
+                "a",
+                "https://docs.rs/tokio/latest/tokio/task/",
+                "Task spawning",
+            ),
+            document(
+                "b",
+                "https://docs.rs/tokio/latest/tokio/time/",
+                "Task timeouts",
+            ),
+            document(
+                "c",
+                "https://tokio.rs/tokio/tutorial/spawning",
+                "Spawning tasks",
+            ),
+            document("d", "https://serde.rs/derive.html", "Serde derive"),
+            document("e", "https://docs.rs/serde/latest/serde/", "Serde traits"),
+        ];
+        let documents: Vec<&Document> = documents.iter().collect();
+
+        let clusters = kmeans(&vectors, 2);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].members, [0, 1, 2]);
+        assert_eq!(clusters[1].members, [3, 4]);
+
+        let topics = describe_topics(&clusters, &documents, &vectors, 3);
+        assert_eq!(topics[0].keywords, ["spawning", "task", "tasks"]);
+        assert_eq!(topics[1].keywords, ["serde", "derive", "traits"]);
+        assert_eq!(topics[0].sources["https://docs.rs/tokio"], 2);
+        // One representative per source
+        assert_eq!(topics[0].representatives.len(), 2);
+        assert_eq!(topics[0].representatives[0].0, "c");
+
+        assert_eq!(
+            source_root("https://serde.rs/derive.html"),
+            "https://serde.rs"
+        );
+        assert_eq!(
+            source_root("https://docs.rs/tokio/"),
+            "https://docs.rs/tokio"
+        );
+        assert_eq!(source_root("https://tokio.rs/"), "https://tokio.rs");
+        assert_eq!(default_topic_count(5), 2);
+
+        // The middle vector is most central; its near-copy is skipped
+        let central = central_members(&vectors[..3], 2, 0.999);
+        assert_eq!(central[0].0, 2);
+        assert_eq!(central.len(), 2);
+        let central = central_members(&[vec![1.0, 0.0], vec![1.0, 0.0]], 2, 0.99);
+        assert_eq!(central.len(), 1);
+        assert_eq!(default_topic_count(200), 10);
+    }
+}
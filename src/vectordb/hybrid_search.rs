@@ -1,13 +1,18 @@
 // Hybrid search implementation combining vector similarity and keyword search
 
+use crate::error::Result;
 use crate::vectordb::storage::VectorStorage;
+use crate::vectordb::tokenizer::Tokenizer;
 use crate::vectordb::types::Document;
 use crate::vectordb::SearchOptions;
-use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::HashMap;
 use tracing::debug;
 
+/// Tag of documents that describe a single symbol, titled with its qualified name
+pub const SYMBOL_TAG: &str = "symbol";
+
 /// Options for hybrid search
 #[derive(Debug, Clone)]
 pub struct HybridSearchOptions {
@@ -21,6 +26,10 @@ pub struct HybridSearchOptions {
     pub keyword_weight: f32,
     /// Parameters for keyword search
     pub keyword_params: KeywordSearchParams,
+    /// How vector and keyword results are combined
+    pub fusion_strategy: FusionStrategy,
+    /// Rank offset `k` for reciprocal rank fusion
+    pub rrf_k: f32,
 }
 
 impl Default for HybridSearchOptions {
@@ -31,25 +40,109 @@ impl Default for HybridSearchOptions {
             vector_weight: 0.7,
             keyword_weight: 0.3,
             keyword_params: KeywordSearchParams::default(),
+            fusion_strategy: FusionStrategy::default(),
+            rrf_k: 60.0,
+        }
+    }
+}
+
+impl HybridSearchOptions {
+    /// Combined score of a document from its vector and keyword results
+    ///
+    /// Ranks are 0-based positions in each result list, `None` when the
+    /// document isn't in that list.
+    fn fuse(&self, vector: (Option<usize>, f32), keyword: (Option<usize>, f32)) -> f32 {
+        match self.fusion_strategy {
+            FusionStrategy::WeightedSum => {
+                self.vector_weight * vector.1 + self.keyword_weight * keyword.1
+            }
+            FusionStrategy::Rrf => [vector.0, keyword.0]
+                .into_iter()
+                .flatten()
+                .map(|rank| 1.0 / (self.rrf_k + rank as f32 + 1.0))
+                .sum(),
         }
     }
 }
 
+/// How hybrid search combines vector and keyword rankings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Weighted sum of the cosine score and the normalized BM25 score
+    #[default]
+    WeightedSum,
+    /// Reciprocal rank fusion: each list adds `1 / (k + rank)`
+    ///
+    /// Only ranks are used, so it is insensitive to the different scales of
+    /// cosine and BM25 scores. The vector and keyword weights are ignored.
+    Rrf,
+}
+
 /// Parameters for keyword search
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct KeywordSearchParams {
     /// K1 parameter for BM25 (controls term frequency saturation)
     pub k1: f32,
     /// B parameter for BM25 (controls length normalization)
     pub b: f32,
+    /// Term frequency weight of the document title relative to the body
+    pub title_weight: f32,
+    /// Term frequency weight of the section heading relative to the body
+    pub section_weight: f32,
 }
 
 impl Default for KeywordSearchParams {
     fn default() -> Self {
-        Self { k1: 1.2, b: 0.75 }
+        Self {
+            k1: 1.2,
+            b: 0.75,
+            title_weight: 3.0,
+            section_weight: 2.0,
+        }
     }
 }
 
+/// Searchable fields of a document
+#[derive(Debug, Clone, Copy)]
+pub struct DocumentFields<'a> {
+    pub title: Option<&'a str>,
+    pub section: Option<&'a str>,
+    pub body: &'a str,
+}
+
+impl<'a> From<&'a Document> for DocumentFields<'a> {
+    fn from(document: &'a Document) -> Self {
+        Self {
+            title: document.title.as_deref(),
+            section: document.section.as_deref(),
+            body: &document.content,
+        }
+    }
+}
+
+/// Whether a query contains a quoted phrase, e.g. `"tokio::spawn" example`
+pub fn has_quoted_phrase(query: &str) -> bool {
+    let segments: Vec<&str> = query.split('"').collect();
+    segments
+        .iter()
+        .enumerate()
+        .any(|(i, segment)| is_quoted_segment(i, segments.len()) && !segment.trim().is_empty())
+}
+
+/// Odd segments of a query split on `"` are quoted, unless the last quote is unclosed
+fn is_quoted_segment(index: usize, segment_count: usize) -> bool {
+    index % 2 == 1 && index + 1 < segment_count
+}
+
+/// A keyword query: terms to score and quoted phrases documents must contain
+#[derive(Debug, Clone, Default)]
+struct KeywordQuery {
+    terms: Vec<String>,
+    /// Phrase terms with their word offsets from the phrase start
+    phrases: Vec<Vec<(usize, String)>>,
+}
+
 /// Result from hybrid search including both scores
 #[derive(Debug, Clone)]
 pub struct HybridSearchResult {
@@ -87,19 +180,29 @@ impl Ord for HybridSearchResult {
 }
 
 /// BM25 index for keyword search
+///
+/// Field weights make this a BM25F variant: title and section terms count
+/// as several body occurrences. Token sequences are kept per field so quoted
+/// phrases can be matched.
 pub struct BM25Index {
     /// Document frequency for each term
     doc_freq: HashMap<String, usize>,
-    /// Term frequency for each document
-    term_freq: HashMap<String, HashMap<String, usize>>,
-    /// Document lengths
-    doc_lengths: HashMap<String, usize>,
+    /// Field-weighted term frequency for each document
+    term_freq: HashMap<String, HashMap<String, f32>>,
+    /// Field-weighted document lengths
+    doc_lengths: HashMap<String, f32>,
+    /// Sum of all document lengths
+    total_doc_length: f32,
+    /// Term positions within each field, for phrase matching
+    field_positions: HashMap<String, Vec<HashMap<String, Vec<usize>>>>,
     /// Average document length
     avg_doc_length: f32,
     /// Total number of documents
     doc_count: usize,
     /// BM25 parameters
     params: KeywordSearchParams,
+    /// Stemming, stopword and identifier-aware tokenizer
+    tokenizer: Tokenizer,
 }
 
 impl BM25Index {
@@ -109,64 +212,206 @@ impl BM25Index {
             doc_freq: HashMap::new(),
             term_freq: HashMap::new(),
             doc_lengths: HashMap::new(),
+            field_positions: HashMap::new(),
+            total_doc_length: 0.0,
             avg_doc_length: 0.0,
             doc_count: 0,
             params,
+            tokenizer: Tokenizer::default(),
         }
     }
 
-    /// Add a document to the index
+    /// Build an index over every document in storage
+    pub fn from_storage(storage: &VectorStorage, params: KeywordSearchParams) -> Self {
+        let mut index = Self::new(params);
+        for entry in storage.get_all_entries() {
+            index.add_document_fields(&entry.id, DocumentFields::from(&entry.document));
+        }
+        index
+    }
+
+    /// Parameters the index was built with
+    pub fn params(&self) -> &KeywordSearchParams {
+        &self.params
+    }
+
+    /// Whether a document is in the index
+    pub fn contains(&self, doc_id: &str) -> bool {
+        self.term_freq.contains_key(doc_id)
+    }
+
+    /// Add a document's body text to the index
     pub fn add_document(&mut self, doc_id: &str, content: &str) {
-        // Tokenize content
-        let tokens = self.tokenize(content);
-        let doc_length = tokens.len();
+        self.add_document_fields(
+            doc_id,
+            DocumentFields {
+                title: None,
+                section: None,
+                body: content,
+            },
+        );
+    }
 
-        // Update document length
-        self.doc_lengths.insert(doc_id.to_string(), doc_length);
+    /// Add a document with title and section fields weighted above the body
+    ///
+    /// Re-adding an indexed document replaces it.
+    pub fn add_document_fields(&mut self, doc_id: &str, fields: DocumentFields) {
+        self.remove_document(doc_id);
+
+        let weighted_fields = [
+            (fields.title, self.params.title_weight),
+            (fields.section, self.params.section_weight),
+            (Some(fields.body), 1.0),
+        ];
+
+        let mut doc_term_freq: HashMap<String, f32> = HashMap::new();
+        let mut doc_length = 0.0;
+        let mut field_positions = Vec::new();
 
-        // Update term frequencies
-        let mut doc_term_freq = HashMap::new();
-        for token in &tokens {
-            *doc_term_freq.entry(token.clone()).or_insert(0) += 1;
+        for (text, weight) in weighted_fields {
+            let Some(text) = text else { continue };
+            let tokens = self.tokenizer.tokenize_with_positions(text);
+
+            // Update term frequencies and positions
+            let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+            for (position, token) in &tokens {
+                *doc_term_freq.entry(token.clone()).or_insert(0.0) += weight;
+                positions.entry(token.clone()).or_default().push(*position);
+            }
+            doc_length += tokens.len() as f32 * weight;
+            field_positions.push(positions);
         }
-        self.term_freq
-            .insert(doc_id.to_string(), doc_term_freq.clone());
+
+        // Update document length
+        self.doc_lengths.insert(doc_id.to_string(), doc_length);
+        self.total_doc_length += doc_length;
+        self.field_positions
+            .insert(doc_id.to_string(), field_positions);
 
         // Update document frequencies
         for term in doc_term_freq.keys() {
             *self.doc_freq.entry(term.clone()).or_insert(0) += 1;
         }
+        self.term_freq.insert(doc_id.to_string(), doc_term_freq);
 
         // Update document count and average length
         self.doc_count += 1;
-        self.avg_doc_length =
-            self.doc_lengths.values().sum::<usize>() as f32 / self.doc_count as f32;
+        self.update_avg_doc_length();
+    }
+
+    /// Remove a document from the index, returning whether it was indexed
+    pub fn remove_document(&mut self, doc_id: &str) -> bool {
+        let Some(doc_term_freq) = self.term_freq.remove(doc_id) else {
+            return false;
+        };
+
+        // Terms no other document uses leave the vocabulary
+        for term in doc_term_freq.keys() {
+            if let Some(df) = self.doc_freq.get_mut(term) {
+                *df -= 1;
+                if *df == 0 {
+                    self.doc_freq.remove(term);
+                }
+            }
+        }
+
+        if let Some(doc_length) = self.doc_lengths.remove(doc_id) {
+            self.total_doc_length -= doc_length;
+        }
+        self.field_positions.remove(doc_id);
+
+        self.doc_count -= 1;
+        self.update_avg_doc_length();
+        true
+    }
+
+    fn update_avg_doc_length(&mut self) {
+        if self.doc_count == 0 {
+            // Reset so float error can't accumulate across an empty index
+            self.total_doc_length = 0.0;
+            self.avg_doc_length = 0.0;
+        } else {
+            self.avg_doc_length = self.total_doc_length / self.doc_count as f32;
+        }
+    }
+
+    /// Split a query into scored terms and required quoted phrases
+    fn parse_query(&self, query: &str) -> KeywordQuery {
+        let mut parsed = KeywordQuery::default();
+        let segments: Vec<&str> = query.split('"').collect();
+
+        for (i, segment) in segments.iter().enumerate() {
+            let tokens = self.tokenizer.tokenize_with_positions(segment);
+            if is_quoted_segment(i, segments.len()) && !tokens.is_empty() {
+                parsed.phrases.push(tokens.clone());
+            }
+            for (_, token) in tokens {
+                if !parsed.terms.contains(&token) {
+                    parsed.terms.push(token);
+                }
+            }
+        }
+
+        parsed
+    }
+
+    /// Whether any field of a document contains the phrase's terms at the same offsets
+    fn contains_phrase(&self, doc_id: &str, phrase: &[(usize, String)]) -> bool {
+        let Some(((first_offset, first), rest)) = phrase.split_first() else {
+            return true;
+        };
+
+        self.field_positions.get(doc_id).is_some_and(|fields| {
+            fields.iter().any(|positions| {
+                positions.get(first).is_some_and(|starts| {
+                    starts.iter().any(|&start| {
+                        rest.iter().all(|(offset, term)| {
+                            let expected = start + offset - first_offset;
+                            positions
+                                .get(term)
+                                .is_some_and(|found| found.contains(&expected))
+                        })
+                    })
+                })
+            })
+        })
     }
 
     /// Search for documents matching the query
+    ///
+    /// Quoted phrases (`"spawn_blocking"`, `"error handling"`) must appear
+    /// verbatim in the title, section or body for a document to match.
     pub fn search(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
-        // Tokenize query
-        let query_tokens = self.tokenize(query);
+        let query = self.parse_query(query);
 
         // Calculate BM25 scores for all documents
         let mut scores = HashMap::new();
 
         for (doc_id, doc_terms) in &self.term_freq {
+            if !query
+                .phrases
+                .iter()
+                .all(|phrase| self.contains_phrase(doc_id, phrase))
+            {
+                continue;
+            }
+
             let mut score = 0.0;
-            let doc_length = self.doc_lengths.get(doc_id).unwrap_or(&0);
+            let doc_length = self.doc_lengths.get(doc_id).copied().unwrap_or(0.0);
 
-            for query_term in &query_tokens {
+            for query_term in &query.terms {
                 if let Some(term_freq) = doc_terms.get(query_term) {
                     // Get document frequency
                     let df = self.doc_freq.get(query_term).unwrap_or(&0);
 
-                    // Calculate IDF
-                    let idf =
-                        ((self.doc_count as f32 - *df as f32 + 0.5) / (*df as f32 + 0.5)).ln();
+                    // Calculate IDF (Lucene variant, never negative for common terms)
+                    let idf = (1.0
+                        + (self.doc_count as f32 - *df as f32 + 0.5) / (*df as f32 + 0.5))
+                        .ln();
 
                     // Calculate BM25 score component
-                    let tf = *term_freq as f32;
-                    let dl = *doc_length as f32;
+                    let tf = *term_freq;
+                    let dl = doc_length;
                     let avgdl = self.avg_doc_length;
 
                     let numerator = tf * (self.params.k1 + 1.0);
@@ -190,14 +435,8 @@ impl BM25Index {
         results
     }
 
-    /// Simple tokenization (can be improved)
     fn tokenize(&self, text: &str) -> Vec<String> {
-        text.to_lowercase()
-            .split_whitespace()
-            .map(|s| s.trim_matches(|c: char| !c.is_alphanumeric()))
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect()
+        self.tokenizer.tokenize(text)
     }
 
     /// Get index statistics
@@ -218,11 +457,26 @@ pub struct BM25Stats {
 }
 
 /// Perform hybrid search combining vector similarity and keyword search
+///
+/// Builds a keyword index over storage for this query; use
+/// `hybrid_search_with_index` to reuse a maintained index.
 pub fn hybrid_search(
     storage: &VectorStorage,
     query_embedding: &[f32],
     query_text: &str,
     options: HybridSearchOptions,
+) -> Result<Vec<HybridSearchResult>> {
+    let bm25_index = BM25Index::from_storage(storage, options.keyword_params.clone());
+    hybrid_search_with_index(storage, &bm25_index, query_embedding, query_text, options)
+}
+
+/// Perform hybrid search using an existing keyword index over the same storage
+pub fn hybrid_search_with_index(
+    storage: &VectorStorage,
+    bm25_index: &BM25Index,
+    query_embedding: &[f32],
+    query_text: &str,
+    options: HybridSearchOptions,
 ) -> Result<Vec<HybridSearchResult>> {
     debug!("Performing hybrid search with query: {}", query_text);
 
@@ -245,15 +499,6 @@ pub fn hybrid_search(
         return Ok(results);
     }
 
-    // Build BM25 index
-    let mut bm25_index = BM25Index::new(options.keyword_params);
-
-    // Index all documents
-    let entries = storage.get_all_entries();
-    for entry in entries {
-        bm25_index.add_document(&entry.id, &entry.document.content);
-    }
-
     // Get vector search results (get more than needed for re-ranking)
     let vector_limit = options.base.limit * 3;
     let vector_options = SearchOptions {
@@ -265,24 +510,38 @@ pub fn hybrid_search(
 
     // Get keyword search results
     let keyword_results = bm25_index.search(query_text, vector_limit);
+    options.base.budget_exceeded("keyword search");
+
+    // Create a map of keyword ranks and scores
+    let keyword_scores: HashMap<&str, (usize, f32)> = keyword_results
+        .iter()
+        .enumerate()
+        .map(|(rank, (doc_id, score))| (doc_id.as_str(), (rank, *score)))
+        .collect();
 
-    // Create a map of keyword scores
-    let keyword_scores: HashMap<String, f32> = keyword_results.into_iter().collect();
+    // Quoted phrases are required: vector hits without them are dropped
+    let require_keyword_match = !bm25_index.parse_query(query_text).phrases.is_empty();
 
     // Combine scores
     let mut combined_results = Vec::new();
 
-    for vector_result in vector_results {
-        let doc_id = &vector_result.document.id;
+    for (vector_rank, vector_result) in vector_results.into_iter().enumerate() {
+        let doc_id = vector_result.document.id.as_str();
         let vector_score = vector_result.score;
-        let keyword_score = keyword_scores.get(doc_id).copied().unwrap_or(0.0);
+        let keyword_rank = keyword_scores.get(doc_id).map(|(rank, _)| *rank);
+        let keyword_score = keyword_scores.get(doc_id).map_or(0.0, |(_, score)| *score);
+        if require_keyword_match && keyword_rank.is_none() {
+            continue;
+        }
 
         // Normalize keyword score to 0-1 range (BM25 scores can be unbounded)
         let normalized_keyword_score = (keyword_score / (1.0 + keyword_score)).min(1.0);
 
         // Calculate combined score
-        let combined_score = options.vector_weight * vector_score
-            + options.keyword_weight * normalized_keyword_score;
+        let combined_score = options.fuse(
+            (Some(vector_rank), vector_score),
+            (keyword_rank, normalized_keyword_score),
+        );
 
         combined_results.push(HybridSearchResult {
             document: vector_result.document,
@@ -293,35 +552,27 @@ pub fn hybrid_search(
     }
 
     // Also check keyword-only results that might not be in vector results
-    for (doc_id, keyword_score) in keyword_scores {
+    for (keyword_rank, (doc_id, keyword_score)) in keyword_results.iter().enumerate() {
         // Skip if already in results
-        if combined_results.iter().any(|r| r.document.id == doc_id) {
+        if combined_results.iter().any(|r| &r.document.id == doc_id) {
             continue;
         }
 
         // Get document from storage
-        if let Some(document) = storage.get_document(&doc_id) {
+        if let Some(document) = storage.get_document(doc_id) {
             // Calculate vector score
             let entry = storage
                 .get_entries()
                 .iter()
-                .find(|e| e.id == doc_id)
+                .find(|e| &e.id == doc_id)
                 .unwrap();
 
-            let vector_score =
-                crate::vectordb::cosine_similarity(query_embedding, &entry.vector.values);
+            let vector_score = storage.entry_similarity(query_embedding, entry)
+                * options.base.score_factor(document);
 
             // Apply filters
-            if let Some(ref source_filter) = options.base.source_filter {
-                if !document.url.contains(source_filter) {
-                    continue;
-                }
-            }
-
-            if let Some(content_type_filter) = options.base.content_type_filter {
-                if document.metadata.content_type != content_type_filter {
-                    continue;
-                }
+            if !options.base.matches(document) {
+                continue;
             }
 
             if let Some(min_score) = options.base.min_score {
@@ -333,9 +584,11 @@ pub fn hybrid_search(
             // Normalize keyword score
             let normalized_keyword_score = (keyword_score / (1.0 + keyword_score)).min(1.0);
 
-            // Calculate combined score
-            let combined_score = options.vector_weight * vector_score
-                + options.keyword_weight * normalized_keyword_score;
+            // Calculate combined score (not ranked by vector search)
+            let combined_score = options.fuse(
+                (None, vector_score),
+                (Some(keyword_rank), normalized_keyword_score),
+            );
 
             combined_results.push(HybridSearchResult {
                 document: document.clone(),
@@ -347,29 +600,77 @@ pub fn hybrid_search(
     }
 
     // Sort by combined score and take top k
-    let mut heap = BinaryHeap::new();
-
-    for result in combined_results {
-        heap.push(result);
-
-        // Keep only top k results for efficiency
-        if heap.len() > options.base.limit * 2 {
-            heap.pop();
-        }
-    }
-
-    // Extract final results
-    let mut results: Vec<_> = heap.into_iter().collect();
+    let mut results = combined_results;
     results.sort_by(|a, b| {
         b.combined_score
             .partial_cmp(&a.combined_score)
             .unwrap_or(Ordering::Equal)
     });
+
+    // Symbols named by the query outrank everything else
+    let symbols = symbol_matches(storage, query_text, &options);
+    if !symbols.is_empty() {
+        let top_score = results.first().map_or(0.0, |r| r.combined_score).max(1.0);
+        results.retain(|r| !symbols.iter().any(|s| s.id == r.document.id));
+        let exact = symbols.into_iter().map(|document| HybridSearchResult {
+            vector_score: storage
+                .get_entries()
+                .iter()
+                .find(|e| e.id == document.id)
+                .map_or(0.0, |e| storage.entry_similarity(query_embedding, e)),
+            document,
+            keyword_score: 1.0,
+            combined_score: top_score,
+        });
+        results = exact.chain(results).collect();
+    }
     results.truncate(options.base.limit);
 
     Ok(results)
 }
 
+/// Symbol documents whose name is the query, e.g. "numpy.ndarray.reshape"
+///
+/// Exact names come first, then names the query is a dotted suffix of, so
+/// "ndarray.reshape" finds "numpy.ndarray.reshape" too.
+fn symbol_matches(
+    storage: &VectorStorage,
+    query_text: &str,
+    options: &HybridSearchOptions,
+) -> Vec<Document> {
+    let name = query_text
+        .trim()
+        .trim_matches('`')
+        .trim_end_matches("()")
+        .trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    let suffix = format!(".{}", name);
+
+    let mut exact = Vec::new();
+    let mut partial = Vec::new();
+    for entry in storage.get_entries() {
+        let document = &entry.document;
+        let Some(title) = document.title.as_deref() else {
+            continue;
+        };
+        if !document.metadata.tags.iter().any(|tag| tag == SYMBOL_TAG)
+            || !options.base.matches(document)
+        {
+            continue;
+        }
+        if title == name {
+            exact.push(document.clone());
+        } else if title.ends_with(&suffix) {
+            partial.push(document.clone());
+        }
+    }
+    partial.sort_by_key(|document| document.title.as_ref().map_or(0, String::len));
+    exact.extend(partial);
+    exact
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -383,10 +684,9 @@ mod tests {
             title: Some(format!("Title {}", id)),
             section: None,
             metadata: DocumentMetadata {
-                content_type: ContentType::Documentation,
                 language: Some("en".to_string()),
-                last_updated: None,
                 tags: vec!["test".to_string()],
+                ..Default::default()
             },
         }
     }
@@ -459,6 +759,77 @@ mod tests {
         assert_eq!(stats.doc_count, 3);
     }
 
+    #[test]
+    fn test_bm25_remove_document() {
+        let mut index = BM25Index::new(KeywordSearchParams::default());
+        index.add_document("1", "rust ownership and borrowing");
+        index.add_document("2", "python generators");
+        index.add_document("3", "rust traits");
+
+        assert!(index.remove_document("1"));
+        assert!(!index.remove_document("1"));
+        assert!(!index.contains("1"));
+
+        // Terms only the removed document used are gone; shared ones remain
+        let stats = index.stats();
+        assert_eq!(stats.doc_count, 2);
+        assert_eq!(stats.term_count, 4);
+        assert_eq!(index.search("ownership", 10), []);
+        assert_eq!(index.search("rust", 10)[0].0, "3");
+
+        // Bookkeeping matches an index built without the document
+        let mut rebuilt = BM25Index::new(KeywordSearchParams::default());
+        rebuilt.add_document("2", "python generators");
+        rebuilt.add_document("3", "rust traits");
+        assert_eq!(
+            index.search("rust traits", 10),
+            rebuilt.search("rust traits", 10)
+        );
+        assert_eq!(index.stats().avg_doc_length, rebuilt.stats().avg_doc_length);
+
+        // Re-adding replaces rather than double counting
+        index.add_document("3", "rust traits");
+        assert_eq!(index.stats().doc_count, 2);
+
+        index.remove_document("2");
+        index.remove_document("3");
+        assert_eq!(index.stats().term_count, 0);
+        assert_eq!(index.stats().avg_doc_length, 0.0);
+    }
+
+    #[test]
+    fn test_phrase_and_field_weighted_search() {
+        let mut index = BM25Index::new(KeywordSearchParams::default());
+        index.add_document_fields(
+            "title",
+            DocumentFields {
+                title: Some("tokio::spawn"),
+                section: None,
+                body: "Spawns a new asynchronous task",
+            },
+        );
+        index.add_document(
+            "body",
+            "You can spawn a task with tokio::spawn or use a new runtime",
+        );
+        index.add_document("split", "tokio can spawn many tasks");
+
+        // Title matches outrank body matches
+        let results = index.search("tokio spawn", 10);
+        assert_eq!(results[0].0, "title");
+        assert_eq!(results.len(), 3);
+
+        // A quoted phrase must appear verbatim
+        let results = index.search("\"tokio::spawn\" task", 10);
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&"split"));
+
+        assert!(has_quoted_phrase("\"error handling\" in axum"));
+        assert!(!has_quoted_phrase("unclosed \"quote"));
+        assert!(!has_quoted_phrase("no quotes"));
+    }
+
     #[test]
     fn test_hybrid_search() -> Result<()> {
         let storage = create_test_storage();
@@ -473,11 +844,22 @@ mod tests {
                 min_score: None,
                 source_filter: None,
                 content_type_filter: None,
+                ef_search: None,
+                allowed_urls: None,
+                lang_filter: None,
+                section_filter: None,
+                version_filter: None,
+                budget: None,
+                source_weights: Vec::new(),
+                recency_half_life_days: None,
+                exclude_sources: Vec::new(),
+                exclude_tags: Vec::new(),
             },
             enable_hybrid: true,
             vector_weight: 0.6,
             keyword_weight: 0.4,
             keyword_params: KeywordSearchParams::default(),
+            ..HybridSearchOptions::default()
         };
 
         let results = hybrid_search(&storage, &query_embedding, query_text, options)?;
@@ -496,6 +878,83 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_rrf_fusion() -> Result<()> {
+        let storage = create_test_storage();
+
+        // Vector search favors the Rust doc, only the Python doc matches the keywords
+        let query_embedding = vec![0.9, 0.2, 0.1];
+        let query_text = "python readability";
+        let options = HybridSearchOptions {
+            base: SearchOptions {
+                limit: 2,
+                ..SearchOptions::default()
+            },
+            ..HybridSearchOptions::default()
+        };
+
+        let results = hybrid_search(&storage, &query_embedding, query_text, options.clone())?;
+        assert_eq!(results[0].document.id, "1");
+
+        // Ranking first in one list and appearing in the other wins under RRF
+        let results = hybrid_search(
+            &storage,
+            &query_embedding,
+            query_text,
+            HybridSearchOptions {
+                fusion_strategy: FusionStrategy::Rrf,
+                ..options
+            },
+        )?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].document.id, "2");
+        assert_eq!(results[1].document.id, "1");
+        assert!((results[1].combined_score - 1.0 / 61.0).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbol_lookup_ranks_first() -> Result<()> {
+        let mut storage = create_test_storage();
+        for (id, name) in [
+            ("5", "numpy.ndarray.reshape"),
+            ("6", "numpy.reshape"),
+            ("7", "numpy.ndarray.resize"),
+        ] {
+            let mut doc = create_test_document(id, &format!("{}\nPython method", name), "");
+            doc.url = format!("https://numpy.org/doc/{}.html", name);
+            doc.title = Some(name.to_string());
+            doc.metadata.tags = vec![SYMBOL_TAG.to_string()];
+            storage.add_document(doc, vec![0.0, 0.0, 1.0])?;
+        }
+        let options = HybridSearchOptions {
+            base: SearchOptions {
+                limit: 3,
+                ..SearchOptions::default()
+            },
+            ..HybridSearchOptions::default()
+        };
+
+        let results = hybrid_search(
+            &storage,
+            &[1.0, 0.1, 0.1],
+            "`numpy.reshape()`",
+            options.clone(),
+        )?;
+        assert_eq!(results[0].document.id, "6");
+        assert!(results[0].combined_score >= results[1].combined_score);
+
+        let results = hybrid_search(&storage, &[1.0, 0.1, 0.1], "reshape", options.clone())?;
+        assert_eq!(results[0].document.id, "6");
+        assert_eq!(results[1].document.id, "5");
+
+        // Not a symbol lookup
+        let results = hybrid_search(&storage, &[1.0, 0.1, 0.1], "rust safety", options)?;
+        assert_eq!(results[0].document.id, "1");
+        Ok(())
+    }
+
     #[test]
     fn test_vector_only_search() -> Result<()> {
         let storage = create_test_storage();
@@ -510,11 +969,22 @@ mod tests {
                 min_score: None,
                 source_filter: None,
                 content_type_filter: None,
+                ef_search: None,
+                allowed_urls: None,
+                lang_filter: None,
+                section_filter: None,
+                version_filter: None,
+                budget: None,
+                source_weights: Vec::new(),
+                recency_half_life_days: None,
+                exclude_sources: Vec::new(),
+                exclude_tags: Vec::new(),
             },
             enable_hybrid: false, // Disable hybrid search
             vector_weight: 1.0,
             keyword_weight: 0.0,
             keyword_params: KeywordSearchParams::default(),
+            ..HybridSearchOptions::default()
         };
 
         let results = hybrid_search(&storage, &query_embedding, query_text, options)?;
@@ -1,11 +1,14 @@
 // HNSW (Hierarchical Navigable Small World) implementation for fast approximate nearest neighbor search
 
+use crate::error::{CodeRagError, Result};
 use crate::vectordb::types::{Vector, VectorId};
-use anyhow::Result;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::time::Instant;
-use tracing::debug;
+use tracing::{debug, info};
+
+/// Candidate ef_search values evaluated by [`HnswIndex::tune`]
+const TUNING_EF_CANDIDATES: [usize; 6] = [16, 32, 64, 128, 256, 512];
 
 /// Entry in priority queue for HNSW search
 #[derive(Debug, Clone)]
@@ -95,7 +98,10 @@ impl HnswConnections {
     /// Add a connection at a specific layer
     fn add_connection(&mut self, layer: usize, id: VectorId) -> Result<()> {
         if layer >= self.connections.len() {
-            anyhow::bail!("Layer index out of bounds: {}", layer);
+            return Err(CodeRagError::Index(format!(
+                "Layer index out of bounds: {}",
+                layer
+            )));
         }
 
         if !self.connections[layer].contains(&id) {
@@ -132,6 +138,8 @@ pub struct HnswIndex {
     dimension: usize,
     /// Random level generator
     level_generator: fn() -> usize,
+    /// Recall@k measured by the last tuning run
+    estimated_recall: Option<f32>,
 }
 
 impl HnswIndex {
@@ -156,9 +164,20 @@ impl HnswIndex {
                 let level = (-r.ln() * scale_factor).floor() as usize;
                 std::cmp::min(level, 10) // Cap at 10 levels for sanity
             },
+            estimated_recall: None,
         }
     }
 
+    /// Get the index parameters
+    pub fn params(&self) -> &HnswParams {
+        &self.params
+    }
+
+    /// Set the default search width used when a query does not override it
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search.max(1);
+    }
+
     /// Calculate distance between vectors based on the index's distance metric
     fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
         if self.params.use_cosine {
@@ -192,26 +211,30 @@ impl HnswIndex {
     pub fn add(&mut self, id: VectorId, vector: Vector) -> Result<()> {
         // Validate vector dimension
         if vector.dimension() != self.dimension {
-            anyhow::bail!(
-                "Vector dimension mismatch: expected {}, got {}",
-                self.dimension,
-                vector.dimension()
-            );
+            return Err(CodeRagError::DimensionMismatch {
+                expected: self.dimension,
+                actual: vector.dimension(),
+                message: format!(
+                    "Vector dimension mismatch: expected {}, got {}",
+                    self.dimension,
+                    vector.dimension()
+                ),
+            });
         }
 
         // Check if ID already exists
         if self.nodes.contains_key(&id) {
-            anyhow::bail!("Node with ID {} already exists", id);
+            return Err(CodeRagError::Index(format!(
+                "Node with ID {} already exists",
+                id
+            )));
         }
 
         // Generate random level for the node
         let level = (self.level_generator)();
 
-        // Update max level if needed
+        // Max level and entry point are raised by connect_node once the node is linked
         let is_first_node = self.nodes.is_empty();
-        if level > self.max_level && !is_first_node {
-            self.max_level = level;
-        }
 
         // Create node with connections up to its level
         let node = HnswNode {
@@ -241,13 +264,18 @@ impl HnswIndex {
     fn connect_node(&mut self, id: &VectorId, level: usize) -> Result<()> {
         let entry_id = match &self.entry_point {
             Some(ep) => ep.clone(),
-            None => anyhow::bail!("No entry point found"),
+            None => return Err(CodeRagError::Index("No entry point found".to_string())),
         };
 
         // Get vector of the new node
         let vector = match self.nodes.get(id) {
             Some(node) => node.vector.values.clone(),
-            None => anyhow::bail!("Node with ID {} not found", id),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "Node with ID {} not found",
+                    id
+                )))
+            }
         };
 
         // Start from entry point at the highest level
@@ -257,16 +285,13 @@ impl HnswIndex {
             &vector,
         );
 
-        // Search from top level down to the node's level
+        // Greedily descend from the top level down to the node's level
         for l in (level + 1..=self.max_level).rev() {
-            let changed = self.search_layer(&vector, &mut cur_node_id, &mut cur_dist, l)?;
-            if !changed {
-                debug!("No change at level {}", l);
-            }
+            while self.search_layer(&vector, &mut cur_node_id, &mut cur_dist, l)? {}
         }
 
-        // For each level from the node's level down to 0
-        for l in (0..=level).rev() {
+        // For each level from the node's level (capped at the graph's top) down to 0
+        for l in (0..=level.min(self.max_level)).rev() {
             // Find nearest neighbors at this level
             let ef = if l == 0 {
                 self.params.ef_construction * 2
@@ -316,13 +341,23 @@ impl HnswIndex {
         // Get the current best node
         let cur_node = match self.nodes.get(best_id) {
             Some(node) => node,
-            None => anyhow::bail!("Node with ID {} not found", best_id),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "Node with ID {} not found",
+                    best_id
+                )))
+            }
         };
 
         // Check all connections at this level
         let connections = match cur_node.connections.get_layer_connections(level) {
             Some(conn) => conn,
-            None => anyhow::bail!("No connections at level {}", level),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "No connections at level {}",
+                    level
+                )))
+            }
         };
 
         for conn_id in connections {
@@ -368,7 +403,12 @@ impl HnswIndex {
         // Get distance to entry point
         let entry_node = match self.nodes.get(&entry_id) {
             Some(node) => node,
-            None => anyhow::bail!("Entry node with ID {} not found", entry_id),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "Entry node with ID {} not found",
+                    entry_id
+                )))
+            }
         };
 
         let entry_dist = self.distance(&entry_node.vector.values, query);
@@ -408,7 +448,12 @@ impl HnswIndex {
             // Check all connections at this level
             let connections = match current_node.connections.get_layer_connections(level) {
                 Some(conn) => conn,
-                None => anyhow::bail!("No connections at level {}", level),
+                None => {
+                    return Err(CodeRagError::Index(format!(
+                        "No connections at level {}",
+                        level
+                    )))
+                }
             };
 
             for conn_id in connections {
@@ -480,13 +525,23 @@ impl HnswIndex {
         // Connect the node to its neighbors
         let node = match self.nodes.get_mut(id) {
             Some(node) => node,
-            None => anyhow::bail!("Node with ID {} not found", id),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "Node with ID {} not found",
+                    id
+                )))
+            }
         };
 
         // Connect up to max_connections
         let connections = match node.connections.get_layer_connections_mut(level) {
             Some(conn) => conn,
-            None => anyhow::bail!("No connections at level {}", level),
+            None => {
+                return Err(CodeRagError::Index(format!(
+                    "No connections at level {}",
+                    level
+                )))
+            }
         };
 
         connections.clear();
@@ -586,16 +641,30 @@ impl HnswIndex {
 
     /// Search for k nearest neighbors
     pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<(VectorId, f32)>> {
+        self.search_with_ef(query, k, self.params.ef_search)
+    }
+
+    /// Search for k nearest neighbors with an explicit search width
+    pub fn search_with_ef(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+    ) -> Result<Vec<(VectorId, f32)>> {
         // Time the search
         let start = Instant::now();
 
         // Validate query dimension
         if query.len() != self.dimension {
-            anyhow::bail!(
-                "Query dimension mismatch: expected {}, got {}",
-                self.dimension,
-                query.len()
-            );
+            return Err(CodeRagError::DimensionMismatch {
+                expected: self.dimension,
+                actual: query.len(),
+                message: format!(
+                    "Query dimension mismatch: expected {}, got {}",
+                    self.dimension,
+                    query.len()
+                ),
+            });
         }
 
         // Return empty result if index is empty
@@ -606,7 +675,7 @@ impl HnswIndex {
         // Get entry point
         let entry_id = match &self.entry_point {
             Some(ep) => ep.clone(),
-            None => anyhow::bail!("No entry point found"),
+            None => return Err(CodeRagError::Index("No entry point found".to_string())),
         };
 
         // Start from entry point
@@ -614,29 +683,22 @@ impl HnswIndex {
         let mut cur_dist =
             self.distance(&self.nodes.get(&cur_node_id).unwrap().vector.values, query);
 
-        // Search from top level down
+        // Greedily descend from the top level
         for l in (1..=self.max_level).rev() {
-            let changed = self.search_layer(query, &mut cur_node_id, &mut cur_dist, l)?;
-            if !changed {
-                debug!("No change at level {}", l);
-            }
+            while self.search_layer(query, &mut cur_node_id, &mut cur_dist, l)? {}
         }
 
         // Find ef_search nearest neighbors at level 0
-        let ef_search = self.params.ef_search.max(k);
+        let ef_search = ef.max(k);
         let nearest = self.search_neighbors(query, cur_node_id, ef_search, 0)?;
 
         // Convert to result format
         let mut results = Vec::with_capacity(k.min(nearest.len()));
         for neighbor in nearest.iter().take(k) {
-            // Convert distance to similarity score (0.0 to 1.0) if using cosine
-            let score = if self.params.use_cosine {
-                1.0 - neighbor.distance // Convert back to similarity
-            } else {
-                1.0 / (1.0 + neighbor.distance) // Convert L2 to similarity-like score
-            };
-
-            results.push((neighbor.id.clone(), score));
+            results.push((
+                neighbor.id.clone(),
+                self.distance_to_score(neighbor.distance),
+            ));
         }
 
         // Log search stats
@@ -651,6 +713,139 @@ impl HnswIndex {
         Ok(results)
     }
 
+    /// Convert an internal distance to a similarity score
+    fn distance_to_score(&self, distance: f32) -> f32 {
+        if self.params.use_cosine {
+            1.0 - distance // Convert back to similarity
+        } else {
+            1.0 / (1.0 + distance) // Convert L2 to similarity-like score
+        }
+    }
+
+    /// Exact k nearest neighbors by scanning every node (ground truth for recall)
+    pub fn exact_search(&self, query: &[f32], k: usize) -> Result<Vec<(VectorId, f32)>> {
+        if query.len() != self.dimension {
+            return Err(CodeRagError::DimensionMismatch {
+                expected: self.dimension,
+                actual: query.len(),
+                message: format!(
+                    "Query dimension mismatch: expected {}, got {}",
+                    self.dimension,
+                    query.len()
+                ),
+            });
+        }
+
+        let mut all: Vec<HnswEntry> = self
+            .nodes
+            .values()
+            .map(|node| HnswEntry {
+                id: node.id.clone(),
+                distance: self.distance(&node.vector.values, query),
+            })
+            .collect();
+        all.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        all.truncate(k);
+
+        Ok(all
+            .into_iter()
+            .map(|e| (e.id, self.distance_to_score(e.distance)))
+            .collect())
+    }
+
+    /// Benchmark recall@k and latency across ef_search values on a sample of stored vectors
+    ///
+    /// The smallest ef reaching `target_recall` becomes the default search width.
+    /// If no candidate reaches the target, a larger M is suggested; changing M
+    /// requires rebuilding the index, so it is reported rather than applied.
+    pub fn tune(
+        &mut self,
+        sample_size: usize,
+        k: usize,
+        target_recall: f32,
+    ) -> Result<HnswTuningReport> {
+        if self.nodes.is_empty() {
+            return Err(CodeRagError::Index(
+                "Cannot tune an empty index".to_string(),
+            ));
+        }
+
+        // Use randomly chosen stored vectors as queries
+        let mut rng = rand::thread_rng();
+        let sample_size = sample_size.clamp(1, self.nodes.len());
+        let queries: Vec<Vec<f32>> =
+            rand::seq::IteratorRandom::choose_multiple(self.nodes.values(), &mut rng, sample_size)
+                .into_iter()
+                .map(|node| node.vector.values.clone())
+                .collect();
+
+        // Ground truth from exact search
+        let ground_truth: Vec<HashSet<VectorId>> = queries
+            .iter()
+            .map(|q| {
+                self.exact_search(q, k)
+                    .map(|r| r.into_iter().map(|(id, _)| id).collect())
+            })
+            .collect::<Result<_>>()?;
+
+        let mut measurements = Vec::with_capacity(TUNING_EF_CANDIDATES.len());
+        for &ef in &TUNING_EF_CANDIDATES {
+            let start = Instant::now();
+            let mut recall_sum = 0.0;
+            for (query, truth) in queries.iter().zip(&ground_truth) {
+                let results = self.search_with_ef(query, k, ef)?;
+                recall_sum += recall_at_k(&results, truth);
+            }
+            let elapsed = start.elapsed();
+
+            measurements.push(EfMeasurement {
+                ef_search: ef,
+                recall: recall_sum / queries.len() as f32,
+                avg_latency_us: elapsed.as_micros() as f64 / queries.len() as f64,
+            });
+        }
+
+        // Pick the cheapest ef meeting the target, otherwise the most accurate one
+        let chosen = measurements
+            .iter()
+            .find(|m| m.recall >= target_recall)
+            .or_else(|| {
+                measurements
+                    .iter()
+                    .max_by(|a, b| a.recall.partial_cmp(&b.recall).unwrap_or(Ordering::Equal))
+            })
+            .cloned()
+            .expect("at least one ef candidate is measured");
+
+        let suggested_max_connections = if chosen.recall >= target_recall {
+            self.params.max_connections
+        } else {
+            self.params.max_connections * 2
+        };
+
+        self.params.ef_search = chosen.ef_search;
+        self.estimated_recall = Some(chosen.recall);
+
+        info!(
+            "HNSW tuning: ef_search={} recall@{}={:.3} ({:.1}µs/query), suggested M={}",
+            chosen.ef_search, k, chosen.recall, chosen.avg_latency_us, suggested_max_connections
+        );
+
+        Ok(HnswTuningReport {
+            sample_size,
+            k,
+            target_recall,
+            measurements,
+            suggested_ef_search: chosen.ef_search,
+            suggested_max_connections,
+            estimated_recall: chosen.recall,
+        })
+    }
+
     /// Get number of nodes in the index
     pub fn len(&self) -> usize {
         self.nodes.len()
@@ -701,10 +896,51 @@ impl HnswIndex {
             connections_per_level,
             max_connections_per_level,
             avg_connections_per_level,
+            ef_search: self.params.ef_search,
+            estimated_recall: self.estimated_recall,
         }
     }
 }
 
+/// Fraction of the exact top-k found in an approximate result list
+fn recall_at_k(results: &[(VectorId, f32)], truth: &HashSet<VectorId>) -> f32 {
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let hits = results.iter().filter(|(id, _)| truth.contains(id)).count();
+    hits as f32 / truth.len() as f32
+}
+
+/// Recall and latency measured for one ef_search value
+#[derive(Debug, Clone)]
+pub struct EfMeasurement {
+    /// Search width evaluated
+    pub ef_search: usize,
+    /// Mean recall@k over the sample queries
+    pub recall: f32,
+    /// Mean query latency in microseconds
+    pub avg_latency_us: f64,
+}
+
+/// Result of an HNSW tuning run
+#[derive(Debug, Clone)]
+pub struct HnswTuningReport {
+    /// Number of sample queries used
+    pub sample_size: usize,
+    /// Number of neighbors evaluated per query
+    pub k: usize,
+    /// Recall the tuner aimed for
+    pub target_recall: f32,
+    /// Measurements for each candidate ef_search
+    pub measurements: Vec<EfMeasurement>,
+    /// Suggested (and applied) default ef_search
+    pub suggested_ef_search: usize,
+    /// Suggested M; takes effect only when the index is rebuilt
+    pub suggested_max_connections: usize,
+    /// Recall@k expected at the suggested ef_search
+    pub estimated_recall: f32,
+}
+
 /// Statistics about the HNSW index
 #[derive(Debug, Clone)]
 pub struct HnswStats {
@@ -722,6 +958,10 @@ pub struct HnswStats {
     pub max_connections_per_level: Vec<usize>,
     /// Average connections per node at each level
     pub avg_connections_per_level: Vec<f32>,
+    /// Default search width
+    pub ef_search: usize,
+    /// Recall@k measured by the last tuning run, if any
+    pub estimated_recall: Option<f32>,
 }
 
 #[cfg(test)]
@@ -775,4 +1015,32 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_hnsw_tune() -> Result<()> {
+        let mut index = HnswIndex::new(3, HnswParams::default());
+        for i in 0..200 {
+            let angle = i as f32 * 0.05;
+            index.add(
+                i.to_string(),
+                Vector::new(vec![angle.cos(), angle.sin(), (i % 5) as f32 * 0.1]),
+            )?;
+        }
+
+        // Exact search finds the query vector itself first
+        let exact = index.exact_search(&[1.0, 0.0, 0.0], 3)?;
+        assert_eq!(exact.len(), 3);
+        assert_eq!(exact[0].0, "0");
+
+        let report = index.tune(20, 5, 0.9)?;
+        assert_eq!(report.measurements.len(), TUNING_EF_CANDIDATES.len());
+        assert!(report.estimated_recall > 0.0 && report.estimated_recall <= 1.0);
+
+        // Tuned values are applied and reported in stats
+        let stats = index.stats();
+        assert_eq!(stats.ef_search, report.suggested_ef_search);
+        assert_eq!(stats.estimated_recall, Some(report.estimated_recall));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,114 @@
+//! Write lock that keeps several server instances from saving the same database
+//!
+//! The first instance to open a database takes an exclusive lock on a
+//! `.lock` file beside it and may write. Later instances (a second editor
+//! window on the same project) open it read-only: they keep serving searches
+//! and reload when the writer saves, but refuse operations that would save.
+
+use crate::error::{CodeRagError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How a server opens its database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenMode {
+    /// Read-write if the write lock is free, otherwise read-only
+    #[default]
+    Auto,
+    /// Read-write; opening fails if another instance holds the write lock
+    ReadWrite,
+    /// Never write, and never take the write lock
+    ReadOnly,
+}
+
+impl FromStr for OpenMode {
+    type Err = CodeRagError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "read_write" | "read-write" => Ok(Self::ReadWrite),
+            "read_only" | "read-only" => Ok(Self::ReadOnly),
+            _ => Err(CodeRagError::Parse(format!(
+                "Invalid open mode: {}. Must be 'auto', 'read_write', or 'read_only'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Exclusive write lock on a database, released when dropped
+#[derive(Debug)]
+pub struct DatabaseLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl DatabaseLock {
+    /// Try to take the write lock for a database file
+    ///
+    /// Returns None if another process holds it.
+    pub fn try_acquire(db_path: &Path) -> Result<Option<Self>> {
+        let path = db_path.with_extension("lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| CodeRagError::io(format!("Failed to open lock file {:?}", path), e))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { path, _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => {
+                Err(CodeRagError::io(format!("Failed to lock {:?}", path), e))
+            }
+        }
+    }
+
+    /// Take the write lock as `mode` asks, returning None when opening read-only
+    pub fn for_mode(db_path: &Path, mode: OpenMode) -> Result<Option<Self>> {
+        match mode {
+            OpenMode::ReadOnly => Ok(None),
+            OpenMode::Auto => Self::try_acquire(db_path),
+            OpenMode::ReadWrite => Self::try_acquire(db_path)?
+                .map(Some)
+                .ok_or_else(|| CodeRagError::Locked(db_path.to_path_buf())),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_lock_is_exclusive() -> Result<()> {
+        let dir =
+            tempfile::tempdir().map_err(|e| CodeRagError::io("Failed to create temp dir", e))?;
+        let db_path = dir.path().join("vectordb.json");
+
+        let lock = DatabaseLock::for_mode(&db_path, OpenMode::Auto)?;
+        assert!(lock.is_some());
+
+        // A second instance falls back to read-only, or fails if it insists on writing
+        assert!(DatabaseLock::for_mode(&db_path, OpenMode::Auto)?.is_none());
+        assert!(matches!(
+            DatabaseLock::for_mode(&db_path, OpenMode::ReadWrite),
+            Err(CodeRagError::Locked(_))
+        ));
+        assert!(DatabaseLock::for_mode(&db_path, OpenMode::ReadOnly)?.is_none());
+
+        drop(lock);
+        assert!(DatabaseLock::for_mode(&db_path, OpenMode::ReadWrite)?.is_some());
+
+        Ok(())
+    }
+}
@@ -7,32 +7,82 @@
 #![allow(unused_imports)]
 
 mod chunking;
+mod clustering;
 mod hybrid_search;
 mod indexing;
+mod lock;
+#[cfg(feature = "qdrant")]
+mod qdrant;
 mod quantization;
 mod search;
 mod storage;
+mod store;
+mod tokenizer;
 mod types;
+mod versions;
 
 pub use chunking::{Chunk, ChunkingStrategy, EnhancedChunker};
+pub use clustering::{source_root, Topic};
 pub use hybrid_search::{
-    hybrid_search, BM25Index, HybridSearchOptions, HybridSearchResult, KeywordSearchParams,
+    has_quoted_phrase, hybrid_search, hybrid_search_with_index, BM25Index, DocumentFields,
+    FusionStrategy, HybridSearchOptions, HybridSearchResult, KeywordSearchParams, SYMBOL_TAG,
 };
-pub use indexing::{HnswIndex, HnswParams, HnswStats};
-pub use quantization::{QuantizationMethod, VectorQuantizer};
-pub use search::{cosine_similarity, SearchOptions, SearchResult};
-pub use storage::VectorStorage;
-pub use types::{ContentType, Document, DocumentMetadata};
-
-use anyhow::Result;
-use std::collections::HashMap;
+pub use indexing::{EfMeasurement, HnswIndex, HnswParams, HnswStats, HnswTuningReport};
+pub use lock::{DatabaseLock, OpenMode};
+#[cfg(feature = "qdrant")]
+pub use qdrant::QdrantStore;
+pub use quantization::{AsymmetricQuery, QuantizationMethod, VectorQuantizer};
+pub use search::{
+    collapse_duplicates, cosine_similarity, merge_layered_results, LayeredSearchResult,
+    SearchBudget, SearchOptions, SearchResult, SearchVerification,
+};
+pub use storage::{StorageBackend, VectorStorage};
+pub use store::VectorStore;
+pub use tokenizer::Tokenizer;
+pub use types::{
+    chunk_core, reassemble_chunks, ContentType, Document, DocumentMetadata, Provenance,
+};
+pub use versions::{Version, VersionRange};
+
+use crate::error::{CodeRagError, Result};
+use crate::telemetry;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
+use std::time::{Instant, SystemTime};
+use tracing::{instrument, warn, Span};
+
+/// Chunks more similar than this to an already chosen central chunk are skipped
+const CENTRAL_MAX_REDUNDANCY: f32 = 0.95;
+
+/// Outcome of compacting the database
+#[derive(Debug, Clone)]
+pub struct CompactionReport {
+    /// Entries dropped because a later entry with the same ID superseded them
+    pub entries_dropped: usize,
+    /// Nodes in the rebuilt HNSW index, if indexing is enabled
+    pub index_nodes: Option<usize>,
+    /// Bytes on disk before compaction
+    pub bytes_before: u64,
+    /// Bytes on disk after the compacted database was saved
+    pub bytes_after: u64,
+}
+
+impl CompactionReport {
+    /// Bytes freed on disk
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
 
 /// Main vector database interface combining storage and search capabilities
 pub struct VectorDatabase {
     storage: VectorStorage,
-    index: Option<HnswIndex>,
-    quantizer: Option<VectorQuantizer>,
+    /// Nearest-neighbour index: the HNSW graph or an external store
+    index: Option<Box<dyn VectorStore>>,
+    /// Keyword index for hybrid search, kept in sync with storage
+    keyword_index: BM25Index,
+    /// Debug mode: check every HNSW search against exact search
+    verify_searches: bool,
 }
 
 impl VectorDatabase {
@@ -43,7 +93,8 @@ impl VectorDatabase {
         Ok(Self {
             storage,
             index: None,
-            quantizer: None,
+            keyword_index: BM25Index::new(KeywordSearchParams::default()),
+            verify_searches: false,
         })
     }
 
@@ -54,132 +105,372 @@ impl VectorDatabase {
         params: HnswParams,
     ) -> Result<Self> {
         let storage = VectorStorage::new(data_path)?;
-        let index = Some(HnswIndex::new(dimension, params));
+        let index: Option<Box<dyn VectorStore>> = Some(Box::new(HnswIndex::new(dimension, params)));
 
         Ok(Self {
             storage,
             index,
-            quantizer: None,
+            keyword_index: BM25Index::new(KeywordSearchParams::default()),
+            verify_searches: false,
         })
     }
 
     /// Create a new vector database instance with vector quantization
+    ///
+    /// Vectors are stored as quantized codes and scored asymmetrically against
+    /// full-precision queries.
     pub fn with_quantization<P: AsRef<Path>>(
         data_path: P,
         dimension: usize,
         method: QuantizationMethod,
     ) -> Result<Self> {
-        let storage = VectorStorage::new(data_path)?;
-        let quantizer = Some(VectorQuantizer::new(method, dimension));
+        let mut storage = VectorStorage::new(data_path)?;
+        storage.enable_quantization(method, dimension);
 
         Ok(Self {
             storage,
             index: None,
-            quantizer,
+            keyword_index: BM25Index::new(KeywordSearchParams::default()),
+            verify_searches: false,
         })
     }
 
+    /// Answer vector searches from `store` instead of an exact scan or the HNSW graph
+    pub fn with_vector_store(mut self, store: Box<dyn VectorStore>) -> Self {
+        self.index = Some(store);
+        self
+    }
+
     /// Load the database from persistent storage
     pub fn load(&mut self) -> Result<()> {
-        // Load storage first
+        // Load storage first (this also trains and applies quantization if enabled)
         self.storage.load()?;
+        self.build_indexes()
+    }
 
-        // Initialize HNSW index if enabled
-        if let Some(index) = &mut self.index {
-            // If index is empty, build it from storage
-            if index.is_empty() {
-                let entries = self.storage.get_all_entries();
-                for entry in entries {
-                    index.add(entry.id.clone(), entry.vector.clone())?;
-                }
-            }
-        }
+    /// Load the database, dropping corrupt entries instead of failing
+    ///
+    /// Returns the number of entries dropped; save to make the repair permanent.
+    pub fn load_repairing(&mut self) -> Result<usize> {
+        let dropped = self.storage.load_repairing()?;
+        self.build_indexes()?;
+        Ok(dropped)
+    }
+
+    /// Fail if the stored vectors came from a different embedding model or dimension
+    pub fn check_model(&self, model: &str, dimension: usize) -> Result<()> {
+        self.storage.check_model(model, dimension)
+    }
+
+    /// Record the embedding model, failing if the stored vectors came from a different one
+    pub fn bind_model(&mut self, model: &str, dimension: usize) -> Result<()> {
+        self.storage.bind_model(model, dimension)
+    }
 
-        // Initialize quantizer if enabled
-        if let Some(quantizer) = &mut self.quantizer {
-            // Initialize with all vectors
-            let entries = self.storage.get_all_entries();
-            let vectors: Vec<_> = entries.iter().map(|e| e.vector.clone()).collect();
+    /// Build the keyword and HNSW indexes from freshly loaded storage
+    fn build_indexes(&mut self) -> Result<()> {
+        self.keyword_index =
+            BM25Index::from_storage(&self.storage, self.keyword_index.params().clone());
 
-            if !vectors.is_empty() {
-                quantizer.initialize(&vectors)?;
+        // Rebuild the index unless it already holds every entry, like a persistent store can
+        if let Some(index) = &self.index {
+            let ids: HashSet<&str> = self
+                .storage
+                .get_entries()
+                .iter()
+                .map(|e| e.id.as_str())
+                .collect();
+            // A remote store may be unreachable; searches then fall back to exact
+            match index.len() {
+                Ok(len) if len == ids.len() => {}
+                Ok(_) => self.rebuild_index()?,
+                Err(e) => warn!("Couldn't check the {} index: {}", index.name(), e),
             }
         }
 
         Ok(())
     }
 
+    /// Reload the database if another process saved it since it was loaded
+    ///
+    /// Returns whether it was reloaded.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        if !self.storage.changed_on_disk() {
+            return Ok(false);
+        }
+        if let Some(index) = self.index.as_mut().filter(|index| !index.persistent()) {
+            index.clear()?;
+        }
+        self.load()?;
+        Ok(true)
+    }
+
     /// Add a document with its embedding to the database
     pub fn add_document(&mut self, doc: Document, embedding: Vec<f32>) -> Result<String> {
-        // Add to storage
-        let id = self.storage.add_document(doc, embedding.clone())?;
-
-        // Add to HNSW index if enabled
+        // Add to the index first, which checks the dimension too
         if let Some(index) = &mut self.index {
             let vector = types::Vector::new(embedding.clone());
-            index.add(id.clone(), vector)?;
+            index.add(doc.id.clone(), vector, &doc)?;
         }
 
-        Ok(id)
+        // Add to storage
+        let fields = DocumentFields::from(&doc);
+        self.keyword_index.add_document_fields(&doc.id, fields);
+        self.storage.add_document(doc, embedding)
+    }
+
+    /// Enable or disable the recall verification debug mode
+    ///
+    /// When enabled, every HNSW search is also run as an exact search and
+    /// queries where the index missed top results are logged.
+    pub fn set_search_verification(&mut self, enabled: bool) {
+        self.verify_searches = enabled;
     }
 
     /// Search for similar documents using the appropriate search method
+    #[instrument(
+        name = "search",
+        skip_all,
+        fields(limit = options.limit, method, results, elapsed_ms)
+    )]
     pub fn search(
         &self,
         query_embedding: &[f32],
         options: SearchOptions,
     ) -> Result<Vec<SearchResult>> {
-        // If HNSW index is enabled, use it for search
-        if let Some(index) = &self.index {
-            // Use HNSW search
-            let results = index.search(query_embedding, options.limit)?;
-
-            // Convert to SearchResult format
-            let mut search_results = Vec::with_capacity(results.len());
-
-            for (id, score) in results {
-                if let Some(document) = self.storage.get_document(&id) {
-                    // Apply filters
-                    if let Some(ref source_filter) = options.source_filter {
-                        if !document.url.contains(source_filter) {
-                            continue;
-                        }
+        let started = Instant::now();
+        let span = Span::current();
+
+        // If an index is enabled, use it for search
+        let results = if let Some(index) = &self.index {
+            if self.verify_searches {
+                span.record("method", "verified");
+                self.verify_search(query_embedding, options)?.0
+            } else {
+                span.record("method", index.name());
+                match self.index_search(index.as_ref(), query_embedding, &options) {
+                    Ok(results) => results,
+                    // Documents are kept locally, so a remote store can be bypassed
+                    Err(e) if index.persistent() => {
+                        warn!("{} search failed, using exact search: {}", index.name(), e);
+                        span.record("method", "exact");
+                        search::search_documents(&self.storage, query_embedding, options)?
                     }
+                    Err(e) => return Err(e),
+                }
+            }
+        } else {
+            // Fall back to standard search
+            span.record("method", "exact");
+            search::search_documents(&self.storage, query_embedding, options)?
+        };
 
-                    if let Some(content_type_filter) = options.content_type_filter {
-                        if document.metadata.content_type != content_type_filter {
-                            continue;
-                        }
-                    }
+        span.record("results", results.len());
+        telemetry::record_elapsed(started);
+        Ok(results)
+    }
 
-                    if let Some(min_score) = options.min_score {
-                        if score < min_score {
-                            continue;
-                        }
-                    }
+    /// Run a search through both the index and exact search and compare them
+    ///
+    /// Returns the HNSW results together with recall@k and rank correlation
+    /// against the exact results. Without an index there is nothing to verify.
+    pub fn verify_search(
+        &self,
+        query_embedding: &[f32],
+        options: SearchOptions,
+    ) -> Result<(Vec<SearchResult>, Option<SearchVerification>)> {
+        let Some(index) = &self.index else {
+            let results = search::search_documents(&self.storage, query_embedding, options)?;
+            return Ok((results, None));
+        };
+
+        let start = Instant::now();
+        let approximate = self.index_search(index.as_ref(), query_embedding, &options)?;
+        let approximate_latency = start.elapsed();
+
+        let start = Instant::now();
+        let exact = search::search_documents(&self.storage, query_embedding, options)?;
+        let exact_latency = start.elapsed();
+
+        let verification =
+            search::verify_results(&approximate, &exact, approximate_latency, exact_latency);
+
+        if !verification.missed.is_empty() {
+            warn!(
+                "Index missed {} of top {} results (recall {:.3}, rank correlation {:.3}): {:?}",
+                verification.missed.len(),
+                verification.k,
+                verification.recall_at_k,
+                verification.rank_correlation,
+                verification.missed
+            );
+        }
+
+        Ok((approximate, Some(verification)))
+    }
 
-                    search_results.push(SearchResult {
-                        document: document.clone(),
-                        score,
-                    });
+    /// Convert index hits to search results, dropping those the options exclude
+    fn filter_results(
+        &self,
+        results: Vec<(String, f32)>,
+        options: &SearchOptions,
+    ) -> Vec<SearchResult> {
+        let mut search_results = Vec::with_capacity(results.len());
+
+        for (id, score) in results {
+            if let Some(document) = self.storage.get_document(&id) {
+                let score = score * options.score_factor(document);
+
+                // Apply filters
+                if !options.matches(document) {
+                    continue;
+                }
+
+                if let Some(min_score) = options.min_score {
+                    if score < min_score {
+                        continue;
+                    }
                 }
+
+                search_results.push(SearchResult {
+                    document: document.clone(),
+                    score,
+                });
+            }
+        }
+
+        search_results
+    }
+
+    fn index_search(
+        &self,
+        index: &dyn VectorStore,
+        query_embedding: &[f32],
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        // Filters are applied after the index lookup, so widen it until enough
+        // candidates pass or every document has been considered
+        let total = self.storage.document_count();
+        let mut fetch = options.limit.min(total);
+        let mut search_results = loop {
+            // Honor a per-query search width
+            let results = index.search(query_embedding, fetch, options.ef_search)?;
+            let search_results = self.filter_results(results, options);
+            if search_results.len() >= options.limit {
+                break search_results;
             }
+            if fetch >= total {
+                // The graph may never reach documents the filters keep, e.g. when
+                // many near-identical vectors crowd them out of the neighbour lists
+                return search::search_documents(&self.storage, query_embedding, options.clone());
+            }
+            fetch = (fetch.max(1) * 4).min(total);
+        };
+        options.budget_exceeded("ann");
 
-            Ok(search_results)
-        } else {
-            // Fall back to standard search
-            search::search_documents(&self.storage, query_embedding, options)
+        // Feedback boosts can reorder the index's ranking
+        search_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        search_results.truncate(options.limit);
+        options.budget_exceeded("rerank");
+
+        Ok(search_results)
+    }
+
+    /// Find documents similar to an existing one ("more like this")
+    ///
+    /// Uses the stored vector of `doc_id` as the query. The document itself is
+    /// excluded from the results.
+    pub fn find_similar(&self, doc_id: &str, k: usize) -> Result<Vec<SearchResult>> {
+        let entry = self
+            .storage
+            .get_entries()
+            .iter()
+            .find(|e| e.id == doc_id)
+            .ok_or_else(|| CodeRagError::DocumentNotFound(doc_id.to_string()))?;
+        let query = self.storage.entry_vector(entry)?;
+
+        let options = SearchOptions {
+            limit: k.saturating_add(1),
+            ..SearchOptions::default()
+        };
+
+        let mut results = self.search(&query.values, options)?;
+        results.retain(|r| r.document.id != doc_id);
+        results.truncate(k);
+
+        Ok(results)
+    }
+
+    /// Search several databases and merge the results into one ranking
+    ///
+    /// Layers are `(origin, database, weight)` in priority order, e.g. a
+    /// project database followed by the global one. Scores of a layer's hits
+    /// are multiplied by its weight, and each hit is labeled with the origin
+    /// of the layer it came from.
+    pub fn search_layered(
+        layers: &[(&str, &VectorDatabase, f32)],
+        query_embedding: &[f32],
+        options: SearchOptions,
+    ) -> Result<Vec<LayeredSearchResult>> {
+        let limit = options.limit;
+        let mut layer_results = Vec::with_capacity(layers.len());
+        for (i, (origin, db, weight)) in layers.iter().enumerate() {
+            // Lower-priority layers are skipped once the time budget runs out
+            if i > 0 && options.budget_exceeded("layered search") {
+                break;
+            }
+            let mut results = db.search(query_embedding, options.clone())?;
+            for result in &mut results {
+                result.score *= weight;
+            }
+            layer_results.push((origin.to_string(), results));
         }
+
+        Ok(merge_layered_results(layer_results, limit))
     }
 
     /// Search for similar documents using hybrid search (vector + keyword)
+    ///
+    /// Reuses the maintained keyword index unless the options ask for
+    /// different keyword parameters.
+    #[instrument(
+        name = "search",
+        skip_all,
+        fields(limit = options.base.limit, method = "hybrid", results, elapsed_ms)
+    )]
     pub fn hybrid_search(
         &self,
         query_embedding: &[f32],
         query_text: &str,
         options: HybridSearchOptions,
     ) -> Result<Vec<HybridSearchResult>> {
-        hybrid_search::hybrid_search(&self.storage, query_embedding, query_text, options)
+        let started = Instant::now();
+        let results = if self.keyword_index.params() == &options.keyword_params {
+            hybrid_search_with_index(
+                &self.storage,
+                &self.keyword_index,
+                query_embedding,
+                query_text,
+                options,
+            )?
+        } else {
+            hybrid_search::hybrid_search(&self.storage, query_embedding, query_text, options)?
+        };
+
+        Span::current().record("results", results.len());
+        telemetry::record_elapsed(started);
+        Ok(results)
+    }
+
+    /// Record helpful/irrelevant feedback on a document, returning its new boost
+    ///
+    /// The boost multiplies the document's similarity score in every search.
+    pub fn apply_feedback(&mut self, doc_id: &str, helpful: bool) -> Result<f32> {
+        let document = self
+            .storage
+            .get_document_mut(doc_id)
+            .ok_or_else(|| CodeRagError::DocumentNotFound(doc_id.to_string()))?;
+        Ok(document.metadata.apply_feedback(helpful))
     }
 
     /// Get total number of documents
@@ -187,6 +478,160 @@ impl VectorDatabase {
         self.storage.document_count()
     }
 
+    /// Get a document by ID
+    pub fn get_document(&self, id: &str) -> Option<&Document> {
+        self.storage.get_document(id)
+    }
+
+    /// Get all chunks stored for a URL, in page order
+    ///
+    /// A URL recorded as an alias of a near-duplicate page resolves to that page.
+    pub fn get_documents_by_url(&self, url: &str) -> Vec<&Document> {
+        let entries = self.storage.get_entries();
+        let mut chunks: Vec<&Document> = entries
+            .iter()
+            .filter(|e| e.document.url == url)
+            .map(|e| &e.document)
+            .collect();
+        if chunks.is_empty() {
+            chunks = entries
+                .iter()
+                .filter(|e| e.document.metadata.aliases.iter().any(|a| a == url))
+                .map(|e| &e.document)
+                .collect();
+        }
+
+        // Stable sort keeps insertion order for chunks without a position tag
+        chunks.sort_by_key(|doc| doc.chunk_position().map_or(usize::MAX, |(index, _)| index));
+        chunks
+    }
+
+    /// Find an indexed page whose simhash differs from `simhash` in at most
+    /// `max_distance` bits, ignoring the page at `exclude_url`
+    pub fn find_near_duplicate(
+        &self,
+        simhash: u64,
+        max_distance: u32,
+        exclude_url: &str,
+    ) -> Option<String> {
+        self.storage
+            .get_entries()
+            .iter()
+            .map(|e| &e.document)
+            .filter(|doc| doc.url != exclude_url)
+            .find(|doc| {
+                doc.metadata
+                    .simhash
+                    .is_some_and(|other| (other ^ simhash).count_ones() <= max_distance)
+            })
+            .map(|doc| doc.url.clone())
+    }
+
+    /// Record `alias` as another URL serving the page at `url`
+    ///
+    /// Returns false if no chunks are stored for `url`.
+    pub fn add_alias(&mut self, url: &str, alias: &str) -> bool {
+        let documents = self.storage.get_documents_by_url_mut(url);
+        if documents.is_empty() {
+            return false;
+        }
+        for doc in documents {
+            if !doc.metadata.aliases.iter().any(|a| a == alias) {
+                doc.metadata.aliases.push(alias.to_string());
+            }
+        }
+        true
+    }
+
+    /// Point chunks about to be added at identical chunks already indexed from other URLs
+    ///
+    /// The same tutorial is often served by several mirrors; search collapses
+    /// a chunk and its copies into one hit. Chunk IDs end in a hash of the
+    /// chunk's text, which narrows the candidates before the texts are compared.
+    /// Returns how many chunks were linked.
+    pub fn link_duplicate_chunks<'a>(
+        &self,
+        documents: impl IntoIterator<Item = &'a mut Document>,
+    ) -> usize {
+        let mut pending: HashMap<u32, Vec<&'a mut Document>> = HashMap::new();
+        for document in documents {
+            if let Some(hash) = id_content_hash(&document.id) {
+                pending.entry(hash).or_default().push(document);
+            }
+        }
+
+        let mut linked = 0;
+        for entry in self.storage.get_entries() {
+            let Some(candidates) = id_content_hash(&entry.id).and_then(|h| pending.get_mut(&h))
+            else {
+                continue;
+            };
+            let existing = &entry.document;
+            for document in candidates.iter_mut() {
+                if document.metadata.canonical.is_none()
+                    && document.url != existing.url
+                    && chunk_core(&document.content) == chunk_core(&existing.content)
+                {
+                    document.metadata.canonical = Some(existing.canonical_id().to_string());
+                    linked += 1;
+                }
+            }
+        }
+        linked
+    }
+
+    /// Group the documents into topics by embedding similarity
+    ///
+    /// `topics` defaults to a count that grows with the database size.
+    pub fn topic_map(
+        &self,
+        topics: Option<usize>,
+        representatives_per_topic: usize,
+    ) -> Result<Vec<Topic>> {
+        let entries = self.storage.get_entries();
+        let vectors = entries
+            .iter()
+            .map(|entry| Ok(self.storage.entry_vector(entry)?.values))
+            .collect::<Result<Vec<_>>>()?;
+        let documents: Vec<&Document> = entries.iter().map(|entry| &entry.document).collect();
+
+        let k = topics.unwrap_or_else(|| clustering::default_topic_count(entries.len()));
+        let clusters = clustering::kmeans(&vectors, k);
+        Ok(clustering::describe_topics(
+            &clusters,
+            &documents,
+            &vectors,
+            representatives_per_topic,
+        ))
+    }
+
+    /// The chunks whose URL starts with `url_prefix` that best represent them
+    ///
+    /// Returns how many chunks matched, and up to `count` of them ranked by
+    /// similarity to their centroid, skipping near-duplicates.
+    pub fn central_documents(
+        &self,
+        url_prefix: &str,
+        count: usize,
+    ) -> Result<(usize, Vec<(&Document, f32)>)> {
+        let entries: Vec<&types::VectorEntry> = self
+            .storage
+            .get_entries()
+            .iter()
+            .filter(|entry| entry.document.url.starts_with(url_prefix))
+            .collect();
+        let vectors = entries
+            .iter()
+            .map(|entry| Ok(self.storage.entry_vector(entry)?.values))
+            .collect::<Result<Vec<_>>>()?;
+
+        let central = clustering::central_members(&vectors, count, CENTRAL_MAX_REDUNDANCY)
+            .into_iter()
+            .map(|(i, centrality)| (&entries[i].document, centrality))
+            .collect();
+        Ok((entries.len(), central))
+    }
+
     /// Get all documents grouped by source URL
     pub fn get_documents_by_source(&self) -> HashMap<String, Vec<&Document>> {
         use std::collections::HashMap;
@@ -207,21 +652,25 @@ impl VectorDatabase {
 
     /// Save the database to disk
     pub fn save(&self) -> Result<()> {
+        if let Some(index) = &self.index {
+            index.flush()?;
+        }
         self.storage.save()
     }
 
+    /// Whether the database has changes since it was last loaded or saved
+    pub fn is_modified(&self) -> bool {
+        self.storage.is_modified()
+    }
+
     /// Clear all documents from the database
     pub fn clear(&mut self) -> Result<()> {
         self.storage.clear()?;
+        self.keyword_index = BM25Index::new(self.keyword_index.params().clone());
 
-        // Clear HNSW index if enabled
+        // Clear the index if enabled
         if let Some(index) = &mut self.index {
-            *index = HnswIndex::new(index.stats().dimension, HnswParams::default());
-        }
-
-        // Clear quantizer cache if enabled
-        if let Some(quantizer) = &mut self.quantizer {
-            quantizer.clear_cache();
+            index.clear()?;
         }
 
         Ok(())
@@ -230,7 +679,7 @@ impl VectorDatabase {
     /// Remove documents from a specific source URL
     pub fn remove_documents_by_source(&mut self, source_url: &str) -> Result<usize> {
         // Get IDs to remove
-        let _ids_to_remove: Vec<String> = self
+        let ids_to_remove: HashSet<String> = self
             .storage
             .get_entries()
             .iter()
@@ -240,63 +689,234 @@ impl VectorDatabase {
 
         // Remove from storage
         let removed_count = self.storage.remove_documents_by_source(source_url)?;
-
-        // Remove from HNSW index if enabled
-        if let Some(index) = &mut self.index {
-            // Rebuild index (simple approach - could be optimized)
-            *index = HnswIndex::new(index.stats().dimension, HnswParams::default());
-
-            // Rebuild from remaining entries
-            let entries = self.storage.get_all_entries();
-            for entry in entries {
-                index.add(entry.id.clone(), entry.vector.clone())?;
-            }
+        for id in &ids_to_remove {
+            self.keyword_index.remove_document(id);
         }
 
+        self.remove_from_index(ids_to_remove)?;
+
         Ok(removed_count)
     }
 
-    /// Remove documents older than specified age in days
-    pub fn remove_documents_by_age(&mut self, max_age_days: u64) -> Result<usize> {
-        // Get IDs to remove (need to do this before removal)
-        let now = std::time::SystemTime::now();
-        let cutoff = now
-            .checked_sub(std::time::Duration::from_secs(max_age_days * 24 * 60 * 60))
-            .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+    /// Number of documents a predicate matches
+    pub fn count_documents_where(&self, matches: impl Fn(&Document) -> bool) -> usize {
+        self.storage
+            .get_entries()
+            .iter()
+            .filter(|e| matches(&e.document))
+            .count()
+    }
 
-        let _ids_to_remove: Vec<String> = self
+    /// Copies of the documents a predicate matches, with their embeddings
+    pub fn documents_with_vectors_where(
+        &self,
+        matches: impl Fn(&Document) -> bool,
+    ) -> Result<Vec<(Document, Vec<f32>)>> {
+        self.storage
+            .get_entries()
+            .iter()
+            .filter(|e| matches(&e.document))
+            .map(|e| Ok((e.document.clone(), self.storage.entry_vector(e)?.values)))
+            .collect()
+    }
+
+    /// Documents a predicate matches, one at a time, for streaming them out
+    ///
+    /// Each comes with the time it was indexed and, with `with_vectors`, its embedding.
+    pub fn stream_documents_where<'a>(
+        &'a self,
+        matches: impl Fn(&Document, SystemTime) -> bool + 'a,
+        with_vectors: bool,
+    ) -> impl Iterator<Item = Result<(&'a Document, SystemTime, Option<Vec<f32>>)>> + 'a {
+        self.storage
+            .get_entries()
+            .iter()
+            .filter(move |e| matches(&e.document, e.indexed_at))
+            .map(move |e| {
+                let vector = match with_vectors {
+                    true => Some(self.storage.entry_vector(e)?.values),
+                    false => None,
+                };
+                Ok((&e.document, e.indexed_at, vector))
+            })
+    }
+
+    /// Remove the documents a predicate matches
+    pub fn remove_documents_where(&mut self, matches: impl Fn(&Document) -> bool) -> Result<usize> {
+        let ids_to_remove: HashSet<String> = self
             .storage
             .get_entries()
             .iter()
-            .filter(|e| e.document.metadata.last_updated.unwrap_or(e.indexed_at) <= cutoff)
+            .filter(|e| matches(&e.document))
             .map(|e| e.id.clone())
             .collect();
+        if ids_to_remove.is_empty() {
+            return Ok(0);
+        }
 
-        // Remove from storage
-        let removed_count = self.storage.remove_documents_by_age(max_age_days)?;
+        let removed_count = self.storage.remove_documents(&ids_to_remove)?;
+        for id in &ids_to_remove {
+            self.keyword_index.remove_document(id);
+        }
+        self.remove_from_index(ids_to_remove)?;
 
-        // Remove from HNSW index if enabled
-        if let Some(index) = &mut self.index {
-            // Rebuild index (simple approach - could be optimized)
-            *index = HnswIndex::new(index.stats().dimension, HnswParams::default());
+        Ok(removed_count)
+    }
 
-            // Rebuild from remaining entries
-            let entries = self.storage.get_all_entries();
-            for entry in entries {
-                index.add(entry.id.clone(), entry.vector.clone())?;
+    /// Rebuild the index, if enabled, from the remaining entries
+    fn rebuild_index(&mut self) -> Result<()> {
+        if let Some(index) = &mut self.index {
+            index.clear()?;
+            for entry in self.storage.get_all_entries() {
+                index.add(
+                    entry.id.clone(),
+                    self.storage.entry_vector(entry)?,
+                    &entry.document,
+                )?;
             }
+            index.flush()?;
         }
+        Ok(())
+    }
+
+    /// Drop removed entries from the index, rebuilding it if it can't remove points
+    fn remove_from_index(&mut self, mut ids: HashSet<String>) -> Result<()> {
+        // An older entry may be gone while a newer one with the same ID remains
+        let remaining: HashSet<&str> = self
+            .storage
+            .get_entries()
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        ids.retain(|id| !remaining.contains(id.as_str()));
+
+        let removed = match &mut self.index {
+            Some(index) => index.remove(&ids)?,
+            None => true,
+        };
+        if !removed {
+            self.rebuild_index()?;
+        }
+        Ok(())
+    }
+
+    /// URLs of crawled pages indexed by an extractor older than `version`
+    ///
+    /// Chunks without provenance predate it, or weren't crawled, and are left out.
+    pub fn outdated_pages(&self, version: u32) -> Vec<String> {
+        let urls: BTreeSet<&str> = self
+            .storage
+            .get_entries()
+            .iter()
+            .map(|e| &e.document)
+            .filter(|doc| {
+                doc.metadata
+                    .provenance
+                    .as_ref()
+                    .is_some_and(|p| p.extractor_version < version)
+            })
+            .map(|doc| doc.url.as_str())
+            .collect();
+        urls.into_iter().map(str::to_string).collect()
+    }
+
+    /// Blob store hashes of the page snapshots documents refer to
+    pub fn snapshots(&self) -> HashSet<&str> {
+        self.storage
+            .get_entries()
+            .iter()
+            .filter_map(|e| e.document.metadata.provenance.as_ref()?.snapshot.as_deref())
+            .collect()
+    }
+
+    /// IDs of the documents [`remove_documents_by_age`](Self::remove_documents_by_age) would remove
+    pub fn documents_older_than(&self, max_age_days: u64) -> HashSet<String> {
+        self.storage.documents_older_than(max_age_days)
+    }
+
+    /// Remove documents older than specified age in days, returning how many were removed
+    pub fn remove_documents_by_age(&mut self, max_age_days: u64) -> Result<usize> {
+        // Get IDs to remove (need to do this before removal)
+        let ids_to_remove = self.documents_older_than(max_age_days);
+        let removed_count = ids_to_remove.len();
+
+        // Remove from storage
+        self.storage.remove_documents_by_age(max_age_days)?;
+        for id in &ids_to_remove {
+            self.keyword_index.remove_document(id);
+        }
+
+        self.remove_from_index(ids_to_remove)?;
 
         Ok(removed_count)
     }
 
+    /// Number of entries compaction would drop
+    pub fn compactable_entries(&self) -> usize {
+        self.storage.superseded_entry_count()
+    }
+
+    /// Bytes the database occupies on disk
+    pub fn disk_usage(&self) -> u64 {
+        self.storage.disk_usage()
+    }
+
+    /// Rewrite storage without dead weight, rebuild the indexes tightly and save
+    pub fn compact(&mut self) -> Result<CompactionReport> {
+        let bytes_before = self.storage.disk_usage();
+
+        let entries_dropped = self.storage.compact()?;
+        self.keyword_index =
+            BM25Index::from_storage(&self.storage, self.keyword_index.params().clone());
+        // A persistent store already holds only the latest entry for each ID
+        if self.index.as_ref().is_some_and(|index| !index.persistent()) {
+            self.rebuild_index()?;
+        }
+        self.save()?;
+
+        Ok(CompactionReport {
+            entries_dropped,
+            index_nodes: self.index.as_ref().map(|index| index.len()).transpose()?,
+            bytes_before,
+            bytes_after: self.storage.disk_usage(),
+        })
+    }
+
     /// Get HNSW index statistics if available
     pub fn index_stats(&self) -> Option<HnswStats> {
-        self.index.as_ref().map(|idx| idx.stats())
+        self.index.as_ref()?.as_hnsw().map(HnswIndex::stats)
+    }
+
+    /// Name of the index answering vector searches, if any
+    pub fn index_name(&self) -> Option<&'static str> {
+        self.index.as_ref().map(|index| index.name())
+    }
+
+    /// Benchmark the HNSW index on a sample of stored vectors and apply the best ef_search
+    ///
+    /// Returns recall/latency measurements and suggested M/ef values.
+    pub fn tune_index(&mut self, sample_size: usize) -> Result<HnswTuningReport> {
+        match self.index.as_mut().and_then(|index| index.as_hnsw_mut()) {
+            Some(index) => index.tune(sample_size, 10, 0.95),
+            None => Err(CodeRagError::Index(
+                "HNSW indexing is not enabled for this database".to_string(),
+            )),
+        }
     }
 
     /// Get quantizer parameters if available
     pub fn quantizer_params(&self) -> Option<serde_json::Value> {
-        self.quantizer.as_ref().map(|q| q.parameters_json())
+        self.storage.quantizer().map(|q| q.parameters_json())
+    }
+}
+
+/// Content hash at the end of an ID made by `chunk_id`, e.g. "…#install_5f3a9c1e",
+/// ignoring the "-2" suffix `ChunkIds` gives repeated chunks
+fn id_content_hash(id: &str) -> Option<u32> {
+    let (_, hash) = id.rsplit_once('_')?;
+    let hash = hash.split_once('-').map_or(hash, |(hash, _)| hash);
+    if hash.len() != 8 {
+        return None;
     }
+    u32::from_str_radix(hash, 16).ok()
 }
@@ -0,0 +1,452 @@
+//! [`VectorStore`] backed by a Qdrant collection, over its REST API.
+//!
+//! Points are keyed by a UUID derived from the document ID, which is kept in
+//! the payload along with the URL. Additions are buffered and upserted in
+//! batches; the collection is created on the first write, with cosine
+//! distance and the dimension of the first vector.
+//!
+//! Requests are blocking, as the [`VectorStore`] trait is synchronous. Called
+//! from an async task on a multi-threaded runtime, they run via
+//! `block_in_place` so the worker thread is handed off instead of stalled.
+
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::store::VectorStore;
+use crate::vectordb::types::{Document, Vector, VectorId};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Points sent per upsert request
+const BATCH_SIZE: usize = 256;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A Qdrant collection holding the vectors of one database
+pub struct QdrantStore {
+    agent: ureq::Agent,
+    url: String,
+    collection: String,
+    api_key: Option<String>,
+    /// Points added but not yet upserted
+    pending: Mutex<Vec<Value>>,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct CountResult {
+    count: usize,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    score: f32,
+    #[serde(default)]
+    payload: Option<Value>,
+}
+
+impl QdrantStore {
+    /// Store using `collection` on the Qdrant server at `url`, e.g. "http://localhost:6333"
+    pub fn new(url: &str, collection: &str, api_key: Option<String>) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            url: url.trim_end_matches('/').to_string(),
+            collection: collection.to_string(),
+            api_key,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Collection name for a database file, so project and global databases don't share one
+    pub fn default_collection(db_path: &Path) -> String {
+        let path = db_path
+            .canonicalize()
+            .unwrap_or_else(|_| db_path.to_path_buf());
+        let hash = Sha256::digest(path.to_string_lossy().as_bytes());
+        format!("coderag_{:x}", hash)[..24].to_string()
+    }
+
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(
+            method,
+            &format!("{}/collections/{}{}", self.url, self.collection, path),
+        );
+        match &self.api_key {
+            Some(key) => request.set("api-key", key),
+            None => request,
+        }
+    }
+
+    /// Send a request to the collection and read its JSON response
+    ///
+    /// Returns `None` when the collection doesn't exist (404).
+    fn call(&self, method: &str, path: &str, body: Option<Value>) -> Result<Option<Value>> {
+        let request = self.request(method, path);
+        blocking(|| {
+            let response = match body {
+                Some(body) => request.send_json(body),
+                None => request.call(),
+            };
+            match response {
+                Ok(response) => response
+                    .into_json()
+                    .map(Some)
+                    .map_err(|e| CodeRagError::io("Failed to read Qdrant response", e)),
+                Err(ureq::Error::Status(404, _)) => Ok(None),
+                Err(e) => Err(qdrant_error(&self.collection, e)),
+            }
+        })
+    }
+
+    fn send(&self, method: &str, path: &str, body: Value) -> Result<()> {
+        match self.call(method, path, Some(body))? {
+            Some(_) => Ok(()),
+            None => Err(CodeRagError::Index(format!(
+                "Qdrant collection {} doesn't exist",
+                self.collection
+            ))),
+        }
+    }
+
+    /// Upsert the buffered points, creating the collection first if needed
+    fn upsert(&self, points: Vec<Value>) -> Result<()> {
+        if points.is_empty() {
+            return Ok(());
+        }
+        let dimension = points[0]["vector"].as_array().map_or(0, Vec::len);
+        self.ensure_collection(dimension)?;
+        for batch in points.chunks(BATCH_SIZE) {
+            self.send("PUT", "/points?wait=true", json!({ "points": batch }))?;
+        }
+        Ok(())
+    }
+
+    fn ensure_collection(&self, dimension: usize) -> Result<()> {
+        match self.call("GET", "", None)? {
+            Some(_) => Ok(()),
+            None => self.send(
+                "PUT",
+                "",
+                json!({ "vectors": { "size": dimension, "distance": "Cosine" } }),
+            ),
+        }
+    }
+}
+
+impl VectorStore for QdrantStore {
+    fn name(&self) -> &'static str {
+        "qdrant"
+    }
+
+    fn persistent(&self) -> bool {
+        true
+    }
+
+    fn len(&self) -> Result<usize> {
+        self.flush()?;
+        match self.call("POST", "/points/count", Some(json!({ "exact": true })))? {
+            Some(response) => {
+                let count: Response<CountResult> = serde_json::from_value(response)?;
+                Ok(count.result.count)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn add(&mut self, id: VectorId, vector: Vector, document: &Document) -> Result<()> {
+        let pending = self.pending.get_mut().unwrap();
+        pending.push(json!({
+            "id": point_id(&id),
+            "vector": vector.values,
+            "payload": { "doc_id": id, "url": document.url },
+        }));
+        if pending.len() >= BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, ids: &HashSet<VectorId>) -> Result<bool> {
+        self.flush()?;
+        let points: Vec<String> = ids.iter().map(|id| point_id(id)).collect();
+        for batch in points.chunks(BATCH_SIZE) {
+            self.call(
+                "POST",
+                "/points/delete?wait=true",
+                Some(json!({ "points": batch })),
+            )?;
+        }
+        Ok(true)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.pending.get_mut().unwrap().clear();
+        self.call("DELETE", "", None)?;
+        Ok(())
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        ef: Option<usize>,
+    ) -> Result<Vec<(VectorId, f32)>> {
+        self.flush()?;
+        let mut body = json!({
+            "vector": query,
+            "limit": limit,
+            "with_payload": ["doc_id"],
+        });
+        if let Some(ef) = ef {
+            body["params"] = json!({ "hnsw_ef": ef });
+        }
+
+        let points: Vec<ScoredPoint> = match self.call("POST", "/points/search", Some(body))? {
+            Some(response) => {
+                serde_json::from_value::<Response<Vec<ScoredPoint>>>(response)?.result
+            }
+            // Nothing has been written yet
+            None => Vec::new(),
+        };
+
+        Ok(points
+            .into_iter()
+            .filter_map(|point| {
+                let id = point.payload?.get("doc_id")?.as_str()?.to_string();
+                Some((id, point.score))
+            })
+            .collect())
+    }
+
+    fn flush(&self) -> Result<()> {
+        let points = std::mem::take(&mut *self.pending.lock().unwrap());
+        self.upsert(points)
+    }
+}
+
+/// Run blocking I/O without stalling the async runtime that called it, if any
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+/// Qdrant only accepts integers and UUIDs as point IDs
+fn point_id(id: &str) -> String {
+    let hash = Sha256::digest(id.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    uuid::Uuid::from_bytes(bytes).to_string()
+}
+
+fn qdrant_error(collection: &str, error: ureq::Error) -> CodeRagError {
+    let detail = match error {
+        ureq::Error::Status(status, response) => format!(
+            "status {}: {}",
+            status,
+            response.into_string().unwrap_or_default()
+        ),
+        ureq::Error::Transport(transport) => transport.to_string(),
+    };
+    CodeRagError::Index(format!(
+        "Qdrant request for collection {} failed ({})",
+        collection, detail
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectordb::store::tests::check_vector_store;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+
+    /// Points of the one collection a [`MockQdrant`] serves, by point ID
+    type Points = Arc<Mutex<Option<HashMap<String, (Vec<f32>, Value)>>>>;
+
+    /// Minimal in-memory stand-in for the Qdrant REST API
+    struct MockQdrant {
+        url: String,
+    }
+
+    impl MockQdrant {
+        fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let points: Points = Arc::default();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    Self::serve(stream, &points);
+                }
+            });
+            Self { url }
+        }
+
+        fn serve(stream: TcpStream, points: &Points) {
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap();
+            let path = parts.next().unwrap().split('?').next().unwrap();
+            let route = path.split_once("/test").map_or("", |(_, route)| route);
+            let (status, result) = Self::handle(method, route, &body, &mut points.lock().unwrap());
+
+            let response = json!({ "result": result }).to_string();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+
+        fn handle(
+            method: &str,
+            route: &str,
+            body: &Value,
+            collection: &mut Option<HashMap<String, (Vec<f32>, Value)>>,
+        ) -> (u16, Value) {
+            let vector = |value: &Value| -> Vec<f32> {
+                value
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|x| x.as_f64().unwrap() as f32)
+                    .collect()
+            };
+            match (method, route, collection.as_mut()) {
+                ("PUT", "", _) => {
+                    *collection = Some(HashMap::new());
+                    (200, json!(true))
+                }
+                ("DELETE", "", _) => {
+                    *collection = None;
+                    (200, json!(true))
+                }
+                (_, _, None) => (404, Value::Null),
+                ("GET", "", Some(_)) => (200, json!({})),
+                ("PUT", "/points", Some(points)) => {
+                    for point in body["points"].as_array().unwrap() {
+                        let id = point["id"].as_str().unwrap().to_string();
+                        points.insert(id, (vector(&point["vector"]), point["payload"].clone()));
+                    }
+                    (200, json!({}))
+                }
+                ("POST", "/points/count", Some(points)) => (200, json!({ "count": points.len() })),
+                ("POST", "/points/delete", Some(points)) => {
+                    for id in body["points"].as_array().unwrap() {
+                        points.remove(id.as_str().unwrap());
+                    }
+                    (200, json!({}))
+                }
+                ("POST", "/points/search", Some(points)) => {
+                    let query = vector(&body["vector"]);
+                    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                    let mut scored: Vec<(f32, &Value)> = points
+                        .values()
+                        .map(|(v, payload)| {
+                            let dot: f32 = v.iter().zip(&query).map(|(a, b)| a * b).sum();
+                            (dot / (norm(v) * norm(&query)), payload)
+                        })
+                        .collect();
+                    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+                    let limit = body["limit"].as_u64().unwrap() as usize;
+                    let hits: Vec<Value> = scored
+                        .into_iter()
+                        .take(limit)
+                        .map(|(score, payload)| json!({ "score": score, "payload": payload }))
+                        .collect();
+                    (200, json!(hits))
+                }
+                _ => (400, Value::Null),
+            }
+        }
+    }
+
+    #[test]
+    fn test_qdrant_store() -> anyhow::Result<()> {
+        let server = MockQdrant::start();
+        check_vector_store(&mut QdrantStore::new(&server.url, "test", None))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_qdrant_store_in_async_context() -> anyhow::Result<()> {
+        let server = MockQdrant::start();
+        check_vector_store(&mut QdrantStore::new(&server.url, "test", None))
+    }
+
+    #[test]
+    fn test_unreachable_server_falls_back_to_exact_search() -> anyhow::Result<()> {
+        use crate::vectordb::search::SearchOptions;
+        use crate::vectordb::VectorDatabase;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("db.json");
+        let mut db = VectorDatabase::new(&path)?;
+        for (id, embedding) in [("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])] {
+            db.add_document(Document::for_test(id), embedding)?;
+        }
+        db.save()?;
+
+        // Nothing listens on a port freed right after binding it
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let store = QdrantStore::new(&format!("http://127.0.0.1:{}", port), "test", None);
+        let mut db = VectorDatabase::new(&path)?.with_vector_store(Box::new(store));
+        db.load()?;
+
+        let results = db.search(&[1.0, 0.0], SearchOptions::default())?;
+        assert_eq!(results[0].document.id, "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_point_ids_and_collections() {
+        let id = point_id("https://tokio.rs/spawn#chunk_0");
+        assert_eq!(id, point_id("https://tokio.rs/spawn#chunk_0"));
+        assert_ne!(id, point_id("https://tokio.rs/spawn#chunk_1"));
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+
+        let project =
+            QdrantStore::default_collection(Path::new("/work/app/.coderag/vectordb.json"));
+        let global =
+            QdrantStore::default_collection(Path::new("/home/me/.coderag/coderag_vectordb.json"));
+        assert!(project.starts_with("coderag_") && project.len() == 24);
+        assert_ne!(project, global);
+    }
+}
@@ -1,27 +1,32 @@
 // Vector quantization implementation for storage efficiency
 
+use crate::error::{CodeRagError, Result};
 use crate::vectordb::types::Vector;
-use anyhow::Result;
+use rand::seq::index::sample;
 use std::collections::HashMap;
 use tracing::debug;
 
+/// Target number of dimensions per product quantization subspace
+const PQ_SUBVECTOR_DIM: usize = 8;
+/// Number of centroids per subspace codebook (one byte per code)
+const PQ_CENTROIDS: usize = 256;
+/// Lloyd iterations used when training each codebook
+const PQ_KMEANS_ITERATIONS: usize = 20;
+/// Cap on training samples so codebook training stays fast on large corpora
+const PQ_MAX_TRAINING_SAMPLES: usize = 20_000;
+
 /// Quantization method for vectors
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum QuantizationMethod {
     /// No quantization (raw f32 vectors)
+    #[default]
     None,
     /// Simple scalar quantization (8-bit per dimension)
     Scalar8Bit,
-    /// Product quantization (future)
+    /// Product quantization (one byte per subspace of ~8 dimensions)
     ProductQuantization,
 }
 
-impl Default for QuantizationMethod {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 /// Vector quantization to reduce storage requirements
 pub struct VectorQuantizer {
     /// Quantization method in use
@@ -32,6 +37,8 @@ pub struct VectorQuantizer {
     min_values: Option<Vec<f32>>,
     /// Maximum values per dimension (for scalar quantization)
     max_values: Option<Vec<f32>>,
+    /// Codebooks per subspace: [subspace][centroid][subspace dimension] (for product quantization)
+    codebooks: Option<Vec<Vec<Vec<f32>>>>,
     /// Cache of already quantized vectors
     quantization_cache: HashMap<String, Vec<u8>>,
 }
@@ -44,6 +51,7 @@ impl VectorQuantizer {
             dimension,
             min_values: None,
             max_values: None,
+            codebooks: None,
             quantization_cache: HashMap::new(),
         }
     }
@@ -57,17 +65,23 @@ impl VectorQuantizer {
 
         // Validate vectors
         if vectors.is_empty() {
-            anyhow::bail!("Cannot initialize quantizer with empty vector set");
+            return Err(CodeRagError::Quantization(
+                "Cannot initialize quantizer with empty vector set".to_string(),
+            ));
         }
 
         // Check if all vectors have the same dimension
         for vector in vectors {
             if vector.dimension() != self.dimension {
-                anyhow::bail!(
-                    "Vector dimension mismatch: expected {}, got {}",
-                    self.dimension,
-                    vector.dimension()
-                );
+                return Err(CodeRagError::DimensionMismatch {
+                    expected: self.dimension,
+                    actual: vector.dimension(),
+                    message: format!(
+                        "Vector dimension mismatch: expected {}, got {}",
+                        self.dimension,
+                        vector.dimension()
+                    ),
+                });
             }
         }
 
@@ -106,12 +120,50 @@ impl VectorQuantizer {
                 Ok(())
             }
             QuantizationMethod::ProductQuantization => {
-                // TODO: Implement product quantization
-                anyhow::bail!("Product quantization not yet implemented");
+                // Subsample large training sets
+                let mut rng = rand::thread_rng();
+                let samples: Vec<&[f32]> = if vectors.len() > PQ_MAX_TRAINING_SAMPLES {
+                    sample(&mut rng, vectors.len(), PQ_MAX_TRAINING_SAMPLES)
+                        .into_iter()
+                        .map(|i| vectors[i].values.as_slice())
+                        .collect()
+                } else {
+                    vectors.iter().map(|v| v.values.as_slice()).collect()
+                };
+
+                // Train one k-means codebook per subspace
+                let codebooks = (0..self.subspace_count())
+                    .map(|m| {
+                        let range = self.subspace_range(m);
+                        let sub_samples: Vec<&[f32]> =
+                            samples.iter().map(|v| &v[range.clone()]).collect();
+                        train_kmeans(&sub_samples, PQ_CENTROIDS, PQ_KMEANS_ITERATIONS)
+                    })
+                    .collect();
+
+                self.codebooks = Some(codebooks);
+
+                debug!(
+                    "Initialized product quantizer with {} subspaces from {} samples",
+                    self.subspace_count(),
+                    samples.len()
+                );
+                Ok(())
             }
         }
     }
 
+    /// Number of product quantization subspaces for this dimension
+    fn subspace_count(&self) -> usize {
+        self.dimension.div_ceil(PQ_SUBVECTOR_DIM).max(1)
+    }
+
+    /// Dimension range covered by a product quantization subspace
+    fn subspace_range(&self, subspace: usize) -> std::ops::Range<usize> {
+        let count = self.subspace_count();
+        (subspace * self.dimension / count)..((subspace + 1) * self.dimension / count)
+    }
+
     /// Quantize a vector according to the configured method
     pub fn quantize(&mut self, vector: &Vector, id: Option<&str>) -> Result<Vec<u8>> {
         // Check cache first if ID is provided
@@ -123,53 +175,74 @@ impl VectorQuantizer {
 
         // Check dimensions
         if vector.dimension() != self.dimension {
-            anyhow::bail!(
-                "Vector dimension mismatch: expected {}, got {}",
-                self.dimension,
-                vector.dimension()
-            );
+            return Err(CodeRagError::DimensionMismatch {
+                expected: self.dimension,
+                actual: vector.dimension(),
+                message: format!(
+                    "Vector dimension mismatch: expected {}, got {}",
+                    self.dimension,
+                    vector.dimension()
+                ),
+            });
         }
 
-        let result =
-            match self.method {
-                QuantizationMethod::None => {
-                    // Just convert f32 to bytes
-                    let mut bytes = Vec::with_capacity(vector.values.len() * 4);
-                    for &val in &vector.values {
-                        bytes.extend_from_slice(&val.to_le_bytes());
-                    }
-                    bytes
+        let result = match self.method {
+            QuantizationMethod::None => {
+                // Just convert f32 to bytes
+                let mut bytes = Vec::with_capacity(vector.values.len() * 4);
+                for &val in &vector.values {
+                    bytes.extend_from_slice(&val.to_le_bytes());
                 }
-                QuantizationMethod::Scalar8Bit => {
-                    // Ensure we've been initialized
-                    let min_values = self.min_values.as_ref().ok_or_else(|| {
-                        anyhow::anyhow!("Quantizer not initialized with min_values")
-                    })?;
-                    let max_values = self.max_values.as_ref().ok_or_else(|| {
-                        anyhow::anyhow!("Quantizer not initialized with max_values")
-                    })?;
-
-                    // Convert each dimension to 8-bit
-                    let mut bytes = Vec::with_capacity(vector.values.len());
-                    for (i, &val) in vector.values.iter().enumerate() {
-                        let range = max_values[i] - min_values[i];
-                        if range <= 0.0 {
-                            bytes.push(0);
-                            continue;
-                        }
-
-                        let normalized = (val - min_values[i]) / range;
-                        let quantized = (normalized * 255.0).round() as u8;
-                        bytes.push(quantized);
+                bytes
+            }
+            QuantizationMethod::Scalar8Bit => {
+                // Ensure we've been initialized
+                let min_values = self.min_values.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with min_values".to_string(),
+                    )
+                })?;
+                let max_values = self.max_values.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with max_values".to_string(),
+                    )
+                })?;
+
+                // Convert each dimension to 8-bit
+                let mut bytes = Vec::with_capacity(vector.values.len());
+                for (i, &val) in vector.values.iter().enumerate() {
+                    let range = max_values[i] - min_values[i];
+                    if range <= 0.0 {
+                        bytes.push(0);
+                        continue;
                     }
 
-                    bytes
+                    let normalized = (val - min_values[i]) / range;
+                    let quantized = (normalized * 255.0).round() as u8;
+                    bytes.push(quantized);
                 }
-                QuantizationMethod::ProductQuantization => {
-                    // TODO: Implement product quantization
-                    anyhow::bail!("Product quantization not yet implemented");
-                }
-            };
+
+                bytes
+            }
+            QuantizationMethod::ProductQuantization => {
+                // Ensure we've been initialized
+                let codebooks = self.codebooks.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with codebooks".to_string(),
+                    )
+                })?;
+
+                // Encode each subspace as its nearest centroid
+                codebooks
+                    .iter()
+                    .enumerate()
+                    .map(|(m, codebook)| {
+                        let sub = &vector.values[self.subspace_range(m)];
+                        nearest_centroid(codebook, sub) as u8
+                    })
+                    .collect()
+            }
+        };
 
         // Add to cache if ID is provided
         if let Some(id) = id {
@@ -186,11 +259,11 @@ impl VectorQuantizer {
             QuantizationMethod::None => {
                 // Ensure byte length is correct
                 if bytes.len() != self.dimension * 4 {
-                    anyhow::bail!(
+                    return Err(CodeRagError::Quantization(format!(
                         "Byte length mismatch: expected {}, got {}",
                         self.dimension * 4,
                         bytes.len()
-                    );
+                    )));
                 }
 
                 // Convert bytes to f32
@@ -207,22 +280,24 @@ impl VectorQuantizer {
             QuantizationMethod::Scalar8Bit => {
                 // Ensure byte length is correct
                 if bytes.len() != self.dimension {
-                    anyhow::bail!(
+                    return Err(CodeRagError::Quantization(format!(
                         "Byte length mismatch: expected {}, got {}",
                         self.dimension,
                         bytes.len()
-                    );
+                    )));
                 }
 
                 // Ensure we've been initialized
-                let min_values = self
-                    .min_values
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("Quantizer not initialized with min_values"))?;
-                let max_values = self
-                    .max_values
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("Quantizer not initialized with max_values"))?;
+                let min_values = self.min_values.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with min_values".to_string(),
+                    )
+                })?;
+                let max_values = self.max_values.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with max_values".to_string(),
+                    )
+                })?;
 
                 // Convert each byte to f32
                 let mut values = Vec::with_capacity(self.dimension);
@@ -236,8 +311,32 @@ impl VectorQuantizer {
                 Ok(Vector::new(values))
             }
             QuantizationMethod::ProductQuantization => {
-                // TODO: Implement product quantization
-                anyhow::bail!("Product quantization not yet implemented");
+                // Ensure we've been initialized
+                let codebooks = self.codebooks.as_ref().ok_or_else(|| {
+                    CodeRagError::Quantization(
+                        "Quantizer not initialized with codebooks".to_string(),
+                    )
+                })?;
+
+                // Ensure byte length is correct
+                if bytes.len() != codebooks.len() {
+                    return Err(CodeRagError::Quantization(format!(
+                        "Byte length mismatch: expected {}, got {}",
+                        codebooks.len(),
+                        bytes.len()
+                    )));
+                }
+
+                // Concatenate the centroids selected by each code
+                let mut values = Vec::with_capacity(self.dimension);
+                for (codebook, &code) in codebooks.iter().zip(bytes) {
+                    let centroid = codebook.get(code as usize).ok_or_else(|| {
+                        CodeRagError::Quantization("Invalid product quantization code".to_string())
+                    })?;
+                    values.extend_from_slice(centroid);
+                }
+
+                Ok(Vector::new(values))
             }
         }
     }
@@ -247,10 +346,62 @@ impl VectorQuantizer {
         match self.method {
             QuantizationMethod::None => self.dimension * 4, // 4 bytes per f32
             QuantizationMethod::Scalar8Bit => self.dimension, // 1 byte per dimension
-            QuantizationMethod::ProductQuantization => {
-                // TODO: Implement product quantization
-                0
+            QuantizationMethod::ProductQuantization => self.subspace_count(), // 1 byte per subspace
+        }
+    }
+
+    /// Check whether the quantizer has been trained and can encode vectors
+    pub fn is_initialized(&self) -> bool {
+        match self.method {
+            QuantizationMethod::None => true,
+            QuantizationMethod::Scalar8Bit => {
+                self.min_values.is_some() && self.max_values.is_some()
             }
+            QuantizationMethod::ProductQuantization => self.codebooks.is_some(),
+        }
+    }
+
+    /// Prepare a full-precision query for asymmetric distance computation
+    ///
+    /// The query stays in f32 while stored vectors are decoded on the fly, which
+    /// keeps recall close to exact search without materializing f32 vectors.
+    pub fn prepare_query<'a>(&'a self, query: &'a [f32]) -> AsymmetricQuery<'a> {
+        let query_norm = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+        // Precompute ADC lookup tables so scoring a PQ code is just table lookups
+        let pq_tables = match (&self.codebooks, self.method) {
+            (Some(codebooks), QuantizationMethod::ProductQuantization)
+                if query.len() == self.dimension =>
+            {
+                let (dot_table, norm_table) = codebooks
+                    .iter()
+                    .enumerate()
+                    .map(|(m, codebook)| {
+                        let sub = &query[self.subspace_range(m)];
+                        let dots = codebook
+                            .iter()
+                            .map(|c| c.iter().zip(sub).map(|(a, b)| a * b).sum())
+                            .collect();
+                        let norms = codebook
+                            .iter()
+                            .map(|c| c.iter().map(|x| x * x).sum())
+                            .collect();
+                        (dots, norms)
+                    })
+                    .unzip();
+                Some(PqTables {
+                    dot_table,
+                    norm_table,
+                })
+            }
+            _ => None,
+        };
+
+        AsymmetricQuery {
+            quantizer: self,
+            query,
+            query_norm,
+            pq_tables,
         }
     }
 
@@ -290,7 +441,8 @@ impl VectorQuantizer {
                 serde_json::json!({
                     "method": "product_quantization",
                     "dimension": self.dimension,
-                    // TODO: Add product quantization parameters
+                    "subspaces": self.subspace_count(),
+                    "codebooks": self.codebooks,
                 })
             }
         }
@@ -298,19 +450,28 @@ impl VectorQuantizer {
 
     /// Create from JSON parameters
     pub fn from_parameters_json(json: &serde_json::Value) -> Result<Self> {
-        let method_str = json["method"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Missing 'method' field in quantization parameters"))?;
+        let method_str = json["method"].as_str().ok_or_else(|| {
+            CodeRagError::Quantization(
+                "Missing 'method' field in quantization parameters".to_string(),
+            )
+        })?;
 
         let dimension = json["dimension"].as_u64().ok_or_else(|| {
-            anyhow::anyhow!("Missing 'dimension' field in quantization parameters")
+            CodeRagError::Quantization(
+                "Missing 'dimension' field in quantization parameters".to_string(),
+            )
         })? as usize;
 
         let method = match method_str {
             "none" => QuantizationMethod::None,
             "scalar_8bit" => QuantizationMethod::Scalar8Bit,
             "product_quantization" => QuantizationMethod::ProductQuantization,
-            _ => anyhow::bail!("Unknown quantization method: {}", method_str),
+            _ => {
+                return Err(CodeRagError::Quantization(format!(
+                    "Unknown quantization method: {}",
+                    method_str
+                )))
+            }
         };
 
         let mut quantizer = Self::new(method, dimension);
@@ -332,14 +493,185 @@ impl VectorQuantizer {
                 quantizer.min_values = Some(min_values);
                 quantizer.max_values = Some(max_values);
             } else {
-                anyhow::bail!("Missing min_values or max_values for scalar quantization");
+                return Err(CodeRagError::Quantization(
+                    "Missing min_values or max_values for scalar quantization".to_string(),
+                ));
+            }
+        }
+
+        // Load codebooks for product quantization
+        if method == QuantizationMethod::ProductQuantization && !json["codebooks"].is_null() {
+            let codebooks: Vec<Vec<Vec<f32>>> = serde_json::from_value(json["codebooks"].clone())
+                .map_err(|e| {
+                CodeRagError::Quantization(format!("Invalid product quantization codebooks: {}", e))
+            })?;
+
+            if codebooks.len() != quantizer.subspace_count() {
+                return Err(CodeRagError::Quantization(format!(
+                    "Codebook count mismatch: expected {}, got {}",
+                    quantizer.subspace_count(),
+                    codebooks.len()
+                )));
             }
+
+            quantizer.codebooks = Some(codebooks);
         }
 
         Ok(quantizer)
     }
 }
 
+/// Index of the centroid closest (L2) to a subvector
+fn nearest_centroid(codebook: &[Vec<f32>], sub: &[f32]) -> usize {
+    let mut best = 0;
+    let mut best_dist = f32::MAX;
+    for (i, centroid) in codebook.iter().enumerate() {
+        let dist: f32 = centroid.iter().zip(sub).map(|(a, b)| (a - b).powi(2)).sum();
+        if dist < best_dist {
+            best = i;
+            best_dist = dist;
+        }
+    }
+    best
+}
+
+/// Train a k-means codebook on subvectors using Lloyd's algorithm
+fn train_kmeans(samples: &[&[f32]], k: usize, iterations: usize) -> Vec<Vec<f32>> {
+    let k = k.min(samples.len());
+    let dim = samples.first().map(|s| s.len()).unwrap_or(0);
+    let mut rng = rand::thread_rng();
+
+    // Seed centroids with distinct random samples
+    let mut centroids: Vec<Vec<f32>> = sample(&mut rng, samples.len(), k)
+        .into_iter()
+        .map(|i| samples[i].to_vec())
+        .collect();
+
+    let mut assignments = vec![0usize; samples.len()];
+    for _ in 0..iterations {
+        // Assignment step
+        let mut changed = false;
+        for (i, s) in samples.iter().enumerate() {
+            let nearest = nearest_centroid(&centroids, s);
+            if assignments[i] != nearest {
+                assignments[i] = nearest;
+                changed = true;
+            }
+        }
+
+        // Update step
+        let mut sums = vec![vec![0.0f32; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (s, &c) in samples.iter().zip(&assignments) {
+            counts[c] += 1;
+            for (acc, &v) in sums[c].iter_mut().zip(s.iter()) {
+                *acc += v;
+            }
+        }
+
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            if counts[c] > 0 {
+                for (value, sum) in centroid.iter_mut().zip(&sums[c]) {
+                    *value = sum / counts[c] as f32;
+                }
+            } else {
+                // Reseed empty clusters from a random sample
+                let i = sample(&mut rng, samples.len(), 1).index(0);
+                *centroid = samples[i].to_vec();
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    centroids
+}
+
+/// Per-query ADC lookup tables for product quantization: [subspace][centroid]
+struct PqTables {
+    /// Dot product between the query subvector and each centroid
+    dot_table: Vec<Vec<f32>>,
+    /// Squared norm of each centroid
+    norm_table: Vec<Vec<f32>>,
+}
+
+/// Full-precision query paired with a quantizer for scoring quantized codes
+pub struct AsymmetricQuery<'a> {
+    quantizer: &'a VectorQuantizer,
+    query: &'a [f32],
+    query_norm: f32,
+    pq_tables: Option<PqTables>,
+}
+
+impl AsymmetricQuery<'_> {
+    /// Cosine similarity between the query and a quantized vector
+    pub fn cosine_similarity(&self, codes: &[u8]) -> f32 {
+        if self.query.len() != self.quantizer.dimension || self.query_norm == 0.0 {
+            return 0.0;
+        }
+
+        let (dot_product, norm_sq) = match self.quantizer.method {
+            QuantizationMethod::Scalar8Bit => {
+                let (Some(min_values), Some(max_values)) =
+                    (&self.quantizer.min_values, &self.quantizer.max_values)
+                else {
+                    return 0.0;
+                };
+                if codes.len() != self.quantizer.dimension {
+                    return 0.0;
+                }
+
+                let mut dot_product = 0.0;
+                let mut norm_sq = 0.0;
+                for (i, &code) in codes.iter().enumerate() {
+                    let range = max_values[i] - min_values[i];
+                    let value = code as f32 / 255.0 * range + min_values[i];
+                    dot_product += self.query[i] * value;
+                    norm_sq += value * value;
+                }
+                (dot_product, norm_sq)
+            }
+            QuantizationMethod::ProductQuantization => {
+                let Some(tables) = &self.pq_tables else {
+                    return 0.0;
+                };
+                if codes.len() != tables.dot_table.len() {
+                    return 0.0;
+                }
+
+                let mut dot_product = 0.0;
+                let mut norm_sq = 0.0;
+                for (m, &code) in codes.iter().enumerate() {
+                    dot_product += tables.dot_table[m][code as usize];
+                    norm_sq += tables.norm_table[m][code as usize];
+                }
+                (dot_product, norm_sq)
+            }
+            QuantizationMethod::None => match self.quantizer.dequantize(codes) {
+                Ok(vector) => {
+                    let dot_product: f32 = self
+                        .query
+                        .iter()
+                        .zip(&vector.values)
+                        .map(|(a, b)| a * b)
+                        .sum();
+                    let norm_sq: f32 = vector.values.iter().map(|x| x * x).sum();
+                    (dot_product, norm_sq)
+                }
+                Err(_) => return 0.0,
+            },
+        };
+
+        if norm_sq == 0.0 {
+            return 0.0;
+        }
+
+        dot_product / (self.query_norm * norm_sq.sqrt())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -453,4 +785,74 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_asymmetric_similarity() -> Result<()> {
+        let vectors = vec![
+            Vector::new(vec![1.0, 0.1, 0.1]),
+            Vector::new(vec![0.1, 1.0, 0.1]),
+            Vector::new(vec![0.1, 0.1, 1.0]),
+        ];
+
+        let mut quantizer = VectorQuantizer::new(QuantizationMethod::Scalar8Bit, 3);
+        quantizer.initialize(&vectors)?;
+
+        let codes: Vec<Vec<u8>> = vectors
+            .iter()
+            .map(|v| quantizer.quantize(v, None))
+            .collect::<Result<_>>()?;
+
+        let query = [0.9, 0.2, 0.1];
+        let prepared = quantizer.prepare_query(&query);
+
+        for (vector, codes) in vectors.iter().zip(&codes) {
+            let exact = crate::vectordb::cosine_similarity(&query, &vector.values);
+            let approx = prepared.cosine_similarity(codes);
+            assert!((exact - approx).abs() < 0.01, "{} vs {}", exact, approx);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_quantization() -> Result<()> {
+        // 16-dimensional vectors clustered around four directions
+        let vectors: Vec<Vector> = (0..400)
+            .map(|i| {
+                let cluster = i % 4;
+                let values = (0..16)
+                    .map(|d| {
+                        let base = if d % 4 == cluster { 1.0 } else { 0.0 };
+                        base + ((i * 31 + d * 7) % 13) as f32 * 0.01
+                    })
+                    .collect();
+                Vector::new(values)
+            })
+            .collect();
+
+        let mut quantizer = VectorQuantizer::new(QuantizationMethod::ProductQuantization, 16);
+        quantizer.initialize(&vectors)?;
+        assert!(quantizer.is_initialized());
+        assert_eq!(quantizer.quantized_size(), 2);
+
+        // Round trip stays close to the original
+        let codes = quantizer.quantize(&vectors[0], None)?;
+        assert_eq!(codes.len(), 2);
+        let decoded = quantizer.dequantize(&codes)?;
+        let similarity = crate::vectordb::cosine_similarity(&vectors[0].values, &decoded.values);
+        assert!(similarity > 0.95, "similarity {}", similarity);
+
+        // ADC scoring matches scoring against the decoded vector
+        let query = vectors[1].values.clone();
+        let prepared = quantizer.prepare_query(&query);
+        let adc = prepared.cosine_similarity(&codes);
+        let exact = crate::vectordb::cosine_similarity(&query, &decoded.values);
+        assert!((adc - exact).abs() < 1e-4, "{} vs {}", adc, exact);
+
+        // Codebooks survive JSON serialization
+        let mut restored = VectorQuantizer::from_parameters_json(&quantizer.parameters_json())?;
+        assert_eq!(restored.quantize(&vectors[0], None)?, codes);
+
+        Ok(())
+    }
 }
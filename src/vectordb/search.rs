@@ -1,10 +1,64 @@
 //! Similarity search implementation for vector database
 
+use crate::error::Result;
+use crate::vectordb::quantization::AsymmetricQuery;
 use crate::vectordb::storage::VectorStorage;
-use crate::vectordb::types::{ContentType, Document};
-use anyhow::Result;
+use crate::vectordb::types::{chunk_core, ContentType, Document, VectorEntry};
+use crate::vectordb::versions::{Version, VersionRange};
+use rayon::prelude::*;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::warn;
+
+/// Entries scored between checks of the time budget
+const SCAN_BLOCK: usize = 1024;
+
+/// Score bonus of a document updated just now when recency boosting is on
+pub const RECENCY_BOOST: f32 = 0.2;
+
+/// Time budget shared by the stages of one search
+///
+/// Clones share state, so a budget passed through layered and hybrid
+/// searches remembers the first stage that ran past the deadline.
+#[derive(Debug, Clone)]
+pub struct SearchBudget {
+    budget: Duration,
+    deadline: Instant,
+    exceeded_in: Arc<OnceLock<&'static str>>,
+}
+
+impl SearchBudget {
+    /// Start a budget that runs out `budget` from now
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            deadline: Instant::now() + budget,
+            exceeded_in: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Whether the deadline has passed, blaming `stage` if it is the first to notice
+    pub fn check(&self, stage: &'static str) -> bool {
+        if Instant::now() < self.deadline {
+            return false;
+        }
+        if self.exceeded_in.set(stage).is_ok() {
+            warn!(
+                "Search ran past its {} ms budget during {}; returning partial results",
+                self.budget.as_millis(),
+                stage
+            );
+        }
+        true
+    }
+
+    /// Stage during which the budget ran out, if it did
+    pub fn exceeded_in(&self) -> Option<&'static str> {
+        self.exceeded_in.get().copied()
+    }
+}
 
 /// Search options for filtering and limiting results
 #[derive(Debug, Clone)]
@@ -17,6 +71,26 @@ pub struct SearchOptions {
     pub source_filter: Option<String>,
     /// Filter by content type
     pub content_type_filter: Option<ContentType>,
+    /// Per-query HNSW search width override (ignored by brute-force search)
+    pub ef_search: Option<usize>,
+    /// Restrict results to documents from these exact source URLs
+    pub allowed_urls: Option<HashSet<String>>,
+    /// Filter by natural language (e.g. "ja"); documents without one don't match
+    pub lang_filter: Option<String>,
+    /// Filter by section path, e.g. "Configuration > TLS"
+    pub section_filter: Option<String>,
+    /// Filter changelog entries by release; documents without a version don't match
+    pub version_filter: Option<VersionRange>,
+    /// Time budget; once it runs out, remaining work is skipped and partial results returned
+    pub budget: Option<SearchBudget>,
+    /// Score multipliers by source URL prefix; the longest matching prefix applies
+    pub source_weights: Vec<(String, f32)>,
+    /// Boost recently updated documents by up to `RECENCY_BOOST`, halving every this many days
+    pub recency_half_life_days: Option<f32>,
+    /// Drop documents whose URL contains any of these patterns
+    pub exclude_sources: Vec<String>,
+    /// Drop documents carrying any of these tags
+    pub exclude_tags: Vec<String>,
 }
 
 impl Default for SearchOptions {
@@ -26,8 +100,148 @@ impl Default for SearchOptions {
             min_score: None,
             source_filter: None,
             content_type_filter: None,
+            ef_search: None,
+            allowed_urls: None,
+            lang_filter: None,
+            section_filter: None,
+            version_filter: None,
+            budget: None,
+            source_weights: Vec::new(),
+            recency_half_life_days: None,
+            exclude_sources: Vec::new(),
+            exclude_tags: Vec::new(),
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Whether the time budget has run out, blaming `stage` if it is the first to notice
+    pub fn budget_exceeded(&self, stage: &'static str) -> bool {
+        self.budget
+            .as_ref()
+            .is_some_and(|budget| budget.check(stage))
+    }
+
+    /// Multiplier applied to a document's similarity: its feedback boost times its source's weight
+    pub fn score_factor(&self, document: &Document) -> f32 {
+        let source_weight = self
+            .source_weights
+            .iter()
+            .filter(|(prefix, _)| document.url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(1.0, |(_, weight)| *weight);
+        document.metadata.boost_factor() * source_weight * self.recency_factor(document)
+    }
+
+    /// Bonus for a recently updated document; 1.0 without recency boosting or an update time
+    fn recency_factor(&self, document: &Document) -> f32 {
+        let (Some(half_life), Some(updated)) =
+            (self.recency_half_life_days, document.metadata.last_updated)
+        else {
+            return 1.0;
+        };
+        if half_life <= 0.0 {
+            return 1.0;
+        }
+        let age_days = SystemTime::now()
+            .duration_since(updated)
+            .unwrap_or_default()
+            .as_secs_f32()
+            / 86_400.0;
+        1.0 + RECENCY_BOOST * 0.5f32.powf(age_days / half_life)
+    }
+
+    /// Whether a document passes the source, URL, content type, language, section, version and exclusion filters
+    pub fn matches(&self, document: &Document) -> bool {
+        if let Some(ref source_filter) = self.source_filter {
+            if !document.url.contains(source_filter) {
+                return false;
+            }
         }
+
+        if self
+            .exclude_sources
+            .iter()
+            .any(|pattern| document.url.contains(pattern.as_str()))
+            || document
+                .metadata
+                .tags
+                .iter()
+                .any(|tag| self.exclude_tags.contains(tag))
+        {
+            return false;
+        }
+
+        if let Some(ref allowed_urls) = self.allowed_urls {
+            if !allowed_urls.contains(&document.url) {
+                return false;
+            }
+        }
+
+        if let Some(content_type_filter) = self.content_type_filter {
+            // Crawled pages are Documentation, but their chunks with code are examples too
+            let is_code = content_type_filter == ContentType::CodeExample
+                && document.metadata.tags.iter().any(|tag| tag == "has-code");
+            if document.metadata.content_type != content_type_filter && !is_code {
+                return false;
+            }
+        }
+
+        if let Some(ref lang_filter) = self.lang_filter {
+            if !document
+                .metadata
+                .lang
+                .as_deref()
+                .is_some_and(|lang| lang.eq_ignore_ascii_case(lang_filter))
+            {
+                return false;
+            }
+        }
+
+        if let Some(ref section_filter) = self.section_filter {
+            if !section_path_matches(&document.metadata.heading_path, section_filter) {
+                return false;
+            }
+        }
+
+        if let Some(ref version_filter) = self.version_filter {
+            if !document
+                .metadata
+                .version
+                .as_deref()
+                .and_then(Version::parse)
+                .is_some_and(|version| version_filter.matches(&version))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether consecutive headings of a path contain the segments of a section filter
+///
+/// Segments are separated by `>` or `›` and compared case-insensitively, so
+/// "tls" matches any section under a TLS heading and "Configuration > TLS"
+/// only the TLS section below Configuration.
+fn section_path_matches(heading_path: &[String], filter: &str) -> bool {
+    let segments: Vec<String> = filter
+        .split(['>', '›'])
+        .map(|segment| segment.trim().to_lowercase())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.is_empty() {
+        return true;
     }
+
+    let headings: Vec<String> = heading_path.iter().map(|h| h.to_lowercase()).collect();
+    headings.windows(segments.len()).any(|window| {
+        window
+            .iter()
+            .zip(&segments)
+            .all(|(heading, segment)| heading.contains(segment.as_str()))
+    })
 }
 
 /// Search result with similarity score
@@ -79,66 +293,368 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     dot_product / (norm_a * norm_b)
 }
 
+/// Minimum number of entries before the brute-force scan is split across threads
+const PARALLEL_SEARCH_THRESHOLD: usize = 2048;
+
+/// Candidate held in a per-thread top-k heap (index into the storage entries)
+#[derive(Debug, Clone, Copy)]
+struct ScoredEntry {
+    index: usize,
+    score: f32,
+}
+
+impl PartialEq for ScoredEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+    }
+}
+
+impl Eq for ScoredEntry {}
+
+impl PartialOrd for ScoredEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reverse order so the heap top is the worst candidate, handle NaN cases
+        other
+            .score
+            .partial_cmp(&self.score)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Push a candidate into a heap holding at most `limit` best-scoring entries
+fn push_top_k(heap: &mut BinaryHeap<ScoredEntry>, candidate: ScoredEntry, limit: usize) {
+    if heap.len() < limit {
+        heap.push(candidate);
+    } else if let Some(worst) = heap.peek() {
+        if candidate.score > worst.score {
+            heap.pop();
+            heap.push(candidate);
+        }
+    }
+}
+
+/// Query embedding, prepared for asymmetric scoring when storage is quantized
+struct QueryScorer<'a> {
+    query: &'a [f32],
+    quantized: Option<AsymmetricQuery<'a>>,
+}
+
+impl QueryScorer<'_> {
+    fn similarity(&self, entry: &VectorEntry) -> f32 {
+        match (&entry.quantized, &self.quantized) {
+            (Some(codes), Some(prepared)) => prepared.cosine_similarity(codes),
+            _ => cosine_similarity(self.query, &entry.vector.values),
+        }
+    }
+}
+
+/// Score a single entry against the query, returning None if it is filtered out
+fn score_entry(entry: &VectorEntry, scorer: &QueryScorer, options: &SearchOptions) -> Option<f32> {
+    // Apply filters
+    if !options.matches(&entry.document) {
+        return None;
+    }
+
+    // Calculate similarity, adjusted by feedback
+    let score = scorer.similarity(entry) * options.score_factor(&entry.document);
+
+    // Apply minimum score filter
+    if let Some(min_score) = options.min_score {
+        if score < min_score {
+            return None;
+        }
+    }
+
+    Some(score)
+}
+
+/// Score a block of entries starting at `offset` into a top-k heap
+fn scan_block(
+    block: &[VectorEntry],
+    offset: usize,
+    scorer: &QueryScorer,
+    options: &SearchOptions,
+    heap: &mut BinaryHeap<ScoredEntry>,
+) {
+    for (i, entry) in block.iter().enumerate() {
+        if let Some(score) = score_entry(entry, scorer, options) {
+            let index = offset + i;
+            push_top_k(heap, ScoredEntry { index, score }, options.limit);
+        }
+    }
+}
+
+/// Scan entries on the current thread, keeping the top `limit` candidates
+///
+/// Stops after the block during which the time budget runs out.
+fn scan_serial(
+    entries: &[VectorEntry],
+    scorer: &QueryScorer,
+    options: &SearchOptions,
+) -> BinaryHeap<ScoredEntry> {
+    let mut heap = BinaryHeap::with_capacity(options.limit.min(entries.len()) + 1);
+
+    for (block_index, block) in entries.chunks(SCAN_BLOCK).enumerate() {
+        scan_block(block, block_index * SCAN_BLOCK, scorer, options, &mut heap);
+        if options.budget_exceeded("scan") {
+            break;
+        }
+    }
+
+    heap
+}
+
+/// Scan entries with rayon, keeping a top-k heap per thread and merging them
+///
+/// Once the time budget runs out, blocks not yet started are skipped; the
+/// first block is always scanned so there is something to return.
+fn scan_parallel(
+    entries: &[VectorEntry],
+    scorer: &QueryScorer,
+    options: &SearchOptions,
+) -> BinaryHeap<ScoredEntry> {
+    let limit = options.limit;
+    let capacity = limit.min(entries.len()) + 1;
+
+    entries
+        .par_chunks(SCAN_BLOCK)
+        .enumerate()
+        .fold(
+            || BinaryHeap::with_capacity(capacity),
+            |mut heap, (block_index, block)| {
+                if block_index == 0 || !options.budget_exceeded("scan") {
+                    scan_block(block, block_index * SCAN_BLOCK, scorer, options, &mut heap);
+                }
+                heap
+            },
+        )
+        .reduce(
+            || BinaryHeap::with_capacity(capacity),
+            |mut merged, heap| {
+                for candidate in heap {
+                    push_top_k(&mut merged, candidate, limit);
+                }
+                merged
+            },
+        )
+}
+
 /// Search documents in the storage by similarity to query embedding
+///
+/// Small corpora are scanned serially; larger ones are split across the rayon
+/// thread pool so the linear fallback stays usable without an HNSW index.
 pub fn search_documents(
     storage: &VectorStorage,
     query_embedding: &[f32],
     options: SearchOptions,
 ) -> Result<Vec<SearchResult>> {
-    let mut heap = BinaryHeap::new();
-
-    // Search through all entries
-    for entry in storage.get_all_entries() {
-        // Apply filters
-        if let Some(ref source_filter) = options.source_filter {
-            if !entry.document.url.contains(source_filter) {
-                continue;
-            }
-        }
+    if options.limit == 0 {
+        return Ok(Vec::new());
+    }
 
-        if let Some(content_type_filter) = options.content_type_filter {
-            if entry.document.metadata.content_type != content_type_filter {
-                continue;
+    let scorer = QueryScorer {
+        query: query_embedding,
+        quantized: storage
+            .quantizer()
+            .filter(|q| q.is_initialized())
+            .map(|q| q.prepare_query(query_embedding)),
+    };
+
+    let entries = storage.get_all_entries();
+    let heap = if entries.len() >= PARALLEL_SEARCH_THRESHOLD {
+        scan_parallel(entries, &scorer, &options)
+    } else {
+        scan_serial(entries, &scorer, &options)
+    };
+
+    // Extract final results, best first
+    let mut candidates = heap.into_vec();
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+
+    let results = candidates
+        .into_iter()
+        .map(|c| SearchResult {
+            document: entries[c.index].document.clone(),
+            score: c.score,
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Comparison of approximate (HNSW) results against exact search for one query
+#[derive(Debug, Clone)]
+pub struct SearchVerification {
+    /// Number of exact results compared against
+    pub k: usize,
+    /// Fraction of the exact top-k present in the approximate results
+    pub recall_at_k: f32,
+    /// Spearman rank correlation over the exact top-k (missing hits ranked last)
+    pub rank_correlation: f32,
+    /// IDs of exact top-k documents the approximate search missed
+    pub missed: Vec<String>,
+    /// Time spent in the approximate search
+    pub approximate_latency: Duration,
+    /// Time spent in the exact search
+    pub exact_latency: Duration,
+}
+
+/// Compute recall@k and rank correlation of approximate results against exact results
+pub fn verify_results(
+    approximate: &[SearchResult],
+    exact: &[SearchResult],
+    approximate_latency: Duration,
+    exact_latency: Duration,
+) -> SearchVerification {
+    let k = exact.len();
+    let approx_ranks: HashMap<&str, usize> = approximate
+        .iter()
+        .enumerate()
+        .map(|(rank, r)| (r.document.id.as_str(), rank))
+        .collect();
+
+    let mut missed = Vec::new();
+    let mut squared_rank_diff = 0.0f64;
+    for (exact_rank, result) in exact.iter().enumerate() {
+        let approx_rank = match approx_ranks.get(result.document.id.as_str()) {
+            Some(&rank) => rank,
+            None => {
+                missed.push(result.document.id.clone());
+                k
             }
-        }
+        };
+        squared_rank_diff += (exact_rank as f64 - approx_rank as f64).powi(2);
+    }
+
+    let recall_at_k = if k == 0 {
+        1.0
+    } else {
+        (k - missed.len()) as f32 / k as f32
+    };
+
+    let rank_correlation = if k < 2 {
+        recall_at_k
+    } else {
+        let n = k as f64;
+        (1.0 - 6.0 * squared_rank_diff / (n * (n * n - 1.0))) as f32
+    };
 
-        // Calculate similarity
-        let score = cosine_similarity(query_embedding, &entry.vector.values);
+    SearchVerification {
+        k,
+        recall_at_k,
+        rank_correlation,
+        missed,
+        approximate_latency,
+        exact_latency,
+    }
+}
 
-        // Apply minimum score filter
-        if let Some(min_score) = options.min_score {
-            if score < min_score {
-                continue;
+/// Search result labeled with the database layer it came from
+#[derive(Debug, Clone)]
+pub struct LayeredSearchResult {
+    pub result: SearchResult,
+    pub origin: String,
+}
+
+/// Merge results from several database layers into one ranked list
+///
+/// Layers are given in priority order. A document present in more than one
+/// layer is kept once, with its best score; ties go to the earlier layer.
+pub fn merge_layered_results(
+    layers: Vec<(String, Vec<SearchResult>)>,
+    limit: usize,
+) -> Vec<LayeredSearchResult> {
+    let mut merged: Vec<LayeredSearchResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for (origin, results) in layers {
+        for result in results {
+            match positions.get(&result.document.id) {
+                Some(&pos) => {
+                    if result.score > merged[pos].result.score {
+                        merged[pos] = LayeredSearchResult {
+                            result,
+                            origin: origin.clone(),
+                        };
+                    }
+                }
+                None => {
+                    positions.insert(result.document.id.clone(), merged.len());
+                    merged.push(LayeredSearchResult {
+                        result,
+                        origin: origin.clone(),
+                    });
+                }
             }
         }
+    }
 
-        // Add to results
-        heap.push(SearchResult {
-            document: entry.document.clone(),
-            score,
-        });
+    // Stable sort keeps layer priority among equal scores
+    merged.sort_by(|a, b| {
+        b.result
+            .score
+            .partial_cmp(&a.result.score)
+            .unwrap_or(Ordering::Equal)
+    });
+    merged.truncate(limit);
+    merged
+}
+
+/// Collapse hits on copies of the same chunk into one
+///
+/// Hits sharing a canonical ID, or with the same text, merge into the best
+/// ranked of them, whose `aliases` then list the other copies' URLs. Hits
+/// are expected best first and keep their order.
+pub fn collapse_duplicates(hits: Vec<LayeredSearchResult>) -> Vec<LayeredSearchResult> {
+    let mut collapsed: Vec<LayeredSearchResult> = Vec::with_capacity(hits.len());
+    let mut by_canonical: HashMap<String, usize> = HashMap::new();
+    let mut by_text: HashMap<String, usize> = HashMap::new();
 
-        // Keep only top K results for efficiency
-        if heap.len() > options.limit * 2 {
-            // Create temporary vector and sort
-            let mut temp: Vec<_> = heap.into_iter().collect();
-            temp.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
-            temp.truncate(options.limit);
-            heap = temp.into_iter().collect();
+    for hit in hits {
+        let document = &hit.result.document;
+        let canonical = document.canonical_id().to_string();
+        let text = chunk_core(&document.content).to_string();
+        let position = by_canonical
+            .get(&canonical)
+            .or_else(|| by_text.get(&text).filter(|_| !text.is_empty()))
+            .copied();
+
+        match position {
+            Some(position) => {
+                let kept = &mut collapsed[position].result.document;
+                let kept_url = kept.citation_url();
+                let urls = std::iter::once(document.citation_url())
+                    .chain(document.metadata.aliases.iter().cloned());
+                for url in urls {
+                    if url != kept_url && !kept.metadata.aliases.contains(&url) {
+                        kept.metadata.aliases.push(url);
+                    }
+                }
+                by_canonical.entry(canonical).or_insert(position);
+            }
+            None => {
+                by_canonical.insert(canonical, collapsed.len());
+                if !text.is_empty() {
+                    by_text.insert(text, collapsed.len());
+                }
+                collapsed.push(hit);
+            }
         }
     }
 
-    // Extract final results
-    let mut results: Vec<_> = heap.into_iter().collect();
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
-    results.truncate(options.limit);
-
-    Ok(results)
+    collapsed
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
 
     #[test]
     fn test_cosine_similarity() {
@@ -155,4 +671,274 @@ mod tests {
         let v4 = vec![-1.0, 0.0, 0.0];
         assert!((cosine_similarity(&v1, &v4) - -1.0).abs() < 0.0001);
     }
+
+    #[test]
+    fn test_feedback_boost_reorders_results() -> Result<()> {
+        use crate::vectordb::types::FEEDBACK_BOOST_RANGE;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new()?;
+        let mut storage = VectorStorage::new(temp_dir.path().join("vectors.json"))?;
+        for (id, embedding) in [("close", [1.0, 0.1]), ("further", [1.0, 0.3])] {
+            storage.add_document(Document::for_test(id), embedding.to_vec())?;
+        }
+
+        let top = |storage: &VectorStorage| -> Result<String> {
+            let results = search_documents(storage, &[1.0, 0.1], SearchOptions::default())?;
+            Ok(results[0].document.id.clone())
+        };
+        assert_eq!(top(&storage)?, "close");
+
+        // Burying the closest match lets the other one overtake it
+        let boost = storage
+            .get_document_mut("close")
+            .unwrap()
+            .metadata
+            .apply_feedback(false);
+        assert!(boost < 1.0);
+        assert_eq!(top(&storage)?, "further");
+
+        // Boosts stay within bounds however many votes accumulate
+        for _ in 0..50 {
+            storage
+                .get_document_mut("close")
+                .unwrap()
+                .metadata
+                .apply_feedback(false);
+        }
+        let metadata = &storage.get_document("close").unwrap().metadata;
+        assert_eq!(metadata.boost, Some(FEEDBACK_BOOST_RANGE.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lang_filter() -> Result<()> {
+        use crate::vectordb::types::DocumentMetadata;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new()?;
+        let mut storage = VectorStorage::new(temp_dir.path().join("vectors.json"))?;
+        for (id, lang) in [("en", Some("en")), ("ja", Some("ja")), ("unknown", None)] {
+            let doc = Document::for_test(id).with_metadata(DocumentMetadata {
+                lang: lang.map(str::to_string),
+                tags: vec![id.to_string()],
+                ..Default::default()
+            });
+            storage.add_document(doc, vec![1.0, 0.0])?;
+        }
+
+        let ids = |lang: Option<&str>| -> Result<Vec<String>> {
+            let options = SearchOptions {
+                lang_filter: lang.map(str::to_string),
+                ..SearchOptions::default()
+            };
+            let results = search_documents(&storage, &[1.0, 0.0], options)?;
+            Ok(results.into_iter().map(|r| r.document.id).collect())
+        };
+        assert_eq!(ids(Some("JA"))?, ["ja"]);
+        assert_eq!(ids(None)?.len(), 3);
+
+        let options = SearchOptions {
+            exclude_sources: vec!["example.com/en".to_string()],
+            exclude_tags: vec!["ja".to_string()],
+            ..SearchOptions::default()
+        };
+        let results = search_documents(&storage, &[1.0, 0.0], options)?;
+        let ids: Vec<String> = results.into_iter().map(|r| r.document.id).collect();
+        assert_eq!(ids, ["unknown"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_section_path_matches() {
+        let path: Vec<String> = ["Guide", "Configuration", "TLS"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+
+        assert!(section_path_matches(&path, "tls"));
+        assert!(section_path_matches(&path, "Configuration > TLS"));
+        assert!(section_path_matches(&path, "Guide › Configuration"));
+        assert!(!section_path_matches(&path, "Guide > TLS"));
+        assert!(!section_path_matches(&[], "TLS"));
+        assert!(section_path_matches(&[], " > "));
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial() -> Result<()> {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new()?;
+        let mut storage = VectorStorage::new(temp_dir.path().join("vectors.json"))?;
+
+        for i in 0..PARALLEL_SEARCH_THRESHOLD + 500 {
+            let angle = i as f32 * 0.01;
+            let doc = Document::for_test(&format!("doc{}", i))
+                .with_url(&format!("https://example.com/{}", i % 7));
+            storage.add_document(doc, vec![angle.cos(), angle.sin(), 0.5])?;
+        }
+
+        let query = [1.0, 0.2, 0.5];
+        let options = SearchOptions {
+            limit: 25,
+            source_filter: Some("example.com/3".to_string()),
+            ..Default::default()
+        };
+
+        let entries = storage.get_all_entries();
+        let scorer = QueryScorer {
+            query: &query,
+            quantized: None,
+        };
+        let mut serial = scan_serial(entries, &scorer, &options).into_sorted_vec();
+        let mut parallel = scan_parallel(entries, &scorer, &options).into_sorted_vec();
+        serial.sort_by_key(|c| c.index);
+        parallel.sort_by_key(|c| c.index);
+
+        let serial_ids: Vec<_> = serial.iter().map(|c| c.index).collect();
+        let parallel_ids: Vec<_> = parallel.iter().map(|c| c.index).collect();
+        assert_eq!(serial_ids.len(), 25);
+        assert_eq!(serial_ids, parallel_ids);
+
+        // Public entry point returns results best-first
+        let results = search_documents(&storage, &query, options.clone())?;
+        assert_eq!(results.len(), 25);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // An exhausted budget stops both scans after the first block
+        let budget = SearchBudget::new(Duration::ZERO);
+        let options = SearchOptions {
+            budget: Some(budget.clone()),
+            ..options
+        };
+        for heap in [
+            scan_serial(entries, &scorer, &options),
+            scan_parallel(entries, &scorer, &options),
+        ] {
+            assert!(!heap.is_empty());
+            assert!(heap.iter().all(|c| c.index < SCAN_BLOCK));
+        }
+        assert_eq!(budget.exceeded_in(), Some("scan"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_results() {
+        let result = |id: &str, score: f32| SearchResult {
+            document: Document::for_test(id),
+            score,
+        };
+
+        let exact = vec![result("a", 0.9), result("b", 0.8), result("c", 0.7)];
+
+        // Identical results
+        let verification = verify_results(&exact, &exact, Duration::ZERO, Duration::ZERO);
+        assert_eq!(verification.recall_at_k, 1.0);
+        assert!((verification.rank_correlation - 1.0).abs() < 1e-6);
+        assert!(verification.missed.is_empty());
+
+        // One miss and a swap
+        let approximate = vec![result("b", 0.8), result("a", 0.9), result("d", 0.6)];
+        let verification = verify_results(&approximate, &exact, Duration::ZERO, Duration::ZERO);
+        assert!((verification.recall_at_k - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(verification.missed, vec!["c".to_string()]);
+        assert!(verification.rank_correlation < 1.0);
+    }
+
+    #[test]
+    fn test_merge_layered_results() {
+        let result = |id: &str, score: f32| SearchResult {
+            document: Document::for_test(id),
+            score,
+        };
+
+        let project = vec![result("a", 0.9), result("shared", 0.7)];
+        let global = vec![result("b", 0.8), result("shared", 0.7), result("c", 0.1)];
+
+        let merged = merge_layered_results(
+            vec![
+                ("project".to_string(), project),
+                ("global".to_string(), global),
+            ],
+            3,
+        );
+
+        let ids: Vec<&str> = merged
+            .iter()
+            .map(|r| r.result.document.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "shared"]);
+        assert_eq!(merged[1].origin, "global");
+        // Duplicate with equal score stays with the higher priority layer
+        assert_eq!(merged[2].origin, "project");
+    }
+
+    #[test]
+    fn test_recency_factor() {
+        use crate::vectordb::types::DocumentMetadata;
+
+        let updated = |days: u64| {
+            Document::for_test("page").with_metadata(DocumentMetadata {
+                last_updated: SystemTime::now().checked_sub(Duration::from_secs(days * 86_400)),
+                ..Default::default()
+            })
+        };
+
+        let options = SearchOptions {
+            recency_half_life_days: Some(30.0),
+            ..SearchOptions::default()
+        };
+        assert!((options.score_factor(&updated(0)) - (1.0 + RECENCY_BOOST)).abs() < 1e-3);
+        assert!((options.score_factor(&updated(30)) - (1.0 + RECENCY_BOOST / 2.0)).abs() < 1e-3);
+        assert!(options.score_factor(&updated(3650)) < 1.001);
+
+        // Off by default
+        assert_eq!(SearchOptions::default().score_factor(&updated(0)), 1.0);
+    }
+
+    #[test]
+    fn test_collapse_duplicates() {
+        use crate::vectordb::types::DocumentMetadata;
+
+        let hit =
+            |id: &str, url: &str, content: &str, canonical: Option<&str>| LayeredSearchResult {
+                result: SearchResult {
+                    document: Document::for_test(id)
+                        .with_content(content)
+                        .with_url(url)
+                        .with_metadata(DocumentMetadata {
+                            content_type: ContentType::Tutorial,
+                            canonical: canonical.map(str::to_string),
+                            ..Default::default()
+                        }),
+                    score: 0.5,
+                },
+                origin: "global".to_string(),
+            };
+
+        let hits = vec![
+            hit("a_1", "https://a.dev/tour", "Spawn a task.", None),
+            hit("b_1", "https://b.dev/tour", "Spawn a task.", Some("a_1")),
+            hit("c_1", "https://c.dev/intro", "Spawning tasks", None),
+            // Same text without a recorded link, e.g. from another database layer
+            hit(
+                "d_1",
+                "https://d.dev/tour",
+                "...Context\n\nSpawn a task.",
+                None,
+            ),
+        ];
+        let collapsed = collapse_duplicates(hits);
+
+        let ids: Vec<&str> = collapsed
+            .iter()
+            .map(|h| h.result.document.id.as_str())
+            .collect();
+        assert_eq!(ids, ["a_1", "c_1"]);
+        assert_eq!(
+            collapsed[0].result.document.metadata.aliases,
+            ["https://b.dev/tour", "https://d.dev/tour"]
+        );
+    }
 }
@@ -1,16 +1,31 @@
 //! File-based persistence for vector database
+//!
+//! A database is one JSON file, rewritten on every save. With the `sqlite`
+//! feature, a `.sqlite`, `.sqlite3` or `.db` path is stored as a SQLite
+//! file instead, where a save only writes the entries changed since the last.
 
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::quantization::{QuantizationMethod, VectorQuantizer};
+use crate::vectordb::search::cosine_similarity;
 use crate::vectordb::types::{Document, Vector, VectorEntry};
-use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
-use tracing::{debug, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
 
 /// Storage format version for compatibility
 const STORAGE_VERSION: u32 = 1;
 
+/// Number of raw vectors to collect before training the quantizer automatically
+const QUANTIZATION_TRAINING_SIZE: usize = 1000;
+
 /// Storage metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct StorageMetadata {
@@ -18,6 +33,15 @@ struct StorageMetadata {
     created_at: SystemTime,
     last_modified: SystemTime,
     document_count: usize,
+    /// Embedding model the vectors were produced with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Dimension of every stored vector
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dimension: Option<usize>,
+    /// CRC32 of the serialized entries, to detect damage outside CodeRAG
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<u32>,
 }
 
 /// Main storage structure
@@ -25,23 +49,81 @@ struct StorageMetadata {
 struct StorageData {
     metadata: StorageMetadata,
     entries: Vec<VectorEntry>,
+    /// Trained quantizer parameters, if entries are stored quantized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quantization: Option<serde_json::Value>,
+}
+
+/// File format of a database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// One JSON file, rewritten on every save
+    #[default]
+    Json,
+    /// A SQLite file, saved incrementally (needs the `sqlite` feature)
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// Backend for a database path, chosen by its extension
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("sqlite" | "sqlite3" | "db") => Self::Sqlite,
+            _ => Self::Json,
+        }
+    }
+
+    /// Extension of database files created for this backend
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+/// Entries changed since the last save, for backends that save incrementally
+#[derive(Debug, Default)]
+struct PendingWrites {
+    /// IDs whose entries were added, changed or removed
+    ids: HashSet<String>,
+    /// Rewrite every entry, after a change that touched all of them
+    all: bool,
+    /// Reclaim the space freed by compaction
+    vacuum: bool,
 }
 
 /// File-based vector storage implementation
 pub struct VectorStorage {
     data_path: PathBuf,
+    backend: StorageBackend,
     data: StorageData,
-    modified: bool,
+    /// Whether the data has changes not yet saved
+    modified: AtomicBool,
+    quantizer: Option<VectorQuantizer>,
+    /// Modification time of the file as last loaded or saved by this instance
+    disk_modified: Mutex<Option<SystemTime>>,
+    pending: Mutex<PendingWrites>,
 }
 
 impl VectorStorage {
     /// Create a new storage instance
     pub fn new<P: AsRef<Path>>(data_path: P) -> Result<Self> {
         let data_path = data_path.as_ref().to_path_buf();
+        let backend = StorageBackend::for_path(&data_path);
+        if backend == StorageBackend::Sqlite && !cfg!(feature = "sqlite") {
+            return Err(CodeRagError::Unsupported(format!(
+                "{:?} is a SQLite database, but SQLite storage is not compiled in; rebuild with `--features sqlite`",
+                data_path
+            )));
+        }
 
         // Create parent directory if needed
         if let Some(parent) = data_path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent).map_err(|e| {
+                CodeRagError::io(format!("Failed to create directory {:?}", parent), e)
+            })?;
         }
 
         let data = StorageData {
@@ -50,57 +132,384 @@ impl VectorStorage {
                 created_at: SystemTime::now(),
                 last_modified: SystemTime::now(),
                 document_count: 0,
+                model: None,
+                dimension: None,
+                checksum: None,
             },
             entries: Vec::new(),
+            quantization: None,
         };
 
         Ok(Self {
             data_path,
+            backend,
             data,
-            modified: false,
+            modified: AtomicBool::new(false),
+            quantizer: None,
+            disk_modified: Mutex::new(None),
+            pending: Mutex::default(),
         })
     }
 
+    /// Store vectors quantized with the given method
+    ///
+    /// Vectors are kept in f32 until enough samples exist to train the quantizer,
+    /// after which they are encoded and the f32 values are dropped.
+    pub fn enable_quantization(&mut self, method: QuantizationMethod, dimension: usize) {
+        self.quantizer = match method {
+            QuantizationMethod::None => None,
+            _ => Some(VectorQuantizer::new(method, dimension)),
+        };
+    }
+
+    /// Get the active quantizer, if quantized storage is enabled
+    pub fn quantizer(&self) -> Option<&VectorQuantizer> {
+        self.quantizer.as_ref()
+    }
+
+    /// Train the quantizer on stored f32 vectors and encode every raw entry
+    pub fn train_quantizer(&mut self) -> Result<()> {
+        let Some(quantizer) = &mut self.quantizer else {
+            return Ok(());
+        };
+
+        if !quantizer.is_initialized() {
+            let vectors: Vec<Vector> = self
+                .data
+                .entries
+                .iter()
+                .filter(|e| e.quantized.is_none())
+                .map(|e| e.vector.clone())
+                .collect();
+
+            if vectors.is_empty() {
+                return Ok(());
+            }
+
+            quantizer.initialize(&vectors)?;
+            self.data.quantization = Some(quantizer.parameters_json());
+            debug!("Trained quantizer on {} vectors", vectors.len());
+        }
+
+        for entry in &mut self.data.entries {
+            if entry.quantized.is_none() {
+                entry.quantized = Some(quantizer.quantize(&entry.vector, None)?);
+                entry.vector = Vector::new(Vec::new());
+                self.pending.get_mut().unwrap().ids.insert(entry.id.clone());
+                *self.modified.get_mut() = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the full-precision vector for an entry, decoding quantized codes if needed
+    pub fn entry_vector(&self, entry: &VectorEntry) -> Result<Vector> {
+        match (&entry.quantized, &self.quantizer) {
+            (Some(codes), Some(quantizer)) => quantizer.dequantize(codes),
+            (Some(_), None) => Err(CodeRagError::Corrupt(format!(
+                "Entry {} is quantized but no quantizer is loaded",
+                entry.id
+            ))),
+            (None, _) => Ok(entry.vector.clone()),
+        }
+    }
+
+    /// Cosine similarity between a query and an entry, using asymmetric scoring for quantized entries
+    pub fn entry_similarity(&self, query: &[f32], entry: &VectorEntry) -> f32 {
+        match (&entry.quantized, &self.quantizer) {
+            (Some(codes), Some(quantizer)) => {
+                quantizer.prepare_query(query).cosine_similarity(codes)
+            }
+            _ => cosine_similarity(query, &entry.vector.values),
+        }
+    }
+
     /// Load data from persistent storage
+    ///
+    /// Fails if the file is damaged or any entry's vector is corrupt.
     pub fn load(&mut self) -> Result<()> {
-        if self.data_path.exists() {
-            info!("Loading vectors from {:?}", self.data_path);
+        self.load_checked(false).map(|_| ())
+    }
 
-            let contents =
-                fs::read_to_string(&self.data_path).context("Failed to read storage file")?;
+    /// Load data, dropping corrupt entries instead of failing
+    ///
+    /// Returns the number of entries dropped; save to make the repair permanent.
+    pub fn load_repairing(&mut self) -> Result<usize> {
+        self.load_checked(true)
+    }
 
-            self.data =
-                serde_json::from_str(&contents).context("Failed to deserialize storage data")?;
+    fn load_checked(&mut self, repair: bool) -> Result<usize> {
+        let mut dropped = 0;
+        let migrate_from = self.legacy_json_path();
+        if self.data_path.exists() || migrate_from.is_some() {
+            info!("Loading vectors from {:?}", self.data_path);
+
+            match (&migrate_from, self.backend) {
+                (Some(json_path), _) => {
+                    info!(
+                        "Migrating {:?} to {:?}; the JSON file is left in place",
+                        json_path, self.data_path
+                    );
+                    self.data = Self::read_json(json_path)?;
+                }
+                (None, StorageBackend::Json) => self.data = Self::read_json(&self.data_path)?,
+                #[cfg(feature = "sqlite")]
+                (None, StorageBackend::Sqlite) => {
+                    if let Some(data) = sqlite::load(&self.data_path)? {
+                        self.data = data;
+                    }
+                }
+                #[cfg(not(feature = "sqlite"))]
+                (None, StorageBackend::Sqlite) => unreachable!("rejected by VectorStorage::new"),
+            }
+            // A migrated database is written out in full on the next save
+            *self.pending.get_mut().unwrap() = PendingWrites {
+                all: migrate_from.is_some(),
+                ..PendingWrites::default()
+            };
+            *self.modified.get_mut() = migrate_from.is_some();
 
             // Check version compatibility
             if self.data.metadata.version != STORAGE_VERSION {
-                anyhow::bail!(
-                    "Storage version mismatch: expected {}, found {}",
-                    STORAGE_VERSION,
-                    self.data.metadata.version
-                );
+                return Err(CodeRagError::StorageVersion {
+                    expected: STORAGE_VERSION,
+                    found: self.data.metadata.version,
+                });
             }
 
+            // Restore the trained quantizer persisted alongside the entries
+            if let Some(params) = &self.data.quantization {
+                self.quantizer = Some(VectorQuantizer::from_parameters_json(params)?);
+            } else if let Some(quantizer) = &self.quantizer {
+                self.quantizer = Some(VectorQuantizer::new(
+                    quantizer.method(),
+                    quantizer.dimension(),
+                ));
+            }
+
+            dropped = self.validate(repair)?;
             debug!("Loaded {} documents", self.data.entries.len());
+            self.record_disk_modified();
         }
 
+        // Encode any entries still stored in f32
+        self.train_quantizer()?;
+
+        Ok(dropped)
+    }
+
+    /// JSON database a new SQLite database takes its entries from
+    ///
+    /// Switching `database.backend` to sqlite renames the database file, e.g.
+    /// from vectordb.json to vectordb.sqlite, so the JSON file next to it is
+    /// migrated instead of silently starting empty.
+    fn legacy_json_path(&self) -> Option<PathBuf> {
+        if self.backend != StorageBackend::Sqlite || self.data_path.exists() {
+            return None;
+        }
+        Some(self.data_path.with_extension("json")).filter(|path| path.exists())
+    }
+
+    fn read_json(path: &Path) -> Result<StorageData> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| CodeRagError::io(format!("Failed to read storage file {:?}", path), e))?;
+
+        serde_json::from_str(&contents).map_err(|e| {
+            CodeRagError::Corrupt(format!(
+                "Database {:?} is truncated or corrupt and can't be read ({}). \
+                Restore a backup with `coderag restore`, or move the file aside and re-crawl",
+                path, e
+            ))
+        })
+    }
+
+    /// Check the checksum and every entry's vector, dropping bad entries when repairing
+    fn validate(&mut self, repair: bool) -> Result<usize> {
+        let checksum_ok = match self.data.metadata.checksum {
+            Some(expected) => entries_checksum(&self.data.entries)? == expected,
+            None => true,
+        };
+
+        // Files written before the header recorded a dimension use the most common one
+        let dimension = self
+            .data
+            .metadata
+            .dimension
+            .or_else(|| self.quantizer.as_ref().map(|q| q.dimension()))
+            .or_else(|| {
+                let mut counts: HashMap<usize, usize> = HashMap::new();
+                for entry in self.data.entries.iter().filter(|e| e.quantized.is_none()) {
+                    *counts.entry(entry.vector.values.len()).or_default() += 1;
+                }
+                counts
+                    .into_iter()
+                    .max_by_key(|&(_, count)| count)
+                    .map(|(len, _)| len)
+            });
+        self.data.metadata.dimension = dimension;
+
+        let corrupt: Vec<(usize, String)> = self
+            .data
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                self.entry_problem(entry, dimension)
+                    .map(|problem| (index, format!("{} ({})", entry.id, problem)))
+            })
+            .collect();
+        if checksum_ok && corrupt.is_empty() {
+            return Ok(0);
+        }
+
+        if !repair {
+            let mut message = format!("Database {:?} failed validation", self.data_path);
+            if !checksum_ok {
+                message.push_str(": checksum mismatch, the file was changed outside CodeRAG");
+            }
+            if !corrupt.is_empty() {
+                let examples: Vec<&str> = corrupt.iter().take(3).map(|(_, e)| e.as_str()).collect();
+                message.push_str(&format!(
+                    ": {} corrupt entries, e.g. {}",
+                    corrupt.len(),
+                    examples.join(", ")
+                ));
+            }
+            message.push_str(
+                ". Run `coderag --repair stats` to drop corrupt entries, \
+                or restore a backup with `coderag restore`",
+            );
+            return Err(CodeRagError::Corrupt(message));
+        }
+
+        if !checksum_ok {
+            warn!(
+                "Checksum mismatch in {:?}, keeping entries that pass validation",
+                self.data_path
+            );
+        }
+        for (_, problem) in &corrupt {
+            warn!("Dropping corrupt entry {}", problem);
+        }
+        let corrupt_indices: HashSet<usize> = corrupt.iter().map(|(index, _)| *index).collect();
+        let mut index = 0;
+        self.data.entries.retain(|_| {
+            index += 1;
+            !corrupt_indices.contains(&(index - 1))
+        });
+        self.pending.get_mut().unwrap().all = true;
+        *self.modified.get_mut() = true;
+
+        Ok(corrupt.len())
+    }
+
+    /// Why an entry's vector can't be used, if it can't
+    fn entry_problem(&self, entry: &VectorEntry, dimension: Option<usize>) -> Option<String> {
+        match &entry.quantized {
+            Some(_) if self.quantizer.is_none() => {
+                Some("quantized codes without quantizer parameters".to_string())
+            }
+            Some(codes) if codes.is_empty() => Some("empty quantized codes".to_string()),
+            Some(_) => None,
+            None if Some(entry.vector.values.len()) != dimension => Some(format!(
+                "{} dimensions, expected {}",
+                entry.vector.values.len(),
+                dimension.unwrap_or_default()
+            )),
+            None if entry.vector.values.iter().any(|v| !v.is_finite()) => {
+                Some("non-finite vector values".to_string())
+            }
+            None => None,
+        }
+    }
+
+    /// Fail if the stored vectors came from a different embedding model or dimension
+    pub fn check_model(&self, model: &str, dimension: usize) -> Result<()> {
+        let metadata = &self.data.metadata;
+        if let Some(stored) = metadata.model.as_deref().filter(|stored| *stored != model) {
+            return Err(CodeRagError::ModelMismatch {
+                path: self.data_path.clone(),
+                stored: stored.to_string(),
+                configured: model.to_string(),
+            });
+        }
+        if let Some(stored) = metadata.dimension.filter(|stored| *stored != dimension) {
+            return Err(CodeRagError::DimensionMismatch {
+                expected: stored,
+                actual: dimension,
+                message: format!(
+                    "Database {:?} holds {}-dimensional vectors, but embedding model {} produces {}. \
+                    Use the model the database was indexed with, or a new database.",
+                    self.data_path, stored, model, dimension
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Record the embedding model, failing if the vectors came from a different one
+    pub fn bind_model(&mut self, model: &str, dimension: usize) -> Result<()> {
+        self.check_model(model, dimension)?;
+
+        let metadata = &mut self.data.metadata;
+        if metadata.model.as_deref() != Some(model) {
+            metadata.model = Some(model.to_string());
+            *self.modified.get_mut() = true;
+        }
+        metadata.dimension = Some(dimension);
         Ok(())
     }
 
     /// Save data to persistent storage
     pub fn save(&self) -> Result<()> {
+        match self.backend {
+            StorageBackend::Json => self.save_json()?,
+            #[cfg(feature = "sqlite")]
+            StorageBackend::Sqlite => {
+                let mut metadata = self.data.metadata.clone();
+                metadata.last_modified = SystemTime::now();
+                metadata.document_count = self.data.entries.len();
+                metadata.checksum = None;
+                let mut pending = self.pending.lock().unwrap();
+                sqlite::save(
+                    &self.data_path,
+                    &metadata,
+                    &self.data.entries,
+                    self.data.quantization.as_ref(),
+                    &pending,
+                )?;
+                *pending = PendingWrites::default();
+                info!(
+                    "Saved {} documents to {:?}",
+                    self.data.entries.len(),
+                    self.data_path
+                );
+            }
+            #[cfg(not(feature = "sqlite"))]
+            StorageBackend::Sqlite => unreachable!("rejected by VectorStorage::new"),
+        }
+        self.record_disk_modified();
+        self.modified.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn save_json(&self) -> Result<()> {
         // Update metadata
         let mut data = self.data.clone();
         data.metadata.last_modified = SystemTime::now();
         data.metadata.document_count = data.entries.len();
+        data.metadata.checksum = Some(entries_checksum(&data.entries)?);
 
         // Write to temporary file first
         let temp_path = self.data_path.with_extension("tmp");
         let json = serde_json::to_string_pretty(&data)?;
-        fs::write(&temp_path, json)?;
+        fs::write(&temp_path, json)
+            .map_err(|e| CodeRagError::io(format!("Failed to write {:?}", temp_path), e))?;
 
         // Atomic rename
-        fs::rename(&temp_path, &self.data_path)?;
+        fs::rename(&temp_path, &self.data_path)
+            .map_err(|e| CodeRagError::io(format!("Failed to replace {:?}", self.data_path), e))?;
 
         info!(
             "Saved {} documents to {:?}",
@@ -110,19 +519,75 @@ impl VectorStorage {
         Ok(())
     }
 
+    /// Whether another process has saved the file since this instance loaded or saved it
+    pub fn changed_on_disk(&self) -> bool {
+        let current = fs::metadata(&self.data_path)
+            .and_then(|m| m.modified())
+            .ok();
+        current.is_some() && current != *self.disk_modified.lock().unwrap()
+    }
+
+    fn record_disk_modified(&self) {
+        let modified = fs::metadata(&self.data_path)
+            .and_then(|m| m.modified())
+            .ok();
+        *self.disk_modified.lock().unwrap() = modified;
+    }
+
     /// Add a new document with its embedding
     pub fn add_document(&mut self, document: Document, embedding: Vec<f32>) -> Result<String> {
         let id = document.id.clone();
 
+        match self.data.metadata.dimension {
+            Some(dimension) if dimension != embedding.len() => {
+                return Err(CodeRagError::DimensionMismatch {
+                    expected: dimension,
+                    actual: embedding.len(),
+                    message: format!(
+                        "Embedding for {} has {} dimensions, but the database stores {}-dimensional vectors",
+                        id,
+                        embedding.len(),
+                        dimension
+                    ),
+                })
+            }
+            Some(_) => {}
+            None => self.data.metadata.dimension = Some(embedding.len()),
+        }
+
+        let vector = Vector::new(embedding);
+
+        // Encode immediately once the quantizer has been trained
+        let quantized = match &mut self.quantizer {
+            Some(quantizer) if quantizer.is_initialized() => {
+                Some(quantizer.quantize(&vector, None)?)
+            }
+            _ => None,
+        };
+
         let entry = VectorEntry {
             id: id.clone(),
             document,
-            vector: Vector::new(embedding),
+            vector: if quantized.is_some() {
+                Vector::new(Vec::new())
+            } else {
+                vector
+            },
+            quantized,
             indexed_at: SystemTime::now(),
         };
 
         self.data.entries.push(entry);
-        self.modified = true;
+        self.pending.get_mut().unwrap().ids.insert(id.clone());
+        *self.modified.get_mut() = true;
+
+        // Train once enough raw vectors have accumulated
+        if let Some(quantizer) = &self.quantizer {
+            if !quantizer.is_initialized() && self.data.entries.len() >= QUANTIZATION_TRAINING_SIZE
+            {
+                self.train_quantizer()?;
+            }
+        }
 
         Ok(id)
     }
@@ -141,53 +606,168 @@ impl VectorStorage {
             .map(|e| &e.document)
     }
 
+    /// Get a specific document by ID for modification, marking storage as modified
+    pub fn get_document_mut(&mut self, id: &str) -> Option<&mut Document> {
+        let entry = self.data.entries.iter_mut().find(|e| e.id == id)?;
+        self.pending.get_mut().unwrap().ids.insert(entry.id.clone());
+        *self.modified.get_mut() = true;
+        Some(&mut entry.document)
+    }
+
     /// Remove a document by ID
     pub fn remove_document(&mut self, id: &str) -> Result<bool> {
         let original_len = self.data.entries.len();
         self.data.entries.retain(|e| e.id != id);
 
         if self.data.entries.len() < original_len {
-            self.modified = true;
+            self.pending.get_mut().unwrap().ids.insert(id.to_string());
+            *self.modified.get_mut() = true;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Remove the documents with the given IDs
+    pub fn remove_documents(&mut self, ids: &HashSet<String>) -> Result<usize> {
+        let original_len = self.data.entries.len();
+        self.data.entries.retain(|e| !ids.contains(&e.id));
+
+        let removed_count = original_len - self.data.entries.len();
+        if removed_count > 0 {
+            self.pending
+                .get_mut()
+                .unwrap()
+                .ids
+                .extend(ids.iter().cloned());
+            *self.modified.get_mut() = true;
+        }
+
+        Ok(removed_count)
+    }
+
     /// Remove all documents from a specific source URL
     pub fn remove_documents_by_source(&mut self, source_url: &str) -> Result<usize> {
+        let pending = self.pending.get_mut().unwrap();
         let original_len = self.data.entries.len();
-        self.data.entries.retain(|e| e.document.url != source_url);
+        self.data.entries.retain(|e| {
+            let keep = e.document.url != source_url;
+            if !keep {
+                pending.ids.insert(e.id.clone());
+            }
+            keep
+        });
 
         let removed_count = original_len - self.data.entries.len();
         if removed_count > 0 {
-            self.modified = true;
+            *self.modified.get_mut() = true;
         }
 
         Ok(removed_count)
     }
 
+    /// IDs of documents last updated (or indexed, if never updated) at least `max_age_days` ago
+    pub fn documents_older_than(&self, max_age_days: u64) -> HashSet<String> {
+        let cutoff = age_cutoff(max_age_days);
+        self.data
+            .entries
+            .iter()
+            .filter(|e| e.last_changed() <= cutoff)
+            .map(|e| e.id.clone())
+            .collect()
+    }
+
     /// Remove documents older than specified age in days
     pub fn remove_documents_by_age(&mut self, max_age_days: u64) -> Result<usize> {
-        use std::time::Duration;
-
-        let cutoff_time = SystemTime::now()
-            .checked_sub(Duration::from_secs(max_age_days * 24 * 60 * 60))
-            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let cutoff = age_cutoff(max_age_days);
 
+        let pending = self.pending.get_mut().unwrap();
         let original_len = self.data.entries.len();
-        self.data
-            .entries
-            .retain(|e| e.document.metadata.last_updated.unwrap_or(e.indexed_at) > cutoff_time);
+        self.data.entries.retain(|e| {
+            let keep = e.last_changed() > cutoff;
+            if !keep {
+                pending.ids.insert(e.id.clone());
+            }
+            keep
+        });
 
         let removed_count = original_len - self.data.entries.len();
         if removed_count > 0 {
-            self.modified = true;
+            *self.modified.get_mut() = true;
         }
 
         Ok(removed_count)
     }
 
+    /// Number of entries superseded by a later entry with the same ID
+    pub fn superseded_entry_count(&self) -> usize {
+        self.superseded_entries().len()
+    }
+
+    /// Indices of entries superseded by a later entry with the same ID
+    fn superseded_entries(&self) -> HashSet<usize> {
+        let mut seen = HashSet::new();
+        self.data
+            .entries
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, e)| !seen.insert(e.id.as_str()))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Drop the dead weight left behind by re-indexing and deletes
+    ///
+    /// Removes superseded entries, raw vectors kept alongside quantized codes,
+    /// the quantizer's code cache and any temp file left by an interrupted save.
+    /// Returns the number of entries dropped.
+    pub fn compact(&mut self) -> Result<usize> {
+        let superseded = self.superseded_entries();
+        let mut index = 0;
+        self.data.entries.retain(|_| {
+            index += 1;
+            !superseded.contains(&(index - 1))
+        });
+
+        for entry in &mut self.data.entries {
+            if entry.quantized.is_some() && !entry.vector.values.is_empty() {
+                entry.vector = Vector::new(Vec::new());
+            }
+        }
+        self.data.entries.shrink_to_fit();
+
+        if let Some(quantizer) = &mut self.quantizer {
+            quantizer.clear_cache();
+        }
+
+        let temp_path = self.data_path.with_extension("tmp");
+        if temp_path.exists() {
+            fs::remove_file(&temp_path)
+                .map_err(|e| CodeRagError::io("Failed to remove stale temp file", e))?;
+        }
+
+        let pending = self.pending.get_mut().unwrap();
+        pending.all = true;
+        pending.vacuum = true;
+        *self.modified.get_mut() = true;
+        Ok(superseded.len())
+    }
+
+    /// Bytes the storage file and any leftover temp or WAL file occupy on disk
+    pub fn disk_usage(&self) -> u64 {
+        let wal = PathBuf::from(format!("{}-wal", self.data_path.display()));
+        [
+            self.data_path.clone(),
+            self.data_path.with_extension("tmp"),
+            wal,
+        ]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+    }
+
     /// Get total number of documents
     pub fn document_count(&self) -> usize {
         self.data.entries.len()
@@ -198,22 +778,65 @@ impl VectorStorage {
         &self.data.entries
     }
 
+    /// Get mutable access to all chunks stored for a URL
+    pub fn get_documents_by_url_mut(&mut self, url: &str) -> Vec<&mut Document> {
+        let pending = self.pending.get_mut().unwrap();
+        let documents: Vec<&mut Document> = self
+            .data
+            .entries
+            .iter_mut()
+            .filter(|e| e.document.url == url)
+            .map(|e| {
+                pending.ids.insert(e.id.clone());
+                &mut e.document
+            })
+            .collect();
+        if !documents.is_empty() {
+            *self.modified.get_mut() = true;
+        }
+        documents
+    }
+
     /// Clear all documents
     pub fn clear(&mut self) -> Result<()> {
         self.data.entries.clear();
-        self.modified = true;
+        self.data.quantization = None;
+        self.data.metadata.dimension = None;
+        if let Some(quantizer) = &self.quantizer {
+            self.quantizer = Some(VectorQuantizer::new(
+                quantizer.method(),
+                quantizer.dimension(),
+            ));
+        }
+        self.pending.get_mut().unwrap().all = true;
+        *self.modified.get_mut() = true;
         Ok(())
     }
 
     /// Check if data has been modified since last save
     pub fn is_modified(&self) -> bool {
-        self.modified
+        self.modified.load(Ordering::Relaxed)
     }
 }
 
+/// CRC32 of the entries as serialized to disk
+/// Time `max_age_days` ago, or the epoch for ages reaching further back
+fn age_cutoff(max_age_days: u64) -> SystemTime {
+    SystemTime::now()
+        .checked_sub(Duration::from_secs(
+            max_age_days.saturating_mul(24 * 60 * 60),
+        ))
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn entries_checksum(entries: &[VectorEntry]) -> Result<u32> {
+    Ok(crc32fast::hash(&serde_json::to_vec(entries)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
     use tempfile::TempDir;
 
     #[test]
@@ -233,8 +856,8 @@ mod tests {
             metadata: crate::vectordb::types::DocumentMetadata {
                 content_type: crate::vectordb::types::ContentType::Documentation,
                 language: Some("en".to_string()),
-                last_updated: None,
                 tags: vec!["test".to_string()],
+                ..Default::default()
             },
         };
 
@@ -242,14 +865,185 @@ mod tests {
         storage.add_document(doc, embedding)?;
 
         assert_eq!(storage.document_count(), 1);
+        assert!(storage.is_modified());
 
         // Test save and load
         storage.save()?;
+        assert!(!storage.is_modified());
 
         let mut storage2 = VectorStorage::new(&storage_path)?;
         storage2.load()?;
 
         assert_eq!(storage2.document_count(), 1);
+        assert!(!storage2.is_modified());
+
+        // Test removal by ID
+        let ids = HashSet::from(["missing".to_string(), "test1".to_string()]);
+        assert_eq!(storage2.remove_documents(&ids)?, 1);
+        assert_eq!(storage2.document_count(), 0);
+        assert!(storage2.is_modified());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_storage_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage_path = temp_dir.path().join("quantized_vectors.json");
+
+        let mut storage = VectorStorage::new(&storage_path)?;
+        storage.enable_quantization(QuantizationMethod::Scalar8Bit, 3);
+
+        for (i, embedding) in [[1.0, 0.1, 0.1], [0.1, 1.0, 0.1], [0.1, 0.1, 1.0]]
+            .iter()
+            .enumerate()
+        {
+            storage.add_document(Document::for_test(&format!("doc{}", i)), embedding.to_vec())?;
+        }
+
+        // Below the training threshold vectors stay in f32
+        assert!(storage
+            .get_all_entries()
+            .iter()
+            .all(|e| e.quantized.is_none()));
+
+        storage.train_quantizer()?;
+        assert!(storage
+            .get_all_entries()
+            .iter()
+            .all(|e| e.quantized.is_some() && e.vector.values.is_empty()));
+
+        storage.save()?;
+
+        let mut storage2 = VectorStorage::new(&storage_path)?;
+        storage2.load()?;
+        assert!(storage2.quantizer().is_some_and(|q| q.is_initialized()));
+
+        let entry = &storage2.get_all_entries()[0];
+        let decoded = storage2.entry_vector(entry)?;
+        assert!((decoded.values[0] - 1.0).abs() < 0.01);
+        assert!(storage2.entry_similarity(&[1.0, 0.1, 0.1], entry) > 0.99);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_drops_superseded_entries() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage_path = temp_dir.path().join("compact_vectors.json");
+
+        let mut storage = VectorStorage::new(&storage_path)?;
+        // Re-crawling a page adds its chunks again under the same IDs
+        for (id, content) in [("a", "old"), ("b", "only"), ("a", "new")] {
+            let doc = Document::for_test(id).with_content(content);
+            storage.add_document(doc, vec![0.1, 0.2, 0.3])?;
+        }
+        storage.save()?;
+        let bytes_before = storage.disk_usage();
+
+        assert_eq!(storage.superseded_entry_count(), 1);
+        assert_eq!(storage.compact()?, 1);
+        assert_eq!(storage.document_count(), 2);
+        assert_eq!(storage.get_document("a").unwrap().content, "new");
+
+        storage.save()?;
+        assert!(storage.disk_usage() < bytes_before);
+        assert_eq!(storage.superseded_entry_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_documents_by_age() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut storage = VectorStorage::new(temp_dir.path().join("age_vectors.json"))?;
+        let ten_days_ago = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        // Documents without an update time count from when they were indexed
+        for (id, last_updated) in [("old", Some(ten_days_ago)), ("new", None)] {
+            let doc =
+                Document::for_test(id).with_metadata(crate::vectordb::types::DocumentMetadata {
+                    last_updated,
+                    ..Default::default()
+                });
+            storage.add_document(doc, vec![0.1, 0.2, 0.3])?;
+        }
+
+        assert_eq!(
+            storage.documents_older_than(5),
+            HashSet::from(["old".to_string()])
+        );
+        assert!(storage.documents_older_than(u64::MAX).is_empty());
+        assert_eq!(storage.documents_older_than(0).len(), 2);
+
+        assert_eq!(storage.remove_documents_by_age(5)?, 1);
+        assert!(storage.get_document("old").is_none());
+        assert!(storage.get_document("new").is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_detects_and_repairs_corrupt_vectors() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let storage_path = temp_dir.path().join("corrupt_vectors.json");
+
+        let mut storage = VectorStorage::new(&storage_path)?;
+        storage.bind_model("all-MiniLM-L6-v2", 3)?;
+        for id in ["good", "bad"] {
+            let doc = Document::for_test(id);
+            storage.add_document(doc.clone(), vec![0.1, 0.2, 0.3])?;
+            // Embeddings of the wrong dimension are rejected up front
+            assert!(matches!(
+                storage.add_document(doc, vec![0.1, 0.2]),
+                Err(CodeRagError::DimensionMismatch {
+                    expected: 3,
+                    actual: 2,
+                    ..
+                })
+            ));
+        }
+        storage.save()?;
+
+        // The header records the model, so another model can't reuse the vectors
+        let mut reopened = VectorStorage::new(&storage_path)?;
+        reopened.load()?;
+        assert!(reopened.check_model("all-MiniLM-L6-v2", 3).is_ok());
+        assert!(matches!(
+            reopened.check_model("bge-small-en-v1.5", 3),
+            Err(CodeRagError::ModelMismatch { .. })
+        ));
+        assert!(matches!(
+            reopened.check_model("all-MiniLM-L6-v2", 384),
+            Err(CodeRagError::DimensionMismatch { .. })
+        ));
+
+        // Truncate one vector behind the header's back
+        let mut data: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&storage_path)?)?;
+        let entries = data["entries"].as_array_mut().unwrap();
+        let bad = entries.iter_mut().find(|e| e["id"] == "bad").unwrap();
+        bad["vector"]["values"] = serde_json::json!([0.1, 0.2]);
+        fs::write(&storage_path, serde_json::to_string(&data)?)?;
+
+        let mut damaged = VectorStorage::new(&storage_path)?;
+        let err = damaged.load().unwrap_err();
+        assert!(matches!(err, CodeRagError::Corrupt(_)));
+        assert!(err.to_string().contains("--repair"), "{}", err);
+
+        let mut repaired = VectorStorage::new(&storage_path)?;
+        assert_eq!(repaired.load_repairing()?, 1);
+        assert_eq!(repaired.document_count(), 1);
+        assert!(repaired.get_document("good").is_some());
+
+        // Once saved, the repaired database loads cleanly
+        repaired.save()?;
+        VectorStorage::new(&storage_path)?.load()?;
+
+        // Unparseable files point at restoring a backup
+        fs::write(&storage_path, "{\"metadata\": ")?;
+        let err = VectorStorage::new(&storage_path)?.load().unwrap_err();
+        assert!(matches!(err, CodeRagError::Corrupt(_)));
+        assert!(err.to_string().contains("coderag restore"));
 
         Ok(())
     }
@@ -0,0 +1,298 @@
+//! SQLite persistence for [`VectorStorage`](super::VectorStorage).
+//!
+//! Documents, their vectors and a per-source summary live in separate
+//! tables, so a save only rewrites the rows of entries changed since the
+//! last one, in a single transaction. The file runs in WAL mode with a busy
+//! timeout, so readers in other processes aren't blocked by a save and
+//! concurrent writers wait instead of failing.
+
+use super::{PendingWrites, StorageData, StorageMetadata};
+use crate::error::{CodeRagError, Result};
+use crate::vectordb::types::{Vector, VectorEntry};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS metadata (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS documents (
+        id TEXT PRIMARY KEY,
+        url TEXT NOT NULL,
+        document TEXT NOT NULL,
+        indexed_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS documents_url ON documents (url);
+    CREATE TABLE IF NOT EXISTS vectors (
+        id TEXT PRIMARY KEY,
+        vector BLOB NOT NULL,
+        quantized BLOB
+    );
+    CREATE TABLE IF NOT EXISTS sources (
+        url TEXT PRIMARY KEY,
+        documents INTEGER NOT NULL,
+        last_indexed INTEGER NOT NULL
+    );
+";
+
+/// How long a save waits for another process's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn open(path: &Path) -> Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+/// Read a database file; `None` if nothing was ever saved to it
+pub(super) fn load(path: &Path) -> Result<Option<StorageData>> {
+    let connection = open(path)?;
+    let metadata: Option<String> = connection
+        .query_row(
+            "SELECT value FROM metadata WHERE key = 'storage'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(metadata) = metadata else {
+        return Ok(None);
+    };
+    let metadata: StorageMetadata = serde_json::from_str(&metadata)?;
+    let quantization: Option<String> = connection
+        .query_row(
+            "SELECT value FROM metadata WHERE key = 'quantization'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let quantization = quantization
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?;
+
+    let mut statement = connection.prepare(
+        "SELECT d.id, d.document, d.indexed_at, v.vector, v.quantized
+        FROM documents d LEFT JOIN vectors v ON v.id = d.id
+        ORDER BY d.rowid",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, Option<Vec<u8>>>(3)?,
+            row.get::<_, Option<Vec<u8>>>(4)?,
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (id, document, indexed_at, vector, quantized) = row?;
+        let document = serde_json::from_str(&document).map_err(|e| {
+            CodeRagError::Corrupt(format!("Document {} in {:?} is corrupt ({})", id, path, e))
+        })?;
+        entries.push(VectorEntry {
+            id,
+            document,
+            vector: Vector::new(decode_vector(vector.as_deref().unwrap_or_default())),
+            quantized,
+            indexed_at: SystemTime::UNIX_EPOCH + Duration::from_millis(indexed_at.max(0) as u64),
+        });
+    }
+
+    Ok(Some(StorageData {
+        metadata,
+        entries,
+        quantization,
+    }))
+}
+
+/// Write the pending changes to a database file in one transaction
+pub(super) fn save(
+    path: &Path,
+    metadata: &StorageMetadata,
+    entries: &[VectorEntry],
+    quantization: Option<&serde_json::Value>,
+    pending: &PendingWrites,
+) -> Result<()> {
+    let mut connection = open(path)?;
+    let transaction = connection.transaction()?;
+
+    transaction.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('storage', ?1)",
+        [serde_json::to_string(metadata)?],
+    )?;
+    match quantization {
+        Some(params) => transaction.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('quantization', ?1)",
+            [serde_json::to_string(params)?],
+        )?,
+        None => transaction.execute("DELETE FROM metadata WHERE key = 'quantization'", [])?,
+    };
+
+    let written: Vec<&VectorEntry> = if pending.all {
+        transaction.execute_batch("DELETE FROM documents; DELETE FROM vectors;")?;
+        entries.iter().collect()
+    } else {
+        let mut delete_document = transaction.prepare("DELETE FROM documents WHERE id = ?1")?;
+        let mut delete_vector = transaction.prepare("DELETE FROM vectors WHERE id = ?1")?;
+        for id in &pending.ids {
+            delete_document.execute([id])?;
+            delete_vector.execute([id])?;
+        }
+        entries
+            .iter()
+            .filter(|e| pending.ids.contains(&e.id))
+            .collect()
+    };
+
+    // The last entry with an ID supersedes earlier ones; rows are inserted in
+    // entry order so loading them by rowid keeps that order
+    let mut latest: HashMap<&str, &VectorEntry> = HashMap::new();
+    for entry in &written {
+        latest.insert(&entry.id, entry);
+    }
+    let changed = written
+        .into_iter()
+        .filter(|entry| std::ptr::eq(latest[entry.id.as_str()], *entry));
+
+    {
+        let mut insert_document = transaction.prepare(
+            "INSERT INTO documents (id, url, document, indexed_at) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_vector = transaction
+            .prepare("INSERT INTO vectors (id, vector, quantized) VALUES (?1, ?2, ?3)")?;
+        for entry in changed {
+            let indexed_at = entry
+                .indexed_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |age| age.as_millis() as i64);
+            insert_document.execute(params![
+                entry.id,
+                entry.document.url,
+                serde_json::to_string(&entry.document)?,
+                indexed_at
+            ])?;
+            insert_vector.execute(params![
+                entry.id,
+                encode_vector(&entry.vector.values),
+                entry.quantized
+            ])?;
+        }
+    }
+
+    transaction.execute_batch(
+        "DELETE FROM sources;
+        INSERT INTO sources (url, documents, last_indexed)
+            SELECT url, COUNT(*), MAX(indexed_at) FROM documents GROUP BY url;",
+    )?;
+    transaction.commit()?;
+
+    if pending.vacuum {
+        connection.execute_batch("VACUUM")?;
+    }
+    // Fold the WAL back into the main file, so backups and change detection see one file
+    connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
+fn encode_vector(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::VectorStorage;
+    use crate::vectordb::types::{ContentType, Document, DocumentMetadata};
+    use std::collections::HashSet;
+    use tempfile::TempDir;
+
+    fn document(id: &str, url: &str) -> Document {
+        Document::for_test(id).with_url(url)
+    }
+
+    #[test]
+    fn test_incremental_saves() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vectors.sqlite");
+
+        let mut storage = VectorStorage::new(&path).unwrap();
+        storage
+            .add_document(document("a", "https://tokio.rs/a"), vec![1.0, 0.0])
+            .unwrap();
+        storage
+            .add_document(document("b", "https://tokio.rs/b"), vec![0.0, 1.0])
+            .unwrap();
+        storage.save().unwrap();
+
+        // Re-indexing replaces the row, removal deletes it
+        storage
+            .add_document(document("a", "https://tokio.rs/a"), vec![0.5, 0.5])
+            .unwrap();
+        storage
+            .remove_documents(&HashSet::from(["b".to_string()]))
+            .unwrap();
+        storage
+            .add_document(document("c", "https://tokio.rs/c"), vec![0.25, 0.75])
+            .unwrap();
+        storage.save().unwrap();
+
+        let mut reloaded = VectorStorage::new(&path).unwrap();
+        reloaded.load().unwrap();
+        let entries = reloaded.get_all_entries();
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c"]);
+        assert_eq!(entries[0].vector.values, [0.5, 0.5]);
+        assert_eq!(entries[1].document.url, "https://tokio.rs/c");
+
+        reloaded.clear().unwrap();
+        reloaded.save().unwrap();
+        let mut cleared = VectorStorage::new(&path).unwrap();
+        cleared.load().unwrap();
+        assert_eq!(cleared.document_count(), 0);
+    }
+
+    #[test]
+    fn test_json_database_is_migrated() {
+        let dir = TempDir::new().unwrap();
+        let json_path = dir.path().join("vectordb.json");
+        let mut json = VectorStorage::new(&json_path).unwrap();
+        json.add_document(document("a", "https://tokio.rs/a"), vec![1.0, 0.0])
+            .unwrap();
+        json.add_document(document("b", "https://tokio.rs/b"), vec![0.0, 1.0])
+            .unwrap();
+        json.save().unwrap();
+
+        // The first open after switching backends reads the JSON file
+        let path = dir.path().join("vectordb.sqlite");
+        let mut storage = VectorStorage::new(&path).unwrap();
+        storage.load().unwrap();
+        assert_eq!(storage.document_count(), 2);
+        assert!(storage.is_modified());
+        storage.save().unwrap();
+        assert!(path.exists());
+        assert!(json_path.exists());
+
+        // Once saved, the SQLite file is used and the JSON file ignored
+        std::fs::write(&json_path, "not json").unwrap();
+        let mut reloaded = VectorStorage::new(&path).unwrap();
+        reloaded.load().unwrap();
+        let ids: Vec<&str> = reloaded
+            .get_all_entries()
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        assert_eq!(ids, ["a", "b"]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Nearest-neighbour search backends.
+//!
+//! Documents and their vectors always live in [`VectorStorage`](super::VectorStorage),
+//! which keyword search, filters and the MCP tools read. A [`VectorStore`]
+//! holds a copy of the vectors and answers the approximate nearest-neighbour
+//! part of a search: the in-process HNSW graph, or an external engine such
+//! as Qdrant for databases too large to search in one process.
+
+use crate::error::Result;
+use crate::vectordb::indexing::HnswIndex;
+use crate::vectordb::types::{Document, Vector, VectorId};
+use std::collections::HashSet;
+
+/// An index answering nearest-neighbour queries over the stored vectors
+pub trait VectorStore: Send + Sync {
+    /// Short name for logs and traces, e.g. "hnsw"
+    fn name(&self) -> &'static str;
+
+    /// Whether the store keeps its points between runs, so it is only rebuilt
+    /// when it no longer matches the database
+    fn persistent(&self) -> bool {
+        false
+    }
+
+    /// Number of points held
+    fn len(&self) -> Result<usize>;
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Add a point, replacing an earlier one with the same ID
+    fn add(&mut self, id: VectorId, vector: Vector, document: &Document) -> Result<()>;
+
+    /// Remove points; returns false if the store can't and must be rebuilt instead
+    fn remove(&mut self, ids: &HashSet<VectorId>) -> Result<bool>;
+
+    /// Remove every point
+    fn clear(&mut self) -> Result<()>;
+
+    /// The `limit` nearest points to `query` as (ID, cosine similarity)
+    ///
+    /// `ef` widens the search where the engine supports it.
+    fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        ef: Option<usize>,
+    ) -> Result<Vec<(VectorId, f32)>>;
+
+    /// Write out points buffered by `add`
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The HNSW graph, for statistics and tuning
+    fn as_hnsw(&self) -> Option<&HnswIndex> {
+        None
+    }
+
+    fn as_hnsw_mut(&mut self) -> Option<&mut HnswIndex> {
+        None
+    }
+}
+
+impl VectorStore for HnswIndex {
+    fn name(&self) -> &'static str {
+        "hnsw"
+    }
+
+    fn len(&self) -> Result<usize> {
+        Ok(HnswIndex::len(self))
+    }
+
+    fn add(&mut self, id: VectorId, vector: Vector, _document: &Document) -> Result<()> {
+        HnswIndex::add(self, id, vector)
+    }
+
+    fn remove(&mut self, _ids: &HashSet<VectorId>) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        *self = HnswIndex::new(self.stats().dimension, self.params().clone());
+        Ok(())
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        ef: Option<usize>,
+    ) -> Result<Vec<(VectorId, f32)>> {
+        self.search_with_ef(query, limit, ef.unwrap_or(self.params().ef_search))
+    }
+
+    fn as_hnsw(&self) -> Option<&HnswIndex> {
+        Some(self)
+    }
+
+    fn as_hnsw_mut(&mut self) -> Option<&mut HnswIndex> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::vectordb::indexing::HnswParams;
+    use crate::vectordb::search::SearchOptions;
+    use crate::vectordb::types::DocumentMetadata;
+    use crate::vectordb::VectorDatabase;
+    use anyhow::Result;
+
+    fn ids(results: &[(VectorId, f32)]) -> Vec<&str> {
+        results.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    /// Exercise the contract every store must meet
+    pub(crate) fn check_vector_store(store: &mut dyn VectorStore) -> Result<()> {
+        assert!(store.is_empty()?);
+        assert!(store.search(&[1.0, 0.0, 0.0], 5, None)?.is_empty());
+
+        for (id, vector) in [
+            ("a", [1.0, 0.0, 0.0]),
+            ("b", [0.8, 0.6, 0.0]),
+            ("c", [0.0, 0.0, 1.0]),
+        ] {
+            store.add(
+                id.to_string(),
+                Vector::new(vector.to_vec()),
+                &Document::for_test(id),
+            )?;
+        }
+        store.flush()?;
+        assert_eq!(store.len()?, 3);
+
+        let results = store.search(&[1.0, 0.0, 0.0], 2, None)?;
+        assert_eq!(ids(&results), ["a", "b"]);
+        assert!((results[0].1 - 1.0).abs() < 1e-4);
+        assert!((results[1].1 - 0.8).abs() < 1e-4);
+
+        if store.remove(&HashSet::from(["b".to_string()]))? {
+            assert_eq!(store.len()?, 2);
+            let results = store.search(&[1.0, 0.0, 0.0], 2, None)?;
+            assert_eq!(ids(&results), ["a", "c"]);
+        }
+
+        store.clear()?;
+        assert!(store.is_empty()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hnsw_store() -> Result<()> {
+        check_vector_store(&mut HnswIndex::new(3, HnswParams::default()))
+    }
+
+    #[test]
+    fn test_filtered_index_search_fills_the_limit() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut db =
+            VectorDatabase::with_hnsw(dir.path().join("db.json"), 2, HnswParams::default())?;
+
+        // The nearest documents are all filtered out
+        for i in 0..50 {
+            let doc = Document::for_test(&format!("en{}", i)).with_metadata(DocumentMetadata {
+                lang: Some("en".to_string()),
+                ..Default::default()
+            });
+            db.add_document(doc, vec![1.0, i as f32 * 0.001])?;
+        }
+        for i in 0..5 {
+            let doc = Document::for_test(&format!("ja{}", i)).with_metadata(DocumentMetadata {
+                lang: Some("ja".to_string()),
+                ..Default::default()
+            });
+            db.add_document(doc, vec![0.5, 1.0 + i as f32 * 0.1])?;
+        }
+
+        let options = SearchOptions {
+            limit: 3,
+            lang_filter: Some("ja".to_string()),
+            ..Default::default()
+        };
+        let results = db.search(&[1.0, 0.0], options)?;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.document.id.starts_with("ja")));
+        Ok(())
+    }
+}
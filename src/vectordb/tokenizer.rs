@@ -0,0 +1,205 @@
+//! Keyword tokenizer for BM25 tuned for programming documentation
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// English stopwords dropped from keyword search (sorted for binary search)
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "been", "but", "by", "can", "do", "does",
+    "for", "from", "has", "have", "how", "i", "if", "in", "into", "is", "it", "its", "of", "on",
+    "or", "so", "such", "than", "that", "the", "their", "then", "there", "these", "this", "to",
+    "was", "we", "were", "what", "when", "where", "which", "while", "who", "why", "will", "with",
+    "you", "your",
+];
+
+/// Splits text into stemmed keyword tokens
+///
+/// Code identifiers are split into their words (`HashMap` → hash, map;
+/// `read_to_string` → read, string; `Vec::new` → vec, new) and compound
+/// identifiers are also kept whole, so both `hashmap` and `hash map` match.
+/// Chinese, Japanese and Korean text has no spaces between words, so it is
+/// indexed as overlapping character bigrams.
+pub struct Tokenizer {
+    stemmer: Stemmer,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self {
+            stemmer: Stemmer::create(Algorithm::English),
+        }
+    }
+}
+
+impl Tokenizer {
+    /// Tokenize text into lowercase, stemmed terms without stopwords
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_with_positions(text)
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect()
+    }
+
+    /// Tokenize text, pairing each term with its word position
+    ///
+    /// Dropped stopwords still advance the position, and a compound
+    /// identifier shares the position of its first word, so phrases can be
+    /// matched by comparing position offsets.
+    pub fn tokenize_with_positions(&self, text: &str) -> Vec<(usize, String)> {
+        let mut tokens = Vec::new();
+        let mut position = 0;
+
+        // `::`, `.`, `-` and other punctuation separate identifiers
+        for (identifier, cjk) in text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .flat_map(split_cjk_runs)
+        {
+            if cjk {
+                let chars: Vec<char> = identifier.chars().collect();
+                if chars.len() == 1 {
+                    tokens.push((position, identifier.to_string()));
+                }
+                for (offset, pair) in chars.windows(2).enumerate() {
+                    tokens.push((position + offset, pair.iter().collect()));
+                }
+                position += chars.len();
+                continue;
+            }
+
+            let words = split_identifier(identifier);
+
+            // Keep compound identifiers whole as well as split
+            if words.len() > 1 {
+                tokens.push((position, identifier.trim_matches('_').to_lowercase()));
+            }
+
+            for word in words {
+                let word = word.to_lowercase();
+                position += 1;
+                if is_stopword(&word) || (word.len() < 2 && !word.chars().all(char::is_numeric)) {
+                    continue;
+                }
+                tokens.push((position - 1, self.stemmer.stem(&word).into_owned()));
+            }
+        }
+
+        tokens
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul syllables
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+    )
+}
+
+/// Split a segment into runs of CJK and other characters, flagging CJK runs
+fn split_cjk_runs(segment: &str) -> Vec<(&str, bool)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+
+    for (index, c) in segment.char_indices() {
+        let cjk = is_cjk(c);
+        if current.is_some_and(|run| run != cjk) {
+            runs.push((&segment[start..index], !cjk));
+            start = index;
+        }
+        current = Some(cjk);
+    }
+    if let Some(cjk) = current {
+        runs.push((&segment[start..], cjk));
+    }
+
+    runs
+}
+
+pub(crate) fn is_stopword(word: &str) -> bool {
+    STOPWORDS.binary_search(&word).is_ok()
+}
+
+/// Split an identifier on underscores and camelCase boundaries
+///
+/// Acronyms stay together: `HTTPServer` → HTTP, Server.
+fn split_identifier(identifier: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+
+    for part in identifier.split('_').filter(|s| !s.is_empty()) {
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+
+        for i in 1..chars.len() {
+            let (index, c) = chars[i];
+            let prev = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).is_some_and(|(_, n)| n.is_lowercase());
+
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_numeric()
+                    || (prev.is_uppercase() && next_is_lower));
+            if boundary {
+                words.push(&part[start..index]);
+                start = index;
+            }
+        }
+        words.push(&part[start..]);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_identifier() {
+        assert_eq!(split_identifier("HashMap"), ["Hash", "Map"]);
+        assert_eq!(split_identifier("read_to_string"), ["read", "to", "string"]);
+        assert_eq!(split_identifier("HTTPServer"), ["HTTP", "Server"]);
+        assert_eq!(
+            split_identifier("parseJSON2Value"),
+            ["parse", "JSON2", "Value"]
+        );
+        assert_eq!(split_identifier("tokio"), ["tokio"]);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokenizer = Tokenizer::default();
+
+        assert_eq!(
+            tokenizer.tokenize("Serializing the values"),
+            ["serial", "valu"]
+        );
+        assert_eq!(tokenizer.tokenize("serialize"), ["serial"]);
+        assert_eq!(tokenizer.tokenize("Vec::new()"), ["vec", "new"]);
+        assert_eq!(
+            tokenizer.tokenize("use read_to_string"),
+            ["use", "read_to_string", "read", "string"]
+        );
+        assert_eq!(tokenizer.tokenize("HashMap"), ["hashmap", "hash", "map"]);
+        assert!(STOPWORDS.windows(2).all(|w| w[0] < w[1]));
+
+        // Stopwords keep their position so phrases don't match across them
+        assert_eq!(
+            tokenizer.tokenize_with_positions("tokio can spawn_blocking"),
+            [
+                (0, "tokio".to_string()),
+                (2, "spawn_blocking".to_string()),
+                (2, "spawn".to_string()),
+                (3, "block".to_string())
+            ]
+        );
+
+        // CJK text is indexed as bigrams, mixed-in identifiers as words
+        assert_eq!(
+            tokenizer.tokenize("非同期タスク"),
+            ["非同", "同期", "期タ", "タス", "スク"]
+        );
+        assert_eq!(tokenizer.tokenize("tokioで実行"), ["tokio", "で実", "実行"]);
+    }
+}
@@ -58,17 +58,100 @@ impl Vector {
 }
 
 /// Document metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub content_type: ContentType,
     pub language: Option<String>,
+    /// Natural language of the text, e.g. "ja" (`language` is the programming language)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
     pub last_updated: Option<SystemTime>,
     pub tags: Vec<String>,
+    /// Fragment of the nearest heading, for deep links into the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// Headings enclosing the chunk, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heading_path: Vec<String>,
+    /// Multiplicative score adjustment learned from feedback (None = 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boost: Option<f32>,
+    /// Absolute URLs of the links in the chunk
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// Other URLs serving a near-duplicate copy of this page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Simhash fingerprint of the whole page's text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<u64>,
+    /// Release a changelog chunk describes, e.g. "1.2.0"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Date of that release (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_date: Option<String>,
+    /// Net votes of a Q&A answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub votes: Option<i64>,
+    /// URLs of the images described in the chunk
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// ID of an identical chunk indexed earlier from another URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    /// Crawl run and extractor that produced a crawled chunk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Where a crawled chunk came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Provenance {
+    /// ID of the crawl run, shared by every chunk it wrote
+    pub session: String,
+    /// `EXTRACTOR_VERSION` of the extractor and chunker at the time
+    pub extractor_version: u32,
+    /// When the page was fetched
+    pub fetched_at: SystemTime,
+    /// Blob store hash of the page as fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+}
+
+/// Boost multiplier applied per "helpful" vote
+pub const FEEDBACK_BOOST_STEP: f32 = 1.15;
+
+/// Boost multiplier applied per "irrelevant" vote
+pub const FEEDBACK_BURY_STEP: f32 = 0.8;
+
+/// Bounds a document's boost can move between
+pub const FEEDBACK_BOOST_RANGE: (f32, f32) = (0.25, 2.0);
+
+impl DocumentMetadata {
+    /// Score multiplier for this document
+    pub fn boost_factor(&self) -> f32 {
+        self.boost.unwrap_or(1.0)
+    }
+
+    /// Record a feedback vote, returning the new boost
+    pub fn apply_feedback(&mut self, helpful: bool) -> f32 {
+        let step = if helpful {
+            FEEDBACK_BOOST_STEP
+        } else {
+            FEEDBACK_BURY_STEP
+        };
+        let (min, max) = FEEDBACK_BOOST_RANGE;
+        let boost = (self.boost_factor() * step).clamp(min, max);
+        self.boost = Some(boost);
+        boost
+    }
 }
 
 /// Type of content in the document
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ContentType {
+    #[default]
     Documentation,
     CodeExample,
     Tutorial,
@@ -94,6 +177,116 @@ impl Document {
         let end = self.content.len().min(200);
         &self.content[..end]
     }
+
+    /// Headings enclosing this chunk as a breadcrumb, e.g. "Guide › Configuration › TLS"
+    pub fn breadcrumb(&self) -> Option<String> {
+        (!self.metadata.heading_path.is_empty()).then(|| self.metadata.heading_path.join(" › "))
+    }
+
+    /// Page URL with the section anchor, for citing this chunk
+    pub fn citation_url(&self) -> String {
+        match &self.metadata.anchor {
+            Some(anchor) if !self.url.contains('#') => format!("{}#{}", self.url, anchor),
+            _ => self.url.clone(),
+        }
+    }
+
+    /// ID of the chunk search results are collapsed under: its canonical copy, or itself
+    pub fn canonical_id(&self) -> &str {
+        self.metadata.canonical.as_deref().unwrap_or(&self.id)
+    }
+
+    /// Position of this chunk within its page as (index, total), from the `chunk-N-of-M` tag
+    pub fn chunk_position(&self) -> Option<(usize, usize)> {
+        self.metadata.tags.iter().find_map(|tag| {
+            let rest = tag.strip_prefix("chunk-")?;
+            let (index, total) = rest.split_once("-of-")?;
+            Some((index.parse().ok()?, total.parse().ok()?))
+        })
+    }
+}
+
+#[cfg(test)]
+impl Document {
+    /// A document for tests, with placeholder content and URL derived from `id`
+    pub(crate) fn for_test(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            content: format!("Content of {}", id),
+            url: format!("https://example.com/{}", id),
+            title: None,
+            section: None,
+            metadata: DocumentMetadata::default(),
+        }
+    }
+
+    pub(crate) fn with_content(mut self, content: &str) -> Self {
+        self.content = content.to_string();
+        self
+    }
+
+    pub(crate) fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    pub(crate) fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub(crate) fn with_section(mut self, section: &str) -> Self {
+        self.section = Some(section.to_string());
+        self
+    }
+
+    pub(crate) fn with_metadata(mut self, metadata: DocumentMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// Text of a chunk without the `...`-marked overlap context copied from its neighbours
+pub fn chunk_core(content: &str) -> &str {
+    let mut text = content.trim();
+    if text.starts_with("...") {
+        text = text.split_once("\n\n").map_or(text, |(_, rest)| rest);
+    }
+    if text.ends_with("...") {
+        text = text.rsplit_once("\n\n").map_or(text, |(head, _)| head);
+    }
+    text.trim()
+}
+
+/// Join the ordered chunks of a page back into one text
+///
+/// Chunks carry `...`-marked overlap context copied from their neighbours;
+/// that context is dropped so the page reads without repetition.
+pub fn reassemble_chunks(chunks: &[&Document]) -> String {
+    let mut parts = Vec::with_capacity(chunks.len());
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut content = chunk.content.trim();
+
+        // Drop backward context taken from the previous chunk
+        if i > 0 && content.starts_with("...") {
+            content = content.split_once("\n\n").map_or("", |(_, rest)| rest);
+        }
+
+        // Drop forward context taken from the next chunk
+        if i + 1 < chunks.len() && content.ends_with("...") {
+            content = content
+                .rsplit_once("\n\n")
+                .map_or(content, |(head, _)| head);
+        }
+
+        let content = content.trim();
+        if !content.is_empty() {
+            parts.push(content);
+        }
+    }
+
+    parts.join("\n\n")
 }
 
 /// Stored vector entry combining document and embedding
@@ -102,9 +295,22 @@ pub struct VectorEntry {
     pub id: VectorId,
     pub document: Document,
     pub vector: Vector,
+    /// Quantized codes; when present, `vector` is left empty to save memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantized: Option<Vec<u8>>,
     pub indexed_at: SystemTime,
 }
 
+impl VectorEntry {
+    /// When the document last changed, falling back to when it was indexed
+    pub fn last_changed(&self) -> SystemTime {
+        self.document
+            .metadata
+            .last_updated
+            .unwrap_or(self.indexed_at)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,6 +325,28 @@ mod tests {
         assert!((v1.cosine_similarity(&v3) - 0.0).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_reassemble_chunks() {
+        let chunk = |content: &str, i: usize| {
+            Document::for_test(&format!("page_chunk_{}", i))
+                .with_content(content)
+                .with_url("https://example.com/page")
+                .with_metadata(DocumentMetadata {
+                    tags: vec![format!("chunk-{}-of-2", i + 1)],
+                    ..Default::default()
+                })
+        };
+
+        let first = chunk("# Intro\n\nFirst part.\n\nSecond part...", 0);
+        let second = chunk("...First part.\n\nSecond part continues.", 1);
+
+        assert_eq!(first.chunk_position(), Some((1, 2)));
+        assert_eq!(
+            reassemble_chunks(&[&first, &second]),
+            "# Intro\n\nFirst part.\n\nSecond part continues."
+        );
+    }
+
     #[test]
     fn test_vector_normalization() {
         let mut v = Vector::new(vec![3.0, 4.0, 0.0]);
@@ -0,0 +1,197 @@
+//! Release versions of changelog entries.
+//!
+//! Changelog chunks record the version they describe, so a question like
+//! "breaking changes since 1.2" can be answered from the entries after 1.2
+//! only. Versions compare numerically component by component, missing
+//! components count as zero, and a pre-release sorts before its release.
+
+use crate::error::{CodeRagError, Result};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A dotted release version such as 1.2, v0.13.1 or 2.0.0-rc.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    parts: Vec<u64>,
+    pre: Option<String>,
+}
+
+impl Version {
+    /// Parse a version, ignoring a leading `v` and build metadata
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches(['v', 'V']);
+        let text = text.split('+').next().unwrap_or(text);
+        let (numbers, pre) = match text.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            _ => (text, None),
+        };
+        let parts = numbers
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some(Self { parts, pre })
+    }
+
+    fn component(&self, index: usize) -> u64 {
+        self.parts.get(index).copied().unwrap_or(0)
+    }
+
+    /// Whether this version belongs to the series `prefix`, e.g. 1.4.2 to 1.4
+    fn in_series(&self, prefix: &Version) -> bool {
+        (0..prefix.parts.len()).all(|i| self.component(i) == prefix.parts[i])
+            && (prefix.pre.is_none() || self.pre == prefix.pre)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.parts.len().max(other.parts.len());
+        (0..length)
+            .map(|i| self.component(i).cmp(&other.component(i)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.parts.iter().map(u64::to_string).collect();
+        write!(f, "{}", parts.join("."))?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Series,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// A set of version constraints such as ">1.2" or ">=1.0, <2.0"
+///
+/// A bare version (or one prefixed with `=`) matches its whole series, so
+/// "1.4" matches 1.4.0 and 1.4.2 but not 1.5.0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange {
+    constraints: Vec<(Comparison, Version)>,
+}
+
+impl VersionRange {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut constraints = Vec::new();
+        for constraint in text.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let (comparison, version) = [
+                (">=", Comparison::GreaterOrEqual),
+                ("<=", Comparison::LessOrEqual),
+                (">", Comparison::Greater),
+                ("<", Comparison::Less),
+                ("=", Comparison::Series),
+            ]
+            .into_iter()
+            .find_map(|(operator, comparison)| {
+                Some((comparison, constraint.strip_prefix(operator)?))
+            })
+            .unwrap_or((Comparison::Series, constraint));
+
+            let version = Version::parse(version).ok_or_else(|| {
+                CodeRagError::Parse(format!("Invalid version constraint: {}", constraint))
+            })?;
+            constraints.push((comparison, version));
+        }
+
+        if constraints.is_empty() {
+            return Err(CodeRagError::Parse("Empty version range".to_string()));
+        }
+        Ok(Self { constraints })
+    }
+
+    /// Whether a version satisfies every constraint
+    pub fn matches(&self, version: &Version) -> bool {
+        self.constraints
+            .iter()
+            .all(|(comparison, bound)| match comparison {
+                Comparison::Series => version.in_series(bound),
+                Comparison::Greater => version > bound,
+                Comparison::GreaterOrEqual => version >= bound,
+                Comparison::Less => version < bound,
+                Comparison::LessOrEqual => version <= bound,
+            })
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let constraints: Vec<String> = self
+            .constraints
+            .iter()
+            .map(|(comparison, version)| {
+                let operator = match comparison {
+                    Comparison::Series => "",
+                    Comparison::Greater => ">",
+                    Comparison::GreaterOrEqual => ">=",
+                    Comparison::Less => "<",
+                    Comparison::LessOrEqual => "<=",
+                };
+                format!("{}{}", operator, version)
+            })
+            .collect();
+        write!(f, "{}", constraints.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_version_order() {
+        assert!(v("1.10.0") > v("1.9.3"));
+        assert_eq!(v("v1.2").cmp(&v("1.2.0")), Ordering::Equal);
+        assert!(v("2.0.0-rc.1") < v("2.0.0"));
+        assert!(v("2.0.0-alpha") < v("2.0.0-beta"));
+        assert_eq!(v("1.2.3+build.5").to_string(), "1.2.3");
+        assert_eq!(Version::parse("latest"), None);
+    }
+
+    #[test]
+    fn test_version_range() {
+        let since = VersionRange::parse(">1.2").unwrap();
+        assert!(since.matches(&v("1.2.1")));
+        assert!(since.matches(&v("1.10")));
+        assert!(!since.matches(&v("1.2.0")));
+
+        let major = VersionRange::parse(">=1.0, <2").unwrap();
+        assert!(major.matches(&v("1.99.0")));
+        assert!(!major.matches(&v("2.0.0")));
+        assert!(!major.matches(&v("0.9")));
+        assert_eq!(major.to_string(), ">=1.0, <2");
+
+        let series = VersionRange::parse("1.4").unwrap();
+        assert!(series.matches(&v("1.4.2")));
+        assert!(!series.matches(&v("1.5.0")));
+
+        assert!(VersionRange::parse(">one").is_err());
+        assert!(VersionRange::parse(" , ").is_err());
+    }
+}
@@ -2,9 +2,10 @@ use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
+use std::process::Stdio;
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
 use tokio::time::timeout;
 
 #[derive(Parser, Debug)]
@@ -34,17 +35,21 @@ enum Commands {
     ListTools,
     /// Test calling a specific tool
     CallTool {
+        /// Tool name, e.g. search_docs
         #[arg(short, long)]
         tool: String,
-        #[arg(short, long)]
+        /// Tool arguments as a JSON object
+        #[arg(short, long, default_value = "{}")]
         params: String,
     },
     /// Run a full lifecycle test
     Lifecycle,
     /// Send a custom request
     Custom {
+        /// JSON-RPC method, e.g. prompts/list
         #[arg(short, long)]
         method: String,
+        /// Request params as JSON (defaults to {})
         #[arg(short, long)]
         params: Option<String>,
     },
@@ -55,7 +60,9 @@ struct McpRequest {
     jsonrpc: String,
     method: String,
     params: Value,
-    id: u64,
+    /// Notifications have no ID and get no response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -89,6 +96,7 @@ impl McpTestClient {
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()
             .context("Failed to spawn MCP server")?;
 
@@ -99,51 +107,62 @@ impl McpTestClient {
         // Spawn a task to read stderr
         let debug = self.debug;
         tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if debug {
-                        eprintln!("[SERVER STDERR] {}", line);
-                    }
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if debug {
+                    eprintln!("[SERVER STDERR] {}", line);
                 }
             }
         });
 
         let mut responses = Vec::new();
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
+        let mut lines = BufReader::new(stdout).lines();
 
         for request in requests {
+            let request_str = serde_json::to_string(&request)?;
             if self.debug {
-                eprintln!("[CLIENT] Sending: {}", serde_json::to_string(&request)?);
+                eprintln!("[CLIENT] Sending: {}", request_str);
             }
 
             // Send request
-            let request_str = serde_json::to_string(&request)?;
-            writeln!(stdin, "{}", request_str)?;
-            stdin.flush()?;
+            stdin
+                .write_all(format!("{}\n", request_str).as_bytes())
+                .await?;
+            stdin.flush().await?;
+
+            let Some(id) = request.id else {
+                continue;
+            };
 
-            // Read response with timeout
+            // Read the response with this request's ID, skipping server notifications
             let response_fut = async {
                 loop {
-                    if let Some(Ok(line)) = lines.next() {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        if self.debug {
-                            eprintln!("[SERVER] Received: {}", line);
-                        }
-                        return serde_json::from_str::<McpResponse>(&line)
-                            .context("Failed to parse response");
-                    } else {
+                    let Some(line) = lines.next_line().await? else {
                         return Err(anyhow::anyhow!("Server closed connection"));
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    if self.debug {
+                        eprintln!("[SERVER] Received: {}", line);
+                    }
+                    let response = serde_json::from_str::<McpResponse>(&line)
+                        .context("Failed to parse response")?;
+                    if response.id == Some(id) {
+                        return Ok(response);
                     }
                 }
             };
 
             let response = timeout(Duration::from_secs(self.timeout_secs), response_fut)
                 .await
-                .context("Timeout waiting for response")??;
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "Timeout after {}s waiting for {} response",
+                        self.timeout_secs,
+                        request.method
+                    )
+                })??;
 
             responses.push(response);
         }
@@ -151,15 +170,8 @@ impl McpTestClient {
         // Try to gracefully shutdown
         drop(stdin);
 
-        // Wait a bit for the server to exit
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
         // Check exit status
-        match timeout(Duration::from_secs(2), async {
-            cmd.wait().context("Failed to wait for server")
-        })
-        .await
-        {
+        match timeout(Duration::from_secs(2), cmd.wait()).await {
             Ok(Ok(status)) => {
                 if !status.success() {
                     eprintln!("[WARNING] Server exited with status: {}", status);
@@ -168,13 +180,118 @@ impl McpTestClient {
             Ok(Err(e)) => eprintln!("[ERROR] Failed to get exit status: {}", e),
             Err(_) => {
                 eprintln!("[WARNING] Server did not exit within timeout, killing...");
-                let _ = cmd.kill();
+                let _ = cmd.kill().await;
             }
         }
 
         Ok(responses)
     }
 
+    /// Initialize a session, then send one request and return its response
+    async fn request_after_init(&self, method: &str, params: Value) -> Result<McpResponse> {
+        let requests = vec![
+            McpRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "initialize".to_string(),
+                params: json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "clientInfo": { "name": "mcp-test-client", "version": "0.1.0" }
+                }),
+                id: Some(1),
+            },
+            McpRequest {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/initialized".to_string(),
+                params: json!({}),
+                id: None,
+            },
+            McpRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params,
+                id: Some(2),
+            },
+        ];
+
+        let mut responses = self.run_test(requests).await?;
+        if let Some(error) = &responses[0].error {
+            return Err(anyhow::anyhow!("Initialization failed: {}", error));
+        }
+        Ok(responses.pop().expect("one response per request"))
+    }
+
+    async fn test_call_tool(&self, tool: &str, params: &str) -> Result<()> {
+        let arguments: Value = serde_json::from_str(params)
+            .with_context(|| format!("Invalid JSON in --params: {}", params))?;
+        if !arguments.is_object() {
+            return Err(anyhow::anyhow!(
+                "--params must be a JSON object of tool arguments, got: {}",
+                arguments
+            ));
+        }
+        println!("🧪 Calling tool {} with {}", tool, arguments);
+
+        let response = self
+            .request_after_init(
+                "tools/call",
+                json!({ "name": tool, "arguments": arguments }),
+            )
+            .await?;
+        if let Some(error) = &response.error {
+            print_error(error);
+            return Err(anyhow::anyhow!("Tool call failed"));
+        }
+        let result = response.result.unwrap_or_default();
+
+        if self.debug {
+            println!(
+                "\n📥 Raw result:\n{}",
+                serde_json::to_string_pretty(&result)?
+            );
+        }
+        let contents = result
+            .get("content")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for content in &contents {
+            print_content(content)?;
+        }
+
+        if result.get("isError").and_then(|e| e.as_bool()) == Some(true) {
+            println!("\n❌ Tool returned an error");
+            return Err(anyhow::anyhow!("Tool {} returned an error", tool));
+        }
+        println!(
+            "\n✅ Tool call succeeded ({} content items)",
+            contents.len()
+        );
+        Ok(())
+    }
+
+    async fn test_custom(&self, method: &str, params: Option<&str>) -> Result<()> {
+        let params: Value = match params {
+            Some(params) => serde_json::from_str(params)
+                .with_context(|| format!("Invalid JSON in --params: {}", params))?,
+            None => json!({}),
+        };
+        println!("🧪 Sending {} with {}", method, params);
+
+        let response = self.request_after_init(method, params).await?;
+        if let Some(error) = &response.error {
+            print_error(error);
+            return Err(anyhow::anyhow!("Request failed"));
+        }
+
+        println!(
+            "\n📥 Result:\n{}",
+            serde_json::to_string_pretty(&response.result.unwrap_or_default())?
+        );
+        println!("\n✅ Request succeeded");
+        Ok(())
+    }
+
     async fn test_init(&self) -> Result<()> {
         println!("🧪 Testing initialization sequence...");
 
@@ -186,13 +303,13 @@ impl McpTestClient {
                     "protocolVersion": "2024-11-05",
                     "capabilities": {}
                 }),
-                id: 1,
+                id: Some(1),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
                 method: "initialized".to_string(),
                 params: json!({}),
-                id: 2,
+                id: Some(2),
             },
         ];
 
@@ -226,19 +343,19 @@ impl McpTestClient {
                     "protocolVersion": "2024-11-05",
                     "capabilities": {}
                 }),
-                id: 1,
+                id: Some(1),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
                 method: "initialized".to_string(),
                 params: json!({}),
-                id: 2,
+                id: Some(2),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
                 method: "tools/list".to_string(),
                 params: json!({}),
-                id: 3,
+                id: Some(3),
             },
         ];
 
@@ -270,19 +387,19 @@ impl McpTestClient {
                     "protocolVersion": "2024-11-05",
                     "capabilities": {}
                 }),
-                id: 1,
+                id: Some(1),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
                 method: "initialized".to_string(),
                 params: json!({}),
-                id: 2,
+                id: Some(2),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
                 method: "tools/list".to_string(),
                 params: json!({}),
-                id: 3,
+                id: Some(3),
             },
             McpRequest {
                 jsonrpc: "2.0".to_string(),
@@ -291,7 +408,7 @@ impl McpTestClient {
                     "name": "list_docs",
                     "arguments": {}
                 }),
-                id: 4,
+                id: Some(4),
             },
         ];
 
@@ -318,6 +435,68 @@ impl McpTestClient {
     }
 }
 
+/// Print a JSON-RPC error with its code, message and any data
+fn print_error(error: &Value) {
+    let code = error
+        .get("code")
+        .and_then(|c| c.as_i64())
+        .unwrap_or_default();
+    let message = error
+        .get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default();
+    println!("\n❌ Error {}: {}", code, message);
+    if let Some(data) = error.get("data").filter(|d| !d.is_null()) {
+        println!("   Data: {}", data);
+    }
+}
+
+/// Print one tool result content item, pretty-printing JSON text
+fn print_content(content: &Value) -> Result<()> {
+    match content.get("type").and_then(|t| t.as_str()) {
+        Some("text") => {
+            let text = content
+                .get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default();
+            match serde_json::from_str::<Value>(text) {
+                Ok(json) if json.is_object() || json.is_array() => {
+                    println!("\n📄 {}", serde_json::to_string_pretty(&json)?)
+                }
+                _ => println!("\n📄 {}", text),
+            }
+        }
+        // Structured content repeats the text fallback, so only its URI is shown
+        Some("resource") => {
+            let resource = content.get("resource").unwrap_or(&Value::Null);
+            println!(
+                "\n📎 Resource {} ({})",
+                resource.get("uri").and_then(|u| u.as_str()).unwrap_or("?"),
+                resource
+                    .get("mimeType")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown type")
+            );
+        }
+        Some("image") => {
+            let data = content
+                .get("data")
+                .and_then(|d| d.as_str())
+                .unwrap_or_default();
+            println!(
+                "\n🖼️  Image ({}, {} base64 bytes)",
+                content
+                    .get("mimeType")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown type"),
+                data.len()
+            );
+        }
+        _ => println!("\n❓ {}", serde_json::to_string_pretty(content)?),
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -335,12 +514,10 @@ async fn main() -> Result<()> {
     match args.command {
         Commands::Init => client.test_init().await?,
         Commands::ListTools => client.test_list_tools().await?,
-        Commands::CallTool { tool, params } => {
-            todo!("Implement tool call test");
-        }
+        Commands::CallTool { tool, params } => client.test_call_tool(&tool, &params).await?,
         Commands::Lifecycle => client.test_lifecycle().await?,
         Commands::Custom { method, params } => {
-            todo!("Implement custom request");
+            client.test_custom(&method, params.as_deref()).await?
         }
     }
 
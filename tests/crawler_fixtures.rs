@@ -0,0 +1,135 @@
+//! Extraction of real documentation pages, replayed from tests/fixtures/http.
+//!
+//! Needs the `test-fixtures` feature. Re-record the pages with
+//! `scripts/record-fixtures.sh` and commit the files it writes.
+
+use coderag::crawler::fetch::ClientOptions;
+use coderag::crawler::fixtures::HttpFixtures;
+use coderag::crawler::{CrawlConfig, CrawlMode, Crawler, UrlPatterns};
+use std::path::Path;
+
+fn replay(url: &str, mode: CrawlMode, include: &str) -> CrawlConfig {
+    let fixtures =
+        HttpFixtures::replay(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/http"));
+    let host = url::Url::parse(url)
+        .unwrap()
+        .host_str()
+        .unwrap()
+        .to_string();
+    CrawlConfig {
+        start_url: url.to_string(),
+        mode,
+        max_depth: 1,
+        delay_ms: 0,
+        allowed_domains: [host].into(),
+        url_patterns: UrlPatterns::custom(&[include.to_string()], &[]).unwrap(),
+        client: ClientOptions {
+            fixtures: Some(fixtures),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_docs_rs_function_page() {
+    let config = replay(
+        "https://docs.rs/tokio/latest/tokio/task/fn.spawn.html",
+        CrawlMode::SinglePage,
+        "/tokio/",
+    );
+    let preview = Crawler::new(config).await.unwrap().preview().await.unwrap();
+
+    assert!(preview.extracted.title.contains("spawn"));
+    assert!(preview
+        .extracted
+        .markdown
+        .contains("Spawns a new asynchronous task"));
+    assert!(preview.extracted.markdown.contains("outside"));
+    assert!(preview
+        .extracted
+        .code_blocks
+        .iter()
+        .any(|block| block.code.contains("TcpListener::bind")));
+    assert!(!preview.documents.is_empty());
+    assert!(preview
+        .links
+        .contains(&"https://docs.rs/tokio/latest/tokio/task/struct.JoinHandle.html".to_string()));
+}
+
+#[tokio::test]
+async fn test_unrecorded_pages_fail_without_network() {
+    let config = replay(
+        "https://docs.rs/tokio/latest/tokio/task/fn.yield_now.html",
+        CrawlMode::SinglePage,
+        "/tokio/",
+    );
+    let error = Crawler::new(config)
+        .await
+        .unwrap()
+        .preview()
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("No recorded response"));
+}
+
+#[tokio::test]
+async fn test_section_plan_from_recording() {
+    let config = replay(
+        "https://docs.rs/tokio/latest/tokio/task/fn.spawn.html",
+        CrawlMode::Section,
+        "/tokio/",
+    );
+    let plan = Crawler::new(config)
+        .await
+        .unwrap()
+        .discover()
+        .await
+        .unwrap();
+
+    assert_eq!(plan.urls[0].found_by, "start");
+    assert!(plan
+        .urls
+        .iter()
+        .any(|url| url.url.ends_with("/task/struct.JoinHandle.html") && url.depth == 1));
+}
+
+#[tokio::test]
+#[ignore = "needs a recording: run scripts/record-fixtures.sh"]
+async fn test_mdn_reference_page() {
+    let config = replay(
+        "https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Array/map",
+        CrawlMode::SinglePage,
+        "/en-US/docs/",
+    );
+    let preview = Crawler::new(config).await.unwrap().preview().await.unwrap();
+
+    assert!(preview.extracted.title.contains("Array.prototype.map()"));
+    assert!(preview.extracted.markdown.contains("callbackFn"));
+    assert!(preview
+        .extracted
+        .code_blocks
+        .iter()
+        .any(|block| block.code.contains(".map(")));
+    assert!(!preview.documents.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs a recording: run scripts/record-fixtures.sh"]
+async fn test_read_the_docs_page() {
+    let config = replay(
+        "https://requests.readthedocs.io/en/latest/user/quickstart/",
+        CrawlMode::SinglePage,
+        "/en/latest/",
+    );
+    let preview = Crawler::new(config).await.unwrap().preview().await.unwrap();
+
+    assert!(preview.extracted.title.contains("Quickstart"));
+    assert!(preview.extracted.markdown.contains("Make a Request"));
+    assert!(preview
+        .extracted
+        .code_blocks
+        .iter()
+        .any(|block| block.code.contains("requests.get(")));
+    assert!(!preview.documents.is_empty());
+}
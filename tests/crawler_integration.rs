@@ -1,4 +1,4 @@
-use coderag::crawler::{CrawlConfig, CrawlMode, Crawler, DocumentationFocus};
+use coderag::crawler::{CrawlConfig, CrawlMode, Crawler, DocumentationFocus, UrlPattern};
 use coderag::embedding_basic::EmbeddingService;
 use coderag::vectordb::VectorDatabase;
 use std::collections::HashSet;
@@ -63,13 +63,17 @@ async fn test_crawler_config_defaults() {
 async fn test_url_pattern_defaults() {
     let config = CrawlConfig::default();
     let patterns = &config.url_patterns;
+    let matches = |list: &[UrlPattern], path: &str| {
+        let url = format!("https://example.com{}page", path);
+        list.iter().any(|pattern| pattern.matches(&url))
+    };
 
     // Check include patterns
-    assert!(patterns.include.contains(&"/docs/".to_string()));
-    assert!(patterns.include.contains(&"/api/".to_string()));
-    assert!(patterns.include.contains(&"/guide/".to_string()));
+    assert!(matches(&patterns.include, "/docs/"));
+    assert!(matches(&patterns.include, "/api/"));
+    assert!(matches(&patterns.include, "/guide/"));
 
     // Check exclude patterns
-    assert!(patterns.exclude.contains(&"/blog/".to_string()));
-    assert!(patterns.exclude.contains(&"/forum/".to_string()));
+    assert!(matches(&patterns.exclude, "/blog/"));
+    assert!(matches(&patterns.exclude, "/forum/"));
 }
@@ -1,12 +1,11 @@
 //! Integration tests for enhanced vector database
 
-use crate::vectordb::{
-    ChunkingStrategy, ContentType, Document, DocumentMetadata, EnhancedChunker, HnswParams,
-    HybridSearchOptions, QuantizationMethod, VectorDatabase,
-};
 use anyhow::Result;
-use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use coderag::vectordb::{
+    ChunkingStrategy, Document, DocumentMetadata, EnhancedChunker, HnswParams, HybridSearchOptions,
+    QuantizationMethod, SearchOptions, VectorDatabase,
+};
+use std::time::SystemTime;
 use tempfile::TempDir;
 
 /// Create a test document
@@ -18,10 +17,10 @@ fn create_test_document(id: &str, content: &str, url: &str) -> Document {
         title: Some(format!("Title {}", id)),
         section: None,
         metadata: DocumentMetadata {
-            content_type: ContentType::Documentation,
             language: Some("en".to_string()),
             last_updated: Some(SystemTime::now()),
             tags: vec!["test".to_string()],
+            ..Default::default()
         },
     }
 }
@@ -69,11 +68,9 @@ async fn test_vector_db_with_hnsw() -> Result<()> {
 
     // Test search
     let query = vec![0.9, 0.1, 0.1]; // Similar to document 1 (Rust)
-    let options = crate::vectordb::SearchOptions {
+    let options = SearchOptions {
         limit: 2,
-        min_score: None,
-        source_filter: None,
-        content_type_filter: None,
+        ..SearchOptions::default()
     };
 
     let results = db.search(&query, options)?;
@@ -89,6 +86,36 @@ async fn test_vector_db_with_hnsw() -> Result<()> {
     Ok(())
 }
 
+/// Test "more like this" search from a stored document
+#[test]
+fn test_find_similar() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut db = VectorDatabase::new(temp_dir.path().join("similar.json"))?;
+    let docs = [
+        ("rust", vec![1.0, 0.0, 0.0]),
+        ("rust-async", vec![0.9, 0.1, 0.0]),
+        ("rust-errors", vec![0.8, 0.2, 0.0]),
+        ("python", vec![0.0, 1.0, 0.0]),
+    ];
+    for (id, vector) in docs {
+        let doc = create_test_document(id, id, &format!("https://example.com/{}", id));
+        db.add_document(doc, vector)?;
+    }
+
+    // The query document is excluded and the nearest others come back
+    let results = db.find_similar("rust", 2)?;
+    let ids: Vec<&str> = results.iter().map(|r| r.document.id.as_str()).collect();
+    assert_eq!(ids, ["rust-async", "rust-errors"]);
+
+    // An unbounded limit returns every other document
+    let results = db.find_similar("rust", usize::MAX)?;
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|r| r.document.id != "rust"));
+
+    assert!(db.find_similar("missing", 2).is_err());
+    Ok(())
+}
+
 /// Test the enhanced vector database with quantization
 #[tokio::test]
 async fn test_vector_db_with_quantization() -> Result<()> {
@@ -133,11 +160,9 @@ async fn test_vector_db_with_quantization() -> Result<()> {
 
     // Test search
     let query = vec![0.9, 0.1, 0.1]; // Similar to document 1 (Rust)
-    let options = crate::vectordb::SearchOptions {
+    let options = SearchOptions {
         limit: 2,
-        min_score: None,
-        source_filter: None,
-        content_type_filter: None,
+        ..SearchOptions::default()
     };
 
     let results = db.search(&query, options)?;
@@ -205,16 +230,14 @@ async fn test_vector_db_with_hybrid_search() -> Result<()> {
     let query_text = "memory safety programming"; // Keywords match doc 4 (Rust and C++)
 
     let options = HybridSearchOptions {
-        base: crate::vectordb::SearchOptions {
+        base: SearchOptions {
             limit: 2,
-            min_score: None,
-            source_filter: None,
-            content_type_filter: None,
+            ..SearchOptions::default()
         },
         enable_hybrid: true,
         vector_weight: 0.6,
         keyword_weight: 0.4,
-        keyword_params: crate::vectordb::hybrid_search::KeywordSearchParams::default(),
+        ..HybridSearchOptions::default()
     };
 
     let results = db.hybrid_search(&query_embedding, query_text, options)?;
@@ -314,11 +337,9 @@ async fn test_hnsw_performance() -> Result<()> {
     }
 
     // Test HNSW search performance
-    let options = crate::vectordb::SearchOptions {
+    let options = SearchOptions {
         limit: 10,
-        min_score: None,
-        source_filter: None,
-        content_type_filter: None,
+        ..SearchOptions::default()
     };
 
     let start = Instant::now();